@@ -0,0 +1,310 @@
+/// 警報規則
+///
+/// 長時間測試時持續以運算式檢查關鍵統計（同步錯誤增加速度、延遲、實體數量），
+/// 條件持續成立時透過日誌、終端響鈴或 webhook 通知，讓退化在發生當下就被發現
+use std::collections::VecDeque;
+use std::io::Write;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde_json::{json, Value};
+
+use crate::config::{AlertMode, AlertNotify, AlertRule, AlertsConfig};
+use crate::expr::{self, Expr};
+
+/// 檢查間隔
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 計算變化率的時間窗口
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// 規則的執行狀態
+#[derive(Debug)]
+pub struct AlertState {
+    /// 規則定義
+    pub rule: AlertRule,
+    expr: Expr,
+    /// 變化率模式的歷史樣本
+    samples: VecDeque<(Instant, f64)>,
+    /// 條件開始成立的時間
+    pending_since: Option<Instant>,
+    /// 目前是否處於觸發狀態
+    pub firing: bool,
+    /// 上一次通知的時間
+    last_fired: Option<Instant>,
+    /// 最近一次判斷用的值
+    pub last_value: Option<f64>,
+    /// 最近一次求值錯誤
+    pub last_error: Option<String>,
+    /// 累計觸發次數
+    pub fire_count: u64,
+}
+
+/// 警報監控器
+#[derive(Debug)]
+pub struct AlertMonitor {
+    rules: Vec<AlertState>,
+    webhook_url: Option<String>,
+    cooldown: Duration,
+    last_check: Option<Instant>,
+}
+
+impl Default for AlertMonitor {
+    fn default() -> Self {
+        Self::from_config(&AlertsConfig::default())
+    }
+}
+
+impl AlertMonitor {
+    /// 從配置創建監控器（無法解析的規則會被略過並記錄警告）
+    pub fn from_config(config: &AlertsConfig) -> Self {
+        let mut monitor = Self {
+            rules: Vec::new(),
+            webhook_url: config.webhook_url.clone(),
+            cooldown: Duration::from_secs_f64(config.cooldown_secs.max(0.0)),
+            last_check: None,
+        };
+        for rule in &config.rules {
+            if let Err(e) = monitor.add(rule.clone()) {
+                warn!("略過無效的警報規則 {}: {}", rule.name, e);
+            }
+        }
+        monitor
+    }
+
+    /// 添加（或替換同名）規則
+    pub fn add(&mut self, rule: AlertRule) -> Result<()> {
+        let expr = Expr::parse(&rule.expr)
+            .with_context(|| format!("無法解析警報運算式: {}", rule.expr))?;
+        let state = AlertState {
+            rule,
+            expr,
+            samples: VecDeque::new(),
+            pending_since: None,
+            firing: false,
+            last_fired: None,
+            last_value: None,
+            last_error: None,
+            fire_count: 0,
+        };
+        match self.rules.iter_mut().find(|r| r.rule.name == state.rule.name) {
+            Some(existing) => *existing = state,
+            None => self.rules.push(state),
+        }
+        Ok(())
+    }
+
+    /// 所有規則
+    pub fn rules(&self) -> &[AlertState] {
+        &self.rules
+    }
+
+    /// 是否到了下一次檢查時間
+    pub fn due(&self) -> bool {
+        !self.rules.is_empty() && self.last_check.is_none_or(|last| last.elapsed() >= CHECK_INTERVAL)
+    }
+
+    /// 以運算式上下文檢查所有規則，返回本次觸發的規則名稱
+    pub fn check(&mut self, ctx: &Value, now: Instant) -> Vec<String> {
+        self.last_check = Some(now);
+
+        let mut fired = Vec::new();
+        for state in &mut self.rules {
+            let triggered = match state.evaluate(ctx, now) {
+                Ok(triggered) => {
+                    state.last_error = None;
+                    triggered
+                }
+                Err(e) => {
+                    state.last_error = Some(e.to_string());
+                    false
+                }
+            };
+
+            if !triggered {
+                if state.firing {
+                    info!("✅ 警報解除: {}", state.rule.name);
+                }
+                state.pending_since = None;
+                state.firing = false;
+                continue;
+            }
+
+            let since = *state.pending_since.get_or_insert(now);
+            if state.firing || now.duration_since(since).as_secs_f64() < state.rule.for_secs {
+                continue;
+            }
+            state.firing = true;
+            if state.last_fired.is_some_and(|last| now.duration_since(last) < self.cooldown) {
+                continue;
+            }
+            state.last_fired = Some(now);
+            state.fire_count += 1;
+            notify(state, self.webhook_url.as_deref());
+            fired.push(state.rule.name.clone());
+        }
+        fired
+    }
+}
+
+impl AlertState {
+    /// 判斷條件是否成立
+    fn evaluate(&mut self, ctx: &Value, now: Instant) -> Result<bool> {
+        let value = self.expr.eval(ctx)?;
+
+        // 沒有門檻時把運算式本身當作條件（例如 `entities.count() == 0`）
+        if self.rule.above.is_none() && self.rule.below.is_none() {
+            self.last_value = expr::as_number(&value).ok();
+            return Ok(expr::truthy(&value));
+        }
+
+        let number = expr::as_number(&value)?;
+        let measured = match self.rule.mode {
+            AlertMode::Value => Some(number),
+            AlertMode::Rate => {
+                self.samples.push_back((now, number));
+                while self.samples.front().is_some_and(|(at, _)| now.duration_since(*at) > RATE_WINDOW) {
+                    self.samples.pop_front();
+                }
+                let (first_at, first_value) = self.samples.front().copied().unwrap_or((now, number));
+                let minutes = now.duration_since(first_at).as_secs_f64() / 60.0;
+                // 樣本跨度太短時不計算，避免單次跳動被放大
+                (minutes >= 1.0 / 60.0).then(|| (number - first_value) / minutes)
+            }
+        };
+        self.last_value = measured;
+
+        Ok(measured.is_some_and(|v| {
+            self.rule.above.is_some_and(|above| v > above) || self.rule.below.is_some_and(|below| v < below)
+        }))
+    }
+
+    /// 觸發條件的說明
+    pub fn describe(&self) -> String {
+        let subject = match self.rule.mode {
+            AlertMode::Value => self.rule.expr.clone(),
+            AlertMode::Rate => format!("{} 每分鐘變化", self.rule.expr),
+        };
+        let mut parts = vec![subject];
+        if let Some(above) = self.rule.above {
+            parts.push(format!("> {}", above));
+        }
+        if let Some(below) = self.rule.below {
+            parts.push(format!("< {}", below));
+        }
+        if self.rule.for_secs > 0.0 {
+            parts.push(format!("持續 {}s", self.rule.for_secs));
+        }
+        parts.join(" ")
+    }
+}
+
+/// 發送通知
+fn notify(state: &AlertState, webhook_url: Option<&str>) {
+    let value = state.last_value.map(|v| format!("{:.2}", v)).unwrap_or_else(|| "-".to_string());
+    let message = format!("{} ({}，目前值 {})", state.rule.name, state.describe(), value);
+
+    for channel in &state.rule.notify {
+        match channel {
+            AlertNotify::Log => warn!("🔔 警報觸發: {}", message),
+            AlertNotify::Bell => {
+                let mut stdout = std::io::stdout();
+                let _ = stdout.write_all(b"\x07");
+                let _ = stdout.flush();
+            }
+            AlertNotify::Webhook => match webhook_url {
+                Some(url) => send_webhook(url.to_string(), json!({
+                    "rule": state.rule.name,
+                    "condition": state.describe(),
+                    "value": state.last_value,
+                    "message": message,
+                    "timestamp_ms": SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64,
+                })),
+                None => warn!("警報 {} 設定了 webhook 通知，但未配置 alerts.webhook_url", state.rule.name),
+            },
+        }
+    }
+}
+
+/// 在背景執行緒發送 webhook，避免阻塞畫面更新
+#[cfg(feature = "webhooks")]
+fn send_webhook(url: String, payload: Value) {
+    std::thread::spawn(move || {
+        if let Err(e) = ureq::post(&url).timeout(Duration::from_secs(5)).send_json(payload) {
+            warn!("發送警報 webhook 失敗: {}", e);
+        }
+    });
+}
+
+#[cfg(not(feature = "webhooks"))]
+fn send_webhook(url: String, _payload: Value) {
+    warn!("此版本建置時未啟用 webhooks 功能，略過發送到 {} 的警報通知", url);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(rule: AlertRule) -> AlertMonitor {
+        AlertMonitor::from_config(&AlertsConfig { rules: vec![rule], webhook_url: None, cooldown_secs: 60.0 })
+    }
+
+    fn rule(name: &str, expr: &str, mode: AlertMode, above: f64, for_secs: f64) -> AlertRule {
+        AlertRule { name: name.to_string(), expr: expr.to_string(), mode, above: Some(above), below: None, for_secs, notify: vec![AlertNotify::Log] }
+    }
+
+    #[test]
+    fn fires_after_the_condition_holds_and_respects_cooldown() {
+        let mut alerts = monitor(rule("high_rtt", "rtt_ms", AlertMode::Value, 100.0, 2.0));
+        let start = Instant::now();
+        let mut check = |secs: u64, rtt: f64| alerts.check(&json!({ "rtt_ms": rtt }), start + Duration::from_secs(secs));
+
+        assert!(check(0, 150.0).is_empty(), "需持續 for_secs");
+        assert!(check(1, 150.0).is_empty());
+        assert_eq!(check(2, 150.0), ["high_rtt"]);
+        assert!(check(3, 150.0).is_empty(), "觸發中不重複通知");
+
+        // 解除後再次成立，但仍在冷卻時間內
+        assert!(check(4, 50.0).is_empty());
+        assert!(check(5, 150.0).is_empty());
+        assert!(check(7, 150.0).is_empty(), "冷卻中不通知");
+        assert!(alerts.rules()[0].firing);
+        assert_eq!(alerts.rules()[0].fire_count, 1);
+
+        let mut check = |secs: u64, rtt: f64| alerts.check(&json!({ "rtt_ms": rtt }), start + Duration::from_secs(secs));
+        check(70, 50.0);
+        check(71, 150.0);
+        assert_eq!(check(73, 150.0), ["high_rtt"], "冷卻結束後再次通知");
+        assert_eq!(alerts.rules()[0].fire_count, 2);
+        assert_eq!(alerts.rules()[0].last_value, Some(150.0));
+    }
+
+    #[test]
+    fn rate_mode_compares_the_change_per_minute() {
+        let mut alerts = monitor(rule("sync_errors", "sync_errors", AlertMode::Rate, 30.0, 0.0));
+        let start = Instant::now();
+        let mut check = |millis: u64, errors: f64| alerts.check(&json!({ "sync_errors": errors }), start + Duration::from_millis(millis));
+
+        assert!(check(0, 0.0).is_empty());
+        assert!(check(500, 10.0).is_empty(), "樣本跨度不足一秒時不計算");
+        assert!(check(6_000, 2.0).is_empty(), "每分鐘 20 次");
+        assert_eq!(check(12_000, 10.0), ["sync_errors"], "每分鐘 50 次");
+        assert_eq!(alerts.rules()[0].last_value, Some(50.0));
+
+        // 超出一分鐘窗口的樣本不再計入
+        let mut check = |millis: u64, errors: f64| alerts.check(&json!({ "sync_errors": errors }), start + Duration::from_millis(millis));
+        assert!(check(71_000, 10.0).is_empty());
+        assert!(!alerts.rules()[0].firing);
+        assert_eq!(alerts.rules()[0].last_value, Some(0.0));
+    }
+
+    #[test]
+    fn reports_evaluation_errors_without_firing() {
+        let mut alerts = monitor(rule("missing", "missing.field", AlertMode::Value, 0.0, 0.0));
+        assert!(alerts.check(&json!({}), Instant::now()).is_empty());
+        assert!(alerts.rules()[0].last_error.is_some());
+    }
+}
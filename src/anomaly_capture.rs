@@ -0,0 +1,138 @@
+/// 異常自動擷取
+///
+/// 實時視圖中出現位置不同步、協議錯誤、資料異常或操作結果與預測不符時，自動把當下的畫面、
+/// 狀態快照與最近的收發訊息寫入異常資料夾，測試者沒在看畫面時一閃而過的問題也能保留下來。
+/// 每個異常一個資料夾：`reasons.txt`、`frame.txt`、`state.json` 與 `messages.jsonl`（錄製檔格式，可直接回放）
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use anyhow::{Context, Result};
+
+use crate::config::AnomalyCaptureConfig;
+use crate::recording::RecordedMessage;
+
+/// 監看各項異常計數，計數增加時排入待擷取
+#[derive(Debug, Clone, Default)]
+pub struct AnomalyWatch {
+    config: AnomalyCaptureConfig,
+    /// 各計數上次看到的值
+    baseline: BTreeMap<&'static str, u64>,
+    /// 尚未擷取的異常與增加的次數
+    pending: BTreeMap<&'static str, u64>,
+    last_capture: Option<Instant>,
+}
+
+impl AnomalyWatch {
+    /// 依配置創建
+    pub fn new(config: AnomalyCaptureConfig) -> Self {
+        Self { config, ..Self::default() }
+    }
+
+    /// 擷取配置
+    pub fn config(&self) -> &AnomalyCaptureConfig {
+        &self.config
+    }
+
+    /// 更新一項異常計數
+    pub fn observe(&mut self, label: &'static str, count: u64) {
+        let previous = self.baseline.insert(label, count).unwrap_or(0);
+        if count > previous {
+            *self.pending.entry(label).or_default() += count - previous;
+        }
+    }
+
+    /// 取出待擷取的異常說明（停用、沒有新異常或距離上次擷取未滿冷卻時間時為 None，異常會保留到下一次）
+    pub fn take(&mut self, now: Instant) -> Option<Vec<String>> {
+        if !self.config.enabled || self.pending.is_empty() {
+            return None;
+        }
+        let cooldown = Duration::from_secs(self.config.cooldown_secs);
+        if self.last_capture.is_some_and(|last| now.duration_since(last) < cooldown) {
+            return None;
+        }
+        self.last_capture = Some(now);
+        Some(std::mem::take(&mut self.pending).into_iter()
+            .map(|(label, count)| format!("{} +{}", label, count))
+            .collect())
+    }
+}
+
+/// 擷取時保存的證據
+#[derive(Debug, Clone)]
+pub struct AnomalyEvidence {
+    /// 觸發擷取的異常
+    pub reasons: Vec<String>,
+    /// 狀態快照（查詢上下文）
+    pub state: serde_json::Value,
+    /// 最近的收發訊息
+    pub messages: Vec<RecordedMessage>,
+    /// 異常資料夾的上層目錄
+    pub dir: PathBuf,
+}
+
+impl AnomalyEvidence {
+    /// 寫入新的異常資料夾，返回資料夾路徑
+    pub fn save(&self, frame: &[String]) -> Result<PathBuf> {
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let folder = self.dir.join(format!("anomaly-{}", millis));
+        std::fs::create_dir_all(&folder)
+            .with_context(|| format!("無法建立異常資料夾: {}", folder.display()))?;
+        write_file(&folder.join("reasons.txt"), &format!("{}\n", self.reasons.join("\n")))?;
+        write_file(&folder.join("frame.txt"), &format!("{}\n", frame.join("\n")))?;
+        write_file(&folder.join("state.json"), &serde_json::to_string_pretty(&self.state)?)?;
+        let mut messages = String::new();
+        for message in &self.messages {
+            messages.push_str(&serde_json::to_string(message)?);
+            messages.push('\n');
+        }
+        write_file(&folder.join("messages.jsonl"), &messages)?;
+        Ok(folder)
+    }
+}
+
+fn write_file(path: &Path, content: &str) -> Result<()> {
+    std::fs::File::create(path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .with_context(|| format!("無法寫入 {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::RecordDirection;
+
+    #[test]
+    fn captures_new_anomalies_once_per_cooldown() {
+        let mut watch = AnomalyWatch::new(AnomalyCaptureConfig { cooldown_secs: 10, ..AnomalyCaptureConfig::default() });
+        let start = Instant::now();
+        watch.observe("位置不同步", 0);
+        assert!(watch.take(start).is_none());
+
+        watch.observe("位置不同步", 2);
+        watch.observe("協議錯誤", 1);
+        assert_eq!(watch.take(start).unwrap(), vec!["位置不同步 +2", "協議錯誤 +1"]);
+
+        watch.observe("協議錯誤", 3);
+        assert!(watch.take(start + Duration::from_secs(5)).is_none(), "冷卻中保留異常");
+        assert_eq!(watch.take(start + Duration::from_secs(10)).unwrap(), vec!["協議錯誤 +2"]);
+    }
+
+    #[test]
+    fn saves_frame_state_and_replayable_messages() {
+        let dir = std::env::temp_dir().join(format!("omobaf_anomalies_{}", std::process::id()));
+        let evidence = AnomalyEvidence {
+            reasons: vec!["協議錯誤 +1".to_string()],
+            state: serde_json::json!({ "player": { "hp": 10 } }),
+            messages: vec![RecordedMessage::new(5, RecordDirection::Incoming, "td/all/res", b"{}")],
+            dir: dir.clone(),
+        };
+        let folder = evidence.save(&["@..".to_string()]).unwrap();
+        let frame = std::fs::read_to_string(folder.join("frame.txt")).unwrap();
+        let messages = std::fs::read_to_string(folder.join("messages.jsonl")).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(frame, "@..\n");
+        let message: RecordedMessage = serde_json::from_str(messages.lines().next().unwrap()).unwrap();
+        assert_eq!(message.topic, "td/all/res");
+    }
+}
@@ -0,0 +1,210 @@
+/// 命令稽核記錄
+///
+/// 共用測試環境中記錄每個使用者命令（互動模式、命令行）的時間、來源、參數與結果，
+/// 以 JSON Lines 格式附加到稽核檔，之後可用 `audit show` 查出是誰在什麼時候做了什麼
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::{Result, Context};
+use colored::*;
+
+/// 預設稽核檔案
+pub const DEFAULT_AUDIT_FILE: &str = "audit.jsonl";
+
+/// 命令來源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditSource {
+    /// 互動式命令列
+    Interactive,
+    /// 命令行子命令
+    Cli,
+}
+
+impl AuditSource {
+    /// 顯示名稱
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuditSource::Interactive => "interactive",
+            AuditSource::Cli => "cli",
+        }
+    }
+}
+
+/// 單一稽核記錄
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// 執行時間（Unix 毫秒）
+    pub timestamp_ms: u64,
+    /// 命令來源
+    pub source: AuditSource,
+    /// 作業系統使用者
+    pub user: String,
+    /// 會話名稱（互動模式）
+    #[serde(default)]
+    pub session: Option<String>,
+    /// 命令名稱
+    pub command: String,
+    /// 命令參數
+    pub args: Vec<String>,
+    /// 是否成功
+    pub ok: bool,
+    /// 失敗時的錯誤訊息
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// 稽核記錄檔
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_AUDIT_FILE)
+    }
+}
+
+impl AuditLog {
+    /// 創建稽核記錄檔
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// 記錄一個命令及其結果
+    pub fn record(&self, source: AuditSource, session: Option<&str>, parts: &[&str], outcome: &Result<()>) -> Result<()> {
+        let Some((command, args)) = parts.split_first() else {
+            return Ok(());
+        };
+        let entry = AuditEntry {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64,
+            source,
+            user: current_user(),
+            session: session.map(str::to_string),
+            command: command.to_string(),
+            args: redact_args(command, args),
+            ok: outcome.is_ok(),
+            error: outcome.as_ref().err().map(|e| e.to_string()),
+        };
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("無法開啟稽核檔案: {:?}", self.path))?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)
+            .with_context(|| format!("無法寫入稽核檔案: {:?}", self.path))?;
+        Ok(())
+    }
+
+    /// 讀取最近的記錄
+    pub fn recent(&self, limit: usize) -> Result<Vec<AuditEntry>> {
+        load_entries(&self.path).map(|entries| {
+            let skip = entries.len().saturating_sub(limit);
+            entries.into_iter().skip(skip).collect()
+        })
+    }
+
+    /// 打印最近的記錄
+    pub fn show(&self, limit: usize) -> Result<()> {
+        let entries = self.recent(limit)?;
+        println!("\n{}", format!("稽核記錄 (最近 {} 筆):", entries.len()).bright_cyan().bold());
+        println!("{}", "-".repeat(40).bright_black());
+
+        if entries.is_empty() {
+            println!("  {}", "尚無記錄".bright_black());
+            return Ok(());
+        }
+
+        for entry in &entries {
+            let status = if entry.ok { "✓".green() } else { "✗".red() };
+            let session = entry.session.as_deref().map(|s| format!(" [{}]", s)).unwrap_or_default();
+            println!("  {} {} {}@{}{} {} {}",
+                status,
+                format_timestamp(entry.timestamp_ms).bright_black(),
+                entry.user,
+                entry.source.label(),
+                session,
+                entry.command.bright_yellow(),
+                entry.args.join(" "));
+            if let Some(error) = &entry.error {
+                println!("      {}", error.red());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 從檔案讀取稽核記錄（檔案不存在時返回空列表，無法解析的行會被略過）
+fn load_entries(path: &Path) -> Result<Vec<AuditEntry>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("無法開啟稽核檔案: {:?}", path))?;
+    Ok(BufReader::new(file)
+        .lines()
+        .map_while(|line| line.ok())
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect())
+}
+
+/// 命令參數（遮蔽 `config auth` 的密碼，避免寫進共用的稽核檔）
+fn redact_args(command: &str, args: &[&str]) -> Vec<String> {
+    args.iter()
+        .enumerate()
+        .map(|(index, arg)| match (command, args.first()) {
+            ("config", Some(&"auth")) if index >= 2 => "***".to_string(),
+            _ => arg.to_string(),
+        })
+        .collect()
+}
+
+/// 目前的作業系統使用者
+fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// 以 UTC 顯示 Unix 毫秒時間
+fn format_timestamp(timestamp_ms: u64) -> String {
+    let secs = timestamp_ms / 1000;
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+
+    // 由 1970-01-01 起算的日數換算為公曆日期
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}Z",
+        year, month, day, secs_of_day / 3600, secs_of_day % 3600 / 60, secs_of_day % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_unix_millis_as_utc() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00Z");
+        assert_eq!(format_timestamp(1_709_258_400_000), "2024-03-01 02:00:00Z");
+    }
+
+    #[test]
+    fn redacts_auth_password() {
+        assert_eq!(redact_args("config", &["auth", "qa", "secret"]), vec!["auth", "qa", "***"]);
+        assert_eq!(redact_args("config", &["name", "qa", "secret"]), vec!["name", "qa", "secret"]);
+    }
+}
@@ -10,6 +10,9 @@ use tokio::time::sleep;
 
 use crate::config::AppConfig;
 
+/// 後端管理日誌使用的會話標籤
+const BACKEND_SESSION_TAG: &str = "backend";
+
 /// 後端管理器
 pub struct BackendManager {
     /// 後端程序句柄
@@ -29,6 +32,10 @@ impl BackendManager {
     
     /// 啟動後端程序
     pub async fn start(&self) -> Result<()> {
+        crate::session_tag::scope(BACKEND_SESSION_TAG, self.start_process()).await
+    }
+    
+    async fn start_process(&self) -> Result<()> {
         let mut process_guard = self.process.lock().await;
         
         // 先清理系統中所有舊的後端進程
@@ -114,6 +121,10 @@ impl BackendManager {
     
     /// 停止後端程序
     pub async fn stop(&self) -> Result<()> {
+        crate::session_tag::scope(BACKEND_SESSION_TAG, self.stop_process()).await
+    }
+    
+    async fn stop_process(&self) -> Result<()> {
         let mut process_guard = self.process.lock().await;
         
         if let Some(mut child) = process_guard.take() {
@@ -173,6 +184,20 @@ impl BackendManager {
         Ok(())
     }
     
+    /// 後端程序意外退出時重新啟動，返回是否已重啟（未啟動或已主動停止的後端不處理）
+    pub async fn restart_if_crashed(&self) -> Result<bool> {
+        let exited = match self.process.lock().await.as_mut() {
+            Some(child) => matches!(child.try_wait(), Ok(Some(_))),
+            None => false,
+        };
+        if !exited {
+            return Ok(false);
+        }
+        warn!("⚠ 後端程序意外退出，重新啟動...");
+        self.start().await?;
+        Ok(true)
+    }
+    
     /// 檢查後端程序是否運行中
     pub async fn is_running(&self) -> bool {
         let mut process_guard = self.process.lock().await;
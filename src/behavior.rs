@@ -0,0 +1,294 @@
+/// 行為樹機器人
+///
+/// 自動遊戲以行為樹決定每一步的操作，取代均勻隨機的操作，讓壓力測試送出接近真實玩家的流量。
+/// 樹由 `selector`（依序嘗試子節點，第一個未失敗的即停止）、`sequence`（子節點依序全部成功才成功）、
+/// 條件節點與操作節點組成，寫在 `[bot] profile` 指定的 TOML 或 YAML 設定檔中；未設定時使用內建的
+/// `scripts/bots/default.toml`：生命過低撤退回出生點、生命偏低且敵人接近時拉開距離、技能就緒時對最近的敵人施放、
+/// 追擊並攻擊最近的敵人，附近沒有敵人時遊走。操作節點一旦送出操作，本次決策即結束（每步最多一個操作）
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::Deserialize;
+use vek::Vec2;
+
+use crate::game_state::{EntityType, GameState};
+
+/// 內建的預設行為樹
+const DEFAULT_PROFILE: &str = include_str!("../scripts/bots/default.toml");
+
+/// 距離出生點多近視為已撤退到出生點（世界單位）
+const SPAWN_ARRIVAL_DISTANCE: f32 = 50.0;
+
+/// 行為樹節點
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Node {
+    /// 依序嘗試子節點，第一個未失敗的子節點即為結果
+    Selector { children: Vec<Node> },
+    /// 依序執行子節點，任一失敗即失敗
+    Sequence { children: Vec<Node> },
+    /// 生命比例低於 ratio
+    HealthBelow { ratio: f32 },
+    /// 最近的敵人在 range 內
+    EnemyWithin { range: f32 },
+    /// 指定技能（未指定時任一技能）冷卻完畢
+    AbilityReady {
+        #[serde(default)]
+        ability: Option<String>,
+    },
+    /// 朝最近敵人的反方向移動 distance
+    Kite { distance: f32 },
+    /// 最近的敵人在 attack_range 內時攻擊，否則朝它移動
+    Chase { attack_range: f32 },
+    /// 對最近的敵人施放冷卻完畢的指定技能（未指定時隨機挑一個）
+    Cast {
+        #[serde(default)]
+        ability: Option<String>,
+    },
+    /// 移動回出生點（已在出生點時失敗）
+    Retreat,
+    /// 在目前位置 radius 範圍內隨機移動
+    Wander { radius: f32 },
+}
+
+/// 節點的執行結果
+#[derive(Debug, Clone, PartialEq)]
+enum Outcome {
+    Failure,
+    Success,
+    /// 送出操作（視為成功，本次決策結束）
+    Act(String, serde_json::Value),
+}
+
+/// 行為樹設定檔
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BehaviorTree {
+    /// 設定檔名稱（預設為檔名）
+    #[serde(default)]
+    pub name: Option<String>,
+    pub root: Node,
+}
+
+impl Default for BehaviorTree {
+    fn default() -> Self {
+        Self::parse_toml(DEFAULT_PROFILE).expect("內建的行為樹設定有誤")
+    }
+}
+
+impl BehaviorTree {
+    /// 載入設定檔（副檔名為 .yaml 或 .yml 時以 YAML 解析，其餘以 TOML 解析）
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("無法讀取行為樹設定: {:?}", path))?;
+        let yaml = path.extension().is_some_and(|ext| ext == "yaml" || ext == "yml");
+        let parsed = if yaml { Self::parse_yaml(&content) } else { Self::parse_toml(&content) };
+        let mut tree = parsed.with_context(|| format!("行為樹設定有誤: {:?}", path))?;
+        if tree.name.is_none() {
+            tree.name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned());
+        }
+        Ok(tree)
+    }
+
+    pub fn parse_toml(content: &str) -> Result<Self> {
+        let tree: Self = toml::from_str(content)?;
+        validate(&tree.root)?;
+        Ok(tree)
+    }
+
+    pub fn parse_yaml(content: &str) -> Result<Self> {
+        let tree: Self = serde_yaml::from_str(content)?;
+        validate(&tree.root)?;
+        Ok(tree)
+    }
+
+    /// 設定檔名稱
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or("default")
+    }
+
+    /// 以目前狀態走一次行為樹，返回要送出的操作（後端座標）
+    pub fn decide(&self, state: &GameState, spawn: Vec2<f32>, rng: &mut StdRng) -> Option<(String, serde_json::Value)> {
+        let mut blackboard = Blackboard { state, spawn, rng };
+        match blackboard.tick(&self.root) {
+            Outcome::Act(action, params) => Some((action, params)),
+            Outcome::Success | Outcome::Failure => None,
+        }
+    }
+}
+
+/// 檢查節點參數
+fn validate(node: &Node) -> Result<()> {
+    match node {
+        Node::Selector { children } | Node::Sequence { children } => {
+            if children.is_empty() {
+                bail!("selector 與 sequence 至少需要一個子節點");
+            }
+            children.iter().try_for_each(validate)
+        }
+        Node::HealthBelow { ratio } if !(*ratio > 0.0 && *ratio <= 1.0) => bail!("health_below.ratio 應介於 0 到 1，目前為 {}", ratio),
+        Node::EnemyWithin { range } if *range <= 0.0 => bail!("enemy_within.range 必須大於 0"),
+        Node::Kite { distance } if *distance <= 0.0 => bail!("kite.distance 必須大於 0"),
+        Node::Chase { attack_range } if *attack_range <= 0.0 => bail!("chase.attack_range 必須大於 0"),
+        Node::Wander { radius } if *radius <= 0.0 => bail!("wander.radius 必須大於 0"),
+        _ => Ok(()),
+    }
+}
+
+/// 一次決策的狀態
+struct Blackboard<'a> {
+    state: &'a GameState,
+    spawn: Vec2<f32>,
+    rng: &'a mut StdRng,
+}
+
+impl Blackboard<'_> {
+    fn tick(&mut self, node: &Node) -> Outcome {
+        let hero = self.state.local_player.position;
+        let succeed = |condition: bool| if condition { Outcome::Success } else { Outcome::Failure };
+        match node {
+            Node::Selector { children } => children.iter()
+                .map(|child| self.tick(child))
+                .find(|outcome| *outcome != Outcome::Failure)
+                .unwrap_or(Outcome::Failure),
+            Node::Sequence { children } => {
+                for child in children {
+                    match self.tick(child) {
+                        Outcome::Success => continue,
+                        outcome => return outcome,
+                    }
+                }
+                Outcome::Success
+            }
+            Node::HealthBelow { ratio } => {
+                let (health, max_health) = self.state.local_player.health;
+                succeed(max_health > 0.0 && health / max_health < *ratio)
+            }
+            Node::EnemyWithin { range } => succeed(self.nearest_enemy().is_some_and(|enemy| enemy.distance(hero) <= *range)),
+            Node::AbilityReady { ability } => succeed(!self.ready_abilities(ability.as_deref()).is_empty()),
+            Node::Kite { distance } => match self.nearest_enemy() {
+                Some(enemy) => {
+                    let away = hero - enemy;
+                    // 與敵人重疊時隨便挑一個方向
+                    let direction = if away.magnitude() > f32::EPSILON { away.normalized() } else { Vec2::unit_x() };
+                    move_to(hero + direction * *distance)
+                }
+                None => Outcome::Failure,
+            },
+            Node::Chase { attack_range } => match self.nearest_enemy() {
+                Some(enemy) if enemy.distance(hero) <= *attack_range => Outcome::Act("attack".to_string(), serde_json::json!({
+                    "target_position": [enemy.x, enemy.y],
+                    "attack_type": "basic",
+                })),
+                Some(enemy) => move_to(enemy),
+                None => Outcome::Failure,
+            },
+            Node::Cast { ability } => {
+                let ready = self.ready_abilities(ability.as_deref());
+                let Some(enemy) = self.nearest_enemy().filter(|_| !ready.is_empty()) else {
+                    return Outcome::Failure;
+                };
+                let (ability_id, level) = ready[self.rng.random_range(0..ready.len())].clone();
+                Outcome::Act("cast_ability".to_string(), serde_json::json!({
+                    "ability_id": ability_id,
+                    "target_position": [enemy.x, enemy.y],
+                    "level": level,
+                }))
+            }
+            Node::Retreat => {
+                if hero.distance(self.spawn) <= SPAWN_ARRIVAL_DISTANCE {
+                    Outcome::Failure
+                } else {
+                    move_to(self.spawn)
+                }
+            }
+            Node::Wander { radius } => {
+                let offset = Vec2::new(self.rng.random_range(-radius..=*radius), self.rng.random_range(-radius..=*radius));
+                move_to(hero + offset)
+            }
+        }
+    }
+
+    /// 最近的敵人位置（其他玩家、小兵與不屬於自己的召喚物）
+    fn nearest_enemy(&self) -> Option<Vec2<f32>> {
+        let me = &self.state.local_player.name;
+        let players = self.state.other_players.values()
+            .filter(|player| player.health.0 > 0.0)
+            .map(|player| Vec2::new(player.position.0, player.position.1));
+        let units = self.state.entities.values()
+            .filter(|entity| entity.health.0 > 0.0)
+            .filter(|entity| match &entity.entity_type {
+                EntityType::Player(name) => name != me,
+                EntityType::Creep => true,
+                EntityType::Summon(_) => entity.owner.as_ref() != Some(me),
+                EntityType::Tower | EntityType::Projectile | EntityType::Effect => false,
+            })
+            .map(|entity| entity.position);
+        let hero = self.state.local_player.position;
+        players.chain(units).min_by(|a, b| a.distance(hero).total_cmp(&b.distance(hero)))
+    }
+
+    /// 冷卻完畢的技能與等級（只看指定技能時最多一個）
+    fn ready_abilities(&self, only: Option<&str>) -> Vec<(String, u8)> {
+        self.state.local_player.abilities.iter()
+            .filter(|ability| only.is_none_or(|id| ability.ability_id == id))
+            .filter(|ability| ability.is_available && ability.cooldown_remaining <= 0.0)
+            .map(|ability| (ability.ability_id.clone(), ability.level))
+            .collect()
+    }
+}
+
+fn move_to(target: Vec2<f32>) -> Outcome {
+    Outcome::Act("move".to_string(), serde_json::json!({ "target_x": target.x, "target_y": target.y }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use crate::game_state::Entity;
+
+    #[test]
+    fn default_tree_retreats_kites_casts_and_chases() {
+        let tree = BehaviorTree::default();
+        let mut rng = StdRng::seed_from_u64(7);
+        let spawn = Vec2::new(0.0, 0.0);
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        state.local_player.position = Vec2::new(500.0, 500.0);
+        for ability in &mut state.local_player.abilities {
+            ability.cooldown_remaining = 5.0;
+        }
+        let decide = |state: &GameState, rng: &mut StdRng| tree.decide(state, spawn, rng).map(|(action, _)| action);
+
+        // 沒有敵人時遊走
+        assert_eq!(decide(&state, &mut rng).as_deref(), Some("move"));
+
+        state.entities.insert(1, Entity {
+            id: 1, entity_type: EntityType::Creep, position: Vec2::new(560.0, 500.0),
+            health: (100.0, 100.0), owner: None, debug_label: None,
+        });
+        assert_eq!(decide(&state, &mut rng).as_deref(), Some("attack"), "範圍內的敵人直接攻擊");
+
+        state.local_player.abilities[0].cooldown_remaining = 0.0;
+        let (action, params) = tree.decide(&state, spawn, &mut rng).unwrap();
+        assert_eq!((action.as_str(), &params["target_position"]), ("cast_ability", &serde_json::json!([560.0, 500.0])));
+
+        state.local_player.health = (40.0, 100.0);
+        let (action, params) = tree.decide(&state, spawn, &mut rng).unwrap();
+        assert_eq!(action, "move");
+        assert!(params["target_x"].as_f64().unwrap() < 500.0, "生命偏低時遠離敵人");
+
+        state.local_player.health = (10.0, 100.0);
+        let (_, params) = tree.decide(&state, spawn, &mut rng).unwrap();
+        assert_eq!(params, serde_json::json!({ "target_x": 0.0, "target_y": 0.0 }), "生命過低時撤退");
+
+        let yaml = BehaviorTree::parse_yaml("root:\n  type: sequence\n  children:\n    - { type: ability_ready, ability: sniper_mode }\n    - { type: retreat }\n").unwrap();
+        assert_eq!(yaml.root, Node::Sequence { children: vec![
+            Node::AbilityReady { ability: Some("sniper_mode".to_string()) },
+            Node::Retreat,
+        ] });
+        assert!(BehaviorTree::parse_toml("[root]\ntype = \"selector\"\nchildren = []").is_err());
+        assert!(BehaviorTree::parse_toml("[root]\ntype = \"health_below\"\nratio = 2.0").is_err());
+    }
+}
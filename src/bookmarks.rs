@@ -0,0 +1,140 @@
+/// 會話書籤
+///
+/// 在實時會話中標記值得回頭查看的時刻（例如出現不同步、後端崩潰），
+/// 書籤以 JSON Lines 格式附加到檔案中，方便之後回放時快速跳轉
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use anyhow::{Result, Context};
+use log::info;
+
+/// 預設書籤檔案
+pub const DEFAULT_BOOKMARK_FILE: &str = "bookmarks.jsonl";
+
+/// 單一書籤
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    /// 建立時間（Unix 毫秒）
+    pub timestamp_ms: u64,
+    /// 距離會話開始的秒數
+    pub session_secs: f64,
+    /// 玩家名稱
+    pub player_name: String,
+    /// 建立時的玩家位置
+    pub position: (f32, f32),
+    /// 備註（可選）
+    pub note: Option<String>,
+}
+
+/// 書籤儲存
+#[derive(Debug, Clone)]
+pub struct BookmarkStore {
+    /// 書籤檔案路徑
+    path: PathBuf,
+    /// 會話開始時間
+    session_start: Instant,
+    /// 本次會話建立的書籤
+    bookmarks: Vec<Bookmark>,
+}
+
+impl BookmarkStore {
+    /// 創建新的書籤儲存
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            session_start: Instant::now(),
+            bookmarks: Vec::new(),
+        }
+    }
+
+    /// 添加書籤並附加到檔案
+    pub fn add(&mut self, player_name: &str, position: (f32, f32), note: Option<String>) -> Result<&Bookmark> {
+        let bookmark = Bookmark {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64,
+            session_secs: self.session_start.elapsed().as_secs_f64(),
+            player_name: player_name.to_string(),
+            position,
+            note,
+        };
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("無法開啟書籤檔案: {:?}", self.path))?;
+        writeln!(file, "{}", serde_json::to_string(&bookmark)?)
+            .with_context(|| format!("無法寫入書籤檔案: {:?}", self.path))?;
+
+        info!("🔖 已添加書籤 +{:.1}s {}", bookmark.session_secs, bookmark.note.as_deref().unwrap_or(""));
+
+        self.bookmarks.push(bookmark);
+        Ok(self.bookmarks.last().unwrap())
+    }
+
+    /// 本次會話建立的書籤
+    pub fn session_bookmarks(&self) -> &[Bookmark] {
+        &self.bookmarks
+    }
+
+    /// 書籤檔案路徑
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 從檔案讀取所有書籤（檔案不存在時返回空列表）
+    pub fn load_from_file(path: &Path) -> Result<Vec<Bookmark>> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("無法讀取書籤檔案: {:?}", path))?;
+
+        content.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str::<Bookmark>(line)
+                    .with_context(|| format!("書籤檔案第 {} 行格式錯誤", i + 1))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appends_bookmarks_and_reads_them_back() {
+        let dir = std::env::temp_dir().join(format!("omobaf_bookmarks_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(DEFAULT_BOOKMARK_FILE);
+        let _ = std::fs::remove_file(&path);
+        assert!(BookmarkStore::load_from_file(&path).unwrap().is_empty(), "檔案不存在時為空");
+
+        let mut store = BookmarkStore::new(&path);
+        store.add("p1", (10.0, 20.0), Some("後端崩潰".to_string())).unwrap();
+        store.add("p1", (30.0, 40.0), None).unwrap();
+        assert_eq!(store.session_bookmarks().len(), 2);
+        assert_eq!(store.path(), path);
+
+        // 另一個會話附加到同一個檔案
+        BookmarkStore::new(&path).add("p2", (0.0, 0.0), None).unwrap();
+        let loaded = BookmarkStore::load_from_file(&path).unwrap();
+        let summary: Vec<(&str, (f32, f32), Option<&str>)> = loaded.iter()
+            .map(|bookmark| (bookmark.player_name.as_str(), bookmark.position, bookmark.note.as_deref()))
+            .collect();
+        assert_eq!(summary, [("p1", (10.0, 20.0), Some("後端崩潰")), ("p1", (30.0, 40.0), None), ("p2", (0.0, 0.0), None)]);
+        assert!(loaded[0].session_secs <= loaded[1].session_secs);
+
+        std::fs::write(&path, std::fs::read_to_string(&path).unwrap() + "\n{broken\n").unwrap();
+        let error = BookmarkStore::load_from_file(&path).unwrap_err();
+        assert!(error.to_string().contains("第 5 行"), "{}", error);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,282 @@
+/// Rhai 機器人腳本
+///
+/// 互動模式的 `script load <檔案>` 編譯一個 Rhai 腳本，`script run [秒數]` 以固定間隔同步狀態後呼叫腳本的
+/// `fn tick(state)`：`state` 是唯讀的狀態快照（`hero`、`entities`、`players`，皆為本地座標），腳本以 `move_to(x, y)`、
+/// `attack(x, y)`、`cast(ability)`、`cast(ability, x, y)` 排入操作，由客戶端經 PlayerSimulator 依序送出。
+/// 函式內的 `this` 是跨 tick 保留的記憶（物件映射），可記錄巡邏點等狀態；腳本的運算次數有上限，無窮迴圈只會讓該次 tick 失敗
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use rhai::{CallFnOptions, Dynamic, Engine, Scope, AST};
+use tokio::time::{interval, Instant, MissedTickBehavior};
+use vek::Vec2;
+
+use crate::coords::CoordinateTransform;
+use crate::game_client::GameClient;
+use crate::game_state::GameState;
+
+/// 每次 tick 腳本可執行的運算次數上限
+const MAX_OPERATIONS: u64 = 200_000;
+
+/// 每次 tick 最多送出的操作數（多出的捨棄，避免腳本灌爆後端）
+const MAX_ACTIONS_PER_TICK: usize = 4;
+
+/// 腳本排入的操作（本地座標）
+#[derive(Debug, Clone, PartialEq)]
+pub enum BotCommand {
+    Move(Vec2<f32>),
+    Attack(Vec2<f32>),
+    Cast { ability: String, target: Option<Vec2<f32>> },
+}
+
+/// 腳本以整數或浮點數傳入的座標
+fn number(value: &Dynamic) -> f32 {
+    value.as_float().ok().map(|value| value as f32)
+        .or_else(|| value.as_int().ok().map(|value| value as f32))
+        .unwrap_or_default()
+}
+
+/// 編譯完成的機器人腳本
+pub struct BotScript {
+    pub path: PathBuf,
+    engine: Engine,
+    ast: AST,
+    /// 腳本頂層宣告的變數
+    scope: Scope<'static>,
+    /// 跨 tick 保留的記憶（腳本中的 this）
+    memory: Dynamic,
+    /// 本次 tick 排入的操作
+    queued: Arc<Mutex<Vec<BotCommand>>>,
+}
+
+/// 一次執行的統計
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BotRunStats {
+    pub ticks: u64,
+    pub actions: u64,
+    pub errors: u64,
+}
+
+impl BotScript {
+    /// 載入並編譯腳本檔
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("無法讀取腳本: {:?}", path))?;
+        let mut script = Self::compile(&source).with_context(|| format!("腳本有誤: {:?}", path))?;
+        script.path = path.to_path_buf();
+        Ok(script)
+    }
+
+    /// 編譯腳本並執行頂層敘述，腳本必須定義 `fn tick(state)`
+    pub fn compile(source: &str) -> Result<Self> {
+        let queued: Arc<Mutex<Vec<BotCommand>>> = Arc::default();
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.on_print(|text| info!("🤖 {}", text));
+        engine.on_debug(|text, _, _| log::debug!("🤖 {}", text));
+        let queue = queued.clone();
+        engine.register_fn("move_to", move |x: Dynamic, y: Dynamic| {
+            queue.lock().unwrap().push(BotCommand::Move(Vec2::new(number(&x), number(&y))));
+        });
+        let queue = queued.clone();
+        engine.register_fn("attack", move |x: Dynamic, y: Dynamic| {
+            queue.lock().unwrap().push(BotCommand::Attack(Vec2::new(number(&x), number(&y))));
+        });
+        let queue = queued.clone();
+        engine.register_fn("cast", move |ability: &str| {
+            queue.lock().unwrap().push(BotCommand::Cast { ability: ability.to_string(), target: None });
+        });
+        let queue = queued.clone();
+        engine.register_fn("cast", move |ability: &str, x: Dynamic, y: Dynamic| {
+            let target = Some(Vec2::new(number(&x), number(&y)));
+            queue.lock().unwrap().push(BotCommand::Cast { ability: ability.to_string(), target });
+        });
+
+        let ast = engine.compile(source).map_err(|e| anyhow!("{}", e))?;
+        if !ast.iter_functions().any(|function| function.name == "tick" && function.params.len() == 1) {
+            return Err(anyhow!("腳本沒有定義 fn tick(state)"));
+        }
+        let mut scope = Scope::new();
+        engine.run_ast_with_scope(&mut scope, &ast).map_err(|e| anyhow!("{}", e))?;
+        // 頂層敘述排入的操作不送出
+        queued.lock().unwrap().clear();
+        Ok(Self { path: PathBuf::new(), engine, ast, scope, memory: Dynamic::from_map(rhai::Map::new()), queued })
+    }
+
+    /// 以狀態快照呼叫 tick，返回排入的操作
+    pub fn decide(&mut self, state: &serde_json::Value) -> Result<Vec<BotCommand>> {
+        let state = rhai::serde::to_dynamic(state).map_err(|e| anyhow!("{}", e))?;
+        let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut self.memory);
+        let result = self.engine.call_fn_with_options::<Dynamic>(options, &mut self.scope, &self.ast, "tick", (state,));
+        let mut commands = std::mem::take(&mut *self.queued.lock().unwrap());
+        result.map(drop).map_err(|e| anyhow!("tick 失敗: {}", e))?;
+        if commands.len() > MAX_ACTIONS_PER_TICK {
+            warn!("腳本一次排入 {} 個操作，只送出前 {} 個", commands.len(), MAX_ACTIONS_PER_TICK);
+            commands.truncate(MAX_ACTIONS_PER_TICK);
+        }
+        Ok(commands)
+    }
+
+    /// 以固定間隔執行腳本，直到時間結束或按下 Ctrl-C
+    pub async fn run(&mut self, client: &mut GameClient, duration: Duration, period: Duration) -> Result<BotRunStats> {
+        let mut stats = BotRunStats::default();
+        let deadline = Instant::now() + duration;
+        let mut ticker = interval(period);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep_until(deadline) => break,
+                _ = tokio::signal::ctrl_c() => break,
+                _ = ticker.tick() => {}
+            }
+            client.tick().await?;
+            if !client.get_state().in_game() {
+                continue;
+            }
+            let state = snapshot(client.get_game_state(), client.coordinates(), stats.ticks);
+            stats.ticks += 1;
+            for command in self.decide(&state)? {
+                match execute(client, &command).await {
+                    Ok(()) => stats.actions += 1,
+                    Err(e) => {
+                        stats.errors += 1;
+                        warn!("腳本操作 {:?} 失敗: {}", command, e);
+                    }
+                }
+            }
+        }
+        Ok(stats)
+    }
+}
+
+/// 腳本看到的狀態快照（本地座標）
+pub fn snapshot(state: &GameState, coordinates: CoordinateTransform, tick: u64) -> serde_json::Value {
+    let local = |position: Vec2<f32>| coordinates.to_local(position);
+    let hero = &state.local_player;
+    let hero_position = local(hero.position);
+    let abilities: Vec<_> = hero.abilities.iter().map(|ability| serde_json::json!({
+        "id": ability.ability_id,
+        "level": ability.level,
+        "cooldown": ability.cooldown_remaining,
+        "ready": ability.is_available && ability.cooldown_remaining <= 0.0,
+    })).collect();
+    let mut entities: Vec<_> = state.entities.values().collect();
+    entities.sort_by_key(|entity| entity.id);
+    let entities: Vec<_> = entities.into_iter().map(|entity| {
+        let position = local(entity.position);
+        serde_json::json!({
+            "id": entity.id,
+            "kind": crate::testkit::entity_kind(&entity.entity_type),
+            "x": position.x,
+            "y": position.y,
+            "health": entity.health.0,
+            "max_health": entity.health.1,
+        })
+    }).collect();
+    let mut players: Vec<_> = state.other_players.values().collect();
+    players.sort_by(|a, b| a.name.cmp(&b.name));
+    let players: Vec<_> = players.into_iter().map(|player| {
+        let position = local(Vec2::new(player.position.0, player.position.1));
+        serde_json::json!({
+            "name": player.name,
+            "hero": player.hero_type,
+            "x": position.x,
+            "y": position.y,
+            "health": player.health.0,
+            "max_health": player.health.1,
+        })
+    }).collect();
+    serde_json::json!({
+        "tick": tick,
+        "hero": {
+            "name": hero.name,
+            "hero": hero.hero_type,
+            "x": hero_position.x,
+            "y": hero_position.y,
+            "health": hero.health.0,
+            "max_health": hero.health.1,
+            "level": hero.level,
+            "abilities": abilities,
+        },
+        "entities": entities,
+        "players": players,
+    })
+}
+
+/// 送出腳本排入的操作
+async fn execute(client: &mut GameClient, command: &BotCommand) -> Result<()> {
+    let backend = |position: Vec2<f32>| client.coordinates().to_backend(position);
+    match command {
+        BotCommand::Move(target) => {
+            let target = backend(*target);
+            client.perform_action("move", serde_json::json!({ "target_x": target.x, "target_y": target.y })).await
+        }
+        BotCommand::Attack(target) => {
+            let target = backend(*target);
+            client.perform_action("attack", serde_json::json!({
+                "target_position": [target.x, target.y],
+                "attack_type": "basic",
+            })).await
+        }
+        BotCommand::Cast { ability, target } => {
+            let mut params = serde_json::json!({ "ability_id": ability });
+            if let Some(target) = target {
+                let target = backend(*target);
+                params["target_position"] = serde_json::json!([target.x, target.y]);
+            }
+            client.perform_action("cast_ability", params).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::{Entity, EntityType};
+
+    #[test]
+    fn script_reads_state_and_queues_actions() {
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        state.local_player.position = Vec2::new(100.0, 100.0);
+        state.entities.insert(4, Entity {
+            id: 4, entity_type: EntityType::Creep, position: Vec2::new(130.0, 140.0),
+            health: (50.0, 100.0), owner: None, debug_label: None,
+        });
+        let mut script = BotScript::compile(r#"
+            const RANGE = 60.0;
+            fn tick(state) {
+                if this.ticks == () { this.ticks = 0; }
+                this.ticks += 1;
+                let hero = state.hero;
+                for creep in state.entities.filter(|e| e.kind == "creep") {
+                    let dx = creep.x - hero.x;
+                    let dy = creep.y - hero.y;
+                    if dx * dx + dy * dy <= RANGE * RANGE {
+                        attack(creep.x, creep.y);
+                        return;
+                    }
+                }
+                if this.ticks > 1 { cast("sniper_mode"); }
+                move_to(0, 0);
+            }
+        "#).unwrap();
+
+        let view = snapshot(&state, CoordinateTransform::default(), 0);
+        assert_eq!(script.decide(&view).unwrap(), vec![BotCommand::Attack(Vec2::new(130.0, 140.0))]);
+        state.entities.clear();
+        let view = snapshot(&state, CoordinateTransform::default(), 1);
+        assert_eq!(script.decide(&view).unwrap(), vec![
+            BotCommand::Cast { ability: "sniper_mode".to_string(), target: None },
+            BotCommand::Move(Vec2::zero()),
+        ], "this 在 tick 之間保留");
+
+        let mut farm = BotScript::load(Path::new("scripts/bots/farm.rhai")).unwrap();
+        assert_eq!(farm.decide(&view).unwrap(), vec![BotCommand::Move(Vec2::new(200.0, 300.0))]);
+
+        assert!(BotScript::compile("let x = 1;").is_err(), "沒有 tick 函式");
+        let mut spinning = BotScript::compile("fn tick(state) { loop {} }").unwrap();
+        assert!(spinning.decide(&view).is_err(), "運算次數有上限");
+    }
+}
@@ -0,0 +1,109 @@
+/// 增益與減益狀態
+///
+/// 後端以 `status_effect` 訊息通知狀態效果：`a` 為 `apply`（新增或刷新）或 `remove`，
+/// `d` 為 `{"id": "haste", "stacks": 2, "duration": 5.0, "source": "p2", "debuff": false, "entity": 12}`。
+/// 玩家主題 `td/{player_name}/send` 上不帶 `entity` 的效果屬於該玩家（只追蹤本地英雄），帶 `entity` 或來自廣播的效果屬於該實體。
+/// 剩餘時間在每幀的 `update_cooldowns` 中遞減，歸零即移除；未帶 `duration` 的效果持續到後端移除。
+/// 終端視圖在狀態列上方以圖示列顯示本地英雄的效果（增益綠色 ▲、減益紅色 ▼）
+use serde::{Deserialize, Serialize};
+
+/// 圖示列中效果名稱最多顯示的字元數
+const ICON_NAME_CHARS: usize = 4;
+
+/// 狀態效果訊息
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StatusEffectData {
+    pub id: String,
+    #[serde(default = "default_stacks")]
+    pub stacks: u32,
+    /// 剩餘秒數（未提供時持續到移除）
+    #[serde(default)]
+    pub duration: Option<f32>,
+    /// 施加者
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub debuff: bool,
+    /// 受影響的實體 ID（未提供時為訊息中的玩家）
+    #[serde(default)]
+    pub entity: Option<u32>,
+}
+
+fn default_stacks() -> u32 {
+    1
+}
+
+/// 單一狀態效果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Buff {
+    pub id: String,
+    pub stacks: u32,
+    /// 剩餘秒數（None 為持續到移除）
+    pub remaining: Option<f32>,
+    pub source: Option<String>,
+    pub debuff: bool,
+}
+
+/// 套用 `apply` 或 `remove` 訊息（同 ID 的效果會被刷新）
+pub fn apply(buffs: &mut Vec<Buff>, action: &str, effect: &StatusEffectData) {
+    buffs.retain(|buff| buff.id != effect.id);
+    if action != "remove" {
+        buffs.push(Buff {
+            id: effect.id.clone(),
+            stacks: effect.stacks,
+            remaining: effect.duration,
+            source: effect.source.clone(),
+            debuff: effect.debuff,
+        });
+    }
+}
+
+/// 遞減剩餘時間並移除到期的效果
+pub fn decay(buffs: &mut Vec<Buff>, delta_time: f32) {
+    for buff in buffs.iter_mut() {
+        if let Some(remaining) = &mut buff.remaining {
+            *remaining -= delta_time;
+        }
+    }
+    buffs.retain(|buff| buff.remaining.is_none_or(|remaining| remaining > 0.0));
+}
+
+impl Buff {
+    /// 圖示列中的一格（例如 `▲hast×2 3s`、`▼slow 1s`）
+    pub fn icon(&self) -> String {
+        let mut icon = format!("{}{}", if self.debuff { '▼' } else { '▲' }, self.id.chars().take(ICON_NAME_CHARS).collect::<String>());
+        if self.stacks > 1 {
+            icon.push_str(&format!("×{}", self.stacks));
+        }
+        if let Some(remaining) = self.remaining {
+            icon.push_str(&format!(" {:.0}s", remaining.ceil()));
+        }
+        icon
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refreshes_removes_and_expires_effects() {
+        let effect: StatusEffectData = serde_json::from_str(r#"{"id": "haste", "stacks": 2, "duration": 3.0}"#).unwrap();
+        let slow: StatusEffectData = serde_json::from_str(r#"{"id": "slow", "duration": 1.0, "debuff": true, "source": "p2"}"#).unwrap();
+        let aura: StatusEffectData = serde_json::from_str(r#"{"id": "aura"}"#).unwrap();
+        let mut buffs = Vec::new();
+        apply(&mut buffs, "apply", &effect);
+        apply(&mut buffs, "apply", &slow);
+        apply(&mut buffs, "apply", &aura);
+        apply(&mut buffs, "apply", &effect);
+        assert_eq!(buffs.iter().map(Buff::icon).collect::<Vec<_>>(), ["▼slow 1s", "▲aura", "▲hast×2 3s"]);
+
+        decay(&mut buffs, 1.5);
+        assert_eq!(buffs.iter().map(|buff| buff.id.as_str()).collect::<Vec<_>>(), ["aura", "haste"], "到期的效果移除");
+        assert_eq!(buffs[1].icon(), "▲hast×2 2s");
+
+        apply(&mut buffs, "remove", &aura);
+        decay(&mut buffs, 10.0);
+        assert!(buffs.is_empty());
+    }
+}
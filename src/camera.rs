@@ -0,0 +1,197 @@
+/// 鏡頭與視野更新
+///
+/// 鏡頭可以跟隨英雄、固定在指定位置或總覽整張地圖。每個 tick 依鏡頭模式與縮放算出視野，
+/// 與上次送出的視野相差一格以上或範圍改變時，經過防抖間隔後送出 update_viewport；
+/// 畫面請求循環也透過共享的視野使用同一個中心與範圍
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use vek::Vec2;
+
+use crate::game_state::GameState;
+
+/// 視為鏡頭移動的最小距離（一個字元）
+const MIN_SHIFT: f32 = 10.0;
+
+/// 縮放範圍（與視窗縮放的限制一致）
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 3.0;
+
+/// 鏡頭模式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraMode {
+    /// 跟隨英雄
+    Follow,
+    /// 固定在指定位置（後端座標）
+    Fixed(Vec2<f32>),
+    /// 總覽整張地圖（地圖邊界未知時跟隨英雄）
+    Overview,
+}
+
+impl CameraMode {
+    /// 顯示用名稱
+    pub fn label(&self) -> &'static str {
+        match self {
+            CameraMode::Follow => "跟隨英雄",
+            CameraMode::Fixed(_) => "固定位置",
+            CameraMode::Overview => "地圖總覽",
+        }
+    }
+}
+
+/// 鏡頭視野（後端座標）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraView {
+    pub center: Vec2<f32>,
+    pub width: f32,
+    pub height: f32,
+    pub zoom: f32,
+}
+
+impl CameraView {
+    /// 視野範圍 (min_x, min_y, max_x, max_y)
+    pub fn area(&self) -> (f32, f32, f32, f32) {
+        (
+            self.center.x - self.width / 2.0,
+            self.center.y - self.height / 2.0,
+            self.center.x + self.width / 2.0,
+            self.center.y + self.height / 2.0,
+        )
+    }
+
+    /// 與另一個視野相比是否有明顯變化
+    fn differs_from(&self, other: &CameraView) -> bool {
+        (self.center - other.center).magnitude() >= MIN_SHIFT
+            || (self.width - other.width).abs() >= MIN_SHIFT
+            || (self.height - other.height).abs() >= MIN_SHIFT
+            || self.zoom != other.zoom
+    }
+}
+
+/// 在背景任務間共享的鏡頭視野（畫面請求循環使用）
+pub type SharedCamera = Arc<Mutex<Option<CameraView>>>;
+
+/// 鏡頭
+#[derive(Debug, Clone)]
+pub struct Camera {
+    mode: CameraMode,
+    zoom: f32,
+    /// 兩次視野更新之間的最短間隔
+    debounce: Duration,
+    last_sent: Option<(CameraView, Instant)>,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            mode: CameraMode::Follow,
+            zoom: 1.0,
+            debounce: Duration::from_millis(250),
+            last_sent: None,
+        }
+    }
+}
+
+impl Camera {
+    /// 鏡頭模式
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    /// 切換鏡頭模式
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        self.mode = mode;
+    }
+
+    /// 設置縮放（限制在 0.5 到 3.0）
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    /// 設置防抖間隔
+    pub fn set_debounce(&mut self, debounce: Duration) {
+        self.debounce = debounce;
+    }
+
+    /// 依鏡頭模式計算目前的視野
+    pub fn view(&self, state: &GameState) -> CameraView {
+        let range = &state.viewport.display_range;
+        let follow = |center: Vec2<f32>| CameraView {
+            center,
+            width: range.width / self.zoom,
+            height: range.height / self.zoom,
+            zoom: self.zoom,
+        };
+        match (self.mode, state.world_bounds) {
+            (CameraMode::Follow, _) | (CameraMode::Overview, None) => follow(state.local_player.position),
+            (CameraMode::Fixed(center), _) => follow(center),
+            (CameraMode::Overview, Some(bounds)) => CameraView {
+                center: Vec2::new((bounds.min_x + bounds.max_x) / 2.0, (bounds.min_y + bounds.max_y) / 2.0),
+                width: bounds.max_x - bounds.min_x,
+                height: bounds.max_y - bounds.min_y,
+                zoom: self.zoom,
+            },
+        }
+    }
+
+    /// 視野有明顯變化且距上次送出已超過防抖間隔時返回需要送出的視野
+    pub fn poll(&mut self, view: CameraView, now: Instant) -> Option<CameraView> {
+        if let Some((last, sent_at)) = &self.last_sent {
+            if !view.differs_from(last) || now.duration_since(*sent_at) < self.debounce {
+                return None;
+            }
+        }
+        self.mark_sent(view, now);
+        Some(view)
+    }
+
+    /// 忘記上次送出的視野，下一次 poll 立即送出（後端遺失視野設定時使用）
+    pub fn forget_sent(&mut self) {
+        self.last_sent = None;
+    }
+
+    /// 記錄已送出的視野
+    pub fn mark_sent(&mut self, view: CameraView, now: Instant) {
+        self.last_sent = Some((view, now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::WorldBounds;
+
+    #[test]
+    fn follows_modes_and_debounces_updates() {
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        state.local_player.position = Vec2::new(100.0, 100.0);
+        let mut camera = Camera::default();
+        let start = Instant::now();
+
+        let view = camera.view(&state);
+        assert_eq!(view.center, Vec2::new(100.0, 100.0));
+        assert!(camera.poll(view, start).is_some());
+
+        // 小於一格的移動不送出；防抖間隔內的大幅移動延後送出
+        state.local_player.position = Vec2::new(105.0, 100.0);
+        assert!(camera.poll(camera.view(&state), start + Duration::from_millis(300)).is_none());
+        state.local_player.position = Vec2::new(200.0, 100.0);
+        assert!(camera.poll(camera.view(&state), start + Duration::from_millis(100)).is_none());
+        assert!(camera.poll(camera.view(&state), start + Duration::from_millis(300)).is_some());
+        // 後端遺失視野設定時立即重送
+        camera.forget_sent();
+        assert!(camera.poll(camera.view(&state), start + Duration::from_millis(310)).is_some());
+
+        camera.set_zoom(2.0);
+        let zoomed = camera.view(&state);
+        assert_eq!(zoomed.width, state.viewport.display_range.width / 2.0);
+
+        camera.set_mode(CameraMode::Fixed(Vec2::new(-50.0, 20.0)));
+        assert_eq!(camera.view(&state).center, Vec2::new(-50.0, 20.0));
+
+        camera.set_mode(CameraMode::Overview);
+        assert_eq!(camera.view(&state).center, Vec2::new(200.0, 100.0));
+        state.world_bounds = Some(WorldBounds { min_x: 0.0, min_y: 0.0, max_x: 1000.0, max_y: 600.0 });
+        let overview = camera.view(&state);
+        assert_eq!(overview.area(), (0.0, 0.0, 1000.0, 600.0));
+    }
+}
@@ -0,0 +1,223 @@
+/// 技能施放統計
+///
+/// 每次送出 cast_ability 記為一次嘗試，等待後端在 `td/{player_name}/cast_result` 回覆
+/// `{"t": "cast_result", "d": {"ability_id": "...", "ok": true}}`；拒絕時 `ok = false` 並帶上 `reason`
+/// （`cooldown`、`range`、`mana`，其他原因歸為 other），超過 `[expectations] timeout_ms` 沒有回覆記為逾時。
+/// 依技能統計嘗試、確認、各原因的拒絕、逾時與平均回覆延遲，互動模式的 `casts` 與斷線時的會話報告會列出，
+/// 後端對某個技能的處理不穩定時一眼可見
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+use crate::mqtt_handler::WireFormat;
+use crate::terminal_view::text_width::pad_to_width;
+
+/// 預設的回覆逾時
+const DEFAULT_ACK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 統計表各欄的顯示寬度
+const TABLE_WIDTHS: [usize; 10] = [20, 6, 6, 6, 6, 6, 6, 6, 8, 10];
+
+/// 後端拒絕施放的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectReason {
+    Cooldown,
+    Range,
+    Mana,
+    #[serde(other)]
+    Other,
+}
+
+/// 後端的施放結果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CastResult {
+    pub ability_id: String,
+    pub ok: bool,
+    #[serde(default)]
+    pub reason: Option<RejectReason>,
+}
+
+/// 解析施放結果（`{"d": {...}}` 或直接是結果本身）
+pub fn parse(format: WireFormat, payload: &[u8]) -> Option<CastResult> {
+    let value: serde_json::Value = format.decode(payload).ok()?;
+    serde_json::from_value(value.get("d").unwrap_or(&value).clone()).ok()
+}
+
+/// 單一技能的施放統計
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AbilityCastStats {
+    pub attempts: u64,
+    pub confirmed: u64,
+    pub rejected_cooldown: u64,
+    pub rejected_range: u64,
+    pub rejected_mana: u64,
+    pub rejected_other: u64,
+    pub timeouts: u64,
+    /// 所有回覆（確認與拒絕）的延遲總和
+    latency_total: Duration,
+}
+
+impl AbilityCastStats {
+    pub fn rejected(&self) -> u64 {
+        self.rejected_cooldown + self.rejected_range + self.rejected_mana + self.rejected_other
+    }
+
+    /// 確認數佔已有結果（確認、拒絕、逾時）的比例
+    pub fn success_rate(&self) -> Option<f64> {
+        let settled = self.confirmed + self.rejected() + self.timeouts;
+        (settled > 0).then(|| self.confirmed as f64 / settled as f64)
+    }
+
+    /// 平均回覆延遲
+    pub fn average_latency(&self) -> Option<Duration> {
+        let acks = self.confirmed + self.rejected();
+        (acks > 0).then(|| self.latency_total / acks as u32)
+    }
+}
+
+/// 技能施放追蹤
+#[derive(Debug, Clone)]
+pub struct CastStats {
+    timeout: Duration,
+    /// 等待回覆的施放（技能、送出時間），依送出順序
+    pending: VecDeque<(String, Instant)>,
+    abilities: BTreeMap<String, AbilityCastStats>,
+}
+
+/// 在事件循環與客戶端間共享的施放統計
+pub type SharedCastStats = Arc<Mutex<CastStats>>;
+
+impl Default for CastStats {
+    fn default() -> Self {
+        Self { timeout: DEFAULT_ACK_TIMEOUT, pending: VecDeque::new(), abilities: BTreeMap::new() }
+    }
+}
+
+impl CastStats {
+    /// 設定回覆逾時
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// 記錄送出一次施放
+    pub fn attempt(&mut self, ability_id: &str, now: Instant) {
+        self.expire(now);
+        self.abilities.entry(ability_id.to_string()).or_default().attempts += 1;
+        self.pending.push_back((ability_id.to_string(), now));
+    }
+
+    /// 以後端的結果對應同一技能最早等待中的施放，返回是否有對應的施放
+    pub fn resolve(&mut self, result: &CastResult, now: Instant) -> bool {
+        self.expire(now);
+        let Some(index) = self.pending.iter().position(|(ability_id, _)| *ability_id == result.ability_id) else {
+            log::debug!("收到沒有對應施放的結果: {:?}", result);
+            return false;
+        };
+        let (ability_id, sent) = self.pending.remove(index).expect("index 來自 position");
+        let stats = self.abilities.entry(ability_id).or_default();
+        stats.latency_total += now.duration_since(sent);
+        match (result.ok, result.reason) {
+            (true, _) => stats.confirmed += 1,
+            (false, Some(RejectReason::Cooldown)) => stats.rejected_cooldown += 1,
+            (false, Some(RejectReason::Range)) => stats.rejected_range += 1,
+            (false, Some(RejectReason::Mana)) => stats.rejected_mana += 1,
+            (false, Some(RejectReason::Other) | None) => stats.rejected_other += 1,
+        }
+        true
+    }
+
+    /// 超過逾時仍沒有回覆的施放記為逾時
+    pub fn expire(&mut self, now: Instant) {
+        while let Some((ability_id, sent)) = self.pending.front() {
+            if now.duration_since(*sent) < self.timeout {
+                break;
+            }
+            log::warn!("⌛ 技能 {} 的施放沒有收到後端結果", ability_id);
+            self.abilities.entry(ability_id.clone()).or_default().timeouts += 1;
+            self.pending.pop_front();
+        }
+    }
+
+    /// 各技能的統計
+    pub fn abilities(&self) -> &BTreeMap<String, AbilityCastStats> {
+        &self.abilities
+    }
+
+    /// 等待回覆的施放數
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// 清除統計
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.abilities.clear();
+    }
+
+    /// 每個技能一行的統計表（第一行為標題）
+    pub fn table(&self) -> Vec<String> {
+        let row = |cells: [String; 10]| cells.iter().zip(TABLE_WIDTHS)
+            .map(|(cell, width)| pad_to_width(cell, width))
+            .collect::<Vec<_>>()
+            .join(" ")
+            .trim_end()
+            .to_string();
+        let header = ["技能", "嘗試", "確認", "冷卻", "距離", "魔力", "其他", "逾時", "成功率", "平均延遲"];
+        let mut lines = vec![row(header.map(str::to_string))];
+        for (ability_id, stats) in &self.abilities {
+            lines.push(row([
+                ability_id.clone(),
+                stats.attempts.to_string(),
+                stats.confirmed.to_string(),
+                stats.rejected_cooldown.to_string(),
+                stats.rejected_range.to_string(),
+                stats.rejected_mana.to_string(),
+                stats.rejected_other.to_string(),
+                stats.timeouts.to_string(),
+                stats.success_rate().map_or("-".to_string(), |rate| format!("{:.0}%", rate * 100.0)),
+                stats.average_latency().map_or("-".to_string(), |latency| format!("{}ms", latency.as_millis())),
+            ]));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(ability_id: &str, ok: bool, reason: Option<RejectReason>) -> CastResult {
+        CastResult { ability_id: ability_id.to_string(), ok, reason }
+    }
+
+    #[test]
+    fn tallies_confirmations_rejections_and_timeouts_per_ability() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut casts = CastStats::default();
+        casts.set_timeout(Duration::from_millis(1000));
+
+        casts.attempt("sniper_mode", at(0));
+        casts.attempt("rain_iron_cannon", at(10));
+        casts.attempt("sniper_mode", at(20));
+        assert!(casts.resolve(&result("sniper_mode", true, None), at(100)));
+        assert!(casts.resolve(&result("sniper_mode", false, Some(RejectReason::Cooldown)), at(320)));
+        assert!(!casts.resolve(&result("sniper_mode", true, None), at(330)), "沒有等待中的施放");
+        casts.expire(at(1500));
+
+        let sniper = &casts.abilities()["sniper_mode"];
+        assert_eq!((sniper.attempts, sniper.confirmed, sniper.rejected_cooldown, sniper.timeouts), (2, 1, 1, 0));
+        assert_eq!(sniper.average_latency(), Some(Duration::from_millis(200)));
+        assert_eq!(sniper.success_rate(), Some(0.5));
+        let rain = &casts.abilities()["rain_iron_cannon"];
+        assert_eq!((rain.timeouts, rain.success_rate(), rain.average_latency()), (1, Some(0.0), None));
+        assert_eq!(casts.pending(), 0);
+        assert_eq!(casts.table().len(), 3);
+
+        let payload = br#"{"t": "cast_result", "d": {"ability_id": "x", "ok": false, "reason": "silenced"}}"#;
+        assert_eq!(parse(WireFormat::Json, payload), Some(result("x", false, Some(RejectReason::Other))));
+        assert_eq!(parse(WireFormat::Json, br#"{"ability_id": "x", "ok": true}"#), Some(result("x", true, None)));
+    }
+}
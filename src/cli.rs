@@ -7,6 +7,8 @@ use log::{info, error, warn};
 use anyhow::Result;
 
 use crate::game_client::{GameClient, GameClientConfig};
+use crate::protocol_errors::ProtocolErrorFeed;
+use crate::strict::StrictReport;
 use crate::terminal_view::UserInput;
 
 /// omobaf - Open MOBA Frontend 假遊戲客戶端
@@ -16,29 +18,72 @@ pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
     
-    /// 服務器 IP 地址
-    #[arg(long, default_value = "127.0.0.1")]
-    pub server_ip: String,
+    /// 服務器 IP 地址（覆蓋 server.mqtt_host）
+    #[arg(long)]
+    pub server_ip: Option<String>,
     
-    /// 服務器端口
-    #[arg(long, default_value_t = 1883)]
-    pub server_port: u16,
+    /// 服務器端口（覆蓋 server.mqtt_port）
+    #[arg(long)]
+    pub server_port: Option<u16>,
     
     /// 客戶端 ID
     #[arg(long, default_value = "omobaf_player")]
     pub client_id: String,
     
-    /// 玩家名稱
-    #[arg(long, default_value = "TestPlayer")]
-    pub player_name: String,
+    /// 玩家名稱（覆蓋 frontend.player_name）
+    #[arg(long)]
+    pub player_name: Option<String>,
     
-    /// 英雄類型
-    #[arg(long, default_value = "saika_magoichi")]
-    pub hero: String,
+    /// 英雄類型（覆蓋 frontend.hero_type）
+    #[arg(long)]
+    pub hero: Option<String>,
+    
+    /// 配置 profile（疊加 config.<名稱>.toml，優先於 OMOBAF_PROFILE）
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
     
     /// 詳細日誌輸出
     #[arg(short, long)]
     pub verbose: bool,
+    
+    /// 試運行：只記錄將發送的操作主題與內容，不實際發佈（仍套用本地預測）
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+    
+    /// 唯讀：禁止所有操作（perform_action 返回權限錯誤），只保留觀看與遙測
+    #[arg(long, global = true)]
+    pub read_only: bool,
+    
+    /// 停用滑鼠，終端視圖改用鍵盤目標模式（覆蓋 input.mouse）
+    #[arg(long, global = true)]
+    pub no_mouse: bool,
+    
+    /// 把收發的每則 MQTT 訊息附加到錄製檔（JSON Lines，覆蓋 recording.path）
+    #[arg(long, global = true, value_name = "FILE")]
+    pub record: Option<String>,
+    
+    /// 嚴格模式：協議錯誤、訊息出現未定義欄位、位置同步差異超過門檻或操作未被確認時命令失敗（非零結束碼）
+    #[arg(long, global = true)]
+    pub strict: bool,
+    
+    /// 自動遊戲的亂數種子（操作歷史匯出中記錄的種子，可重現同一串隨機操作；swarm 的第 N 個客戶端使用種子 + N - 1）
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+}
+
+impl Cli {
+    /// 命令行明確指定的配置值
+    pub fn overrides(&self) -> crate::config_layers::CliOverrides {
+        crate::config_layers::CliOverrides {
+            profile: self.profile.clone(),
+            server_ip: self.server_ip.clone(),
+            server_port: self.server_port,
+            player_name: self.player_name.clone(),
+            hero: self.hero.clone(),
+            no_mouse: self.no_mouse,
+            record: self.record.clone(),
+        }
+    }
 }
 
 /// 子命令
@@ -62,7 +107,7 @@ pub enum Commands {
     
     /// 開始遊戲，選擇英雄
     Play {
-        /// 英雄類型 (saika_magoichi 或 date_masamune)
+        /// 英雄 ID（heroes/*.toml 中定義，例如 saika_magoichi）
         #[arg(short, long)]
         hero: Option<String>,
     },
@@ -109,10 +154,57 @@ pub enum Commands {
         /// 持續時間（秒）
         #[arg(short, long, default_value_t = 60)]
         duration: u64,
+        /// 結束後把操作歷史與種子匯出到 JSON 檔
+        #[arg(long, value_name = "FILE")]
+        export: Option<std::path::PathBuf>,
     },
     
-    /// 執行演示序列
-    Demo,
+    /// 不開啟終端視圖，執行 YAML 動作腳本（任一步驟失敗時以非零狀態結束，適合 CI）
+    Run {
+        /// 腳本檔 (YAML)
+        script: std::path::PathBuf,
+    },
+    
+    /// 訂閱所有客戶端發佈的狀態雜湊（[state_hash] publish = true），依幀比對並標示持續的分歧
+    HashWatch {
+        /// 連續幾幀與多數不同才標示為分歧
+        #[arg(long, default_value_t = 3)]
+        tolerance: u32,
+    },
+    
+    /// 壓力測試：在同一個行程中建立 N 個自動遊戲的客戶端，並每秒輸出各連線狀態的人數
+    Swarm {
+        /// 客戶端數量
+        count: usize,
+        /// 總時長（秒）
+        #[arg(short, long, default_value_t = 60)]
+        duration: u64,
+        /// 相鄰兩個客戶端開始連線的間隔（毫秒）
+        #[arg(long, default_value_t = 200)]
+        ramp_ms: u64,
+        /// 每個客戶端兩次自動操作的間隔（毫秒）
+        #[arg(long, default_value_t = 1000)]
+        interval_ms: u64,
+        /// 玩家名稱前綴（玩家為 <前綴>_01、<前綴>_02 …）
+        #[arg(long, default_value = "swarm")]
+        prefix: String,
+        /// 身分池檔案（依序分配玩家名稱、英雄、陣營與 client ID，不足的以前綴編號補齊）
+        #[arg(long)]
+        identities: Option<std::path::PathBuf>,
+    },
+    
+    /// 引導回城到出生點
+    Recall,
+    
+    /// 回城、購買配置的物品後返回線上
+    ShopTrip,
+    
+    /// 執行英雄的演示序列並檢查預期結果（heroes/<英雄ID>/demo.toml）
+    Demo {
+        /// 英雄 ID（預設為配置的英雄）
+        #[arg(long)]
+        hero: Option<String>,
+    },
     
     /// 列出可用技能
     Abilities,
@@ -136,14 +228,155 @@ pub enum Commands {
         live: bool,
     },
     
+    /// 觀戰：以終端視圖顯示指定玩家的畫面（不發送任何操作，適合觀看機器人對戰）
+    Spectate {
+        /// 被觀戰的玩家名稱
+        player: String,
+        /// 視圖範圍半徑
+        #[arg(short, long, default_value_t = 20.0)]
+        radius: f32,
+        /// 是否顯示視野範圍
+        #[arg(long)]
+        show_vision: bool,
+    },
+    
+    /// 並排比較兩份錄製的回放
+    #[cfg(feature = "replay")]
+    Compare {
+        /// 左側錄製檔 (JSON Lines)
+        left: std::path::PathBuf,
+        /// 右側錄製檔 (JSON Lines)
+        right: std::path::PathBuf,
+        /// 回放速度倍率
+        #[arg(long, default_value_t = 1.0)]
+        speed: f32,
+        /// 位置分歧門檻（世界單位）
+        #[arg(long, default_value_t = 5.0)]
+        threshold: f32,
+    },
+    
+    /// 不連線後端，依原始時間回放錄製檔中收到的訊息並以終端視圖顯示
+    #[cfg(feature = "replay")]
+    Replay {
+        /// 錄製檔 (JSON Lines，以 --record 錄製)
+        file: std::path::PathBuf,
+        /// 回放速度倍率
+        #[arg(long, default_value_t = 1.0)]
+        speed: f32,
+        /// 是否顯示視野範圍
+        #[arg(long)]
+        show_vision: bool,
+    },
+    
+    /// 以內建的簡易 broker 把錄製檔中收到的訊息發佈給連上的其他 omobaf 實例（不需要後端）
+    #[cfg(feature = "replay")]
+    Serve {
+        /// 錄製檔 (JSON Lines，以 --record 錄製)
+        file: std::path::PathBuf,
+        /// 監聽位址
+        #[arg(long, default_value = "127.0.0.1:1884")]
+        listen: String,
+        /// 回放速度倍率
+        #[arg(long, default_value_t = 1.0)]
+        speed: f32,
+        /// 等待幾個客戶端訂閱後才開始回放
+        #[arg(long, default_value_t = 1)]
+        clients: usize,
+        /// 播完後從頭重播
+        #[arg(long = "loop")]
+        repeat: bool,
+    },
+    
     /// 斷開連接
     Disconnect,
+    
+    /// 命令稽核記錄
+    Audit {
+        #[command(subcommand)]
+        action: AuditAction,
+    },
+    
+    /// 配置檢視
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    
+    /// 開始會話前的自我測試
+    Selftest {
+        #[command(subcommand)]
+        target: SelftestTarget,
+    },
+    
+    /// 顯示版本、協議版本與支援的編碼格式
+    Version,
+    
+    /// 檢查並安裝新版的 omobaf（下載後以 SHA-256 校驗再取代目前的執行檔）
+    #[cfg(feature = "self-update")]
+    Update {
+        /// 只檢查是否有新版本，不下載
+        #[arg(long)]
+        check_only: bool,
+    },
+}
+
+impl Commands {
+    /// 是否使用終端視圖（視圖模式改用終端日誌系統）
+    fn is_view(&self) -> bool {
+        match self {
+            Commands::View { .. } | Commands::Spectate { .. } | Commands::Interactive { auto_view: true, .. } => true,
+            #[cfg(feature = "replay")]
+            Commands::Compare { .. } | Commands::Replay { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+/// 稽核記錄操作
+#[derive(Subcommand)]
+pub enum AuditAction {
+    /// 顯示最近的命令記錄
+    Show {
+        /// 顯示筆數
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+    },
+}
+
+/// 配置操作
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// 顯示合併各層後的有效配置與每個值的來源
+    Effective,
+}
+
+/// 自我測試項目
+#[derive(Subcommand)]
+pub enum SelftestTarget {
+    /// 透過 broker 回送 nonce，檢查往返延遲與 QoS
+    Mqtt {
+        /// 等待每個回應的逾時（毫秒）
+        #[arg(long, default_value_t = 3000)]
+        timeout_ms: u64,
+    },
 }
 
 /// CLI 處理器
 pub struct CliHandler {
     game_client: Option<GameClient>,
     backend_manager: Option<crate::backend_manager::BackendManager>,
+    /// 是否為試運行模式
+    dry_run: bool,
+    /// 是否為唯讀模式
+    read_only: bool,
+    /// 觀戰的玩家
+    spectate: Option<String>,
+    /// 是否為嚴格模式
+    strict: bool,
+    /// 自動遊戲的亂數種子
+    seed: Option<u64>,
+    /// 嚴格模式下訂閱的協議錯誤
+    protocol_errors: Option<ProtocolErrorFeed>,
 }
 
 impl CliHandler {
@@ -152,6 +385,12 @@ impl CliHandler {
         Self {
             game_client: None,
             backend_manager: None,
+            dry_run: false,
+            read_only: false,
+            spectate: None,
+            strict: false,
+            seed: None,
+            protocol_errors: None,
         }
     }
     
@@ -176,7 +415,7 @@ impl CliHandler {
     /// 處理 CLI 命令
     pub async fn handle_command(&mut self, cli: Cli) -> Result<()> {
         // 根據命令類型設置不同的日誌系統
-        let is_view_command = matches!(cli.command, Commands::View { .. } | Commands::Interactive { auto_view: true, .. });
+        let is_view_command = cli.command.is_view();
         
         if is_view_command {
             // 視圖模式使用自定義日誌系統
@@ -184,22 +423,46 @@ impl CliHandler {
         } else {
             // 其他模式使用標準日誌系統
             if cli.verbose {
-                env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+                env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug"))
+                    .format(crate::session_tag::format_record)
+                    .init();
             } else {
-                env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+                env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
+                    .format(crate::session_tag::format_record)
+                    .init();
             }
         }
         
-        // 創建遊戲客戶端配置
+        // 創建遊戲客戶端配置（命令行參數已疊加在配置之上）
+        let app_config = crate::config::AppConfig::load();
+        let server = app_config.server;
+        let frontend = app_config.frontend;
         let config = GameClientConfig {
-            server_ip: cli.server_ip.clone(),
-            server_port: cli.server_port,
+            server_ip: server.mqtt_host.clone(),
+            server_port: server.mqtt_port,
             client_id: cli.client_id.clone(),
-            player_name: cli.player_name.clone(),
-            hero_type: cli.hero.clone(),
+            player_name: frontend.player_name.clone(),
+            hero_type: frontend.hero_type.clone(),
+            team: None,
+            seed: cli.seed,
+            dry_run: cli.dry_run,
+            read_only: cli.read_only,
+            spectate: None,
+            wire_format: server.wire_format,
+            username: server.username,
+            password: server.password,
+            tls: server.tls,
+            qos: server.qos,
+            topics: server.topics,
+            failover: server.failover,
+            binary_decoders: server.binary_decoders,
         };
+        self.dry_run = cli.dry_run;
+        self.read_only = cli.read_only;
+        self.strict = cli.strict;
+        self.seed = cli.seed;
         
-        match cli.command {
+        let result = match cli.command {
             Commands::Interactive { auto_view, size, show_vision } => {
                 self.cmd_interactive(config, auto_view, size, show_vision).await
             },
@@ -207,7 +470,7 @@ impl CliHandler {
                 self.cmd_connect(config).await
             },
             Commands::Play { hero } => {
-                let hero_type = hero.unwrap_or(cli.hero);
+                let hero_type = hero.unwrap_or(frontend.hero_type);
                 let mut play_config = config;
                 play_config.hero_type = hero_type;
                 self.cmd_play(play_config).await
@@ -224,11 +487,33 @@ impl CliHandler {
             Commands::Status => {
                 self.cmd_status().await
             },
-            Commands::Auto { duration } => {
-                self.cmd_auto(duration).await
+            Commands::Auto { duration, export } => {
+                self.cmd_auto(duration, export.as_deref()).await
+            },
+            Commands::Run { script } => {
+                self.cmd_run(config, &script).await
+            },
+            Commands::HashWatch { tolerance } => {
+                self.cmd_hash_watch(config, tolerance).await
+            },
+            Commands::Swarm { count, duration, ramp_ms, interval_ms, prefix, identities } => {
+                let options = crate::swarm::SwarmOptions {
+                    duration: std::time::Duration::from_secs(duration),
+                    ramp: std::time::Duration::from_millis(ramp_ms),
+                    action_interval: std::time::Duration::from_millis(interval_ms),
+                };
+                self.cmd_swarm(config, count, &prefix, identities.as_deref(), options).await
+            },
+            Commands::Recall => {
+                self.cmd_recall().await
+            },
+            Commands::ShopTrip => {
+                self.cmd_shop_trip().await
             },
-            Commands::Demo => {
-                self.cmd_demo().await
+            Commands::Demo { hero } => {
+                let mut demo_config = config;
+                demo_config.hero_type = hero.unwrap_or(frontend.hero_type);
+                self.cmd_demo(demo_config).await
             },
             Commands::Abilities => {
                 self.cmd_abilities().await
@@ -236,10 +521,72 @@ impl CliHandler {
             Commands::View { radius, width, height, show_vision, live } => {
                 self.cmd_view(radius, width, height, show_vision, live).await
             },
+            Commands::Spectate { player, radius, show_vision } => {
+                info!("👁 觀戰玩家 {}", player);
+                self.spectate = Some(player);
+                self.cmd_view(Some(radius), None, None, show_vision, true).await
+            },
+            #[cfg(feature = "replay")]
+            Commands::Compare { left, right, speed, threshold } => {
+                self.cmd_compare(&left, &right, &frontend.player_name, &frontend.hero_type, speed, threshold).await
+            },
+            #[cfg(feature = "replay")]
+            Commands::Replay { file, speed, show_vision } => {
+                self.cmd_replay(&file, &frontend.player_name, &frontend.hero_type, speed, show_vision).await
+            },
+            #[cfg(feature = "replay")]
+            Commands::Serve { file, listen, speed, clients, repeat } => {
+                self.cmd_serve(&file, &listen, speed, clients, repeat).await
+            },
             Commands::Disconnect => {
                 self.cmd_disconnect().await
             },
+            Commands::Audit { action: AuditAction::Show { limit } } => {
+                crate::audit::AuditLog::default().show(limit)
+            },
+            Commands::Config { action: ConfigAction::Effective } => {
+                crate::config_layers::ResolvedConfig::resolve_current()?.print();
+                Ok(())
+            },
+            Commands::Selftest { target: SelftestTarget::Mqtt { timeout_ms } } => {
+                self.cmd_selftest_mqtt(config, timeout_ms).await
+            },
+            Commands::Version => {
+                // 命令行模式不保持連線，後端版本請在互動模式連線後查看
+                crate::version::print_version(None);
+                Ok(())
+            },
+            #[cfg(feature = "self-update")]
+            Commands::Update { check_only } => {
+                self.cmd_update(app_config.update, check_only).await
+            },
+        };
+        if self.strict && result.is_ok() {
+            self.check_strict().await?;
         }
+        result
+    }
+    
+    /// 嚴格模式：檢查這次執行期間的協議錯誤、同步差異與未確認的操作
+    async fn check_strict(&mut self) -> Result<()> {
+        let Some(client) = &mut self.game_client else {
+            return Ok(());
+        };
+        // 先處理已收到的訊息，讓最後送出的操作有機會被確認
+        client.tick().await?;
+        let protocol_errors = self.protocol_errors.as_mut()
+            .map(|feed| {
+                feed.poll();
+                feed.count()
+            })
+            .unwrap_or(0);
+        let config = crate::config::AppConfig::load().strict;
+        let report = StrictReport::evaluate(client.get_game_state(), protocol_errors, client.expectations().tallies(), &config);
+        report.print();
+        if !report.passed() {
+            return Err(anyhow::anyhow!("嚴格模式：{} 項檢查未通過", report.violations.len()));
+        }
+        Ok(())
     }
     
     /// 互動式命令
@@ -257,19 +604,32 @@ impl CliHandler {
                 let view_result = crate::terminal_view::TerminalView::new(size, show_vision);
                 match view_result {
                     Ok(mut view) => {
+                        view.viewport.transform = client.coordinates();
+                        view.watch_protocol_errors(client.subscribe_protocol_errors());
+                        let app_config = crate::config::AppConfig::load();
+                    view.input_handler.configure(&app_config.input);
+                    view.install_overlays(crate::terminal_view::overlays::OverlayManager::new(&app_config.overlays));
                         info!("啟動實時終端視圖 (按 'q' 或 Esc 退出)");
                         if let Err(e) = view.init_terminal() {
                             error!("初始化終端失敗: {}", e);
                         } else {
                             loop {
                                 // 同步共享遊戲狀態
-                                if let Err(e) = client.sync_shared_state().await {
+                                if let Err(e) = client.tick().await {
                                     error!("同步遊戲狀態失敗: {}", e);
                                 }
                                 
                                 // 更新技能冷卻時間
                                 client.get_game_state_mut().update_cooldowns(0.016); // 600ms = 0.6s
                                 tokio::time::sleep(std::time::Duration::from_millis(16)).await;
+                                view.update_stats_overlay(client);
+                                view.update_network_overlay(client.mqtt_stats());
+                                if let Some(evidence) = client.take_anomaly() {
+                                    view.capture_anomaly(&evidence, client.get_game_state());
+                                }
+                                view.update_link_status(client.get_state());
+                                view.update_latency_header(client.heartbeat_stats());
+                                view.update_version_warning(client.protocol_mismatch());
                                 match view.render_live(client.get_game_state()) {
                                     Ok(crate::terminal_view::UserInput::Continue) => {
                                         
@@ -278,8 +638,8 @@ impl CliHandler {
                                     Ok(crate::terminal_view::UserInput::Move(world_pos)) => {
                                         info!("移動到: ({:.1}, {:.1})", world_pos.x, world_pos.y);
                                         if let Err(e) = client.perform_action("move", serde_json::json!({
-                                            "x": world_pos.x,
-                                            "y": world_pos.y
+                                            "target_x": world_pos.x,
+                                            "target_y": world_pos.y
                                         })).await {
                                             error!("移動指令失敗: {}", e);
                                         }
@@ -297,8 +657,8 @@ impl CliHandler {
                                         info!("移動攻擊到: ({:.1}, {:.1})", world_pos.x, world_pos.y);
                                         // 先移動再攻擊
                                         if let Err(e) = client.perform_action("move", serde_json::json!({
-                                            "x": world_pos.x,
-                                            "y": world_pos.y
+                                            "target_x": world_pos.x,
+                                            "target_y": world_pos.y
                                         })).await {
                                             error!("移動攻擊移動部分失敗: {}", e);
                                         } else {
@@ -319,19 +679,33 @@ impl CliHandler {
                                             error!("強制攻擊指令失敗: {}", e);
                                         }
                                     }
-                                    Ok(crate::terminal_view::UserInput::CastAbility(ability_id, world_pos)) => {
-                                        info!("施放技能 {} 於位置: ({:.1}, {:.1})", ability_id, world_pos.x, world_pos.y);
-                                        if let Err(e) = client.perform_action("cast_ability", serde_json::json!({
-                                            "ability_id": ability_id,
-                                            "target_position": [world_pos.x, world_pos.y],
-                                            "level": 1
-                                        })).await {
+                                    Ok(crate::terminal_view::UserInput::CastAbility(ability_id, target)) => {
+                                        info!("施放技能 {} 於{}", ability_id, target.describe());
+                                        if let Err(e) = client.perform_action("cast_ability", target.params(&ability_id)).await {
                                             error!("技能施放指令失敗: {}", e);
                                         }
                                     }
+                                    Ok(crate::terminal_view::UserInput::Select(target)) => {
+                                        client.select_target(target).await;
+                                    }
                                     Ok(crate::terminal_view::UserInput::Cancel) => {
                                         // 技能選擇被取消，繼續遊戲循環
                                     }
+                                    Ok(crate::terminal_view::UserInput::Bookmark) => {
+                                        if let Err(e) = client.add_bookmark(None) {
+                                            error!("添加書籤失敗: {}", e);
+                                        }
+                                    }
+                                    Ok(crate::terminal_view::UserInput::Chat(message)) => {
+                                        if let Err(e) = client.send_chat(&message).await {
+                                            error!("發送聊天訊息失敗: {}", e);
+                                        }
+                                    }
+                                    Ok(crate::terminal_view::UserInput::Macro(key, commands)) => {
+                                        if let Err(e) = client.run_macro(&key, &commands).await {
+                                            error!("{}", e);
+                                        }
+                                    }
                                     Ok(crate::terminal_view::UserInput::UseItem(item_id, _target_pos)) => {
                                         info!("使用道具: {}", item_id);
                                         if let Err(e) = client.perform_action("use_item", serde_json::json!({
@@ -368,6 +742,10 @@ impl CliHandler {
         info!("正在連接到服務器 {}:{}...", config.server_ip, config.server_port);
         
         let mut client = GameClient::new(config);
+        if self.strict {
+            self.protocol_errors = Some(ProtocolErrorFeed::new(client.subscribe_protocol_errors()));
+        }
+        configure_client(&mut client, &crate::config::AppConfig::load());
         info!("🔄 GameClient 已創建，開始連接...");
         
         client.connect().await?;
@@ -379,6 +757,74 @@ impl CliHandler {
         Ok(())
     }
     
+    /// 腳本命令
+    async fn cmd_run(&mut self, mut config: GameClientConfig, path: &std::path::Path) -> Result<()> {
+        let script = crate::script::Script::load(path)?;
+        if let Some(hero) = &script.hero {
+            config.hero_type = hero.clone();
+        }
+        info!("執行腳本 {:?}（{} 個步驟）", path, script.steps.len());
+        
+        let mut client = GameClient::new(config);
+        configure_client(&mut client, &crate::config::AppConfig::load());
+        let report = script.run(&mut client).await;
+        let _ = client.disconnect().await;
+        report.print();
+        if !report.passed() {
+            return Err(anyhow::anyhow!("腳本 {} 有步驟未通過", report.name));
+        }
+        Ok(())
+    }
+    
+    /// 壓力測試命令
+    async fn cmd_swarm(
+        &mut self,
+        config: GameClientConfig,
+        count: usize,
+        prefix: &str,
+        identities: Option<&std::path::Path>,
+        options: crate::swarm::SwarmOptions,
+    ) -> Result<()> {
+        if count == 0 {
+            return Err(anyhow::anyhow!("客戶端數量至少為 1"));
+        }
+        let pool = match identities {
+            Some(path) => crate::identities::IdentityPool::load(path)?,
+            None => crate::identities::IdentityPool::default(),
+        };
+        info!("🐝 壓力測試：{} 個客戶端，每 {} 毫秒連線一個，持續 {} 秒（按 Ctrl-C 提前結束）",
+            count, options.ramp.as_millis(), options.duration.as_secs());
+        let app_config = crate::config::AppConfig::load();
+        let clients = pool.assign(count, prefix, &config.client_id, &config.hero_type).into_iter()
+            .enumerate()
+            .map(|(index, identity)| {
+                let mut client = GameClient::new(GameClientConfig {
+                    client_id: identity.client_id,
+                    player_name: identity.name,
+                    hero_type: identity.hero,
+                    team: identity.team,
+                    seed: config.seed.map(|seed| seed.wrapping_add(index as u64)),
+                    ..config.clone()
+                });
+                configure_client(&mut client, &app_config);
+                client
+            })
+            .collect();
+        let members = crate::swarm::run(clients, options).await;
+        crate::swarm::print_report(&members);
+        Ok(())
+    }
+    
+    /// 狀態雜湊比對命令
+    async fn cmd_hash_watch(&mut self, config: GameClientConfig, tolerance: u32) -> Result<()> {
+        let pattern = config.topics.any_player("state_hash");
+        let wire_format = config.wire_format;
+        let options = GameClient::new(config).observer_options("hash_watch")?;
+        let summary = crate::state_hash::watch(options, &pattern, wire_format, tolerance).await?;
+        info!("🧮 比對結束：收到 {} 個雜湊，標示 {} 次分歧", summary.reports, summary.divergences);
+        Ok(())
+    }
+    
     /// 開始遊戲命令
     async fn cmd_play(&mut self, config: GameClientConfig) -> Result<()> {
         info!("開始遊戲 - 玩家: {}, 英雄: {}", config.player_name, config.hero_type);
@@ -403,9 +849,10 @@ impl CliHandler {
     /// 移動命令
     async fn cmd_move(&mut self, x: f32, y: f32) -> Result<()> {
         if let Some(client) = &mut self.game_client {
+            let target = client.coordinates().to_backend(vek::Vec2::new(x, y));
             let params = serde_json::json!({
-                "target_x": x,
-                "target_y": y
+                "target_x": target.x,
+                "target_y": target.y
             });
             
             client.perform_action("move", params).await?;
@@ -426,7 +873,8 @@ impl CliHandler {
             });
             
             if let (Some(x), Some(y)) = (x, y) {
-                params["target_position"] = serde_json::json!([x, y]);
+                let target = client.coordinates().to_backend(vek::Vec2::new(x, y));
+                params["target_position"] = serde_json::json!([target.x, target.y]);
             }
             
             client.perform_action("cast_ability", params).await?;
@@ -441,8 +889,9 @@ impl CliHandler {
     /// 攻擊命令
     async fn cmd_attack(&mut self, x: f32, y: f32, attack_type: String) -> Result<()> {
         if let Some(client) = &mut self.game_client {
+            let target = client.coordinates().to_backend(vek::Vec2::new(x, y));
             let params = serde_json::json!({
-                "target_position": [x, y],
+                "target_position": [target.x, target.y],
                 "attack_type": attack_type
             });
             
@@ -463,6 +912,15 @@ impl CliHandler {
             
             println!("=== 遊戲狀態 ===");
             println!("客戶端狀態: {:?}", state);
+            if let Some(stats) = client.heartbeat_stats() {
+                println!("心跳: {}", stats.summary());
+            }
+            if let Some(summary) = client.server_clock().summary() {
+                println!("伺服器時鐘: {}", summary);
+            }
+            if let Some(frame_hash) = client.state_hash() {
+                println!("狀態雜湊: 幀 {} {:016x}（{} 個物件）", frame_hash.frame, frame_hash.hash, frame_hash.objects);
+            }
             println!("{}", game_state.get_status_summary());
             
             // 顯示可用技能
@@ -480,10 +938,16 @@ impl CliHandler {
     }
     
     /// 自動遊戲命令
-    async fn cmd_auto(&mut self, duration: u64) -> Result<()> {
+    async fn cmd_auto(&mut self, duration: u64, export: Option<&std::path::Path>) -> Result<()> {
         if let Some(client) = &mut self.game_client {
             info!("開始自動遊戲模式，持續 {} 秒", duration);
-            client.auto_play(duration).await?;
+            let result = client.auto_play(duration).await;
+            // 失敗時也匯出，才能以記錄的種子重現
+            if let Some(path) = export {
+                client.export_action_history(path)?;
+                info!("📝 已匯出操作歷史（種子 {}）到 {:?}", client.auto_play_seed(), path);
+            }
+            result?;
         } else {
             error!("未連接到遊戲服務器。請先使用 'connect' 命令。");
         }
@@ -491,31 +955,21 @@ impl CliHandler {
         Ok(())
     }
     
-    /// 演示命令
-    async fn cmd_demo(&mut self) -> Result<()> {
+    /// 回城命令
+    async fn cmd_recall(&mut self) -> Result<()> {
         if let Some(client) = &mut self.game_client {
-            info!("執行演示序列...");
-            
-            // 獲取演示序列
-            let demo_sequence = client.get_game_state().local_player.name.clone();
-            let player_simulator = crate::player::PlayerSimulator::new(
-                demo_sequence, 
-                client.get_game_state().local_player.hero_type.clone()
-            );
-            
-            let sequence = player_simulator.get_demo_sequence();
-            
-            for (action, params) in sequence {
-                info!("演示操作: {} - {}", action, params);
-                if let Err(e) = client.perform_action(&action, params).await {
-                    error!("演示操作失敗: {}", e);
-                }
-                
-                // 等待一秒
-                tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-            }
-            
-            info!("演示序列完成");
+            client.recall().await?;
+        } else {
+            error!("未連接到遊戲服務器。請先使用 'connect' 命令。");
+        }
+        
+        Ok(())
+    }
+    
+    /// 購物行程命令
+    async fn cmd_shop_trip(&mut self) -> Result<()> {
+        if let Some(client) = &mut self.game_client {
+            client.shop_trip().await?;
         } else {
             error!("未連接到遊戲服務器。請先使用 'connect' 命令。");
         }
@@ -523,21 +977,39 @@ impl CliHandler {
         Ok(())
     }
     
+    /// 演示命令：進入遊戲後執行英雄的演示序列，有步驟未通過時返回錯誤
+    async fn cmd_demo(&mut self, config: GameClientConfig) -> Result<()> {
+        let hero_type = config.hero_type.clone();
+        let sequence = crate::demo::DemoSequence::load(&hero_type)?;
+        info!("執行 {} 的演示序列（{} 個步驟）...", hero_type, sequence.steps.len());
+        
+        if self.game_client.is_none() {
+            self.cmd_play(config).await?;
+        }
+        let Some(client) = &mut self.game_client else {
+            return Err(anyhow::anyhow!("遊戲客戶端為空，無法執行演示"));
+        };
+        
+        let report = client.run_demo(&hero_type, &sequence).await?;
+        report.print();
+        if !report.passed() {
+            return Err(anyhow::anyhow!("{} 的演示序列有步驟未通過", hero_type));
+        }
+        info!("演示序列完成");
+        Ok(())
+    }
+    
     /// 技能列表命令
     async fn cmd_abilities(&mut self) -> Result<()> {
         println!("=== 可用英雄和技能 ===");
         
-        println!("\n雜賀孫一 (saika_magoichi):");
-        println!("  - sniper_mode: 狙擊模式");
-        println!("  - saika_reinforcements: 雜賀眾");
-        println!("  - rain_iron_cannon: 雨鐵炮");
-        println!("  - three_stage_technique: 三段擊");
-        
-        println!("\n伊達政宗 (date_masamune):");
-        println!("  - flame_blade: 火焰刀");
-        println!("  - fire_dash: 火焰衝刺");
-        println!("  - flame_assault: 火焰突擊");
-        println!("  - matchlock_gun: 火繩槍");
+        for hero in crate::hero_catalog::registry().heroes() {
+            println!("\n{} ({}):", hero.name, hero.id);
+            for ability in &hero.abilities {
+                println!("  - {}: {}（冷卻 {}s，魔力 {}，距離 {}，{}）",
+                    ability.id, ability.name, ability.cooldown, ability.mana_cost, ability.range, ability.cast_type.label());
+            }
+        }
         
         println!("\n使用方法:");
         println!("  omobaf cast <ability_id> --x <x> --y <y> --level <level>");
@@ -573,6 +1045,19 @@ impl CliHandler {
                 client_id: "omobaf_viewer".to_string(),
                 player_name: config.frontend.player_name,
                 hero_type: config.frontend.hero_type,
+                team: None,
+                seed: self.seed,
+                dry_run: self.dry_run,
+                read_only: self.read_only,
+                spectate: self.spectate.clone(),
+                wire_format: config.server.wire_format,
+                username: config.server.username,
+                password: config.server.password,
+                tls: config.server.tls,
+                qos: config.server.qos,
+                topics: config.server.topics,
+                failover: config.server.failover,
+                binary_decoders: config.server.binary_decoders,
             };
             
             // 自動連接和進入遊戲
@@ -601,6 +1086,11 @@ impl CliHandler {
             
             match view_result {
                 Ok(mut view) => {
+                    view.viewport.transform = client.coordinates();
+                    view.watch_protocol_errors(client.subscribe_protocol_errors());
+                    let app_config = crate::config::AppConfig::load();
+                    view.input_handler.configure(&app_config.input);
+                    view.install_overlays(crate::terminal_view::overlays::OverlayManager::new(&app_config.overlays));
                     if live {
                         info!("啟動實時終端視圖 (按 'q' 或 Esc 退出)");
                         if let Err(e) = view.init_terminal() {
@@ -608,7 +1098,7 @@ impl CliHandler {
                         } else {
                             loop {
                                 // 同步共享遊戲狀態
-                                if let Err(e) = client.sync_shared_state().await {
+                                if let Err(e) = client.tick().await {
                                     error!("同步遊戲狀態失敗: {}", e);
                                 }
                                 
@@ -616,6 +1106,14 @@ impl CliHandler {
                                 client.get_game_state_mut().update_cooldowns(0.016); // 600ms = 0.6s
                                 tokio::time::sleep(std::time::Duration::from_millis(16)).await;
 
+                                view.update_stats_overlay(client);
+                                view.update_network_overlay(client.mqtt_stats());
+                                if let Some(evidence) = client.take_anomaly() {
+                                    view.capture_anomaly(&evidence, client.get_game_state());
+                                }
+                                view.update_link_status(client.get_state());
+                                view.update_latency_header(client.heartbeat_stats());
+                                view.update_version_warning(client.protocol_mismatch());
                                 match view.render_live(client.get_game_state()) {
                                     Ok(UserInput::Continue) => {
                                     }
@@ -623,8 +1121,8 @@ impl CliHandler {
                                     Ok(UserInput::Move(world_pos)) => {
                                         info!("移動到: ({:.1}, {:.1})", world_pos.x, world_pos.y);
                                         if let Err(e) = client.perform_action("move", serde_json::json!({
-                                            "x": world_pos.x,
-                                            "y": world_pos.y
+                                            "target_x": world_pos.x,
+                                            "target_y": world_pos.y
                                         })).await {
                                             error!("移動指令失敗: {}", e);
                                         }
@@ -642,8 +1140,8 @@ impl CliHandler {
                                         info!("移動攻擊到: ({:.1}, {:.1})", world_pos.x, world_pos.y);
                                         // 先移動再攻擊
                                         if let Err(e) = client.perform_action("move", serde_json::json!({
-                                            "x": world_pos.x,
-                                            "y": world_pos.y
+                                            "target_x": world_pos.x,
+                                            "target_y": world_pos.y
                                         })).await {
                                             error!("移動攻擊移動部分失敗: {}", e);
                                         } else {
@@ -666,19 +1164,33 @@ impl CliHandler {
                                             error!("強制攻擊指令失敗: {}", e);
                                         }
                                     }
-                                    Ok(UserInput::CastAbility(ability_id, world_pos)) => {
-                                        info!("施放技能 {} 於位置: ({:.1}, {:.1})", ability_id, world_pos.x, world_pos.y);
-                                        if let Err(e) = client.perform_action("cast_ability", serde_json::json!({
-                                            "ability_id": ability_id,
-                                            "target_position": [world_pos.x, world_pos.y],
-                                            "level": 1
-                                        })).await {
+                                    Ok(UserInput::CastAbility(ability_id, target)) => {
+                                        info!("施放技能 {} 於{}", ability_id, target.describe());
+                                        if let Err(e) = client.perform_action("cast_ability", target.params(&ability_id)).await {
                                             error!("技能施放指令失敗: {}", e);
                                         }
                                     }
+                                    Ok(UserInput::Select(target)) => {
+                                        client.select_target(target).await;
+                                    }
                                     Ok(UserInput::Cancel) => {
                                         // 技能選擇被取消，繼續遊戲循環
                                     }
+                                    Ok(UserInput::Bookmark) => {
+                                        if let Err(e) = client.add_bookmark(None) {
+                                            error!("添加書籤失敗: {}", e);
+                                        }
+                                    }
+                                    Ok(UserInput::Chat(message)) => {
+                                        if let Err(e) = client.send_chat(&message).await {
+                                            error!("發送聊天訊息失敗: {}", e);
+                                        }
+                                    }
+                                    Ok(UserInput::Macro(key, commands)) => {
+                                        if let Err(e) = client.run_macro(&key, &commands).await {
+                                            error!("{}", e);
+                                        }
+                                    }
                                     Ok(UserInput::UseItem(item_id, _target_pos)) => {
                                         info!("使用道具: {}", item_id);
                                         if let Err(e) = client.perform_action("use_item", serde_json::json!({
@@ -717,6 +1229,56 @@ impl CliHandler {
         Ok(())
     }
     
+    /// 回放比較命令
+    #[cfg(feature = "replay")]
+    async fn cmd_compare(&mut self, left: &std::path::Path, right: &std::path::Path, player_name: &str, hero: &str, speed: f32, threshold: f32) -> Result<()> {
+        info!("啟動回放比較 (速度: {}x, 分歧門檻: {})", speed, threshold);
+        let app_config = crate::config::AppConfig::load();
+        let mut comparison = crate::replay_compare::ReplayComparison::new(left, right, player_name, hero, threshold, speed, &app_config)?;
+        comparison.run().await
+    }
+    
+    #[cfg(feature = "replay")]
+    async fn cmd_replay(&mut self, file: &std::path::Path, player_name: &str, hero: &str, speed: f32, show_vision: bool) -> Result<()> {
+        info!("啟動錄製回放 (速度: {}x)", speed);
+        let mut replay = crate::replay::Replay::new(file, player_name, hero, speed)?;
+        replay.run(show_vision).await
+    }
+    
+    #[cfg(feature = "replay")]
+    async fn cmd_serve(&mut self, file: &std::path::Path, listen: &str, speed: f32, clients: usize, repeat: bool) -> Result<()> {
+        let recording = crate::recording::Recording::load(file)?;
+        let broker = crate::replay_broker::ReplayBroker::start(listen).await?;
+        info!("🎞️ 回放 broker 監聽 {}，錄製 {} ({} 筆)", broker.local_addr(), recording.name, recording.messages.len());
+        info!("其他 omobaf 以錄製時的玩家名稱連到此位址（--server-port {}），按 Ctrl-C 結束", broker.local_addr().port());
+        let stats = tokio::select! {
+            stats = broker.play(&recording, speed, clients, repeat) => stats?,
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        };
+        info!("回放結束：發佈 {} 則訊息，送達 {} 次；broker 持續運行，按 Ctrl-C 結束", stats.published, stats.delivered);
+        tokio::signal::ctrl_c().await?;
+        Ok(())
+    }
+    
+    /// 更新命令
+    #[cfg(feature = "self-update")]
+    async fn cmd_update(&mut self, config: crate::config::UpdateConfig, check_only: bool) -> Result<()> {
+        info!("🔎 檢查新版本: {}", config.release_url);
+        tokio::task::spawn_blocking(move || crate::self_update::run(&config, check_only)).await??;
+        Ok(())
+    }
+    
+    async fn cmd_selftest_mqtt(&mut self, config: GameClientConfig, timeout_ms: u64) -> Result<()> {
+        info!("🔁 MQTT 回送自我測試: {}:{}", config.server_ip, config.server_port);
+        let options = GameClient::new(config).observer_options("selftest")?;
+        let report = crate::selftest::mqtt_loopback(options, std::time::Duration::from_millis(timeout_ms)).await?;
+        report.print();
+        if !report.passed() {
+            return Err(anyhow::anyhow!("MQTT 回送自我測試未通過"));
+        }
+        Ok(())
+    }
+    
     /// 斷開連接命令
     async fn cmd_disconnect(&mut self) -> Result<()> {
         if let Some(client) = &mut self.game_client {
@@ -737,4 +1299,35 @@ impl CliHandler {
         self.backend_manager = None;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// 依配置設定客戶端的各項功能
+fn configure_client(client: &mut GameClient, app_config: &crate::config::AppConfig) {
+    client.configure_metrics(&app_config.metrics);
+    client.configure_sanity(&app_config.sanity);
+    client.configure_alerts(&app_config.alerts);
+    client.configure_coordinates(&app_config.coordinates);
+    client.configure_map(&app_config.map);
+    client.configure_targeting(&app_config.targeting);
+    client.configure_macros(&app_config.macros);
+    client.configure_death_recap(&app_config.death_recap);
+    client.configure_credit(&app_config.credit);
+    client.configure_heartbeat(&app_config.heartbeat);
+    client.configure_time_sync(&app_config.time_sync);
+    client.configure_state_hash(&app_config.state_hash);
+    client.configure_liveness(&app_config.liveness);
+    client.configure_expectations(&app_config.expectations);
+    client.configure_offline_queue(&app_config.offline_queue);
+    client.configure_rate_limit(&app_config.rate_limit);
+    client.configure_rpc(&app_config.rpc);
+    client.configure_anomaly_capture(&app_config.anomaly_capture);
+    client.configure_dummy(&app_config.dummy);
+    client.configure_sandbox(&app_config.sandbox);
+    client.configure_recording(&app_config.recording);
+    client.configure_humanize(&app_config.humanize);
+    client.configure_bot(&app_config.bot);
+    client.configure_shop(&app_config.shop);
+    client.configure_screen_updates(&app_config.screen_updates);
+    client.configure_prediction(&app_config.prediction);
+    client.configure_camera(&app_config.camera);
+}
@@ -0,0 +1,164 @@
+/// 可跨任務共享的遊戲客戶端控制代碼
+///
+/// GameClient 的操作都需要 `&mut self`，只能由單一擁有者依序呼叫。把客戶端移入專屬任務後，
+/// HTTP API、腳本或視圖循環都可以透過可複製的 GameClientHandle 以命令通道同時送出操作，
+/// 命令依到達順序執行，任務在命令之間定期 tick 保持狀態同步與監控運作
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+use log::warn;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+use crate::anomaly_capture::AnomalyEvidence;
+use crate::game_client::{ClientState, GameClient};
+use crate::game_state::GameState;
+use crate::heartbeat::HeartbeatStats;
+use crate::mqtt_stats::MqttStatsSnapshot;
+
+/// 命令通道容量
+const COMMAND_BUFFER: usize = 32;
+
+/// 沒有命令時的 tick 間隔
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 在客戶端任務中對 GameClient 執行的任意操作
+type ClientTask = Box<dyn for<'a> FnOnce(&'a mut GameClient) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> + Send>;
+
+/// 送進客戶端任務的命令
+enum ClientCommand {
+    Run(ClientTask),
+    Shutdown,
+}
+
+/// 客戶端狀態快照（視圖渲染所需的全部資料）
+#[derive(Debug, Clone)]
+pub struct ClientSnapshot {
+    pub state: ClientState,
+    pub game_state: GameState,
+    pub heartbeat: Option<HeartbeatStats>,
+    pub protocol_mismatch: Option<String>,
+    /// 統計浮層文字行
+    pub metrics_overlay: Vec<String>,
+    /// MQTT 收發統計
+    pub mqtt_stats: MqttStatsSnapshot,
+    /// 這一幀要擷取的異常（由視圖補上畫面後寫出）
+    pub anomaly: Option<AnomalyEvidence>,
+}
+
+impl ClientSnapshot {
+    fn of(client: &mut GameClient) -> Self {
+        Self {
+            state: client.get_state().clone(),
+            game_state: client.get_game_state().clone(),
+            heartbeat: client.heartbeat_stats(),
+            protocol_mismatch: client.protocol_mismatch(),
+            metrics_overlay: client.metrics_overlay(20),
+            mqtt_stats: client.mqtt_stats().snapshot(),
+            anomaly: client.take_anomaly(),
+        }
+    }
+}
+
+/// 遊戲客戶端控制代碼
+#[derive(Debug, Clone)]
+pub struct GameClientHandle {
+    commands: mpsc::Sender<ClientCommand>,
+}
+
+impl GameClientHandle {
+    /// 把客戶端移入專屬任務，返回控制代碼與任務（任務結束時交還客戶端）
+    pub fn spawn(client: GameClient) -> (Self, JoinHandle<GameClient>) {
+        let (commands, receiver) = mpsc::channel(COMMAND_BUFFER);
+        let task = crate::session_tag::spawn(client.session_tag(), run(client, receiver));
+        (Self { commands }, task)
+    }
+
+    /// 在客戶端任務中執行任意操作（例如 `handle.with(|client| Box::pin(client.recall()))`）
+    pub async fn with<T: Send + 'static>(
+        &self,
+        task: impl for<'a> FnOnce(&'a mut GameClient) -> Pin<Box<dyn Future<Output = T> + Send + 'a>> + Send + 'static,
+    ) -> Result<T> {
+        let (reply, response) = oneshot::channel();
+        let command = ClientCommand::Run(Box::new(move |client| Box::pin(async move {
+            // 呼叫端已放棄等待時忽略回覆失敗
+            let _ = reply.send(task(client).await);
+        })));
+        self.commands.send(command).await
+            .map_err(|_| anyhow!("客戶端任務已結束"))?;
+        response.await.map_err(|_| anyhow!("客戶端任務已結束"))
+    }
+
+    /// 執行玩家操作
+    pub async fn perform_action(&self, action: &str, params: serde_json::Value) -> Result<()> {
+        let action = action.to_string();
+        self.with(move |client| Box::pin(async move { client.perform_action(&action, params).await })).await?
+    }
+
+    /// 同步狀態並推進 `elapsed` 秒的技能冷卻，返回快照（視圖每幀使用）
+    pub async fn frame(&self, elapsed: f32) -> Result<ClientSnapshot> {
+        self.with(move |client| Box::pin(async move {
+            if let Err(e) = client.tick().await {
+                warn!("同步遊戲狀態失敗: {}", e);
+            }
+            client.get_game_state_mut().update_cooldowns(elapsed);
+            ClientSnapshot::of(client)
+        })).await
+    }
+
+    /// 結束客戶端任務（任務交還客戶端，其他控制代碼之後的命令會失敗）
+    pub async fn shutdown(&self) {
+        let _ = self.commands.send(ClientCommand::Shutdown).await;
+    }
+}
+
+/// 客戶端任務：依序執行命令，閒置時定期 tick
+async fn run(mut client: GameClient, mut commands: mpsc::Receiver<ClientCommand>) -> GameClient {
+    let mut interval = tokio::time::interval(TICK_INTERVAL);
+    loop {
+        tokio::select! {
+            command = commands.recv() => match command {
+                None | Some(ClientCommand::Shutdown) => break,
+                Some(ClientCommand::Run(task)) => task(&mut client).await,
+            },
+            _ = interval.tick() => {
+                if let Err(e) = client.tick().await {
+                    warn!("客戶端任務 tick 失敗: {}", e);
+                }
+            }
+        }
+    }
+    client
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_client::GameClientConfig;
+
+    #[tokio::test]
+    async fn clones_issue_commands_concurrently_and_shutdown_returns_client() {
+        let config = GameClientConfig { player_name: "p1".to_string(), ..GameClientConfig::default() };
+        let (handle, task) = GameClientHandle::spawn(GameClient::new(config));
+
+        let other = handle.clone();
+        let (name, snapshot, hero) = tokio::join!(
+            handle.with(|client| Box::pin(async move { client.query("player.name") })),
+            other.frame(0.1),
+            other.with(|client| Box::pin(async move { client.get_game_state().local_player.hero_type.clone() })),
+        );
+        assert_eq!(name.unwrap().unwrap(), serde_json::json!("p1"));
+        assert_eq!(snapshot.unwrap().state, ClientState::Disconnected);
+        assert_eq!(hero.unwrap(), GameClientConfig::default().hero_type);
+
+        // 未進入遊戲時操作失敗，錯誤原樣返回給呼叫端
+        let error = handle.perform_action("move", serde_json::json!({ "target_x": 1.0, "target_y": 2.0 })).await;
+        assert!(error.unwrap_err().to_string().contains("未在遊戲中"));
+
+        handle.shutdown().await;
+        let client = task.await.unwrap();
+        assert_eq!(client.get_state(), &ClientState::Disconnected);
+        assert!(other.frame(0.1).await.is_err());
+    }
+}
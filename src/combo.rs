@@ -0,0 +1,214 @@
+/// 技能連段延遲量測
+///
+/// `combo <技能>[@延遲毫秒] ...` 依指定間隔精確施放一串技能，每一步記錄實際送出的時間、
+/// 後端確認（伺服器狀態中的技能進入冷卻）與效果（本地英雄以該技能造成傷害）相對送出的延遲，
+/// 用來驗證後端的施法佇列：冷卻中或前一個技能尚未結束時送出的技能是否被排隊、丟棄或延後
+use std::time::{Duration, SystemTime};
+use anyhow::{anyhow, Result};
+use colored::*;
+
+use crate::game_state::GameState;
+
+/// 冷卻時間的變化門檻（秒），低於此值視為同一次冷卻
+const COOLDOWN_EPSILON: f32 = 0.05;
+
+/// 連段中的一步
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComboStep {
+    pub ability_id: String,
+    /// 與上一步送出的間隔（毫秒，第一步為開始後的延遲）
+    pub delay_ms: u64,
+}
+
+impl ComboStep {
+    /// 解析 `技能` 或 `技能@延遲毫秒`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (ability_id, delay_ms) = match spec.split_once('@') {
+            Some((ability_id, delay)) => {
+                let delay_ms = delay.parse().map_err(|_| anyhow!("無效的延遲: {}", spec))?;
+                (ability_id, delay_ms)
+            }
+            None => (spec, 0),
+        };
+        if ability_id.is_empty() {
+            return Err(anyhow!("缺少技能 ID: {}", spec));
+        }
+        Ok(Self { ability_id: ability_id.to_string(), delay_ms })
+    }
+}
+
+/// 單一步驟的時間量測
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepTiming {
+    pub ability_id: String,
+    /// 預定送出時間（相對連段開始，毫秒）
+    pub scheduled_ms: u64,
+    /// 實際送出時間（相對連段開始，毫秒）
+    pub sent_ms: Option<f64>,
+    /// 送出後到後端確認的延遲（毫秒）
+    pub ack_ms: Option<f64>,
+    /// 送出後到效果出現的延遲（毫秒）
+    pub effect_ms: Option<f64>,
+    /// 送出失敗的原因
+    pub error: Option<String>,
+    sent_at: Option<SystemTime>,
+    /// 送出時伺服器狀態中的冷卻與是否可用
+    before: Option<(f32, bool)>,
+}
+
+/// 連段量測進度
+#[derive(Debug, Clone)]
+pub struct ComboTracker {
+    start: SystemTime,
+    steps: Vec<StepTiming>,
+}
+
+impl ComboTracker {
+    /// 從 `start` 開始依步驟間隔排程
+    pub fn new(steps: &[ComboStep], start: SystemTime) -> Self {
+        let mut scheduled_ms = 0;
+        let steps = steps.iter()
+            .map(|step| {
+                scheduled_ms += step.delay_ms;
+                StepTiming {
+                    ability_id: step.ability_id.clone(),
+                    scheduled_ms,
+                    sent_ms: None,
+                    ack_ms: None,
+                    effect_ms: None,
+                    error: None,
+                    sent_at: None,
+                    before: None,
+                }
+            })
+            .collect();
+        Self { start, steps }
+    }
+
+    /// 第 `index` 步的預定送出時間
+    pub fn due(&self, index: usize) -> SystemTime {
+        self.start + Duration::from_millis(self.steps[index].scheduled_ms)
+    }
+
+    /// 記錄第 `index` 步送出的時間與當下的伺服器技能狀態
+    pub fn mark_sent(&mut self, index: usize, state: &GameState, now: SystemTime) {
+        let start = self.start;
+        let step = &mut self.steps[index];
+        step.sent_ms = Some(millis(now.duration_since(start).unwrap_or_default()));
+        step.sent_at = Some(now);
+        step.before = ability_state(state, &step.ability_id);
+    }
+
+    /// 記錄第 `index` 步送出失敗
+    pub fn mark_failed(&mut self, index: usize, error: String) {
+        let step = &mut self.steps[index];
+        step.error = Some(error);
+        step.sent_at = None;
+    }
+
+    /// 以伺服器狀態確認已送出步驟的確認與效果時間
+    pub fn observe(&mut self, state: &GameState, now: SystemTime) {
+        for step in &mut self.steps {
+            let Some(sent_at) = step.sent_at else {
+                continue;
+            };
+            if step.ack_ms.is_none() {
+                let acknowledged = match (step.before, ability_state(state, &step.ability_id)) {
+                    (Some((cooldown, available)), Some((current, now_available))) =>
+                        current > cooldown + COOLDOWN_EPSILON || (available && !now_available),
+                    (None, Some((current, now_available))) => current > COOLDOWN_EPSILON || !now_available,
+                    _ => false,
+                };
+                if acknowledged {
+                    step.ack_ms = Some(millis(now.duration_since(sent_at).unwrap_or_default()));
+                }
+            }
+            if step.effect_ms.is_none() {
+                if let Some(hit) = state.ability_hits.get(&step.ability_id).filter(|hit| **hit >= sent_at) {
+                    step.effect_ms = Some(millis(hit.duration_since(sent_at).unwrap_or_default()));
+                }
+            }
+        }
+    }
+
+    /// 所有已送出的步驟是否都已確認且出現效果
+    pub fn settled(&self) -> bool {
+        self.steps.iter()
+            .filter(|step| step.sent_at.is_some())
+            .all(|step| step.ack_ms.is_some() && step.effect_ms.is_some())
+    }
+
+    /// 量測結果
+    pub fn report(self) -> ComboReport {
+        ComboReport { steps: self.steps }
+    }
+}
+
+/// 連段延遲報告
+#[derive(Debug, Clone)]
+pub struct ComboReport {
+    pub steps: Vec<StepTiming>,
+}
+
+impl ComboReport {
+    /// 顯示每一步的延遲
+    pub fn print(&self) {
+        println!("\n{}", "連段延遲:".bright_cyan().bold());
+        println!("  {:<3} {:<20} {:>8} {:>8} {:>10} {:>10}", "#", "技能", "預定", "送出", "確認", "效果");
+        let format_ms = |value: Option<f64>| value.map(|ms| format!("{:.1}ms", ms)).unwrap_or_else(|| "-".to_string());
+        for (i, step) in self.steps.iter().enumerate() {
+            let line = format!("  {:<3} {:<20} {:>6}ms {:>8} {:>10} {:>10}",
+                i + 1, step.ability_id, step.scheduled_ms, format_ms(step.sent_ms), format_ms(step.ack_ms), format_ms(step.effect_ms));
+            match &step.error {
+                Some(error) => println!("{} {}", line.red(), error.bright_black()),
+                None if step.ack_ms.is_none() => println!("{} {}", line.yellow(), "後端未確認（可能被丟棄或仍在佇列中）".bright_black()),
+                None => println!("{}", line),
+            }
+        }
+    }
+}
+
+/// 伺服器狀態中技能的剩餘冷卻與是否可用
+fn ability_state(state: &GameState, ability_id: &str) -> Option<(f32, bool)> {
+    state.local_player.abilities.iter()
+        .find(|ability| ability.ability_id == ability_id)
+        .map(|ability| (ability.cooldown_remaining, ability.is_available))
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_ack_and_effect_per_step() {
+        let steps: Vec<ComboStep> = ["sniper_mode", "rain_iron_cannon@300"].iter()
+            .map(|spec| ComboStep::parse(spec).unwrap())
+            .collect();
+        assert_eq!(steps[1].delay_ms, 300);
+        assert!(ComboStep::parse("sniper_mode@soon").is_err());
+
+        let start = SystemTime::now();
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        let mut tracker = ComboTracker::new(&steps, start);
+        assert_eq!(tracker.due(1), start + Duration::from_millis(300));
+
+        tracker.mark_sent(0, &state, start);
+        tracker.mark_failed(1, "未在遊戲中".to_string());
+        let sniper = state.local_player.abilities.iter_mut().find(|a| a.ability_id == "sniper_mode").unwrap();
+        sniper.cooldown_remaining = 3.0;
+        tracker.observe(&state, start + Duration::from_millis(40));
+        assert!(!tracker.settled());
+
+        state.ability_hits.insert("sniper_mode".to_string(), start + Duration::from_millis(90));
+        tracker.observe(&state, start + Duration::from_millis(100));
+        assert!(tracker.settled());
+        let report = tracker.report();
+        assert_eq!(report.steps[0].ack_ms, Some(40.0));
+        assert_eq!(report.steps[0].effect_ms, Some(90.0));
+        assert_eq!(report.steps[1].error.as_deref(), Some("未在遊戲中"));
+    }
+}
@@ -1,64 +1,1342 @@
 /// 配置檔案處理
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 use anyhow::{Result, Context};
 
+use crate::game_state::{MapStructure, WorldBounds};
+use crate::mqtt_handler::WireFormat;
+use crate::payload::BinaryDecoders;
+use crate::topics::TopicScheme;
+
 /// 應用程序配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct AppConfig {
     pub server: ServerConfig,
     pub backend: BackendConfig,
     pub frontend: FrontendConfig,
+    /// 自訂指標
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    /// 伺服器資料合理性監控
+    #[serde(default)]
+    pub sanity: SanityConfig,
+    /// 警報規則
+    #[serde(default)]
+    pub alerts: AlertsConfig,
+    /// 座標轉換
+    #[serde(default)]
+    pub coordinates: CoordinatesConfig,
+    /// 地圖設定
+    #[serde(default)]
+    pub map: MapConfig,
+    /// 瞄準設定
+    #[serde(default)]
+    pub targeting: TargetingConfig,
+    /// 內建巨集（回城、購物）
+    #[serde(default)]
+    pub macros: MacrosConfig,
+    /// 死亡回顧
+    #[serde(default)]
+    pub death_recap: DeathRecapConfig,
+    /// 擊殺/助攻歸屬驗證
+    #[serde(default)]
+    pub credit: CreditConfig,
+    /// 連線心跳
+    #[serde(default)]
+    pub heartbeat: HeartbeatConfig,
+    /// 伺服器時間同步
+    #[serde(default)]
+    pub time_sync: TimeSyncConfig,
+    /// 跨客戶端一致性檢查的狀態雜湊
+    #[serde(default)]
+    pub state_hash: StateHashConfig,
+    /// 自我更新
+    #[serde(default)]
+    pub update: UpdateConfig,
+    /// 伺服器資料停止更新的偵測
+    #[serde(default)]
+    pub liveness: LivenessConfig,
+    /// 模擬器預測與伺服器結果的比對
+    #[serde(default)]
+    pub expectations: ExpectationsConfig,
+    /// 斷線期間的玩家操作佇列
+    #[serde(default)]
+    pub offline_queue: OfflineQueueConfig,
+    /// 發佈限流
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    /// 後端請求的回應關聯
+    #[serde(default)]
+    pub rpc: RpcConfig,
+    /// 實時視圖的異常自動擷取
+    #[serde(default)]
+    pub anomaly_capture: AnomalyCaptureConfig,
+    /// 終端視圖按鍵與施法模式
+    #[serde(default)]
+    pub input: InputConfig,
+    /// 終端視圖鏡頭
+    #[serde(default)]
+    pub camera: CameraConfig,
+    /// 終端視圖的地圖疊加層
+    #[serde(default)]
+    pub overlays: OverlaysConfig,
+    /// 訓練假人與 DPS 統計
+    #[serde(default)]
+    pub dummy: DummyConfig,
+    /// 練習沙盒
+    #[serde(default)]
+    pub sandbox: SandboxConfig,
+    /// MQTT 流量錄製
+    #[serde(default)]
+    pub recording: RecordingConfig,
+    /// 自動遊戲輸入擬人化
+    #[serde(default)]
+    pub humanize: HumanizeConfig,
+    /// 自動遊戲的行為樹機器人
+    #[serde(default)]
+    pub bot: BotConfig,
+    /// 商店目錄
+    #[serde(default)]
+    pub shop: ShopConfig,
+    /// 畫面狀態更新
+    #[serde(default)]
+    pub screen_updates: ScreenUpdatesConfig,
+    /// 本地移動預測
+    #[serde(default)]
+    pub prediction: PredictionConfig,
+    /// 嚴格模式（--strict）的門檻
+    #[serde(default)]
+    pub strict: StrictConfig,
+}
+
+/// 服務器配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServerConfig {
+    pub mqtt_host: String,
+    pub mqtt_port: u16,
+    /// 訊息編碼格式（json 或 msgpack，需與後端一致）
+    #[serde(default)]
+    pub wire_format: WireFormat,
+    /// broker 認證使用者名稱
+    #[serde(default)]
+    pub username: Option<String>,
+    /// broker 認證密碼
+    #[serde(default)]
+    pub password: Option<String>,
+    /// MQTT over TLS
+    #[serde(default)]
+    pub tls: TlsConfig,
+    /// 各類主題的 QoS 等級
+    #[serde(default)]
+    pub qos: QosConfig,
+    /// 遊戲主題的命名空間
+    #[serde(default)]
+    pub topics: TopicScheme,
+    /// 備援 broker（主要 broker 無法連線時依序嘗試）
+    #[serde(default)]
+    pub failover: Vec<BrokerEndpoint>,
+    /// 各頻道二進位負載的解碼器（例如 `{ screen_response = "msgpack" }`）
+    #[serde(default)]
+    pub binary_decoders: BinaryDecoders,
+}
+
+/// broker 位址
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BrokerEndpoint {
+    pub host: String,
+    pub port: u16,
+}
+
+impl std::fmt::Display for BrokerEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.host, self.port)
+    }
+}
+
+/// 各類主題的 QoS 等級（0、1 或 2）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QosConfig {
+    /// 玩家操作（td/{player_name}/send，含 enter_game）
+    #[serde(default = "default_qos_at_least_once")]
+    pub actions: u8,
+    /// 畫面狀態請求（td/{player_name}/request 與定期畫面請求）
+    #[serde(default = "default_qos_at_least_once")]
+    pub screen_requests: u8,
+    /// 訂閱廣播主題（td/all/res、td/+/send）
+    #[serde(default = "default_qos_at_least_once")]
+    pub broadcasts: u8,
+    /// 訂閱玩家回應主題（screen_response、hello_ack）與發送握手
+    #[serde(default = "default_qos_at_least_once")]
+    pub responses: u8,
+    /// 心跳 ping 與 pong
+    #[serde(default)]
+    pub heartbeat: u8,
+}
+
+fn default_qos_at_least_once() -> u8 {
+    1
+}
+
+impl Default for QosConfig {
+    fn default() -> Self {
+        Self {
+            actions: default_qos_at_least_once(),
+            screen_requests: default_qos_at_least_once(),
+            broadcasts: default_qos_at_least_once(),
+            responses: default_qos_at_least_once(),
+            heartbeat: 0,
+        }
+    }
+}
+
+impl QosConfig {
+    /// 各欄位名稱與等級
+    pub fn levels(&self) -> [(&'static str, u8); 5] {
+        [
+            ("actions", self.actions),
+            ("screen_requests", self.screen_requests),
+            ("broadcasts", self.broadcasts),
+            ("responses", self.responses),
+            ("heartbeat", self.heartbeat),
+        ]
+    }
+
+    /// 把等級轉成 rumqttc 的 QoS（超出範圍時視為 2）
+    pub fn qos(level: u8) -> rumqttc::QoS {
+        match level {
+            0 => rumqttc::QoS::AtMostOnce,
+            1 => rumqttc::QoS::AtLeastOnce,
+            _ => rumqttc::QoS::ExactlyOnce,
+        }
+    }
+}
+
+/// MQTT TLS 配置（憑證皆為 PEM 檔案路徑）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// 是否以 TLS 連接 broker
+    #[serde(default)]
+    pub enabled: bool,
+    /// CA 憑證（未設置時使用系統根憑證）
+    #[serde(default)]
+    pub ca_cert: Option<String>,
+    /// 用戶端憑證（雙向 TLS）
+    #[serde(default)]
+    pub client_cert: Option<String>,
+    /// 用戶端私鑰（雙向 TLS）
+    #[serde(default)]
+    pub client_key: Option<String>,
+    /// 驗證伺服器憑證使用的名稱（只套用到主要 broker，連線位址與 SNI 仍是 broker 位址），未設置時驗證 broker 位址
+    #[serde(default)]
+    pub server_name: Option<String>,
+}
+
+/// 後端配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BackendConfig {
+    pub executable_path: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub working_directory: Option<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+}
+
+/// 前端配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FrontendConfig {
+    pub player_name: String,
+    pub hero_type: String,
+    pub auto_start_backend: bool,
+    pub backend_start_delay: u64,
+    pub backend_shutdown_timeout: u64,
+    /// 螢幕顯示範圍配置
+    pub screen_range: ScreenRangeConfig,
+}
+
+/// 螢幕顯示範圍配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScreenRangeConfig {
+    /// 螢幕顯示範圍寬度（遊戲世界單位）
+    pub width: f32,
+    /// 螢幕顯示範圍高度（遊戲世界單位）
+    pub height: f32,
+    /// 是否啟用動態範圍調整（根據縮放等調整）
+    pub dynamic_range: bool,
+    /// 最小顯示範圍（防止過度縮小）
+    pub min_width: f32,
+    /// 最小顯示範圍（防止過度縮小）
+    pub min_height: f32,
+    /// 最大顯示範圍（防止過度放大）
+    pub max_width: f32,
+    /// 最大顯示範圍（防止過度放大）
+    pub max_height: f32,
+}
+
+/// 單一指標定義
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetricDefinition {
+    /// 指標名稱
+    pub name: String,
+    /// 求值運算式
+    pub expr: String,
+}
+
+/// 指標配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsConfig {
+    /// 指標定義列表
+    #[serde(default)]
+    pub definitions: Vec<MetricDefinition>,
+    /// 取樣間隔（毫秒，0 表示每個 tick 都取樣）
+    #[serde(default = "default_sample_interval_ms")]
+    pub sample_interval_ms: u64,
+    /// 每個指標保留的歷史樣本數
+    #[serde(default = "default_history_len")]
+    pub history_len: usize,
+    /// 遙測匯出檔案（JSON Lines，可選）
+    #[serde(default)]
+    pub export_path: Option<String>,
+}
+
+fn default_sample_interval_ms() -> u64 {
+    0
+}
+
+fn default_history_len() -> usize {
+    60
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            definitions: Vec::new(),
+            sample_interval_ms: default_sample_interval_ms(),
+            history_len: default_history_len(),
+            export_path: None,
+        }
+    }
+}
+
+/// 伺服器資料合理性監控配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SanityConfig {
+    /// 是否啟用
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 單位最大移動速度（世界單位/秒）
+    #[serde(default = "default_max_speed")]
+    pub max_speed: f32,
+    /// 瞬移判定的額外容許距離（世界單位）
+    #[serde(default = "default_teleport_grace")]
+    pub teleport_grace: f32,
+    /// 冷卻時間增加的容許誤差（秒）
+    #[serde(default = "default_cooldown_tolerance")]
+    pub cooldown_tolerance: f32,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_speed() -> f32 {
+    1000.0
+}
+
+fn default_teleport_grace() -> f32 {
+    50.0
+}
+
+fn default_cooldown_tolerance() -> f32 {
+    0.1
+}
+
+impl Default for SanityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_true(),
+            max_speed: default_max_speed(),
+            teleport_grace: default_teleport_grace(),
+            cooldown_tolerance: default_cooldown_tolerance(),
+        }
+    }
+}
+
+/// 瞄準配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TargetingConfig {
+    /// 攻擊移動中的單位時，以估計速度 × 往返延遲預判目標位置
+    #[serde(default)]
+    pub lead_target: bool,
+    /// 點擊位置與單位的最大距離（世界單位），超過則不預判
+    #[serde(default = "default_pick_radius")]
+    pub pick_radius: f32,
+}
+
+fn default_pick_radius() -> f32 {
+    50.0
+}
+
+impl Default for TargetingConfig {
+    fn default() -> Self {
+        Self {
+            lead_target: false,
+            pick_radius: default_pick_radius(),
+        }
+    }
+}
+
+/// 內建巨集配置（座標皆為後端座標）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MacrosConfig {
+    /// 回城引導時間（秒），期間受到傷害會中斷
+    #[serde(default = "default_recall_channel_secs")]
+    pub recall_channel_secs: f32,
+    /// 覆寫英雄目錄中的出生點
+    #[serde(default)]
+    pub spawn_point: Option<(f32, f32)>,
+    /// 購物後返回的線上位置（未設置時返回回城前的位置）
+    #[serde(default)]
+    pub lane_point: Option<(f32, f32)>,
+    /// 購物行程要購買的物品 ID（依序經商店目錄購買，買不起或放不下的略過）
+    #[serde(default)]
+    pub shop_items: Vec<String>,
+    /// 自動遊戲時生命比例低於此值就執行購物行程（0 為停用）
+    #[serde(default)]
+    pub auto_recall_below: f32,
+}
+
+fn default_recall_channel_secs() -> f32 {
+    3.0
+}
+
+impl Default for MacrosConfig {
+    fn default() -> Self {
+        Self {
+            recall_channel_secs: default_recall_channel_secs(),
+            spawn_point: None,
+            lane_point: None,
+            shop_items: Vec::new(),
+            auto_recall_below: 0.0,
+        }
+    }
+}
+
+/// 死亡回顧配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeathRecapConfig {
+    /// 回顧顯示死亡前最後幾筆傷害事件
+    #[serde(default = "default_recap_size")]
+    pub size: usize,
+}
+
+fn default_recap_size() -> usize {
+    crate::death_recap::DEFAULT_RECAP_SIZE
+}
+
+impl Default for DeathRecapConfig {
+    fn default() -> Self {
+        Self {
+            size: default_recap_size(),
+        }
+    }
+}
+
+/// 擊殺/助攻歸屬驗證配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreditConfig {
+    /// 擊殺前計入助攻的時間窗口（秒）
+    #[serde(default = "default_assist_window_secs")]
+    pub assist_window_secs: f32,
+    /// 窗口內至少造成多少傷害才應得到助攻
+    #[serde(default = "default_min_assist_damage")]
+    pub min_assist_damage: f32,
+    /// 擊殺應歸屬最後一擊的來源
+    #[serde(default = "default_true")]
+    pub require_last_hit: bool,
+}
+
+fn default_assist_window_secs() -> f32 {
+    10.0
+}
+
+fn default_min_assist_damage() -> f32 {
+    1.0
+}
+
+impl Default for CreditConfig {
+    fn default() -> Self {
+        Self {
+            assist_window_secs: default_assist_window_secs(),
+            min_assist_damage: default_min_assist_damage(),
+            require_last_hit: true,
+        }
+    }
+}
+
+/// 連線心跳配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeartbeatConfig {
+    /// 是否定期發送 ping
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// ping 間隔（毫秒）
+    #[serde(default = "default_heartbeat_interval_ms")]
+    pub interval_ms: u64,
+}
+
+fn default_heartbeat_interval_ms() -> u64 {
+    1000
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_ms: default_heartbeat_interval_ms(),
+        }
+    }
+}
+
+/// 伺服器時間同步配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimeSyncConfig {
+    /// 是否定期發送 time_sync
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// time_sync 間隔（毫秒）
+    #[serde(default = "default_time_sync_interval_ms")]
+    pub interval_ms: u64,
+}
+
+fn default_time_sync_interval_ms() -> u64 {
+    5000
+}
+
+impl Default for TimeSyncConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_ms: default_time_sync_interval_ms(),
+        }
+    }
+}
+
+/// 狀態雜湊的觀察區域（後端座標）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StateHashArea {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+}
+
+/// 狀態雜湊配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StateHashConfig {
+    /// 是否在每個幀邊界計算狀態雜湊
+    #[serde(default)]
+    pub enabled: bool,
+    /// 是否發佈到 td/{player_name}/state_hash（供 hash-watch 比對）
+    #[serde(default)]
+    pub publish: bool,
+    /// 幀間隔（毫秒，以伺服器時間對齊）
+    #[serde(default = "default_state_hash_interval_ms")]
+    pub interval_ms: u64,
+    /// 位置與生命值的量化單位
+    #[serde(default = "default_state_hash_precision")]
+    pub precision: f32,
+    /// 只雜湊此範圍內的實體與玩家（未設置時為整個狀態）
+    #[serde(default)]
+    pub area: Option<StateHashArea>,
+}
+
+fn default_state_hash_interval_ms() -> u64 {
+    500
+}
+
+fn default_state_hash_precision() -> f32 {
+    1.0
+}
+
+impl Default for StateHashConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            publish: false,
+            interval_ms: default_state_hash_interval_ms(),
+            precision: default_state_hash_precision(),
+            area: None,
+        }
+    }
+}
+
+/// 自我更新配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateConfig {
+    /// 最新發佈的資訊（GitHub releases API 的 latest 端點，或相同格式的 JSON）
+    #[serde(default = "default_update_release_url")]
+    pub release_url: String,
+}
+
+fn default_update_release_url() -> String {
+    "https://api.github.com/repos/damody/open_moba_frontend/releases/latest".to_string()
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self {
+            release_url: default_update_release_url(),
+        }
+    }
+}
+
+/// 伺服器資料存活偵測配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LivenessConfig {
+    /// 超過此秒數沒有收到伺服器資料即視為資料停滯
+    #[serde(default = "default_liveness_stale_secs")]
+    pub stale_secs: u64,
+    /// 超過此秒數沒有收到伺服器資料即視為失去伺服器
+    #[serde(default = "default_liveness_lost_secs")]
+    pub lost_secs: u64,
+}
+
+fn default_liveness_stale_secs() -> u64 {
+    5
+}
+
+fn default_liveness_lost_secs() -> u64 {
+    30
+}
+
+impl Default for LivenessConfig {
+    fn default() -> Self {
+        Self {
+            stale_secs: default_liveness_stale_secs(),
+            lost_secs: default_liveness_lost_secs(),
+        }
+    }
+}
+
+/// 操作結果驗證配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExpectationsConfig {
+    /// 等待伺服器確認的時間（毫秒），逾時仍未確認即判定不符
+    #[serde(default = "default_expectation_timeout_ms")]
+    pub timeout_ms: u64,
+    /// 移動到達判定的位置誤差（遊戲世界單位）
+    #[serde(default = "default_position_tolerance")]
+    pub position_tolerance: f32,
+}
+
+fn default_expectation_timeout_ms() -> u64 {
+    3000
+}
+
+fn default_position_tolerance() -> f32 {
+    20.0
+}
+
+impl Default for ExpectationsConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: default_expectation_timeout_ms(),
+            position_tolerance: default_position_tolerance(),
+        }
+    }
+}
+
+/// 斷線期間的玩家操作佇列配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OfflineQueueConfig {
+    /// 最多暫存的操作數（0 表示不暫存，斷線期間的操作直接失敗）
+    #[serde(default = "default_offline_queue_size")]
+    pub max_size: usize,
+    /// 暫存操作的保存檔（JSON Lines，可選）：設置後程式重啟也不會遺失未送出的操作，進入遊戲後先補送；
+    /// 同一目錄執行多個實例時需各自設置不同的檔案
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+fn default_offline_queue_size() -> usize {
+    100
+}
+
+impl Default for OfflineQueueConfig {
+    fn default() -> Self {
+        Self {
+            max_size: default_offline_queue_size(),
+            path: None,
+        }
+    }
+}
+
+/// 發佈限流配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// 各訊息類型每秒最多發佈次數（玩家操作以操作名稱、畫面請求以 screen_request 表示；未列出的類型不限流）
+    #[serde(default = "default_rate_limit_per_second")]
+    pub per_second: BTreeMap<String, f32>,
+}
+
+fn default_rate_limit_per_second() -> BTreeMap<String, f32> {
+    BTreeMap::from([
+        ("update_viewport".to_string(), 5.0),
+        ("screen_request".to_string(), 2.0),
+    ])
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            per_second: default_rate_limit_per_second(),
+        }
+    }
+}
+
+/// 後端請求配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RpcConfig {
+    /// 請求送出後等待回應的毫秒數，逾時視為遺失
+    #[serde(default = "default_rpc_timeout_ms")]
+    pub timeout_ms: u64,
+    /// 玩家操作也帶上請求 ID，後端回應時記錄操作確認延遲（演示序列的 action_ack_p99_ms 門檻需要開啟）
+    #[serde(default)]
+    pub track_actions: bool,
+}
+
+fn default_rpc_timeout_ms() -> u64 {
+    5000
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: default_rpc_timeout_ms(),
+            track_actions: false,
+        }
+    }
+}
+
+/// 異常自動擷取配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnomalyCaptureConfig {
+    /// 實時視圖中出現不同步、協議錯誤或斷言失敗時是否自動擷取
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 異常資料夾的上層目錄
+    #[serde(default = "default_anomaly_dir")]
+    pub dir: String,
+    /// 保留最近幾秒的收發訊息
+    #[serde(default = "default_anomaly_history_secs")]
+    pub history_secs: u64,
+    /// 兩次擷取間至少間隔幾秒（期間發生的異常併入下一次）
+    #[serde(default = "default_anomaly_cooldown_secs")]
+    pub cooldown_secs: u64,
+}
+
+fn default_anomaly_dir() -> String {
+    "anomalies".to_string()
+}
+
+fn default_anomaly_history_secs() -> u64 {
+    30
+}
+
+fn default_anomaly_cooldown_secs() -> u64 {
+    10
+}
+
+impl Default for AnomalyCaptureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_true(),
+            dir: default_anomaly_dir(),
+            history_secs: default_anomaly_history_secs(),
+            cooldown_secs: default_anomaly_cooldown_secs(),
+        }
+    }
+}
+
+/// 施法模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CastMode {
+    /// 按技能鍵選擇後，以滑鼠左鍵點擊目標施放
+    #[default]
+    Normal,
+    /// 按技能鍵立即朝滑鼠所在位置施放
+    Quick,
+}
+
+impl CastMode {
+    /// 顯示名稱
+    pub fn label(&self) -> &'static str {
+        match self {
+            CastMode::Normal => "一般",
+            CastMode::Quick => "快速",
+        }
+    }
+}
+
+/// 終端視圖按鍵配置（不分大小寫）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Keymap {
+    /// 技能欄按鍵（依英雄目錄的技能順序）
+    #[serde(default = "default_ability_keys")]
+    pub abilities: Vec<char>,
+    /// 添加書籤
+    #[serde(default = "default_bookmark_key")]
+    pub bookmark: char,
+    /// 切換統計浮層
+    #[serde(default = "default_stats_key")]
+    pub stats: char,
+    /// 切換死亡回顧浮層
+    #[serde(default = "default_death_recap_key")]
+    pub death_recap: char,
+    /// 切換網路統計浮層
+    #[serde(default = "default_network_key")]
+    pub network: char,
+    /// 切換實體旁的後端除錯標籤
+    #[serde(default = "default_debug_labels_key")]
+    pub debug_labels: char,
+    /// 切換戰爭迷霧
+    #[serde(default = "default_fog_key")]
+    pub fog: char,
+}
+
+fn default_ability_keys() -> Vec<char> {
+    vec!['w', 'e', 'r', 't']
+}
+
+fn default_bookmark_key() -> char {
+    'b'
+}
+
+fn default_stats_key() -> char {
+    'm'
+}
+
+fn default_death_recap_key() -> char {
+    'd'
+}
+
+fn default_network_key() -> char {
+    'n'
+}
+
+fn default_debug_labels_key() -> char {
+    'l'
+}
+
+fn default_fog_key() -> char {
+    'v'
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            abilities: default_ability_keys(),
+            bookmark: default_bookmark_key(),
+            stats: default_stats_key(),
+            death_recap: default_death_recap_key(),
+            network: default_network_key(),
+            debug_labels: default_debug_labels_key(),
+            fog: default_fog_key(),
+        }
+    }
+}
+
+/// 終端視圖輸入配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InputConfig {
+    #[serde(default)]
+    pub keymap: Keymap,
+    #[serde(default)]
+    pub cast_mode: CastMode,
+    /// 啟用滑鼠（false 或終端不支援時使用鍵盤目標模式）
+    #[serde(default = "default_true")]
+    pub mouse: bool,
+    /// 巨集按鍵：F2–F10 對應依序執行的命令
+    #[serde(default)]
+    pub macros: BTreeMap<String, Vec<String>>,
 }
 
-/// 服務器配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ServerConfig {
-    pub mqtt_host: String,
-    pub mqtt_port: u16,
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            keymap: Keymap::default(),
+            cast_mode: CastMode::default(),
+            mouse: true,
+            macros: BTreeMap::new(),
+        }
+    }
 }
 
-/// 後端配置
+/// 終端視圖鏡頭配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraConfig {
+    /// 未指定大小時的視圖寬度（世界單位）
+    #[serde(default = "default_camera_size")]
+    pub view_width: f32,
+    /// 未指定大小時的視圖高度（世界單位）
+    #[serde(default = "default_camera_size")]
+    pub view_height: f32,
+    /// 畫面請求範圍的縮放等級
+    #[serde(default = "default_camera_zoom")]
+    pub zoom: f32,
+    /// 預設顯示視野範圍
+    #[serde(default)]
+    pub show_vision: bool,
+    /// 鏡頭移動後送出視野更新的最短間隔（毫秒）
+    #[serde(default = "default_viewport_debounce_ms")]
+    pub viewport_debounce_ms: u64,
+}
+
+fn default_camera_size() -> f32 {
+    40.0
+}
+
+fn default_camera_zoom() -> f32 {
+    1.0
+}
+
+fn default_viewport_debounce_ms() -> u64 {
+    250
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            view_width: default_camera_size(),
+            view_height: default_camera_size(),
+            zoom: default_camera_zoom(),
+            show_vision: false,
+            viewport_debounce_ms: default_viewport_debounce_ms(),
+        }
+    }
+}
+
+/// 地圖疊加層配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OverlaysConfig {
+    /// 預設開啟的疊加層（fog、grid、heatmap、trails、ranges、labels）
+    #[serde(default)]
+    pub enabled: Vec<String>,
+    /// 戰爭迷霧：英雄與己方召喚物的視野半徑（世界單位）
+    #[serde(default = "default_overlay_fog_radius")]
+    pub fog_radius: f32,
+    /// 網格層：網格線間距（世界單位）
+    #[serde(default = "default_overlay_grid_spacing")]
+    pub grid_spacing: f32,
+    /// 熱圖層：統計格大小（世界單位）
+    #[serde(default = "default_overlay_heatmap_cell")]
+    pub heatmap_cell: f32,
+    /// 軌跡層：每個單位保留的位置數
+    #[serde(default = "default_overlay_trail_length")]
+    pub trail_length: usize,
+}
+
+fn default_overlay_fog_radius() -> f32 {
+    500.0
+}
+
+fn default_overlay_grid_spacing() -> f32 {
+    100.0
+}
+
+fn default_overlay_heatmap_cell() -> f32 {
+    50.0
+}
+
+fn default_overlay_trail_length() -> usize {
+    12
+}
+
+impl Default for OverlaysConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Vec::new(),
+            fog_radius: default_overlay_fog_radius(),
+            grid_spacing: default_overlay_grid_spacing(),
+            heatmap_cell: default_overlay_heatmap_cell(),
+            trail_length: default_overlay_trail_length(),
+        }
+    }
+}
+
+/// 訓練假人配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DummyConfig {
+    /// DPS 統計窗口（秒）
+    #[serde(default = "default_dummy_window_secs")]
+    pub window_secs: f32,
+    /// 假人與英雄的距離（世界單位）
+    #[serde(default = "default_dummy_distance")]
+    pub distance: f32,
+    /// 未指定時的假人生命值
+    #[serde(default = "default_dummy_hp")]
+    pub hp: f32,
+}
+
+fn default_dummy_window_secs() -> f32 {
+    10.0
+}
+
+fn default_dummy_distance() -> f32 {
+    150.0
+}
+
+fn default_dummy_hp() -> f32 {
+    1000.0
+}
+
+impl Default for DummyConfig {
+    fn default() -> Self {
+        Self {
+            window_secs: default_dummy_window_secs(),
+            distance: default_dummy_distance(),
+            hp: default_dummy_hp(),
+        }
+    }
+}
+
+/// 練習沙盒配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SandboxConfig {
+    /// 兩次重置冷卻（或補滿）之間至少間隔的毫秒數，避免後端尚未套用時重複送出
+    #[serde(default = "default_sandbox_reset_interval_ms")]
+    pub reset_interval_ms: u64,
+    /// 受傷時補滿生命值
+    #[serde(default = "default_true")]
+    pub refill_health: bool,
+    /// 道具次數減少時補滿
+    #[serde(default = "default_true")]
+    pub refill_items: bool,
+    /// 每隔幾秒重新生成訓練假人（0 表示不循環）
+    #[serde(default = "default_sandbox_dummy_loop_secs")]
+    pub dummy_loop_secs: f32,
+}
+
+fn default_sandbox_reset_interval_ms() -> u64 {
+    250
+}
+
+fn default_sandbox_dummy_loop_secs() -> f32 {
+    20.0
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            reset_interval_ms: default_sandbox_reset_interval_ms(),
+            refill_health: default_true(),
+            refill_items: default_true(),
+            dummy_loop_secs: default_sandbox_dummy_loop_secs(),
+        }
+    }
+}
+
+/// MQTT 流量錄製配置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecordingConfig {
+    /// 錄製檔路徑（JSON Lines，附加寫入；未設置時不錄製）
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+/// 嚴格模式配置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StrictConfig {
+    /// 允許的位置同步差異次數，超過時失敗
+    #[serde(default)]
+    pub max_sync_errors: u64,
+}
+
+/// 畫面狀態更新配置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScreenUpdatesConfig {
+    /// 向後端要求增量更新（只送新增、改變與移除的實體），後端不支援時仍收到完整畫面
+    #[serde(default)]
+    pub delta: bool,
+}
+
+/// 本地移動預測配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PredictionConfig {
+    /// 是否啟用（關閉時本地英雄直接跳到伺服器位置）
+    #[serde(default)]
+    pub enabled: bool,
+    /// 預測用的英雄移動速度（世界單位/秒）
+    #[serde(default = "default_prediction_move_speed")]
+    pub move_speed: f32,
+    /// 收到伺服器位置後消化校正偏移的時間（毫秒）
+    #[serde(default = "default_prediction_smoothing_ms")]
+    pub smoothing_ms: u64,
+    /// 預測與伺服器位置差距超過此距離時直接對齊（世界單位）
+    #[serde(default = "default_prediction_snap_distance")]
+    pub snap_distance: f32,
+}
+
+fn default_prediction_move_speed() -> f32 {
+    350.0
+}
+
+fn default_prediction_smoothing_ms() -> u64 {
+    150
+}
+
+fn default_prediction_snap_distance() -> f32 {
+    200.0
+}
+
+impl Default for PredictionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            move_speed: default_prediction_move_speed(),
+            smoothing_ms: default_prediction_smoothing_ms(),
+            snap_distance: default_prediction_snap_distance(),
+        }
+    }
+}
+
+/// 自動遊戲輸入擬人化配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HumanizeConfig {
+    /// 是否啟用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 每個操作送出前的最短反應延遲（毫秒）
+    #[serde(default = "default_humanize_min_delay_ms")]
+    pub min_delay_ms: u64,
+    /// 每個操作送出前的最長反應延遲（毫秒）
+    #[serde(default = "default_humanize_max_delay_ms")]
+    pub max_delay_ms: u64,
+    /// 目標座標的抖動半徑（世界單位）
+    #[serde(default = "default_humanize_jitter")]
+    pub jitter: f32,
+    /// 誤點機率（0 到 1）
+    #[serde(default = "default_humanize_misclick_chance")]
+    pub misclick_chance: f32,
+    /// 誤點時偏離目標的距離（世界單位）
+    #[serde(default = "default_humanize_misclick_distance")]
+    pub misclick_distance: f32,
+}
+
+fn default_humanize_min_delay_ms() -> u64 {
+    80
+}
+
+fn default_humanize_max_delay_ms() -> u64 {
+    350
+}
+
+fn default_humanize_jitter() -> f32 {
+    8.0
+}
+
+fn default_humanize_misclick_chance() -> f32 {
+    0.03
+}
+
+fn default_humanize_misclick_distance() -> f32 {
+    60.0
+}
+
+impl Default for HumanizeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_delay_ms: default_humanize_min_delay_ms(),
+            max_delay_ms: default_humanize_max_delay_ms(),
+            jitter: default_humanize_jitter(),
+            misclick_chance: default_humanize_misclick_chance(),
+            misclick_distance: default_humanize_misclick_distance(),
+        }
+    }
+}
+
+/// 自動遊戲的行為樹機器人配置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BotConfig {
+    /// 行為樹設定檔（.toml、.yaml 或 .yml，未設置時使用內建的 scripts/bots/default.toml）
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+/// 商店目錄配置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShopConfig {
+    /// 商店目錄檔（TOML，未設置時使用內建的 data/shop.toml）
+    #[serde(default)]
+    pub catalog: Option<String>,
+}
+
+/// 地圖配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MapConfig {
+    /// 世界邊界（後端提供邊界時以後端為準）
+    #[serde(default)]
+    pub bounds: Option<WorldBounds>,
+    /// 塔與主堡的位置、陣營與佔地（終端視圖以 2x2 或 3x3 圖樣顯示）
+    #[serde(default)]
+    pub structures: Vec<MapStructure>,
+}
+
+/// 座標轉換配置（後端座標與終端座標方向不一致時使用）
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct BackendConfig {
-    pub executable_path: String,
+#[serde(deny_unknown_fields)]
+pub struct CoordinatesConfig {
+    /// 後端 Y 軸朝上時設為 true
     #[serde(default)]
-    pub args: Vec<String>,
-    pub working_directory: Option<String>,
+    pub flip_y: bool,
+    /// 本地原點對應的後端 X 座標
     #[serde(default)]
-    pub env: HashMap<String, String>,
+    pub origin_x: f32,
+    /// 本地原點對應的後端 Y 座標
+    #[serde(default)]
+    pub origin_y: f32,
+    /// 每個本地單位對應的後端單位
+    #[serde(default = "default_coordinate_scale")]
+    pub scale: f32,
 }
 
-/// 前端配置
+fn default_coordinate_scale() -> f32 {
+    1.0
+}
+
+impl Default for CoordinatesConfig {
+    fn default() -> Self {
+        Self {
+            flip_y: false,
+            origin_x: 0.0,
+            origin_y: 0.0,
+            scale: default_coordinate_scale(),
+        }
+    }
+}
+
+/// 警報判斷方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertMode {
+    /// 直接比較運算式的值
+    #[default]
+    Value,
+    /// 比較每分鐘變化量
+    Rate,
+}
+
+/// 警報通知方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertNotify {
+    /// 寫入日誌
+    Log,
+    /// 終端響鈴
+    Bell,
+    /// 發送到 webhook_url
+    Webhook,
+}
+
+/// 警報規則
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct FrontendConfig {
-    pub player_name: String,
-    pub hero_type: String,
-    pub auto_start_backend: bool,
-    pub backend_start_delay: u64,
-    pub backend_shutdown_timeout: u64,
-    /// 螢幕顯示範圍配置
-    pub screen_range: ScreenRangeConfig,
+#[serde(deny_unknown_fields)]
+pub struct AlertRule {
+    /// 規則名稱
+    pub name: String,
+    /// 運算式（語法同 query 命令）
+    pub expr: String,
+    /// 判斷方式
+    #[serde(default)]
+    pub mode: AlertMode,
+    /// 高於此值時觸發
+    #[serde(default)]
+    pub above: Option<f64>,
+    /// 低於此值時觸發
+    #[serde(default)]
+    pub below: Option<f64>,
+    /// 條件需持續成立的秒數
+    #[serde(default)]
+    pub for_secs: f64,
+    /// 通知方式
+    #[serde(default = "default_alert_notify")]
+    pub notify: Vec<AlertNotify>,
 }
 
-/// 螢幕顯示範圍配置
+fn default_alert_notify() -> Vec<AlertNotify> {
+    vec![AlertNotify::Log]
+}
+
+/// 警報配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ScreenRangeConfig {
-    /// 螢幕顯示範圍寬度（遊戲世界單位）
-    pub width: f32,
-    /// 螢幕顯示範圍高度（遊戲世界單位）
-    pub height: f32,
-    /// 是否啟用動態範圍調整（根據縮放等調整）
-    pub dynamic_range: bool,
-    /// 最小顯示範圍（防止過度縮小）
-    pub min_width: f32,
-    /// 最小顯示範圍（防止過度縮小）
-    pub min_height: f32,
-    /// 最大顯示範圍（防止過度放大）
-    pub max_width: f32,
-    /// 最大顯示範圍（防止過度放大）
-    pub max_height: f32,
+#[serde(deny_unknown_fields)]
+pub struct AlertsConfig {
+    /// 警報規則列表
+    #[serde(default)]
+    pub rules: Vec<AlertRule>,
+    /// webhook 通知網址（以 POST JSON 發送）
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// 同一規則再次觸發前的冷卻時間（秒）
+    #[serde(default = "default_alert_cooldown_secs")]
+    pub cooldown_secs: f64,
+}
+
+fn default_alert_cooldown_secs() -> f64 {
+    60.0
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            webhook_url: None,
+            cooldown_secs: default_alert_cooldown_secs(),
+        }
+    }
 }
 
 impl Default for AppConfig {
@@ -67,6 +1345,14 @@ impl Default for AppConfig {
             server: ServerConfig {
                 mqtt_host: "127.0.0.1".to_string(),
                 mqtt_port: 1883,
+                wire_format: WireFormat::default(),
+                username: None,
+                password: None,
+                tls: TlsConfig::default(),
+                qos: QosConfig::default(),
+                topics: TopicScheme::default(),
+                failover: Vec::new(),
+                binary_decoders: BinaryDecoders::default(),
             },
             backend: BackendConfig {
                 executable_path: "../omobab/target/debug/omobab".to_string(),
@@ -90,36 +1376,334 @@ impl Default for AppConfig {
                     max_height: 600.0,
                 },
             },
+            metrics: MetricsConfig::default(),
+            sanity: SanityConfig::default(),
+            alerts: AlertsConfig::default(),
+            coordinates: CoordinatesConfig::default(),
+            map: MapConfig::default(),
+            targeting: TargetingConfig::default(),
+            macros: MacrosConfig::default(),
+            death_recap: DeathRecapConfig::default(),
+            credit: CreditConfig::default(),
+            heartbeat: HeartbeatConfig::default(),
+            time_sync: TimeSyncConfig::default(),
+            state_hash: StateHashConfig::default(),
+            update: UpdateConfig::default(),
+            liveness: LivenessConfig::default(),
+            expectations: ExpectationsConfig::default(),
+            offline_queue: OfflineQueueConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            rpc: RpcConfig::default(),
+            anomaly_capture: AnomalyCaptureConfig::default(),
+            input: InputConfig::default(),
+            camera: CameraConfig::default(),
+            overlays: OverlaysConfig::default(),
+            dummy: DummyConfig::default(),
+            sandbox: SandboxConfig::default(),
+            recording: RecordingConfig::default(),
+            humanize: HumanizeConfig::default(),
+            bot: BotConfig::default(),
+            shop: ShopConfig::default(),
+            screen_updates: ScreenUpdatesConfig::default(),
+            prediction: PredictionConfig::default(),
+            strict: StrictConfig::default(),
         }
     }
 }
 
-impl AppConfig {
-    /// 從檔案載入配置
-    pub fn from_file(path: &str) -> Result<Self> {
-        let content = std::fs::read_to_string(path)
-            .with_context(|| format!("無法讀取配置檔案: {}", path))?;
-        
-        let config: AppConfig = toml::from_str(&content)
-            .with_context(|| format!("無法解析配置檔案: {}", path))?;
-        
-        Ok(config)
+/// 預設配置檔案
+pub const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+/// 後端啟動等待與關閉逾時的上限（毫秒）
+const MAX_DELAY_MS: u64 = 60_000;
+
+/// 鏡頭縮放範圍（與視窗縮放的限制一致）
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 3.0;
+
+/// 離線操作佇列的上限
+const MAX_OFFLINE_QUEUE: usize = 10_000;
+
+/// 終端視圖寫死、不能配置的字元按鍵：q 退出、1-9 道具，以及鍵盤目標模式的 a 攻擊、/ 聊天、空白鍵準星回到英雄
+/// （Enter、Tab 等控制鍵不是字元，另外拒絕）
+const RESERVED_KEYS: &str = "q123456789a/ ";
+
+/// 配置檢查發現的問題
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+    /// 欄位路徑（例如 `frontend.screen_range.min_width`）
+    pub field: String,
+    pub message: String,
+}
+
+impl ConfigIssue {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { field: field.into(), message: message.into() }
     }
-    
-    /// 載入配置 (優先使用檔案，否則使用預設值)
+}
+
+/// 在配置內容中尋找欄位所在的行號（從 1 開始），`alerts.rules[1].expr` 對應第二個 `[[alerts.rules]]`
+pub(crate) fn locate_field(content: &str, field: &str) -> Option<usize> {
+    let (table, key) = field.rsplit_once('.').unwrap_or(("", field));
+    let (table, occurrence) = match table.strip_suffix(']').and_then(|t| t.rsplit_once('[')) {
+        Some((name, index)) => (name, index.parse::<usize>().ok()?),
+        None => (table, 0),
+    };
+
+    let mut in_table = table.is_empty();
+    let mut seen = 0;
+    for (number, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            let name = trimmed.trim_start_matches('[').split(']').next().unwrap_or("").trim();
+            in_table = name == table && seen == occurrence;
+            if name == table {
+                seen += 1;
+            }
+            continue;
+        }
+        if in_table {
+            if let Some(rest) = trimmed.strip_prefix(key) {
+                if rest.trim_start().starts_with('=') {
+                    return Some(number + 1);
+                }
+            }
+        }
+    }
+    None
+}
+
+impl AppConfig {
+    /// 載入配置（預設值 < config.toml < profile < 環境變數 < 命令行，無法解析時使用預設值）
     pub fn load() -> Self {
-        match Self::from_file("config.toml") {
-            Ok(config) => {
-                log::info!("已載入配置檔案: config.toml");
-                config
+        match crate::config_layers::ResolvedConfig::resolve_current() {
+            Ok(resolved) => {
+                log::info!("已載入配置檔案: {}", DEFAULT_CONFIG_FILE);
+                resolved.config
             },
             Err(e) => {
-                log::warn!("無法載入配置檔案，使用預設值: {}", e);
+                log::warn!("無法載入配置檔案，使用預設值: {:#}", e);
                 Self::default()
             }
         }
     }
     
+    /// 檢查數值範圍、檔案路徑與欄位之間的關係，返回所有發現的問題
+    pub fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        let mut check = |ok: bool, field: &str, message: String| {
+            if !ok {
+                issues.push(ConfigIssue::new(field, message));
+            }
+        };
+
+        // 伺服器
+        check(!self.server.mqtt_host.trim().is_empty(), "server.mqtt_host", "不可為空".to_string());
+        check(self.server.mqtt_port != 0, "server.mqtt_port", "連接埠必須介於 1 到 65535".to_string());
+        check(self.server.password.is_none() || self.server.username.is_some(), "server.password",
+            "設置密碼時必須同時設置 username".to_string());
+        for (index, endpoint) in self.server.failover.iter().enumerate() {
+            check(!endpoint.host.trim().is_empty(), &format!("server.failover[{}].host", index), "不可為空".to_string());
+            check(endpoint.port != 0, &format!("server.failover[{}].port", index), "連接埠必須介於 1 到 65535".to_string());
+        }
+        let tls = &self.server.tls;
+        if tls.enabled {
+            for (field, path) in [
+                ("server.tls.ca_cert", &tls.ca_cert),
+                ("server.tls.client_cert", &tls.client_cert),
+                ("server.tls.client_key", &tls.client_key),
+            ] {
+                if let Some(path) = path {
+                    check(std::path::Path::new(path).exists(), field, format!("檔案不存在: {}", path));
+                }
+            }
+            check(tls.client_cert.is_some() == tls.client_key.is_some(), "server.tls.client_cert",
+                "client_cert 與 client_key 必須同時設置".to_string());
+        }
+        for (field, level) in self.server.qos.levels() {
+            check(level <= 2, &format!("server.qos.{}", field), format!("QoS 等級 {} 無效（必須是 0、1 或 2）", level));
+        }
+        check(cfg!(feature = "msgpack") || self.server.wire_format != WireFormat::MessagePack, "server.wire_format",
+            "此版本建置時未啟用 msgpack 功能".to_string());
+        check(cfg!(feature = "recording") || self.recording.path.is_none(), "recording.path",
+            "此版本建置時未啟用 recording 功能".to_string());
+        if let Some(issue) = self.server.topics.prefix_issue() {
+            check(false, "server.topics.prefix", issue);
+        }
+
+        // 後端與前端
+        let frontend = &self.frontend;
+        if frontend.auto_start_backend {
+            if let Err(e) = self.get_backend_executable_path() {
+                check(false, "backend.executable_path", format!("{}（或將 frontend.auto_start_backend 設為 false）", e));
+            }
+        }
+        check(frontend.backend_start_delay <= MAX_DELAY_MS, "frontend.backend_start_delay",
+            format!("{} 毫秒超出上限 {} 毫秒", frontend.backend_start_delay, MAX_DELAY_MS));
+        check((1..=MAX_DELAY_MS).contains(&frontend.backend_shutdown_timeout), "frontend.backend_shutdown_timeout",
+            format!("應介於 1 到 {} 毫秒，目前為 {}", MAX_DELAY_MS, frontend.backend_shutdown_timeout));
+        let range = &frontend.screen_range;
+        for (axis, value, min, max) in [
+            ("width", range.width, range.min_width, range.max_width),
+            ("height", range.height, range.min_height, range.max_height),
+        ] {
+            check(min > 0.0, &format!("frontend.screen_range.min_{}", axis), format!("必須大於 0，目前為 {}", min));
+            if min > max {
+                check(false, &format!("frontend.screen_range.min_{}", axis),
+                    format!("min_{} ({}) 大於 max_{} ({})", axis, min, axis, max));
+            } else {
+                check((min..=max).contains(&value), &format!("frontend.screen_range.{}", axis),
+                    format!("{} 不在 min_{} ({}) 到 max_{} ({}) 之間", value, axis, min, axis, max));
+            }
+        }
+
+        // 指標與警報
+        for (index, metric) in self.metrics.definitions.iter().enumerate() {
+            if let Err(e) = crate::expr::Expr::parse(&metric.expr) {
+                check(false, &format!("metrics.definitions[{}].expr", index), format!("運算式錯誤: {}", e));
+            }
+        }
+        check(self.metrics.history_len > 0, "metrics.history_len", "必須大於 0".to_string());
+        check(cfg!(feature = "metrics") || self.metrics.definitions.is_empty(), "metrics.definitions",
+            "此版本建置時未啟用 metrics 功能".to_string());
+        for (index, rule) in self.alerts.rules.iter().enumerate() {
+            let field = |name: &str| format!("alerts.rules[{}].{}", index, name);
+            if let Err(e) = crate::expr::Expr::parse(&rule.expr) {
+                check(false, &field("expr"), format!("運算式錯誤: {}", e));
+            }
+            check(rule.above.is_some() || rule.below.is_some(), &field("name"),
+                format!("規則 {} 需要設置 above 或 below", rule.name));
+            check(rule.for_secs >= 0.0, &field("for_secs"), "不可為負數".to_string());
+            check(!rule.notify.contains(&AlertNotify::Webhook) || self.alerts.webhook_url.is_some(), &field("notify"),
+                "使用 webhook 通知需要設置 alerts.webhook_url".to_string());
+            check(cfg!(feature = "webhooks") || !rule.notify.contains(&AlertNotify::Webhook), &field("notify"),
+                "此版本建置時未啟用 webhooks 功能".to_string());
+        }
+        check(self.alerts.cooldown_secs >= 0.0, "alerts.cooldown_secs", "不可為負數".to_string());
+
+        // 合理性監控、座標與地圖
+        check(self.sanity.max_speed > 0.0, "sanity.max_speed", "必須大於 0".to_string());
+        check(self.sanity.teleport_grace >= 0.0, "sanity.teleport_grace", "不可為負數".to_string());
+        check(self.sanity.cooldown_tolerance >= 0.0, "sanity.cooldown_tolerance", "不可為負數".to_string());
+        check(self.coordinates.scale > 0.0, "coordinates.scale", format!("必須大於 0，目前為 {}", self.coordinates.scale));
+        if let Some(bounds) = &self.map.bounds {
+            check(bounds.min_x < bounds.max_x, "map.bounds.min_x",
+                format!("min_x ({}) 必須小於 max_x ({})", bounds.min_x, bounds.max_x));
+            check(bounds.min_y < bounds.max_y, "map.bounds.min_y",
+                format!("min_y ({}) 必須小於 max_y ({})", bounds.min_y, bounds.max_y));
+        }
+        for (index, structure) in self.map.structures.iter().enumerate() {
+            check((2..=3).contains(&structure.footprint()), &format!("map.structures[{}].footprint", index),
+                format!("必須為 2 或 3，目前為 {}", structure.footprint()));
+        }
+        check(self.targeting.pick_radius >= 0.0, "targeting.pick_radius", "不可為負數".to_string());
+
+        // 巨集、死亡回顧與擊殺歸屬
+        check(self.macros.recall_channel_secs >= 0.0, "macros.recall_channel_secs", "不可為負數".to_string());
+        check((0.0..=1.0).contains(&self.macros.auto_recall_below), "macros.auto_recall_below",
+            format!("生命比例應介於 0 到 1，目前為 {}", self.macros.auto_recall_below));
+        check(self.death_recap.size > 0, "death_recap.size", "必須大於 0".to_string());
+        check(self.credit.assist_window_secs > 0.0, "credit.assist_window_secs", "必須大於 0".to_string());
+        check(self.credit.min_assist_damage >= 0.0, "credit.min_assist_damage", "不可為負數".to_string());
+        check(!self.heartbeat.enabled || self.heartbeat.interval_ms >= 100, "heartbeat.interval_ms",
+            format!("間隔至少 100 毫秒，目前為 {}", self.heartbeat.interval_ms));
+        check(!self.time_sync.enabled || self.time_sync.interval_ms >= 100, "time_sync.interval_ms",
+            format!("間隔至少 100 毫秒，目前為 {}", self.time_sync.interval_ms));
+        check(!self.state_hash.enabled || self.state_hash.interval_ms >= 100, "state_hash.interval_ms",
+            format!("間隔至少 100 毫秒，目前為 {}", self.state_hash.interval_ms));
+        check(self.state_hash.precision > 0.0, "state_hash.precision", "必須大於 0".to_string());
+        if let Some(area) = &self.state_hash.area {
+            check(area.radius > 0.0, "state_hash.area.radius", "必須大於 0".to_string());
+        }
+        check(self.update.release_url.starts_with("https://") || self.update.release_url.starts_with("http://"), "update.release_url",
+            format!("必須是 http(s) 網址，目前為 {:?}", self.update.release_url));
+        check(self.liveness.stale_secs > 0, "liveness.stale_secs", "必須大於 0".to_string());
+        check(self.liveness.lost_secs >= self.liveness.stale_secs, "liveness.lost_secs",
+            format!("不可小於 stale_secs ({})", self.liveness.stale_secs));
+        check(self.expectations.timeout_ms > 0, "expectations.timeout_ms", "必須大於 0".to_string());
+        check(self.expectations.position_tolerance > 0.0, "expectations.position_tolerance", "必須大於 0".to_string());
+        check(self.offline_queue.max_size <= MAX_OFFLINE_QUEUE, "offline_queue.max_size",
+            format!("{} 超出上限 {}", self.offline_queue.max_size, MAX_OFFLINE_QUEUE));
+        for (kind, rate) in &self.rate_limit.per_second {
+            check(rate.is_finite() && *rate > 0.0, &format!("rate_limit.per_second.{}", kind),
+                format!("每秒次數必須大於 0，目前為 {}", rate));
+        }
+        check(self.rpc.timeout_ms > 0, "rpc.timeout_ms", "必須大於 0".to_string());
+        check(!self.anomaly_capture.enabled || !self.anomaly_capture.dir.is_empty(), "anomaly_capture.dir",
+            "不可為空".to_string());
+        check(self.anomaly_capture.history_secs > 0, "anomaly_capture.history_secs", "必須大於 0".to_string());
+
+        // 輸入與鏡頭
+        let keymap = &self.input.keymap;
+        let mut keys: Vec<(String, char)> = keymap.abilities.iter()
+            .enumerate()
+            .map(|(index, key)| (format!("abilities[{}]", index), *key))
+            .collect();
+        keys.push(("bookmark".to_string(), keymap.bookmark));
+        keys.push(("stats".to_string(), keymap.stats));
+        keys.push(("death_recap".to_string(), keymap.death_recap));
+        keys.push(("network".to_string(), keymap.network));
+        keys.push(("debug_labels".to_string(), keymap.debug_labels));
+        keys.push(("fog".to_string(), keymap.fog));
+        for (index, (name, key)) in keys.iter().enumerate() {
+            let field = format!("input.keymap.{}", name.split('[').next().unwrap_or(name));
+            let key = key.to_ascii_lowercase();
+            check(!RESERVED_KEYS.contains(key) && !key.is_control(), &field,
+                format!("{} 的按鍵 {:?} 與內建按鍵（q 退出、1-9 道具、Enter 聊天、Tab 選擇目標，鍵盤目標模式的 a 攻擊、/ 聊天、空白鍵）衝突", name, key));
+            if let Some((other, _)) = keys[..index].iter().find(|(_, k)| k.to_ascii_lowercase() == key) {
+                check(false, &field, format!("{} 與 {} 使用相同按鍵 '{}'", name, other, key));
+            }
+        }
+        if let Err(e) = crate::key_macros::KeyMacros::parse(&self.input.macros) {
+            check(false, "input.macros", format!("{:#}", e));
+        }
+        let camera = &self.camera;
+        check(camera.view_width > 0.0 && camera.view_height > 0.0, "camera.view_width", "視圖大小必須大於 0".to_string());
+        check((MIN_ZOOM..=MAX_ZOOM).contains(&camera.zoom), "camera.zoom",
+            format!("縮放應介於 {} 到 {}，目前為 {}", MIN_ZOOM, MAX_ZOOM, camera.zoom));
+        check(camera.viewport_debounce_ms <= MAX_DELAY_MS, "camera.viewport_debounce_ms",
+            format!("{} 毫秒超出上限 {} 毫秒", camera.viewport_debounce_ms, MAX_DELAY_MS));
+        for name in &self.overlays.enabled {
+            check(crate::terminal_view::overlays::LAYER_NAMES.contains(&name.as_str()), "overlays.enabled",
+                format!("未知的疊加層 {:?}（可用: {}）", name, crate::terminal_view::overlays::LAYER_NAMES.join(", ")));
+        }
+        check(self.overlays.fog_radius > 0.0, "overlays.fog_radius", "必須大於 0".to_string());
+        check(self.overlays.grid_spacing > 0.0, "overlays.grid_spacing", "必須大於 0".to_string());
+        check(self.overlays.heatmap_cell > 0.0, "overlays.heatmap_cell", "必須大於 0".to_string());
+        check(self.overlays.trail_length > 0, "overlays.trail_length", "必須大於 0".to_string());
+        check(self.dummy.window_secs > 0.0, "dummy.window_secs", "必須大於 0".to_string());
+        check(self.dummy.distance >= 0.0, "dummy.distance", "不可為負數".to_string());
+        check(self.dummy.hp > 0.0, "dummy.hp", "必須大於 0".to_string());
+        check(self.sandbox.reset_interval_ms > 0, "sandbox.reset_interval_ms", "必須大於 0".to_string());
+        check(self.sandbox.dummy_loop_secs >= 0.0, "sandbox.dummy_loop_secs", "不可為負數".to_string());
+        let humanize = &self.humanize;
+        check(humanize.min_delay_ms <= humanize.max_delay_ms, "humanize.min_delay_ms",
+            format!("min_delay_ms ({}) 不可大於 max_delay_ms ({})", humanize.min_delay_ms, humanize.max_delay_ms));
+        check(humanize.max_delay_ms <= MAX_DELAY_MS, "humanize.max_delay_ms",
+            format!("{} 毫秒超出上限 {} 毫秒", humanize.max_delay_ms, MAX_DELAY_MS));
+        check(humanize.jitter >= 0.0, "humanize.jitter", "不可為負數".to_string());
+        check((0.0..=1.0).contains(&humanize.misclick_chance), "humanize.misclick_chance",
+            format!("機率應介於 0 到 1，目前為 {}", humanize.misclick_chance));
+        check(humanize.misclick_distance >= 0.0, "humanize.misclick_distance", "不可為負數".to_string());
+        let prediction = &self.prediction;
+        check(prediction.move_speed > 0.0, "prediction.move_speed", "必須大於 0".to_string());
+        check(prediction.smoothing_ms <= MAX_DELAY_MS, "prediction.smoothing_ms",
+            format!("{} 毫秒超出上限 {} 毫秒", prediction.smoothing_ms, MAX_DELAY_MS));
+        check(prediction.snap_distance > 0.0, "prediction.snap_distance", "必須大於 0".to_string());
+        if let Some(profile) = &self.bot.profile {
+            if let Err(e) = crate::behavior::BehaviorTree::load(std::path::Path::new(profile)) {
+                check(false, "bot.profile", format!("{:#}", e));
+            }
+        }
+        if let Some(catalog) = &self.shop.catalog {
+            if let Err(e) = crate::shop::ShopCatalog::load(std::path::Path::new(catalog)) {
+                check(false, "shop.catalog", format!("{:#}", e));
+            }
+        }
+
+        issues
+    }
+    
     /// 儲存配置到檔案
     pub fn save_to_file(&self, path: &str) -> Result<()> {
         let content = toml::to_string_pretty(self)
@@ -180,4 +1764,64 @@ impl AppConfig {
             max_height: self.frontend.screen_range.max_height,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_reports_range_and_keymap_issues() {
+        let mut config = AppConfig::default();
+        config.frontend.auto_start_backend = false;
+        assert!(config.validate().is_empty(), "{:?}", config.validate());
+
+        config.frontend.screen_range.min_width = 900.0;
+        config.input.keymap.stats = 'W';
+        config.server.qos.broadcasts = 3;
+        let fields: Vec<String> = config.validate().into_iter().map(|issue| issue.field).collect();
+        assert_eq!(fields, vec!["server.qos.broadcasts", "frontend.screen_range.min_width", "input.keymap.stats"]);
+    }
+
+    #[test]
+    fn validate_checks_the_debug_labels_key() {
+        let mut config = AppConfig::default();
+        config.frontend.auto_start_backend = false;
+        config.input.keymap.debug_labels = 'M';
+        let issues = config.validate();
+        assert_eq!(issues.iter().map(|issue| issue.field.as_str()).collect::<Vec<_>>(), ["input.keymap.debug_labels"]);
+        assert!(issues[0].message.contains("stats"), "{}", issues[0].message);
+
+        for reserved in ['q', '5', 'A', '/', ' ', '\t', '\n'] {
+            config.input.keymap.debug_labels = reserved;
+            let fields: Vec<String> = config.validate().into_iter().map(|issue| issue.field).collect();
+            assert_eq!(fields, vec!["input.keymap.debug_labels"], "{:?}", reserved);
+        }
+    }
+
+    #[test]
+    fn parses_and_validates_tls_settings() {
+        let tls: TlsConfig = toml::from_str("enabled = true\nca_cert = \"data/tls/test_ca.pem\"\nserver_name = \"broker.local\"\n").unwrap();
+        assert_eq!(tls.server_name.as_deref(), Some("broker.local"));
+        assert!(toml::from_str::<TlsConfig>("enabled = true\nsni = \"broker.local\"\n").is_err(), "未知欄位");
+
+        let mut config = AppConfig::default();
+        config.frontend.auto_start_backend = false;
+        config.server.tls = tls;
+        assert!(config.validate().is_empty(), "{:?}", config.validate());
+        config.server.tls.ca_cert = Some("missing_ca.pem".to_string());
+        config.server.tls.client_cert = Some("data/tls/test_broker.pem".to_string());
+        let fields: Vec<String> = config.validate().into_iter().map(|issue| issue.field).collect();
+        assert_eq!(fields, vec!["server.tls.ca_cert", "server.tls.client_cert"]);
+        config.server.tls.enabled = false;
+        assert!(config.validate().is_empty(), "停用 TLS 時不檢查憑證");
+    }
+
+    #[test]
+    fn locates_fields_in_tables_and_arrays() {
+        let content = "[server]\nmqtt_port = 0\n\n[[alerts.rules]]\nexpr = \"a\"\n\n[[alerts.rules]]\nname = \"b\"\nexpr = \"(\"\n";
+        assert_eq!(locate_field(content, "server.mqtt_port"), Some(2));
+        assert_eq!(locate_field(content, "alerts.rules[1].expr"), Some(9));
+        assert_eq!(locate_field(content, "camera.zoom"), None);
+    }
+}
@@ -0,0 +1,355 @@
+/// 分層配置解析
+///
+/// 依序疊加 預設值 < config.toml < profile（`config.<名稱>.toml`）< 環境變數 `OMOBAF_*` < 命令行參數，
+/// 並記錄每個值最後由哪一層決定，供 `config effective` 顯示與配置檢查報告定位問題
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::OnceLock;
+use anyhow::{Context, Result};
+use colored::*;
+use toml::{Table, Value};
+
+use crate::config::{locate_field, AppConfig};
+
+/// 環境變數前綴（巢狀欄位以 `__` 分隔，例如 `OMOBAF_SERVER__MQTT_PORT`）
+const ENV_PREFIX: &str = "OMOBAF_";
+
+/// 選擇 profile 的環境變數
+const PROFILE_ENV: &str = "OMOBAF_PROFILE";
+
+/// 命令行參數層（程序啟動時設置一次）
+static CLI_OVERRIDES: OnceLock<CliOverrides> = OnceLock::new();
+
+/// 配置值的來源
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigSource {
+    Default,
+    File(String),
+    Profile { name: String, path: String },
+    Env(String),
+    Cli(&'static str),
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "預設值"),
+            ConfigSource::File(path) => write!(f, "{}", path),
+            ConfigSource::Profile { name, path } => write!(f, "profile {} ({})", name, path),
+            ConfigSource::Env(var) => write!(f, "環境變數 {}", var),
+            ConfigSource::Cli(flag) => write!(f, "命令行 {}", flag),
+        }
+    }
+}
+
+/// 命令行明確指定的配置值（未指定的參數不覆蓋其他層）
+#[derive(Debug, Clone, Default)]
+pub struct CliOverrides {
+    pub profile: Option<String>,
+    pub server_ip: Option<String>,
+    pub server_port: Option<u16>,
+    pub player_name: Option<String>,
+    pub hero: Option<String>,
+    /// --no-mouse（停用滑鼠，使用鍵盤目標模式）
+    pub no_mouse: bool,
+    /// --record <file>（錄製 MQTT 流量）
+    pub record: Option<String>,
+}
+
+/// 設置命令行參數層（只有第一次設置有效）
+pub fn set_cli_overrides(overrides: CliOverrides) {
+    let _ = CLI_OVERRIDES.set(overrides);
+}
+
+/// 合併後的配置與每個值的來源
+#[derive(Debug, Clone)]
+pub struct ResolvedConfig {
+    pub config: AppConfig,
+    /// 合併後的原始值
+    merged: Table,
+    /// 各欄位（以 `.` 連接的路徑）的來源
+    sources: BTreeMap<String, ConfigSource>,
+}
+
+impl ResolvedConfig {
+    /// 以預設配置檔案、目前的環境變數與命令行參數解析
+    pub fn resolve_current() -> Result<Self> {
+        let cli = CLI_OVERRIDES.get().cloned().unwrap_or_default();
+        Self::resolve(crate::config::DEFAULT_CONFIG_FILE, &cli, std::env::vars())
+    }
+
+    /// 依序疊加各層配置，任何一層解析失敗或最終配置檢查未通過時返回附帶來源的錯誤
+    pub fn resolve(path: &str, cli: &CliOverrides, env: impl IntoIterator<Item = (String, String)>) -> Result<Self> {
+        let env: Vec<(String, String)> = env.into_iter()
+            .filter(|(key, _)| key.starts_with(ENV_PREFIX))
+            .collect();
+        let mut merged = Table::try_from(AppConfig::default()).context("無法序列化預設配置")?;
+        let mut sources = BTreeMap::new();
+        record_sources(&mut sources, "", &merged, &ConfigSource::Default);
+        let mut contents: Vec<(ConfigSource, String)> = Vec::new();
+
+        // config.toml（不存在時只使用預設值）
+        if Path::new(path).exists() {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("無法讀取配置檔案: {}", path))?;
+            // 先以完整結構解析，讓未知欄位與型別錯誤帶上行號
+            toml::from_str::<AppConfig>(&content)
+                .with_context(|| format!("無法解析配置檔案: {}", path))?;
+            let table: Table = toml::from_str(&content)
+                .with_context(|| format!("無法解析配置檔案: {}", path))?;
+            let source = ConfigSource::File(path.to_string());
+            merge_table(&mut merged, &mut sources, "", table, &source);
+            contents.push((source, content));
+        }
+
+        // profile
+        let profile = cli.profile.clone()
+            .or_else(|| env.iter().find(|(key, _)| key == PROFILE_ENV).map(|(_, value)| value.clone()));
+        if let Some(name) = profile {
+            let profile_path = profile_path(path, &name);
+            let content = std::fs::read_to_string(&profile_path)
+                .with_context(|| format!("找不到 profile {} 的配置檔案: {}", name, profile_path))?;
+            let table: Table = toml::from_str(&content)
+                .with_context(|| format!("無法解析 profile 配置檔案: {}", profile_path))?;
+            let source = ConfigSource::Profile { name: name.clone(), path: profile_path.clone() };
+            merge_table(&mut merged, &mut sources, "", table, &source);
+            merged.clone().try_into::<AppConfig>()
+                .with_context(|| format!("套用 profile {} ({}) 後的配置無效", name, profile_path))?;
+            contents.push((source, content));
+        }
+
+        // 環境變數
+        for (key, raw) in &env {
+            let Some(field) = env_field(key) else {
+                continue;
+            };
+            let value = parse_override(lookup(&merged, &field), raw)
+                .with_context(|| format!("環境變數 {} 的值無效: {}", key, raw))?;
+            set_value(&mut merged, &mut sources, &field, value, ConfigSource::Env(key.clone()));
+        }
+        if !env.is_empty() {
+            merged.clone().try_into::<AppConfig>().context("套用環境變數 OMOBAF_* 後的配置無效")?;
+        }
+
+        // 命令行參數
+        let cli_values = [
+            ("server.mqtt_host", "--server-ip", cli.server_ip.clone().map(Value::String)),
+            ("server.mqtt_port", "--server-port", cli.server_port.map(|port| Value::Integer(port.into()))),
+            ("frontend.player_name", "--player-name", cli.player_name.clone().map(Value::String)),
+            ("frontend.hero_type", "--hero", cli.hero.clone().map(Value::String)),
+            ("input.mouse", "--no-mouse", cli.no_mouse.then_some(Value::Boolean(false))),
+            ("recording.path", "--record", cli.record.clone().map(Value::String)),
+        ];
+        for (field, flag, value) in cli_values {
+            if let Some(value) = value {
+                set_value(&mut merged, &mut sources, field, value, ConfigSource::Cli(flag));
+            }
+        }
+
+        let config: AppConfig = merged.clone().try_into().context("合併後的配置無效")?;
+        let resolved = Self { config, merged, sources };
+
+        let issues = resolved.config.validate();
+        if !issues.is_empty() {
+            let report: Vec<String> = issues.iter()
+                .map(|issue| {
+                    let location = match resolved.source_of(&issue.field) {
+                        Some(source @ (ConfigSource::File(_) | ConfigSource::Profile { .. })) => {
+                            let line = contents.iter()
+                                .find(|(layer, _)| layer == source)
+                                .and_then(|(_, content)| locate_field(content, &issue.field));
+                            match line {
+                                Some(line) => format!("（{} 第 {} 行）", source, line),
+                                None => format!("（{}）", source),
+                            }
+                        },
+                        Some(source @ (ConfigSource::Env(_) | ConfigSource::Cli(_))) => format!("（{}）", source),
+                        _ => String::new(),
+                    };
+                    format!("  - {}{}: {}", issue.field, location, issue.message)
+                })
+                .collect();
+            anyhow::bail!("配置有 {} 個問題:\n{}", issues.len(), report.join("\n"));
+        }
+
+        Ok(resolved)
+    }
+
+    /// 欄位的來源（陣列元素與 Option 欄位以最接近的上層路徑為準）
+    pub fn source_of(&self, field: &str) -> Option<&ConfigSource> {
+        let mut path = field.split('[').next().unwrap_or(field);
+        loop {
+            if let Some(source) = self.sources.get(path) {
+                return Some(source);
+            }
+            path = path.rsplit_once('.')?.0;
+        }
+    }
+
+    /// 所有欄位的值與來源（依路徑排序）
+    pub fn entries(&self) -> Vec<(String, String, &ConfigSource)> {
+        let mut values = BTreeMap::new();
+        flatten(&mut values, "", &self.merged);
+        values.into_iter()
+            .filter_map(|(path, value)| {
+                let source = self.sources.get(&path)?;
+                let shown = if path.ends_with("password") { "\"***\"".to_string() } else { value.to_string() };
+                Some((path, shown, source))
+            })
+            .collect()
+    }
+
+    /// 打印有效配置與每個值的來源
+    pub fn print(&self) {
+        println!("\n{}", "有效配置 (預設值 < config.toml < profile < 環境變數 OMOBAF_* < 命令行):".bright_cyan().bold());
+        println!("{}", "-".repeat(40).bright_black());
+        for (path, value, source) in self.entries() {
+            let source = match source {
+                ConfigSource::Default => source.to_string().bright_black(),
+                _ => source.to_string().bright_yellow(),
+            };
+            println!("  {} = {}  {}", path.green(), value, source);
+        }
+    }
+}
+
+/// profile 配置檔案路徑（與配置檔案同目錄的 `config.<名稱>.toml`）
+fn profile_path(path: &str, name: &str) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("config");
+    path.with_file_name(format!("{}.{}.toml", stem, name)).to_string_lossy().into_owned()
+}
+
+/// 環境變數對應的欄位路徑（`OMOBAF_FRONTEND__SCREEN_RANGE__WIDTH` → `frontend.screen_range.width`）
+fn env_field(key: &str) -> Option<String> {
+    let rest = key.strip_prefix(ENV_PREFIX)?;
+    if !rest.contains("__") {
+        return None;
+    }
+    Some(rest.split("__").map(str::to_lowercase).collect::<Vec<_>>().join("."))
+}
+
+/// 依現有值的型別解析覆蓋值；欄位沒有現有值時依 TOML 語法推斷，無法解析則視為字串
+fn parse_override(current: Option<&Value>, raw: &str) -> Result<Value> {
+    let inferred = || toml::from_str::<Table>(&format!("v = {}", raw)).ok().and_then(|mut table| table.remove("v"));
+    Ok(match current {
+        Some(Value::String(_)) => Value::String(raw.to_string()),
+        Some(Value::Integer(_)) => Value::Integer(raw.trim().parse().context("應為整數")?),
+        Some(Value::Float(_)) => Value::Float(raw.trim().parse().context("應為數字")?),
+        Some(Value::Boolean(_)) => Value::Boolean(match raw.trim() {
+            "true" | "on" | "1" => true,
+            "false" | "off" | "0" => false,
+            _ => anyhow::bail!("應為 true 或 false"),
+        }),
+        Some(Value::Array(_)) => match inferred() {
+            Some(value @ Value::Array(_)) => value,
+            _ => anyhow::bail!("應為 TOML 陣列，例如 [\"a\", \"b\"]"),
+        },
+        Some(Value::Table(_)) => anyhow::bail!("無法覆蓋整個區段，請指定其中的欄位"),
+        _ => inferred().unwrap_or_else(|| Value::String(raw.to_string())),
+    })
+}
+
+fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() { key.to_string() } else { format!("{}.{}", prefix, key) }
+}
+
+/// 把上層表格疊加到合併結果（表格逐欄合併，其他值整個取代）
+fn merge_table(target: &mut Table, sources: &mut BTreeMap<String, ConfigSource>, prefix: &str, layer: Table, source: &ConfigSource) {
+    for (key, value) in layer {
+        let path = join(prefix, &key);
+        match (target.get_mut(&key), value) {
+            (Some(Value::Table(existing)), Value::Table(table)) => merge_table(existing, sources, &path, table, source),
+            (_, value) => {
+                sources.retain(|existing, _| existing != &path && !existing.starts_with(&format!("{}.", path)));
+                record_value_sources(sources, &path, &value, source);
+                target.insert(key, value);
+            }
+        }
+    }
+}
+
+fn record_sources(sources: &mut BTreeMap<String, ConfigSource>, prefix: &str, table: &Table, source: &ConfigSource) {
+    for (key, value) in table {
+        record_value_sources(sources, &join(prefix, key), value, source);
+    }
+}
+
+fn record_value_sources(sources: &mut BTreeMap<String, ConfigSource>, path: &str, value: &Value, source: &ConfigSource) {
+    match value {
+        Value::Table(table) => record_sources(sources, path, table, source),
+        _ => {
+            sources.insert(path.to_string(), source.clone());
+        }
+    }
+}
+
+/// 依路徑查找值
+fn lookup<'a>(table: &'a Table, field: &str) -> Option<&'a Value> {
+    let (parent, key) = match field.rsplit_once('.') {
+        Some((parent, key)) => (Some(parent), key),
+        None => (None, field),
+    };
+    let mut current = table;
+    for segment in parent.into_iter().flat_map(|parent| parent.split('.')) {
+        current = current.get(segment)?.as_table()?;
+    }
+    current.get(key)
+}
+
+/// 依路徑設置值（缺少的上層表格會自動建立）
+fn set_value(table: &mut Table, sources: &mut BTreeMap<String, ConfigSource>, field: &str, value: Value, source: ConfigSource) {
+    let mut current = table;
+    let mut segments: Vec<&str> = field.split('.').collect();
+    let key = segments.pop().unwrap_or(field);
+    for segment in segments {
+        let entry = current.entry(segment.to_string()).or_insert_with(|| Value::Table(Table::new()));
+        if !entry.is_table() {
+            *entry = Value::Table(Table::new());
+        }
+        current = entry.as_table_mut().unwrap();
+    }
+    current.insert(key.to_string(), value);
+    sources.insert(field.to_string(), source);
+}
+
+fn flatten(values: &mut BTreeMap<String, Value>, prefix: &str, table: &Table) {
+    for (key, value) in table {
+        let path = join(prefix, key);
+        match value {
+            Value::Table(inner) => flatten(values, &path, inner),
+            _ => {
+                values.insert(path, value.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_layers_override_earlier_ones() {
+        let cli = CliOverrides { server_port: Some(1999), ..CliOverrides::default() };
+        let env = vec![
+            ("OMOBAF_FRONTEND__AUTO_START_BACKEND".to_string(), "false".to_string()),
+            ("OMOBAF_SERVER__MQTT_PORT".to_string(), "1884".to_string()),
+            ("OMOBAF_SERVER__MQTT_HOST".to_string(), "broker.test".to_string()),
+            ("OMOBAF_VERBOSE".to_string(), "1".to_string()),
+        ];
+        let resolved = ResolvedConfig::resolve("/nonexistent/config.toml", &cli, env).unwrap();
+
+        assert_eq!(resolved.config.server.mqtt_host, "broker.test");
+        assert_eq!(resolved.config.server.mqtt_port, 1999);
+        assert_eq!(resolved.source_of("server.mqtt_port"), Some(&ConfigSource::Cli("--server-port")));
+        assert_eq!(resolved.source_of("server.mqtt_host"), Some(&ConfigSource::Env("OMOBAF_SERVER__MQTT_HOST".to_string())));
+        assert_eq!(resolved.source_of("frontend.player_name"), Some(&ConfigSource::Default));
+    }
+
+    #[test]
+    fn rejects_mistyped_env_override() {
+        let env = vec![("OMOBAF_SERVER__MQTT_PORT".to_string(), "abc".to_string())];
+        assert!(ResolvedConfig::resolve("/nonexistent/config.toml", &CliOverrides::default(), env).is_err());
+    }
+}
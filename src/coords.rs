@@ -0,0 +1,79 @@
+/// 座標轉換
+///
+/// 部分後端設定中 Y 軸朝上、原點或單位與終端不同，導致移動方向鏡像。
+/// 這裡集中定義後端座標與本地座標（Y 軸朝下，終端顯示與使用者輸入所用）之間的轉換，
+/// 視口映射與送出的操作都經過同一組轉換
+use vek::Vec2;
+
+use crate::config::CoordinatesConfig;
+
+/// 後端座標與本地座標之間的轉換
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoordinateTransform {
+    /// 後端 Y 軸朝上（本地 Y 軸朝下）
+    pub flip_y: bool,
+    /// 本地原點對應的後端座標
+    pub origin: Vec2<f32>,
+    /// 每個本地單位對應的後端單位
+    pub scale: f32,
+}
+
+impl Default for CoordinateTransform {
+    fn default() -> Self {
+        Self {
+            flip_y: false,
+            origin: Vec2::zero(),
+            scale: 1.0,
+        }
+    }
+}
+
+impl CoordinateTransform {
+    /// 從配置創建轉換（無效的比例視為 1）
+    pub fn from_config(config: &CoordinatesConfig) -> Self {
+        Self {
+            flip_y: config.flip_y,
+            origin: Vec2::new(config.origin_x, config.origin_y),
+            scale: if config.scale > 0.0 { config.scale } else { 1.0 },
+        }
+    }
+
+    fn y_sign(self) -> f32 {
+        if self.flip_y { -1.0 } else { 1.0 }
+    }
+
+    /// 後端座標轉本地座標
+    pub fn to_local(self, backend: Vec2<f32>) -> Vec2<f32> {
+        let relative = (backend - self.origin) / self.scale;
+        Vec2::new(relative.x, relative.y * self.y_sign())
+    }
+
+    /// 本地座標轉後端座標
+    pub fn to_backend(self, local: Vec2<f32>) -> Vec2<f32> {
+        Vec2::new(local.x, local.y * self.y_sign()) * self.scale + self.origin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity_by_default() {
+        let transform = CoordinateTransform::default();
+        let p = Vec2::new(12.5, -3.0);
+        assert_eq!(transform.to_local(p), p);
+        assert_eq!(transform.to_backend(p), p);
+    }
+
+    #[test]
+    fn flips_and_offsets_round_trip() {
+        let transform = CoordinateTransform {
+            flip_y: true,
+            origin: Vec2::new(100.0, 200.0),
+            scale: 2.0,
+        };
+        assert_eq!(transform.to_local(Vec2::new(110.0, 220.0)), Vec2::new(5.0, -10.0));
+        assert_eq!(transform.to_backend(Vec2::new(5.0, -10.0)), Vec2::new(110.0, 220.0));
+    }
+}
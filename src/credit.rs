@@ -0,0 +1,233 @@
+/// 擊殺/助攻歸屬驗證
+///
+/// 依戰鬥日誌彙總每個單位在被擊殺前一段時間內受到的傷害，
+/// 收到後端的擊殺訊息時比對擊殺者與助攻名單是否與觀測到的傷害貢獻一致，
+/// 不一致的結果會由合理性監控記錄為協議異常，用來抓出後端計分系統的歸屬錯誤
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, SystemTime};
+
+use crate::config::CreditConfig;
+
+/// 保留的驗證結果上限
+const MAX_CHECKS: usize = 100;
+
+/// 單次傷害
+#[derive(Debug, Clone)]
+struct Hit {
+    time: SystemTime,
+    source: String,
+    amount: f32,
+}
+
+/// 後端送出的擊殺歸屬
+#[derive(Debug, Clone, PartialEq)]
+pub struct KillCredit {
+    pub victim: String,
+    pub killer: String,
+    pub assists: Vec<String>,
+}
+
+/// 單一來源在助攻窗口內的傷害貢獻
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contribution {
+    pub source: String,
+    pub damage: f32,
+}
+
+/// 一次擊殺的驗證結果
+#[derive(Debug, Clone)]
+pub struct CreditCheck {
+    /// 遞增序號（供合理性監控辨識新的結果）
+    pub seq: u64,
+    pub time: SystemTime,
+    pub credit: KillCredit,
+    /// 觀測到的傷害貢獻（傷害高的在前）
+    pub contributions: Vec<Contribution>,
+    /// 不一致之處（空表示通過）
+    pub failures: Vec<String>,
+}
+
+impl CreditCheck {
+    /// 是否通過驗證
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// 傷害貢獻時間線
+#[derive(Debug, Clone)]
+pub struct ContributionTimeline {
+    config: CreditConfig,
+    /// 各單位受到的傷害（由舊到新）
+    damage: HashMap<String, VecDeque<Hit>>,
+    checks: VecDeque<CreditCheck>,
+    next_seq: u64,
+}
+
+impl Default for ContributionTimeline {
+    fn default() -> Self {
+        Self::new(CreditConfig::default())
+    }
+}
+
+impl ContributionTimeline {
+    /// 創建時間線
+    pub fn new(config: CreditConfig) -> Self {
+        Self {
+            config,
+            damage: HashMap::new(),
+            checks: VecDeque::new(),
+            next_seq: 1,
+        }
+    }
+
+    /// 更新驗證規則
+    pub fn set_config(&mut self, config: CreditConfig) {
+        self.config = config;
+    }
+
+    fn window(&self) -> Duration {
+        Duration::from_secs_f32(self.config.assist_window_secs.max(0.0))
+    }
+
+    /// 記錄一次傷害（只保留助攻窗口內的紀錄）
+    pub fn record_damage(&mut self, victim: &str, source: &str, amount: f32, now: SystemTime) {
+        let window = self.window();
+        let hits = self.damage.entry(victim.to_string()).or_default();
+        hits.push_back(Hit { time: now, source: source.to_string(), amount });
+        while hits.front().is_some_and(|hit| now.duration_since(hit.time).unwrap_or_default() > window) {
+            hits.pop_front();
+        }
+    }
+
+    /// 來源在 `since` 之後對任何單位造成的傷害總和（只涵蓋助攻窗口內的紀錄）
+    pub fn damage_dealt_since(&self, source: &str, since: SystemTime) -> f32 {
+        self.damage.values()
+            .flatten()
+            .filter(|hit| hit.source == source && hit.time >= since)
+            .map(|hit| hit.amount)
+            .sum()
+    }
+
+    /// 單位在 `now` 之前助攻窗口內受到的傷害，依來源彙總
+    pub fn contributions(&self, victim: &str, now: SystemTime) -> Vec<Contribution> {
+        let window = self.window();
+        let mut contributions: Vec<Contribution> = Vec::new();
+        for hit in self.recent_hits(victim, now, window) {
+            match contributions.iter_mut().find(|c| c.source == hit.source) {
+                Some(contribution) => contribution.damage += hit.amount,
+                None => contributions.push(Contribution { source: hit.source.clone(), damage: hit.amount }),
+            }
+        }
+        contributions.sort_by(|a, b| b.damage.total_cmp(&a.damage));
+        contributions
+    }
+
+    fn recent_hits(&self, victim: &str, now: SystemTime, window: Duration) -> impl Iterator<Item = &Hit> {
+        self.damage.get(victim)
+            .into_iter()
+            .flatten()
+            .filter(move |hit| now.duration_since(hit.time).unwrap_or_default() <= window)
+    }
+
+    /// 以觀測到的傷害驗證擊殺歸屬，並清除被擊殺單位的時間線
+    pub fn verify_kill(&mut self, credit: KillCredit, now: SystemTime) -> &CreditCheck {
+        let window = self.window();
+        let contributions = self.contributions(&credit.victim, now);
+        let last_hitter = self.recent_hits(&credit.victim, now, window)
+            .last()
+            .map(|hit| hit.source.clone());
+
+        let mut failures = Vec::new();
+        if !contributions.iter().any(|c| c.source == credit.killer) {
+            failures.push(format!("擊殺者 {} 在 {:.0} 秒內未造成傷害", credit.killer, window.as_secs_f32()));
+        } else if self.config.require_last_hit {
+            if let Some(last) = last_hitter.filter(|last| *last != credit.killer) {
+                failures.push(format!("最後一擊來自 {}，擊殺卻歸屬 {}", last, credit.killer));
+            }
+        }
+
+        let expected: Vec<&str> = contributions.iter()
+            .filter(|c| c.source != credit.killer && c.source != credit.victim)
+            .filter(|c| c.damage >= self.config.min_assist_damage)
+            .map(|c| c.source.as_str())
+            .collect();
+        let missing: Vec<&str> = expected.iter()
+            .filter(|source| !credit.assists.iter().any(|assist| assist == *source))
+            .copied()
+            .collect();
+        let extra: Vec<&str> = credit.assists.iter()
+            .map(String::as_str)
+            .filter(|assist| !expected.contains(assist))
+            .collect();
+        if !missing.is_empty() {
+            failures.push(format!("遺漏助攻: {}", missing.join(", ")));
+        }
+        if !extra.is_empty() {
+            failures.push(format!("多出助攻（無足夠傷害貢獻）: {}", extra.join(", ")));
+        }
+
+        self.damage.remove(&credit.victim);
+        if self.checks.len() >= MAX_CHECKS {
+            self.checks.pop_front();
+        }
+        self.checks.push_back(CreditCheck {
+            seq: self.next_seq,
+            time: now,
+            credit,
+            contributions,
+            failures,
+        });
+        self.next_seq += 1;
+        self.checks.back().unwrap()
+    }
+
+    /// 最近的驗證結果（由舊到新）
+    pub fn checks(&self) -> &VecDeque<CreditCheck> {
+        &self.checks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn credit(killer: &str, assists: &[&str]) -> KillCredit {
+        KillCredit {
+            victim: "Bob".to_string(),
+            killer: killer.to_string(),
+            assists: assists.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    fn timeline() -> (ContributionTimeline, SystemTime) {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let mut timeline = ContributionTimeline::new(CreditConfig::default());
+        // 超出助攻窗口的傷害不算貢獻
+        timeline.record_damage("Bob", "Carol", 80.0, start);
+        let later = start + Duration::from_secs(30);
+        timeline.record_damage("Bob", "Dave", 40.0, later);
+        timeline.record_damage("Bob", "Alice", 60.0, later + Duration::from_secs(1));
+        (timeline, later + Duration::from_secs(2))
+    }
+
+    #[test]
+    fn matching_credit_passes() {
+        let (mut timeline, now) = timeline();
+        let check = timeline.verify_kill(credit("Alice", &["Dave"]), now);
+        assert!(check.passed(), "{:?}", check.failures);
+        assert_eq!(check.contributions.len(), 2);
+        assert!(timeline.contributions("Bob", now).is_empty());
+    }
+
+    #[test]
+    fn flags_wrong_killer_and_assists() {
+        let (mut timeline, now) = timeline();
+        let check = timeline.verify_kill(credit("Dave", &["Carol"]), now);
+        assert_eq!(check.failures, vec![
+            "最後一擊來自 Alice，擊殺卻歸屬 Dave".to_string(),
+            "遺漏助攻: Alice".to_string(),
+            "多出助攻（無足夠傷害貢獻）: Carol".to_string(),
+        ]);
+    }
+}
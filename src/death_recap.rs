@@ -0,0 +1,212 @@
+/// 死亡回顧
+///
+/// 收集本地英雄最近受到的傷害事件（來自後端的戰鬥日誌訊息），英雄死亡時整理成回顧：
+/// 誰用什麼造成了多少傷害、在多長的時間內，方便 QA 核對後端的傷害歸屬
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+/// 預設保留的傷害事件數量
+pub const DEFAULT_RECAP_SIZE: usize = 10;
+
+/// 單次傷害事件
+#[derive(Debug, Clone, PartialEq)]
+pub struct DamageEvent {
+    /// 收到事件的時間
+    pub time: SystemTime,
+    /// 傷害來源（玩家、召喚物、塔等）
+    pub source: String,
+    /// 造成傷害的技能
+    pub ability: Option<String>,
+    /// 傷害量
+    pub amount: f32,
+}
+
+/// 單一來源的傷害統計
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceDamage {
+    pub source: String,
+    pub total: f32,
+    pub hits: usize,
+    /// 此來源使用過的技能（依首次出現順序）
+    pub abilities: Vec<String>,
+}
+
+/// 一次死亡的回顧
+#[derive(Debug, Clone)]
+pub struct DeathRecap {
+    /// 死亡時間
+    pub died_at: SystemTime,
+    /// 死亡前最後的傷害事件（由舊到新）
+    pub events: Vec<DamageEvent>,
+}
+
+impl DeathRecap {
+    /// 回顧涵蓋的時間範圍（第一筆傷害到死亡）
+    pub fn window(&self) -> Duration {
+        self.events.first()
+            .and_then(|first| self.died_at.duration_since(first.time).ok())
+            .unwrap_or_default()
+    }
+
+    /// 總傷害
+    pub fn total_damage(&self) -> f32 {
+        self.events.iter().map(|event| event.amount).sum()
+    }
+
+    /// 依來源彙總傷害（傷害高的在前）
+    pub fn by_source(&self) -> Vec<SourceDamage> {
+        let mut sources: Vec<SourceDamage> = Vec::new();
+        for event in &self.events {
+            let entry = match sources.iter().position(|s| s.source == event.source) {
+                Some(index) => &mut sources[index],
+                None => {
+                    sources.push(SourceDamage {
+                        source: event.source.clone(),
+                        total: 0.0,
+                        hits: 0,
+                        abilities: Vec::new(),
+                    });
+                    sources.last_mut().unwrap()
+                }
+            };
+            entry.total += event.amount;
+            entry.hits += 1;
+            if let Some(ability) = &event.ability {
+                if !entry.abilities.contains(ability) {
+                    entry.abilities.push(ability.clone());
+                }
+            }
+        }
+        sources.sort_by(|a, b| b.total.total_cmp(&a.total));
+        sources
+    }
+
+    /// 浮層與命令列顯示用的文字行
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("💀 死亡回顧: {} 次傷害，共 {:.0}，{:.1} 秒內",
+            self.events.len(), self.total_damage(), self.window().as_secs_f32())];
+        for source in self.by_source() {
+            let abilities = if source.abilities.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", source.abilities.join(", "))
+            };
+            lines.push(format!("  {} {:.0} ×{}{}", source.source, source.total, source.hits, abilities));
+        }
+        lines.push("  ── 時間線 ──".to_string());
+        for event in &self.events {
+            let before = self.died_at.duration_since(event.time).unwrap_or_default();
+            lines.push(format!("  -{:.1}s {} {} {:.0}",
+                before.as_secs_f32(),
+                event.source,
+                event.ability.as_deref().unwrap_or("普攻"),
+                event.amount));
+        }
+        lines
+    }
+}
+
+/// 本地英雄的戰鬥日誌
+#[derive(Debug, Clone)]
+pub struct CombatLog {
+    capacity: usize,
+    /// 本次存活期間最後的傷害事件
+    events: VecDeque<DamageEvent>,
+    /// 已記錄死亡、尚未復活
+    dead: bool,
+    /// 最近一次死亡的回顧
+    last_recap: Option<DeathRecap>,
+}
+
+impl Default for CombatLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_RECAP_SIZE)
+    }
+}
+
+impl CombatLog {
+    /// 創建保留最後 `capacity` 筆傷害的戰鬥日誌
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            events: VecDeque::new(),
+            dead: false,
+            last_recap: None,
+        }
+    }
+
+    /// 調整保留的傷害事件數量
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.events.len() > self.capacity {
+            self.events.pop_front();
+        }
+    }
+
+    /// 記錄受到的傷害
+    pub fn record_damage(&mut self, event: DamageEvent) {
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    /// 記錄死亡並整理回顧（同一次死亡只整理一次），返回是否產生了新回顧
+    pub fn mark_death(&mut self, died_at: SystemTime) -> bool {
+        if self.dead {
+            return false;
+        }
+        self.dead = true;
+        self.last_recap = Some(DeathRecap {
+            died_at,
+            events: self.events.drain(..).collect(),
+        });
+        true
+    }
+
+    /// 記錄復活
+    pub fn mark_alive(&mut self) {
+        self.dead = false;
+    }
+
+    /// 最近一次死亡的回顧
+    pub fn last_recap(&self) -> Option<&DeathRecap> {
+        self.last_recap.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(secs_before: u64, source: &str, ability: Option<&str>, amount: f32, died_at: SystemTime) -> DamageEvent {
+        DamageEvent {
+            time: died_at - Duration::from_secs(secs_before),
+            source: source.to_string(),
+            ability: ability.map(str::to_string),
+            amount,
+        }
+    }
+
+    #[test]
+    fn recap_keeps_last_events_and_groups_by_source() {
+        let died_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let mut log = CombatLog::new(3);
+        log.record_damage(hit(9, "tower", None, 200.0, died_at));
+        log.record_damage(hit(4, "Alice", Some("flame_blade"), 30.0, died_at));
+        log.record_damage(hit(2, "Bob", None, 25.0, died_at));
+        log.record_damage(hit(1, "Alice", Some("fire_dash"), 45.0, died_at));
+
+        assert!(log.mark_death(died_at));
+        assert!(!log.mark_death(died_at + Duration::from_secs(1)));
+
+        let recap = log.last_recap().unwrap();
+        assert_eq!(recap.events.len(), 3);
+        assert_eq!(recap.window(), Duration::from_secs(4));
+        let sources = recap.by_source();
+        assert_eq!(sources[0].source, "Alice");
+        assert_eq!(sources[0].total, 75.0);
+        assert_eq!(sources[0].abilities, vec!["flame_blade", "fire_dash"]);
+        assert_eq!(sources[1].source, "Bob");
+    }
+}
@@ -0,0 +1,161 @@
+/// 英雄演示序列
+///
+/// 每個英雄的演示步驟放在 `heroes/<英雄ID>/demo.toml`，每一步有名稱、操作、參數與可選的預期結果
+/// （查詢運算式）。`demo --hero <id>` 依序執行並檢查預期結果，兼作各英雄技能組的冒煙測試；
+/// `[performance]` 設定的效能門檻（見 perf 模組）與步驟一起列在報告中
+use std::path::PathBuf;
+use anyhow::{anyhow, bail, Context, Result};
+use colored::*;
+use serde::Deserialize;
+
+use crate::expr::Expr;
+use crate::hero_catalog;
+use crate::perf::PerfGates;
+
+/// 演示序列檔名
+const DEMO_FILE: &str = "demo.toml";
+
+fn default_params() -> serde_json::Value {
+    serde_json::json!({})
+}
+
+fn default_wait_ms() -> u64 {
+    2000
+}
+
+/// 演示步驟
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DemoStep {
+    /// 步驟名稱
+    pub name: String,
+    /// 玩家操作（move、cast_ability、attack 等）
+    pub action: String,
+    /// 操作參數（後端座標）
+    #[serde(default = "default_params")]
+    pub params: serde_json::Value,
+    /// 執行後等待時間（毫秒）
+    #[serde(default = "default_wait_ms")]
+    pub wait_ms: u64,
+    /// 預期結果（查詢運算式，結果為真即通過）
+    #[serde(default)]
+    pub expect: Option<String>,
+}
+
+/// 英雄的演示序列
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DemoSequence {
+    pub steps: Vec<DemoStep>,
+    /// 效能門檻
+    #[serde(default)]
+    pub performance: PerfGates,
+}
+
+impl DemoSequence {
+    /// 英雄演示序列檔案的路徑
+    pub fn path(hero_id: &str) -> PathBuf {
+        PathBuf::from(hero_catalog::HERO_DATA_DIR).join(hero_id).join(DEMO_FILE)
+    }
+
+    /// 載入英雄的演示序列
+    pub fn load(hero_id: &str) -> Result<Self> {
+        let path = Self::path(hero_id);
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("無法讀取演示序列: {:?}", path))?;
+        Self::parse(hero_id, &content).with_context(|| format!("演示序列有誤: {:?}", path))
+    }
+
+    /// 解析並檢查演示序列
+    pub fn parse(hero_id: &str, content: &str) -> Result<Self> {
+        let hero = hero_catalog::find_hero(hero_id).ok_or_else(|| anyhow!("未知的英雄: {}", hero_id))?;
+        let sequence: DemoSequence = toml::from_str(content)?;
+        if sequence.steps.is_empty() {
+            bail!("沒有任何步驟");
+        }
+        for step in &sequence.steps {
+            if step.action == "cast_ability" {
+                let ability = step.params.get("ability_id").and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow!("步驟「{}」缺少 ability_id", step.name))?;
+                if hero.ability(ability).is_none() {
+                    bail!("步驟「{}」的技能 {} 不屬於 {}", step.name, ability, hero.id);
+                }
+            }
+            if let Some(expect) = &step.expect {
+                Expr::parse(expect).with_context(|| format!("步驟「{}」的預期結果無法解析", step.name))?;
+            }
+        }
+        Ok(sequence)
+    }
+}
+
+/// 單一步驟的結果
+#[derive(Debug, Clone)]
+pub struct StepOutcome {
+    pub name: String,
+    pub passed: bool,
+    /// 失敗原因或預期結果的實際值
+    pub detail: String,
+}
+
+/// 演示執行報告
+#[derive(Debug, Clone)]
+pub struct DemoReport {
+    pub hero_id: String,
+    pub outcomes: Vec<StepOutcome>,
+    /// 效能門檻的檢查結果
+    pub gates: Vec<StepOutcome>,
+}
+
+impl DemoReport {
+    /// 是否所有步驟與效能門檻都通過
+    pub fn passed(&self) -> bool {
+        self.outcomes.iter().chain(&self.gates).all(|outcome| outcome.passed)
+    }
+
+    /// 顯示報告
+    pub fn print(&self) {
+        println!("\n{} {}", "演示結果:".bright_cyan().bold(), self.hero_id);
+        for outcome in &self.outcomes {
+            let mark = if outcome.passed { "✓".green() } else { "✗".red() };
+            println!("  {} {} {}", mark, outcome.name, outcome.detail.bright_black());
+        }
+        let passed = self.outcomes.iter().filter(|outcome| outcome.passed).count();
+        println!("  通過 {}/{}", passed, self.outcomes.len());
+        if !self.gates.is_empty() {
+            println!("{}", "效能門檻:".bright_cyan().bold());
+            for gate in &self.gates {
+                let mark = if gate.passed { "✓".green() } else { "✗".red() };
+                println!("  {} {} {}", mark, gate.name, gate.detail.bright_black());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_sequences_parse_for_every_hero() {
+        let saika = DemoSequence::parse("saika_magoichi", include_str!("../heroes/saika_magoichi/demo.toml")).unwrap();
+        assert_eq!(saika.steps[0].action, "move");
+        assert_eq!(saika.steps[0].wait_ms, 2000);
+        let ctx = serde_json::json!({
+            "player": { "abilities": [{ "id": "sniper_mode", "cooldown": 3.0 }] },
+        });
+        let expect = Expr::parse(saika.steps[1].expect.as_ref().unwrap()).unwrap();
+        assert!(crate::expr::truthy(&expect.eval(&ctx).unwrap()));
+        assert_eq!(saika.performance.frame_time_p99_ms, Some(50.0));
+        DemoSequence::parse("date_masamune", include_str!("../heroes/date_masamune/demo.toml")).unwrap();
+
+        let foreign = r#"
+            [[steps]]
+            name = "施放"
+            action = "cast_ability"
+            params = { ability_id = "flame_blade" }
+        "#;
+        assert!(DemoSequence::parse("saika_magoichi", foreign).is_err());
+        assert!(DemoSequence::parse("unknown_hero", foreign).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+/// 訓練假人與 DPS 統計
+///
+/// `dummy spawn [n] [hp]` 以作弊操作 spawn_dummy 要求後端在英雄周圍生成不會移動的訓練假人，
+/// 之後記錄本地英雄（含召喚物）對假人造成的每次傷害，依統計窗口算出 DPS 與各技能、各假人的貢獻，
+/// 是驗證技能傷害調整的標準做法
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, SystemTime};
+use colored::*;
+use vek::Vec2;
+
+use crate::config::DummyConfig;
+
+/// 一次最多生成的假人數
+pub const MAX_DUMMIES: usize = 20;
+
+/// 對假人的一次傷害
+#[derive(Debug, Clone)]
+struct DummyHit {
+    time: SystemTime,
+    dummy: String,
+    ability: Option<String>,
+    amount: f32,
+}
+
+/// DPS 報告
+#[derive(Debug, Clone, PartialEq)]
+pub struct DpsReport {
+    /// 統計窗口（秒）
+    pub window_secs: f32,
+    /// 第一次命中到現在的時間（不超過統計窗口，秒）
+    pub elapsed_secs: f32,
+    pub hits: usize,
+    pub damage: f32,
+    pub dps: f32,
+    /// 各技能造成的傷害（普攻為 "attack"，傷害高的在前）
+    pub by_ability: Vec<(String, f32)>,
+    /// 各假人受到的傷害
+    pub by_dummy: BTreeMap<String, f32>,
+}
+
+impl DpsReport {
+    /// 顯示報告
+    pub fn print(&self) {
+        println!("\n{} (最近 {:.0} 秒)", "DPS 報告:".bright_cyan().bold(), self.window_secs);
+        if self.hits == 0 {
+            println!("  {}", "尚未對訓練假人造成傷害".bright_black());
+            return;
+        }
+        println!("  DPS: {}", format!("{:.1}", self.dps).yellow().bold());
+        println!("  總傷害: {:.0}（{} 次命中，{:.1} 秒）", self.damage, self.hits, self.elapsed_secs);
+        println!("  {}", "技能:".bright_cyan());
+        for (ability, damage) in &self.by_ability {
+            println!("    {:<20} {:>8.0} ({:.1} DPS, {:.0}%)",
+                ability, damage, damage / self.elapsed_secs, damage / self.damage * 100.0);
+        }
+        println!("  {}", "假人:".bright_cyan());
+        for (dummy, damage) in &self.by_dummy {
+            println!("    {:<20} {:>8.0}", dummy, damage);
+        }
+    }
+}
+
+/// 訓練假人傷害追蹤
+#[derive(Debug, Clone)]
+pub struct DummyTracker {
+    window: Duration,
+    /// 追蹤中的假人 ID
+    dummies: Vec<String>,
+    /// 已生成過的假人數（產生不重複的 ID）
+    spawned: usize,
+    /// 窗口內的命中（由舊到新）
+    hits: VecDeque<DummyHit>,
+}
+
+impl Default for DummyTracker {
+    fn default() -> Self {
+        Self::new(&DummyConfig::default())
+    }
+}
+
+impl DummyTracker {
+    /// 創建追蹤器
+    pub fn new(config: &DummyConfig) -> Self {
+        Self {
+            window: Duration::from_secs_f32(config.window_secs.max(0.0)),
+            dummies: Vec::new(),
+            spawned: 0,
+            hits: VecDeque::new(),
+        }
+    }
+
+    /// 更新統計窗口
+    pub fn set_config(&mut self, config: &DummyConfig) {
+        self.window = Duration::from_secs_f32(config.window_secs.max(0.0));
+    }
+
+    /// 產生下一批假人的 ID 並開始追蹤
+    pub fn track_new(&mut self, player_name: &str, count: usize) -> Vec<String> {
+        let ids: Vec<String> = (0..count)
+            .map(|i| format!("dummy_{}_{}", player_name, self.spawned + i + 1))
+            .collect();
+        self.spawned += count;
+        self.dummies.extend(ids.iter().cloned());
+        ids
+    }
+
+    /// 追蹤中的假人
+    pub fn dummies(&self) -> &[String] {
+        &self.dummies
+    }
+
+    /// 停止追蹤所有假人並清除命中紀錄
+    pub fn clear(&mut self) {
+        self.dummies.clear();
+        self.hits.clear();
+    }
+
+    /// 記錄對假人的傷害（目標不是追蹤中的假人時忽略）
+    pub fn record(&mut self, target: &str, ability: Option<&str>, amount: f32, now: SystemTime) {
+        if !self.dummies.iter().any(|dummy| dummy == target) {
+            return;
+        }
+        self.hits.push_back(DummyHit {
+            time: now,
+            dummy: target.to_string(),
+            ability: ability.map(str::to_string),
+            amount,
+        });
+        while self.hits.front().is_some_and(|hit| now.duration_since(hit.time).unwrap_or_default() > self.window) {
+            self.hits.pop_front();
+        }
+    }
+
+    /// 統計窗口內的 DPS
+    pub fn report(&self, now: SystemTime) -> DpsReport {
+        let hits: Vec<&DummyHit> = self.hits.iter()
+            .filter(|hit| now.duration_since(hit.time).unwrap_or_default() <= self.window)
+            .collect();
+        let damage: f32 = hits.iter().map(|hit| hit.amount).sum();
+        // 至少以一秒計算，避免只有一次命中時 DPS 暴增
+        let elapsed_secs = hits.first()
+            .map(|first| now.duration_since(first.time).unwrap_or_default().min(self.window).as_secs_f32().max(1.0))
+            .unwrap_or(0.0);
+
+        let mut by_ability: BTreeMap<String, f32> = BTreeMap::new();
+        let mut by_dummy: BTreeMap<String, f32> = BTreeMap::new();
+        for hit in &hits {
+            *by_ability.entry(hit.ability.clone().unwrap_or_else(|| "attack".to_string())).or_default() += hit.amount;
+            *by_dummy.entry(hit.dummy.clone()).or_default() += hit.amount;
+        }
+        let mut by_ability: Vec<(String, f32)> = by_ability.into_iter().collect();
+        by_ability.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        DpsReport {
+            window_secs: self.window.as_secs_f32(),
+            elapsed_secs,
+            hits: hits.len(),
+            damage,
+            dps: if elapsed_secs > 0.0 { damage / elapsed_secs } else { 0.0 },
+            by_ability,
+            by_dummy,
+        }
+    }
+}
+
+/// 在中心周圍等距排列的假人位置
+pub fn dummy_positions(center: Vec2<f32>, count: usize, distance: f32) -> Vec<Vec2<f32>> {
+    (0..count)
+        .map(|i| {
+            let angle = i as f32 / count as f32 * std::f32::consts::TAU;
+            center + Vec2::new(angle.cos(), angle.sin()) * distance
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_dps_within_window_per_ability() {
+        let start = SystemTime::now();
+        let mut tracker = DummyTracker::new(&DummyConfig { window_secs: 10.0, ..DummyConfig::default() });
+        let ids = tracker.track_new("p1", 2);
+        assert_eq!(ids, vec!["dummy_p1_1", "dummy_p1_2"]);
+        assert_eq!(tracker.track_new("p1", 1), vec!["dummy_p1_3"]);
+
+        tracker.record("dummy_p1_1", None, 50.0, start);
+        tracker.record("dummy_p1_2", Some("sniper_mode"), 150.0, start + Duration::from_secs(2));
+        tracker.record("enemy_hero", None, 999.0, start + Duration::from_secs(3));
+        let report = tracker.report(start + Duration::from_secs(4));
+        assert_eq!(report.hits, 2);
+        assert_eq!(report.dps, 50.0);
+        assert_eq!(report.by_ability[0], ("sniper_mode".to_string(), 150.0));
+        assert_eq!(report.by_dummy["dummy_p1_1"], 50.0);
+
+        // 超出窗口的命中不計入
+        let report = tracker.report(start + Duration::from_secs(11));
+        assert_eq!(report.damage, 150.0);
+
+        let positions = dummy_positions(Vec2::new(100.0, 100.0), 2, 50.0);
+        assert!((positions[0] - Vec2::new(150.0, 100.0)).magnitude() < 0.01);
+        assert!((positions[1] - Vec2::new(50.0, 100.0)).magnitude() < 0.01);
+    }
+}
@@ -0,0 +1,226 @@
+/// 操作結果驗證
+///
+/// PlayerSimulator 為每個操作預測結果（移動後的位置與距離、攻擊是否在範圍內），
+/// 之後以伺服器送來的狀態確認預測是否成立，依操作類型統計相符與不符的次數並保留最近的差異
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, SystemTime};
+use serde::Serialize;
+use vek::Vec2;
+
+use crate::config::ExpectationsConfig;
+use crate::game_state::GameState;
+
+/// 保留的差異筆數
+const MAX_DISCREPANCIES: usize = 20;
+
+/// 模擬器預測的操作結果
+#[derive(Debug, Clone, PartialEq)]
+pub enum Prediction {
+    /// 從 start 移動到 target，移動距離 distance
+    Move { start: Vec2<f32>, target: Vec2<f32>, distance: f32 },
+    /// 技能施放後進入冷卻
+    Cast { ability_id: String },
+    /// 攻擊是否在範圍內（在範圍內應造成傷害）
+    Attack { in_range: bool },
+}
+
+impl Prediction {
+    /// 從模擬器的結果建立預測（不需驗證的操作返回 None）
+    pub fn from_result(action: &str, result: &serde_json::Value, start: Vec2<f32>) -> Option<Self> {
+        let number = |key: &str| result.get(key).and_then(|v| v.as_f64()).map(|v| v as f32);
+        match action {
+            "move" => Some(Prediction::Move {
+                start,
+                target: Vec2::new(number("x")?, number("y")?),
+                distance: number("distance_moved")?,
+            }),
+            "cast_ability" => Some(Prediction::Cast {
+                ability_id: result.get("ability_id")?.as_str()?.to_string(),
+            }),
+            "attack" => Some(Prediction::Attack {
+                in_range: result.get("in_range")?.as_bool()?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// 等待伺服器確認的預測
+#[derive(Debug, Clone)]
+struct Pending {
+    action: String,
+    prediction: Prediction,
+    issued: SystemTime,
+}
+
+/// 預測與伺服器結果不符的紀錄
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discrepancy {
+    pub time: SystemTime,
+    pub action: String,
+    pub detail: String,
+}
+
+/// 單一操作類型的比對統計
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ActionTally {
+    pub matched: u64,
+    pub mismatched: u64,
+}
+
+/// 操作結果驗證器
+#[derive(Debug, Clone)]
+pub struct ExpectationChecker {
+    config: ExpectationsConfig,
+    pending: Vec<Pending>,
+    tallies: BTreeMap<String, ActionTally>,
+    discrepancies: VecDeque<Discrepancy>,
+}
+
+impl Default for ExpectationChecker {
+    fn default() -> Self {
+        Self::new(ExpectationsConfig::default())
+    }
+}
+
+impl ExpectationChecker {
+    /// 創建驗證器
+    pub fn new(config: ExpectationsConfig) -> Self {
+        Self {
+            config,
+            pending: Vec::new(),
+            tallies: BTreeMap::new(),
+            discrepancies: VecDeque::new(),
+        }
+    }
+
+    /// 更新驗證設定
+    pub fn set_config(&mut self, config: ExpectationsConfig) {
+        self.config = config;
+    }
+
+    /// 記錄一個已送出操作的預測
+    pub fn expect(&mut self, action: &str, prediction: Prediction, now: SystemTime) {
+        self.pending.push(Pending { action: action.to_string(), prediction, issued: now });
+    }
+
+    /// 以伺服器狀態確認等待中的預測
+    pub fn check(&mut self, state: &GameState, now: SystemTime) {
+        let timeout = Duration::from_millis(self.config.timeout_ms);
+        let pending = std::mem::take(&mut self.pending);
+        for expectation in pending {
+            let expired = now.duration_since(expectation.issued).unwrap_or_default() >= timeout;
+            match self.evaluate(&expectation, state, expired) {
+                Some(Ok(())) => self.tally(&expectation.action).matched += 1,
+                Some(Err(detail)) => {
+                    self.tally(&expectation.action).mismatched += 1;
+                    log::warn!("⚠ 操作結果與預測不符 [{}]: {}", expectation.action, detail);
+                    if self.discrepancies.len() == MAX_DISCREPANCIES {
+                        self.discrepancies.pop_front();
+                    }
+                    self.discrepancies.push_back(Discrepancy { time: now, action: expectation.action, detail });
+                }
+                None => self.pending.push(expectation),
+            }
+        }
+    }
+
+    /// 判定預測：Some(Ok) 相符、Some(Err) 不符、None 尚未能判定
+    fn evaluate(&self, expectation: &Pending, state: &GameState, expired: bool) -> Option<Result<(), String>> {
+        let player = &state.local_player;
+        match &expectation.prediction {
+            Prediction::Move { start, target, distance } => {
+                if (player.position - *target).magnitude() <= self.config.position_tolerance {
+                    return Some(Ok(()));
+                }
+                expired.then(|| Err(format!(
+                    "預測移動 {:.1} 到 ({:.1}, {:.1})，伺服器位置 ({:.1}, {:.1})（移動 {:.1}）",
+                    distance, target.x, target.y, player.position.x, player.position.y,
+                    (player.position - *start).magnitude())))
+            }
+            Prediction::Cast { ability_id } => {
+                let confirmed = player.abilities.iter()
+                    .any(|ability| ability.ability_id == *ability_id && (ability.cooldown_remaining > 0.0 || !ability.is_available));
+                if confirmed {
+                    return Some(Ok(()));
+                }
+                expired.then(|| Err(format!("伺服器未確認 {} 進入冷卻", ability_id)))
+            }
+            Prediction::Attack { in_range } => {
+                let dealt = state.credits.damage_dealt_since(&player.name, expectation.issued);
+                match (in_range, dealt > 0.0) {
+                    (true, true) => Some(Ok(())),
+                    (false, true) => Some(Err(format!("預測超出攻擊範圍，伺服器仍記錄 {:.0} 傷害", dealt))),
+                    (true, false) => expired.then(|| Err("預測在攻擊範圍內，伺服器未記錄任何傷害".to_string())),
+                    (false, false) => expired.then_some(Ok(())),
+                }
+            }
+        }
+    }
+
+    fn tally(&mut self, action: &str) -> &mut ActionTally {
+        self.tallies.entry(action.to_string()).or_default()
+    }
+
+    /// 各操作類型的統計
+    pub fn tallies(&self) -> &BTreeMap<String, ActionTally> {
+        &self.tallies
+    }
+
+    /// 最近的差異（由舊到新）
+    pub fn discrepancies(&self) -> &VecDeque<Discrepancy> {
+        &self.discrepancies
+    }
+
+    /// 等待確認中的預測數
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// 清除統計與差異紀錄
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.tallies.clear();
+        self.discrepancies.clear();
+    }
+
+    /// 查詢用的統計快照（`expectations.move.mismatched`）
+    pub fn context(&self) -> serde_json::Value {
+        serde_json::json!(self.tallies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_and_reports_per_action_type() {
+        let start = SystemTime::now();
+        let mut checker = ExpectationChecker::default();
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        state.local_player.position = Vec2::new(100.0, 100.0);
+
+        let result = serde_json::json!({ "x": 300.0, "y": 100.0, "distance_moved": 200.0 });
+        let prediction = Prediction::from_result("move", &result, state.local_player.position).unwrap();
+        checker.expect("move", prediction.clone(), start);
+        checker.expect("move", prediction, start);
+        checker.expect("attack", Prediction::Attack { in_range: false }, start);
+
+        // 第一次檢查時尚未到達、沒有傷害，全部等待中
+        checker.check(&state, start + Duration::from_millis(500));
+        assert_eq!(checker.pending(), 3);
+
+        state.local_player.position = Vec2::new(295.0, 102.0);
+        state.credits.record_damage("enemy", "p1", 30.0, start + Duration::from_millis(600));
+        checker.check(&state, start + Duration::from_millis(700));
+        assert_eq!(checker.tallies()["move"], ActionTally { matched: 2, mismatched: 0 });
+        assert_eq!(checker.tallies()["attack"], ActionTally { matched: 0, mismatched: 1 });
+        assert!(checker.discrepancies()[0].detail.contains("超出攻擊範圍"));
+
+        checker.expect("cast_ability", Prediction::Cast { ability_id: "sniper_mode".to_string() }, start);
+        checker.check(&state, start + Duration::from_secs(5));
+        assert_eq!(checker.tallies()["cast_ability"].mismatched, 1);
+        assert_eq!(checker.pending(), 0);
+    }
+}
@@ -0,0 +1,750 @@
+/// 狀態運算式引擎
+///
+/// 以小型運算式語言查詢遊戲狀態，例如 `player.health.0 / player.health.1`、
+/// `entities[type=="summon"].count()`、`distance(player.position, players[0].position)`。
+/// 運算式在 GameState 的 JSON 快照上求值，供自訂指標、查詢與斷言共用
+use anyhow::{anyhow, bail, Result};
+use serde_json::{json, Map, Value};
+
+use crate::game_state::{EntityType, GameState};
+
+/// 二元運算子
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+/// 一元運算子
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+/// 運算式語法樹
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// 常數
+    Literal(Value),
+    /// 根層級名稱（例如 `player`、`entities`）
+    Ident(String),
+    /// 欄位存取 `a.b`；數字欄位 `a.0` 代表元組存取
+    Field(Box<Expr>, String),
+    /// 索引 `a[0]`
+    Index(Box<Expr>, Box<Expr>),
+    /// 函數呼叫 `distance(a, b)`
+    Call(String, Vec<Expr>),
+    /// 方法呼叫 `a.count()`
+    Method(Box<Expr>, String, Vec<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Str(String),
+    Ident(String),
+    Dot,
+    Comma,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Op(&'static str),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                // `a.0.1` 這類欄位存取不應被當成小數
+                if chars[i] == '.' && !(i + 1 < chars.len() && chars[i + 1].is_ascii_digit()) {
+                    break;
+                }
+                if chars[i] == '.' && matches!(tokens.last(), Some(Token::Dot)) {
+                    break;
+                }
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let number = text.parse::<f64>().map_err(|_| anyhow!("無效的數字: {}", text))?;
+            tokens.push(Token::Number(number));
+            continue;
+        }
+        if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            continue;
+        }
+        if c == '"' || c == '\'' {
+            let quote = c;
+            i += 1;
+            let start = i;
+            while i < chars.len() && chars[i] != quote {
+                i += 1;
+            }
+            if i >= chars.len() {
+                bail!("字串缺少結尾引號");
+            }
+            tokens.push(Token::Str(chars[start..i].iter().collect()));
+            i += 1;
+            continue;
+        }
+
+        let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+        let op = match two.as_str() {
+            "==" => Some("=="),
+            "!=" => Some("!="),
+            "<=" => Some("<="),
+            ">=" => Some(">="),
+            "&&" => Some("&&"),
+            "||" => Some("||"),
+            _ => None,
+        };
+        if let Some(op) = op {
+            tokens.push(Token::Op(op));
+            i += 2;
+            continue;
+        }
+
+        let token = match c {
+            '.' => Token::Dot,
+            ',' => Token::Comma,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '+' => Token::Op("+"),
+            '-' => Token::Op("-"),
+            '*' => Token::Op("*"),
+            '/' => Token::Op("/"),
+            '%' => Token::Op("%"),
+            '<' => Token::Op("<"),
+            '>' => Token::Op(">"),
+            '!' => Token::Op("!"),
+            other => bail!("無法識別的字元: '{}'", other),
+        };
+        tokens.push(token);
+        i += 1;
+    }
+
+    Ok(tokens)
+}
+
+/// 遞迴下降語法分析器
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => bail!("預期 {:?}，但得到 {:?}", expected, token),
+            None => bail!("預期 {:?}，但運算式已結束", expected),
+        }
+    }
+
+    fn peek_op(&self, ops: &[&'static str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Op(op)) if ops.contains(op) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn parse_binary_level(&mut self, level: usize) -> Result<Expr> {
+        const LEVELS: [&[&str]; 5] = [
+            &["||"],
+            &["&&"],
+            &["==", "!=", "<", "<=", ">", ">="],
+            &["+", "-"],
+            &["*", "/", "%"],
+        ];
+        if level >= LEVELS.len() {
+            return self.parse_unary();
+        }
+
+        let mut left = self.parse_binary_level(level + 1)?;
+        while let Some(op) = self.peek_op(LEVELS[level]) {
+            self.pos += 1;
+            let right = self.parse_binary_level(level + 1)?;
+            let op = match op {
+                "||" => BinaryOp::Or,
+                "&&" => BinaryOp::And,
+                "==" => BinaryOp::Eq,
+                "!=" => BinaryOp::Ne,
+                "<" => BinaryOp::Lt,
+                "<=" => BinaryOp::Le,
+                ">" => BinaryOp::Gt,
+                ">=" => BinaryOp::Ge,
+                "+" => BinaryOp::Add,
+                "-" => BinaryOp::Sub,
+                "*" => BinaryOp::Mul,
+                "/" => BinaryOp::Div,
+                _ => BinaryOp::Rem,
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        if let Some(op) = self.peek_op(&["-", "!"]) {
+            self.pos += 1;
+            let operand = self.parse_unary()?;
+            let op = if op == "-" { UnaryOp::Neg } else { UnaryOp::Not };
+            return Ok(Expr::Unary(op, Box::new(operand)));
+        }
+        self.parse_postfix()
+    }
+
+    fn parse_args(&mut self) -> Result<Vec<Expr>> {
+        let mut args = Vec::new();
+        if self.peek() == Some(&Token::RParen) {
+            self.pos += 1;
+            return Ok(args);
+        }
+        loop {
+            args.push(self.parse_binary_level(0)?);
+            match self.next() {
+                Some(Token::Comma) => continue,
+                Some(Token::RParen) => return Ok(args),
+                other => bail!("參數列表格式錯誤: {:?}", other),
+            }
+        }
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_primary()?;
+        loop {
+            match self.peek() {
+                Some(Token::Dot) => {
+                    self.pos += 1;
+                    let name = match self.next() {
+                        Some(Token::Ident(name)) => name,
+                        Some(Token::Number(n)) if n.fract() == 0.0 && n >= 0.0 => format!("{}", n as u64),
+                        other => bail!("'.' 之後需要欄位名稱，但得到 {:?}", other),
+                    };
+                    if self.peek() == Some(&Token::LParen) {
+                        self.pos += 1;
+                        let args = self.parse_args()?;
+                        expr = Expr::Method(Box::new(expr), name, args);
+                    } else {
+                        expr = Expr::Field(Box::new(expr), name);
+                    }
+                }
+                Some(Token::LBracket) => {
+                    self.pos += 1;
+                    let index = self.parse_binary_level(0)?;
+                    self.expect(Token::RBracket)?;
+                    expr = Expr::Index(Box::new(expr), Box::new(index));
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Literal(json!(n))),
+            Some(Token::Str(s)) => Ok(Expr::Literal(Value::String(s))),
+            Some(Token::Ident(name)) => match name.as_str() {
+                "true" => Ok(Expr::Literal(Value::Bool(true))),
+                "false" => Ok(Expr::Literal(Value::Bool(false))),
+                "null" => Ok(Expr::Literal(Value::Null)),
+                _ if self.peek() == Some(&Token::LParen) => {
+                    self.pos += 1;
+                    let args = self.parse_args()?;
+                    Ok(Expr::Call(name, args))
+                }
+                _ => Ok(Expr::Ident(name)),
+            },
+            Some(Token::LParen) => {
+                let expr = self.parse_binary_level(0)?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some(token) => bail!("非預期的符號: {:?}", token),
+            None => bail!("運算式不完整"),
+        }
+    }
+}
+
+impl Expr {
+    /// 解析運算式
+    pub fn parse(source: &str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_binary_level(0)?;
+        if let Some(token) = parser.peek() {
+            bail!("運算式結尾有多餘的內容: {:?}", token);
+        }
+        Ok(expr)
+    }
+
+    /// 在指定的上下文中求值
+    pub fn eval(&self, ctx: &Value) -> Result<Value> {
+        match self {
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Ident(name) => ctx.get(name)
+                .cloned()
+                .ok_or_else(|| anyhow!("未知的名稱: {}", name)),
+            Expr::Field(target, name) => {
+                let target = target.eval(ctx)?;
+                get_field(&target, name)
+            }
+            Expr::Index(target, index) => {
+                let target = target.eval(ctx)?;
+                // 方括號內可在外層上下文求值為數字/字串時是索引，否則視為逐元素篩選條件
+                match (&target, index.eval(ctx)) {
+                    (_, Ok(key @ (Value::Number(_) | Value::String(_)))) => get_index(&target, &key),
+                    (Value::Array(items), _) => filter(items, index, ctx),
+                    (_, Err(e)) => Err(e),
+                    (_, Ok(other)) => bail!("無法以 {} 索引", type_name(&other)),
+                }
+            }
+            Expr::Call(name, args) => {
+                let args = args.iter().map(|a| a.eval(ctx)).collect::<Result<Vec<_>>>()?;
+                call_function(name, &args)
+            }
+            Expr::Method(target, name, args) => {
+                let target = target.eval(ctx)?;
+                let mut all = vec![target];
+                for arg in args {
+                    all.push(arg.eval(ctx)?);
+                }
+                call_function(name, &all)
+            }
+            Expr::Unary(op, operand) => {
+                let value = operand.eval(ctx)?;
+                match op {
+                    UnaryOp::Neg => Ok(json!(-as_number(&value)?)),
+                    UnaryOp::Not => Ok(Value::Bool(!truthy(&value))),
+                }
+            }
+            Expr::Binary(op, left, right) => {
+                let lhs = left.eval(ctx)?;
+                // 邏輯運算短路
+                match op {
+                    BinaryOp::And if !truthy(&lhs) => return Ok(Value::Bool(false)),
+                    BinaryOp::Or if truthy(&lhs) => return Ok(Value::Bool(true)),
+                    _ => {}
+                }
+                let rhs = right.eval(ctx)?;
+                binary(*op, &lhs, &rhs)
+            }
+        }
+    }
+
+    /// 求值並轉為數值（布林值轉為 0/1）
+    #[cfg(feature = "metrics")]
+    pub fn eval_number(&self, ctx: &Value) -> Result<f64> {
+        as_number(&self.eval(ctx)?)
+    }
+}
+
+/// 以條件篩選陣列元素
+///
+/// 條件在元素的上下文中求值：元素的欄位可直接引用（例如 `type == "summon"`），
+/// `it` 代表元素本身，外層的根名稱仍然可用
+fn filter(items: &[Value], predicate: &Expr, ctx: &Value) -> Result<Value> {
+    let mut kept = Vec::new();
+    for item in items {
+        let mut item_ctx = ctx.clone();
+        if let Value::Object(fields) = item {
+            for (key, value) in fields {
+                extend_context(&mut item_ctx, key, value.clone());
+            }
+        }
+        extend_context(&mut item_ctx, "it", item.clone());
+        if truthy(&predicate.eval(&item_ctx)?) {
+            kept.push(item.clone());
+        }
+    }
+    Ok(Value::Array(kept))
+}
+
+fn get_field(target: &Value, name: &str) -> Result<Value> {
+    if let Ok(index) = name.parse::<usize>() {
+        // `.0` 是元組存取：對元組陣列逐一取值，`[0]` 才是陣列索引
+        if let Value::Array(items) = target {
+            if !items.is_empty() && items.iter().all(Value::is_array) {
+                return items.iter().map(|item| get_index(item, &json!(index))).collect::<Result<Vec<_>>>().map(Value::Array);
+            }
+        }
+        return get_index(target, &json!(index));
+    }
+    match target {
+        Value::Object(map) => map.get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("欄位不存在: {}", name)),
+        // 對陣列取欄位時，對每個元素取值（投影）
+        Value::Array(items) => Ok(Value::Array(
+            items.iter().filter_map(|item| get_field(item, name).ok()).collect(),
+        )),
+        other => bail!("無法在 {} 上取欄位 {}", type_name(other), name),
+    }
+}
+
+fn get_index(target: &Value, index: &Value) -> Result<Value> {
+    match (target, index) {
+        (Value::Array(items), Value::Number(_)) => {
+            let i = as_number(index)?;
+            let len = items.len() as i64;
+            // 支援負索引（從尾端計算）
+            let i = if i < 0.0 { len + i as i64 } else { i as i64 };
+            items.get(i as usize)
+                .filter(|_| i >= 0)
+                .cloned()
+                .ok_or_else(|| anyhow!("索引超出範圍: {} (長度 {})", i, len))
+        }
+        (Value::Object(map), Value::String(key)) => map.get(key)
+            .cloned()
+            .ok_or_else(|| anyhow!("欄位不存在: {}", key)),
+        (other, index) => bail!("無法以 {} 索引 {}", type_name(index), type_name(other)),
+    }
+}
+
+fn numbers_of(value: &Value) -> Result<Vec<f64>> {
+    match value {
+        Value::Array(items) => items.iter().map(as_number).collect(),
+        other => bail!("需要陣列，但得到 {}", type_name(other)),
+    }
+}
+
+/// 取得 {x, y} 物件或 [x, y] 陣列的座標
+fn as_point(value: &Value) -> Result<(f64, f64)> {
+    match value {
+        Value::Object(map) => {
+            let x = map.get("x").ok_or_else(|| anyhow!("座標缺少 x"))?;
+            let y = map.get("y").ok_or_else(|| anyhow!("座標缺少 y"))?;
+            Ok((as_number(x)?, as_number(y)?))
+        }
+        Value::Array(items) if items.len() == 2 => Ok((as_number(&items[0])?, as_number(&items[1])?)),
+        other => bail!("需要座標，但得到 {}", type_name(other)),
+    }
+}
+
+fn call_function(name: &str, args: &[Value]) -> Result<Value> {
+    let arg = |i: usize| args.get(i).ok_or_else(|| anyhow!("{}() 缺少第 {} 個參數", name, i + 1));
+
+    match name {
+        "count" | "len" => match arg(0)? {
+            Value::Array(items) => Ok(json!(items.len())),
+            Value::Object(map) => Ok(json!(map.len())),
+            Value::String(s) => Ok(json!(s.chars().count())),
+            other => bail!("{}() 不支援 {}", name, type_name(other)),
+        },
+        "sum" => Ok(json!(numbers_of(arg(0)?)?.iter().sum::<f64>())),
+        "avg" => {
+            let values = numbers_of(arg(0)?)?;
+            if values.is_empty() {
+                return Ok(Value::Null);
+            }
+            Ok(json!(values.iter().sum::<f64>() / values.len() as f64))
+        }
+        "min" | "max" => {
+            // 單一陣列參數時取陣列內的極值，否則比較所有參數
+            let values = if args.len() == 1 {
+                numbers_of(arg(0)?)?
+            } else {
+                args.iter().map(as_number).collect::<Result<Vec<_>>>()?
+            };
+            let result = if name == "min" {
+                values.into_iter().reduce(f64::min)
+            } else {
+                values.into_iter().reduce(f64::max)
+            };
+            Ok(result.map(|v| json!(v)).unwrap_or(Value::Null))
+        }
+        "abs" => Ok(json!(as_number(arg(0)?)?.abs())),
+        "sqrt" => Ok(json!(as_number(arg(0)?)?.sqrt())),
+        "round" => Ok(json!(as_number(arg(0)?)?.round())),
+        "first" | "last" => match arg(0)? {
+            Value::Array(items) => {
+                let item = if name == "first" { items.first() } else { items.last() };
+                Ok(item.cloned().unwrap_or(Value::Null))
+            }
+            other => bail!("{}() 需要陣列，但得到 {}", name, type_name(other)),
+        },
+        "any" => match arg(0)? {
+            Value::Array(items) => Ok(Value::Bool(items.iter().any(truthy))),
+            other => Ok(Value::Bool(truthy(other))),
+        },
+        "all" => match arg(0)? {
+            Value::Array(items) => Ok(Value::Bool(items.iter().all(truthy))),
+            other => Ok(Value::Bool(truthy(other))),
+        },
+        "distance" => {
+            let (ax, ay) = as_point(arg(0)?)?;
+            let (bx, by) = as_point(arg(1)?)?;
+            Ok(json!(((ax - bx).powi(2) + (ay - by).powi(2)).sqrt()))
+        }
+        _ => bail!("未知的函數: {}", name),
+    }
+}
+
+fn binary(op: BinaryOp, lhs: &Value, rhs: &Value) -> Result<Value> {
+    match op {
+        BinaryOp::Eq => return Ok(Value::Bool(values_equal(lhs, rhs))),
+        BinaryOp::Ne => return Ok(Value::Bool(!values_equal(lhs, rhs))),
+        BinaryOp::And => return Ok(Value::Bool(truthy(rhs))),
+        BinaryOp::Or => return Ok(Value::Bool(truthy(rhs))),
+        BinaryOp::Add => {
+            if let (Value::String(a), Value::String(b)) = (lhs, rhs) {
+                return Ok(Value::String(format!("{}{}", a, b)));
+            }
+        }
+        _ => {}
+    }
+
+    let a = as_number(lhs)?;
+    let b = as_number(rhs)?;
+    let result = match op {
+        BinaryOp::Add => json!(a + b),
+        BinaryOp::Sub => json!(a - b),
+        BinaryOp::Mul => json!(a * b),
+        BinaryOp::Div => {
+            if b == 0.0 {
+                bail!("除以零");
+            }
+            json!(a / b)
+        }
+        BinaryOp::Rem => {
+            if b == 0.0 {
+                bail!("除以零");
+            }
+            json!(a % b)
+        }
+        BinaryOp::Lt => Value::Bool(a < b),
+        BinaryOp::Le => Value::Bool(a <= b),
+        BinaryOp::Gt => Value::Bool(a > b),
+        BinaryOp::Ge => Value::Bool(a >= b),
+        BinaryOp::Eq | BinaryOp::Ne | BinaryOp::And | BinaryOp::Or => unreachable!(),
+    };
+    Ok(result)
+}
+
+fn values_equal(lhs: &Value, rhs: &Value) -> bool {
+    match (lhs, rhs) {
+        (Value::Number(_), Value::Number(_)) => as_number(lhs).ok() == as_number(rhs).ok(),
+        _ => lhs == rhs,
+    }
+}
+
+/// 將值轉為數值
+pub fn as_number(value: &Value) -> Result<f64> {
+    match value {
+        Value::Number(n) => n.as_f64().ok_or_else(|| anyhow!("無效的數值")),
+        Value::Bool(b) => Ok(if *b { 1.0 } else { 0.0 }),
+        other => bail!("需要數值，但得到 {}", type_name(other)),
+    }
+}
+
+/// 值的真假判斷
+pub fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().map(|v| v != 0.0).unwrap_or(false),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "布林值",
+        Value::Number(_) => "數值",
+        Value::String(_) => "字串",
+        Value::Array(_) => "陣列",
+        Value::Object(_) => "物件",
+    }
+}
+
+/// 建立遊戲狀態的查詢快照
+///
+/// 根層級名稱：`player`、`players`、`entities`、`summons`、`sync_errors`、`schema_errors`
+pub fn state_context(state: &GameState) -> Value {
+    let player = &state.local_player;
+
+    let mut players: Vec<&crate::mqtt_handler::PlayerState> = state.other_players.values().collect();
+    players.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut entities: Vec<&crate::game_state::Entity> = state.entities.values().collect();
+    entities.sort_by_key(|e| e.id);
+
+    json!({
+        "player": {
+            "name": player.name,
+            "hero_type": player.hero_type,
+            "position": { "x": player.position.x, "y": player.position.y },
+            "health": [player.health.0, player.health.1],
+            "mana": [player.mana.0, player.mana.1],
+            "level": player.level,
+            "experience": player.experience,
+            "gold": player.gold,
+            "abilities": player.abilities.iter().map(|a| json!({
+                "id": a.ability_id,
+                "level": a.level,
+                "cooldown": a.cooldown_remaining,
+                "available": a.is_available,
+            })).collect::<Vec<_>>(),
+            "items": player.items.iter().map(|i| json!({
+                "id": i.item_id,
+                "name": i.name,
+                "slot": i.slot,
+                "charges": i.charges,
+                "cooldown": i.cooldown_remaining,
+            })).collect::<Vec<_>>(),
+        },
+        "players": players.iter().map(|p| json!({
+            "name": p.name,
+            "hero_type": p.hero_type,
+            "position": { "x": p.position.0, "y": p.position.1 },
+            "health": [p.health.0, p.health.1],
+        })).collect::<Vec<_>>(),
+        "entities": entities.iter().map(|e| {
+            let (kind, subtype) = match &e.entity_type {
+                EntityType::Player(name) => ("player", Some(name.clone())),
+                EntityType::Summon(unit) => ("summon", Some(unit.clone())),
+                EntityType::Projectile => ("projectile", None),
+                EntityType::Effect => ("effect", None),
+                EntityType::Creep => ("creep", None),
+                EntityType::Tower => ("tower", None),
+            };
+            json!({
+                "id": e.id,
+                "type": kind,
+                "subtype": subtype,
+                "position": { "x": e.position.x, "y": e.position.y },
+                "health": [e.health.0, e.health.1],
+                "owner": e.owner,
+            })
+        }).collect::<Vec<_>>(),
+        "summons": player.summons.iter().map(|s| json!({
+            "id": s.id,
+            "unit_type": s.unit_type,
+            "position": { "x": s.position.x, "y": s.position.y },
+            "health": [s.health.0, s.health.1],
+        })).collect::<Vec<_>>(),
+        "sync_errors": state.sync_errors,
+        "schema_errors": state.schema_errors,
+    })
+}
+
+/// 將額外的根層級名稱加入查詢快照（例如自訂指標的最新值）
+pub fn extend_context(ctx: &mut Value, name: &str, value: Value) {
+    if let Value::Object(map) = ctx {
+        map.insert(name.to_string(), value);
+    } else {
+        let mut map = Map::new();
+        map.insert(name.to_string(), value);
+        *ctx = Value::Object(map);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx() -> Value {
+        json!({
+            "player": { "health": [50.0, 200.0], "position": { "x": 0.0, "y": 0.0 } },
+            "players": [ { "name": "B", "position": { "x": 3.0, "y": 4.0 } } ],
+            "entities": [
+                { "id": 1, "type": "summon", "health": [10.0, 10.0] },
+                { "id": 2, "type": "projectile", "health": [0.0, 0.0] },
+            ],
+        })
+    }
+
+    fn eval(source: &str) -> Value {
+        Expr::parse(source).unwrap().eval(&ctx()).unwrap()
+    }
+
+    #[test]
+    fn arithmetic_and_precedence() {
+        assert_eq!(eval("1 + 2 * 3"), json!(7.0));
+        assert_eq!(eval("(1 + 2) * 3"), json!(9.0));
+        assert_eq!(eval("-2 + 5 % 3"), json!(0.0));
+    }
+
+    #[test]
+    fn tuple_field_access() {
+        assert_eq!(eval("player.health.0 / player.health.1"), json!(0.25));
+    }
+
+    #[test]
+    fn array_projection_and_aggregates() {
+        assert_eq!(eval("entities.count()"), json!(2));
+        assert_eq!(eval("entities.health.0.sum()"), json!(10.0));
+        assert_eq!(eval("entities[-1].id"), json!(2));
+    }
+
+    #[test]
+    fn filter_by_element_fields() {
+        assert_eq!(eval("entities[type==\"summon\"].count()"), json!(1));
+        assert_eq!(eval("entities[health.0 > 5 || id == 2].id"), json!([1, 2]));
+        assert_eq!(eval("entities[type==\"effect\"].first()"), Value::Null);
+        assert_eq!(eval("player.health[it > 100].count()"), json!(1));
+    }
+
+    #[test]
+    fn distance_between_points() {
+        assert_eq!(eval("distance(player.position, players[0].position)"), json!(5.0));
+    }
+
+    #[test]
+    fn comparisons_and_logic() {
+        assert_eq!(eval("player.health.0 < 100 && entities.count() == 2"), json!(true));
+        assert_eq!(eval("!(1 > 2) || missing"), json!(true));
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        assert!(Expr::parse("1 +").is_err());
+        assert!(Expr::parse("player.").is_err());
+        assert!(Expr::parse("unknown").unwrap().eval(&ctx()).is_err());
+        assert!(Expr::parse("1 / 0").unwrap().eval(&ctx()).is_err());
+    }
+}
@@ -1,15 +1,57 @@
 /// 遊戲客戶端核心
 /// 
 /// 模擬真實遊戲客戶端，處理與 omobab 後端的連接和通信
-use rumqttc::{AsyncClient, MqttOptions, QoS, Event, Packet};
+use bytes::Bytes;
+use rumqttc::{AsyncClient, LastWill, MqttOptions, QoS, Event, Outgoing, Packet};
 use std::time::Duration;
+use std::collections::VecDeque;
 use tokio::time::sleep;
 use log::{info, warn, error, debug};
-use anyhow::Result;
+use anyhow::{Context, Result};
+use vek::Vec2;
 
-use crate::mqtt_handler::MqttHandler;
-use crate::game_state::GameState;
+use crate::mqtt_handler::{MqttHandler, WireFormat};
+use crate::payload::BinaryDecoders;
+use crate::perf::{PerfGates, SharedPerf};
+use crate::key_macros::MacroCommand;
+use crate::mqtt_stats::MqttStats;
+use crate::game_state::{GameState, Team};
 use crate::player::PlayerSimulator;
+use crate::bookmarks::{Bookmark, BookmarkStore, DEFAULT_BOOKMARK_FILE};
+use crate::config::{AlertsConfig, AnomalyCaptureConfig, CameraConfig, CoordinatesConfig, CreditConfig, DeathRecapConfig, DummyConfig, ExpectationsConfig, HeartbeatConfig, HumanizeConfig, BotConfig, PredictionConfig, ShopConfig, TimeSyncConfig, StateHashConfig, LivenessConfig, MacrosConfig, MapConfig, MetricsConfig, OfflineQueueConfig, RateLimitConfig, RecordingConfig, RpcConfig, SandboxConfig, SanityConfig, ScreenUpdatesConfig, QosConfig, TargetingConfig, TlsConfig, BrokerEndpoint};
+use crate::latency::SharedRtt;
+use crate::heartbeat::{HeartbeatStats, SharedHeartbeat};
+use crate::cast_stats::{CastStats, SharedCastStats};
+use crate::liveness::Liveness;
+use crate::shutdown::{Shutdown, SHUTDOWN_GRACE};
+use crate::version::{BackendVersion, SharedBackendVersion};
+use crate::camera::{Camera, CameraMode, CameraView, SharedCamera};
+use crate::demo::{DemoReport, DemoSequence, StepOutcome};
+use crate::dummy::DpsReport;
+use crate::map_editor::TerrainMap;
+use crate::combo::{ComboReport, ComboStep, ComboTracker};
+use crate::humanize::Humanizer;
+use crate::behavior::BehaviorTree;
+use crate::shop::ShopCatalog;
+use crate::patrol::PatrolRoute;
+use crate::prediction::MovementPredictor;
+use crate::topics::TopicScheme;
+use crate::protocol_errors::ProtocolError;
+use crate::expectations::{ExpectationChecker, Prediction};
+use crate::reconnect::{Backoff, BrokerRotation, SharedLink};
+use crate::rate_limit::{RateLimitStats, RateLimiter, SharedRateLimiter};
+use crate::recording::{RecordDirection, TrafficRecorder};
+use crate::targeting::VelocityTracker;
+use crate::coords::CoordinateTransform;
+use crate::alerts::AlertMonitor;
+#[cfg(feature = "metrics")]
+use crate::metrics::MetricsSampler;
+use crate::sanity::SanityMonitor;
+use crate::anomaly_capture::{AnomalyEvidence, AnomalyWatch};
+use crate::sandbox::{Sandbox, SandboxAction};
+use crate::rpc::{response_id, RequestManager, RpcResponse, RpcStats};
+use crate::time_sync::{unix_ms, ServerClock};
+use crate::state_hash::FrameHash;
 
 /// 遊戲客戶端配置
 #[derive(Debug, Clone)]
@@ -19,6 +61,45 @@ pub struct GameClientConfig {
     pub client_id: String,
     pub player_name: String,
     pub hero_type: String,
+    /// 進入遊戲時要求的陣營（未設置時由後端分配）
+    pub team: Option<Team>,
+    /// 自動遊戲的亂數種子（未設置時隨機產生，仍會記錄在操作歷史中）
+    pub seed: Option<u64>,
+    /// 試運行：只記錄將發送的玩家操作，不實際發佈
+    pub dry_run: bool,
+    /// 唯讀：禁止所有玩家操作，只保留觀看與遙測
+    pub read_only: bool,
+    /// 觀戰的玩家：訂閱該玩家的畫面與狀態主題並以該玩家為中心顯示，不發送任何操作
+    pub spectate: Option<String>,
+    /// 訊息編碼格式
+    pub wire_format: WireFormat,
+    /// broker 認證使用者名稱（未設置時不送出認證）
+    pub username: Option<String>,
+    /// broker 認證密碼
+    pub password: Option<String>,
+    /// MQTT over TLS（未啟用時使用明文連接）
+    pub tls: TlsConfig,
+    /// 各類主題的 QoS 等級
+    pub qos: QosConfig,
+    /// 遊戲主題的命名空間
+    pub topics: TopicScheme,
+    /// 備援 broker（主要 broker 無法連線時依序嘗試）
+    pub failover: Vec<BrokerEndpoint>,
+    /// 各頻道二進位負載的解碼器
+    pub binary_decoders: BinaryDecoders,
+}
+
+impl GameClientConfig {
+    /// 所有 broker，主要 broker 在最前面
+    pub fn brokers(&self) -> Vec<BrokerEndpoint> {
+        let primary = BrokerEndpoint { host: self.server_ip.clone(), port: self.server_port };
+        std::iter::once(primary).chain(self.failover.iter().cloned()).collect()
+    }
+    
+    /// 畫面與狀態以哪個玩家為中心（觀戰時為被觀戰的玩家）
+    pub fn watched_player(&self) -> &str {
+        self.spectate.as_deref().unwrap_or(&self.player_name)
+    }
 }
 
 impl Default for GameClientConfig {
@@ -29,10 +110,147 @@ impl Default for GameClientConfig {
             client_id: "omobaf_player".to_string(),
             player_name: "TestPlayer".to_string(),
             hero_type: "saika_magoichi".to_string(),
+            team: None,
+            seed: None,
+            dry_run: false,
+            read_only: false,
+            spectate: None,
+            wire_format: WireFormat::default(),
+            username: None,
+            password: None,
+            tls: TlsConfig::default(),
+            qos: QosConfig::default(),
+            topics: TopicScheme::default(),
+            failover: Vec::new(),
+            binary_decoders: BinaryDecoders::default(),
         }
     }
 }
 
+/// 遊戲相關的訂閱主題（連線與重連後都會訂閱）
+fn game_topics(config: &GameClientConfig) -> Vec<(String, QoS)> {
+    let (topics, player_name, qos) = (&config.topics, config.player_name.as_str(), &config.qos);
+    let broadcasts = QosConfig::qos(qos.broadcasts);
+    let responses = QosConfig::qos(qos.responses);
+    vec![
+        // 遊戲狀態主題 (實際後端使用的主題)
+        (topics.broadcast(), broadcasts),
+        // 玩家特定主題
+        (topics.any_player("send"), broadcasts),
+        // 畫面狀態回應主題 (使用 player_name 而不是 client_id，觀戰時訂閱被觀戰玩家的畫面)
+        (topics.player(config.watched_player(), "screen_response"), responses),
+        // 能力測試回應主題
+        ("ability_test/response".to_string(), QoS::AtMostOnce),
+        // 心跳回應主題
+        (topics.player(player_name, "pong"), QosConfig::qos(qos.heartbeat)),
+        // 版本握手回應主題
+        (topics.player(player_name, "hello_ack"), responses),
+        // 時間同步回應主題
+        (topics.player(player_name, "time_sync_ack"), QosConfig::qos(qos.heartbeat)),
+        // 後端請求的回應主題（依請求 ID 對應）
+        (topics.player(player_name, "response"), responses),
+        // 技能施放結果主題
+        (topics.player(player_name, "cast_result"), responses),
+    ]
+}
+
+/// pong 訊息中的序號（`{"d": {"seq": n}}` 或 `{"seq": n}`）
+fn pong_seq(format: WireFormat, payload: &[u8]) -> Option<u64> {
+    let value: serde_json::Value = format.decode(payload).ok()?;
+    value.pointer("/d/seq").or_else(|| value.get("seq"))?.as_u64()
+}
+
+/// 發佈訊息並記入收發統計，錄製中時同時寫入錄製檔
+async fn publish(client: &AsyncClient, recorder: Option<&TrafficRecorder>, stats: &MqttStats, topic: &str, qos: QoS, payload: Vec<u8>) -> Result<(), rumqttc::ClientError> {
+    let payload = Bytes::from(payload);
+    if let Some(recorder) = recorder {
+        recorder.record(RecordDirection::Outgoing, topic, &payload);
+    }
+    stats.record_sent(topic, &payload);
+    client.publish_bytes(topic, qos, false, payload).await
+}
+
+/// 把移動參數統一為 `target_x`/`target_y`（也接受 `x`/`y`），模擬器、移動預測與後端都只看這個形狀
+fn normalize_move_params(params: &mut serde_json::Value) {
+    let Some(object) = params.as_object_mut() else {
+        return;
+    };
+    if object.contains_key("target_x") || !(object.contains_key("x") && object.contains_key("y")) {
+        return;
+    }
+    let (x, y) = (object.remove("x").unwrap(), object.remove("y").unwrap());
+    object.insert("target_x".to_string(), x);
+    object.insert("target_y".to_string(), y);
+}
+
+/// 重新連上後恢復會話：重新訂閱主題、重新握手、重送 enter_game 並補送暫存的操作
+async fn resume_session(client: &AsyncClient, topics: &[(String, QoS)], qos: QosConfig, hello: (String, Vec<u8>), link: SharedLink, recorder: Option<TrafficRecorder>, stats: MqttStats) -> Result<()> {
+    for (topic, qos) in topics {
+        client.subscribe(topic, *qos).await?;
+    }
+    info!("已重新訂閱 {} 個主題", topics.len());
+    publish(client, recorder.as_ref(), &stats, &hello.0, QosConfig::qos(qos.responses), hello.1).await?;
+    let enter_game = link.lock().unwrap().enter_game.clone();
+    if let Some((topic, message)) = enter_game {
+        publish(client, recorder.as_ref(), &stats, &topic, QosConfig::qos(qos.actions), message).await?;
+        info!("已重新發送 enter_game");
+    }
+    let flushed = flush_outbound(client, recorder.as_ref(), &stats, QosConfig::qos(qos.actions), &link).await?;
+    if flushed > 0 {
+        info!("📤 已補送 {} 個斷線期間的操作", flushed);
+    }
+    Ok(())
+}
+
+/// 依序補送暫存的操作，返回送出的數量（送出後才移出佇列，補送期間的新操作仍排在後面，維持發送順序）
+async fn flush_outbound(client: &AsyncClient, recorder: Option<&TrafficRecorder>, stats: &MqttStats, qos: QoS, link: &SharedLink) -> Result<usize> {
+    let mut flushed = 0;
+    loop {
+        let next = link.lock().unwrap().outbound.front().cloned();
+        let Some((topic, message)) = next else {
+            break;
+        };
+        publish(client, recorder, stats, &topic, qos, message).await?;
+        link.lock().unwrap().mark_sent();
+        flushed += 1;
+    }
+    Ok(flushed)
+}
+
+/// 唯讀模式下仍允許發送的操作（只影響觀看範圍，觀戰時也不發送）
+const READ_ONLY_ALLOWED_ACTIONS: &[&str] = &["update_viewport"];
+
+/// 連段量測時同步伺服器狀態的間隔（決定確認延遲的解析度）
+const COMBO_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// 主動斷線時等待離開訊息送出的最長時間
+const GRACEFUL_LEAVE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 收到的訊息通道容量（訂閱者來不及讀取時丟棄最舊的）
+const INCOMING_BUFFER: usize = 256;
+
+/// 收到的一則訊息（有訂閱者時才轉發）
+#[derive(Debug, Clone)]
+pub struct IncomingMessage {
+    pub topic: String,
+    /// 依傳輸格式解碼後的內容文字
+    pub text: String,
+}
+
+/// 唯讀模式拒絕的操作
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermissionDenied {
+    pub action: String,
+}
+
+impl std::fmt::Display for PermissionDenied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "唯讀模式禁止操作: {}", self.action)
+    }
+}
+
+impl std::error::Error for PermissionDenied {}
+
 /// 遊戲客戶端狀態
 #[derive(Debug, Clone, PartialEq)]
 pub enum ClientState {
@@ -40,9 +258,22 @@ pub enum ClientState {
     Connecting,
     Connected,
     InGame,
+    /// 連線中斷，正在重試（第幾次）
+    Reconnecting(u32),
+    /// 遊戲中但伺服器資料停止更新（已停滯的秒數）
+    Stale(u64),
+    /// 遊戲中但長時間沒有伺服器資料，視為失去伺服器（已停滯的秒數）
+    ServerLost(u64),
     Error(String),
 }
 
+impl ClientState {
+    /// 是否在遊戲中（伺服器資料停滯時仍在遊戲中）
+    pub fn in_game(&self) -> bool {
+        matches!(self, ClientState::InGame | ClientState::Stale(_) | ClientState::ServerLost(_))
+    }
+}
+
 /// 遊戲客戶端
 pub struct GameClient {
     config: GameClientConfig,
@@ -52,19 +283,106 @@ pub struct GameClient {
     player_simulator: PlayerSimulator,
     client: Option<AsyncClient>,
     shared_game_state: Option<std::sync::Arc<tokio::sync::Mutex<GameState>>>,
-    screen_request_handle: Option<tokio::task::JoinHandle<()>>,
+    bookmarks: BookmarkStore,
+    #[cfg(feature = "metrics")]
+    metrics: MetricsSampler,
+    sanity: SanityMonitor,
+    /// 模擬器預測與伺服器結果的比對
+    expectations: ExpectationChecker,
+    /// 各技能的施放嘗試與後端結果
+    cast_stats: SharedCastStats,
+    /// 實時視圖的異常自動擷取
+    anomaly_watch: AnomalyWatch,
+    offline_queue: OfflineQueueConfig,
+    /// 離線佇列保存檔中上次未送出的操作（進入遊戲後補送）
+    restored_actions: VecDeque<(String, Vec<u8>)>,
+    alerts: AlertMonitor,
+    coordinates: CoordinateTransform,
+    targeting: TargetingConfig,
+    macros: MacrosConfig,
+    /// 訓練假人配置
+    dummy: DummyConfig,
+    /// 練習沙盒
+    sandbox: Sandbox,
+    /// 流量錄製配置
+    recording: RecordingConfig,
+    /// 錄製中的流量錄製器（第一次連線時開啟，重連與重新連線沿用同一個檔案）
+    recorder: Option<TrafficRecorder>,
+    /// 自動遊戲輸入擬人化
+    humanizer: Humanizer,
+    /// 自動遊戲的行為樹
+    behavior: BehaviorTree,
+    /// 行為樹配置（恢復會話時重新載入設定檔）
+    bot: BotConfig,
+    /// 商店目錄
+    shop: ShopCatalog,
+    /// 本地英雄的移動預測
+    movement: MovementPredictor,
+    /// 畫面請求是否要求增量更新
+    delta_screen: bool,
+    /// 其他單位的速度估計
+    velocities: VelocityTracker,
+    /// 與後端的往返延遲
+    rtt: SharedRtt,
+    /// 與畫面請求循環、事件循環共享的後端請求表
+    requests: RequestManager,
+    /// 後端請求等待回應的時限
+    rpc_timeout: Duration,
+    /// 玩家操作是否帶上請求 ID 以記錄操作確認延遲
+    track_actions: bool,
+    /// 與事件循環、請求表共享的效能指標
+    perf: SharedPerf,
+    /// 收到的訊息（腳本斷言等需要檢查原始訊息時訂閱）
+    incoming: tokio::sync::broadcast::Sender<IncomingMessage>,
+    /// 連線心跳
+    heartbeat: SharedHeartbeat,
+    heartbeat_config: HeartbeatConfig,
+    time_sync_config: TimeSyncConfig,
+    state_hash_config: StateHashConfig,
+    /// 與狀態雜湊循環共享的最近一幀雜湊
+    last_state_hash: std::sync::Arc<std::sync::Mutex<Option<FrameHash>>>,
+    liveness_config: LivenessConfig,
+    /// 失去伺服器後最近一次自動恢復會話的時間
+    last_restore: Option<std::time::Instant>,
+    /// 與事件循環共享的連線狀態
+    link: SharedLink,
+    /// 後端在握手中回報的版本
+    backend_version: SharedBackendVersion,
+    /// 鏡頭（決定視野更新與畫面請求的中心與範圍）
+    camera: Camera,
+    /// 與畫面請求循環共享的鏡頭視野
+    shared_camera: SharedCamera,
+    /// 與畫面請求循環共享的發佈限流器
+    rate_limiter: SharedRateLimiter,
+    /// 背景任務（MQTT 事件循環、心跳與畫面請求循環），斷線時依序停止
+    tasks: Shutdown,
+    /// 重連期間暫存的原本狀態，連線恢復後還原
+    resume_state: Option<ClientState>,
+    /// 會話標籤（背景任務的日誌會帶上此標籤）
+    session_tag: Option<String>,
+    /// 連線時向 broker 註冊的遺囑（客戶端異常斷線時由 broker 代為發佈）
+    will: Option<LastWill>,
 }
 
 impl GameClient {
     /// 創建新的遊戲客戶端
     pub fn new(config: GameClientConfig) -> Self {
-        let mqtt_handler = MqttHandler::new();
-        let game_state = GameState::new(config.player_name.clone(), config.hero_type.clone());
-        let player_simulator = PlayerSimulator::new(config.player_name.clone(), config.hero_type.clone());
+        let backend_version = SharedBackendVersion::default();
+        let mqtt_handler = MqttHandler::with_format(config.wire_format)
+            .with_topics(config.topics.clone())
+            .with_binary_decoders(config.binary_decoders.clone())
+            .with_backend_version(backend_version.clone());
+        // 觀戰時以被觀戰的玩家作為本地玩家，畫面與狀態更新都以該玩家為中心
+        let game_state = GameState::new(config.watched_player().to_string(), config.hero_type.clone());
+        let perf = SharedPerf::default();
+        let mut player_simulator = PlayerSimulator::new(config.player_name.clone(), config.hero_type.clone());
+        if let Some(seed) = config.seed {
+            player_simulator = player_simulator.with_seed(seed);
+        }
         
         info!("遊戲客戶端已創建 - 玩家: {}, 英雄: {}", config.player_name, config.hero_type);
         
-        Self {
+        let mut client = Self {
             config,
             state: ClientState::Disconnected,
             mqtt_handler,
@@ -72,8 +390,87 @@ impl GameClient {
             player_simulator,
             client: None,
             shared_game_state: None,
-            screen_request_handle: None,
+            bookmarks: BookmarkStore::new(DEFAULT_BOOKMARK_FILE),
+            #[cfg(feature = "metrics")]
+            metrics: MetricsSampler::default(),
+            sanity: SanityMonitor::new(SanityConfig::default()),
+            expectations: ExpectationChecker::default(),
+            cast_stats: SharedCastStats::default(),
+            anomaly_watch: AnomalyWatch::default(),
+            offline_queue: OfflineQueueConfig::default(),
+            restored_actions: VecDeque::new(),
+            alerts: AlertMonitor::default(),
+            coordinates: CoordinateTransform::default(),
+            targeting: TargetingConfig::default(),
+            macros: MacrosConfig::default(),
+            dummy: DummyConfig::default(),
+            sandbox: Sandbox::default(),
+            recording: RecordingConfig::default(),
+            recorder: None,
+            humanizer: Humanizer::default(),
+            behavior: BehaviorTree::default(),
+            bot: BotConfig::default(),
+            shop: ShopCatalog::default(),
+            movement: MovementPredictor::new(PredictionConfig::default()),
+            delta_screen: false,
+            velocities: VelocityTracker::default(),
+            rtt: SharedRtt::default(),
+            requests: RequestManager::default().with_perf(perf.clone()),
+            rpc_timeout: Duration::from_millis(RpcConfig::default().timeout_ms),
+            track_actions: RpcConfig::default().track_actions,
+            perf,
+            incoming: tokio::sync::broadcast::channel(INCOMING_BUFFER).0,
+            heartbeat: SharedHeartbeat::default(),
+            heartbeat_config: HeartbeatConfig::default(),
+            time_sync_config: TimeSyncConfig::default(),
+            state_hash_config: StateHashConfig::default(),
+            last_state_hash: Default::default(),
+            liveness_config: LivenessConfig::default(),
+            last_restore: None,
+            link: SharedLink::default(),
+            backend_version,
+            camera: Camera::default(),
+            shared_camera: SharedCamera::default(),
+            rate_limiter: SharedRateLimiter::default(),
+            tasks: Shutdown::default(),
+            resume_state: None,
+            session_tag: None,
+            will: None,
+        };
+        
+        // 預設遺囑為 leave_game，後端即使沒收到正常離開也能清理斷線的玩家
+        let (topic, message) = client.action_message("leave_game", serde_json::json!({}));
+        match client.config.wire_format.encode(&message) {
+            Ok(payload) => client.set_will(topic, payload),
+            Err(e) => warn!("無法編碼遺囑訊息: {}", e),
         }
+        client
+    }
+    
+    /// 設置連線時註冊的遺囑（需在 connect 前設置；測試可藉此驗證後端對斷線玩家的清理）
+    pub fn set_will(&mut self, topic: impl Into<String>, payload: impl Into<Vec<u8>>) {
+        let payload: Vec<u8> = payload.into();
+        self.will = Some(LastWill::new(topic, payload, QosConfig::qos(self.config.qos.actions), false));
+    }
+    
+    /// 切換唯讀模式
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.config.read_only = read_only;
+    }
+    
+    /// 切換試運行模式
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.config.dry_run = dry_run;
+    }
+    
+    /// 會話標籤
+    pub fn session_tag(&self) -> Option<String> {
+        self.session_tag.clone()
+    }
+    
+    /// 設置會話標籤（需在 connect 前設置才會套用到背景任務）
+    pub fn set_session_tag(&mut self, tag: impl Into<String>) {
+        self.session_tag = Some(tag.into());
     }
     
     /// 連接到遊戲服務器
@@ -81,33 +478,147 @@ impl GameClient {
         info!("正在連接到遊戲服務器 {}:{}", self.config.server_ip, self.config.server_port);
         self.state = ClientState::Connecting;
         
-        let mut mqttoptions = MqttOptions::new(
-            &self.config.client_id,
-            &self.config.server_ip,
-            self.config.server_port
-        );
-        mqttoptions.set_keep_alive(Duration::from_secs(30));
-        mqttoptions.set_clean_session(true);
+        let brokers = self.config.brokers();
+        let options = brokers.iter().enumerate()
+            .map(|(index, broker)| self.mqtt_options(broker, index == 0))
+            .collect::<Result<Vec<_>>>()?;
+        if brokers.len() > 1 {
+            info!("🔀 備援 broker: {}", brokers[1..].iter().map(ToString::to_string).collect::<Vec<_>>().join(", "));
+        }
+        
+        if self.recorder.is_none() {
+            if let Some(path) = &self.recording.path {
+                self.recorder = Some(TrafficRecorder::open(std::path::Path::new(path))?);
+                info!("⏺️ 錄製 MQTT 流量到 {}", path);
+            }
+        }
         
-        let (client, mut connection) = AsyncClient::new(mqttoptions, 10);
+        let (client, mut connection) = AsyncClient::new(options[0].clone(), 10);
         self.client = Some(client.clone());
+        let client_for_heartbeat = client.clone();
         
-        // 訂閱遊戲相關主題
+        // 訂閱遊戲相關主題並送出版本握手
         self.subscribe_game_topics(&client).await?;
+        let hello_topic = self.config.topics.player(&self.config.player_name, "hello");
+        let hello = self.config.wire_format.encode(&crate::version::hello_message(&self.config.player_name))?;
+        publish(&client, self.recorder.as_ref(), self.mqtt_handler.stats(), &hello_topic, QosConfig::qos(self.config.qos.responses), hello.clone()).await?;
         
         // 啟動 MQTT 事件處理循環 - 使用 Arc<Mutex> 來共享遊戲狀態
         let mqtt_handler = self.mqtt_handler.clone();
         let game_state = std::sync::Arc::new(tokio::sync::Mutex::new(self.game_state.clone()));
         let game_state_clone = game_state.clone();
+        let rtt = self.rtt.clone();
+        let perf = self.perf.clone();
+        let requests = self.requests.clone();
+        let screen_response_topic = self.config.topics.player(&self.config.player_name, "screen_response");
+        let response_topic = self.config.topics.player(&self.config.player_name, "response");
+        self.link = SharedLink::default();
+        let link = self.link.clone();
+        let topics = game_topics(&self.config);
+        let qos = self.config.qos;
+        let session_tag = self.session_tag.clone();
+        let heartbeat = self.heartbeat.clone();
+        let pong_topic = self.config.topics.player(&self.config.player_name, "pong");
+        let hello_ack_topic = self.config.topics.player(&self.config.player_name, "hello_ack");
+        let time_sync_ack_topic = self.config.topics.player(&self.config.player_name, "time_sync_ack");
+        let cast_result_topic = self.config.topics.player(&self.config.player_name, "cast_result");
+        let cast_stats = self.cast_stats.clone();
+        *self.backend_version.lock().unwrap() = None;
+        let backend_version = self.backend_version.clone();
+        let wire_format = self.config.wire_format;
+        let recorder = self.recorder.clone();
+        let incoming = self.incoming.clone();
+        let stats = self.mqtt_handler.stats().clone();
+        let mut rotation = BrokerRotation::new(options.len());
         
         // 保存共享的遊戲狀態引用以供後續使用
         self.shared_game_state = Some(game_state);
         
-        // 啟動 MQTT 事件處理循環
-        tokio::spawn(async move {
+        // 啟動 MQTT 事件處理循環（rumqttc 在 poll 出錯後的下一次 poll 會重新連線）
+        self.tasks.spawn("mqtt_loop", self.session_tag.clone(), |shutdown| async move {
+            let mut backoff = Backoff::default();
             loop {
-                match connection.poll().await {
+                let event = tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    event = connection.poll() => event,
+                };
+                match event {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) if backoff.attempt() > 0 => {
+                        info!("🔌 已重新連接到 MQTT broker {}（重試 {} 次）", brokers[rotation.current()], backoff.attempt());
+                        backoff.reset();
+                        link.lock().unwrap().reconnecting = None;
+                        // 在另一個任務中恢復會話，避免請求佇列已滿時阻塞事件循環
+                        let client = client.clone();
+                        let topics = topics.clone();
+                        let hello = (hello_topic.clone(), hello.clone());
+                        let link = link.clone();
+                        let recorder = recorder.clone();
+                        let stats = stats.clone();
+                        crate::session_tag::spawn(session_tag.clone(), async move {
+                            if let Err(e) = resume_session(&client, &topics, qos, hello, link, recorder, stats).await {
+                                error!("恢復會話失敗: {}", e);
+                            }
+                        });
+                    },
                     Ok(Event::Incoming(Packet::Publish(publish))) => {
+                        if let Some(recorder) = &recorder {
+                            recorder.record(RecordDirection::Incoming, &publish.topic, &publish.payload);
+                        }
+                        if incoming.receiver_count() > 0 {
+                            let text = wire_format.describe(&publish.payload).into_owned();
+                            let _ = incoming.send(IncomingMessage { topic: publish.topic.clone(), text });
+                        }
+                        if publish.topic == pong_topic {
+                            if let Some(seq) = pong_seq(wire_format, &publish.payload) {
+                                heartbeat.lock().unwrap().observe_pong(seq, std::time::Instant::now());
+                            }
+                            continue;
+                        }
+                        if publish.topic == time_sync_ack_topic {
+                            match crate::time_sync::parse_ack(wire_format, &publish.payload) {
+                                Some((client_time, server_time)) => {
+                                    let now = unix_ms(std::time::SystemTime::now());
+                                    game_state_clone.lock().await.server_clock.observe(client_time, server_time, now);
+                                }
+                                None => warn!("無法解析時間同步回應: {}", wire_format.describe(&publish.payload)),
+                            }
+                            continue;
+                        }
+                        if publish.topic == cast_result_topic {
+                            match crate::cast_stats::parse(wire_format, &publish.payload) {
+                                Some(result) => {
+                                    cast_stats.lock().unwrap().resolve(&result, std::time::Instant::now());
+                                }
+                                None => warn!("無法解析施放結果: {}", wire_format.describe(&publish.payload)),
+                            }
+                            continue;
+                        }
+                        if publish.topic == hello_ack_topic {
+                            match BackendVersion::parse(wire_format, &publish.payload) {
+                                Some(version) => {
+                                    info!("🤝 後端版本: {} (協議 {:?})", version.version, version.protocol);
+                                    if let Some(warning) = version.protocol_mismatch() {
+                                        warn!("{}", warning.trim());
+                                    }
+                                    *backend_version.lock().unwrap() = Some(version);
+                                }
+                                None => warn!("無法解析後端版本回應: {}", wire_format.describe(&publish.payload)),
+                            }
+                            continue;
+                        }
+                        if publish.topic == screen_response_topic || publish.topic == response_topic {
+                            // 只讀出 ID，有人等待這個回應時才解碼完整內容
+                            if let Some(id) = response_id(wire_format, &publish.payload) {
+                                requests.resolve(&id, &publish.topic, || wire_format.decode(&publish.payload).unwrap_or_default());
+                            }
+                            if publish.topic == response_topic {
+                                continue;
+                            }
+                        }
+                        if publish.topic.ends_with("/screen_response") {
+                            rtt.lock().unwrap().observe_response(std::time::Instant::now());
+                            perf.lock().unwrap().record_screen_response(publish.payload.len());
+                        }
                         let mut state = game_state_clone.lock().await;
                         if let Err(e) = mqtt_handler.handle_message(&publish, &mut *state).await {
                             error!("處理 MQTT 訊息失敗: {}", e);
@@ -115,15 +626,39 @@ impl GameClient {
                             debug!("MQTT 訊息處理成功 - 主題: {}", publish.topic);
                         }
                     },
+                    // 主動斷線的 DISCONNECT 已送出，結束事件循環
+                    Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
                     Ok(_) => {},
                     Err(e) => {
-                        error!("MQTT 連接錯誤: {}", e);
-                        sleep(Duration::from_secs(1)).await;
+                        let delay = backoff.next_delay();
+                        link.lock().unwrap().reconnecting = Some(backoff.attempt());
+                        if options.len() > 1 {
+                            let next = rotation.advance();
+                            connection.mqtt_options = options[next].clone();
+                            link.lock().unwrap().broker = next;
+                            warn!("MQTT 連接中斷 ({}): {}，{:.1} 秒後第 {} 次重試，改連 {}",
+                                brokers[(next + brokers.len() - 1) % brokers.len()], e, delay.as_secs_f32(), backoff.attempt(), brokers[next]);
+                        } else {
+                            warn!("MQTT 連接中斷: {}，{:.1} 秒後第 {} 次重試", e, delay.as_secs_f32(), backoff.attempt());
+                        }
+                        tokio::select! {
+                            _ = shutdown.cancelled() => break,
+                            _ = sleep(delay) => {}
+                        }
                     }
                 }
             }
         });
         
+        if self.time_sync_config.enabled {
+            self.start_time_sync(client_for_heartbeat.clone());
+        }
+        if self.state_hash_config.enabled {
+            self.start_state_hash(client_for_heartbeat.clone());
+        }
+        if self.heartbeat_config.enabled {
+            self.start_heartbeat(client_for_heartbeat);
+        }
         
         self.state = ClientState::Connected;
         info!("已成功連接到遊戲服務器");
@@ -131,25 +666,157 @@ impl GameClient {
         Ok(())
     }
     
+    /// 連到指定 broker 的 MQTT 選項（TLS 伺服器名稱只套用到主要 broker）
+    fn mqtt_options(&self, broker: &BrokerEndpoint, primary: bool) -> Result<MqttOptions> {
+        let mut mqttoptions = self.broker_options(&self.config.client_id, broker, primary)?;
+        // 試運行、唯讀與觀戰模式不會對後端送出操作，也不註冊遺囑
+        if let Some(will) = self.will.clone().filter(|_| !self.config.dry_run && !self.config.read_only && self.config.spectate.is_none()) {
+            debug!("註冊遺囑到主題 {}", will.topic);
+            mqttoptions.set_last_will(will);
+        }
+        Ok(mqttoptions)
+    }
+    
+    /// 主題監聽用的 MQTT 選項（連到主要 broker，使用另一個客戶端 ID，不註冊遺囑）
+    pub fn sniffer_options(&self) -> Result<MqttOptions> {
+        self.observer_options("sniff")
+    }
+    
+    /// 只訂閱不操作的觀察連線選項，客戶端 ID 加上指定後綴
+    pub fn observer_options(&self, suffix: &str) -> Result<MqttOptions> {
+        self.endpoint_options(&self.config.brokers()[0], suffix)
+    }
+    
+    /// 連到指定 broker 的觀察連線選項（沿用認證與 TLS 設定，驗證憑證名稱只套用到主要 broker）
+    pub fn endpoint_options(&self, broker: &BrokerEndpoint, suffix: &str) -> Result<MqttOptions> {
+        let primary = *broker == self.config.brokers()[0];
+        self.broker_options(&format!("{}_{}", self.config.client_id, suffix), broker, primary)
+    }
+    
+    /// 連線、認證與 TLS 設定
+    fn broker_options(&self, client_id: &str, broker: &BrokerEndpoint, primary: bool) -> Result<MqttOptions> {
+        let mut mqttoptions = MqttOptions::new(client_id, &broker.host, broker.port);
+        mqttoptions.set_keep_alive(Duration::from_secs(30));
+        mqttoptions.set_clean_session(true);
+        if let Some(username) = &self.config.username {
+            mqttoptions.set_credentials(username, self.config.password.as_deref().unwrap_or_default());
+            info!("🔑 使用帳號 {} 認證", username);
+        }
+        if self.config.tls.enabled {
+            // 連線位址不變，指定伺服器名稱時只用於驗證憑證
+            let verify_name = self.config.tls.server_name.as_deref().filter(|_| primary);
+            mqttoptions.set_transport(crate::tls::transport(&self.config.tls, verify_name)?);
+            info!("🔒 使用 TLS 連接 (驗證憑證名稱: {})", verify_name.unwrap_or(&broker.host));
+        }
+        Ok(mqttoptions)
+    }
+    
+    /// 啟動心跳循環：定期發佈帶序號的 ping
+    fn start_heartbeat(&mut self, client: AsyncClient) {
+        let topic = self.config.topics.player(&self.config.player_name, "ping");
+        let interval = Duration::from_millis(self.heartbeat_config.interval_ms);
+        let heartbeat = self.heartbeat.clone();
+        let wire_format = self.config.wire_format;
+        let qos = QosConfig::qos(self.config.qos.heartbeat);
+        let recorder = self.recorder.clone();
+        let stats = self.mqtt_handler.stats().clone();
+        self.tasks.spawn("heartbeat", self.session_tag.clone(), |shutdown| async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                let seq = heartbeat.lock().unwrap().mark_sent(std::time::Instant::now());
+                let message = serde_json::json!({ "t": "ping", "a": "ping", "d": { "seq": seq } });
+                let Ok(payload) = wire_format.encode(&message) else {
+                    continue;
+                };
+                if let Err(e) = publish(&client, recorder.as_ref(), &stats, &topic, qos, payload).await {
+                    debug!("發送心跳失敗: {}", e);
+                }
+            }
+        });
+        info!("💓 心跳已啟動 (間隔 {}ms)", interval.as_millis());
+    }
+    
+    /// 啟動時間同步循環：定期發佈帶本地時間的 time_sync
+    fn start_time_sync(&mut self, client: AsyncClient) {
+        let topic = self.config.topics.player(&self.config.player_name, "time_sync");
+        let interval = Duration::from_millis(self.time_sync_config.interval_ms);
+        let wire_format = self.config.wire_format;
+        let qos = QosConfig::qos(self.config.qos.heartbeat);
+        let recorder = self.recorder.clone();
+        let stats = self.mqtt_handler.stats().clone();
+        self.tasks.spawn("time_sync", self.session_tag.clone(), |shutdown| async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                let message = crate::time_sync::request(unix_ms(std::time::SystemTime::now()));
+                let Ok(payload) = wire_format.encode(&message) else {
+                    continue;
+                };
+                if let Err(e) = publish(&client, recorder.as_ref(), &stats, &topic, qos, payload).await {
+                    debug!("發送時間同步失敗: {}", e);
+                }
+            }
+        });
+        info!("🕒 時間同步已啟動 (間隔 {}ms)", interval.as_millis());
+    }
+    
+    /// 啟動狀態雜湊循環：在每個伺服器時間的幀邊界計算雜湊，開啟發佈時送到 td/{player_name}/state_hash
+    fn start_state_hash(&mut self, client: AsyncClient) {
+        let Some(game_state) = self.shared_game_state.clone() else {
+            return;
+        };
+        let config = self.state_hash_config.clone();
+        let interval_ms = config.interval_ms.max(1) as i64;
+        let player_name = self.config.player_name.clone();
+        let topic = self.config.topics.player(&player_name, "state_hash");
+        let last_state_hash = self.last_state_hash.clone();
+        let wire_format = self.config.wire_format;
+        let qos = QosConfig::qos(self.config.qos.heartbeat);
+        let recorder = self.recorder.clone();
+        let stats = self.mqtt_handler.stats().clone();
+        self.tasks.spawn("state_hash", self.session_tag.clone(), |shutdown| async move {
+            loop {
+                // 對齊到下一個伺服器時間的幀邊界，讓各客戶端在同一時刻取樣
+                let server_now = game_state.lock().await.server_clock.server_time(unix_ms(std::time::SystemTime::now()));
+                let wait = interval_ms - server_now.rem_euclid(interval_ms);
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = sleep(Duration::from_millis(wait as u64)) => {}
+                }
+                let frame_hash = {
+                    let state = game_state.lock().await;
+                    let server_time = state.server_clock.server_time(unix_ms(std::time::SystemTime::now()));
+                    let frame = ((server_time + interval_ms / 2) / interval_ms).max(0) as u64;
+                    crate::state_hash::compute(&state, &config, frame)
+                };
+                *last_state_hash.lock().unwrap() = Some(frame_hash);
+                if !config.publish {
+                    continue;
+                }
+                let Ok(payload) = wire_format.encode(&crate::state_hash::message(&player_name, &frame_hash)) else {
+                    continue;
+                };
+                if let Err(e) = publish(&client, recorder.as_ref(), &stats, &topic, qos, payload).await {
+                    debug!("發送狀態雜湊失敗: {}", e);
+                }
+            }
+        });
+        info!("🧮 狀態雜湊已啟動 (間隔 {}ms)", interval_ms);
+    }
+    
     /// 訂閱遊戲相關主題
     async fn subscribe_game_topics(&self, client: &AsyncClient) -> Result<()> {
-        // 訂閱遊戲狀態主題 (實際後端使用的主題)
-        client.subscribe("td/all/res", QoS::AtLeastOnce).await?;
-        debug!("已訂閱遊戲狀態主題: td/all/res");
-        
-        // 也訂閱玩家特定主題
-        client.subscribe("td/+/send", QoS::AtLeastOnce).await?;
-        debug!("已訂閱玩家主題: td/+/send");
-        
-        // 訂閱畫面狀態回應主題 (使用 player_name 而不是 client_id)
-        let screen_response_topic = format!("td/{}/screen_response", self.config.player_name);
-        client.subscribe(&screen_response_topic, QoS::AtLeastOnce).await?;
-        debug!("已訂閱畫面狀態回應主題: {}", screen_response_topic);
-        
-        // 訂閱能力測試主題（如果需要）
-        client.subscribe("ability_test/response", QoS::AtMostOnce).await?;
-        debug!("已訂閱能力測試回應主題");
-        
+        for (topic, qos) in game_topics(&self.config) {
+            client.subscribe(&topic, qos).await?;
+            debug!("已訂閱主題: {}", topic);
+        }
         Ok(())
     }
     
@@ -167,24 +834,54 @@ impl GameClient {
         let view_width = term_width as f32 * WORLD_UNITS_PER_CHAR;
         let view_height = term_height as f32 * WORLD_UNITS_PER_CHAR;
         
-        // 發送進入遊戲訊息，包含視野範圍
-        self.send_player_action("enter_game", serde_json::json!({
-            "player_name": self.config.player_name,
-            "hero_type": self.config.hero_type,
-            "viewport": {
-                "width": view_width,
-                "height": view_height,
-                "units_per_char": WORLD_UNITS_PER_CHAR
+        // 發送進入遊戲訊息，包含視野範圍（唯讀模式只觀看，不在伺服器上生成英雄）
+        if let Some(watched) = &self.config.spectate {
+            info!("👁 觀戰 {}：只訂閱該玩家的畫面，不發送任何操作", watched);
+        } else if self.config.read_only {
+            info!("👁 唯讀模式：以觀察者身分進入，不發送 enter_game");
+        } else {
+            let mut data = serde_json::json!({
+                "player_name": self.config.player_name,
+                "hero_type": self.config.hero_type,
+                "viewport": {
+                    "width": view_width,
+                    "height": view_height,
+                    "units_per_char": WORLD_UNITS_PER_CHAR
+                },
+                // 協議版本握手，後端回覆到 hello_ack
+                "protocol": crate::version::PROTOCOL_VERSION,
+                "client_version": crate::version::CRATE_VERSION
+            });
+            if let Some(team) = self.config.team {
+                data["team"] = serde_json::json!(team);
             }
-        })).await?;
+            self.send_player_action("enter_game", data.clone()).await?;
+            if !self.config.dry_run {
+                // 重連後以相同內容重新進入遊戲
+                let (topic, message) = self.action_message("enter_game", data);
+                self.link.lock().unwrap().enter_game = Some((topic, self.config.wire_format.encode(&message)?));
+                self.flush_restored_actions().await;
+            }
+        }
         
         // 更新本地視野設定
         self.game_state.viewport.width = view_width;
         self.game_state.viewport.height = view_height;
         
+        // 伺服器資料的停滯時間從進入遊戲起計算
+        if let Some(shared_state) = &self.shared_game_state {
+            shared_state.lock().await.last_update = std::time::SystemTime::now();
+        }
         self.state = ClientState::InGame;
         info!("已進入遊戲");
         
+        // 觀戰時畫面由被觀戰玩家的客戶端請求，只套用本地鏡頭
+        if self.config.spectate.is_some() {
+            let view = self.camera.view(&self.game_state);
+            self.apply_camera_view(view);
+            return Ok(());
+        }
+        
         // 發送初始視窗範圍
         self.send_viewport_update().await?;
         
@@ -194,192 +891,1413 @@ impl GameClient {
         Ok(())
     }
     
+    /// 切換英雄：離開遊戲後以新英雄重新進入，保留 MQTT 連接
+    pub async fn switch_hero(&mut self, hero_type: &str) -> Result<()> {
+        self.ensure_writable("switch_hero")?;
+        if crate::hero_catalog::find_hero(hero_type).is_none() {
+            return Err(anyhow::anyhow!("未知的英雄: {}", hero_type));
+        }
+        if !self.state.in_game() && self.state != ClientState::Connected {
+            return Err(anyhow::anyhow!("客戶端未連接到服務器"));
+        }
+        
+        info!("切換英雄: {} -> {}", self.config.hero_type, hero_type);
+        
+        if self.state.in_game() {
+            self.tasks.stop("screen_request", SHUTDOWN_GRACE).await;
+            self.send_player_action("leave_game", serde_json::json!({})).await?;
+            self.link.lock().unwrap().enter_game = None;
+            self.state = ClientState::Connected;
+        }
+        
+        self.config.hero_type = hero_type.to_string();
+        self.player_simulator = PlayerSimulator::new(self.config.player_name.clone(), hero_type.to_string())
+            .with_seed(self.player_simulator.seed);
+        self.game_state.switch_hero(hero_type);
+        if let Some(shared_state) = &self.shared_game_state {
+            shared_state.lock().await.switch_hero(hero_type);
+        }
+        
+        self.enter_game().await
+    }
+    
     /// 執行玩家操作
     pub async fn perform_action(&mut self, action: &str, params: serde_json::Value) -> Result<()> {
-        if self.state != ClientState::InGame {
+        self.ensure_writable(action)?;
+        // 重連中仍接受操作，由離線佇列在重新連上後補送
+        let in_game = self.state.in_game()
+            || (matches!(self.state, ClientState::Reconnecting(_)) && self.resume_state.as_ref().is_some_and(ClientState::in_game));
+        if !in_game {
             return Err(anyhow::anyhow!("玩家未在遊戲中"));
         }
         
         debug!("執行玩家操作: {} - 參數: {}", action, params);
         
+        if action == "cast_ability" {
+            if let Some(ability_id) = params.get("ability_id").and_then(|v| v.as_str()) {
+                self.sanity.note_cast(ability_id);
+            }
+        }
+        
+        let mut params = params;
+        if action == "move" {
+            normalize_move_params(&mut params);
+        }
+        if action == "attack" && self.targeting.lead_target {
+            self.apply_lead_target(&mut params);
+        }
+        
         // 通過模擬器處理操作
         let result = self.player_simulator.perform_action(action, params.clone()).await?;
         
         // 發送操作到服務器
-        self.send_player_action(action, params.clone()).await?;
+        let sent = self.send_player_action(action, params.clone()).await?;
         
-        // 更新本地遊戲狀態
-        self.game_state.apply_local_action(action, &result);
+        // 記錄模擬器的預測，之後以伺服器狀態確認（試運行或暫存在離線佇列時不會及時有伺服器結果）
+        if sent {
+            if let Some(ability_id) = params.get("ability_id").and_then(|v| v.as_str()).filter(|_| action == "cast_ability") {
+                self.cast_stats.lock().unwrap().attempt(ability_id, std::time::Instant::now());
+            }
+            if let Some(prediction) = Prediction::from_result(action, &result, self.game_state.local_player.position) {
+                self.expectations.expect(action, prediction, std::time::SystemTime::now());
+            }
+        }
         
-        // 如果是移動操作，發送視野範圍更新
-        if action == "move" {
-            self.send_viewport_update().await?;
+        // 更新本地遊戲狀態（鏡頭跟隨英雄時，下一個 tick 依新位置更新視野）；
+        // 啟用移動預測時不直接跳到模擬器的結果，由預測器每幀沿移動方向推進
+        let target = params.get("target_x").and_then(|x| x.as_f64()).zip(params.get("target_y").and_then(|y| y.as_f64()));
+        match target.filter(|_| action == "move" && self.movement.enabled()) {
+            Some((x, y)) => self.movement.issue_move(Vec2::new(x as f32, y as f32)),
+            None => self.game_state.apply_local_action(action, &result),
         }
+        crate::invariants::verify(&mut self.game_state, || format!("本地操作 {}", action));
         
         Ok(())
     }
     
-    /// 發送視窗範圍更新
-    pub async fn send_viewport_update(&self) -> Result<()> {
-        // 使用玩家當前位置作為視野中心
-        let player_pos = self.game_state.local_player.position;
-        
-        // 計算視野邊界（考慮每個字符代表10x10單位）
-        const WORLD_UNITS_PER_CHAR: f32 = 10.0;
-        let (term_width, term_height) = crossterm::terminal::size().unwrap_or((80, 24));
-        let view_width = term_width as f32 * WORLD_UNITS_PER_CHAR;
-        let view_height = term_height as f32 * WORLD_UNITS_PER_CHAR;
-        
-        let min_x = player_pos.x - view_width / 2.0;
-        let min_y = player_pos.y - view_height / 2.0;
-        let max_x = player_pos.x + view_width / 2.0;
-        let max_y = player_pos.y + view_height / 2.0;
-        
-        let viewport_data = serde_json::json!({
-            "center_x": player_pos.x,
-            "center_y": player_pos.y,
-            "width": view_width,
-            "height": view_height,
-            "units_per_char": WORLD_UNITS_PER_CHAR,
-            "min_x": min_x,
-            "min_y": min_y,
-            "max_x": max_x,
-            "max_y": max_y,
-        });
+    /// 預判瞄準：把攻擊目標往移動中單位的前進方向推「速度 × 往返延遲」
+    fn apply_lead_target(&self, params: &mut serde_json::Value) {
+        let Some(target) = params.get("target_position")
+            .and_then(|v| v.as_array())
+            .and_then(|a| Some(Vec2::new(a.first()?.as_f64()? as f32, a.get(1)?.as_f64()? as f32)))
+        else {
+            return;
+        };
+        let Some(rtt) = self.rtt() else {
+            debug!("尚未量測到往返延遲，略過預判瞄準");
+            return;
+        };
+        if let Some(lead) = self.velocities.lead(target, self.targeting.pick_radius, rtt, std::time::Instant::now()) {
+            info!("🎯 預判瞄準 {}: ({:.1}, {:.1}) 速度 ({:.1}, {:.1}) × {}ms -> ({:.1}, {:.1})",
+                lead.subject, lead.position.x, lead.position.y, lead.velocity.x, lead.velocity.y,
+                rtt.as_millis(), lead.led_position.x, lead.led_position.y);
+            params["target_position"] = serde_json::json!([lead.led_position.x, lead.led_position.y]);
+        }
+    }
+    
+    /// 目前連線（或正在嘗試）的 broker
+    pub fn active_broker(&self) -> BrokerEndpoint {
+        let brokers = self.config.brokers();
+        let index = self.link.lock().unwrap().broker;
+        brokers.get(index).cloned().unwrap_or_else(|| brokers[0].clone())
+    }
+    
+    /// 是否已改用備援 broker
+    pub fn on_failover(&self) -> bool {
+        self.link.lock().unwrap().broker != 0
+    }
+    
+    /// 平滑後的往返延遲
+    pub fn rtt(&self) -> Option<Duration> {
+        self.rtt.lock().unwrap().smoothed()
+    }
+    
+    /// 後端在握手中回報的版本（尚未回報時為 None）
+    pub fn backend_version(&self) -> Option<BackendVersion> {
+        self.backend_version.lock().unwrap().clone()
+    }
+    
+    /// 訂閱之後的訊息解碼與路由錯誤
+    pub fn subscribe_protocol_errors(&self) -> tokio::sync::broadcast::Receiver<ProtocolError> {
+        self.mqtt_handler.errors().subscribe()
+    }
+    
+    /// 訂閱之後收到的所有訊息
+    pub fn subscribe_incoming(&self) -> tokio::sync::broadcast::Receiver<IncomingMessage> {
+        self.incoming.subscribe()
+    }
+    
+    /// 前後端協議版本不同時的警告
+    pub fn protocol_mismatch(&self) -> Option<String> {
+        self.backend_version.lock().unwrap().as_ref().and_then(BackendVersion::protocol_mismatch)
+    }
+    
+    /// 心跳延遲統計（尚未收到 pong 時為 None）
+    pub fn heartbeat_stats(&self) -> Option<HeartbeatStats> {
+        self.heartbeat.lock().unwrap().stats()
+    }
+    
+    /// 估計的伺服器時鐘
+    pub fn server_clock(&self) -> &ServerClock {
+        &self.game_state.server_clock
+    }
+    
+    /// 最近一幀的狀態雜湊（未開啟或尚未計算時為 None）
+    pub fn state_hash(&self) -> Option<FrameHash> {
+        *self.last_state_hash.lock().unwrap()
+    }
+    
+    /// 依配置設定瞄準方式
+    pub fn configure_targeting(&mut self, config: &TargetingConfig) {
+        self.targeting = config.clone();
+    }
+    
+    /// 切換預判瞄準
+    pub fn set_lead_target(&mut self, enabled: bool) {
+        self.targeting.lead_target = enabled;
+    }
+    
+    /// 依配置設定內建巨集
+    pub fn configure_macros(&mut self, config: &MacrosConfig) {
+        self.macros = config.clone();
+    }
+    
+    /// 回城的目的地（配置覆寫優先，否則使用英雄目錄的出生點）
+    pub fn spawn_point(&self) -> Vec2<f32> {
+        let (x, y) = self.macros.spawn_point
+            .unwrap_or_else(|| crate::hero_catalog::hero_spawn_point(&self.config.hero_type));
+        Vec2::new(x, y)
+    }
+    
+    /// 回城：原地引導後回到出生點，引導期間受到傷害會中斷
+    pub async fn recall(&mut self) -> Result<()> {
+        self.ensure_writable("recall")?;
+        if !self.state.in_game() {
+            return Err(anyhow::anyhow!("玩家未在遊戲中"));
+        }
         
-        debug!("發送視野更新: 中心({:.1}, {:.1}), 範圍({:.1}x{:.1})", 
-               player_pos.x, player_pos.y, view_width, view_height);
+        let spawn = self.spawn_point();
+        let channel = Duration::from_secs_f32(self.macros.recall_channel_secs.max(0.0));
+        info!("🏠 開始回城引導 ({:.1} 秒)", channel.as_secs_f32());
+        self.perform_action("interact", serde_json::json!({ "type": "recall" })).await?;
         
-        self.send_player_action("update_viewport", viewport_data).await?;
-        debug!("已發送視窗範圍更新");
+        let start_health = self.game_state.local_player.health.0;
+        let end_time = std::time::Instant::now() + channel;
+        while std::time::Instant::now() < end_time {
+            sleep(Duration::from_millis(100)).await;
+            self.tick().await?;
+            let health = self.game_state.local_player.health.0;
+            if health < start_health {
+                warn!("🏠 回城引導被打斷：生命 {:.0} -> {:.0}", start_health, health);
+                return Err(anyhow::anyhow!("回城引導被打斷"));
+            }
+        }
         
+        self.perform_action("move", serde_json::json!({
+            "target_x": spawn.x,
+            "target_y": spawn.y
+        })).await?;
+        // 回城是傳送，模擬器的位置直接設到出生點（不受單次移動距離限制），也不沿路預測
+        self.player_simulator.current_position = spawn;
+        self.movement.reset();
+        self.game_state.local_player.position = spawn;
+        info!("🏠 已回到基地 ({:.1}, {:.1})", spawn.x, spawn.y);
         Ok(())
     }
     
-    /// 發送玩家操作到服務器
-    async fn send_player_action(&self, action: &str, data: serde_json::Value) -> Result<()> {
-        if let Some(client) = &self.client {
-            let topic = format!("td/{}/action", self.config.player_name);
-            let message = serde_json::json!({
-                "t": "player_action",
-                "a": action,
-                "d": data
-            });
-            
-            client.publish(
-                &topic,
-                QoS::AtLeastOnce,
-                false,
-                message.to_string()
-            ).await?;
-            
-            debug!("已發送玩家操作: {} 到主題: {}", action, topic);
+    /// 購物行程：回城、依商店目錄購買配置的物品（買不起或放不下的略過）、返回線上
+    pub async fn shop_trip(&mut self) -> Result<()> {
+        let lane = self.macros.lane_point
+            .map(|(x, y)| Vec2::new(x, y))
+            .unwrap_or(self.game_state.local_player.position);
+        
+        self.recall().await?;
+        
+        for item_id in self.macros.shop_items.clone() {
+            if let Err(e) = self.buy_item(&item_id).await {
+                warn!("🛒 略過購買 {}: {}", item_id, e);
+            }
         }
         
+        self.perform_action("move", serde_json::json!({
+            "target_x": lane.x,
+            "target_y": lane.y
+        })).await?;
+        info!("🛤️ 返回線上 ({:.1}, {:.1})", lane.x, lane.y);
         Ok(())
     }
     
-    /// 自動遊戲模式
-    pub async fn auto_play(&mut self, duration_secs: u64) -> Result<()> {
-        if self.state != ClientState::InGame {
+    /// 依步驟間隔施放技能連段，量測每一步的後端確認與效果延遲（送出後最多等待 `timeout`）
+    pub async fn run_combo(&mut self, steps: &[ComboStep], target: Option<Vec2<f32>>, timeout: Duration) -> Result<ComboReport> {
+        if !self.state.in_game() {
             return Err(anyhow::anyhow!("玩家未在遊戲中"));
         }
+        let hero = crate::hero_catalog::find_hero(&self.config.hero_type);
+        if let Some(step) = steps.iter().find(|step| hero.is_some_and(|hero| hero.ability(&step.ability_id).is_none())) {
+            return Err(anyhow::anyhow!("技能 {} 不屬於 {}", step.ability_id, self.config.hero_type));
+        }
         
-        info!("開始自動遊戲模式，持續 {} 秒", duration_secs);
-        
-        let end_time = std::time::Instant::now() + Duration::from_secs(duration_secs);
-        
-        while std::time::Instant::now() < end_time {
-            // 生成隨機操作
-            if let Some((action, params)) = self.player_simulator.generate_random_action() {
-                if let Err(e) = self.perform_action(&action, params).await {
-                    warn!("自動操作失敗: {}", e);
+        let mut tracker = ComboTracker::new(steps, std::time::SystemTime::now());
+        for (index, step) in steps.iter().enumerate() {
+            // 等到預定時間，等待期間持續同步狀態以記錄前面步驟的確認時間
+            loop {
+                self.tick().await?;
+                let now = std::time::SystemTime::now();
+                tracker.observe(&self.game_state, now);
+                match tracker.due(index).duration_since(now) {
+                    Ok(wait) if !wait.is_zero() => sleep(wait.min(COMBO_POLL_INTERVAL)).await,
+                    _ => break,
                 }
             }
-            
-            // 等待一段時間後執行下一個操作
-            sleep(Duration::from_millis(1000)).await;
+            let mut params = serde_json::json!({ "ability_id": step.ability_id, "level": 1 });
+            if let Some(target) = target {
+                params["target_position"] = serde_json::json!([target.x, target.y]);
+            }
+            tracker.mark_sent(index, &self.game_state, std::time::SystemTime::now());
+            if let Err(e) = self.perform_action("cast_ability", params).await {
+                tracker.mark_failed(index, e.to_string());
+            }
         }
         
-        info!("自動遊戲模式結束");
-        Ok(())
-    }
-    
-    /// 獲取客戶端狀態
-    pub fn get_state(&self) -> &ClientState {
-        &self.state
+        let deadline = std::time::Instant::now() + timeout;
+        while !tracker.settled() && std::time::Instant::now() < deadline {
+            sleep(COMBO_POLL_INTERVAL).await;
+            self.tick().await?;
+            tracker.observe(&self.game_state, std::time::SystemTime::now());
+        }
+        Ok(tracker.report())
     }
     
-    /// 獲取遊戲狀態
-    pub fn get_game_state(&self) -> &GameState {
-        &self.game_state
+    /// 在英雄周圍生成訓練假人並開始統計對假人的 DPS，返回假人 ID
+    pub async fn spawn_dummies(&mut self, count: usize, hp: Option<f32>) -> Result<Vec<String>> {
+        if !self.state.in_game() {
+            return Err(anyhow::anyhow!("玩家未在遊戲中"));
+        }
+        if !(1..=crate::dummy::MAX_DUMMIES).contains(&count) {
+            return Err(anyhow::anyhow!("假人數量應介於 1 到 {}", crate::dummy::MAX_DUMMIES));
+        }
+        let hp = hp.unwrap_or(self.dummy.hp);
+        let positions = crate::dummy::dummy_positions(self.game_state.local_player.position, count, self.dummy.distance);
+        let ids = self.game_state.dummies.track_new(&self.config.player_name, count);
+        let dummies: Vec<serde_json::Value> = ids.iter().zip(&positions)
+            .map(|(id, position)| serde_json::json!({ "id": id, "x": position.x, "y": position.y, "hp": hp }))
+            .collect();
+        self.send_player_action("spawn_dummy", serde_json::json!({ "dummies": dummies })).await?;
+        self.sandbox.remember_dummies(count, hp, std::time::Instant::now());
+        if let Some(shared_state) = &self.shared_game_state {
+            shared_state.lock().await.dummies = self.game_state.dummies.clone();
+        }
+        info!("🎯 已要求生成 {} 個訓練假人（生命值 {:.0}）", count, hp);
+        Ok(ids)
     }
     
-    /// 獲取可變遊戲狀態
-    pub fn get_game_state_mut(&mut self) -> &mut GameState {
-        &mut self.game_state
+    /// 移除所有訓練假人並清除 DPS 統計，返回移除的數量
+    pub async fn clear_dummies(&mut self) -> Result<usize> {
+        let ids = self.game_state.dummies.dummies().to_vec();
+        if !ids.is_empty() {
+            self.send_player_action("despawn_dummy", serde_json::json!({ "ids": ids })).await?;
+        }
+        self.game_state.dummies.clear();
+        self.sandbox.forget_dummies();
+        if let Some(shared_state) = &self.shared_game_state {
+            shared_state.lock().await.dummies.clear();
+        }
+        Ok(ids.len())
     }
     
-    /// 同步共享遊戲狀態
-    pub async fn sync_shared_state(&mut self) -> Result<()> {
-        if let Some(shared_state) = &self.shared_game_state {
-            let state = shared_state.lock().await;
-            self.game_state = state.clone();
-            debug!("同步共享遊戲狀態完成");
+    /// 啟用或停用練習沙盒
+    pub fn set_sandbox(&mut self, active: bool) -> Result<()> {
+        if active && !self.state.in_game() {
+            return Err(anyhow::anyhow!("玩家未在遊戲中"));
         }
+        self.sandbox.set_active(active);
+        info!("🧪 練習沙盒已{}", if active { "啟用" } else { "停用" });
         Ok(())
     }
     
-    /// 發送固定範圍畫面請求
-    pub async fn request_screen_area(&self, min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Result<()> {
-        if let Some(client) = &self.client {
-            let request_message = serde_json::json!({
-                "t": "screen_request",
-                "a": "get_screen_area",
-                "d": {
-                    "player_name": self.config.player_name,
-                    "request_type": "fixed_area",
-                    "min_x": min_x,
-                    "min_y": min_y,
-                    "max_x": max_x,
-                    "max_y": max_y,
-                    "timestamp": std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap()
-                        .as_millis()
+    /// 練習沙盒狀態
+    pub fn sandbox(&self) -> &Sandbox {
+        &self.sandbox
+    }
+    
+    /// 依沙盒狀態送出重置冷卻、補滿與重新生成假人的作弊操作
+    async fn run_sandbox(&mut self) -> Result<()> {
+        if !self.state.in_game() {
+            return Ok(());
+        }
+        for action in self.sandbox.check(&self.game_state, std::time::Instant::now()) {
+            match action {
+                SandboxAction::ResetCooldowns { abilities, items } => {
+                    self.send_player_action("reset_cooldowns", serde_json::json!({ "abilities": abilities, "items": items })).await?;
                 }
-            });
+                SandboxAction::Refill { health, items } => {
+                    self.send_player_action("refill", serde_json::json!({ "health": health, "items": items })).await?;
+                }
+                SandboxAction::RespawnDummies { count, hp } => {
+                    self.clear_dummies().await?;
+                    self.spawn_dummies(count, Some(hp)).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    /// 以作弊操作 load_terrain 把測試地形推送給後端（取代後端目前的地形），返回地形格數
+    pub async fn push_terrain(&mut self, map: &TerrainMap) -> Result<usize> {
+        if !self.state.in_game() {
+            return Err(anyhow::anyhow!("玩家未在遊戲中"));
+        }
+        self.send_player_action("load_terrain", serde_json::to_value(map)?).await?;
+        info!("🧱 已推送測試地形（{} 格，每格 {} 單位）", map.terrain.len(), map.cell_size);
+        Ok(map.terrain.len())
+    }
+    
+    /// 清除訊息結構驗證統計
+    pub async fn clear_schema_report(&mut self) {
+        self.game_state.schema_report.clear();
+        if let Some(shared_state) = &self.shared_game_state {
+            shared_state.lock().await.schema_report.clear();
+        }
+    }
+    
+    /// 選擇目標（None 為取消選擇）
+    pub async fn select_target(&mut self, target: Option<u32>) {
+        self.game_state.selected_target = target;
+        if let Some(shared_state) = &self.shared_game_state {
+            shared_state.lock().await.selected_target = target;
+        }
+        match target {
+            Some(id) => info!("🎯 選擇目標 #{}", id),
+            None => info!("🎯 取消選擇目標"),
+        }
+    }
+    
+    /// 購買道具：本地檢查金錢與道具欄後送出 `buy_item` 互動，成功後扣除金錢並放入道具欄，返回放入的格子
+    pub async fn buy_item(&mut self, item_id: &str) -> Result<u8> {
+        let item = self.shop.find(item_id).cloned()
+            .ok_or_else(|| anyhow::anyhow!("商店沒有道具: {}", item_id))?;
+        // 先在副本上檢查，送出成功後才套用到目前的狀態
+        let slot = self.game_state.clone().buy_item(&item)?;
+        self.perform_action("interact", serde_json::json!({
+            "type": "buy_item",
+            "item_id": item.id,
+            "slot": slot
+        })).await?;
+        let slot = self.game_state.buy_item(&item)?;
+        if let Some(shared_state) = &self.shared_game_state {
+            if let Err(e) = shared_state.lock().await.buy_item(&item) {
+                warn!("共享狀態未套用購買: {}", e);
+            }
+        }
+        info!("🛒 購買物品: {} -> {} 號位", item.id, slot);
+        Ok(slot)
+    }
+    
+    /// 賣出一格道具：送出 `sell_item` 互動，返回賣出的道具 ID 與退還的金錢
+    pub async fn sell_item(&mut self, slot: u8) -> Result<(String, u32)> {
+        let (sold, _) = self.game_state.clone().sell_item(slot, &self.shop)?;
+        self.perform_action("interact", serde_json::json!({
+            "type": "sell_item",
+            "item_id": sold.item_id,
+            "slot": slot
+        })).await?;
+        let (sold, refund) = self.game_state.sell_item(slot, &self.shop)?;
+        if let Some(shared_state) = &self.shared_game_state {
+            if let Err(e) = shared_state.lock().await.sell_item(slot, &self.shop) {
+                warn!("共享狀態未套用賣出: {}", e);
+            }
+        }
+        info!("💰 賣出物品: {} ({} 號位，退還 {})", sold.item_id, slot, refund);
+        Ok((sold.item_id, refund))
+    }
+    
+    /// 統計窗口內對訓練假人的 DPS
+    pub fn dps_report(&self) -> DpsReport {
+        self.game_state.dummies.report(std::time::SystemTime::now())
+    }
+    
+    /// 立即發送目前鏡頭的視窗範圍更新
+    pub async fn send_viewport_update(&mut self) -> Result<()> {
+        let view = self.camera.view(&self.game_state);
+        self.apply_camera_view(view);
+        self.camera.mark_sent(view, std::time::Instant::now());
+        self.publish_viewport(view).await
+    }
+    
+    /// 依鏡頭模式更新視野，視野明顯變化時（防抖後）發送視窗範圍更新
+    async fn update_camera(&mut self) -> Result<()> {
+        let view = self.camera.view(&self.game_state);
+        self.apply_camera_view(view);
+        if self.state.in_game() && self.config.spectate.is_none() {
+            if let Some(view) = self.camera.poll(view, std::time::Instant::now()) {
+                self.publish_viewport(view).await?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// 把鏡頭視野套用到本地視窗與畫面請求循環
+    fn apply_camera_view(&mut self, view: CameraView) {
+        self.game_state.viewport.center = view.center;
+        self.game_state.viewport.zoom = view.zoom;
+        *self.shared_camera.lock().unwrap() = Some(view);
+    }
+    
+    /// 發送視窗範圍更新
+    async fn publish_viewport(&self, view: CameraView) -> Result<()> {
+        // 每個字符代表10x10單位
+        const WORLD_UNITS_PER_CHAR: f32 = 10.0;
+        let (min_x, min_y, max_x, max_y) = view.area();
+        
+        let viewport_data = serde_json::json!({
+            "center_x": view.center.x,
+            "center_y": view.center.y,
+            "width": view.width,
+            "height": view.height,
+            "zoom": view.zoom,
+            "units_per_char": WORLD_UNITS_PER_CHAR,
+            "min_x": min_x,
+            "min_y": min_y,
+            "max_x": max_x,
+            "max_y": max_y,
+        });
+        
+        debug!("發送視野更新: 中心({:.1}, {:.1}), 範圍({:.1}x{:.1})", 
+               view.center.x, view.center.y, view.width, view.height);
+        
+        self.send_player_action("update_viewport", viewport_data).await?;
+        debug!("已發送視窗範圍更新");
+        
+        Ok(())
+    }
+    
+    /// 鏡頭
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+    
+    /// 切換鏡頭模式（下一個 tick 依新模式更新視野）
+    pub fn set_camera_mode(&mut self, mode: CameraMode) {
+        self.camera.set_mode(mode);
+    }
+    
+    /// 設置鏡頭縮放
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.camera.set_zoom(zoom);
+        let view = self.camera.view(&self.game_state);
+        self.apply_camera_view(view);
+    }
+    
+    /// 發送聊天訊息
+    pub async fn send_chat(&self, message: &str) -> Result<()> {
+        if !self.state.in_game() {
+            return Err(anyhow::anyhow!("玩家未在遊戲中"));
+        }
+        self.send_player_action("chat", serde_json::json!({
+            "player_name": self.config.player_name,
+            "message": message,
+        })).await?;
+        info!("💬 {}: {}", self.config.player_name, message);
+        Ok(())
+    }
+    
+    /// 唯讀模式下拒絕會改變遊戲狀態的操作（觀戰時拒絕所有操作）
+    fn ensure_writable(&self, action: &str) -> Result<()> {
+        if self.config.spectate.is_some() || (self.config.read_only && !READ_ONLY_ALLOWED_ACTIONS.contains(&action)) {
+            return Err(PermissionDenied { action: action.to_string() }.into());
+        }
+        Ok(())
+    }
+    
+    /// 發送玩家操作到服務器
+    /// 發送玩家操作，返回是否已立即發佈（試運行、超過發佈頻率或暫存到離線佇列時為 false）
+    async fn send_player_action(&self, action: &str, data: serde_json::Value) -> Result<bool> {
+        self.ensure_writable(action)?;
+        if self.client.is_none() {
+            return Ok(false);
+        }
+        let Some(data) = self.rate_limiter.lock().unwrap().submit(action, data, std::time::Instant::now()) else {
+            debug!("{} 超過發佈頻率，合併到下一次發佈", action);
+            return Ok(false);
+        };
+        self.dispatch_action(action, data).await
+    }
+    
+    /// 發佈已通過限流的玩家操作
+    async fn dispatch_action(&self, action: &str, data: serde_json::Value) -> Result<bool> {
+        let Some(client) = &self.client else {
+            return Ok(false);
+        };
+        let (topic, mut message) = self.action_message(action, data);
+        
+        if self.config.dry_run {
+            info!("🧪 [試運行] 未發送 {} 到主題 {}: {}", action, topic, message);
+            return Ok(false);
+        }
+        
+        {
+            // 斷線中、失去伺服器或仍有未補送的操作時排入佇列，重新連上或恢復會話後依序補送；
+            // 觀看範圍不排隊，恢復會話後要立即送出才會重新收到伺服器資料
+            let server_lost = matches!(self.state, ClientState::ServerLost(_)) && !READ_ONLY_ALLOWED_ACTIONS.contains(&action);
+            let mut link = self.link.lock().unwrap();
+            if link.reconnecting.is_some() || server_lost || !link.outbound.is_empty() {
+                if link.outbound.len() >= self.offline_queue.max_size {
+                    return Err(anyhow::anyhow!("離線佇列已滿（{} 個操作），{} 未送出", link.outbound.len(), action));
+                }
+                link.enqueue(topic, self.config.wire_format.encode(&message)?);
+                info!("📥 無法送達後端，{} 已排入離線佇列（{} 個待補送）", action, link.outbound.len());
+                return Ok(false);
+            }
+        }
+        
+        // 帶上請求 ID，後端回應時記錄操作確認延遲（補送的操作不計）
+        let id = self.track_actions.then(|| self.requests.track_action(action));
+        if let Some(id) = &id {
+            message["id"] = serde_json::json!(id);
+        }
+        let qos = QosConfig::qos(self.config.qos.actions);
+        let sent = match self.config.wire_format.encode(&message) {
+            Ok(payload) => publish(client, self.recorder.as_ref(), self.mqtt_handler.stats(), &topic, qos, payload).await.map_err(Into::into),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = sent {
+            if let Some(id) = &id {
+                self.requests.cancel(id);
+            }
+            return Err(e);
+        }
+        
+        debug!("已發送玩家操作: {} 到主題: {}", action, topic);
+        Ok(true)
+    }
+    
+    /// 發佈限流中間隔已到期的待發佈訊息
+    async fn flush_rate_limited(&self) -> Result<()> {
+        let due = self.rate_limiter.lock().unwrap().due(std::time::Instant::now());
+        for (action, data) in due {
+            self.dispatch_action(&action, data).await?;
+        }
+        Ok(())
+    }
+    
+    /// MQTT 收發統計
+    pub fn mqtt_stats(&self) -> &MqttStats {
+        self.mqtt_handler.stats()
+    }
+    
+    /// 發佈限流統計
+    pub fn rate_limit_stats(&self) -> RateLimitStats {
+        self.rate_limiter.lock().unwrap().stats()
+    }
+    
+    /// 離線佇列中等待補送的操作數
+    pub fn queued_actions(&self) -> usize {
+        self.link.lock().unwrap().outbound.len()
+    }
+    
+    /// 玩家操作的發送主題與訊息（ts 為估計的伺服器時間，尚未同步時為本地時間）
+    fn action_message(&self, action: &str, data: serde_json::Value) -> (String, serde_json::Value) {
+        let topic = self.config.topics.player(&self.config.player_name, "action");
+        let message = serde_json::json!({
+            "t": "player_action",
+            "a": action,
+            "d": data,
+            "ts": self.game_state.server_clock.server_time(unix_ms(std::time::SystemTime::now())),
+        });
+        (topic, message)
+    }
+    
+    /// 自動遊戲模式
+    pub async fn auto_play(&mut self, duration_secs: u64) -> Result<()> {
+        self.ensure_writable("auto_play")?;
+        if !self.state.in_game() {
+            return Err(anyhow::anyhow!("玩家未在遊戲中"));
+        }
+        
+        info!("開始自動遊戲模式，持續 {} 秒（種子 {}，以 --seed {} 重現）", duration_secs, self.player_simulator.seed, self.player_simulator.seed);
+        
+        let end_time = std::time::Instant::now() + Duration::from_secs(duration_secs);
+        
+        while std::time::Instant::now() < end_time {
+            if let Err(e) = self.auto_play_step().await {
+                warn!("自動操作失敗: {}", e);
+            }
             
-            let topic = format!("td/{}/request", self.config.player_name);
-            client.publish(
-                &topic,
-                QoS::AtLeastOnce,
-                false,
-                request_message.to_string()
-            ).await?;
+            // 等待一段時間後執行下一個操作
+            sleep(Duration::from_millis(1000)).await;
+        }
+        
+        info!("自動遊戲模式結束");
+        Ok(())
+    }
+    
+    /// 沿巡邏路線（後端座標）循環行走，直到時間結束或按下 Ctrl-C，抵達路徑點後才送出下一個移動
+    pub async fn patrol(&mut self, route: &mut PatrolRoute, duration: Duration) -> Result<()> {
+        self.ensure_writable("patrol")?;
+        if !self.state.in_game() {
+            return Err(anyhow::anyhow!("玩家未在遊戲中"));
+        }
+        
+        info!("🚶 開始巡邏 {} 個路徑點，持續 {} 秒", route.waypoints().len(), duration.as_secs());
+        let deadline = tokio::time::Instant::now() + duration;
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep_until(deadline) => break,
+                _ = tokio::signal::ctrl_c() => break,
+                _ = sleep(Duration::from_millis(100)) => {}
+            }
+            self.tick().await?;
+            let position = self.game_state.local_player.position;
+            if let Some(target) = route.advance(position, std::time::Instant::now()) {
+                if let Err(e) = self.perform_action("move", serde_json::json!({
+                    "target_x": target.x,
+                    "target_y": target.y
+                })).await {
+                    warn!("巡邏移動失敗: {}", e);
+                }
+            }
+        }
+        info!("🚶 巡邏結束：走完 {} 圈，送出 {} 個移動", route.laps, route.moves);
+        Ok(())
+    }
+    
+    /// 自動遊戲的一步：生命過低時執行購物行程，否則執行一個行為樹決定的操作
+    pub async fn auto_play_step(&mut self) -> Result<()> {
+        if self.needs_auto_recall().await? {
+            let (health, max_health) = self.game_state.local_player.health;
+            info!("生命過低 ({:.0}/{:.0})，執行購物行程", health, max_health);
+            if let Err(e) = self.shop_trip().await {
+                warn!("購物行程失敗: {}", e);
+            }
+            return Ok(());
+        }
+        self.sync_shared_state().await?;
+        let decision = self.behavior.decide(&self.game_state, self.spawn_point(), self.player_simulator.rng());
+        if let Some((action, mut params)) = decision {
+            // 由行為樹決定操作，啟用擬人化時加上反應延遲與目標抖動
+            if self.humanizer.enabled() {
+                let humanized = self.humanizer.apply(&action, &mut params, self.player_simulator.rng());
+                if humanized.misclick {
+                    debug!("模擬誤點: {} {}", action, params);
+                }
+                sleep(humanized.delay).await;
+            }
+            self.perform_action(&action, params).await?;
+        }
+        Ok(())
+    }
+    
+    /// 依序執行演示步驟並檢查各步驟的預期結果，最後以演示期間收集的指標檢查效能門檻
+    pub async fn run_demo(&mut self, hero_id: &str, sequence: &DemoSequence) -> Result<DemoReport> {
+        self.ensure_writable("demo")?;
+        if !self.state.in_game() {
+            return Err(anyhow::anyhow!("玩家未在遊戲中"));
+        }
+        self.perf.lock().unwrap().clear();
+        
+        let mut outcomes = Vec::new();
+        for step in &sequence.steps {
+            info!("演示操作: {} ({} - {})", step.name, step.action, step.params);
+            if let Err(e) = self.perform_action(&step.action, step.params.clone()).await {
+                outcomes.push(StepOutcome { name: step.name.clone(), passed: false, detail: format!("操作失敗: {}", e) });
+                continue;
+            }
+            sleep(Duration::from_millis(step.wait_ms)).await;
+            self.tick().await?;
             
-            info!("🔄 已發送固定範圍畫面請求: ({},{}) 到 ({},{}) 到主題: {}", 
-                  min_x, min_y, max_x, max_y, topic);
+            let outcome = match &step.expect {
+                None => StepOutcome { name: step.name.clone(), passed: true, detail: String::new() },
+                Some(expect) => match self.query(expect) {
+                    Ok(value) => StepOutcome {
+                        name: step.name.clone(),
+                        passed: crate::expr::truthy(&value),
+                        detail: format!("{} => {}", expect, value),
+                    },
+                    Err(e) => StepOutcome { name: step.name.clone(), passed: false, detail: format!("{} 求值失敗: {}", expect, e) },
+                },
+            };
+            if !outcome.passed {
+                warn!("演示步驟未通過: {} {}", outcome.name, outcome.detail);
+            }
+            outcomes.push(outcome);
+        }
+        
+        let gates = self.evaluate_perf(&sequence.performance);
+        for gate in gates.iter().filter(|gate| !gate.passed) {
+            warn!("效能門檻未通過: {} {}", gate.name, gate.detail);
+        }
+        Ok(DemoReport { hero_id: hero_id.to_string(), outcomes, gates })
+    }
+    
+    /// 依序執行功能鍵綁定的巨集命令，有命令失敗時停止並返回錯誤
+    pub async fn run_macro(&mut self, key: &str, commands: &[MacroCommand]) -> Result<()> {
+        info!("⌨️ 執行巨集 {}（{} 個命令）", key, commands.len());
+        for (index, command) in commands.iter().enumerate() {
+            self.run_macro_command(command).await
+                .map_err(|e| anyhow::anyhow!("巨集 {} 第 {} 個命令失敗: {}", key, index + 1, e))?;
+        }
+        Ok(())
+    }
+    
+    async fn run_macro_command(&mut self, command: &MacroCommand) -> Result<()> {
+        match command {
+            MacroCommand::Move(x, y) => {
+                let target = self.coordinates.to_backend(Vec2::new(*x, *y));
+                self.perform_action("move", serde_json::json!({ "target_x": target.x, "target_y": target.y })).await
+            }
+            MacroCommand::Attack(x, y) => {
+                let target = self.coordinates.to_backend(Vec2::new(*x, *y));
+                self.perform_action("attack", serde_json::json!({
+                    "target_position": [target.x, target.y],
+                    "attack_type": "basic",
+                })).await
+            }
+            MacroCommand::Cast { ability, target } => {
+                let target = match target {
+                    Some((x, y)) => self.coordinates.to_backend(Vec2::new(*x, *y)),
+                    None => self.game_state.local_player.position,
+                };
+                self.perform_action("cast_ability", serde_json::json!({
+                    "ability_id": ability,
+                    "target_position": [target.x, target.y],
+                    "level": 1,
+                })).await
+            }
+            MacroCommand::Action { action, params } => {
+                if !self.state.in_game() {
+                    return Err(anyhow::anyhow!("玩家未在遊戲中"));
+                }
+                self.send_player_action(action, params.clone()).await.map(|_| ())
+            }
+            MacroCommand::Wait(ms) => {
+                sleep(Duration::from_millis(*ms)).await;
+                self.tick().await
+            }
+            MacroCommand::Bookmark(note) => self.add_bookmark(note.clone()).map(|_| ()),
+            MacroCommand::Chat(message) => self.send_chat(message).await,
+            MacroCommand::Query(expr) => {
+                info!("🔎 {} => {}", expr, self.query(expr)?);
+                Ok(())
+            }
+            MacroCommand::Snapshot(path) => {
+                let path = path.clone().unwrap_or_else(|| {
+                    std::path::PathBuf::from(format!("snapshot-{}.json", unix_ms(std::time::SystemTime::now())))
+                });
+                std::fs::write(&path, serde_json::to_string_pretty(&self.query_context())?)
+                    .map_err(|e| anyhow::anyhow!("無法寫入狀態快照 {}: {}", path.display(), e))?;
+                info!("📝 狀態快照已寫入 {}", path.display());
+                Ok(())
+            }
+        }
+    }
+    
+    /// 以目前收集的效能指標檢查門檻
+    pub fn evaluate_perf(&self, gates: &PerfGates) -> Vec<StepOutcome> {
+        gates.evaluate(&self.perf.lock().unwrap())
+    }
+    
+    /// 自動遊戲時是否應回城補給（生命比例低於配置門檻）
+    async fn needs_auto_recall(&mut self) -> Result<bool> {
+        if self.macros.auto_recall_below <= 0.0 {
+            return Ok(false);
+        }
+        self.sync_shared_state().await?;
+        let (health, max_health) = self.game_state.local_player.health;
+        Ok(max_health > 0.0 && health / max_health < self.macros.auto_recall_below)
+    }
+    
+    /// 玩家名稱
+    pub fn player_name(&self) -> &str {
+        &self.config.player_name
+    }
+    
+    /// 自動遊戲的亂數種子
+    pub fn auto_play_seed(&self) -> u64 {
+        self.player_simulator.seed
+    }
+    
+    /// 把操作歷史與種子匯出為 JSON 檔
+    pub fn export_action_history(&self, path: &std::path::Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(&self.player_simulator.export_history())?;
+        std::fs::write(path, content).with_context(|| format!("無法寫入操作歷史: {:?}", path))?;
+        Ok(())
+    }
+    
+    /// 英雄、陣營與 client ID（日誌與壓力測試報告中辨識客戶端用）
+    pub fn identity(&self) -> String {
+        let team = self.config.team.map(|team| format!(", {}", team.name())).unwrap_or_default();
+        format!("{}{} [{}]", self.config.hero_type, team, self.config.client_id)
+    }
+    
+    /// 獲取客戶端狀態
+    pub fn get_state(&self) -> &ClientState {
+        &self.state
+    }
+    
+    /// 獲取遊戲狀態
+    pub fn get_game_state(&self) -> &GameState {
+        &self.game_state
+    }
+    
+    /// 獲取可變遊戲狀態
+    pub fn get_game_state_mut(&mut self) -> &mut GameState {
+        &mut self.game_state
+    }
+    
+    /// 同步共享遊戲狀態
+    pub async fn sync_shared_state(&mut self) -> Result<()> {
+        if let Some(shared_state) = &self.shared_game_state {
+            let state = shared_state.lock().await;
+            self.game_state = state.clone();
+            debug!("同步共享遊戲狀態完成");
+        }
+        Ok(())
+    }
+    
+    /// 每幀更新：同步共享狀態、檢查伺服器資料合理性、取樣自訂指標並檢查警報規則
+    pub async fn tick(&mut self) -> Result<()> {
+        let started = std::time::Instant::now();
+        self.sync_link_state();
+        self.sync_shared_state().await?;
+        self.sync_liveness();
+        let now = std::time::Instant::now();
+        if self.restore_due(now) {
+            // 後端重啟後沒有原本的玩家會話，不會再送資料過來
+            self.last_restore = Some(now);
+            info!("🔁 失去伺服器，後端可能已重啟，嘗試恢復會話");
+            if let Err(e) = self.restore_session().await {
+                warn!("恢復會話失敗: {}", e);
+            }
+        }
+        self.update_camera().await?;
+        self.flush_rate_limited().await?;
+        for action in self.requests.expire(self.rpc_timeout, std::time::Instant::now()) {
+            debug!("⌛ 請求 {} 逾時未回應", action);
+        }
+        // 在套用任何本地修改前檢查，確保比較的是伺服器送來的狀態
+        self.sanity.check(&self.game_state);
+        self.expectations.check(&self.game_state, std::time::SystemTime::now());
+        self.watch_anomalies();
+        if self.movement.enabled() && self.state.in_game() {
+            let server = self.game_state.local_player.position;
+            self.game_state.local_player.position = self.movement.frame(server, std::time::Instant::now());
         }
+        if let Err(e) = self.run_sandbox().await {
+            warn!("練習沙盒操作失敗: {}", e);
+        }
+        self.velocities.update(&self.game_state, std::time::Instant::now());
+        #[cfg(feature = "metrics")]
+        self.metrics.tick(&self.game_state);
+        if self.alerts.due() {
+            let ctx = self.query_context();
+            self.alerts.check(&ctx, std::time::Instant::now());
+        }
+        self.perf.lock().unwrap().record_frame(started.elapsed());
         Ok(())
     }
+    
+    /// 更新異常計數（不同步、協議錯誤、資料異常與操作結果不符）
+    fn watch_anomalies(&mut self) {
+        let watch = &mut self.anomaly_watch;
+        watch.observe("位置不同步", self.game_state.sync_errors);
+        watch.observe("協議錯誤", self.mqtt_handler.stats().parse_failures());
+        watch.observe("資料異常", self.sanity.counts().values().sum());
+        watch.observe("操作結果不符", self.expectations.tallies().values().map(|tally| tally.mismatched).sum());
+    }
+    
+    /// 取出待擷取的異常證據（狀態快照與最近的收發訊息；畫面由視圖補上）
+    pub fn take_anomaly(&mut self) -> Option<AnomalyEvidence> {
+        let reasons = self.anomaly_watch.take(std::time::Instant::now())?;
+        Some(AnomalyEvidence {
+            reasons,
+            state: self.query_context(),
+            messages: self.mqtt_handler.stats().history().recent(),
+            dir: self.anomaly_watch.config().dir.clone().into(),
+        })
+    }
+    
+    /// 後端重啟後恢復會話：重新握手、重送 enter_game 與暫存的操作、重新載入行為樹設定檔，並在下一個 tick 重新送出視野
+    pub async fn restore_session(&mut self) -> Result<()> {
+        let Some(client) = self.client.clone() else {
+            return Err(anyhow::anyhow!("未連接到服務器"));
+        };
+        if matches!(self.state, ClientState::Reconnecting(_)) {
+            info!("MQTT 連線中斷中，重新連上後會自動恢復會話");
+            return Ok(());
+        }
+        let topics = game_topics(&self.config);
+        let hello = (
+            self.config.topics.player(&self.config.player_name, "hello"),
+            self.config.wire_format.encode(&crate::version::hello_message(&self.config.player_name))?,
+        );
+        *self.backend_version.lock().unwrap() = None;
+        resume_session(&client, &topics, self.config.qos, hello, self.link.clone(), self.recorder.clone(), self.mqtt_handler.stats().clone()).await?;
+        self.camera.forget_sent();
+        self.rate_limiter.lock().unwrap().forget_sent();
+        let bot = self.bot.clone();
+        self.configure_bot(&bot);
+        info!("🔁 已在重啟後的後端上恢復會話");
+        Ok(())
+    }
+    
+    /// 依事件循環回報的連線狀態切換到重連中或還原原本的狀態
+    fn sync_link_state(&mut self) {
+        let reconnecting = self.link.lock().unwrap().reconnecting;
+        match reconnecting {
+            Some(attempt) => {
+                if !matches!(self.state, ClientState::Reconnecting(_)) {
+                    self.resume_state = Some(self.state.clone());
+                }
+                self.state = ClientState::Reconnecting(attempt);
+            },
+            None => {
+                if let Some(state) = self.resume_state.take() {
+                    info!("連線已恢復");
+                    self.state = state;
+                    self.camera.forget_sent();
+                    self.rate_limiter.lock().unwrap().forget_sent();
+                }
+            }
+        }
+    }
+    
+    /// 依最後一次收到伺服器資料的時間在遊戲中、資料停滯與失去伺服器之間切換
+    fn sync_liveness(&mut self) {
+        if self.shared_game_state.is_none() || self.config.dry_run || !self.state.in_game() {
+            return;
+        }
+        let liveness = crate::liveness::check(&self.liveness_config, self.game_state.last_update, std::time::SystemTime::now());
+        self.state = match liveness {
+            Liveness::Alive => {
+                if self.state != ClientState::InGame {
+                    info!("伺服器資料已恢復更新");
+                }
+                self.last_restore = None;
+                ClientState::InGame
+            }
+            Liveness::Stale(age) => {
+                if self.state == ClientState::InGame {
+                    warn!("⚠ 已 {} 秒沒有收到伺服器資料", age.as_secs());
+                }
+                ClientState::Stale(age.as_secs())
+            }
+            Liveness::Lost(age) => {
+                if !matches!(self.state, ClientState::ServerLost(_)) {
+                    warn!("❌ 已 {} 秒沒有收到伺服器資料，視為失去伺服器", age.as_secs());
+                }
+                ClientState::ServerLost(age.as_secs())
+            }
+        };
+    }
+    
+    /// 失去伺服器時是否該嘗試恢復會話（每隔 `lost_secs` 重試一次）
+    fn restore_due(&self, now: std::time::Instant) -> bool {
+        matches!(self.state, ClientState::ServerLost(_))
+            && self.last_restore.is_none_or(|at| now.duration_since(at) >= Duration::from_secs(self.liveness_config.lost_secs))
+    }
+    
+    /// 依配置設定地圖邊界（需在 connect 前設置）
+    pub fn configure_map(&mut self, config: &MapConfig) {
+        self.game_state.world_bounds = config.bounds;
+        self.game_state.structures = config.structures.clone();
+    }
+    
+    /// 依配置設定死亡回顧保留的傷害事件數量（需在 connect 前設置）
+    pub fn configure_death_recap(&mut self, config: &DeathRecapConfig) {
+        self.game_state.combat_log.set_capacity(config.size);
+    }
+    
+    /// 依配置設定連線心跳（需在 connect 前設置）
+    pub fn configure_heartbeat(&mut self, config: &HeartbeatConfig) {
+        self.heartbeat_config = config.clone();
+    }
+    
+    /// 設置時間同步（需在 connect 前設置）
+    pub fn configure_time_sync(&mut self, config: &TimeSyncConfig) {
+        self.time_sync_config = config.clone();
+    }
+    
+    /// 設置狀態雜湊（需在 connect 前設置）
+    pub fn configure_state_hash(&mut self, config: &StateHashConfig) {
+        self.state_hash_config = config.clone();
+    }
+    
+    /// 依配置設定伺服器資料停滯與失去伺服器的判斷時間
+    pub fn configure_liveness(&mut self, config: &LivenessConfig) {
+        self.liveness_config = config.clone();
+    }
+    
+    /// 依配置設定操作結果驗證
+    pub fn configure_expectations(&mut self, config: &ExpectationsConfig) {
+        self.expectations.set_config(config.clone());
+        self.cast_stats.lock().unwrap().set_timeout(Duration::from_millis(config.timeout_ms));
+    }
+    
+    /// 依配置設定鏡頭縮放與視野更新的防抖間隔
+    pub fn configure_camera(&mut self, config: &CameraConfig) {
+        self.camera.set_debounce(Duration::from_millis(config.viewport_debounce_ms));
+        self.set_zoom(config.zoom);
+    }
+    
+    /// 依配置設定練習沙盒
+    pub fn configure_sandbox(&mut self, config: &SandboxConfig) {
+        self.sandbox.set_config(config);
+    }
+    
+    /// 依配置設定訓練假人的距離、生命值與 DPS 統計窗口
+    pub fn configure_dummy(&mut self, config: &DummyConfig) {
+        self.dummy = config.clone();
+        self.game_state.dummies.set_config(config);
+    }
+    
+    /// 依配置設定流量錄製檔（連線時開啟）
+    pub fn configure_recording(&mut self, config: &RecordingConfig) {
+        self.recording = config.clone();
+    }
+    
+    /// 依配置設定自動遊戲的輸入擬人化
+    pub fn configure_humanize(&mut self, config: &HumanizeConfig) {
+        self.humanizer = Humanizer::new(config.clone());
+    }
+    
+    /// 依配置載入自動遊戲的行為樹（載入失敗時沿用內建的預設行為樹）
+    pub fn configure_bot(&mut self, config: &BotConfig) {
+        self.bot = config.clone();
+        let Some(profile) = &config.profile else {
+            return;
+        };
+        match BehaviorTree::load(std::path::Path::new(profile)) {
+            Ok(tree) => {
+                info!("🤖 自動遊戲行為樹: {}", tree.name());
+                self.behavior = tree;
+            }
+            Err(e) => warn!("無法載入行為樹，使用預設行為樹: {:#}", e),
+        }
+    }
+    
+    /// 依配置載入商店目錄並以其初始道具重建道具欄（需在連線前設置，載入失敗時沿用內建目錄）
+    pub fn configure_shop(&mut self, config: &ShopConfig) {
+        let Some(catalog) = &config.catalog else {
+            return;
+        };
+        match ShopCatalog::load(std::path::Path::new(catalog)) {
+            Ok(shop) => {
+                info!("🛒 商店目錄: {} 種道具", shop.items.len());
+                self.game_state.local_player.items = shop.starting_inventory();
+                self.shop = shop;
+            }
+            Err(e) => warn!("無法載入商店目錄，使用內建目錄: {:#}", e),
+        }
+    }
+    
+    pub fn shop(&self) -> &ShopCatalog {
+        &self.shop
+    }
+    
+    /// 依配置設定本地移動預測
+    pub fn configure_prediction(&mut self, config: &PredictionConfig) {
+        self.movement = MovementPredictor::new(config.clone());
+    }
+    
+    /// 依配置設定畫面請求是否要求增量更新（需在 enter_game 前設置）
+    pub fn configure_screen_updates(&mut self, config: &ScreenUpdatesConfig) {
+        self.delta_screen = config.delta;
+    }
+    
+    /// 依配置設定離線操作佇列
+    pub fn configure_offline_queue(&mut self, config: &OfflineQueueConfig) {
+        self.offline_queue = config.clone();
+        let journal = config.path.as_ref().map(std::path::PathBuf::from);
+        if let Some(path) = &journal {
+            match crate::reconnect::load_journal(path) {
+                Ok(restored) if !restored.is_empty() => {
+                    info!("📥 離線佇列保存檔中有 {} 個上次未送出的操作，進入遊戲後補送", restored.len());
+                    self.restored_actions = restored;
+                }
+                Ok(_) => {}
+                Err(e) => warn!("{:#}", e),
+            }
+        }
+        self.link.lock().unwrap().journal = journal;
+    }
+    
+    /// 進入遊戲後補送上次執行時保存、尚未送出的操作（補送失敗的留在佇列中，重新連上後再送）
+    async fn flush_restored_actions(&mut self) {
+        let Some(client) = self.client.clone() else {
+            return;
+        };
+        if self.restored_actions.is_empty() {
+            return;
+        }
+        {
+            // 保存檔依序是上次的操作與之後暫存的操作，佇列也依相同順序
+            let mut link = self.link.lock().unwrap();
+            let queued = std::mem::take(&mut link.outbound);
+            link.outbound = std::mem::take(&mut self.restored_actions);
+            link.outbound.extend(queued);
+        }
+        match flush_outbound(&client, self.recorder.as_ref(), self.mqtt_handler.stats(), QosConfig::qos(self.config.qos.actions), &self.link).await {
+            Ok(flushed) => info!("📤 已補送 {} 個上次未送出的操作", flushed),
+            Err(e) => warn!("補送上次未送出的操作失敗（留在離線佇列中）: {}", e),
+        }
+    }
+    
+    /// 依配置設定各訊息類型的發佈頻率上限
+    pub fn configure_rate_limit(&mut self, config: &RateLimitConfig) {
+        *self.rate_limiter.lock().unwrap() = RateLimiter::new(config);
+    }
+    
+    /// 依配置設定異常自動擷取
+    pub fn configure_anomaly_capture(&mut self, config: &AnomalyCaptureConfig) {
+        self.mqtt_handler.stats().history().set_window(std::time::Duration::from_secs(config.history_secs));
+        self.anomaly_watch = AnomalyWatch::new(config.clone());
+    }
+    
+    /// 獲取操作結果驗證器
+    pub fn expectations(&self) -> &ExpectationChecker {
+        &self.expectations
+    }
+    
+    /// 獲取可變操作結果驗證器
+    pub fn expectations_mut(&mut self) -> &mut ExpectationChecker {
+        &mut self.expectations
+    }
+    
+    /// 各技能的施放統計（逾時未回覆的施放已計入）
+    pub fn cast_stats(&self) -> CastStats {
+        let mut cast_stats = self.cast_stats.lock().unwrap();
+        cast_stats.expire(std::time::Instant::now());
+        cast_stats.clone()
+    }
+    
+    /// 清除施放統計
+    pub fn clear_cast_stats(&self) {
+        self.cast_stats.lock().unwrap().clear();
+    }
+    
+    /// 依配置設定擊殺/助攻歸屬驗證規則（需在 connect 前設置）
+    pub fn configure_credit(&mut self, config: &CreditConfig) {
+        self.game_state.credits.set_config(config.clone());
+    }
+    
+    /// 依配置設定座標轉換
+    pub fn configure_coordinates(&mut self, config: &CoordinatesConfig) {
+        self.coordinates = CoordinateTransform::from_config(config);
+    }
+    
+    /// 後端座標與本地座標之間的轉換
+    pub fn coordinates(&self) -> CoordinateTransform {
+        self.coordinates
+    }
+    
+    /// 依配置設定警報規則
+    pub fn configure_alerts(&mut self, config: &AlertsConfig) {
+        self.alerts = AlertMonitor::from_config(config);
+    }
+    
+    /// 獲取警報監控器
+    pub fn alerts(&self) -> &AlertMonitor {
+        &self.alerts
+    }
+    
+    /// 依配置設定合理性監控
+    pub fn configure_sanity(&mut self, config: &SanityConfig) {
+        self.sanity = SanityMonitor::new(config.clone());
+    }
+    
+    /// 獲取合理性監控器
+    pub fn sanity(&self) -> &SanityMonitor {
+        &self.sanity
+    }
+    
+    /// 獲取可變合理性監控器
+    pub fn sanity_mut(&mut self) -> &mut SanityMonitor {
+        &mut self.sanity
+    }
+    
+    /// 依配置設定自訂指標
+    #[cfg(feature = "metrics")]
+    pub fn configure_metrics(&mut self, config: &MetricsConfig) {
+        self.metrics = MetricsSampler::from_config(config);
+    }
+    
+    /// 建置時未啟用 metrics 功能：指標定義由配置檢查指出，這裡不做任何事
+    #[cfg(not(feature = "metrics"))]
+    pub fn configure_metrics(&mut self, _config: &MetricsConfig) {}
+    
+    /// 獲取指標取樣器
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> &MetricsSampler {
+        &self.metrics
+    }
+    
+    /// 獲取可變指標取樣器
+    #[cfg(feature = "metrics")]
+    pub fn metrics_mut(&mut self) -> &mut MetricsSampler {
+        &mut self.metrics
+    }
+    
+    /// 統計浮層的指標文字行
+    #[cfg(feature = "metrics")]
+    pub fn metrics_overlay(&self, rows: usize) -> Vec<String> {
+        self.metrics.overlay_lines(rows)
+    }
+    
+    #[cfg(not(feature = "metrics"))]
+    pub fn metrics_overlay(&self, _rows: usize) -> Vec<String> {
+        vec!["此版本建置時未啟用 metrics 功能".to_string()]
+    }
+    
+    /// 建立運算式查詢上下文（遊戲狀態快照加上自訂指標的最新值）
+    pub fn query_context(&self) -> serde_json::Value {
+        let mut ctx = crate::expr::state_context(&self.game_state);
+        #[cfg(feature = "metrics")]
+        crate::expr::extend_context(&mut ctx, "metrics", self.metrics.latest_values());
+        let rtt_ms = self.rtt().map(|rtt| rtt.as_secs_f64() * 1000.0);
+        crate::expr::extend_context(&mut ctx, "rtt_ms", serde_json::json!(rtt_ms));
+        let heartbeat = self.heartbeat_stats().map(|stats| serde_json::json!({
+            "avg_ms": stats.average.as_secs_f64() * 1000.0,
+            "p95_ms": stats.p95.as_secs_f64() * 1000.0,
+            "lost": stats.lost,
+            "sent": stats.sent,
+        }));
+        crate::expr::extend_context(&mut ctx, "heartbeat", serde_json::json!(heartbeat));
+        let checks = self.game_state.credits.checks();
+        crate::expr::extend_context(&mut ctx, "credits", serde_json::json!({
+            "checked": checks.len(),
+            "failed": checks.iter().filter(|check| !check.passed()).count(),
+        }));
+        crate::expr::extend_context(&mut ctx, "expectations", self.expectations.context());
+        ctx
+    }
+    
+    /// 對當前遊戲狀態求值運算式
+    pub fn query(&self, source: &str) -> Result<serde_json::Value> {
+        let expr = crate::expr::Expr::parse(source)?;
+        expr.eval(&self.query_context())
+    }
+    
+    /// 在當前時刻添加書籤（錄製中時同時寫入錄製檔）
+    pub fn add_bookmark(&mut self, note: Option<String>) -> Result<Bookmark> {
+        let player = &self.game_state.local_player;
+        let position = (player.position.x, player.position.y);
+        let name = player.name.clone();
+        let bookmark = self.bookmarks.add(&name, position, note)?.clone();
+        if let Some(recorder) = &self.recorder {
+            recorder.bookmark(&bookmark)?;
+        }
+        Ok(bookmark)
+    }
+    
+    /// 獲取書籤儲存
+    pub fn get_bookmarks(&self) -> &BookmarkStore {
+        &self.bookmarks
+    }
+    
+    /// 發送後端請求並等待對應的回應（請求帶上 ID，後端在回應中原樣帶回；超過 `[rpc] timeout_ms` 未回應時失敗）
+    pub async fn request(&self, kind: &str, action: &str, data: serde_json::Value) -> Result<RpcResponse> {
+        let client = self.client.as_ref().ok_or_else(|| anyhow::anyhow!("未連接到服務器"))?;
+        let (id, reply) = self.requests.register(action);
+        let message = serde_json::json!({ "t": kind, "a": action, "id": id, "d": data });
+        let topic = self.config.topics.player(&self.config.player_name, "request");
+        let sent = publish(
+            client,
+            self.recorder.as_ref(),
+            self.mqtt_handler.stats(),
+            &topic,
+            QosConfig::qos(self.config.qos.screen_requests),
+            self.config.wire_format.encode(&message)?,
+        ).await;
+        if let Err(e) = sent {
+            self.requests.cancel(&id);
+            return Err(e.into());
+        }
+        debug!("已發送請求 {} ({}) 到主題: {}", action, id, topic);
+        match tokio::time::timeout(self.rpc_timeout, reply).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err(anyhow::anyhow!("請求 {} 已被取消", action)),
+            Err(_) => {
+                self.requests.time_out(&id);
+                Err(anyhow::anyhow!("請求 {} 在 {} 毫秒內沒有回應", action, self.rpc_timeout.as_millis()))
+            }
+        }
+    }
+    
+    /// 請求固定範圍的畫面並等待回應
+    pub async fn request_screen_area(&self, min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Result<RpcResponse> {
+        if !self.rate_limiter.lock().unwrap().admit("screen_request", std::time::Instant::now()) {
+            return Err(anyhow::anyhow!("畫面請求超過發佈頻率"));
+        }
+        let data = serde_json::json!({
+            "player_name": self.config.player_name,
+            "request_type": "fixed_area",
+            "min_x": min_x,
+            "min_y": min_y,
+            "max_x": max_x,
+            "max_y": max_y,
+            "timestamp": std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis()
+        });
+        let response = self.request("screen_request", "get_screen_area", data).await?;
+        info!("🔄 固定範圍畫面請求 ({},{}) 到 ({},{}) 已回應，耗時 {} 毫秒",
+              min_x, min_y, max_x, max_y, response.latency.as_millis());
+        Ok(response)
+    }
+    
+    /// 後端請求統計
+    pub fn rpc_stats(&self) -> RpcStats {
+        self.requests.stats()
+    }
+    
+    /// 等待回應中的後端請求數
+    pub fn pending_requests(&self) -> usize {
+        self.requests.pending()
+    }
+    
+    /// 依配置設定後端請求的逾時
+    pub fn configure_rpc(&mut self, config: &RpcConfig) {
+        self.rpc_timeout = Duration::from_millis(config.timeout_ms);
+        self.track_actions = config.track_actions;
+    }
 
     /// 啟動畫面狀態請求循環
     async fn start_screen_request_loop(&mut self) -> Result<()> {
         if let Some(client) = &self.client {
             let client_for_requests = client.clone();
+            let recorder = self.recorder.clone();
+            let stats = self.mqtt_handler.stats().clone();
             let player_name = self.config.player_name.clone();
+            let topic = self.config.topics.player(&player_name, "send");
             let game_state = self.shared_game_state.clone();
+            let camera = self.shared_camera.clone();
+            let rate_limiter = self.rate_limiter.clone();
+            let rtt = self.rtt.clone();
+            let requests = self.requests.clone();
+            let wire_format = self.config.wire_format;
+            let qos = QosConfig::qos(self.config.qos.screen_requests);
+            let delta = self.delta_screen;
             
-            info!("🔄 啟動畫面狀態請求循環 (每3秒一次)");
+            info!("🔄 啟動畫面狀態請求循環 (每3秒一次{})", if delta { "，增量更新" } else { "" });
             
-            let handle = tokio::spawn(async move {
+            self.tasks.spawn("screen_request", self.session_tag.clone(), |shutdown| async move {
                 let mut interval = tokio::time::interval(Duration::from_secs(3));
                 loop {
-                    interval.tick().await;
+                    tokio::select! {
+                        _ = shutdown.cancelled() => break,
+                        _ = interval.tick() => {}
+                    }
+                    if !rate_limiter.lock().unwrap().admit("screen_request", std::time::Instant::now()) {
+                        debug!("畫面請求超過發佈頻率，略過這次請求");
+                        continue;
+                    }
                     
-                    // 從共享遊戲狀態獲取當前顯示範圍
-                    let display_area = if let Some(shared_state) = &game_state {
+                    // 使用鏡頭視野，尚未計算時從共享遊戲狀態獲取當前顯示範圍
+                    let view = *camera.lock().unwrap();
+                    let display_area = if let Some(view) = view {
+                        view.area()
+                    } else if let Some(shared_state) = &game_state {
                         let state = shared_state.lock().await;
                         state.viewport.get_display_area()
                     } else {
@@ -390,7 +2308,7 @@ impl GameClient {
                     let (min_x, min_y, max_x, max_y) = display_area;
                     
                     // 發送畫面狀態請求 - 使用當前螢幕顯示範圍
-                    let request_message = serde_json::json!({
+                    let mut request_message = serde_json::json!({
                         "name": player_name,
                         "t": "screen_request",
                         "a": "get_area",  // 使用 get_area 而不是 get_screen_area
@@ -412,22 +2330,43 @@ impl GameClient {
                         }
                     });
                     
-                    let topic = format!("td/{}/send", player_name);
-                    if let Err(e) = client_for_requests.publish(
+                    // 增量更新以最後套用的畫面序號為基準，尚未同步時後端送完整畫面
+                    if delta {
+                        let base_seq = match &game_state {
+                            Some(shared_state) => shared_state.lock().await.screen_seq,
+                            None => None,
+                        };
+                        request_message["d"]["delta"] = serde_json::json!(true);
+                        request_message["d"]["base_seq"] = serde_json::json!(base_seq);
+                    }
+                    
+                    let id = requests.track("get_area");
+                    request_message["id"] = serde_json::json!(id);
+                    let payload = match wire_format.encode(&request_message) {
+                        Ok(payload) => payload,
+                        Err(e) => {
+                            requests.cancel(&id);
+                            warn!("編碼畫面狀態請求失敗: {}", e);
+                            continue;
+                        }
+                    };
+                    if let Err(e) = publish(
+                        &client_for_requests,
+                        recorder.as_ref(),
+                        &stats,
                         &topic,
-                        QoS::AtLeastOnce,
-                        false,
-                        request_message.to_string()
+                        qos,
+                        payload
                     ).await {
+                        requests.cancel(&id);
                         warn!("發送畫面狀態請求失敗: {}", e);
                     } else {
+                        rtt.lock().unwrap().mark_sent(std::time::Instant::now());
                         info!("🔄 已發送 get_area 請求 - 範圍: ({:.1},{:.1}) 到 ({:.1},{:.1}) 到主題: {}", 
                               min_x, min_y, max_x, max_y, topic);
                     }
                 }
             });
-            
-            self.screen_request_handle = Some(handle);
         }
         
         Ok(())
@@ -435,25 +2374,320 @@ impl GameClient {
     
     /// 斷開連接
     pub async fn disconnect(&mut self) -> Result<()> {
-        // 停止畫面請求循環
-        if let Some(handle) = self.screen_request_handle.take() {
-            handle.abort();
+        // 先停止畫面請求循環與心跳，之後不再有新的訊息排入
+        if self.tasks.stop("screen_request", SHUTDOWN_GRACE).await {
             info!("已停止畫面狀態請求循環");
         }
+        self.tasks.stop("heartbeat", SHUTDOWN_GRACE).await;
+        self.tasks.stop("time_sync", SHUTDOWN_GRACE).await;
+        self.tasks.stop("state_hash", SHUTDOWN_GRACE).await;
         
         if let Some(client) = &self.client {
             // 發送離開遊戲訊息
-            if self.state == ClientState::InGame {
+            if self.state.in_game() {
                 let _ = self.send_player_action("leave_game", serde_json::json!({})).await;
             }
             
+            // 正常送出 DISCONNECT 後 broker 不會發佈遺囑
             client.disconnect().await?;
         }
         
+        // 等待事件循環送完 leave_game 與 DISCONNECT，逾時（例如斷線重連中）才要求它停止，不再重連
+        if !self.tasks.wait("mqtt_loop", GRACEFUL_LEAVE_TIMEOUT).await {
+            warn!("等待離開訊息送出逾時，停止事件循環");
+        }
+        let report = self.tasks.shutdown(SHUTDOWN_GRACE).await;
+        if !report.aborted.is_empty() {
+            warn!("強制中止了 {} 個未結束的背景任務: {}", report.aborted.len(), report.aborted.join(", "));
+        }
         self.state = ClientState::Disconnected;
+        self.resume_state = None;
         self.client = None;
         
+        // 會話報告：本次連線的技能施放統計
+        let cast_stats = self.cast_stats();
+        if !cast_stats.abilities().is_empty() {
+            info!("🪄 本次會話的技能施放統計:");
+            for line in cast_stats.table() {
+                info!("  {}", line);
+            }
+        }
+        let prediction = self.movement.stats();
+        if let Some(average) = prediction.average_error() {
+            info!("🧭 移動預測校正 {} 次（直接對齊 {} 次），平均誤差 {:.1}，最大誤差 {:.1}",
+                prediction.corrections, prediction.snaps, average, prediction.max_error);
+        }
         info!("已斷開與遊戲服務器的連接");
         Ok(())
     }
-}
\ No newline at end of file
+    
+    /// 模擬異常斷線：停止事件循環而不送出 leave_game 與 DISCONNECT，由 broker 發佈遺囑
+    pub fn drop_connection(&mut self) {
+        self.tasks.cancel_all();
+        self.state = ClientState::Disconnected;
+        self.resume_state = None;
+        self.client = None;
+        warn!("⚡ 已模擬異常斷線，broker 將代為發佈遺囑");
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rumqttc::Transport;
+
+    #[tokio::test]
+    async fn restores_and_flushes_actions_saved_by_a_previous_run() {
+        let path = std::env::temp_dir().join(format!("omobaf_restored_queue_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut previous = crate::reconnect::LinkState { journal: Some(path.clone()), ..Default::default() };
+        previous.enqueue("td/p1/action".to_string(), b"first".to_vec());
+        previous.enqueue("td/p1/action".to_string(), b"second".to_vec());
+
+        let mut client = GameClient::new(GameClientConfig::default());
+        client.configure_offline_queue(&OfflineQueueConfig { max_size: 10, path: Some(path.display().to_string()) });
+        assert_eq!(client.restored_actions.len(), 2);
+        assert_eq!(client.queued_actions(), 0, "進入遊戲前不擋住其他操作");
+
+        let (mqtt, _connection) = AsyncClient::new(MqttOptions::new("test", "127.0.0.1", 1883), 10);
+        client.client = Some(mqtt);
+        client.flush_restored_actions().await;
+        assert!(client.restored_actions.is_empty());
+        assert_eq!(client.queued_actions(), 0);
+        assert!(!path.exists(), "補送完畢後刪除保存檔");
+    }
+
+    #[tokio::test]
+    async fn queues_actions_while_reconnecting_up_to_limit() {
+        let mut client = GameClient::new(GameClientConfig::default());
+        client.configure_offline_queue(&OfflineQueueConfig { max_size: 2, path: None });
+        let (mqtt, _connection) = AsyncClient::new(MqttOptions::new("test", "127.0.0.1", 1883), 10);
+        client.client = Some(mqtt);
+        client.state = ClientState::InGame;
+        client.link.lock().unwrap().reconnecting = Some(1);
+        client.sync_link_state();
+
+        let attack = serde_json::json!({ "target_position": [1.0, 2.0], "attack_type": "basic" });
+        client.perform_action("attack", attack.clone()).await.unwrap();
+        client.perform_action("attack", attack.clone()).await.unwrap();
+        assert_eq!(client.queued_actions(), 2);
+        assert_eq!(client.expectations().pending(), 0);
+
+        let error = client.perform_action("attack", attack).await.unwrap_err();
+        assert!(error.to_string().contains("離線佇列已滿"));
+        assert_eq!(client.queued_actions(), 2);
+    }
+
+    #[tokio::test]
+    async fn restores_session_when_server_is_lost() {
+        let profile = std::env::temp_dir().join(format!("omobaf_restore_profile_{}.toml", std::process::id()));
+        let write_profile = |name: &str| std::fs::write(&profile, format!("name = \"{}\"\n[root]\ntype = \"retreat\"\n", name)).unwrap();
+        write_profile("before");
+        let mut client = GameClient::new(GameClientConfig::default());
+        client.configure_bot(&BotConfig { profile: Some(profile.display().to_string()) });
+        client.configure_liveness(&LivenessConfig { stale_secs: 5, lost_secs: 30 });
+        let (mqtt, _connection) = AsyncClient::new(MqttOptions::new("test", "127.0.0.1", 1883), 64);
+        client.client = Some(mqtt);
+        client.state = ClientState::InGame;
+        let mut state = client.get_game_state().clone();
+        state.last_update = std::time::SystemTime::now() - Duration::from_secs(60);
+        let shared = std::sync::Arc::new(tokio::sync::Mutex::new(state));
+        client.shared_game_state = Some(shared.clone());
+
+        // 失去伺服器：重新握手並重新載入行為樹，之後的操作排入佇列
+        write_profile("after");
+        client.tick().await.unwrap();
+        assert!(matches!(client.get_state(), ClientState::ServerLost(_)));
+        assert!(client.last_restore.is_some());
+        assert_eq!(client.behavior.name(), "after");
+        let attack = serde_json::json!({ "target_position": [1.0, 2.0], "attack_type": "basic" });
+        client.perform_action("attack", attack).await.unwrap();
+        assert_eq!(client.queued_actions(), 1);
+        assert!(!client.restore_due(std::time::Instant::now()), "重試前等待 lost_secs");
+        assert!(client.restore_due(std::time::Instant::now() + Duration::from_secs(30)));
+
+        // 資料恢復後不再重試，下一次恢復會話時補送佇列
+        shared.lock().await.last_update = std::time::SystemTime::now();
+        client.tick().await.unwrap();
+        assert_eq!(client.get_state(), &ClientState::InGame);
+        assert!(!client.restore_due(std::time::Instant::now() + Duration::from_secs(30)));
+        client.restore_session().await.unwrap();
+        assert_eq!(client.queued_actions(), 0);
+        std::fs::remove_file(&profile).unwrap();
+    }
+
+    #[test]
+    fn observer_options_carry_credentials_and_tls() {
+        let client = GameClient::new(GameClientConfig {
+            username: Some("qa".to_string()),
+            password: Some("secret".to_string()),
+            tls: TlsConfig { enabled: true, ca_cert: Some("data/tls/test_ca.pem".to_string()), server_name: Some("broker.local".to_string()), ..TlsConfig::default() },
+            ..GameClientConfig::default()
+        });
+        let other = BrokerEndpoint { host: "10.0.0.2".to_string(), port: 8883 };
+        for options in [client.observer_options("selftest").unwrap(), client.endpoint_options(&other, "selftest").unwrap()] {
+            assert_eq!(options.credentials(), Some(("qa".to_string(), "secret".to_string())));
+            assert!(matches!(options.transport(), Transport::Tls(_)));
+        }
+        assert_eq!(client.endpoint_options(&other, "selftest").unwrap().broker_address(), ("10.0.0.2".to_string(), 8883));
+    }
+
+    fn recall_client(macros: MacrosConfig) -> GameClient {
+        let mut client = GameClient::new(GameClientConfig { dry_run: true, ..GameClientConfig::default() });
+        client.configure_macros(&MacrosConfig { spawn_point: Some((100.0, 100.0)), recall_channel_secs: 0.2, ..macros });
+        client.state = ClientState::InGame;
+        client.player_simulator.current_position = Vec2::new(250.0, 100.0);
+        client.game_state.local_player.position = Vec2::new(250.0, 100.0);
+        client.game_state.local_player.health = (100.0, 100.0);
+        client
+    }
+
+    #[tokio::test]
+    async fn recall_is_interrupted_by_damage() {
+        let mut client = recall_client(MacrosConfig::default());
+        let shared = std::sync::Arc::new(tokio::sync::Mutex::new(client.get_game_state().clone()));
+        client.shared_game_state = Some(shared.clone());
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(50)).await;
+            shared.lock().await.local_player.health.0 = 80.0;
+        });
+
+        let error = client.recall().await.unwrap_err();
+        assert!(error.to_string().contains("回城引導被打斷"));
+        assert_eq!(client.get_game_state().local_player.position, Vec2::new(250.0, 100.0), "被打斷時不回到出生點");
+    }
+
+    #[tokio::test]
+    async fn shop_trip_returns_to_the_lane_point_or_where_it_left() {
+        // 未設置線上位置時回到出發前的位置
+        let mut client = recall_client(MacrosConfig { shop_items: vec!["potion".to_string()], ..MacrosConfig::default() });
+        client.shop_trip().await.unwrap();
+        assert_eq!(client.player_simulator.current_position, Vec2::new(250.0, 100.0), "商店沒有的物品略過");
+
+        let mut client = recall_client(MacrosConfig { lane_point: Some((200.0, 150.0)), ..MacrosConfig::default() });
+        client.recall().await.unwrap();
+        assert_eq!(client.get_game_state().local_player.position, Vec2::new(100.0, 100.0), "回城直接傳送到出生點");
+        client.shop_trip().await.unwrap();
+        assert_eq!(client.player_simulator.current_position, Vec2::new(200.0, 150.0));
+    }
+
+    #[tokio::test]
+    async fn shop_trip_spends_gold_and_fills_slots() {
+        let items = ["health_potion", "health_potion", "swift_boots", "mana_potion"].map(String::from).to_vec();
+        let mut client = recall_client(MacrosConfig { shop_items: items, ..MacrosConfig::default() });
+        client.game_state.local_player.gold = 200;
+        client.game_state.local_player.items.clear();
+        client.shop_trip().await.unwrap();
+
+        assert_eq!(client.game_state.local_player.gold, 40, "買不起的疾風草鞋略過，繼續購買後面的物品");
+        let bought: Vec<(&str, u8, u32)> = client.game_state.local_player.items.iter()
+            .map(|item| (item.item_id.as_str(), item.slot, item.charges))
+            .collect();
+        assert_eq!(bought, [("health_potion", 1, 2), ("mana_potion", 2, 1)], "同種道具疊加在同一格");
+        assert_eq!(client.player_simulator.current_position, Vec2::new(250.0, 100.0));
+    }
+
+    #[tokio::test]
+    async fn auto_recall_triggers_below_the_health_ratio() {
+        let mut client = recall_client(MacrosConfig { auto_recall_below: 0.3, ..MacrosConfig::default() });
+        assert!(!client.needs_auto_recall().await.unwrap());
+        client.game_state.local_player.health = (29.0, 100.0);
+        assert!(client.needs_auto_recall().await.unwrap());
+        client.game_state.local_player.health = (0.0, 0.0);
+        assert!(!client.needs_auto_recall().await.unwrap(), "沒有生命上限時不判斷");
+
+        let mut client = recall_client(MacrosConfig { auto_recall_below: 0.0, ..MacrosConfig::default() });
+        client.game_state.local_player.health = (1.0, 100.0);
+        assert!(!client.needs_auto_recall().await.unwrap(), "門檻為 0 時停用");
+    }
+
+    #[tokio::test]
+    async fn buying_and_selling_keep_the_local_action_bookkeeping() {
+        let mut client = GameClient::new(GameClientConfig { dry_run: true, ..GameClientConfig::default() });
+        client.state = ClientState::InGame;
+        client.game_state.local_player.gold = 200;
+        client.game_state.viewport.width = 0.0;
+
+        let slot = client.buy_item("health_potion").await.unwrap();
+        assert_eq!(client.game_state.local_player.gold, 150);
+        assert!(client.game_state.local_player.items.iter().any(|item| item.slot == slot && item.item_id == "health_potion"));
+        let violations = crate::invariants::check(&client.game_state);
+        assert!(!violations.is_empty());
+        assert!(client.game_state.invariants.observe(violations).is_empty(), "購買時的不變量檢查結果保留在目前的狀態中");
+
+        let (sold, refund) = client.sell_item(slot).await.unwrap();
+        assert_eq!(sold, "health_potion");
+        assert_eq!(client.game_state.local_player.gold, 150 + refund);
+        assert!(client.buy_item("swift_boots").await.is_err(), "金錢不足時不送出");
+        assert_eq!(client.game_state.local_player.gold, 150 + refund);
+    }
+
+    #[test]
+    fn registers_leave_game_will_unless_overridden() {
+        let mut client = GameClient::new(GameClientConfig { player_name: "p1".to_string(), ..GameClientConfig::default() });
+        let will = client.will.clone().unwrap();
+        assert_eq!(will.topic, "td/p1/action");
+        let message: serde_json::Value = client.config.wire_format.decode(&will.message).unwrap();
+        assert_eq!(message["a"], "leave_game");
+        assert_eq!(will.qos, QosConfig::qos(client.config.qos.actions));
+
+        client.set_will("td/p1/test_will", b"dropped".to_vec());
+        let will = client.will.clone().unwrap();
+        assert_eq!(will.topic, "td/p1/test_will");
+        assert_eq!(&will.message[..], b"dropped");
+    }
+
+    #[tokio::test]
+    async fn move_macro_sends_backend_target_coordinates() {
+        let mut client = GameClient::new(GameClientConfig::default());
+        client.state = ClientState::InGame;
+        client.run_macro("F2", &[MacroCommand::Move(350.0, 260.0), MacroCommand::Attack(360.0, 260.0)]).await.unwrap();
+        assert_eq!(client.get_game_state().local_player.position, Vec2::new(350.0, 260.0));
+    }
+
+    #[tokio::test]
+    async fn live_view_moves_are_normalized_and_predicted() {
+        let mut client = GameClient::new(GameClientConfig::default());
+        client.state = ClientState::InGame;
+        client.configure_prediction(&PredictionConfig { enabled: true, ..PredictionConfig::default() });
+        let start = client.get_game_state().local_player.position;
+        let (handle, task) = crate::client_handle::GameClientHandle::spawn(client);
+
+        // 預測器沿移動方向推進，不直接跳到目標：本地位置不變，之後每幀往目標前進
+        let advance = |handle: crate::client_handle::GameClientHandle| async move {
+            handle.with(move |client| Box::pin(async move {
+                let now = std::time::Instant::now();
+                client.movement.frame(start, now);
+                let moved = client.movement.frame(start, now + Duration::from_millis(100));
+                (client.get_game_state().local_player.position, moved)
+            })).await.unwrap()
+        };
+        let target = Vec2::new(350.0, 260.0);
+        crate::interactive::commands::apply_view_input(&handle, crate::terminal_view::UserInput::Move(target)).await.unwrap();
+        let (position, moved) = advance(handle.clone()).await;
+        assert_eq!(position, start);
+        assert!(moved.distance(target) < start.distance(target));
+
+        let target = Vec2::new(start.x, start.y + 100.0);
+        handle.perform_action("move", serde_json::json!({ "x": target.x, "y": target.y })).await.unwrap();
+        let (position, moved) = advance(handle.clone()).await;
+        assert_eq!(position, start, "x/y 參數也會正規化");
+        assert!(moved.distance(target) < start.distance(target));
+        handle.shutdown().await;
+        task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn spectating_follows_the_watched_player_and_sends_nothing() {
+        let config = GameClientConfig { player_name: "viewer".to_string(), spectate: Some("bot1".to_string()), ..GameClientConfig::default() };
+        let topics = game_topics(&config);
+        assert!(topics.iter().any(|(topic, _)| topic == "td/bot1/screen_response"));
+        assert!(topics.iter().any(|(topic, _)| topic == "td/viewer/pong"));
+
+        let mut client = GameClient::new(config);
+        assert_eq!(client.get_game_state().local_player.name, "bot1");
+        client.state = ClientState::InGame;
+        let error = client.perform_action("move", serde_json::json!({ "x": 1.0, "y": 2.0 })).await.unwrap_err();
+        assert!(error.downcast_ref::<PermissionDenied>().is_some());
+        assert!(client.ensure_writable("update_viewport").is_err());
+    }
+}
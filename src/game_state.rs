@@ -1,13 +1,32 @@
 /// 遊戲狀態管理
 /// 
 /// 維護本地遊戲狀態副本，用於驗證後端同步
-// use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::time::SystemTime;
 use log::{info, warn, debug};
 use vek::Vec2;
 
-use crate::mqtt_handler::{PlayerState, AbilityData, SummonData};
+use crate::mqtt_handler::{PlayerState, AbilityData, SummonData, CombatData, KillData, ManaData};
+use crate::credit::{ContributionTimeline, KillCredit};
+use crate::death_recap::{CombatLog, DamageEvent};
+use crate::dummy::DummyTracker;
+use crate::schema::SchemaReport;
+use crate::invariants::InvariantTracker;
+use crate::time_sync::ServerClock;
+use crate::progression::{ExperienceData, GoldData, Progression};
+use crate::buffs::{self, Buff, StatusEffectData};
+use crate::shop::{self, ShopCatalog, ShopItem};
+use crate::scoreboard::{DeathData, LifeState, Scoreboard};
+use crate::terrain::TerrainChunks;
+use crate::world_map::WorldMemory;
+use anyhow::{anyhow, bail, Result};
+
+/// 後端送來魔力前的預設魔力上限
+const DEFAULT_MAX_MANA: f32 = 300.0;
+
+/// 後端送來魔力前的預設每秒魔力回復
+const DEFAULT_MANA_REGEN: f32 = 1.5;
 
 /// 遊戲狀態管理器
 #[derive(Debug, Clone)]
@@ -18,12 +37,128 @@ pub struct GameState {
     pub other_players: HashMap<String, PlayerState>,
     /// 遊戲實體
     pub entities: HashMap<u32, Entity>,
+    /// 投射物的飛行速度（世界單位/秒，來自投射物廣播，畫面外推用）
+    pub projectile_velocities: HashMap<u32, Vec2<f32>>,
+    /// 最後套用的畫面序號（增量更新的基準，未同步時為 None）
+    pub screen_seq: Option<u64>,
     /// 最後更新時間
     pub last_update: SystemTime,
     /// 狀態差異計數
     pub sync_errors: u64,
+    /// 不符合協議結構而被拒絕的訊息數
+    pub schema_errors: u64,
+    /// 依訊息類型統計的欄位差異
+    pub schema_report: SchemaReport,
+    /// 目前被破壞的狀態不變量（除錯建置）
+    pub invariants: InvariantTracker,
     /// 虛擬螢幕範圍
     pub viewport: Viewport,
+    /// 地圖世界邊界（來自配置或後端）
+    pub world_bounds: Option<WorldBounds>,
+    /// 地圖目錄中的塔與主堡（來自配置）
+    pub structures: Vec<MapStructure>,
+    /// 本地英雄受到的傷害與死亡回顧
+    pub combat_log: CombatLog,
+    /// 所有單位的傷害貢獻與擊殺歸屬驗證結果
+    pub credits: ContributionTimeline,
+    /// 訓練假人與本地英雄對假人的 DPS
+    pub dummies: DummyTracker,
+    /// 本地英雄各技能最近一次造成傷害的時間（連段延遲量測用）
+    pub ability_hits: HashMap<String, SystemTime>,
+    /// 估計的伺服器時鐘
+    pub server_clock: ServerClock,
+    /// 本地英雄的金錢收支與升級記錄
+    pub progression: Progression,
+    /// 各實體身上的增益與減益（畫面快照不帶狀態效果，獨立保存）
+    pub entity_buffs: HashMap<u32, Vec<Buff>>,
+    /// 選擇的目標實體 ID（點擊或 Tab 選擇）
+    pub selected_target: Option<u32>,
+    /// 其他玩家的存活狀態與重生倒數（畫面快照會整筆取代玩家狀態，獨立保存）
+    pub player_lives: HashMap<String, LifeState>,
+    /// 各玩家的擊殺、死亡與助攻
+    pub scoreboard: Scoreboard,
+    /// 後端送來的地形（靜態，不隨畫面快照清除）
+    pub terrain: TerrainChunks,
+    /// 看過的建築與各區塊最後看見的時間（鏡頭移開後繼續顯示）
+    pub world_map: WorldMemory,
+}
+
+/// 地圖世界邊界（遊戲世界單位）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorldBounds {
+    pub min_x: f32,
+    pub min_y: f32,
+    pub max_x: f32,
+    pub max_y: f32,
+}
+
+impl WorldBounds {
+    /// 位置是否在邊界內
+    pub fn contains(&self, position: Vec2<f32>) -> bool {
+        position.x >= self.min_x && position.x <= self.max_x
+            && position.y >= self.min_y && position.y <= self.max_y
+    }
+    
+    /// 將位置限制在邊界內
+    pub fn clamp(&self, position: Vec2<f32>) -> Vec2<f32> {
+        Vec2::new(
+            position.x.clamp(self.min_x, self.max_x),
+            position.y.clamp(self.min_y, self.max_y),
+        )
+    }
+}
+
+/// 地圖目錄中的建築種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StructureKind {
+    Tower,
+    Base,
+}
+
+/// 建築所屬陣營
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Team {
+    Blue,
+    Red,
+    #[default]
+    Neutral,
+}
+
+impl Team {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Team::Blue => "blue",
+            Team::Red => "red",
+            Team::Neutral => "neutral",
+        }
+    }
+}
+
+/// 地圖目錄中的建築（位置與佔地，終端視圖以多格圖樣顯示）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MapStructure {
+    pub kind: StructureKind,
+    #[serde(default)]
+    pub team: Team,
+    /// 中心位置（後端座標）
+    pub position: (f32, f32),
+    /// 佔地邊長（終端格數 2 或 3，未設置時塔為 2、主堡為 3）
+    #[serde(default)]
+    pub footprint: Option<usize>,
+}
+
+impl MapStructure {
+    /// 佔地邊長
+    pub fn footprint(&self) -> usize {
+        self.footprint.unwrap_or(match self.kind {
+            StructureKind::Tower => 2,
+            StructureKind::Base => 3,
+        })
+    }
 }
 
 /// 虛擬螢幕範圍
@@ -61,11 +196,21 @@ pub struct LocalPlayer {
     pub hero_type: String,
     pub position: Vec2<f32>,
     pub health: (f32, f32),  // (current, max)
+    pub mana: (f32, f32),    // (current, max)
+    /// 每秒魔力回復
+    pub mana_regen: f32,
     pub abilities: Vec<AbilityState>,
-    pub items: Vec<ItemState>,       // 道具欄 (1-9 號位)
+    pub items: Vec<ItemState>,       // 道具欄 (1-6 號位)
     pub summons: Vec<SummonState>,
     pub level: u8,
     pub experience: u32,
+    /// 升到下一級所需的累積經驗（後端未提供時為 None）
+    pub next_level_experience: Option<u32>,
+    pub gold: u32,
+    /// 身上的增益與減益
+    pub buffs: Vec<Buff>,
+    /// 存活狀態與重生倒數
+    pub life: LifeState,
 }
 
 /// 技能狀態
@@ -85,6 +230,8 @@ pub struct ItemState {
     pub name: String,
     pub slot: u8,           // 道具欄位置 (1-9)
     pub charges: u32,       // 使用次數
+    /// 使用後的冷卻秒數（來自商店目錄）
+    pub cooldown: f32,
     pub cooldown_remaining: f32,
     pub is_available: bool,
     pub last_used: Option<SystemTime>,
@@ -119,6 +266,8 @@ pub struct Entity {
     pub position: Vec2<f32>,
     pub health: (f32, f32),
     pub owner: Option<String>,
+    /// 後端附帶的除錯標籤（例如 AI 狀態、路徑節點 ID）
+    pub debug_label: Option<String>,
 }
 
 /// 實體類型
@@ -128,6 +277,8 @@ pub enum EntityType {
     Summon(String),  // 召喚物類型
     Projectile,      // 投射物
     Effect,          // 特效
+    Creep,           // 小兵
+    Tower,           // 防禦塔
 }
 
 impl Viewport {
@@ -220,11 +371,6 @@ impl Viewport {
         self.center = player_pos;
     }
     
-    /// 設置縮放
-    pub fn set_zoom(&mut self, zoom: f32) {
-        self.zoom = zoom.clamp(0.5, 3.0);
-    }
-    
     /// 設置視窗大小
     pub fn set_size(&mut self, width: f32, height: f32) {
         self.width = width;
@@ -240,11 +386,17 @@ impl GameState {
             hero_type: hero_type.clone(),
             position: Vec2::zero(),
             health: (100.0, 100.0),
+            mana: (DEFAULT_MAX_MANA, DEFAULT_MAX_MANA),
+            mana_regen: DEFAULT_MANA_REGEN,
             abilities: Self::init_hero_abilities(&hero_type),
-            items: Self::init_default_items(),
+            items: ShopCatalog::default().starting_inventory(),
             summons: Vec::new(),
             level: 1,
             experience: 0,
+            next_level_experience: None,
+            gold: 0,
+            buffs: Vec::new(),
+            life: LifeState::default(),
         };
         
         info!("初始化遊戲狀態 - 玩家: {}, 英雄: {}", player_name, hero_type);
@@ -253,73 +405,34 @@ impl GameState {
             local_player,
             other_players: HashMap::new(),
             entities: HashMap::new(),
+            projectile_velocities: HashMap::new(),
+            screen_seq: None,
             last_update: SystemTime::now(),
             sync_errors: 0,
+            schema_errors: 0,
+            schema_report: SchemaReport::default(),
+            invariants: InvariantTracker::default(),
             viewport: Viewport::for_screen(1920, 1080), // 預設 1920x1080 解析度
+            world_bounds: None,
+            structures: Vec::new(),
+            combat_log: CombatLog::default(),
+            credits: ContributionTimeline::default(),
+            dummies: DummyTracker::default(),
+            ability_hits: HashMap::new(),
+            server_clock: ServerClock::default(),
+            progression: Progression::default(),
+            entity_buffs: HashMap::new(),
+            selected_target: None,
+            player_lives: HashMap::new(),
+            scoreboard: Scoreboard::default(),
+            terrain: TerrainChunks::default(),
+            world_map: WorldMemory::default(),
         }
     }
     
-    /// 初始化默認道具
-    fn init_default_items() -> Vec<ItemState> {
-        vec![
-            ItemState {
-                item_id: "health_potion".to_string(),
-                name: "生命藥水".to_string(),
-                slot: 1,
-                charges: 5,
-                cooldown_remaining: 0.0,
-                is_available: true,
-                last_used: None,
-            },
-            ItemState {
-                item_id: "mana_potion".to_string(),
-                name: "魔力藥水".to_string(),
-                slot: 2,
-                charges: 3,
-                cooldown_remaining: 0.0,
-                is_available: true,
-                last_used: None,
-            },
-            ItemState {
-                item_id: "teleport_scroll".to_string(),
-                name: "傳送卷軸".to_string(),
-                slot: 3,
-                charges: 2,
-                cooldown_remaining: 0.0,
-                is_available: true,
-                last_used: None,
-            },
-            ItemState {
-                item_id: "smoke_bomb".to_string(),
-                name: "煙霧彈".to_string(),
-                slot: 4,
-                charges: 4,
-                cooldown_remaining: 0.0,
-                is_available: true,
-                last_used: None,
-            },
-        ]
-    }
-    
     /// 初始化英雄技能
     fn init_hero_abilities(hero_type: &str) -> Vec<AbilityState> {
-        let ability_ids = match hero_type {
-            "saika_magoichi" => vec![
-                "sniper_mode",
-                "saika_reinforcements", 
-                "rain_iron_cannon",
-                "three_stage_technique"
-            ],
-            "date_masamune" => vec![
-                "flame_blade",
-                "fire_dash",
-                "flame_assault", 
-                "matchlock_gun"
-            ],
-            _ => vec![]
-        };
-        
-        ability_ids.into_iter().map(|id| AbilityState {
+        crate::hero_catalog::hero_ability_ids(hero_type).into_iter().map(|id| AbilityState {
             ability_id: id.to_string(),
             level: 1,
             cooldown_remaining: 0.0,
@@ -328,6 +441,22 @@ impl GameState {
         }).collect()
     }
     
+    /// 切換本地玩家的英雄，依目錄重建技能欄並重置英雄成長
+    pub fn switch_hero(&mut self, hero_type: &str) {
+        let player = &mut self.local_player;
+        player.hero_type = hero_type.to_string();
+        player.abilities = Self::init_hero_abilities(hero_type);
+        player.summons.clear();
+        player.health = (100.0, 100.0);
+        player.mana = (DEFAULT_MAX_MANA, DEFAULT_MAX_MANA);
+        player.mana_regen = DEFAULT_MANA_REGEN;
+        player.level = 1;
+        player.experience = 0;
+        player.next_level_experience = None;
+        player.buffs.clear();
+        info!("切換英雄 - 玩家: {}, 英雄: {}", player.name, hero_type);
+    }
+    
     /// 更新玩家位置
     pub fn update_player_position(&mut self, player_name: &str, x: f32, y: f32) {
         if player_name == self.local_player.name {
@@ -370,16 +499,204 @@ impl GameState {
         if player_name == self.local_player.name {
             self.local_player.health = (current, max);
             debug!("更新本地玩家生命值: {}/{}", current, max);
+            if current > 0.0 {
+                self.combat_log.mark_alive();
+            }
         } else {
             if let Some(player) = self.other_players.get_mut(player_name) {
                 player.health = (current, max);
             }
             debug!("更新其他玩家 {} 生命值: {}/{}", player_name, current, max);
         }
+        if current <= 0.0 {
+            self.mark_dead(player_name, None);
+        } else {
+            self.mark_respawned(player_name);
+        }
         
         self.last_update = SystemTime::now();
     }
     
+    /// 更新玩家魔力（只追蹤本地英雄）
+    pub fn update_player_mana(&mut self, player_name: &str, mana: &ManaData) {
+        if player_name != self.local_player.name {
+            return;
+        }
+        self.local_player.mana = (mana.current, mana.max);
+        if let Some(regen) = mana.regen {
+            self.local_player.mana_regen = regen;
+        }
+        self.last_update = SystemTime::now();
+    }
+    
+    /// 處理戰鬥日誌（所有單位計入傷害貢獻，本地英雄另外記錄死亡回顧，對訓練假人的傷害計入 DPS）
+    pub fn apply_combat_event(&mut self, target: &str, combat: &CombatData) {
+        self.credits.record_damage(target, &combat.source, combat.amount, SystemTime::now());
+        let own_summon = self.local_player.summons.iter().any(|summon| summon.id.to_string() == combat.source);
+        if combat.source == self.local_player.name || own_summon {
+            self.dummies.record(target, combat.ability.as_deref(), combat.amount, SystemTime::now());
+            if let Some(ability) = &combat.ability {
+                self.ability_hits.insert(ability.clone(), SystemTime::now());
+            }
+        }
+        if target != self.local_player.name {
+            debug!("{} 受到 {} 的傷害 {:.0}", target, combat.source, combat.amount);
+            return;
+        }
+        self.combat_log.record_damage(DamageEvent {
+            time: SystemTime::now(),
+            source: combat.source.clone(),
+            ability: combat.ability.clone(),
+            amount: combat.amount,
+        });
+        if combat.fatal {
+            self.mark_dead(target, None);
+        }
+        self.last_update = SystemTime::now();
+    }
+    
+    /// 處理金錢訊息（只追蹤本地英雄）
+    pub fn apply_gold(&mut self, player_name: &str, gold: &GoldData) {
+        if player_name != self.local_player.name {
+            return;
+        }
+        let change = self.progression.record_gold(gold);
+        self.local_player.gold = gold.gold;
+        debug!("金錢 {} ({:+}，{})", gold.gold, change, gold.reason.as_deref().unwrap_or("-"));
+        self.last_update = SystemTime::now();
+    }
+    
+    /// 處理狀態效果訊息（帶實體 ID 時套用到該實體，否則只追蹤本地英雄）
+    pub fn apply_status_effect(&mut self, player_name: &str, action: &str, effect: &StatusEffectData) {
+        let target = match effect.entity {
+            Some(id) => self.entity_buffs.entry(id).or_default(),
+            None if player_name == self.local_player.name => &mut self.local_player.buffs,
+            None => return,
+        };
+        buffs::apply(target, action, effect);
+        debug!("狀態效果 {} {} ({})", action, effect.id, effect.entity.map_or_else(|| player_name.to_string(), |id| id.to_string()));
+        self.last_update = SystemTime::now();
+    }
+    
+    /// 購買道具：扣除金錢並放入道具欄，返回放入的格子（金錢不足或道具欄已滿時不變動）
+    pub fn buy_item(&mut self, item: &ShopItem) -> Result<u8> {
+        let player = &mut self.local_player;
+        if player.gold < item.cost {
+            bail!("金錢不足：{} 需要 {}，目前 {}", item.name, item.cost, player.gold);
+        }
+        let slot = shop::stock(&mut player.items, item)?;
+        player.gold -= item.cost;
+        debug!("購買道具: {} -> {} 號位 (剩餘金錢 {})", item.id, slot, player.gold);
+        self.last_update = SystemTime::now();
+        Ok(slot)
+    }
+    
+    /// 賣出一格道具，返回賣出的道具與退還的金錢
+    pub fn sell_item(&mut self, slot: u8, catalog: &ShopCatalog) -> Result<(ItemState, u32)> {
+        let player = &mut self.local_player;
+        let item = player.items.iter().find(|item| item.slot == slot)
+            .ok_or_else(|| anyhow!("{} 號位沒有道具", slot))?;
+        let entry = catalog.find(&item.item_id)
+            .ok_or_else(|| anyhow!("道具 {} 不在商店目錄中", item.item_id))?;
+        let refund = shop::refund(entry, item.charges);
+        let sold = shop::unstock(&mut player.items, slot)?;
+        player.gold += refund;
+        debug!("賣出道具: {} ({} 號位，退還 {})", sold.item_id, slot, refund);
+        self.last_update = SystemTime::now();
+        Ok((sold, refund))
+    }
+    
+    /// 處理經驗與等級訊息（只追蹤本地英雄）
+    pub fn apply_experience(&mut self, player_name: &str, experience: &ExperienceData) {
+        if player_name != self.local_player.name {
+            return;
+        }
+        let player = &mut self.local_player;
+        if experience.level > player.level {
+            info!("⬆️ 升級: {} -> {}", player.level, experience.level);
+        }
+        self.progression.record_level(player.level, experience.level, SystemTime::now());
+        player.level = experience.level;
+        player.experience = experience.experience;
+        player.next_level_experience = experience.next_level;
+        self.last_update = SystemTime::now();
+    }
+    
+    /// 處理擊殺訊息，以觀測到的傷害貢獻驗證擊殺與助攻歸屬
+    pub fn apply_kill_credit(&mut self, victim: &str, kill: &KillData) {
+        let credit = KillCredit {
+            victim: victim.to_string(),
+            killer: kill.killer.clone(),
+            assists: kill.assists.clone(),
+        };
+        let check = self.credits.verify_kill(credit, SystemTime::now());
+        if check.passed() {
+            info!("⚔️ {} 擊殺 {}（助攻: {}）歸屬一致", kill.killer, victim, kill.assists.join(", "));
+        }
+        self.scoreboard.record_kill(&kill.killer, &kill.assists);
+        self.mark_dead(victim, None);
+        self.last_update = SystemTime::now();
+    }
+    
+    /// 處理死亡與重生訊息（`a` 為 `death` 或 `respawn`）
+    pub fn apply_death_event(&mut self, player_name: &str, action: &str, death: &DeathData) {
+        if action == "respawn" {
+            self.mark_respawned(player_name);
+            if let Some((x, y)) = death.position {
+                self.update_player_position(player_name, x, y);
+            }
+        } else {
+            self.mark_dead(player_name, death.respawn_time);
+        }
+        self.last_update = SystemTime::now();
+    }
+    
+    /// 玩家的存活狀態（未記錄的其他玩家視為存活）
+    pub fn life_of(&self, player_name: &str) -> LifeState {
+        if player_name == self.local_player.name {
+            self.local_player.life
+        } else {
+            self.player_lives.get(player_name).copied().unwrap_or_default()
+        }
+    }
+    
+    fn life_mut(&mut self, player_name: &str) -> &mut LifeState {
+        if player_name == self.local_player.name {
+            &mut self.local_player.life
+        } else {
+            self.player_lives.entry(player_name.to_string()).or_default()
+        }
+    }
+    
+    /// 標記陣亡，從存活變為陣亡時記一次死亡（本地英雄另外整理死亡回顧）
+    fn mark_dead(&mut self, player_name: &str, respawn_time: Option<f32>) {
+        if !self.life_mut(player_name).die(respawn_time) {
+            return;
+        }
+        self.scoreboard.record_death(player_name);
+        if player_name == self.local_player.name {
+            self.record_local_death();
+        } else {
+            info!("💀 {} 陣亡", player_name);
+        }
+    }
+    
+    /// 標記重生
+    fn mark_respawned(&mut self, player_name: &str) {
+        if self.life_mut(player_name).respawn() {
+            info!("✨ {} 重生", player_name);
+        }
+    }
+    
+    /// 記錄本地英雄死亡並整理死亡回顧
+    fn record_local_death(&mut self) {
+        if self.combat_log.mark_death(SystemTime::now()) {
+            if let Some(recap) = self.combat_log.last_recap() {
+                warn!("💀 英雄陣亡 - {} 次傷害，共 {:.0}", recap.events.len(), recap.total_damage());
+            }
+        }
+    }
+    
     /// 更新召喚物狀態
     pub fn update_summon_state(&mut self, owner: &str, summon_data: &SummonData) {
         if owner == self.local_player.name {
@@ -473,19 +790,11 @@ impl GameState {
                         .find(|a| a.ability_id == ability_id) {
                         ability.is_available = false;
                         ability.last_used = Some(SystemTime::now());
-                        // 設置測試冷卻時間（實際應由服務器提供）
-                        ability.cooldown_remaining = match ability_id {
-                            "sniper_mode" => 8.0,
-                            "saika_reinforcements" => 12.0,
-                            "rain_iron_cannon" => 15.0,
-                            "three_stage_technique" => 20.0,
-                            "flame_blade" => 6.0,
-                            "fire_dash" => 10.0,
-                            "flame_assault" => 18.0,
-                            "matchlock_gun" => 25.0,
-                            _ => 5.0, // 默認冷卻時間
-                        };
-                        debug!("應用本地技能施放: {} (冷卻 {:.1}s)", ability_id, ability.cooldown_remaining);
+                        // 依英雄目錄設置冷卻並扣除魔力（實際應由服務器提供）
+                        ability.cooldown_remaining = crate::hero_catalog::ability_cooldown(ability_id);
+                        let mana = &mut self.local_player.mana;
+                        mana.0 = (mana.0 - crate::hero_catalog::ability_mana_cost(ability_id)).max(0.0);
+                        debug!("應用本地技能施放: {} (冷卻 {:.1}s，剩餘魔力 {:.0})", ability_id, ability.cooldown_remaining, mana.0);
                     }
                 }
             },
@@ -496,14 +805,7 @@ impl GameState {
                         if item.charges > 0 {
                             item.charges -= 1;
                             item.last_used = Some(SystemTime::now());
-                            // 設置測試冷卻時間
-                            item.cooldown_remaining = match item_id {
-                                "health_potion" => 3.0,
-                                "mana_potion" => 2.0,
-                                "teleport_scroll" => 60.0,
-                                "smoke_bomb" => 15.0,
-                                _ => 5.0,
-                            };
+                            item.cooldown_remaining = item.cooldown;
                             item.is_available = false;
                             debug!("使用道具: {} (剩餘 {} 個，冷卻 {:.1}s)", item_id, item.charges, item.cooldown_remaining);
                         }
@@ -528,7 +830,7 @@ impl GameState {
     /// 獲取玩家狀態摘要
     pub fn get_status_summary(&self) -> String {
         format!(
-            "玩家: {} ({}) | 位置: ({:.1}, {:.1}) | 生命值: {:.0}/{:.0} | 召喚物: {} | 同步錯誤: {}",
+            "玩家: {} ({}) | 位置: ({:.1}, {:.1}) | 生命值: {:.0}/{:.0} | 召喚物: {} | 同步錯誤: {} | 結構錯誤: {}",
             self.local_player.name,
             self.local_player.hero_type,
             self.local_player.position.x,
@@ -536,12 +838,31 @@ impl GameState {
             self.local_player.health.0,
             self.local_player.health.1,
             self.local_player.summons.len(),
-            self.sync_errors
+            self.sync_errors,
+            self.schema_errors
         )
     }
     
-    /// 更新技能和道具冷卻時間（每幀調用）
+    /// 更新技能和道具冷卻時間、魔力回復與狀態效果剩餘時間（每幀調用）
     pub fn update_cooldowns(&mut self, delta_time: f32) {
+        // 魔力回復（後端下次送來魔力時以其為準）
+        let player = &mut self.local_player;
+        player.mana.0 = (player.mana.0 + player.mana_regen * delta_time).min(player.mana.1);
+        
+        // 重生倒數
+        player.life.tick(delta_time);
+        for life in self.player_lives.values_mut() {
+            life.tick(delta_time);
+        }
+        self.world_map.tick(delta_time);
+        
+        // 狀態效果到期移除
+        buffs::decay(&mut player.buffs, delta_time);
+        for entity_buffs in self.entity_buffs.values_mut() {
+            buffs::decay(entity_buffs, delta_time);
+        }
+        self.entity_buffs.retain(|_, entity_buffs| !entity_buffs.is_empty());
+        
         // 更新技能冷卻
         for ability in &mut self.local_player.abilities {
             if ability.cooldown_remaining > 0.0 {
@@ -590,4 +911,20 @@ impl GameState {
         
         false
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn world_bounds_contain_edges_and_clamp_each_axis() {
+        let bounds = WorldBounds { min_x: 0.0, min_y: -100.0, max_x: 1000.0, max_y: 500.0 };
+        assert!(bounds.contains(Vec2::new(0.0, -100.0)) && bounds.contains(Vec2::new(1000.0, 500.0)), "邊界上的點算在內");
+        assert!(!bounds.contains(Vec2::new(-0.1, 0.0)));
+        assert!(!bounds.contains(Vec2::new(10.0, 500.1)));
+        assert_eq!(bounds.clamp(Vec2::new(-50.0, 200.0)), Vec2::new(0.0, 200.0));
+        assert_eq!(bounds.clamp(Vec2::new(1200.0, -300.0)), Vec2::new(1000.0, -100.0));
+        assert_eq!(bounds.clamp(Vec2::new(300.0, 200.0)), Vec2::new(300.0, 200.0));
+    }
+}
@@ -0,0 +1,135 @@
+/// 連線心跳
+///
+/// 定期發佈帶序號的 ping 到 `td/{player_name}/ping`，後端以相同序號回覆到 `td/{player_name}/pong`，
+/// 以最近的樣本計算平均與 p95 往返延遲及遺失率，用來判斷同步抖動來自網路還是後端邏輯
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 計算統計用的樣本數
+const WINDOW: usize = 50;
+
+/// 超過此時間仍未收到 pong 的 ping 視為遺失
+const PONG_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 心跳延遲統計
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeartbeatStats {
+    pub last: Duration,
+    pub average: Duration,
+    pub p95: Duration,
+    /// 統計窗口內遺失的 ping 數
+    pub lost: usize,
+    /// 統計窗口內送出的 ping 數
+    pub sent: usize,
+}
+
+impl HeartbeatStats {
+    /// 單行摘要
+    pub fn summary(&self) -> String {
+        format!("延遲 {}ms (平均 {}ms / p95 {}ms) 遺失 {}/{}",
+            self.last.as_millis(), self.average.as_millis(), self.p95.as_millis(), self.lost, self.sent)
+    }
+}
+
+/// 心跳追蹤器
+#[derive(Debug)]
+pub struct HeartbeatTracker {
+    next_seq: u64,
+    /// 尚未收到 pong 的 ping（序號與送出時間）
+    pending: VecDeque<(u64, Instant)>,
+    /// 最近的結果（None 表示遺失）
+    results: VecDeque<Option<Duration>>,
+}
+
+/// 在背景任務間共享的心跳追蹤器
+pub type SharedHeartbeat = Arc<Mutex<HeartbeatTracker>>;
+
+impl Default for HeartbeatTracker {
+    fn default() -> Self {
+        Self {
+            next_seq: 1,
+            pending: VecDeque::new(),
+            results: VecDeque::new(),
+        }
+    }
+}
+
+impl HeartbeatTracker {
+    /// 記錄送出 ping，返回其序號
+    pub fn mark_sent(&mut self, now: Instant) -> u64 {
+        self.expire(now);
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.pending.push_back((seq, now));
+        seq
+    }
+
+    /// 記錄收到 pong，返回往返延遲（未知或已逾時的序號返回 None）
+    pub fn observe_pong(&mut self, seq: u64, now: Instant) -> Option<Duration> {
+        self.expire(now);
+        let index = self.pending.iter().position(|(pending, _)| *pending == seq)?;
+        let (_, sent) = self.pending.remove(index)?;
+        let rtt = now.duration_since(sent);
+        self.push_result(Some(rtt));
+        Some(rtt)
+    }
+
+    /// 把逾時的 ping 記為遺失
+    fn expire(&mut self, now: Instant) {
+        while self.pending.front().is_some_and(|(_, sent)| now.duration_since(*sent) > PONG_TIMEOUT) {
+            self.pending.pop_front();
+            self.push_result(None);
+        }
+    }
+
+    fn push_result(&mut self, result: Option<Duration>) {
+        if self.results.len() == WINDOW {
+            self.results.pop_front();
+        }
+        self.results.push_back(result);
+    }
+
+    /// 最近樣本的統計（尚未收到任何 pong 時為 None）
+    pub fn stats(&self) -> Option<HeartbeatStats> {
+        let mut samples: Vec<Duration> = self.results.iter().flatten().copied().collect();
+        let last = *samples.last()?;
+        let average = samples.iter().sum::<Duration>() / samples.len() as u32;
+        samples.sort();
+        let p95 = samples[((samples.len() as f64 * 0.95).ceil() as usize).clamp(1, samples.len()) - 1];
+        Some(HeartbeatStats {
+            last,
+            average,
+            p95,
+            lost: self.results.iter().filter(|result| result.is_none()).count(),
+            sent: self.results.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_average_p95_and_loss() {
+        let start = Instant::now();
+        let mut tracker = HeartbeatTracker::default();
+        for ms in 1..=20u64 {
+            let sent = start + Duration::from_secs(ms);
+            let seq = tracker.mark_sent(sent);
+            tracker.observe_pong(seq, sent + Duration::from_millis(ms * 10));
+        }
+        // 沒有回應的 ping 逾時後記為遺失
+        let lost_at = start + Duration::from_secs(30);
+        tracker.mark_sent(lost_at);
+        tracker.mark_sent(lost_at + PONG_TIMEOUT + Duration::from_secs(1));
+
+        let stats = tracker.stats().unwrap();
+        assert_eq!(stats.last, Duration::from_millis(200));
+        assert_eq!(stats.average, Duration::from_millis(105));
+        assert_eq!(stats.p95, Duration::from_millis(190));
+        assert_eq!((stats.lost, stats.sent), (1, 21));
+        assert_eq!(tracker.observe_pong(999, lost_at), None);
+    }
+}
@@ -0,0 +1,275 @@
+/// 英雄目錄
+///
+/// 英雄定義放在 `heroes/<英雄ID>.toml`：顯示名稱、出生點與技能列表（技能順序即技能欄按鍵順序，另含快捷鍵、冷卻、
+/// 魔力消耗、施放距離與施放方式）。啟動時載入整個目錄到共用的英雄目錄（目錄不存在時使用內建的兩位英雄），
+/// 供遊戲狀態初始化、本地冷卻與魔力估計、模擬器驗證、快捷鍵對應、切換英雄與回城使用，新增英雄不需修改程式
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::OnceLock;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+/// 英雄資料目錄
+pub const HERO_DATA_DIR: &str = "heroes";
+
+/// 預設基地出生點（後端座標）
+pub const DEFAULT_SPAWN_POINT: (f32, f32) = (400.0, 300.0);
+
+/// 目錄中沒有的技能使用的冷卻與魔力消耗
+pub const DEFAULT_COOLDOWN: f32 = 5.0;
+pub const DEFAULT_MANA_COST: f32 = 50.0;
+
+/// 內建的英雄定義（英雄資料目錄不存在時使用）
+const BUILTIN_HEROES: &[&str] = &[
+    include_str!("../heroes/saika_magoichi.toml"),
+    include_str!("../heroes/date_masamune.toml"),
+];
+
+static REGISTRY: OnceLock<HeroRegistry> = OnceLock::new();
+
+/// 技能的施放方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CastType {
+    /// 指定地點
+    #[default]
+    Point,
+    /// 指定單位
+    Unit,
+    /// 英雄自己（按下快捷鍵即施放）
+    #[serde(rename = "self")]
+    SelfCast,
+    /// 朝游標方向發射
+    Direction,
+}
+
+impl CastType {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CastType::Point => "指定地點",
+            CastType::Unit => "指定單位",
+            CastType::SelfCast => "自身",
+            CastType::Direction => "方向",
+        }
+    }
+}
+
+/// 技能定義
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AbilityDefinition {
+    pub id: String,
+    pub name: String,
+    /// 快捷鍵（必須是技能欄按鍵之一，未設置時依技能順序對應）
+    #[serde(default)]
+    pub hotkey: Option<char>,
+    /// 冷卻秒數
+    #[serde(default = "default_cooldown")]
+    pub cooldown: f32,
+    #[serde(default = "default_mana_cost")]
+    pub mana_cost: f32,
+    /// 施放距離（0 為不限）
+    #[serde(default)]
+    pub range: f32,
+    #[serde(default)]
+    pub cast_type: CastType,
+}
+
+fn default_cooldown() -> f32 {
+    DEFAULT_COOLDOWN
+}
+
+fn default_mana_cost() -> f32 {
+    DEFAULT_MANA_COST
+}
+
+fn default_spawn_point() -> (f32, f32) {
+    DEFAULT_SPAWN_POINT
+}
+
+/// 英雄定義
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeroDefinition {
+    /// 英雄 ID
+    pub id: String,
+    /// 顯示名稱
+    pub name: String,
+    /// 出生點（後端座標，回城的目的地）
+    #[serde(default = "default_spawn_point")]
+    pub spawn_point: (f32, f32),
+    /// 技能（依技能欄順序）
+    #[serde(rename = "ability")]
+    pub abilities: Vec<AbilityDefinition>,
+}
+
+impl HeroDefinition {
+    pub fn parse(content: &str) -> Result<Self> {
+        let hero: Self = toml::from_str(content)?;
+        if hero.abilities.is_empty() {
+            bail!("英雄 {} 沒有任何技能", hero.id);
+        }
+        let mut ids = HashSet::new();
+        let mut hotkeys = HashSet::new();
+        for ability in &hero.abilities {
+            if !ids.insert(ability.id.as_str()) {
+                bail!("技能 {} 重複定義", ability.id);
+            }
+            if let Some(hotkey) = ability.hotkey {
+                if !hotkeys.insert(hotkey.to_ascii_lowercase()) {
+                    bail!("快捷鍵 {} 重複", hotkey);
+                }
+            }
+            if ability.cooldown < 0.0 || ability.mana_cost < 0.0 || ability.range < 0.0 {
+                bail!("技能 {} 的冷卻、魔力消耗與施放距離不能為負", ability.id);
+            }
+        }
+        Ok(hero)
+    }
+
+    pub fn ability(&self, ability_id: &str) -> Option<&AbilityDefinition> {
+        self.abilities.iter().find(|ability| ability.id == ability_id)
+    }
+
+    /// 技能欄第 `index` 個按鍵 `key` 對應的技能：先找快捷鍵相符的技能，再依順序對應未設置快捷鍵的技能
+    pub fn ability_for_key(&self, key: char, index: usize) -> Option<&AbilityDefinition> {
+        let key = key.to_ascii_lowercase();
+        self.abilities.iter()
+            .find(|ability| ability.hotkey.is_some_and(|hotkey| hotkey.to_ascii_lowercase() == key))
+            .or_else(|| self.abilities.get(index).filter(|ability| ability.hotkey.is_none()))
+    }
+}
+
+/// 所有可選英雄
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeroRegistry {
+    heroes: Vec<HeroDefinition>,
+}
+
+impl Default for HeroRegistry {
+    fn default() -> Self {
+        let heroes = BUILTIN_HEROES.iter()
+            .map(|content| HeroDefinition::parse(content).expect("內建的英雄定義有誤"))
+            .collect();
+        Self { heroes }
+    }
+}
+
+impl HeroRegistry {
+    /// 載入目錄中的所有 `*.toml`（依檔名排序）
+    pub fn load_dir(dir: &Path) -> Result<Self> {
+        let mut paths = std::fs::read_dir(dir)
+            .with_context(|| format!("無法讀取英雄資料目錄: {:?}", dir))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        paths.retain(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "toml"));
+        paths.sort();
+        let heroes = paths.iter()
+            .map(|path| {
+                let content = std::fs::read_to_string(path)
+                    .with_context(|| format!("無法讀取英雄定義: {:?}", path))?;
+                HeroDefinition::parse(&content).with_context(|| format!("英雄定義有誤: {:?}", path))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Self::new(heroes)
+    }
+
+    pub fn new(heroes: Vec<HeroDefinition>) -> Result<Self> {
+        if heroes.is_empty() {
+            bail!("沒有任何英雄定義");
+        }
+        let mut ids = HashSet::new();
+        for hero in &heroes {
+            if !ids.insert(hero.id.as_str()) {
+                bail!("英雄 {} 重複定義", hero.id);
+            }
+        }
+        Ok(Self { heroes })
+    }
+
+    pub fn heroes(&self) -> &[HeroDefinition] {
+        &self.heroes
+    }
+
+    pub fn find(&self, id: &str) -> Option<&HeroDefinition> {
+        self.heroes.iter().find(|hero| hero.id == id)
+    }
+
+    /// 在所有英雄中查找技能
+    pub fn ability(&self, ability_id: &str) -> Option<&AbilityDefinition> {
+        self.heroes.iter().find_map(|hero| hero.ability(ability_id))
+    }
+}
+
+/// 啟動時載入英雄資料目錄（目錄不存在時使用內建定義；只有第一次呼叫有效）
+pub fn init(dir: &Path) -> Result<()> {
+    let registry = if dir.is_dir() { HeroRegistry::load_dir(dir)? } else { HeroRegistry::default() };
+    let _ = REGISTRY.set(registry);
+    Ok(())
+}
+
+/// 共用的英雄目錄（尚未載入時使用內建定義）
+pub fn registry() -> &'static HeroRegistry {
+    REGISTRY.get_or_init(HeroRegistry::default)
+}
+
+/// 依 ID 查找英雄
+pub fn find_hero(id: &str) -> Option<&'static HeroDefinition> {
+    registry().find(id)
+}
+
+/// 英雄的技能 ID 列表（未知英雄返回空列表）
+pub fn hero_ability_ids(id: &str) -> Vec<&'static str> {
+    find_hero(id)
+        .map(|hero| hero.abilities.iter().map(|ability| ability.id.as_str()).collect())
+        .unwrap_or_default()
+}
+
+/// 英雄的出生點（未知英雄使用預設出生點）
+pub fn hero_spawn_point(id: &str) -> (f32, f32) {
+    find_hero(id).map(|hero| hero.spawn_point).unwrap_or(DEFAULT_SPAWN_POINT)
+}
+
+/// 技能的冷卻秒數
+pub fn ability_cooldown(ability_id: &str) -> f32 {
+    registry().ability(ability_id).map_or(DEFAULT_COOLDOWN, |ability| ability.cooldown)
+}
+
+/// 技能的魔力消耗
+pub fn ability_mana_cost(ability_id: &str) -> f32 {
+    registry().ability(ability_id).map_or(DEFAULT_MANA_COST, |ability| ability.mana_cost)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_hero_files_and_maps_hotkeys() {
+        let registry = HeroRegistry::default();
+        let saika = registry.find("saika_magoichi").unwrap();
+        assert_eq!(saika.abilities.len(), 4);
+        assert_eq!(saika.ability("sniper_mode").unwrap().cast_type, CastType::SelfCast);
+        assert_eq!(registry.ability("matchlock_gun").unwrap().cooldown, 25.0);
+        assert_eq!(saika.ability_for_key('e', 1).unwrap().id, "saika_reinforcements");
+
+        let hero = HeroDefinition::parse(r#"
+            id = "test_hero"
+            name = "測試"
+            [[ability]]
+            id = "a"
+            name = "A"
+            [[ability]]
+            id = "b"
+            name = "B"
+            hotkey = "W"
+        "#).unwrap();
+        assert_eq!(hero.spawn_point, DEFAULT_SPAWN_POINT);
+        assert_eq!(hero.ability_for_key('w', 0).unwrap().id, "b", "快捷鍵優先");
+        assert_eq!(hero.ability_for_key('e', 1), None, "已有快捷鍵的技能不再依順序對應");
+        assert_eq!((hero.abilities[0].cooldown, hero.abilities[0].cast_type), (DEFAULT_COOLDOWN, CastType::Point));
+
+        assert!(HeroDefinition::parse("id = \"x\"\nname = \"X\"\nability = []").is_err());
+        assert!(HeroRegistry::new(vec![saika.clone(), saika.clone()]).is_err());
+    }
+}
@@ -0,0 +1,116 @@
+/// 模擬輸入擬人化
+///
+/// 自動遊戲產生的操作原本以固定節奏送出、座標精準，壓測時的流量因此像節拍器一樣規律。
+/// 啟用 `[humanize]` 後每個操作送出前加上隨機的反應延遲，目標座標加上小幅抖動，
+/// 並以設定的機率偏離目標（誤點），讓多個客戶端的負載更接近真人的操作模式
+use std::time::Duration;
+use rand::Rng;
+use vek::Vec2;
+
+use crate::config::HumanizeConfig;
+
+/// 擬人化處理後的操作
+#[derive(Debug, Clone, PartialEq)]
+pub struct HumanizedAction {
+    /// 送出前等待的時間
+    pub delay: Duration,
+    /// 是否為誤點
+    pub misclick: bool,
+}
+
+/// 輸入擬人化
+#[derive(Debug, Clone, Default)]
+pub struct Humanizer {
+    config: HumanizeConfig,
+}
+
+impl Humanizer {
+    /// 創建擬人化器
+    pub fn new(config: HumanizeConfig) -> Self {
+        Self { config }
+    }
+
+    /// 是否啟用
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// 為操作加上抖動與誤點（直接修改參數中的目標座標），返回送出前的延遲
+    pub fn apply(&self, action: &str, params: &mut serde_json::Value, rng: &mut impl Rng) -> HumanizedAction {
+        let config = &self.config;
+        let delay_ms = if config.max_delay_ms > config.min_delay_ms {
+            rng.random_range(config.min_delay_ms..=config.max_delay_ms)
+        } else {
+            config.min_delay_ms
+        };
+        let misclick = rng.random_bool(config.misclick_chance.clamp(0.0, 1.0) as f64);
+        let radius = if misclick { config.misclick_distance } else { config.jitter };
+        let offset = random_offset(rng, radius, misclick);
+
+        match action {
+            "move" => {
+                for (key, delta) in [("target_x", offset.x), ("target_y", offset.y)] {
+                    if let Some(value) = params.get(key).and_then(|v| v.as_f64()) {
+                        params[key] = serde_json::json!(value as f32 + delta);
+                    }
+                }
+            }
+            _ => {
+                if let Some(target) = params.get_mut("target_position").and_then(|v| v.as_array_mut()) {
+                    for (value, delta) in target.iter_mut().zip([offset.x, offset.y]) {
+                        if let Some(number) = value.as_f64() {
+                            *value = serde_json::json!(number as f32 + delta);
+                        }
+                    }
+                }
+            }
+        }
+
+        HumanizedAction { delay: Duration::from_millis(delay_ms), misclick }
+    }
+}
+
+/// 隨機方向的位移：誤點固定偏離 `radius`，一般抖動落在半徑內
+fn random_offset(rng: &mut impl Rng, radius: f32, exact: bool) -> Vec2<f32> {
+    if radius <= 0.0 {
+        return Vec2::zero();
+    }
+    let angle = rng.random_range(0.0..std::f32::consts::TAU);
+    let distance = if exact { radius } else { radius * rng.random::<f32>().sqrt() };
+    Vec2::new(angle.cos(), angle.sin()) * distance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn jitters_targets_and_misclicks_within_limits() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let config = HumanizeConfig {
+            enabled: true,
+            min_delay_ms: 100,
+            max_delay_ms: 300,
+            jitter: 5.0,
+            misclick_chance: 0.0,
+            misclick_distance: 60.0,
+        };
+        let humanizer = Humanizer::new(config.clone());
+        for _ in 0..50 {
+            let mut params = serde_json::json!({ "target_x": 100.0, "target_y": 200.0 });
+            let result = humanizer.apply("move", &mut params, &mut rng);
+            assert!((100..=300).contains(&(result.delay.as_millis() as u64)));
+            assert!(!result.misclick);
+            let target = Vec2::new(params["target_x"].as_f64().unwrap() as f32, params["target_y"].as_f64().unwrap() as f32);
+            assert!((target - Vec2::new(100.0, 200.0)).magnitude() <= 5.0 + 0.01);
+        }
+
+        let humanizer = Humanizer::new(HumanizeConfig { misclick_chance: 1.0, ..config });
+        let mut params = serde_json::json!({ "target_position": [10.0, 10.0], "attack_type": "basic" });
+        assert!(humanizer.apply("attack", &mut params, &mut rng).misclick);
+        let target = Vec2::new(params["target_position"][0].as_f64().unwrap() as f32, params["target_position"][1].as_f64().unwrap() as f32);
+        assert!(((target - Vec2::new(10.0, 10.0)).magnitude() - 60.0).abs() < 0.01);
+    }
+}
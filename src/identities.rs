@@ -0,0 +1,124 @@
+/// 壓力測試的客戶端身分池
+///
+/// `omobaf swarm <n> --identities identities.toml` 依檔案中 `[[identity]]` 的順序分配玩家名稱、英雄、陣營與 client ID，
+/// 同一個檔案每次產生相同的機器人組合，在日誌與終端視圖中也能一眼認出是哪個機器人。
+/// 客戶端數量超過身分數時，其餘以 `<前綴>_<編號>` 補齊；名稱或 client ID 重複時拒絕載入
+use std::collections::HashSet;
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use crate::game_state::Team;
+
+/// 一個身分（未設置的欄位沿用命令行與配置的預設值）
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Identity {
+    pub name: String,
+    #[serde(default)]
+    pub hero: Option<String>,
+    #[serde(default)]
+    pub team: Option<Team>,
+    /// MQTT client ID（未設置時為 `<client_id>_<name>`）
+    #[serde(default)]
+    pub client_id: Option<String>,
+}
+
+/// 分配給單一客戶端的身分
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assigned {
+    pub name: String,
+    pub hero: String,
+    pub team: Option<Team>,
+    pub client_id: String,
+}
+
+/// 身分池檔案
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IdentityPool {
+    #[serde(default, rename = "identity")]
+    pub identities: Vec<Identity>,
+}
+
+impl IdentityPool {
+    /// 從檔案載入身分池
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("無法讀取身分池: {:?}", path))?;
+        Self::parse(&content).with_context(|| format!("身分池有誤: {:?}", path))
+    }
+
+    /// 解析並檢查身分池
+    pub fn parse(content: &str) -> Result<Self> {
+        let pool: Self = toml::from_str(content)?;
+        let mut names = HashSet::new();
+        for identity in &pool.identities {
+            if identity.name.trim().is_empty() {
+                bail!("身分名稱不可為空");
+            }
+            if !names.insert(identity.name.as_str()) {
+                bail!("身分名稱重複: {}", identity.name);
+            }
+        }
+        let mut client_ids = HashSet::new();
+        for client_id in pool.identities.iter().filter_map(|identity| identity.client_id.as_deref()) {
+            if !client_ids.insert(client_id) {
+                bail!("client ID 重複: {}", client_id);
+            }
+        }
+        Ok(pool)
+    }
+
+    /// 依序分配 count 個身分，不足的以 `<prefix>_<編號>` 補齊（編號跳過已被身分使用的名稱）
+    pub fn assign(&self, count: usize, prefix: &str, client_id: &str, hero: &str) -> Vec<Assigned> {
+        let taken: HashSet<&str> = self.identities.iter().map(|identity| identity.name.as_str()).collect();
+        let generated = (1..)
+            .map(|index| format!("{}_{:02}", prefix, index))
+            .filter(|name| !taken.contains(name.as_str()))
+            .map(|name| Identity { name, hero: None, team: None, client_id: None });
+        self.identities.iter().cloned()
+            .chain(generated)
+            .take(count)
+            .map(|identity| Assigned {
+                client_id: identity.client_id.unwrap_or_else(|| format!("{}_{}", client_id, identity.name)),
+                hero: identity.hero.unwrap_or_else(|| hero.to_string()),
+                team: identity.team,
+                name: identity.name,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assigns_identities_in_order_and_fills_the_rest() {
+        let pool = IdentityPool::parse(r#"
+            [[identity]]
+            name = "swarm_02"
+            hero = "date_masamune"
+            team = "red"
+
+            [[identity]]
+            name = "blue_sniper"
+            team = "blue"
+            client_id = "bot_blue_sniper"
+        "#).unwrap();
+
+        let assigned = pool.assign(4, "swarm", "omobaf", "saika_magoichi");
+        let names: Vec<&str> = assigned.iter().map(|identity| identity.name.as_str()).collect();
+        assert_eq!(names, vec!["swarm_02", "blue_sniper", "swarm_01", "swarm_03"]);
+        assert_eq!(assigned[0], Assigned {
+            name: "swarm_02".to_string(), hero: "date_masamune".to_string(), team: Some(Team::Red), client_id: "omobaf_swarm_02".to_string(),
+        });
+        assert_eq!(assigned[1].client_id, "bot_blue_sniper");
+        assert_eq!((assigned[2].hero.as_str(), assigned[2].team), ("saika_magoichi", None));
+        assert_eq!(pool.assign(1, "swarm", "omobaf", "saika_magoichi").len(), 1);
+
+        assert!(IdentityPool::parse("[[identity]]\nname = \"a\"\n[[identity]]\nname = \"a\"").is_err());
+        assert!(IdentityPool::parse("[[identity]]\nname = \"a\"\nclient_id = \"x\"\n[[identity]]\nname = \"b\"\nclient_id = \"x\"").is_err());
+    }
+}
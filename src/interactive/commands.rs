@@ -1,11 +1,33 @@
 /// 命令處理模塊
+use std::collections::BTreeMap;
 use std::io::{self, Write};
 use anyhow::Result;
 use colored::*;
+use vek::Vec2;
 use crate::game_client::{GameClient, GameClientConfig, ClientState};
-use crate::config::AppConfig;
+use crate::client_handle::GameClientHandle;
+use crate::camera::CameraMode;
+use crate::combo::ComboStep;
+use crate::config::{AppConfig, BrokerEndpoint};
+use crate::audit::AuditLog;
+use crate::presets::{Preset, PresetStore, DEFAULT_PRESET_FILE};
+use crate::sniffer::DEFAULT_PATTERN;
+use crate::map_editor::{TerrainGrid, Tile, DEFAULT_CELL_SIZE};
 use crate::backend_manager::BackendManager;
 use crate::terminal_view::UserInput;
+use crate::terminal_view::overlays::OverlayManager;
+use crate::patrol::PatrolRoute;
+use crate::progression::level_bar;
+use crate::selection;
+
+/// 預設會話名稱
+pub const DEFAULT_SESSION: &str = "main";
+
+/// 非使用中的會話（MQTT 事件循環在背景繼續運行）
+pub struct ParkedSession {
+    pub game_client: Option<GameClient>,
+    pub config: GameClientConfig,
+}
 
 /// 命令處理器
 pub struct CommandHandler {
@@ -13,6 +35,21 @@ pub struct CommandHandler {
     pub config: GameClientConfig,
     pub app_config: AppConfig,
     pub backend_manager: Option<BackendManager>,
+    /// 使用中的會話名稱
+    pub active_session: String,
+    /// 其他會話
+    pub parked_sessions: BTreeMap<String, ParkedSession>,
+    /// 命令稽核記錄
+    pub audit: AuditLog,
+    /// 設定預設
+    pub presets: PresetStore,
+    /// 編輯中的測試地形
+    pub terrain: TerrainGrid,
+    /// 終端視圖的地圖疊加層（開啟 view 時交給視圖）
+    pub overlays: OverlayManager,
+    /// 已載入的機器人腳本
+    #[cfg(feature = "scripting")]
+    pub bot_script: Option<crate::bot_script::BotScript>,
 }
 
 impl CommandHandler {
@@ -21,18 +58,63 @@ impl CommandHandler {
         Self {
             game_client: None,
             config,
+            overlays: OverlayManager::new(&app_config.overlays),
             backend_manager: if app_config.frontend.auto_start_backend {
                 Some(BackendManager::new(app_config.clone()))
             } else {
                 None
             },
             app_config,
+            active_session: DEFAULT_SESSION.to_string(),
+            parked_sessions: BTreeMap::new(),
+            audit: AuditLog::default(),
+            presets: PresetStore::load(DEFAULT_PRESET_FILE).unwrap_or_else(|e| {
+                log::warn!("載入預設失敗: {}", e);
+                PresetStore::empty(DEFAULT_PRESET_FILE)
+            }),
+            terrain: TerrainGrid::default(),
+            #[cfg(feature = "scripting")]
+            bot_script: None,
         }
     }
     
+    /// 依目前配置創建使用中會話的客戶端
+    fn create_client(&self) -> GameClient {
+        let mut client = GameClient::new(self.config.clone());
+        client.configure_metrics(&self.app_config.metrics);
+        client.configure_sanity(&self.app_config.sanity);
+        client.configure_alerts(&self.app_config.alerts);
+        client.configure_coordinates(&self.app_config.coordinates);
+        client.configure_map(&self.app_config.map);
+        client.configure_targeting(&self.app_config.targeting);
+        client.configure_macros(&self.app_config.macros);
+        client.configure_death_recap(&self.app_config.death_recap);
+        client.configure_credit(&self.app_config.credit);
+        client.configure_heartbeat(&self.app_config.heartbeat);
+        client.configure_time_sync(&self.app_config.time_sync);
+        client.configure_state_hash(&self.app_config.state_hash);
+        client.configure_liveness(&self.app_config.liveness);
+        client.configure_expectations(&self.app_config.expectations);
+        client.configure_offline_queue(&self.app_config.offline_queue);
+        client.configure_rate_limit(&self.app_config.rate_limit);
+        client.configure_rpc(&self.app_config.rpc);
+        client.configure_anomaly_capture(&self.app_config.anomaly_capture);
+        client.configure_dummy(&self.app_config.dummy);
+        client.configure_sandbox(&self.app_config.sandbox);
+        client.configure_recording(&self.app_config.recording);
+        client.configure_humanize(&self.app_config.humanize);
+        client.configure_bot(&self.app_config.bot);
+        client.configure_shop(&self.app_config.shop);
+        client.configure_screen_updates(&self.app_config.screen_updates);
+        client.configure_prediction(&self.app_config.prediction);
+        client.configure_camera(&self.app_config.camera);
+        client.set_session_tag(self.active_session.clone());
+        client
+    }
+    
     /// 自動連接到本地端
     pub async fn auto_connect_localhost(&mut self) -> Result<()> {
-        let mut client = GameClient::new(self.config.clone());
+        let mut client = self.create_client();
         client.connect().await?;
         self.game_client = Some(client);
         Ok(())
@@ -57,7 +139,7 @@ impl CommandHandler {
         self.config.server_ip = ip;
         self.config.server_port = port;
         
-        let mut client = GameClient::new(self.config.clone());
+        let mut client = self.create_client();
         client.connect().await?;
         
         self.game_client = Some(client);
@@ -66,11 +148,152 @@ impl CommandHandler {
         Ok(())
     }
     
+    /// 處理自我測試命令
+    pub async fn handle_selftest(&mut self, parts: &[&str]) -> Result<()> {
+        match parts.get(1).copied() {
+            Some("mqtt") => {
+                let host = parts.get(2).map(|s| s.to_string()).unwrap_or_else(|| self.config.server_ip.clone());
+                let port = match parts.get(3) {
+                    Some(port) => port.parse()?,
+                    None => self.config.server_port,
+                };
+                println!("{} 測試 {}:{} 的 MQTT 回送...", "→".green(), host, port);
+                let options = self.create_client().endpoint_options(&BrokerEndpoint { host, port }, "selftest")?;
+                let report = crate::selftest::mqtt_loopback(options, std::time::Duration::from_secs(3)).await?;
+                report.print();
+            }
+            _ => {
+                println!("{} 用法: selftest mqtt [ip] [port]", "!".yellow());
+            }
+        }
+        Ok(())
+    }
+    
+    /// 處理會話管理命令
+    pub async fn handle_session(&mut self, parts: &[&str]) -> Result<()> {
+        match parts.get(1).copied() {
+            None | Some("list") => self.list_sessions(),
+            Some("new") => {
+                let name = parts.get(2)
+                    .ok_or_else(|| anyhow::anyhow!("用法: session new <名稱> [玩家名稱] [英雄]"))?;
+                self.new_session(name, parts.get(3).copied(), parts.get(4).copied()).await?;
+            }
+            Some("use") => {
+                let name = parts.get(2)
+                    .ok_or_else(|| anyhow::anyhow!("用法: session use <名稱>"))?;
+                self.use_session(name)?;
+            }
+            Some("close") => {
+                let name = parts.get(2)
+                    .ok_or_else(|| anyhow::anyhow!("用法: session close <名稱>"))?;
+                self.close_session(name).await?;
+            }
+            Some(other) => {
+                println!("{} 未知的子命令: {}", "!".red(), other);
+            }
+        }
+        Ok(())
+    }
+    
+    fn list_sessions(&self) {
+        println!("\n{}", "會話列表:".bright_cyan().bold());
+        println!("{}", "-".repeat(40).bright_black());
+        
+        let describe = |client: &Option<GameClient>| match client.as_ref().map(|c| c.get_state()) {
+            Some(ClientState::InGame) => "遊戲中".bright_green(),
+            Some(ClientState::Connected) => "已連接".green(),
+            Some(ClientState::Connecting) => "連接中".yellow(),
+            Some(ClientState::Reconnecting(_)) => "重連中".yellow(),
+            Some(ClientState::Stale(_)) => "資料停滯".yellow(),
+            Some(ClientState::ServerLost(_)) => "失去伺服器".bright_red(),
+            Some(ClientState::Error(_)) => "錯誤".bright_red(),
+            Some(ClientState::Disconnected) | None => "未連接".red(),
+        };
+        
+        println!("  {} {} - 玩家 {} [{}]", "*".bright_yellow(), self.active_session.bright_white().bold(),
+            self.config.player_name, describe(&self.game_client));
+        for (name, session) in &self.parked_sessions {
+            println!("    {} - 玩家 {} [{}]", name, session.config.player_name, describe(&session.game_client));
+        }
+    }
+    
+    /// 創建新會話並切換過去；使用中的會話已連接時，新會話會連接到同一服務器
+    async fn new_session(&mut self, name: &str, player_name: Option<&str>, hero: Option<&str>) -> Result<()> {
+        if name == self.active_session || self.parked_sessions.contains_key(name) {
+            return Err(anyhow::anyhow!("會話已存在: {}", name));
+        }
+        
+        let mut config = self.config.clone();
+        config.player_name = player_name.unwrap_or(name).to_string();
+        config.client_id = format!("omobaf_{}", name);
+        if let Some(hero) = hero {
+            config.hero_type = hero.to_string();
+        }
+        
+        let connect = self.game_client.is_some();
+        self.park_active();
+        self.active_session = name.to_string();
+        self.config = config;
+        
+        println!("{} 已創建會話 {} (玩家: {})", "✓".green(), name.bright_yellow(), self.config.player_name);
+        if connect {
+            let mut client = self.create_client();
+            client.connect().await?;
+            self.game_client = Some(client);
+            println!("{} 會話 {} 已連接到 {}:{}", "✓".green(), name, self.config.server_ip, self.config.server_port);
+        }
+        Ok(())
+    }
+    
+    /// 切換使用中的會話
+    fn use_session(&mut self, name: &str) -> Result<()> {
+        if name == self.active_session {
+            return Ok(());
+        }
+        let session = self.parked_sessions.remove(name)
+            .ok_or_else(|| anyhow::anyhow!("找不到會話: {}", name))?;
+        
+        self.park_active();
+        self.active_session = name.to_string();
+        self.game_client = session.game_client;
+        self.config = session.config;
+        
+        println!("{} 已切換至會話 {}", "✓".green(), name.bright_yellow());
+        Ok(())
+    }
+    
+    /// 關閉會話並斷開其連接
+    async fn close_session(&mut self, name: &str) -> Result<()> {
+        if name == self.active_session {
+            return Err(anyhow::anyhow!("無法關閉使用中的會話，請先切換到其他會話"));
+        }
+        let session = self.parked_sessions.remove(name)
+            .ok_or_else(|| anyhow::anyhow!("找不到會話: {}", name))?;
+        if let Some(mut client) = session.game_client {
+            client.disconnect().await?;
+        }
+        println!("{} 已關閉會話 {}", "✓".green(), name);
+        Ok(())
+    }
+    
+    fn park_active(&mut self) {
+        let parked = ParkedSession {
+            game_client: self.game_client.take(),
+            config: self.config.clone(),
+        };
+        self.parked_sessions.insert(self.active_session.clone(), parked);
+    }
+    
     /// 處理斷開連接命令
-    pub async fn handle_disconnect(&mut self) -> Result<()> {
+    pub async fn handle_disconnect(&mut self, parts: &[&str]) -> Result<()> {
         if let Some(mut client) = self.game_client.take() {
-            client.disconnect().await?;
-            println!("{} 已斷開連接", "✓".green());
+            if parts.get(1) == Some(&"--abrupt") {
+                client.drop_connection();
+                println!("{} 已模擬異常斷線，後端將收到遺囑 leave_game", "⚡".yellow());
+            } else {
+                client.disconnect().await?;
+                println!("{} 已斷開連接", "✓".green());
+            }
         } else {
             println!("{} 尚未連接到服務器", "!".yellow());
         }
@@ -79,13 +302,28 @@ impl CommandHandler {
     
     /// 處理配置命令
     pub fn handle_config(&mut self, parts: &[&str]) -> Result<()> {
-        if parts.len() == 1 {
+        if parts.get(1) == Some(&"effective") {
+            crate::config_layers::ResolvedConfig::resolve_current()?.print();
+            println!("\n{} 以 config 命令做的修改只影響目前會話，不會反映在上面", "!".yellow());
+        } else if parts.len() == 1 {
             // 顯示當前配置
             println!("\n{}", "當前配置:".bright_cyan().bold());
             println!("  服務器: {}:{}", self.config.server_ip, self.config.server_port);
+            if !self.config.failover.is_empty() {
+                println!("  備援: {}", self.config.failover.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "));
+            }
+            println!("  TLS: {}", if self.config.tls.enabled { "開啟" } else { "關閉" });
+            println!("  訊息編碼: {}", self.config.wire_format.label());
+            println!("  QoS: {}", self.config.qos.levels().iter()
+                .map(|(topic, level)| format!("{}={}", topic, level)).collect::<Vec<_>>().join(" "));
+            println!("  認證: {}", self.config.username.as_deref().unwrap_or("無"));
             println!("  客戶端ID: {}", self.config.client_id);
             println!("  玩家名稱: {}", self.config.player_name);
             println!("  英雄類型: {}", self.config.hero_type);
+            println!("  試運行: {}", if self.config.dry_run { "開啟" } else { "關閉" });
+            println!("  唯讀: {}", if self.config.read_only { "開啟" } else { "關閉" });
+            println!("  觀戰: {}", self.config.spectate.as_deref().unwrap_or("關閉"));
+            println!("  預判瞄準: {}", if self.app_config.targeting.lead_target { "開啟" } else { "關閉" });
         } else if parts.len() >= 3 {
             // 修改配置
             let key = parts[1];
@@ -108,6 +346,51 @@ impl CommandHandler {
                     self.config.hero_type = value;
                     println!("{} 英雄類型設為: {}", "✓".green(), self.config.hero_type);
                 },
+                "dry-run" | "dry_run" => {
+                    self.config.dry_run = matches!(value.as_str(), "on" | "true" | "1");
+                    if let Some(client) = &mut self.game_client {
+                        client.set_dry_run(self.config.dry_run);
+                    }
+                    println!("{} 試運行模式: {}", "✓".green(), if self.config.dry_run { "開啟（操作只記錄不發送）" } else { "關閉" });
+                },
+                "lead" => {
+                    self.app_config.targeting.lead_target = matches!(value.as_str(), "on" | "true" | "1");
+                    if let Some(client) = &mut self.game_client {
+                        client.set_lead_target(self.app_config.targeting.lead_target);
+                    }
+                    println!("{} 預判瞄準: {}", "✓".green(), if self.app_config.targeting.lead_target { "開啟" } else { "關閉" });
+                },
+                "read-only" | "read_only" => {
+                    self.config.read_only = matches!(value.as_str(), "on" | "true" | "1");
+                    if let Some(client) = &mut self.game_client {
+                        client.set_read_only(self.config.read_only);
+                    }
+                    println!("{} 唯讀模式: {}", "✓".green(), if self.config.read_only { "開啟（只能觀看）" } else { "關閉" });
+                },
+                "spectate" => {
+                    self.config.spectate = (value != "off").then_some(value);
+                    match &self.config.spectate {
+                        Some(player) => println!("{} 觀戰玩家設為: {}（不發送任何操作）", "✓".green(), player),
+                        None => println!("{} 已關閉觀戰", "✓".green()),
+                    }
+                    if self.game_client.is_some() {
+                        println!("{} 重新連接後生效", "!".yellow());
+                    }
+                },
+                "auth" => {
+                    if value == "off" {
+                        self.config.username = None;
+                        self.config.password = None;
+                        println!("{} 已清除 broker 認證", "✓".green());
+                    } else {
+                        self.config.username = Some(parts[2].to_string());
+                        self.config.password = parts.get(3).map(|password| password.to_string());
+                        println!("{} broker 認證帳號設為: {}", "✓".green(), parts[2]);
+                    }
+                    if self.game_client.is_some() {
+                        println!("{} 重新連接後生效", "!".yellow());
+                    }
+                },
                 _ => {
                     println!("{} 未知配置項: {}", "!".red(), key);
                 }
@@ -125,17 +408,69 @@ impl CommandHandler {
             Some(client) => {
                 let state = client.get_state();
                 println!("  連接狀態: {}", format!("{:?}", state).bright_white());
+                let broker = client.active_broker().to_string();
+                if client.on_failover() {
+                    println!("  Broker: {} {}", broker.yellow(), "(備援)".yellow());
+                } else {
+                    println!("  Broker: {}", broker.bright_white());
+                }
+                match client.heartbeat_stats() {
+                    Some(stats) => println!("  心跳: {}", stats.summary()),
+                    None => println!("  心跳: {}", "尚未收到 pong".bright_black()),
+                }
+                match client.server_clock().summary() {
+                    Some(summary) => println!("  伺服器時鐘: {}", summary),
+                    None => println!("  伺服器時鐘: {}", "尚未同步".bright_black()),
+                }
+                let queued = client.queued_actions();
+                if queued > 0 {
+                    println!("  離線佇列: {} 個操作待補送", queued.to_string().yellow());
+                }
+                let limited = client.rate_limit_stats();
+                if limited.deferred > 0 || limited.coalesced > 0 {
+                    println!("  發佈限流: 延後 {} 則，合併 {} 則", limited.deferred, limited.coalesced);
+                }
+                let rpc = client.rpc_stats();
+                if rpc.sent > 0 {
+                    println!("  後端請求: 送出 {}，回應 {}，逾時 {}，未對應 {}，等待中 {}",
+                             rpc.sent, rpc.answered, rpc.timed_out, rpc.unmatched, client.pending_requests());
+                }
+                if client.sandbox().active() {
+                    let sandbox = client.sandbox().stats();
+                    println!("  練習沙盒: 啟用（重置冷卻 {} 次，補滿 {} 次）", sandbox.resets, sandbox.refills);
+                }
                 
-                if let ClientState::InGame = state {
+                if state.in_game() {
                     let game_state = client.get_game_state();
                     println!("  玩家: {}", game_state.local_player.name.bright_yellow());
                     println!("  英雄: {}", game_state.local_player.hero_type.bright_yellow());
-                    println!("  位置: ({:.1}, {:.1})", 
-                        game_state.local_player.position.x,
-                        game_state.local_player.position.y);
+                    let position = client.coordinates().to_local(game_state.local_player.position);
+                    println!("  位置: ({:.1}, {:.1})", position.x, position.y);
+                    if let Some(rtt) = client.rtt() {
+                        println!("  往返延遲: {}ms", rtt.as_millis());
+                    }
                     println!("  生命值: {:.0}/{:.0}", 
                         game_state.local_player.health.0, 
                         game_state.local_player.health.1);
+                    let hero = &game_state.local_player;
+                    println!("  魔力: {:.0}/{:.0}（每秒 +{:.1}）", hero.mana.0, hero.mana.1, hero.mana_regen);
+                    println!("  等級: {}", level_bar(hero.level, hero.experience, hero.next_level_experience, 20).bright_white());
+                    let progression = &game_state.progression;
+                    println!("  金錢: {}（獲得 {}，花費 {}）", hero.gold.to_string().bright_yellow(),
+                        progression.total_earned(), progression.total_spent());
+                    if !progression.earned.is_empty() || !progression.spent.is_empty() {
+                        println!("  收支: {}", progression.breakdown());
+                    }
+                    if !hero.buffs.is_empty() {
+                        let icons: Vec<_> = hero.buffs.iter()
+                            .map(|buff| format!("{}{}", buff.icon(), buff.source.as_ref().map_or_else(String::new, |source| format!("（來自 {}）", source))))
+                            .collect();
+                        println!("  狀態效果: {}", icons.join("  "));
+                    }
+                    if let Some(countdown) = hero.life.countdown() {
+                        println!("  {}", countdown.bright_red());
+                    }
+                    println!("  K/D/A: {}", game_state.scoreboard.get(&hero.name));
                 }
             },
             None => {
@@ -172,10 +507,14 @@ impl CommandHandler {
         let y: f32 = parts[2].parse()?;
         
         if let Some(client) = &mut self.game_client {
+            let target = client.coordinates().to_backend(Vec2::new(x, y));
+            if let Some(cell) = client.get_game_state().terrain.blocking_at(target) {
+                return Err(anyhow::anyhow!("目標 ({}, {}) 是{}，無法移動", x, y, cell.kind.label()));
+            }
             println!("{} 移動到 ({}, {})", "→".green(), x, y);
             client.perform_action("move", serde_json::json!({
-                "x": x,
-                "y": y
+                "target_x": target.x,
+                "target_y": target.y
             })).await?;
             println!("{} 移動完成", "✓".green());
         } else {
@@ -205,7 +544,13 @@ impl CommandHandler {
             });
             
             if let (Some(x), Some(y)) = (x, y) {
-                params["target_position"] = serde_json::json!([x, y]);
+                let target = client.coordinates().to_backend(Vec2::new(x, y));
+                params["target_position"] = serde_json::json!([target.x, target.y]);
+            } else if let Some(entity) = selection::selected_entity(client.get_game_state()) {
+                // 未指定位置時施放在選擇的目標上
+                println!("  目標: #{} {}", entity.id, selection::kind_label(&entity.entity_type));
+                params["target_position"] = serde_json::json!([entity.position.x, entity.position.y]);
+                params["target_entity"] = serde_json::json!(entity.id);
             }
             
             client.perform_action("cast_ability", params).await?;
@@ -219,18 +564,31 @@ impl CommandHandler {
     
     /// 處理攻擊命令
     pub async fn handle_attack(&mut self, parts: &[&str]) -> Result<()> {
-        if parts.len() < 3 {
-            return Err(anyhow::anyhow!("用法: attack <x> <y>"));
+        if parts.len() == 2 || parts.len() > 3 {
+            return Err(anyhow::anyhow!("用法: attack [x y]（未指定位置時攻擊選擇的目標）"));
         }
         
-        let x: f32 = parts[1].parse()?;
-        let y: f32 = parts[2].parse()?;
-        
         if let Some(client) = &mut self.game_client {
-            println!("{} 攻擊位置 ({}, {})", "→".green(), x, y);
-            client.perform_action("attack", serde_json::json!({
-                "target_position": [x, y]
-            })).await?;
+            let params = if parts.len() == 3 {
+                let x: f32 = parts[1].parse()?;
+                let y: f32 = parts[2].parse()?;
+                println!("{} 攻擊位置 ({}, {})", "→".green(), x, y);
+                let target = client.coordinates().to_backend(Vec2::new(x, y));
+                serde_json::json!({
+                    "target_position": [target.x, target.y],
+                    "attack_type": "basic"
+                })
+            } else {
+                let entity = selection::selected_entity(client.get_game_state())
+                    .ok_or_else(|| anyhow::anyhow!("沒有選擇目標，請指定位置或先用 select 選擇目標"))?;
+                println!("{} 攻擊目標 #{} {}", "→".green(), entity.id, selection::kind_label(&entity.entity_type));
+                serde_json::json!({
+                    "target_position": [entity.position.x, entity.position.y],
+                    "target_entity": entity.id,
+                    "attack_type": "basic"
+                })
+            };
+            client.perform_action("attack", params).await?;
             println!("{} 攻擊完成", "✓".green());
         } else {
             return Err(anyhow::anyhow!("請先連接到服務器"));
@@ -239,6 +597,40 @@ impl CommandHandler {
         Ok(())
     }
     
+    /// 處理選擇目標命令
+    pub async fn handle_select(&mut self, parts: &[&str]) -> Result<()> {
+        let client = self.game_client.as_mut().ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        let target = match parts.get(1).copied() {
+            None => {
+                let lines = selection::panel_lines(client.get_game_state());
+                if lines.is_empty() {
+                    println!("{} 沒有選擇目標", "ℹ".bright_blue());
+                } else {
+                    println!("{}", lines.join("，"));
+                }
+                let targets = selection::visible_targets(client.get_game_state());
+                println!("  視野內: {}", targets.iter().map(|id| format!("#{}", id)).collect::<Vec<_>>().join(" "));
+                return Ok(());
+            }
+            Some("none") => None,
+            Some("next") | Some("prev") => Some(selection::cycle(client.get_game_state(), parts[1] == "prev")
+                .ok_or_else(|| anyhow::anyhow!("視野內沒有可選擇的單位"))?),
+            Some(id) => {
+                let id: u32 = id.trim_start_matches('#').parse()
+                    .map_err(|_| anyhow::anyhow!("用法: select [實體ID|next|prev|none]"))?;
+                if !client.get_game_state().entities.contains_key(&id) {
+                    return Err(anyhow::anyhow!("找不到實體 #{}", id));
+                }
+                Some(id)
+            }
+        };
+        client.select_target(target).await;
+        for line in selection::panel_lines(client.get_game_state()) {
+            println!("  {}", line);
+        }
+        Ok(())
+    }
+    
     /// 處理自動遊戲命令
     pub async fn handle_auto(&mut self, parts: &[&str]) -> Result<()> {
         let duration = if parts.len() > 1 {
@@ -258,6 +650,116 @@ impl CommandHandler {
         Ok(())
     }
     
+    /// 處理巡邏命令
+    pub async fn handle_patrol(&mut self, parts: &[&str]) -> Result<()> {
+        let usage = "用法: patrol <x,y> <x,y> ... [秒數]";
+        let (points, seconds) = match parts.last() {
+            Some(last) if parts.len() > 1 && !last.contains(',') => (&parts[1..parts.len() - 1], last.parse::<u64>()?),
+            _ => (&parts[1..], 60),
+        };
+        let client = self.game_client.as_mut().ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        let coordinates = client.coordinates();
+        let mut route = PatrolRoute::parse(points)
+            .map_err(|e| anyhow::anyhow!("{}\n{}", e, usage))?
+            .map(|point| coordinates.to_backend(point));
+        println!("{} 巡邏 {} 個路徑點，持續 {} 秒（按 Ctrl-C 提前結束）", "→".green(), points.len(), seconds);
+        client.patrol(&mut route, std::time::Duration::from_secs(seconds)).await?;
+        println!("{} 巡邏結束：走完 {} 圈，送出 {} 個移動", "✓".green(), route.laps, route.moves);
+        Ok(())
+    }
+    
+    /// 處理機器人腳本命令
+    #[cfg(feature = "scripting")]
+    pub async fn handle_script(&mut self, parts: &[&str]) -> Result<()> {
+        let usage = "用法: script load <檔案> | script run [秒數] [間隔毫秒] | script";
+        match parts.get(1).copied() {
+            Some("load") => {
+                let path = parts.get(2).ok_or_else(|| anyhow::anyhow!(usage))?;
+                let script = crate::bot_script::BotScript::load(std::path::Path::new(path))?;
+                println!("{} 已載入腳本 {}", "✓".green(), path);
+                self.bot_script = Some(script);
+            }
+            Some("run") => {
+                let seconds: u64 = parts.get(2).map(|value| value.parse()).transpose()?.unwrap_or(30);
+                let period_ms: u64 = parts.get(3).map(|value| value.parse()).transpose()?.unwrap_or(500);
+                let script = self.bot_script.as_mut().ok_or_else(|| anyhow::anyhow!("請先以 script load <檔案> 載入腳本"))?;
+                let client = self.game_client.as_mut().ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+                println!("{} 執行腳本 {:?}，持續 {} 秒（按 Ctrl-C 提前結束）", "→".green(), script.path, seconds);
+                let stats = script.run(client, std::time::Duration::from_secs(seconds), std::time::Duration::from_millis(period_ms.max(50))).await?;
+                println!("{} 腳本結束：{} 次 tick，送出 {} 個操作，{} 個失敗", "✓".green(), stats.ticks, stats.actions, stats.errors);
+            }
+            None => match &self.bot_script {
+                Some(script) => println!("已載入腳本 {:?}", script.path),
+                None => println!("尚未載入腳本"),
+            },
+            Some(_) => return Err(anyhow::anyhow!(usage)),
+        }
+        Ok(())
+    }
+    
+    /// 處理回城命令
+    pub async fn handle_recall(&mut self) -> Result<()> {
+        if let Some(client) = &mut self.game_client {
+            let spawn = client.coordinates().to_local(client.spawn_point());
+            println!("{} 回城中，目的地 ({:.1}, {:.1})", "→".green(), spawn.x, spawn.y);
+            client.recall().await?;
+            println!("{} 已回到基地", "✓".green());
+        } else {
+            return Err(anyhow::anyhow!("請先連接到服務器"));
+        }
+        
+        Ok(())
+    }
+    
+    /// 處理購物行程命令
+    pub async fn handle_shop_trip(&mut self) -> Result<()> {
+        if let Some(client) = &mut self.game_client {
+            let items = &self.app_config.macros.shop_items;
+            println!("{} 購物行程: 回城 → 購買 [{}] → 返回線上", "→".green(), items.join(", "));
+            client.shop_trip().await?;
+            println!("{} 購物行程完成", "✓".green());
+        } else {
+            return Err(anyhow::anyhow!("請先連接到服務器"));
+        }
+        
+        Ok(())
+    }
+    
+    /// 處理商店命令：列出商品與道具欄
+    pub async fn handle_shop(&mut self) -> Result<()> {
+        let client = self.game_client.as_mut().ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        client.sync_shared_state().await?;
+        println!("{} 商店（金錢 {}）:", "🛒".bright_yellow(), client.get_game_state().local_player.gold.to_string().bright_yellow());
+        for item in &client.shop().items {
+            println!("  {:<16} {:<8} 價格 {:>5}  賣出 {:>5}  每格 {} 份", item.id.bright_white(), item.name, item.cost, item.sell_value(), item.max_stack);
+        }
+        println!("{} 道具欄:", "🎒".bright_yellow());
+        for item in &client.get_game_state().local_player.items {
+            println!("  {} 號位 {:<16} {} × {}", item.slot, item.item_id, item.name, item.charges);
+        }
+        Ok(())
+    }
+    
+    /// 處理購買命令
+    pub async fn handle_buy(&mut self, parts: &[&str]) -> Result<()> {
+        let item_id = parts.get(1).ok_or_else(|| anyhow::anyhow!("用法: buy <道具 ID>"))?;
+        let client = self.game_client.as_mut().ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        client.sync_shared_state().await?;
+        let slot = client.buy_item(item_id).await?;
+        println!("{} 已購買 {}，放入 {} 號位（剩餘金錢 {}）", "✓".green(), item_id, slot, client.get_game_state().local_player.gold);
+        Ok(())
+    }
+    
+    /// 處理賣出命令
+    pub async fn handle_sell(&mut self, parts: &[&str]) -> Result<()> {
+        let slot: u8 = parts.get(1).ok_or_else(|| anyhow::anyhow!("用法: sell <號位>"))?.parse()?;
+        let client = self.game_client.as_mut().ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        client.sync_shared_state().await?;
+        let (item_id, refund) = client.sell_item(slot).await?;
+        println!("{} 已賣出 {} 號位的 {}，退還 {} 金錢", "✓".green(), slot, item_id, refund);
+        Ok(())
+    }
+    
     /// 處理視窗設置命令
     pub async fn handle_viewport(&mut self, parts: &[&str]) -> Result<()> {
         if let Some(client) = &mut self.game_client {
@@ -300,7 +802,7 @@ impl CommandHandler {
         let zoom: f32 = parts[1].parse()?;
         
         if let Some(client) = &mut self.game_client {
-            client.get_game_state_mut().viewport.set_zoom(zoom);
+            client.set_zoom(zoom);
             client.send_viewport_update().await?;
             
             println!("{} 縮放設為: {:.1}x", "✓".green(), zoom);
@@ -315,6 +817,60 @@ impl CommandHandler {
         Ok(())
     }
     
+    /// 處理疊加層命令（列出或以名稱、編號開關）
+    pub fn handle_overlays(&mut self, parts: &[&str]) -> Result<()> {
+        let Some(key) = parts.get(1) else {
+            println!("{}", "地圖疊加層（終端視圖中按 Alt+編號切換）:".bright_cyan());
+            for (number, name, description, enabled) in self.overlays.entries() {
+                let state = if enabled { "開啟".green() } else { "關閉".bright_black() };
+                println!("  {} {:<8} {} - {}", number, name.yellow(), state, description);
+            }
+            return Ok(());
+        };
+        let result = match parts.get(2).copied() {
+            None => self.overlays.toggle(key),
+            Some("on") => self.overlays.set(key, true).map(|name| (name, true)),
+            Some("off") => self.overlays.set(key, false).map(|name| (name, false)),
+            Some(_) => return Err(anyhow::anyhow!("用法: overlays [<名稱|編號> [on|off]]")),
+        };
+        let (name, enabled) = result.ok_or_else(|| anyhow::anyhow!("未知的疊加層: {}（輸入 overlays 列出）", key))?;
+        println!("{} 疊加層 {}: {}", "✓".green(), name, if enabled { "開啟" } else { "關閉" });
+        Ok(())
+    }
+    
+    /// 處理鏡頭命令（跟隨英雄、固定位置或地圖總覽）
+    pub fn handle_camera(&mut self, parts: &[&str]) -> Result<()> {
+        let Some(client) = &mut self.game_client else {
+            return Err(anyhow::anyhow!("請先連接到服務器"));
+        };
+        let mode = match parts.get(1).copied() {
+            None => {
+                let view = client.camera().view(client.get_game_state());
+                let center = client.coordinates().to_local(view.center);
+                println!("\n{}", "鏡頭:".bright_cyan().bold());
+                println!("  模式: {}", client.camera().mode().label().bright_white());
+                println!("  中心: ({:.1}, {:.1})", center.x, center.y);
+                println!("  範圍: {:.0} x {:.0} (縮放 {:.1}x)", view.width, view.height, view.zoom);
+                return Ok(());
+            }
+            Some("follow") => CameraMode::Follow,
+            Some("overview") => {
+                if client.get_game_state().world_bounds.is_none() {
+                    println!("{} 地圖邊界未知，總覽模式暫時跟隨英雄", "!".yellow());
+                }
+                CameraMode::Overview
+            }
+            Some(x) if parts.len() >= 3 => {
+                let local = Vec2::new(x.parse()?, parts[2].parse()?);
+                CameraMode::Fixed(client.coordinates().to_backend(local))
+            }
+            _ => return Err(anyhow::anyhow!("用法: camera [follow|overview|<x> <y>]")),
+        };
+        client.set_camera_mode(mode);
+        println!("{} 鏡頭模式: {}（視野更新將自動送出）", "✓".green(), mode.label());
+        Ok(())
+    }
+    
     /// 處理後端管理命令
     pub async fn handle_backend(&mut self, parts: &[&str]) -> Result<()> {
         if parts.len() < 2 {
@@ -353,7 +909,10 @@ impl CommandHandler {
             "restart" => {
                 println!("{} 重啟後端...", "🔄".bright_white());
                 match backend_manager.restart().await {
-                    Ok(_) => println!("{} 後端已重啟", "✅".green()),
+                    Ok(_) => {
+                        println!("{} 後端已重啟", "✅".green());
+                        self.restore_session().await;
+                    },
                     Err(e) => println!("{} 重啟失敗: {}", "❌".red(), e),
                 }
             },
@@ -386,81 +945,843 @@ impl CommandHandler {
         Ok(())
     }
     
-    /// 處理技能列表命令
-    pub fn handle_abilities(&self) -> Result<()> {
-        println!("\n{}", "可用英雄和技能:".bright_cyan().bold());
+    /// 受管理的後端意外退出時重新啟動並恢復遊戲會話（每個命令執行前檢查）
+    pub async fn supervise_backend(&mut self) {
+        let Some(backend_manager) = &self.backend_manager else {
+            return;
+        };
+        match backend_manager.restart_if_crashed().await {
+            Ok(true) => {
+                println!("{} 後端意外退出，已自動重啟", "🔄".bright_white());
+                self.restore_session().await;
+            }
+            Ok(false) => {}
+            Err(e) => println!("{} 自動重啟後端失敗: {}", "❌".red(), e),
+        }
+    }
+    
+    /// 重啟後的後端沒有原本的玩家會話，自動重新握手、進入遊戲並送出視野
+    async fn restore_session(&mut self) {
+        if let Some(client) = self.game_client.as_mut()
+            .filter(|client| client.get_state().in_game() || matches!(client.get_state(), ClientState::Connected | ClientState::Reconnecting(_))) {
+            match client.restore_session().await {
+                Ok(()) => println!("{} 已恢復遊戲會話", "🔁".bright_white()),
+                Err(e) => println!("{} 恢復會話失敗: {}", "❌".red(), e),
+            }
+        }
+    }
+    
+    /// 處理添加書籤命令
+    pub fn handle_bookmark(&mut self, parts: &[&str]) -> Result<()> {
+        let note = if parts.len() > 1 {
+            Some(parts[1..].join(" "))
+        } else {
+            None
+        };
+        
+        if let Some(client) = &mut self.game_client {
+            let bookmark = client.add_bookmark(note)?;
+            println!("{} 已添加書籤 +{:.1}s 位置 ({:.1}, {:.1})", "🔖".bright_yellow(),
+                bookmark.session_secs, bookmark.position.0, bookmark.position.1);
+        } else {
+            return Err(anyhow::anyhow!("請先連接到服務器"));
+        }
+        
+        Ok(())
+    }
+    
+    /// 處理書籤列表命令
+    pub fn handle_bookmarks(&self) -> Result<()> {
+        let path = match &self.game_client {
+            Some(client) => client.get_bookmarks().path().to_path_buf(),
+            None => std::path::PathBuf::from(crate::bookmarks::DEFAULT_BOOKMARK_FILE),
+        };
+        let bookmarks = crate::bookmarks::BookmarkStore::load_from_file(&path)?;
+        
+        println!("\n{} ({})", "書籤列表:".bright_cyan().bold(), path.display());
+        println!("{}", "-".repeat(40).bright_black());
+        
+        if bookmarks.is_empty() {
+            println!("  {}", "尚無書籤".bright_black());
+        }
+        
+        for (i, bookmark) in bookmarks.iter().enumerate() {
+            println!("  [{}] +{:.1}s {} ({:.1}, {:.1}) {}",
+                i + 1,
+                bookmark.session_secs,
+                bookmark.player_name.bright_yellow(),
+                bookmark.position.0,
+                bookmark.position.1,
+                bookmark.note.as_deref().unwrap_or("").green());
+        }
+        
+        if let Some(client) = &self.game_client {
+            println!("  本次會話: {} 個書籤", client.get_bookmarks().session_bookmarks().len());
+        }
+        
+        Ok(())
+    }
+    
+    /// 處理協議異常列表命令
+    pub fn handle_anomalies(&mut self, parts: &[&str]) -> Result<()> {
+        let client = self.game_client.as_mut()
+            .ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        
+        if parts.get(1) == Some(&"clear") {
+            client.sanity_mut().clear();
+            println!("{} 已清除協議異常記錄", "✓".green());
+            return Ok(());
+        }
+        
+        let monitor = client.sanity();
+        println!("\n{}", "協議異常:".bright_cyan().bold());
         println!("{}", "-".repeat(40).bright_black());
         
-        println!("\n{} (saika_magoichi):", "雜賀孫市".bright_yellow());
-        println!("  • {} - 狙擊模式", "sniper_mode".green());
-        println!("  • {} - 雜賀眾", "saika_reinforcements".green());
-        println!("  • {} - 雨鐵炮", "rain_iron_cannon".green());
-        println!("  • {} - 三段擊", "three_stage_technique".green());
+        if monitor.anomalies().is_empty() {
+            println!("  {}", "未發現異常".bright_black());
+            return Ok(());
+        }
+        
+        for (kind, count) in monitor.counts() {
+            println!("  {}: {}", kind.label().bright_yellow(), count);
+        }
+        println!();
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_millis() as u64;
+        for anomaly in monitor.anomalies().iter().rev().take(20) {
+            println!("  {} {:>6.1}s 前 [{}] {} - {}",
+                "🚨".red(),
+                now_ms.saturating_sub(anomaly.timestamp_ms) as f64 / 1000.0,
+                anomaly.kind.label().bright_red(),
+                anomaly.subject.bright_white(),
+                anomaly.details);
+        }
+        
+        Ok(())
+    }
+    
+    /// 處理訊息結構驗證報告命令
+    pub async fn handle_schema(&mut self, parts: &[&str]) -> Result<()> {
+        let client = self.game_client.as_mut()
+            .ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
         
-        println!("\n{} (date_masamune):", "伊達政宗".bright_yellow());
-        println!("  • {} - 火焰刀", "flame_blade".green());
-        println!("  • {} - 火焰衝刺", "fire_dash".green());
-        println!("  • {} - 火焰突擊", "flame_assault".green());
-        println!("  • {} - 火繩槍", "matchlock_gun".green());
+        if parts.get(1) == Some(&"clear") {
+            client.clear_schema_report().await;
+            println!("{} 已清除訊息結構驗證統計", "✓".green());
+            return Ok(());
+        }
         
+        client.sync_shared_state().await?;
+        client.get_game_state().schema_report.print();
         Ok(())
     }
     
-    /// 處理實時視圖輸入動作
-    pub async fn handle_view_input(&mut self, input: UserInput) -> Result<()> {
+    /// 處理擊殺歸屬驗證結果命令
+    pub async fn handle_credits(&mut self) -> Result<()> {
+        let client = self.game_client.as_mut()
+            .ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        client.sync_shared_state().await?;
+        
+        let checks = client.get_game_state().credits.checks();
+        println!("\n{}", "擊殺歸屬驗證:".bright_cyan().bold());
+        println!("{}", "-".repeat(40).bright_black());
+        
+        if checks.is_empty() {
+            println!("  {}", "尚未收到擊殺訊息".bright_black());
+            return Ok(());
+        }
+        
+        let failed = checks.iter().filter(|check| !check.passed()).count();
+        println!("  已驗證 {} 次，不符 {} 次", checks.len(), failed);
+        for check in checks.iter().rev().take(20) {
+            let status = if check.passed() { "✓".green() } else { "✗".red() };
+            let ago = check.time.elapsed().unwrap_or_default().as_secs_f64();
+            println!("  {} {:>6.1}s 前 {} 擊殺 {}，助攻 [{}]",
+                status,
+                ago,
+                check.credit.killer.bright_yellow(),
+                check.credit.victim.bright_white(),
+                check.credit.assists.join(", "));
+            let contributions: Vec<String> = check.contributions.iter()
+                .map(|c| format!("{} {:.0}", c.source, c.damage))
+                .collect();
+            println!("      傷害貢獻: {}", contributions.join(", ").bright_black());
+            for failure in &check.failures {
+                println!("      {}", failure.red());
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// 處理技能施放統計命令
+    pub fn handle_casts(&mut self, parts: &[&str]) -> Result<()> {
+        let client = self.game_client.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        if parts.get(1) == Some(&"clear") {
+            client.clear_cast_stats();
+            println!("{} 已清除技能施放統計", "✓".green());
+            return Ok(());
+        }
+        
+        let cast_stats = client.cast_stats();
+        println!("\n{}", "技能施放統計:".bright_cyan().bold());
+        if cast_stats.abilities().is_empty() {
+            println!("  {}", "尚未施放任何技能".bright_black());
+            return Ok(());
+        }
+        let mut lines = cast_stats.table().into_iter();
+        if let Some(header) = lines.next() {
+            println!("  {}", header.bright_black());
+        }
+        for line in lines {
+            println!("  {}", line);
+        }
+        println!("  等待結果: {}", cast_stats.pending());
+        Ok(())
+    }
+    
+    /// 處理操作結果驗證命令
+    pub async fn handle_expectations(&mut self, parts: &[&str]) -> Result<()> {
+        let client = self.game_client.as_mut()
+            .ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        if parts.get(1) == Some(&"clear") {
+            client.expectations_mut().clear();
+            println!("{} 已清除操作結果驗證紀錄", "✓".green());
+            return Ok(());
+        }
+        client.tick().await?;
+        
+        let expectations = client.expectations();
+        println!("\n{}", "操作結果驗證:".bright_cyan().bold());
+        println!("{}", "-".repeat(40).bright_black());
+        
+        if expectations.tallies().is_empty() {
+            println!("  {} (等待確認 {} 個)", "尚未完成任何比對".bright_black(), expectations.pending());
+            return Ok(());
+        }
+        
+        for (action, tally) in expectations.tallies() {
+            let mismatched = if tally.mismatched > 0 { tally.mismatched.to_string().red() } else { "0".green() };
+            println!("  {:<14} 相符 {}  不符 {}", action, tally.matched, mismatched);
+        }
+        println!("  等待確認: {}", expectations.pending());
+        
+        if !expectations.discrepancies().is_empty() {
+            println!("\n  {}", "最近的差異:".yellow());
+            for discrepancy in expectations.discrepancies().iter().rev() {
+                let ago = discrepancy.time.elapsed().unwrap_or_default().as_secs_f64();
+                println!("  {:>6.1}s 前 [{}] {}", ago, discrepancy.action.bright_white(), discrepancy.detail);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// 處理技能連段延遲量測命令
+    pub async fn handle_combo(&mut self, parts: &[&str]) -> Result<()> {
+        let usage = "用法: combo <技能>[@延遲毫秒] ... [--at <x> <y>] [--timeout <毫秒>]";
+        let mut steps = Vec::new();
+        let mut target = None;
+        let mut timeout_ms = 3000;
+        let mut args = parts.iter().skip(1);
+        while let Some(arg) = args.next() {
+            match *arg {
+                "--at" => {
+                    let (Some(x), Some(y)) = (args.next(), args.next()) else {
+                        return Err(anyhow::anyhow!("{}", usage));
+                    };
+                    target = Some(Vec2::new(x.parse::<f32>()?, y.parse::<f32>()?));
+                }
+                "--timeout" => {
+                    timeout_ms = args.next().ok_or_else(|| anyhow::anyhow!("{}", usage))?.parse()?;
+                }
+                spec => steps.push(ComboStep::parse(spec)?),
+            }
+        }
+        if steps.is_empty() {
+            return Err(anyhow::anyhow!("{}", usage));
+        }
+        
+        let client = self.game_client.as_mut()
+            .ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        let target = target.map(|target| client.coordinates().to_backend(target));
+        println!("{} 施放連段: {}", "→".green(), steps.iter()
+            .map(|step| format!("{}@{}ms", step.ability_id, step.delay_ms))
+            .collect::<Vec<_>>()
+            .join(" → "));
+        let report = client.run_combo(&steps, target, std::time::Duration::from_millis(timeout_ms)).await?;
+        report.print();
+        Ok(())
+    }
+    
+    /// 處理訓練假人命令
+    pub async fn handle_dummy(&mut self, parts: &[&str]) -> Result<()> {
+        let client = self.game_client.as_mut()
+            .ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        match parts.get(1).copied() {
+            Some("spawn") => {
+                let count = match parts.get(2) {
+                    Some(count) => count.parse().map_err(|_| anyhow::anyhow!("無效的數量: {}", count))?,
+                    None => 1,
+                };
+                let hp = match parts.get(3) {
+                    Some(hp) => Some(hp.parse().map_err(|_| anyhow::anyhow!("無效的生命值: {}", hp))?),
+                    None => None,
+                };
+                let ids = client.spawn_dummies(count, hp).await?;
+                println!("{} 已生成訓練假人: {}", "✓".green(), ids.join(", "));
+                println!("  攻擊假人後以 {} 查看 DPS", "dummy report".bright_white());
+            }
+            Some("report") => {
+                client.tick().await?;
+                client.dps_report().print();
+            }
+            Some("clear") => {
+                let count = client.clear_dummies().await?;
+                println!("{} 已移除 {} 個訓練假人", "✓".green(), count);
+            }
+            _ => println!("用法: dummy spawn [n] [hp] | dummy report | dummy clear"),
+        }
+        Ok(())
+    }
+    
+    /// 處理練習沙盒命令
+    pub fn handle_sandbox(&mut self, parts: &[&str]) -> Result<()> {
+        let client = self.game_client.as_mut()
+            .ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        match parts.get(1).copied() {
+            Some("on") => {
+                client.set_sandbox(true)?;
+                println!("{} 練習沙盒已啟用：使用技能或道具後自動重置冷卻並補滿", "✓".green());
+                println!("  以 {} 生成的訓練假人會循環重新生成", "dummy spawn".bright_white());
+            }
+            Some("off") => {
+                client.set_sandbox(false)?;
+                println!("{} 練習沙盒已停用", "✓".green());
+            }
+            Some("status") | None => client.sandbox().print(),
+            _ => println!("用法: sandbox on | sandbox off | sandbox status"),
+        }
+        Ok(())
+    }
+    
+    /// 處理 MQTT 統計命令
+    pub fn handle_stats(&self) -> Result<()> {
+        let client = self.game_client.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        client.mqtt_stats().snapshot().print();
+        Ok(())
+    }
+    
+    /// 處理主題監聽命令（另開一條連線訂閱主題過濾器，未連接時使用目前配置的 broker）
+    pub async fn handle_sniff(&self, parts: &[&str]) -> Result<()> {
+        let pattern = parts.get(1).copied().unwrap_or(DEFAULT_PATTERN);
+        let options = match &self.game_client {
+            Some(client) => client.sniffer_options()?,
+            None => self.create_client().sniffer_options()?,
+        };
+        let received = crate::sniffer::run(options, pattern, self.config.wire_format).await?;
+        println!("{} 監聽 {} 結束，共收到 {} 則訊息", "✓".green(), pattern, received);
+        Ok(())
+    }
+    
+    /// 處理固定範圍畫面請求命令
+    pub async fn handle_area(&self, parts: &[&str]) -> Result<()> {
+        let client = self.game_client.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        if parts.len() < 5 {
+            return Err(anyhow::anyhow!("用法: area <min_x> <min_y> <max_x> <max_y>"));
+        }
+        let bounds = parts[1..5].iter()
+            .map(|value| value.parse::<f32>().map_err(|_| anyhow::anyhow!("無效的座標: {}", value)))
+            .collect::<Result<Vec<_>>>()?;
+        let response = client.request_screen_area(bounds[0], bounds[1], bounds[2], bounds[3]).await?;
+        println!("{} 請求 {} 由 {} 回應，耗時 {} 毫秒", "✓".green(), response.id.bright_black(),
+                 response.topic, response.latency.as_millis());
+        println!("{}", serde_json::to_string_pretty(&response.message)?);
+        Ok(())
+    }
+    
+    /// 處理測試地形編輯命令
+    pub async fn handle_map(&mut self, parts: &[&str]) -> Result<()> {
+        let usage = "用法: map show | map new <寬> <高> [每格單位] | map paint <wall|water|tree|empty> <x> <y> [x2 y2] | map save <檔案> | map load <檔案> | map push";
+        let cell = |index: usize| -> Result<usize> {
+            let value = parts.get(index).ok_or_else(|| anyhow::anyhow!(usage))?;
+            value.parse().map_err(|_| anyhow::anyhow!("無效的格子座標: {}", value))
+        };
+        match parts.get(1).copied() {
+            Some("show") | None => self.terrain.print(),
+            Some("new") => {
+                let cell_size = match parts.get(4) {
+                    Some(size) => size.parse().map_err(|_| anyhow::anyhow!("無效的格子大小: {}", size))?,
+                    None => DEFAULT_CELL_SIZE,
+                };
+                self.terrain = TerrainGrid::new(cell(2)?, cell(3)?, cell_size)?;
+                println!("{} 已建立空白地形", "✓".green());
+            }
+            Some("paint") => {
+                let name = parts.get(2).ok_or_else(|| anyhow::anyhow!(usage))?;
+                let tile = Tile::parse(name).ok_or_else(|| anyhow::anyhow!("未知的地形: {}（可用 wall、water、tree、empty）", name))?;
+                let from = (cell(3)?, cell(4)?);
+                let to = if parts.len() > 5 { (cell(5)?, cell(6)?) } else { from };
+                let changed = self.terrain.paint_rect(from, to, tile);
+                println!("{} 已將 {} 格繪製為 {}", "✓".green(), changed, tile.name());
+            }
+            Some("save") => {
+                let path = parts.get(2).ok_or_else(|| anyhow::anyhow!(usage))?;
+                self.terrain.save(path)?;
+                println!("{} 已匯出地形到 {}", "✓".green(), path);
+            }
+            Some("load") => {
+                let path = parts.get(2).ok_or_else(|| anyhow::anyhow!(usage))?;
+                self.terrain = TerrainGrid::load(path)?;
+                println!("{} 已從 {} 載入地形", "✓".green(), path);
+            }
+            Some("push") => {
+                let client = self.game_client.as_mut()
+                    .ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+                let count = client.push_terrain(&self.terrain.to_map()).await?;
+                println!("{} 已推送 {} 格地形到後端", "✓".green(), count);
+            }
+            _ => println!("{}", usage),
+        }
+        Ok(())
+    }
+    
+    /// 處理設定預設命令
+    pub async fn handle_preset(&mut self, parts: &[&str]) -> Result<()> {
+        match (parts.get(1).copied(), parts.get(2).copied()) {
+            (Some("list") | None, _) => self.list_presets(),
+            (Some("save"), Some(name)) => {
+                let preset = Preset {
+                    hero_type: self.config.hero_type.clone(),
+                    shop_items: self.app_config.macros.shop_items.clone(),
+                    input: self.app_config.input.clone(),
+                    camera: self.app_config.camera.clone(),
+                };
+                self.presets.save(name, preset)?;
+                println!("{} 已保存預設: {}", "✓".green(), name.bright_yellow());
+            }
+            (Some("load"), Some(name)) => {
+                let preset = self.presets.get(name).cloned()
+                    .ok_or_else(|| anyhow::anyhow!("找不到預設: {}", name))?;
+                self.apply_preset(&preset).await?;
+                println!("{} 已切換到預設: {}", "✓".green(), name.bright_yellow());
+            }
+            (Some("show"), Some(name)) => {
+                let preset = self.presets.get(name)
+                    .ok_or_else(|| anyhow::anyhow!("找不到預設: {}", name))?;
+                print_preset(name, preset);
+            }
+            (Some("delete"), Some(name)) => {
+                if self.presets.remove(name)? {
+                    println!("{} 已刪除預設: {}", "✓".green(), name);
+                } else {
+                    println!("{} 找不到預設: {}", "!".yellow(), name);
+                }
+            }
+            _ => println!("{} 用法: preset [list|save <名稱>|load <名稱>|show <名稱>|delete <名稱>]", "!".yellow()),
+        }
+        Ok(())
+    }
+    
+    /// 列出所有設定預設
+    fn list_presets(&self) {
+        println!("\n{}", "設定預設:".bright_cyan().bold());
+        println!("{}", "-".repeat(40).bright_black());
+        if self.presets.presets().is_empty() {
+            println!("  {}", "尚無預設（preset save <名稱> 保存目前設定）".bright_black());
+            return;
+        }
+        for (name, preset) in self.presets.presets() {
+            println!("  {} - 英雄 {}，施法 {}，出裝 [{}]",
+                name.bright_yellow(), preset.hero_type, preset.input.cast_mode.label(), preset.shop_items.join(", "));
+        }
+    }
+    
+    /// 套用設定預設（連線中會立即切換英雄、出裝與鏡頭縮放）
+    async fn apply_preset(&mut self, preset: &Preset) -> Result<()> {
+        if crate::hero_catalog::find_hero(&preset.hero_type).is_none() {
+            return Err(anyhow::anyhow!("預設中的英雄未知: {}", preset.hero_type));
+        }
+        
+        if let Some(client) = &mut self.game_client {
+            if client.get_state().in_game() && preset.hero_type != self.config.hero_type {
+                client.switch_hero(&preset.hero_type).await?;
+            }
+        }
+        
+        self.config.hero_type = preset.hero_type.clone();
+        self.app_config.macros.shop_items = preset.shop_items.clone();
+        self.app_config.input = preset.input.clone();
+        self.app_config.camera = preset.camera.clone();
+        
         if let Some(client) = &mut self.game_client {
-            match input {
-                UserInput::Move(world_pos) => {
-                    println!("{} 移動到: ({:.1}, {:.1})", "🚶".bright_green(), world_pos.x, world_pos.y);
-                    client.perform_action("move", serde_json::json!({
-                        "x": world_pos.x,
-                        "y": world_pos.y
-                    })).await?;
+            client.configure_macros(&self.app_config.macros);
+            client.configure_camera(&preset.camera);
+            if client.get_state().in_game() {
+                client.send_viewport_update().await?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// 處理版本命令
+    pub fn handle_version(&self) -> Result<()> {
+        let backend = self.game_client.as_ref().and_then(|client| client.backend_version());
+        crate::version::print_version(backend.as_ref());
+        Ok(())
+    }
+    
+    /// 處理稽核記錄命令
+    pub fn handle_audit(&self, parts: &[&str]) -> Result<()> {
+        match parts.get(1).copied() {
+            Some("show") => {
+                let limit = match parts.get(2) {
+                    Some(n) => n.parse()?,
+                    None => 20,
+                };
+                self.audit.show(limit)
+            }
+            _ => {
+                println!("{} 用法: audit show [筆數]", "!".yellow());
+                Ok(())
+            }
+        }
+    }
+    
+    /// 處理記分板命令
+    pub async fn handle_scoreboard(&mut self) -> Result<()> {
+        let client = self.game_client.as_mut()
+            .ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        client.sync_shared_state().await?;
+        
+        let game_state = client.get_game_state();
+        let rows = game_state.scoreboard.rows();
+        if rows.is_empty() {
+            println!("{} 尚未記錄到擊殺或陣亡", "!".yellow());
+            return Ok(());
+        }
+        println!("\n{}", "記分板:".bright_cyan().bold());
+        println!("{}", "-".repeat(40).bright_black());
+        for (name, kda) in rows {
+            let life = game_state.life_of(name);
+            let status = life.countdown().unwrap_or_else(|| "存活".to_string());
+            let line = format!("  {:<12} {:>9}  KDA {:.1}  {}", name, kda.to_string(), kda.ratio(), status);
+            if name == game_state.local_player.name {
+                println!("{}", line.bright_yellow());
+            } else {
+                println!("{}", line);
+            }
+        }
+        Ok(())
+    }
+    
+    /// 處理死亡回顧命令
+    pub async fn handle_recap(&mut self) -> Result<()> {
+        let client = self.game_client.as_mut()
+            .ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        client.sync_shared_state().await?;
+        
+        match client.get_game_state().combat_log.last_recap() {
+            Some(recap) => {
+                let mut lines = recap.lines().into_iter();
+                if let Some(title) = lines.next() {
+                    println!("\n{}", title.bright_red().bold());
                 }
-                UserInput::Attack(world_pos) => {
-                    println!("{} 攻擊位置: ({:.1}, {:.1})", "⚔️".bright_red(), world_pos.x, world_pos.y);
-                    client.perform_action("attack", serde_json::json!({
-                        "target_position": [world_pos.x, world_pos.y],
-                        "attack_type": "basic"
-                    })).await?;
+                println!("{}", "-".repeat(40).bright_black());
+                for line in lines {
+                    println!("{}", line);
                 }
-                UserInput::MoveAttack(world_pos) => {
-                    println!("{} 移動攻擊到: ({:.1}, {:.1})", "🏃⚔️".bright_yellow(), world_pos.x, world_pos.y);
-                    // 先移動再攻擊
-                    client.perform_action("move", serde_json::json!({
-                        "x": world_pos.x,
-                        "y": world_pos.y
-                    })).await?;
-                    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
-                    client.perform_action("attack", serde_json::json!({
-                        "target_position": [world_pos.x, world_pos.y],
-                        "attack_type": "move_attack"
-                    })).await?;
+            }
+            None => println!("{} 尚未記錄到陣亡", "!".yellow()),
+        }
+        
+        Ok(())
+    }
+    
+    /// 處理警報命令
+    pub async fn handle_alerts(&mut self) -> Result<()> {
+        let client = self.game_client.as_mut()
+            .ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        // 互動模式沒有每幀更新，顯示前先檢查一次
+        client.tick().await?;
+        
+        println!("\n{}", "警報規則:".bright_cyan().bold());
+        println!("{}", "-".repeat(40).bright_black());
+        
+        let rules = client.alerts().rules();
+        if rules.is_empty() {
+            println!("  {}", "尚未定義警報規則（在 config.toml 的 [[alerts.rules]] 中添加）".bright_black());
+            return Ok(());
+        }
+        
+        for state in rules {
+            let status = if state.firing { "觸發中".bright_red() } else { "正常".green() };
+            let value = state.last_value.map(|v| format!("{:.2}", v)).unwrap_or_else(|| "-".to_string());
+            println!("  {} [{}] {} - 目前值 {}，已觸發 {} 次",
+                state.rule.name.bright_yellow(), status, state.describe(), value, state.fire_count);
+            if let Some(error) = &state.last_error {
+                println!("    {} {}", "錯誤:".red(), error);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// 處理狀態查詢命令
+    pub async fn handle_query(&mut self, parts: &[&str]) -> Result<()> {
+        if parts.len() < 2 {
+            println!("{} 用法: query <運算式>", "!".yellow());
+            println!("  例如: query entities[type==\"summon\"].count()");
+            println!("        query player.health.0 / player.health.1");
+            return Ok(());
+        }
+        
+        let client = self.game_client.as_mut()
+            .ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        client.sync_shared_state().await?;
+        
+        let source = parts[1..].join(" ");
+        match client.query(&source) {
+            Ok(value) => {
+                let text = match &value {
+                    serde_json::Value::Array(_) | serde_json::Value::Object(_) => serde_json::to_string_pretty(&value)?,
+                    other => other.to_string(),
+                };
+                println!("{} {}", "=".bright_green(), text.bright_white());
+            }
+            Err(e) => {
+                println!("{} 查詢失敗: {}", "❌".red(), e);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// 處理自訂指標命令
+    #[cfg(feature = "metrics")]
+    pub fn handle_metrics(&mut self, parts: &[&str]) -> Result<()> {
+        let client = self.game_client.as_mut()
+            .ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        
+        match parts.get(1).copied() {
+            Some("add") => {
+                if parts.len() < 4 {
+                    println!("{} 用法: metrics add <名稱> <運算式>", "!".yellow());
+                    return Ok(());
                 }
-                UserInput::ForceAttack(world_pos) => {
-                    println!("{} 強制攻擊位置: ({:.1}, {:.1})", "💥".bright_red(), world_pos.x, world_pos.y);
-                    client.perform_action("attack", serde_json::json!({
-                        "target_position": [world_pos.x, world_pos.y],
-                        "attack_type": "force_attack"
-                    })).await?;
+                let name = parts[2];
+                let source = parts[3..].join(" ");
+                client.metrics_mut().add(name, &source)?;
+                println!("{} 已添加指標 {} = {}", "📈".bright_green(), name.bright_yellow(), source);
+            }
+            Some("remove") => {
+                let name = parts.get(2)
+                    .ok_or_else(|| anyhow::anyhow!("用法: metrics remove <名稱>"))?;
+                if client.metrics_mut().remove(name) {
+                    println!("{} 已移除指標 {}", "✓".green(), name);
+                } else {
+                    println!("{} 找不到指標 {}", "!".yellow(), name);
                 }
-                UserInput::CastAbility(ability_id, world_pos) => {
-                    println!("{} 施放技能 {} 於位置: ({:.1}, {:.1})", "✨".bright_magenta(), ability_id, world_pos.x, world_pos.y);
-                    client.perform_action("cast_ability", serde_json::json!({
-                        "ability_id": ability_id,
-                        "target_position": [world_pos.x, world_pos.y],
-                        "level": 1
-                    })).await?;
+            }
+            Some(other) => {
+                println!("{} 未知的子命令: {}", "!".red(), other);
+            }
+            None => {
+                println!("\n{}", "自訂指標:".bright_cyan().bold());
+                println!("{}", "-".repeat(40).bright_black());
+                
+                let metrics = client.metrics().metrics();
+                if metrics.is_empty() {
+                    println!("  {}", "尚未定義指標".bright_black());
                 }
-                UserInput::UseItem(item_id, _target_pos) => {
-                    println!("{} 使用道具: {}", "🧪".bright_blue(), item_id);
-                    client.perform_action("use_item", serde_json::json!({
-                        "item_id": item_id
-                    })).await?;
+                for metric in metrics {
+                    let value = match metric.latest() {
+                        Some(v) => format!("{:.2}", v),
+                        None => "-".to_string(),
+                    };
+                    let samples: Vec<f64> = metric.history.iter().copied().collect();
+                    println!("  {} = {} {}  {}",
+                        metric.name.bright_yellow(),
+                        value.bright_white(),
+                        crate::metrics::sparkline(&samples).cyan(),
+                        metric.source.bright_black());
+                    if let Some(error) = &metric.last_error {
+                        println!("    {} {}", "錯誤:".red(), error);
+                    }
                 }
-                _ => {} // Continue 和 Cancel 不需要處理
             }
         }
+        
+        Ok(())
+    }
+    
+    /// 處理技能列表命令
+    pub fn handle_abilities(&self) -> Result<()> {
+        println!("\n{}", "可用英雄和技能:".bright_cyan().bold());
+        println!("{}", "-".repeat(40).bright_black());
+        
+        for hero in crate::hero_catalog::registry().heroes() {
+            println!("\n{} ({}):", hero.name.bright_yellow(), hero.id);
+            for ability in &hero.abilities {
+                println!("  • {} - {}（冷卻 {}s，魔力 {}，{}）", ability.id.green(), ability.name, ability.cooldown, ability.mana_cost, ability.cast_type.label());
+            }
+        }
+        
         Ok(())
     }
-}
\ No newline at end of file
+    
+    /// 處理切換英雄命令
+    pub async fn handle_switch_hero(&mut self, parts: &[&str]) -> Result<()> {
+        let hero_type = parts.get(1)
+            .ok_or_else(|| anyhow::anyhow!("用法: switch-hero <英雄ID>"))?;
+        let hero = crate::hero_catalog::find_hero(hero_type)
+            .ok_or_else(|| anyhow::anyhow!("未知的英雄: {}（輸入 abilities 查看可用英雄）", hero_type))?;
+        
+        let client = self.game_client.as_mut()
+            .ok_or_else(|| anyhow::anyhow!("請先連接到服務器"))?;
+        
+        println!("{} 切換英雄至 {} ({})...", "→".green(), hero.name.bright_yellow(), hero.id);
+        client.switch_hero(&hero.id).await?;
+        self.config.hero_type = hero.id.clone();
+        
+        let abilities: Vec<&str> = hero.abilities.iter().map(|ability| ability.name.as_str()).collect();
+        println!("{} 已切換英雄，技能欄: W={} E={} R={} T={}", "✓".green(),
+            abilities.first().unwrap_or(&"-"),
+            abilities.get(1).unwrap_or(&"-"),
+            abilities.get(2).unwrap_or(&"-"),
+            abilities.get(3).unwrap_or(&"-"));
+        Ok(())
+    }
+}
+
+/// 在客戶端上執行實時視圖的輸入動作
+pub async fn apply_view_input(handle: &GameClientHandle, input: UserInput) -> Result<()> {
+    match input {
+        UserInput::Move(world_pos) => {
+            println!("{} 移動到: ({:.1}, {:.1})", "🚶".bright_green(), world_pos.x, world_pos.y);
+            handle.perform_action("move", serde_json::json!({
+                "target_x": world_pos.x,
+                "target_y": world_pos.y
+            })).await?;
+        }
+        UserInput::Attack(world_pos) => {
+            println!("{} 攻擊位置: ({:.1}, {:.1})", "⚔️".bright_red(), world_pos.x, world_pos.y);
+            handle.perform_action("attack", serde_json::json!({
+                "target_position": [world_pos.x, world_pos.y],
+                "attack_type": "basic"
+            })).await?;
+        }
+        UserInput::MoveAttack(world_pos) => {
+            println!("{} 移動攻擊到: ({:.1}, {:.1})", "🏃⚔️".bright_yellow(), world_pos.x, world_pos.y);
+            // 先移動再攻擊
+            handle.perform_action("move", serde_json::json!({
+                "target_x": world_pos.x,
+                "target_y": world_pos.y
+            })).await?;
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            handle.perform_action("attack", serde_json::json!({
+                "target_position": [world_pos.x, world_pos.y],
+                "attack_type": "move_attack"
+            })).await?;
+        }
+        UserInput::ForceAttack(world_pos) => {
+            println!("{} 強制攻擊位置: ({:.1}, {:.1})", "💥".bright_red(), world_pos.x, world_pos.y);
+            handle.perform_action("attack", serde_json::json!({
+                "target_position": [world_pos.x, world_pos.y],
+                "attack_type": "force_attack"
+            })).await?;
+        }
+        UserInput::CastAbility(ability_id, target) => {
+            println!("{} 施放技能 {} 於{}", "✨".bright_magenta(), ability_id, target.describe());
+            handle.perform_action("cast_ability", target.params(&ability_id)).await?;
+        }
+        UserInput::Select(target) => {
+            handle.with(move |client| Box::pin(async move { client.select_target(target).await })).await?;
+        }
+        UserInput::UseItem(item_id, _target_pos) => {
+            println!("{} 使用道具: {}", "🧪".bright_blue(), item_id);
+            handle.perform_action("use_item", serde_json::json!({
+                "item_id": item_id
+            })).await?;
+        }
+        UserInput::Bookmark => {
+            let bookmark = handle.with(|client| Box::pin(async move { client.add_bookmark(None) })).await??;
+            println!("{} 已添加書籤 +{:.1}s", "🔖".bright_yellow(), bookmark.session_secs);
+        }
+        UserInput::Chat(message) => {
+            handle.with(move |client| Box::pin(async move { client.send_chat(&message).await })).await??;
+        }
+        UserInput::Macro(key, commands) => {
+            handle.with(move |client| Box::pin(async move { client.run_macro(&key, &commands).await })).await??;
+        }
+        _ => {} // Continue 和 Cancel 不需要處理
+    }
+    Ok(())
+}
+
+/// 打印單一設定預設
+fn print_preset(name: &str, preset: &Preset) {
+    let keymap = &preset.input.keymap;
+    let ability_keys: String = keymap.abilities.iter().map(|key| key.to_ascii_uppercase()).collect();
+    println!("\n{} {}", "設定預設:".bright_cyan().bold(), name.bright_yellow());
+    println!("{}", "-".repeat(40).bright_black());
+    println!("  英雄: {}", preset.hero_type);
+    println!("  出裝: [{}]", preset.shop_items.join(", "));
+    println!("  施法模式: {}", preset.input.cast_mode.label());
+    println!("  按鍵: 技能 {}，書籤 {}，統計 {}，死亡回顧 {}，網路統計 {}",
+        ability_keys,
+        keymap.bookmark.to_ascii_uppercase(),
+        keymap.stats.to_ascii_uppercase(),
+        keymap.death_recap.to_ascii_uppercase(),
+        keymap.network.to_ascii_uppercase());
+    println!("  鏡頭: {:.0} x {:.0}，縮放 {:.1}x，視野 {}",
+        preset.camera.view_width, preset.camera.view_height, preset.camera.zoom,
+        if preset.camera.show_vision { "顯示" } else { "隱藏" });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handler() -> CommandHandler {
+        let config = GameClientConfig { player_name: "hero".to_string(), ..GameClientConfig::default() };
+        CommandHandler::new(config, AppConfig::default())
+    }
+
+    #[tokio::test]
+    async fn parks_and_switches_sessions() {
+        let mut handler = handler();
+        handler.handle_session(&["session", "new", "bot1", "p1", "date_masamune"]).await.unwrap();
+        assert_eq!(handler.active_session, "bot1");
+        assert_eq!(handler.config.player_name, "p1");
+        assert_eq!(handler.config.hero_type, "date_masamune");
+        assert_eq!(handler.config.client_id, "omobaf_bot1");
+        assert_eq!(handler.parked_sessions.keys().collect::<Vec<_>>(), [DEFAULT_SESSION]);
+        assert!(handler.new_session("main", None, None).await.is_err(), "名稱重複");
+        
+        handler.use_session(DEFAULT_SESSION).unwrap();
+        assert_eq!(handler.active_session, DEFAULT_SESSION);
+        assert_eq!(handler.config.player_name, "hero");
+        assert_eq!(handler.parked_sessions["bot1"].config.player_name, "p1");
+        assert!(handler.use_session("missing").is_err());
+        assert_eq!(handler.active_session, DEFAULT_SESSION, "切換失敗時保留原會話");
+    }
+
+    #[tokio::test]
+    async fn closes_only_parked_sessions() {
+        let mut handler = handler();
+        handler.handle_session(&["session", "new", "bot1"]).await.unwrap();
+        handler.handle_session(&["session", "new", "bot2"]).await.unwrap();
+        assert!(handler.close_session("bot2").await.is_err(), "使用中的會話不能關閉");
+        
+        handler.close_session("bot1").await.unwrap();
+        assert_eq!(handler.parked_sessions.keys().collect::<Vec<_>>(), [DEFAULT_SESSION]);
+        assert!(handler.close_session("bot1").await.is_err());
+        assert!(handler.use_session("bot1").is_err());
+        assert_eq!(handler.active_session, "bot2");
+    }
+}
@@ -7,7 +7,9 @@ use colored::*;
 use crate::game_client::{GameClientConfig, ClientState};
 use crate::terminal_view::{TerminalView, UserInput};
 use crate::config::AppConfig;
-use super::commands::CommandHandler;
+use crate::audit::AuditSource;
+use crate::client_handle::GameClientHandle;
+use super::commands::{apply_view_input, CommandHandler};
 
 /// 互動式 CLI 處理器
 pub struct InteractiveCli {
@@ -19,7 +21,21 @@ impl InteractiveCli {
     /// 創建新的互動式 CLI
     pub fn new() -> Self {
         let app_config = AppConfig::load();
-        let config = GameClientConfig::default();
+        let config = GameClientConfig {
+            server_ip: app_config.server.mqtt_host.clone(),
+            server_port: app_config.server.mqtt_port,
+            player_name: app_config.frontend.player_name.clone(),
+            hero_type: app_config.frontend.hero_type.clone(),
+            wire_format: app_config.server.wire_format,
+            username: app_config.server.username.clone(),
+            password: app_config.server.password.clone(),
+            tls: app_config.server.tls.clone(),
+            qos: app_config.server.qos,
+            topics: app_config.server.topics.clone(),
+            failover: app_config.server.failover.clone(),
+            binary_decoders: app_config.server.binary_decoders.clone(),
+            ..GameClientConfig::default()
+        };
         
         Self {
             command_handler: CommandHandler::new(config, app_config),
@@ -46,7 +62,8 @@ impl InteractiveCli {
         
         // 自動嘗試連接到本地端
         println!("🔗 自動連接到本地端...");
-        match self.command_handler.auto_connect_localhost().await {
+        let tag = self.command_handler.active_session.clone();
+        match crate::session_tag::scope(tag, self.command_handler.auto_connect_localhost()).await {
             Ok(_) => {
                 println!("✅ 已連接到 127.0.0.1:1883");
             },
@@ -65,8 +82,15 @@ impl InteractiveCli {
             if parts.is_empty() {
                 continue;
             }
+            self.command_handler.supervise_backend().await;
             
-            match self.handle_command(&parts).await {
+            // 命令執行期間的日誌帶上使用中的會話標籤
+            let tag = self.command_handler.active_session.clone();
+            let result = crate::session_tag::scope(tag.clone(), self.handle_command(&parts)).await;
+            if let Err(e) = self.command_handler.audit.record(AuditSource::Interactive, Some(&tag), &parts, &result) {
+                warn!("寫入稽核記錄失敗: {}", e);
+            }
+            match result {
                 Ok(_) => {},
                 Err(e) => {
                     println!("{} {}", "錯誤:".red(), e);
@@ -92,16 +116,36 @@ impl InteractiveCli {
                 ClientState::Connected => "[已連接]".green(),
                 ClientState::InGame => "[遊戲中]".bright_green(),
                 ClientState::Connecting => "[連接中]".yellow(),
+                ClientState::Reconnecting(attempt) => format!("[重連中 #{}]", attempt).yellow(),
+                ClientState::Stale(age) => format!("[資料停滯 {}s]", age).yellow(),
+                ClientState::ServerLost(age) => format!("[失去伺服器 {}s]", age).bright_red(),
                 ClientState::Disconnected => "[未連接]".red(),
                 ClientState::Error(_) => "[錯誤]".bright_red(),
             },
             None => "[未連接]".red(),
         };
+        // 改用備援 broker 時在狀態後標示目前的 broker
+        let broker = match &self.command_handler.game_client {
+            Some(client) if client.on_failover() => format!("[{}]", client.active_broker()).yellow().to_string(),
+            _ => String::new(),
+        };
         
-        print!("{} {} ", status, ">".bright_white());
+        match self.session_label() {
+            Some(label) => print!("{} {}{} {} ", label, status, broker, ">".bright_white()),
+            None => print!("{}{} {} ", status, broker, ">".bright_white()),
+        }
         io::stdout().flush().unwrap();
     }
     
+    /// 提示符前的會話標籤（只有一個會話時不顯示）
+    fn session_label(&self) -> Option<String> {
+        if self.command_handler.parked_sessions.is_empty() {
+            None
+        } else {
+            Some(crate::session_tag::colored_label(&self.command_handler.active_session))
+        }
+    }
+    
     /// 讀取用戶輸入
     fn read_input(&self) -> Result<String> {
         let mut input = String::new();
@@ -116,19 +160,56 @@ impl InteractiveCli {
         match command {
             "help" | "?" => self.show_help(),
             "connect" => self.command_handler.handle_connect(parts).await?,
-            "disconnect" => self.command_handler.handle_disconnect().await?,
+            "disconnect" => self.command_handler.handle_disconnect(parts).await?,
             "config" => self.command_handler.handle_config(parts)?,
             "status" => self.command_handler.handle_status()?,
             "play" => self.command_handler.handle_play(parts).await?,
+            "switch-hero" => self.command_handler.handle_switch_hero(parts).await?,
+            "session" => self.command_handler.handle_session(parts).await?,
+            "selftest" => self.command_handler.handle_selftest(parts).await?,
             "move" => self.command_handler.handle_move(parts).await?,
             "cast" => self.command_handler.handle_cast(parts).await?,
             "attack" => self.command_handler.handle_attack(parts).await?,
+            "select" => self.command_handler.handle_select(parts).await?,
             "abilities" => self.command_handler.handle_abilities()?,
             "auto" => self.command_handler.handle_auto(parts).await?,
+            #[cfg(feature = "scripting")]
+            "script" => self.command_handler.handle_script(parts).await?,
+            "recall" => self.command_handler.handle_recall().await?,
+            "patrol" => self.command_handler.handle_patrol(parts).await?,
+            "shop-trip" => self.command_handler.handle_shop_trip().await?,
+            "shop" => self.command_handler.handle_shop().await?,
+            "buy" => self.command_handler.handle_buy(parts).await?,
+            "sell" => self.command_handler.handle_sell(parts).await?,
             "view" => self.handle_view(parts).await?,
             "viewport" => self.command_handler.handle_viewport(parts).await?,
             "zoom" => self.command_handler.handle_zoom(parts).await?,
+            "camera" => self.command_handler.handle_camera(parts)?,
+            "overlays" => self.command_handler.handle_overlays(parts)?,
             "backend" => self.command_handler.handle_backend(parts).await?,
+            "bookmark" => self.command_handler.handle_bookmark(parts)?,
+            "bookmarks" => self.command_handler.handle_bookmarks()?,
+            #[cfg(feature = "metrics")]
+            "metrics" => self.command_handler.handle_metrics(parts)?,
+            "query" => self.command_handler.handle_query(parts).await?,
+            "anomalies" => self.command_handler.handle_anomalies(parts)?,
+            "schema" => self.command_handler.handle_schema(parts).await?,
+            "stats" => self.command_handler.handle_stats()?,
+            "sniff" => self.command_handler.handle_sniff(parts).await?,
+            "area" => self.command_handler.handle_area(parts).await?,
+            "alerts" => self.command_handler.handle_alerts().await?,
+            "recap" => self.command_handler.handle_recap().await?,
+            "scoreboard" => self.command_handler.handle_scoreboard().await?,
+            "credits" => self.command_handler.handle_credits().await?,
+            "expectations" => self.command_handler.handle_expectations(parts).await?,
+            "casts" => self.command_handler.handle_casts(parts)?,
+            "combo" => self.command_handler.handle_combo(parts).await?,
+            "dummy" => self.command_handler.handle_dummy(parts).await?,
+            "map" => self.command_handler.handle_map(parts).await?,
+            "sandbox" => self.command_handler.handle_sandbox(parts)?,
+            "preset" => self.command_handler.handle_preset(parts).await?,
+            "audit" => self.command_handler.handle_audit(parts)?,
+            "version" => self.command_handler.handle_version()?,
             "clear" => self.clear_screen(),
             "exit" | "quit" => self.handle_exit().await?,
             _ => {
@@ -147,19 +228,58 @@ impl InteractiveCli {
         
         println!("  {} - 顯示此幫助訊息", "help, ?".green());
         println!("  {} <ip> [port] - 連接到服務器", "connect".green());
-        println!("  {} - 斷開連接", "disconnect".green());
-        println!("  {} [key] [value] - 查看或修改配置", "config".green());
+        println!("  {} [--abrupt] - 斷開連接（--abrupt 模擬異常斷線，由 broker 發佈遺囑）", "disconnect".green());
+        println!("  {} [list|new <name> [player] [hero]|use <name>|close <name>] - 管理多個會話", "session".green());
+        println!("  {} mqtt [ip] [port] - 測試 broker 回送延遲與 QoS", "selftest".green());
+        println!("  {} [key] [value] - 查看或修改配置 (server/port/name/hero/dry-run/read-only/spectate/lead)", "config".green());
+        println!("  {} <user> [pass] | off - 設置或清除 broker 認證", "config auth".green());
+        println!("  {} - 顯示合併各層後的有效配置與每個值的來源", "config effective".green());
         println!("  {} - 查看當前狀態", "status".green());
         println!("  {} [hero] - 開始遊戲", "play".green());
+        println!("  {} <hero> - 切換英雄（保留連接，重新進入遊戲）", "switch-hero".green());
         println!("  {} <x> <y> - 移動到指定位置", "move".green());
-        println!("  {} <ability> [x] [y] [level] - 施放技能", "cast".green());
-        println!("  {} <x> <y> - 攻擊指定位置", "attack".green());
+        println!("  {} <ability> [x] [y] [level] - 施放技能（未指定位置時施放在選擇的目標上）", "cast".green());
+        println!("  {} [x y] - 攻擊指定位置（未指定時攻擊選擇的目標）", "attack".green());
+        println!("  {} [實體ID|next|prev|none] - 選擇目標，不帶參數時顯示目前目標與視野內的單位", "select".green());
         println!("  {} - 列出可用技能", "abilities".green());
         println!("  {} [duration] - 自動遊戲模式", "auto".green());
+        #[cfg(feature = "scripting")]
+        println!("  {} load <file> | run [秒數] [間隔毫秒] - 載入 Rhai 機器人腳本並以腳本的 tick(state) 驅動英雄", "script".green());
+        println!("  {} - 引導回城到出生點", "recall".green());
+        println!("  {} <x,y> <x,y> ... [秒數] - 沿路徑點循環巡邏（預設 60 秒）", "patrol".green());
+        println!("  {} - 回城、購買配置的物品後返回線上", "shop-trip".green());
+        println!("  {} - 列出商店商品與道具欄", "shop".green());
+        println!("  {} <道具 ID> - 購買道具（扣除金錢並放入道具欄）", "buy".green());
+        println!("  {} <號位> - 賣出一格道具", "sell".green());
         println!("  {} [size] [--vision] [--live] - 顯示終端地圖視圖 (支援滑鼠操作)", "view".green());
         println!("  {} [width] [height] - 設置視窗大小", "viewport".green());
         println!("  {} <level> - 設置縮放等級 (0.5-3.0)", "zoom".green());
+        println!("  {} [follow|overview|<x> <y>] - 鏡頭跟隨英雄、地圖總覽或固定位置", "camera".green());
+        println!("  {} [<名稱|編號> [on|off]] - 列出或切換地圖疊加層（fog/grid/heatmap/trails/ranges/labels，視圖中 Alt+編號）", "overlays".green());
         println!("  {} <start|stop|restart|status> - 後端管理", "backend".green());
+        println!("  {} [note] - 在當前時刻添加書籤", "bookmark".green());
+        println!("  {} - 列出所有書籤", "bookmarks".green());
+        #[cfg(feature = "metrics")]
+        println!("  {} [add <name> <expr>|remove <name>] - 自訂指標", "metrics".green());
+        println!("  {} [clear] - 列出伺服器資料的協議異常", "anomalies".green());
+        println!("  {} [clear] - 依訊息類型列出缺少與多出的欄位", "schema".green());
+        println!("  {} - MQTT 收發統計：各主題的訊息速率、位元組數與解析失敗數", "stats".green());
+        println!("  {} [pattern] - 訂閱主題過濾器（支援 + 與 #，預設 #），在可捲動窗格中顯示解碼後的訊息", "sniff".green());
+        println!("  {} <min_x> <min_y> <max_x> <max_y> - 請求固定範圍的畫面並等待對應的回應", "area".green());
+        println!("  {} - 列出警報規則與觸發狀態", "alerts".green());
+        println!("  {} - 查看最近一次陣亡的死亡回顧", "recap".green());
+        println!("  {} - 各玩家的擊殺/死亡/助攻與重生倒數", "scoreboard".green());
+        println!("  {} - 查看擊殺/助攻歸屬與傷害貢獻的比對結果", "credits".green());
+        println!("  {} [clear] - 依操作類型比對模擬器預測與伺服器結果", "expectations".green());
+        println!("  {} [clear] - 各技能的施放嘗試、確認、拒絕原因、逾時與平均回覆延遲", "casts".green());
+        println!("  {} <技能>[@延遲ms] ... [--at x y] [--timeout ms] - 依間隔施放技能連段並量測每步的確認與效果延遲", "combo".green());
+        println!("  {} spawn [n] [hp] | report | clear - 在英雄周圍生成訓練假人並統計 DPS", "dummy".green());
+        println!("  {} [on|off|status] - 練習沙盒：使用技能或道具後自動重置冷卻、補滿生命值與道具，並循環生成訓練假人", "sandbox".green());
+        println!("  {} [show|new|paint|save|load|push] - 繪製牆、水、樹的測試地形並匯出或推送到後端", "map".green());
+        println!("  {} [list|save|load|show|delete] [name] - 保存或切換按鍵、出裝、施法模式與鏡頭預設", "preset".green());
+        println!("  {} show [n] - 查看最近的命令稽核記錄", "audit".green());
+        println!("  {} - 顯示版本、協議版本與後端回報的版本", "version".green());
+        println!("  {} <expr> - 以運算式查詢遊戲狀態 (例如 entities[type==\"summon\"].count())", "query".green());
         println!("  {} - 清除畫面", "clear".green());
         println!("  {} - 退出程式", "exit, quit".green());
         
@@ -168,6 +288,12 @@ impl InteractiveCli {
         println!("  右鍵點擊 - 攻擊目標位置");
         println!("  Shift+左鍵 - 移動攻擊");
         println!("  Ctrl+左鍵 - 強制攻擊");
+        println!("  B 鍵 - 在當前時刻添加書籤");
+        println!("  M 鍵 - 切換統計浮層 (自訂指標火花線)");
+        println!("  D 鍵 - 切換死亡回顧浮層 (陣亡時自動顯示)");
+        println!("  N 鍵 - 切換網路統計浮層 (各主題訊息速率、位元組數與解析失敗數)");
+        println!("  Enter - 開啟聊天輸入框 (支援中文輸入，Enter 送出，Esc 取消)");
+        println!("  F2-F10 - 執行 [input.macros] 綁定的巨集命令");
         
         println!("\n{}", "鍵盤技能控制 (在實時視圖中):".bright_cyan().bold());
         println!("  {} - 選擇技能後左鍵點擊施放", "W/E/R/T".yellow());
@@ -198,10 +324,11 @@ impl InteractiveCli {
         }
         
         // 解析參數
+        let camera = &self.command_handler.app_config.camera;
         let mut size = 20.0;  // 默認正方形大小
         let mut width: Option<f32> = None;
         let mut height: Option<f32> = None;
-        let mut show_vision = false;
+        let mut show_vision = camera.show_vision;
         let mut live_mode = false;
         
         // 解析命令行參數
@@ -233,6 +360,12 @@ impl InteractiveCli {
             i += 1;
         }
         
+        // 未指定大小時使用鏡頭設定
+        if width.is_none() && !parts[1..].iter().any(|arg| arg.parse::<f32>().is_ok()) {
+            width = Some(camera.view_width);
+            height = Some(camera.view_height);
+        }
+        
         // 創建終端視圖
         let view_result = if let (Some(w), Some(h)) = (width, height) {
             TerminalView::new_rect(w, h, show_vision)
@@ -242,11 +375,20 @@ impl InteractiveCli {
         
         match view_result {
             Ok(mut view) => {
-                if live_mode {
-                    self.run_live_view(&mut view, size, width, height, show_vision).await?;
-                } else {
-                    self.run_static_view(&mut view, size, width, height).await?;
+                if let Some(client) = &self.command_handler.game_client {
+                    view.viewport.transform = client.coordinates();
+                    view.watch_protocol_errors(client.subscribe_protocol_errors());
                 }
+                view.input_handler.configure(&self.command_handler.app_config.input);
+                // 視圖中以 Alt+數字鍵切換的疊加層，結束後交還給 overlays 命令
+                view.install_overlays(std::mem::take(&mut self.command_handler.overlays));
+                let result = if live_mode {
+                    self.run_live_view(&mut view, size, width, height, show_vision).await
+                } else {
+                    self.run_static_view(&mut view, size, width, height).await
+                };
+                self.command_handler.overlays = view.take_overlays();
+                result?;
             }
             Err(e) => {
                 println!("{} 創建終端視圖失敗: {}", "❌".red(), e);
@@ -281,27 +423,34 @@ impl InteractiveCli {
             return Ok(());
         }
         
+        // 客戶端移入專屬任務，視圖循環與輸入動作透過控制代碼同時操作，
+        // 輸入動作在背景執行，渲染不必等待動作完成
+        let Some(client) = self.command_handler.game_client.take() else {
+            let _ = view.cleanup_terminal();
+            return Ok(());
+        };
+        let (handle, client_task) = GameClientHandle::spawn(client);
+        
         // 實時循環
         let mut loop_counter = 0u64;
         let timeout_cycles = 300; // 30秒後自動退出 (300 * 100ms)
         
         loop {
-            // 同步共享遊戲狀態
-            if let Some(client) = self.command_handler.game_client.as_mut() {
-                if let Err(e) = client.sync_shared_state().await {
+            // 同步共享遊戲狀態並更新技能冷卻時間（100ms = 0.1s）
+            let snapshot = match handle.frame(0.1).await {
+                Ok(snapshot) => snapshot,
+                Err(e) => {
                     println!("{} 同步遊戲狀態失敗: {}", "❌".red(), e);
+                    break;
                 }
-                
-                // 更新技能冷卻時間
-                client.get_game_state_mut().update_cooldowns(0.1); // 100ms = 0.1s
-            }
+            };
             
             // 渲染視圖
-            let render_result = if let Some(client) = self.command_handler.game_client.as_ref() {
-                view.render_live(client.get_game_state())
-            } else {
-                break; // 沒有客戶端連接，退出循環
-            };
+            view.update_from_snapshot(&snapshot);
+            if let Some(evidence) = &snapshot.anomaly {
+                view.capture_anomaly(evidence, &snapshot.game_state);
+            }
+            let render_result = view.render_live(&snapshot.game_state);
             
             match render_result {
                 Ok(UserInput::Continue) => {
@@ -310,6 +459,10 @@ impl InteractiveCli {
                     if loop_counter % 100 == 0 {
                         eprintln!("按 q, Esc 或 Ctrl+C 退出視圖... ({}/{})", loop_counter / 10, timeout_cycles / 10);
                     }
+                    // 每秒檢查受管理的後端，意外退出時重啟並恢復會話（外部重啟由客戶端的存活偵測恢復）
+                    if loop_counter.is_multiple_of(10) {
+                        self.supervise_live_backend(&handle).await;
+                    }
                     // 30秒後自動退出（作為workaround）
                     if loop_counter >= timeout_cycles {
                         println!("\n{} 視圖超時，自動退出", "⏰".bright_yellow());
@@ -320,9 +473,12 @@ impl InteractiveCli {
                 Ok(UserInput::Quit) => break, // 用戶按了退出鍵
                 Ok(input) => {
                     // 處理用戶輸入動作
-                    if let Err(e) = self.command_handler.handle_view_input(input).await {
-                        println!("{} 處理輸入失敗: {}", "❌".red(), e);
-                    }
+                    let handle = handle.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = apply_view_input(&handle, input).await {
+                            println!("{} 處理輸入失敗: {}", "❌".red(), e);
+                        }
+                    });
                     tokio::time::sleep(std::time::Duration::from_millis(100)).await;
                 }
                 Err(e) => {
@@ -331,12 +487,34 @@ impl InteractiveCli {
                 }
             }
         }
+        handle.shutdown().await;
+        match client_task.await {
+            Ok(client) => self.command_handler.game_client = Some(client),
+            Err(e) => println!("{} 客戶端任務異常結束: {}", "❌".red(), e),
+        }
         let _ = view.cleanup_terminal();
         println!("{} 退出實時視圖模式", "✓".green());
         
         Ok(())
     }
     
+    /// 實時視圖中受管理的後端意外退出時重啟，並透過控制代碼恢復會話
+    async fn supervise_live_backend(&self, handle: &GameClientHandle) {
+        let Some(backend_manager) = &self.command_handler.backend_manager else {
+            return;
+        };
+        match backend_manager.restart_if_crashed().await {
+            Ok(true) => {
+                let restored = handle.with(|client| Box::pin(client.restore_session())).await;
+                if let Err(e) = restored.and_then(|result| result) {
+                    warn!("後端重啟後恢復會話失敗: {}", e);
+                }
+            }
+            Ok(false) => {}
+            Err(e) => warn!("自動重啟後端失敗: {}", e),
+        }
+    }
+    
     /// 運行靜態視圖模式
     async fn run_static_view(
         &mut self,
@@ -389,9 +567,41 @@ impl InteractiveCli {
             println!("{} 斷開連接...", "→".yellow());
             client.disconnect().await?;
         }
-        
+
+        // 斷開其他會話
+        for (name, session) in std::mem::take(&mut self.command_handler.parked_sessions) {
+            if let Some(mut client) = session.game_client {
+                println!("{} 斷開會話 {}...", "→".yellow(), name);
+                if let Err(e) = client.disconnect().await {
+                    warn!("斷開會話 {} 時發生錯誤: {}", name, e);
+                }
+            }
+        }
+
         println!("{} 再見！", "👋".bright_white());
         self.running = false;
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn prompt_label_shows_only_with_parked_sessions() {
+        let mut cli = InteractiveCli {
+            command_handler: CommandHandler::new(GameClientConfig::default(), AppConfig::default()),
+            running: true,
+        };
+        assert_eq!(cli.session_label(), None);
+        
+        cli.command_handler.handle_session(&["session", "new", "bot1"]).await.unwrap();
+        assert_eq!(cli.session_label(), Some(crate::session_tag::colored_label("bot1")));
+        
+        cli.command_handler.handle_session(&["session", "use", "main"]).await.unwrap();
+        assert_eq!(cli.session_label(), Some(crate::session_tag::colored_label("main")));
+        
+        cli.command_handler.handle_session(&["session", "close", "bot1"]).await.unwrap();
+        assert_eq!(cli.session_label(), None);
+    }
+}
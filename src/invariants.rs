@@ -0,0 +1,143 @@
+/// 遊戲狀態不變量檢查
+///
+/// 除錯建置中每次套用伺服器訊息或本地操作後檢查 GameState：實體與召喚物 ID 不重複、
+/// 生命值介於 0 與上限之間、冷卻時間不為負、視口大小合理。新出現的違規連同造成它的更新一起記錄，
+/// 讓狀態損壞在發生的那一刻就被發現，而不是在之後的畫面或驗證中才顯現
+use std::collections::HashSet;
+use log::warn;
+
+use crate::game_state::GameState;
+
+/// 目前存在的違規（只在違規第一次出現時記錄）
+#[derive(Debug, Clone, Default)]
+pub struct InvariantTracker {
+    active: HashSet<String>,
+}
+
+impl InvariantTracker {
+    /// 更新目前的違規，返回這次新出現的違規
+    pub fn observe(&mut self, violations: Vec<String>) -> Vec<String> {
+        let fresh = violations.iter().filter(|v| !self.active.contains(*v)).cloned().collect();
+        self.active = violations.into_iter().collect();
+        fresh
+    }
+}
+
+/// 檢查狀態並返回所有違規
+pub fn check(state: &GameState) -> Vec<String> {
+    let mut violations = Vec::new();
+
+    for (id, entity) in &state.entities {
+        if entity.id != *id {
+            violations.push(format!("實體鍵 {} 對應到 ID 為 {} 的實體", id, entity.id));
+        }
+        check_health(&mut violations, &format!("實體 {}", id), entity.health);
+    }
+    let mut summon_ids = HashSet::new();
+    for summon in &state.local_player.summons {
+        if !summon_ids.insert(summon.id) {
+            violations.push(format!("召喚物 ID {} 重複", summon.id));
+        }
+        if state.entities.get(&summon.id).is_some_and(|entity| !matches!(entity.entity_type, crate::game_state::EntityType::Summon(_))) {
+            violations.push(format!("召喚物 ID {} 與其他類型的實體重複", summon.id));
+        }
+        check_health(&mut violations, &format!("召喚物 {}", summon.id), summon.health);
+    }
+
+    let player = &state.local_player;
+    check_health(&mut violations, &format!("玩家 {}", player.name), player.health);
+    for (name, other) in &state.other_players {
+        if other.name != *name {
+            violations.push(format!("玩家鍵 {} 對應到名稱為 {} 的玩家", name, other.name));
+        }
+        check_health(&mut violations, &format!("玩家 {}", name), other.health);
+    }
+
+    for ability in &player.abilities {
+        if is_negative(ability.cooldown_remaining) {
+            violations.push(format!("技能 {} 冷卻時間 {} 為負", ability.ability_id, ability.cooldown_remaining));
+        }
+    }
+    for item in &player.items {
+        if is_negative(item.cooldown_remaining) {
+            violations.push(format!("道具 {} 冷卻時間 {} 為負", item.item_id, item.cooldown_remaining));
+        }
+    }
+
+    let viewport = &state.viewport;
+    if !is_positive(viewport.width) || !is_positive(viewport.height) {
+        violations.push(format!("視口大小 {}x{} 無效", viewport.width, viewport.height));
+    }
+    if !is_positive(viewport.zoom) {
+        violations.push(format!("視口縮放 {} 無效", viewport.zoom));
+    }
+    if !(viewport.center.x.is_finite() && viewport.center.y.is_finite()) {
+        violations.push(format!("視口中心 ({}, {}) 無效", viewport.center.x, viewport.center.y));
+    }
+
+    violations
+}
+
+/// 生命值需介於 0 與上限之間（NaN 也視為違規）
+fn check_health(violations: &mut Vec<String>, subject: &str, (current, max): (f32, f32)) {
+    if !(0.0..=max).contains(&current) {
+        violations.push(format!("{} 生命值 {}/{} 超出範圍", subject, current, max));
+    }
+}
+
+/// 負數或 NaN
+fn is_negative(value: f32) -> bool {
+    value.is_nan() || value < 0.0
+}
+
+/// 有限的正數
+fn is_positive(value: f32) -> bool {
+    value.is_finite() && value > 0.0
+}
+
+/// 除錯建置中檢查狀態，記錄這次更新造成的新違規（發行建置不檢查）
+pub fn verify(state: &mut GameState, update: impl FnOnce() -> String) {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    let violations = check(state);
+    let fresh = state.invariants.observe(violations);
+    if !fresh.is_empty() {
+        let update = update();
+        for violation in fresh {
+            warn!("🧩 狀態不變量被破壞: {}（更新: {}）", violation, update);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::{Entity, EntityType};
+    use vek::Vec2;
+
+    #[test]
+    fn reports_only_newly_introduced_violations() {
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        assert!(check(&state).is_empty(), "{:?}", check(&state));
+
+        state.entities.insert(1, Entity { id: 2, entity_type: EntityType::Creep, position: Vec2::zero(), health: (120.0, 100.0), owner: None, debug_label: None });
+        state.local_player.abilities[0].cooldown_remaining = -1.0;
+        state.viewport.width = 0.0;
+        let violations = check(&state);
+        assert_eq!(violations.len(), 4, "{:?}", violations);
+
+        let mut tracker = InvariantTracker::default();
+        assert_eq!(tracker.observe(violations.clone()).len(), 4);
+        assert!(tracker.observe(violations).is_empty());
+
+        state.local_player.health = (f32::NAN, 100.0);
+        let fresh = tracker.observe(check(&state));
+        assert_eq!(fresh, vec!["玩家 p1 生命值 NaN/100 超出範圍".to_string()]);
+
+        state.viewport.width = 1920.0;
+        assert!(tracker.observe(check(&state)).is_empty());
+        state.viewport.width = 0.0;
+        assert_eq!(tracker.observe(check(&state)).len(), 1, "修復後再次出現的違規要重新記錄");
+    }
+}
@@ -0,0 +1,164 @@
+/// 實時視圖的巨集按鍵
+///
+/// `[input.macros]` 把 F2–F10 綁定到一串命令，在實時視圖中按下時依序執行，常用的測試操作（生成一波小兵、
+/// 送出破壞狀態的作弊操作、匯出狀態快照）一鍵就能完成。命令在載入配置時解析，格式錯誤會在配置驗證中列出；
+/// 執行中途有命令失敗時停止後續命令，`wait` 期間畫面暫停更新
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::expr::Expr;
+
+/// 可綁定巨集的功能鍵
+pub const MACRO_KEYS: RangeInclusive<u8> = 2..=10;
+
+/// 巨集中的一個命令
+#[derive(Debug, Clone, PartialEq)]
+pub enum MacroCommand {
+    /// `move <x> <y>`
+    Move(f32, f32),
+    /// `attack <x> <y>`
+    Attack(f32, f32),
+    /// `cast <技能> [x y]`（未指定位置時以英雄位置為目標）
+    Cast { ability: String, target: Option<(f32, f32)> },
+    /// `action <操作> [JSON 參數]`：直接送出玩家操作（包含 spawn_dummy 等作弊操作）
+    Action { action: String, params: serde_json::Value },
+    /// `wait <ms>`
+    Wait(u64),
+    /// `bookmark [備註]`
+    Bookmark(Option<String>),
+    /// `chat <訊息>`
+    Chat(String),
+    /// `query <運算式>`：結果寫入日誌
+    Query(String),
+    /// `snapshot [路徑]`：把狀態快照（查詢上下文）寫成 JSON
+    Snapshot(Option<PathBuf>),
+}
+
+impl MacroCommand {
+    /// 解析一行命令
+    pub fn parse(line: &str) -> Result<Self> {
+        let line = line.trim();
+        let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let text = |what: &str| if rest.is_empty() { Err(anyhow!("{} 缺少{}", name, what)) } else { Ok(rest.to_string()) };
+        let optional = || (!rest.is_empty()).then(|| rest.to_string());
+        Ok(match name {
+            "move" => {
+                let (x, y) = point(rest)?.ok_or_else(|| anyhow!("move 需要 <x> <y>"))?;
+                MacroCommand::Move(x, y)
+            }
+            "attack" => {
+                let (x, y) = point(rest)?.ok_or_else(|| anyhow!("attack 需要 <x> <y>"))?;
+                MacroCommand::Attack(x, y)
+            }
+            "cast" => {
+                let (ability, target) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                if ability.is_empty() {
+                    bail!("cast 缺少技能 ID");
+                }
+                MacroCommand::Cast { ability: ability.to_string(), target: point(target)? }
+            }
+            "action" => {
+                let (action, params) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                if action.is_empty() {
+                    bail!("action 缺少操作名稱");
+                }
+                let params = match params.trim() {
+                    "" => serde_json::json!({}),
+                    json => serde_json::from_str(json).with_context(|| format!("action {} 的參數不是合法的 JSON", action))?,
+                };
+                MacroCommand::Action { action: action.to_string(), params }
+            }
+            "wait" => MacroCommand::Wait(rest.parse().map_err(|_| anyhow!("wait 需要毫秒數: {}", rest))?),
+            "bookmark" => MacroCommand::Bookmark(optional()),
+            "chat" => MacroCommand::Chat(text("訊息")?),
+            "query" => {
+                let expr = text("運算式")?;
+                Expr::parse(&expr).with_context(|| format!("無法解析運算式: {}", expr))?;
+                MacroCommand::Query(expr)
+            }
+            "snapshot" => MacroCommand::Snapshot(optional().map(PathBuf::from)),
+            _ => bail!("未知的巨集命令: {}", name),
+        })
+    }
+}
+
+/// 解析 `<x> <y>`（空白時為 None）
+fn point(text: &str) -> Result<Option<(f32, f32)>> {
+    let numbers: Vec<&str> = text.split_whitespace().collect();
+    match numbers.as_slice() {
+        [] => Ok(None),
+        [x, y] => Ok(Some((
+            x.parse().map_err(|_| anyhow!("座標不是數字: {}", x))?,
+            y.parse().map_err(|_| anyhow!("座標不是數字: {}", y))?,
+        ))),
+        _ => bail!("座標應為 <x> <y>: {}", text),
+    }
+}
+
+/// 解析功能鍵名稱（F2–F10，不分大小寫）
+pub fn parse_key(name: &str) -> Result<u8> {
+    name.strip_prefix(['F', 'f'])
+        .and_then(|number| number.parse().ok())
+        .filter(|number| MACRO_KEYS.contains(number))
+        .ok_or_else(|| anyhow!("巨集按鍵應為 F{} 到 F{}: {}", MACRO_KEYS.start(), MACRO_KEYS.end(), name))
+}
+
+/// 依功能鍵編號解析好的巨集
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyMacros {
+    macros: BTreeMap<u8, Vec<MacroCommand>>,
+}
+
+impl KeyMacros {
+    /// 解析 `[input.macros]`（按鍵名稱對應命令列表）
+    pub fn parse(config: &BTreeMap<String, Vec<String>>) -> Result<Self> {
+        let mut macros = BTreeMap::new();
+        for (name, lines) in config {
+            let key = parse_key(name)?;
+            if lines.is_empty() {
+                bail!("{} 沒有任何命令", name);
+            }
+            let commands = lines.iter()
+                .map(|line| MacroCommand::parse(line).with_context(|| format!("{} 的命令有誤: {}", name, line)))
+                .collect::<Result<Vec<_>>>()?;
+            macros.insert(key, commands);
+        }
+        Ok(Self { macros })
+    }
+
+    /// 功能鍵綁定的命令
+    pub fn get(&self, key: u8) -> Option<&[MacroCommand]> {
+        self.macros.get(&key).map(Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_function_key_command_sequences() {
+        let config: BTreeMap<String, Vec<String>> = toml::from_str(r#"
+            F2 = ["action spawn_wave {\"lane\": \"mid\", \"count\": 6}", "wait 500", "bookmark 兵線"]
+            f10 = ["cast sniper_mode", "cast rain_iron_cannon 120 80", "snapshot"]
+        "#).unwrap();
+        let macros = KeyMacros::parse(&config).unwrap();
+        assert_eq!(macros.get(2).unwrap(), &[
+            MacroCommand::Action { action: "spawn_wave".to_string(), params: serde_json::json!({ "lane": "mid", "count": 6 }) },
+            MacroCommand::Wait(500),
+            MacroCommand::Bookmark(Some("兵線".to_string())),
+        ]);
+        assert_eq!(macros.get(10).unwrap()[1], MacroCommand::Cast { ability: "rain_iron_cannon".to_string(), target: Some((120.0, 80.0)) });
+        assert_eq!(macros.get(10).unwrap()[2], MacroCommand::Snapshot(None));
+        assert!(macros.get(3).is_none());
+
+        assert!(parse_key("F1").is_err());
+        assert!(MacroCommand::parse("move 1").is_err());
+        assert!(MacroCommand::parse("action corrupt {bad").is_err());
+        assert!(MacroCommand::parse("query player.hp >").is_err());
+        assert!(MacroCommand::parse("teleport 1 2").is_err());
+    }
+}
@@ -0,0 +1,83 @@
+/// 往返延遲量測
+///
+/// 以畫面狀態請求（get_area）送出到收到畫面回應的時間估算與後端之間的往返延遲，
+/// 以指數移動平均平滑，供預判瞄準與查詢上下文（`rtt_ms`）使用
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 平滑係數（新樣本的權重）
+const SMOOTHING: f64 = 0.2;
+
+/// 超過此時間仍未收到回應的請求視為遺失
+const PENDING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 往返延遲追蹤器
+#[derive(Debug, Default)]
+pub struct RttTracker {
+    /// 尚未收到回應的請求送出時間
+    pending: Option<Instant>,
+    /// 平滑後的延遲
+    smoothed: Option<Duration>,
+}
+
+/// 在背景任務間共享的延遲追蹤器
+pub type SharedRtt = Arc<Mutex<RttTracker>>;
+
+impl RttTracker {
+    /// 記錄請求送出
+    pub fn mark_sent(&mut self, now: Instant) {
+        // 上一個請求還在等待時保留較早的時間，避免回應延遲被低估為重發間隔
+        if self.pending.is_none_or(|sent| now.duration_since(sent) > PENDING_TIMEOUT) {
+            self.pending = Some(now);
+        }
+    }
+
+    /// 記錄收到回應
+    pub fn observe_response(&mut self, now: Instant) {
+        let Some(sent) = self.pending.take() else {
+            return;
+        };
+        let sample = now.duration_since(sent);
+        self.smoothed = Some(match self.smoothed {
+            Some(previous) => previous.mul_f64(1.0 - SMOOTHING) + sample.mul_f64(SMOOTHING),
+            None => sample,
+        });
+    }
+
+    /// 平滑後的往返延遲
+    pub fn smoothed(&self) -> Option<Duration> {
+        self.smoothed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smooths_round_trips_and_keeps_the_earliest_pending_request() {
+        let start = Instant::now();
+        let mut rtt = RttTracker::default();
+        rtt.observe_response(start);
+        assert_eq!(rtt.smoothed(), None, "沒有等待中的請求時忽略回應");
+
+        rtt.mark_sent(start);
+        rtt.observe_response(start + Duration::from_millis(100));
+        assert_eq!(rtt.smoothed(), Some(Duration::from_millis(100)), "第一個樣本直接採用");
+
+        // 重發時保留較早的送出時間
+        rtt.mark_sent(start + Duration::from_millis(1000));
+        rtt.mark_sent(start + Duration::from_millis(1100));
+        rtt.observe_response(start + Duration::from_millis(1200));
+        assert_eq!(rtt.smoothed(), Some(Duration::from_millis(120)), "100 × 0.8 + 200 × 0.2");
+        rtt.observe_response(start + Duration::from_millis(1300));
+        assert_eq!(rtt.smoothed(), Some(Duration::from_millis(120)), "回應只配對一次");
+
+        // 等待超過 PENDING_TIMEOUT 的請求視為遺失，改以新的送出時間計算
+        let lost = start + Duration::from_secs(2);
+        rtt.mark_sent(lost);
+        rtt.mark_sent(lost + PENDING_TIMEOUT + Duration::from_millis(1));
+        rtt.observe_response(lost + PENDING_TIMEOUT + Duration::from_millis(121));
+        assert_eq!(rtt.smoothed(), Some(Duration::from_millis(120)));
+    }
+}
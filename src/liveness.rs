@@ -0,0 +1,50 @@
+/// 伺服器資料存活偵測
+///
+/// 後端停止發佈時客戶端仍會持續渲染最後收到的狀態，看起來一切正常。依最後一次收到伺服器資料的時間判斷，
+/// 超過 `stale_secs` 視為資料停滯、超過 `lost_secs` 視為失去伺服器，客戶端狀態與終端視圖的提示隨之切換
+use std::time::{Duration, SystemTime};
+
+use crate::config::LivenessConfig;
+
+/// 伺服器資料的存活狀態
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Liveness {
+    Alive,
+    /// 資料停滯（距離上次收到資料的時間）
+    Stale(Duration),
+    /// 失去伺服器（距離上次收到資料的時間）
+    Lost(Duration),
+}
+
+/// 依最後一次收到伺服器資料的時間判斷存活狀態
+pub fn check(config: &LivenessConfig, last_update: SystemTime, now: SystemTime) -> Liveness {
+    let age = now.duration_since(last_update).unwrap_or_default();
+    if age >= Duration::from_secs(config.lost_secs) {
+        Liveness::Lost(age)
+    } else if age >= Duration::from_secs(config.stale_secs) {
+        Liveness::Stale(age)
+    } else {
+        Liveness::Alive
+    }
+}
+
+/// 終端視圖頂部的提示
+pub fn banner(age_secs: u64) -> String {
+    format!(" ⚠ NO SERVER DATA ({}s) ", age_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ages_from_alive_to_stale_to_lost() {
+        let config = LivenessConfig { stale_secs: 5, lost_secs: 30 };
+        let last = SystemTime::now();
+        assert_eq!(check(&config, last, last + Duration::from_secs(4)), Liveness::Alive);
+        assert_eq!(check(&config, last, last + Duration::from_secs(12)), Liveness::Stale(Duration::from_secs(12)));
+        assert_eq!(check(&config, last, last + Duration::from_secs(30)), Liveness::Lost(Duration::from_secs(30)));
+        assert_eq!(check(&config, last + Duration::from_secs(1), last), Liveness::Alive, "時鐘倒退時不算停滯");
+        assert_eq!(banner(12), " ⚠ NO SERVER DATA (12s) ");
+    }
+}
@@ -14,6 +14,79 @@ mod terminal_view;
 mod config;
 mod backend_manager;
 mod terminal_logger;
+mod bookmarks;
+mod recording;
+#[cfg(feature = "replay")]
+mod replay_compare;
+#[cfg(feature = "replay")]
+mod replay;
+#[cfg(feature = "replay")]
+mod replay_broker;
+#[cfg(feature = "self-update")]
+mod self_update;
+mod expr;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod sanity;
+mod hero_catalog;
+mod session_tag;
+mod selftest;
+mod alerts;
+mod audit;
+mod coords;
+mod latency;
+mod targeting;
+mod death_recap;
+mod credit;
+mod presets;
+mod reconnect;
+mod config_layers;
+mod heartbeat;
+mod cast_stats;
+mod behavior;
+mod patrol;
+mod prediction;
+mod progression;
+mod buffs;
+mod shop;
+mod selection;
+mod scoreboard;
+mod terrain;
+mod world_map;
+mod tls;
+mod version;
+mod demo;
+mod expectations;
+mod client_handle;
+mod camera;
+mod dummy;
+mod combo;
+mod humanize;
+mod topics;
+mod protocol_errors;
+mod strict;
+mod schema;
+mod invariants;
+mod rate_limit;
+mod map_editor;
+mod mqtt_stats;
+mod anomaly_capture;
+mod sniffer;
+mod sandbox;
+mod rpc;
+mod liveness;
+mod shutdown;
+mod time_sync;
+mod payload;
+mod perf;
+mod key_macros;
+mod swarm;
+mod script;
+mod testkit;
+mod state_hash;
+mod identities;
+#[cfg(feature = "scripting")]
+mod bot_script;
 
 use cli::{Cli, CliHandler};
 use interactive::InteractiveCli;
@@ -22,11 +95,29 @@ use interactive::InteractiveCli;
 async fn main() {
     // 解析命令行參數
     let args: Vec<String> = std::env::args().collect();
-    
+    let cli = (args.len() > 1).then(Cli::parse);
+    if let Some(cli) = &cli {
+        config_layers::set_cli_overrides(cli.overrides());
+    }
+
+    // 配置有誤時立即停止，而不是在執行中才出現難以追查的錯誤
+    if let Err(e) = config_layers::ResolvedConfig::resolve_current() {
+        eprintln!("❌ {:#}", e);
+        std::process::exit(1);
+    }
+
+    // 載入英雄定義（heroes/*.toml，目錄不存在時使用內建定義）
+    if let Err(e) = hero_catalog::init(std::path::Path::new(hero_catalog::HERO_DATA_DIR)) {
+        eprintln!("❌ {:#}", e);
+        std::process::exit(1);
+    }
+
     // 如果沒有參數，啟動互動式模式
-    if args.len() == 1 {
-        // 初始化日誌
-        env_logger::init();
+    let Some(cli) = cli else {
+        // 初始化日誌（多會話時每行帶上會話標籤）
+        env_logger::Builder::from_default_env()
+            .format(session_tag::format_record)
+            .init();
         
         // 啟動互動式 CLI
         let mut interactive = InteractiveCli::new();
@@ -34,20 +125,23 @@ async fn main() {
             error!("互動式 CLI 錯誤: {}", e);
             std::process::exit(1);
         }
-    } else {
-        // 使用原本的命令行模式
-        let cli = Cli::parse();
-        
-        // 創建 CLI 處理器
-        let mut handler = CliHandler::new();
-        
-        // 處理命令
-        match handler.handle_command(cli).await {
-            Ok(_) => {},
-            Err(e) => {
-                error!("命令執行失敗: {}", e);
-                std::process::exit(1);
-            }
+        return;
+    };
+    
+    // 使用原本的命令行模式
+    let mut handler = CliHandler::new();
+    
+    // 處理命令並寫入稽核記錄
+    let result = handler.handle_command(cli).await;
+    let parts: Vec<&str> = args.iter().skip(1).map(String::as_str).collect();
+    if let Err(e) = audit::AuditLog::default().record(audit::AuditSource::Cli, None, &parts, &result) {
+        error!("寫入稽核記錄失敗: {}", e);
+    }
+    match result {
+        Ok(_) => {},
+        Err(e) => {
+            error!("命令執行失敗: {}", e);
+            std::process::exit(1);
         }
     }
 }
@@ -0,0 +1,238 @@
+/// 測試地形編輯器
+///
+/// 在本地格子上繪製牆、水與樹，匯出成後端地形格式（與 screen_response 的 `terrain` 相同：
+/// 每格一筆 `{"position", "terrain_type", "properties"}`），或以作弊操作 load_terrain 直接推送給後端，
+/// 方便在 omobaf 內建立尋路測試用的自訂障礙配置
+use std::path::Path;
+use anyhow::{Context, Result};
+use colored::*;
+use serde::{Deserialize, Serialize};
+
+use crate::mqtt_handler::TerrainData;
+
+/// 預設格子數（配合 800x600 的地圖）
+pub const DEFAULT_WIDTH: usize = 80;
+pub const DEFAULT_HEIGHT: usize = 60;
+/// 預設每格的世界單位
+pub const DEFAULT_CELL_SIZE: f32 = 10.0;
+/// 每邊最多的格子數
+pub const MAX_CELLS: usize = 400;
+
+/// 地形格
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tile {
+    #[default]
+    Empty,
+    Wall,
+    Water,
+    Tree,
+}
+
+impl Tile {
+    /// 可繪製的地形
+    pub const ALL: [Tile; 4] = [Tile::Empty, Tile::Wall, Tile::Water, Tile::Tree];
+
+    /// 後端的地形類型名稱
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tile::Empty => "empty",
+            Tile::Wall => "wall",
+            Tile::Water => "water",
+            Tile::Tree => "tree",
+        }
+    }
+
+    /// 依名稱解析
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|tile| tile.name() == name)
+    }
+
+    /// 編輯器中顯示的字元
+    pub fn symbol(&self) -> char {
+        match self {
+            Tile::Empty => '.',
+            Tile::Wall => '#',
+            Tile::Water => '~',
+            Tile::Tree => 'T',
+        }
+    }
+
+    /// 是否阻擋移動
+    pub fn blocking(&self) -> bool {
+        !matches!(self, Tile::Empty)
+    }
+}
+
+/// 地圖檔（後端地形格式加上格子大小）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerrainMap {
+    pub width: usize,
+    pub height: usize,
+    pub cell_size: f32,
+    pub terrain: Vec<TerrainData>,
+}
+
+/// 本地地形格子（格子座標 (0, 0) 為世界原點所在的格子）
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerrainGrid {
+    width: usize,
+    height: usize,
+    cell_size: f32,
+    tiles: Vec<Tile>,
+}
+
+impl Default for TerrainGrid {
+    fn default() -> Self {
+        Self {
+            width: DEFAULT_WIDTH,
+            height: DEFAULT_HEIGHT,
+            cell_size: DEFAULT_CELL_SIZE,
+            tiles: vec![Tile::Empty; DEFAULT_WIDTH * DEFAULT_HEIGHT],
+        }
+    }
+}
+
+impl TerrainGrid {
+    /// 創建空白地形
+    pub fn new(width: usize, height: usize, cell_size: f32) -> Result<Self> {
+        if !(1..=MAX_CELLS).contains(&width) || !(1..=MAX_CELLS).contains(&height) {
+            return Err(anyhow::anyhow!("格子數應介於 1 到 {}", MAX_CELLS));
+        }
+        if !(cell_size.is_finite() && cell_size > 0.0) {
+            return Err(anyhow::anyhow!("格子大小必須大於 0"));
+        }
+        Ok(Self { width, height, cell_size, tiles: vec![Tile::Empty; width * height] })
+    }
+
+    /// 在兩個角落之間的矩形範圍繪製地形（超出範圍的部分忽略），返回改變的格子數
+    pub fn paint_rect(&mut self, from: (usize, usize), to: (usize, usize), tile: Tile) -> usize {
+        let (x0, x1) = (from.0.min(to.0), from.0.max(to.0).min(self.width.saturating_sub(1)));
+        let (y0, y1) = (from.1.min(to.1), from.1.max(to.1).min(self.height.saturating_sub(1)));
+        let mut changed = 0;
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let cell = &mut self.tiles[y * self.width + x];
+                if *cell != tile {
+                    *cell = tile;
+                    changed += 1;
+                }
+            }
+        }
+        changed
+    }
+
+    /// 世界座標所在的格子
+    pub fn cell_at(&self, x: f32, y: f32) -> Option<(usize, usize)> {
+        let (cx, cy) = ((x / self.cell_size).floor(), (y / self.cell_size).floor());
+        (cx >= 0.0 && cy >= 0.0 && (cx as usize) < self.width && (cy as usize) < self.height)
+            .then_some((cx as usize, cy as usize))
+    }
+
+    /// 轉為後端地形數據（只含非空白的格子，位置為格子中心）
+    pub fn to_terrain(&self) -> Vec<TerrainData> {
+        let mut terrain = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let tile = self.tiles[y * self.width + x];
+                if tile == Tile::Empty {
+                    continue;
+                }
+                terrain.push(TerrainData {
+                    position: ((x as f32 + 0.5) * self.cell_size, (y as f32 + 0.5) * self.cell_size),
+                    terrain_type: tile.name().to_string(),
+                    properties: serde_json::json!({ "blocking": tile.blocking(), "size": self.cell_size }),
+                });
+            }
+        }
+        terrain
+    }
+
+    /// 轉為地圖檔
+    pub fn to_map(&self) -> TerrainMap {
+        TerrainMap { width: self.width, height: self.height, cell_size: self.cell_size, terrain: self.to_terrain() }
+    }
+
+    /// 從地圖檔還原（未知的地形類型與超出範圍的位置會被拒絕）
+    pub fn from_map(map: &TerrainMap) -> Result<Self> {
+        let mut grid = Self::new(map.width, map.height, map.cell_size)?;
+        for data in &map.terrain {
+            let tile = Tile::parse(&data.terrain_type)
+                .ok_or_else(|| anyhow::anyhow!("未知的地形類型: {}", data.terrain_type))?;
+            let cell = grid.cell_at(data.position.0, data.position.1)
+                .ok_or_else(|| anyhow::anyhow!("地形位置 ({}, {}) 超出地圖範圍", data.position.0, data.position.1))?;
+            grid.paint_rect(cell, cell, tile);
+        }
+        Ok(grid)
+    }
+
+    /// 儲存為地圖檔
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let content = serde_json::to_string_pretty(&self.to_map())?;
+        std::fs::write(path, content).with_context(|| format!("無法寫入地圖檔: {}", path.display()))
+    }
+
+    /// 載入地圖檔
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("無法讀取地圖檔: {}", path.display()))?;
+        let map: TerrainMap = serde_json::from_str(&content)
+            .with_context(|| format!("無法解析地圖檔: {}", path.display()))?;
+        Self::from_map(&map)
+    }
+
+    /// 顯示地形（上方為 y 較大的一側）
+    pub fn print(&self) {
+        println!("\n{} {}x{} 格，每格 {} 單位",
+            "測試地形:".bright_cyan().bold(), self.width, self.height, self.cell_size);
+        for y in (0..self.height).rev() {
+            let row: String = (0..self.width).map(|x| {
+                let tile = self.tiles[y * self.width + x];
+                let symbol = tile.symbol().to_string();
+                match tile {
+                    Tile::Empty => symbol.bright_black(),
+                    Tile::Wall => symbol.white(),
+                    Tile::Water => symbol.blue(),
+                    Tile::Tree => symbol.green(),
+                }.to_string()
+            }).collect();
+            println!("{:>4} {}", y, row);
+        }
+        let counts: Vec<String> = Tile::ALL[1..].iter()
+            .map(|tile| format!("{} {} {}", tile.symbol(), tile.name(), self.tiles.iter().filter(|t| *t == tile).count()))
+            .collect();
+        println!("     {}", counts.join("  ").bright_black());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paints_and_round_trips_through_the_backend_format() {
+        let mut grid = TerrainGrid::new(10, 8, 10.0).unwrap();
+        assert_eq!(grid.paint_rect((2, 1), (4, 1), Tile::Wall), 3);
+        assert_eq!(grid.paint_rect((4, 1), (2, 1), Tile::Wall), 0);
+        assert_eq!(grid.paint_rect((8, 6), (20, 20), Tile::Water), 4, "超出範圍的部分忽略");
+        grid.paint_rect((0, 0), (0, 0), Tile::Tree);
+
+        let terrain = grid.to_terrain();
+        assert_eq!(terrain.len(), 8);
+        assert_eq!(terrain[0].position, (5.0, 5.0));
+        assert_eq!(terrain[0].terrain_type, "tree");
+        assert_eq!(terrain[1].position, (25.0, 15.0));
+        assert_eq!(terrain[1].properties["blocking"], true);
+
+        let json = serde_json::to_string(&grid.to_map()).unwrap();
+        let map: TerrainMap = serde_json::from_str(&json).unwrap();
+        assert_eq!(TerrainGrid::from_map(&map).unwrap(), grid);
+
+        assert!(TerrainGrid::new(0, 8, 10.0).is_err());
+        let mut bad = grid.to_map();
+        bad.terrain[0].terrain_type = "lava".to_string();
+        assert!(TerrainGrid::from_map(&bad).is_err());
+    }
+}
@@ -0,0 +1,280 @@
+/// 自訂指標
+///
+/// 使用者在配置檔中以運算式定義衍生指標（例如「存活小兵數」、「英雄間距離」），
+/// 每個 tick 取樣一次並保留歷史，可匯出為遙測資料並在統計浮層中以火花線顯示。
+/// 匯出檔案在建立取樣器時開啟一次並緩衝寫入，每秒（與取樣器釋放時）寫出
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use anyhow::{Result, Context};
+use log::{info, warn};
+use serde_json::{json, Value};
+
+use crate::config::MetricsConfig;
+use crate::expr::{self, Expr};
+use crate::game_state::GameState;
+
+/// 追蹤中的指標
+#[derive(Debug, Clone)]
+pub struct TrackedMetric {
+    /// 指標名稱
+    pub name: String,
+    /// 原始運算式
+    pub source: String,
+    expr: Expr,
+    /// 歷史樣本
+    pub history: VecDeque<f64>,
+    /// 最近一次求值錯誤
+    pub last_error: Option<String>,
+}
+
+impl TrackedMetric {
+    /// 最新的樣本值
+    pub fn latest(&self) -> Option<f64> {
+        self.history.back().copied()
+    }
+}
+
+/// 匯出檔案寫出緩衝的間隔
+const EXPORT_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 開啟中的匯出檔案
+#[derive(Debug)]
+struct MetricsExport {
+    path: String,
+    writer: BufWriter<File>,
+    last_flush: Instant,
+}
+
+/// 指標取樣器
+#[derive(Debug)]
+pub struct MetricsSampler {
+    metrics: Vec<TrackedMetric>,
+    sample_interval_ms: u64,
+    history_len: usize,
+    /// 匯出檔案（開啟或寫入失敗後停止匯出，只記錄一次警告）
+    export: Option<MetricsExport>,
+    last_sample: Option<Instant>,
+}
+
+impl Default for MetricsSampler {
+    fn default() -> Self {
+        Self::from_config(&MetricsConfig::default())
+    }
+}
+
+impl MetricsSampler {
+    /// 從配置創建取樣器（無法解析的定義會被略過並記錄警告）
+    pub fn from_config(config: &MetricsConfig) -> Self {
+        let mut sampler = Self {
+            metrics: Vec::new(),
+            sample_interval_ms: config.sample_interval_ms,
+            history_len: config.history_len.max(1),
+            export: config.export_path.as_deref().and_then(|path| match Self::open_export(path) {
+                Ok(export) => Some(export),
+                Err(e) => {
+                    warn!("停止指標匯出: {:#}", e);
+                    None
+                }
+            }),
+            last_sample: None,
+        };
+        for definition in &config.definitions {
+            if let Err(e) = sampler.add(&definition.name, &definition.expr) {
+                warn!("略過無效的指標定義 {}: {}", definition.name, e);
+            }
+        }
+        sampler
+    }
+
+    /// 添加（或替換同名）指標
+    pub fn add(&mut self, name: &str, source: &str) -> Result<()> {
+        let expr = Expr::parse(source)
+            .with_context(|| format!("無法解析指標運算式: {}", source))?;
+        let metric = TrackedMetric {
+            name: name.to_string(),
+            source: source.to_string(),
+            expr,
+            history: VecDeque::new(),
+            last_error: None,
+        };
+        match self.metrics.iter_mut().find(|m| m.name == name) {
+            Some(existing) => *existing = metric,
+            None => self.metrics.push(metric),
+        }
+        info!("📈 已添加指標 {} = {}", name, source);
+        Ok(())
+    }
+
+    /// 移除指標
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.metrics.len();
+        self.metrics.retain(|m| m.name != name);
+        self.metrics.len() != before
+    }
+
+    /// 所有指標
+    pub fn metrics(&self) -> &[TrackedMetric] {
+        &self.metrics
+    }
+
+    /// 最新值組成的物件，可加入運算式上下文（`metrics.<name>`）
+    pub fn latest_values(&self) -> Value {
+        let map = self.metrics.iter()
+            .filter_map(|m| m.latest().map(|v| (m.name.clone(), json!(v))))
+            .collect();
+        Value::Object(map)
+    }
+
+    /// 每個 tick 呼叫；到達取樣間隔時對所有指標求值
+    pub fn tick(&mut self, state: &GameState) {
+        if self.metrics.is_empty() {
+            return;
+        }
+        if let Some(last) = self.last_sample {
+            if (last.elapsed().as_millis() as u64) < self.sample_interval_ms {
+                return;
+            }
+        }
+        self.last_sample = Some(Instant::now());
+        self.sample(state);
+    }
+
+    /// 立即取樣一次
+    pub fn sample(&mut self, state: &GameState) {
+        let mut ctx = expr::state_context(state);
+        // 指標可引用其他指標的上一個值
+        expr::extend_context(&mut ctx, "metrics", self.latest_values());
+        let mut row = serde_json::Map::new();
+
+        for metric in &mut self.metrics {
+            match metric.expr.eval_number(&ctx) {
+                Ok(value) => {
+                    if metric.history.len() >= self.history_len {
+                        metric.history.pop_front();
+                    }
+                    metric.history.push_back(value);
+                    metric.last_error = None;
+                    row.insert(metric.name.clone(), json!(value));
+                }
+                Err(e) => {
+                    metric.last_error = Some(e.to_string());
+                }
+            }
+        }
+
+        if let Some(export) = &mut self.export {
+            if let Err(e) = Self::export_row(export, row) {
+                warn!("指標匯出失敗，停止匯出到 {}: {}", export.path, e);
+                self.export = None;
+            }
+        }
+    }
+
+    fn open_export(path: &str) -> Result<MetricsExport> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("無法開啟指標匯出檔案: {}", path))?;
+        Ok(MetricsExport { path: path.to_string(), writer: BufWriter::new(file), last_flush: Instant::now() })
+    }
+
+    fn export_row(export: &mut MetricsExport, values: serde_json::Map<String, Value>) -> Result<()> {
+        let record = json!({
+            "timestamp_ms": SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64,
+            "metrics": values,
+        });
+        writeln!(export.writer, "{}", record)?;
+        if export.last_flush.elapsed() >= EXPORT_FLUSH_INTERVAL {
+            export.writer.flush()?;
+            export.last_flush = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// 統計浮層的文字行（名稱、最新值與火花線）
+    pub fn overlay_lines(&self, spark_width: usize) -> Vec<String> {
+        self.metrics.iter()
+            .map(|m| {
+                let value = match (&m.last_error, m.latest()) {
+                    (Some(_), _) => "錯誤".to_string(),
+                    (None, Some(v)) => format!("{:.2}", v),
+                    (None, None) => "-".to_string(),
+                };
+                let samples: Vec<f64> = m.history.iter().copied().collect();
+                let start = samples.len().saturating_sub(spark_width);
+                format!("{} {} {}", m.name, value, sparkline(&samples[start..]))
+            })
+            .collect()
+    }
+}
+
+/// 將樣本繪製為火花線
+pub fn sparkline(samples: &[f64]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let min = samples.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = samples.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    samples.iter()
+        .map(|v| {
+            if range <= f64::EPSILON {
+                BARS[0]
+            } else {
+                let level = ((v - min) / range * (BARS.len() - 1) as f64).round() as usize;
+                BARS[level.min(BARS.len() - 1)]
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_every_tick_unless_an_interval_is_set() {
+        let state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        let mut sampler = MetricsSampler::default();
+        sampler.add("one", "1").unwrap();
+        for _ in 0..3 {
+            sampler.tick(&state);
+        }
+        assert_eq!(sampler.metrics()[0].history.len(), 3, "預設每個 tick 取樣");
+
+        let mut sampler = MetricsSampler::from_config(&MetricsConfig { sample_interval_ms: 60_000, ..MetricsConfig::default() });
+        sampler.add("one", "1").unwrap();
+        for _ in 0..3 {
+            sampler.tick(&state);
+        }
+        assert_eq!(sampler.metrics()[0].history.len(), 1);
+    }
+
+    #[test]
+    fn exports_buffered_rows_and_stops_after_a_bad_path() {
+        let state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        let path = std::env::temp_dir().join(format!("omobaf_metrics_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let config = MetricsConfig { export_path: Some(path.display().to_string()), ..MetricsConfig::default() };
+        let mut sampler = MetricsSampler::from_config(&config);
+        sampler.add("one", "1").unwrap();
+        for _ in 0..3 {
+            sampler.tick(&state);
+        }
+        drop(sampler);
+        let rows: Vec<Value> = std::fs::read_to_string(&path).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(rows.len(), 3, "釋放取樣器時寫出緩衝");
+        assert_eq!(rows[0]["metrics"]["one"], 1.0);
+        std::fs::remove_file(&path).unwrap();
+
+        let bad = MetricsConfig { export_path: Some(path.join("missing").display().to_string()), ..MetricsConfig::default() };
+        let mut sampler = MetricsSampler::from_config(&bad);
+        assert!(sampler.export.is_none(), "無法開啟時停止匯出");
+        sampler.add("one", "1").unwrap();
+        sampler.tick(&state);
+        assert_eq!(sampler.metrics()[0].history.len(), 1, "取樣不受影響");
+    }
+}
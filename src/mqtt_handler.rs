@@ -2,15 +2,30 @@
 /// 
 /// 處理來自 omobab 後端的遊戲 MQTT 訊息
 use rumqttc::Publish;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json;
 use log::{info, warn, debug, error};
 use anyhow::Result;
+use std::borrow::Cow;
 use std::time::SystemTime;
 
-use crate::game_state::GameState;
+use vek::Vec2;
+use crate::game_state::{Entity, EntityType, GameState};
+use crate::progression::{ExperienceData, GoldData};
+use crate::buffs::StatusEffectData;
+use crate::scoreboard::DeathData;
+use crate::topics::{GameTopic, TopicScheme};
+use crate::protocol_errors::{ErrorBus, ProtocolError};
+use crate::schema::FieldDiff;
+use crate::mqtt_stats::MqttStats;
+use crate::payload::{hex_preview, BinaryDecoders, Payload, PayloadKind};
+use crate::version::{SharedBackendVersion, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+
+/// 表示實體消失的廣播動作（小兵死亡、塔被摧毀）
+const REMOVE_ACTIONS: [&str; 4] = ["death", "destroy", "destroyed", "remove"];
 
 /// MQTT 訊息格式（對應後端的 MqttMsg）
+#[cfg(test)]
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct MqttMessage {
     pub topic: String,
@@ -27,84 +42,287 @@ pub struct PlayerData {
     pub d: serde_json::Value,  // 數據
 }
 
+/// 訊息編碼格式（前後端需設定相同的格式）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WireFormat {
+    /// JSON 文字
+    #[default]
+    #[serde(rename = "json")]
+    Json,
+    /// MessagePack（以欄位名稱編碼，結構與 JSON 相同，適合高頻的 screen_response）
+    #[serde(rename = "msgpack")]
+    MessagePack,
+}
+
+impl WireFormat {
+    /// 所有支援的格式
+    #[cfg(feature = "msgpack")]
+    pub const ALL: &[WireFormat] = &[WireFormat::Json, WireFormat::MessagePack];
+    #[cfg(not(feature = "msgpack"))]
+    pub const ALL: &[WireFormat] = &[WireFormat::Json];
+
+    /// 顯示名稱
+    pub fn label(&self) -> &'static str {
+        match self {
+            WireFormat::Json => "json",
+            WireFormat::MessagePack => "msgpack",
+        }
+    }
+    
+    /// 編碼訊息
+    pub fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        Ok(match self {
+            WireFormat::Json => serde_json::to_vec(value)?,
+            #[cfg(feature = "msgpack")]
+            WireFormat::MessagePack => rmp_serde::to_vec_named(value)?,
+            #[cfg(not(feature = "msgpack"))]
+            WireFormat::MessagePack => return Err(msgpack_disabled()),
+        })
+    }
+    
+    /// 解碼訊息
+    pub fn decode<T: DeserializeOwned>(&self, payload: &[u8]) -> Result<T> {
+        Ok(match self {
+            WireFormat::Json => serde_json::from_slice(payload)?,
+            #[cfg(feature = "msgpack")]
+            WireFormat::MessagePack => rmp_serde::from_slice(payload)?,
+            #[cfg(not(feature = "msgpack"))]
+            WireFormat::MessagePack => return Err(msgpack_disabled()),
+        })
+    }
+    
+    /// 日誌用的文字（JSON 為合法 UTF-8 時直接借用負載，否則顯示十六進位預覽；MessagePack 轉成 JSON 顯示）
+    pub fn describe<'a>(&self, payload: &'a [u8]) -> Cow<'a, str> {
+        match self {
+            WireFormat::Json => match std::str::from_utf8(payload) {
+                Ok(text) => Cow::Borrowed(text),
+                Err(_) => Cow::Owned(hex_preview(payload)),
+            },
+            #[cfg(feature = "msgpack")]
+            WireFormat::MessagePack => Cow::Owned(match rmp_serde::from_slice::<serde_json::Value>(payload) {
+                Ok(value) => value.to_string(),
+                Err(_) => format!("<{} 位元組的 msgpack>", payload.len()),
+            }),
+            #[cfg(not(feature = "msgpack"))]
+            WireFormat::MessagePack => Cow::Owned(format!("<{} 位元組的 msgpack>", payload.len())),
+        }
+    }
+}
+
+/// 建置時未啟用 msgpack 功能
+#[cfg(not(feature = "msgpack"))]
+fn msgpack_disabled() -> anyhow::Error {
+    anyhow::anyhow!("此版本建置時未啟用 msgpack 功能，無法使用 MessagePack 編碼")
+}
+
+/// 訊息內容不符合前端預期的結構
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaMismatch {
+    /// 訊息結構名稱（例如 position、screen_response）
+    pub schema: &'static str,
+    pub reason: String,
+    /// 後端回報的協議版本
+    pub backend_protocol: Option<u32>,
+}
+
+impl std::fmt::Display for SchemaMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let backend = self.backend_protocol.map(|protocol| format!("v{}", protocol)).unwrap_or_else(|| "未知".to_string());
+        write!(f, "{} 訊息不符合協議 v{} 的結構（後端協議 {}）: {}", self.schema, PROTOCOL_VERSION, backend, self.reason)
+    }
+}
+
+impl std::error::Error for SchemaMismatch {}
+
 /// MQTT 訊息處理器
 #[derive(Debug, Clone)]
 pub struct MqttHandler {
-    // 統計信息
-    pub messages_received: u64,
-    pub messages_processed: u64,
-    pub last_message_time: Option<SystemTime>,
+    /// 訊息編碼格式
+    format: WireFormat,
+    /// 遊戲主題的命名空間
+    topics: TopicScheme,
+    /// 解碼與路由失敗的錯誤通道
+    errors: ErrorBus,
+    /// 後端在握手中回報的版本
+    backend: SharedBackendVersion,
+    /// 收發統計（複製出的處理器共用同一份）
+    stats: MqttStats,
+    /// 各頻道的二進位解碼器
+    binary_decoders: BinaryDecoders,
 }
 
 impl MqttHandler {
     /// 創建新的 MQTT 處理器
     pub fn new() -> Self {
         Self {
-            messages_received: 0,
-            messages_processed: 0,
-            last_message_time: None,
+            format: WireFormat::default(),
+            topics: TopicScheme::default(),
+            errors: ErrorBus::default(),
+            backend: SharedBackendVersion::default(),
+            stats: MqttStats::default(),
+            binary_decoders: BinaryDecoders::default(),
+        }
+    }
+    
+    /// 創建使用指定編碼格式的 MQTT 處理器
+    pub fn with_format(format: WireFormat) -> Self {
+        Self { format, ..Self::new() }
+    }
+    
+    /// 使用指定的主題命名空間
+    pub fn with_topics(mut self, topics: TopicScheme) -> Self {
+        self.topics = topics;
+        self
+    }
+    
+    /// 使用各頻道的二進位解碼器
+    pub fn with_binary_decoders(mut self, decoders: BinaryDecoders) -> Self {
+        self.binary_decoders = decoders;
+        self
+    }
+    
+    /// 協議錯誤通道
+    pub fn errors(&self) -> &ErrorBus {
+        &self.errors
+    }
+    
+    /// 使用與客戶端共享的後端版本（依協議版本決定是否處理遊戲訊息）
+    pub fn with_backend_version(mut self, backend: SharedBackendVersion) -> Self {
+        self.backend = backend;
+        self
+    }
+    
+    /// 後端回報的協議版本
+    fn backend_protocol(&self) -> Option<u32> {
+        self.backend.lock().unwrap().as_ref().and_then(|backend| backend.protocol)
+    }
+    
+    /// 後端協議過舊時拒絕處理遊戲訊息（未回報版本的後端視為相容）
+    fn check_protocol(&self) -> Result<()> {
+        match self.backend_protocol() {
+            Some(protocol) if protocol < MIN_PROTOCOL_VERSION => Err(anyhow::anyhow!(
+                "後端協議 v{} 過舊（前端最低支援 v{}），拒絕處理遊戲訊息", protocol, MIN_PROTOCOL_VERSION)),
+            _ => Ok(()),
         }
     }
     
+    /// 依結構名稱解析訊息數據並記錄欄位差異，失敗時返回 SchemaMismatch
+    fn decode_data<T: DeserializeOwned>(&self, schema: &'static str, data: &serde_json::Value, game_state: &mut GameState) -> Result<T> {
+        let diff = FieldDiff::of::<T>(data);
+        let decoded = serde_json::from_value(data.clone());
+        for field in game_state.schema_report.record(schema, &diff, decoded.is_ok()) {
+            warn!("⚠️ {} 訊息出現前端未定義的欄位: {}", schema, field);
+        }
+        decoded.map_err(|e| {
+            let reason = if diff.missing.is_empty() {
+                e.to_string()
+            } else {
+                format!("{}（數據缺少欄位: {}）", e, diff.missing.join(", "))
+            };
+            self.mismatch(schema, reason).into()
+        })
+    }
+    
+    fn mismatch(&self, schema: &'static str, reason: impl std::fmt::Display) -> SchemaMismatch {
+        SchemaMismatch { schema, reason: reason.to_string(), backend_protocol: self.backend_protocol() }
+    }
+    
     /// 處理接收到的 MQTT 訊息
     pub async fn handle_message(&self, publish: &Publish, game_state: &mut GameState) -> Result<()> {
-        let mut handler = self.clone();
-        handler.messages_received += 1;
-        handler.last_message_time = Some(SystemTime::now());
-        
         let topic = &publish.topic;
-        let payload = String::from_utf8_lossy(&publish.payload);
+        let payload: &[u8] = &publish.payload;
+        self.stats.record_received(topic, &publish.payload);
         
         // 增強調試信息 - 顯示收到的消息
-        info!("📨 收到 MQTT 訊息 - 主題: {}, 負載: {}", topic, payload);
+        info!("📨 收到 MQTT 訊息 - 主題: {}, 負載: {}", topic, self.format.describe(payload));
         
-        // 根據主題路由訊息
-        match self.route_message(topic, &payload, game_state).await {
+        // 根據主題路由訊息（二進位負載先交給頻道的解碼器）
+        let routed = match self.decode_frame(topic, &Payload::new(publish.payload.clone())) {
+            Ok((format, frame)) if format == self.format => self.route_message(topic, &frame, game_state).await,
+            Ok((format, frame)) => Self { format, ..self.clone() }.route_message(topic, &frame, game_state).await,
+            Err(e) => Err(e),
+        };
+        match routed {
             Ok(_) => {
-                handler.messages_processed += 1;
+                self.stats.record_processed();
                 info!("✅ MQTT 訊息處理成功 - 主題: {}", topic);
             },
             Err(e) => {
+                if e.downcast_ref::<SchemaMismatch>().is_some() {
+                    game_state.schema_errors += 1;
+                }
+                self.stats.record_failure(topic);
                 warn!("❌ MQTT 訊息處理失敗 - 主題: {}, 錯誤: {}", topic, e);
+                self.errors.publish(ProtocolError::from_error(topic, &e));
             }
         }
+        crate::invariants::verify(game_state, || format!("{} {}", topic, self.format.describe(payload)));
         
         Ok(())
     }
     
+    /// 依負載內容決定解碼格式：JSON 文字一律以 JSON 解碼，有對應解碼器的二進位負載先轉成 JSON，其餘依設定的編碼格式
+    fn decode_frame<'a>(&self, topic: &str, payload: &'a Payload) -> Result<(WireFormat, Cow<'a, [u8]>)> {
+        let bytes = payload.bytes();
+        match payload.kind() {
+            PayloadKind::Json => Ok((WireFormat::Json, Cow::Borrowed(bytes))),
+            PayloadKind::Binary => {
+                let channel = match self.topics.parse(topic) {
+                    Some(GameTopic::Broadcast) => "broadcast",
+                    Some(GameTopic::Player { channel, .. }) => channel,
+                    None => topic,
+                };
+                match self.binary_decoders.get(channel) {
+                    Some(codec) => {
+                        let value = codec.decode(bytes)?;
+                        Ok((WireFormat::Json, Cow::Owned(serde_json::to_vec(&value)?)))
+                    }
+                    None if self.format == WireFormat::Json =>
+                        Err(anyhow::anyhow!("二進位負載沒有對應的解碼器（頻道 {}）: {}", channel, hex_preview(bytes))),
+                    None => Ok((self.format, Cow::Borrowed(bytes))),
+                }
+            }
+            PayloadKind::Text => Ok((self.format, Cow::Borrowed(bytes))),
+        }
+    }
+    
     /// 根據主題路由訊息
-    async fn route_message(&self, topic: &str, payload: &str, game_state: &mut GameState) -> Result<()> {
-        if topic == "td/all/res" {
+    async fn route_message(&self, topic: &str, payload: &[u8], game_state: &mut GameState) -> Result<()> {
+        let game_topic = self.topics.parse(topic);
+        if game_topic.is_some() {
+            self.check_protocol()?;
+        }
+        match game_topic {
             // 後端遊戲狀態廣播訊息
-            self.handle_game_broadcast_message(topic, payload, game_state).await
-        } else if topic.starts_with("td/") && topic.ends_with("/send") {
+            Some(GameTopic::Broadcast) => self.handle_game_broadcast_message(topic, payload, game_state).await,
             // 玩家特定遊戲狀態訊息
-            self.handle_game_state_message(topic, payload, game_state).await
-        } else if topic.starts_with("td/") && topic.ends_with("/screen_response") {
+            Some(GameTopic::Player { player, channel: "send" }) =>
+                self.handle_game_state_message(player, payload, game_state).await,
             // 畫面狀態回應訊息
-            self.handle_screen_response_message(topic, payload, game_state).await
-        } else if topic == "ability_test/response" {
+            Some(GameTopic::Player { channel: "screen_response", .. }) =>
+                self.handle_screen_response_message(topic, payload, game_state).await,
             // 能力測試回應
-            self.handle_ability_test_response(payload, game_state).await
-        } else {
-            debug!("未知主題: {}", topic);
-            Ok(())
+            None if topic == "ability_test/response" => self.handle_ability_test_response(payload, game_state).await,
+            _ => {
+                debug!("未知主題: {}", topic);
+                Ok(())
+            }
         }
     }
     
-    /// 處理遊戲廣播訊息 (td/all/res)
-    async fn handle_game_broadcast_message(&self, topic: &str, payload: &str, game_state: &mut GameState) -> Result<()> {
-        info!("收到遊戲廣播訊息 - 主題: {}, 負載: {}", topic, payload);
+    /// 處理遊戲廣播訊息 ({prefix}/all/res)
+    async fn handle_game_broadcast_message(&self, topic: &str, payload: &[u8], game_state: &mut GameState) -> Result<()> {
+        info!("收到遊戲廣播訊息 - 主題: {}, 負載: {}", topic, self.format.describe(payload));
         
         // 嘗試解析 PlayerData 格式
-        match serde_json::from_str::<PlayerData>(payload) {
+        match self.format.decode::<PlayerData>(payload) {
             Ok(player_data) => {
                 info!("解析廣播數據 - 類型: {}, 動作: {}", player_data.t, player_data.a);
                 self.process_broadcast_data(&player_data, game_state).await
             },
             Err(_) => {
                 // 如果不是 PlayerData 格式，嘗試直接解析 JSON
-                match serde_json::from_str::<serde_json::Value>(payload) {
+                match self.format.decode::<serde_json::Value>(payload) {
                     Ok(data) => {
                         info!("解析原始廣播數據: {}", data);
                         self.process_raw_game_data(&data, game_state).await
@@ -123,21 +341,68 @@ impl MqttHandler {
         info!("處理廣播數據 - 類型: {}, 動作: {}", player_data.t, player_data.a);
         
         match player_data.t.as_str() {
-            "creep" => {
-                info!("收到 creep 廣播: {}", player_data.d);
-                // 處理小兵相關訊息
-            },
-            "tower" => {
-                info!("收到 tower 廣播: {}", player_data.d);
-                // 處理塔相關訊息
+            "creep" | "tower" => {
+                let (entity_type, schema) = if player_data.t == "creep" { (EntityType::Creep, "creep") } else { (EntityType::Tower, "tower") };
+                if REMOVE_ACTIONS.contains(&player_data.a.as_str()) {
+                    let unit: EntityRef = self.decode_data("entity_remove", &player_data.d, game_state)?;
+                    game_state.entities.remove(&unit.id);
+                } else {
+                    let unit: UnitData = self.decode_data(schema, &player_data.d, game_state)?;
+                    let existing = game_state.entities.get(&unit.id);
+                    let health = unit.health.or_else(|| existing.map(|entity| entity.health));
+                    // 廣播不帶除錯標籤，保留畫面回應中最後的標籤
+                    let debug_label = existing.and_then(|entity| entity.debug_label.clone());
+                    game_state.entities.insert(unit.id, Entity {
+                        id: unit.id,
+                        entity_type,
+                        position: Vec2::new(unit.position.0, unit.position.1),
+                        health: health.unwrap_or((100.0, 100.0)),
+                        owner: None,
+                        debug_label,
+                    });
+                }
             },
             "player" => {
                 info!("收到 player 廣播: {}", player_data.d);
                 // 處理玩家相關訊息
             },
             "projectile" => {
-                info!("收到 projectile 廣播: {}", player_data.d);
-                // 處理投射物相關訊息
+                // 投射物命中或飛行結束時同樣移除
+                if REMOVE_ACTIONS.contains(&player_data.a.as_str()) || matches!(player_data.a.as_str(), "hit" | "expire") {
+                    let projectile: EntityRef = self.decode_data("entity_remove", &player_data.d, game_state)?;
+                    game_state.entities.remove(&projectile.id);
+                    game_state.projectile_velocities.remove(&projectile.id);
+                } else {
+                    let projectile: ProjectileData = self.decode_data("projectile", &player_data.d, game_state)?;
+                    game_state.projectile_velocities.insert(projectile.id, Vec2::new(projectile.velocity.0, projectile.velocity.1));
+                    game_state.entities.insert(projectile.id, Entity {
+                        id: projectile.id,
+                        entity_type: EntityType::Projectile,
+                        position: Vec2::new(projectile.position.0, projectile.position.1),
+                        health: (0.0, 0.0),
+                        owner: Some(projectile.owner),
+                        debug_label: None,
+                    });
+                }
+            },
+            "combat" => {
+                // 戰鬥日誌也可能以廣播送出
+                let combat_data: CombatData = self.decode_data("combat", &player_data.d, game_state)?;
+                game_state.apply_combat_event(&player_data.name, &combat_data);
+            },
+            "kill" => {
+                let kill_data: KillData = self.decode_data("kill", &player_data.d, game_state)?;
+                game_state.apply_kill_credit(&player_data.name, &kill_data);
+            },
+            "death" => {
+                // 廣播的陣亡與重生（name 為該玩家）
+                let death: DeathData = self.decode_data("death", &player_data.d, game_state)?;
+                game_state.apply_death_event(&player_data.name, &player_data.a, &death);
+            },
+            "status_effect" => {
+                // 廣播的狀態效果帶有實體 ID
+                let effect: StatusEffectData = self.decode_data("status_effect", &player_data.d, game_state)?;
+                game_state.apply_status_effect(&player_data.name, &player_data.a, &effect);
             },
             _ => {
                 debug!("未知的廣播數據類型: {}", player_data.t);
@@ -147,23 +412,18 @@ impl MqttHandler {
         Ok(())
     }
 
-    /// 處理遊戲狀態訊息 (td/+/send)
-    async fn handle_game_state_message(&self, topic: &str, payload: &str, game_state: &mut GameState) -> Result<()> {
-        // 解析主題以獲取玩家名稱
-        let parts: Vec<&str> = topic.split('/').collect();
-        if parts.len() >= 2 {
-            let player_name = parts[1];
-            debug!("處理玩家 {} 的遊戲狀態更新", player_name);
-        }
+    /// 處理遊戲狀態訊息 ({prefix}/+/send)
+    async fn handle_game_state_message(&self, player_name: &str, payload: &[u8], game_state: &mut GameState) -> Result<()> {
+        debug!("處理玩家 {} 的遊戲狀態更新", player_name);
         
         // 嘗試解析 PlayerData 格式
-        match serde_json::from_str::<PlayerData>(payload) {
+        match self.format.decode::<PlayerData>(payload) {
             Ok(player_data) => {
                 self.process_player_data(&player_data, game_state).await
             },
             Err(_) => {
                 // 如果不是 PlayerData 格式，嘗試直接解析 JSON
-                match serde_json::from_str::<serde_json::Value>(payload) {
+                match self.format.decode::<serde_json::Value>(payload) {
                     Ok(data) => {
                         self.process_raw_game_data(&data, game_state).await
                     },
@@ -184,31 +444,65 @@ impl MqttHandler {
         match player_data.t.as_str() {
             "position" => {
                 // 位置更新
-                if let Ok(pos_data) = serde_json::from_value::<PositionData>(player_data.d.clone()) {
-                    game_state.update_player_position(&player_data.name, pos_data.x, pos_data.y);
-                    debug!("更新玩家 {} 位置: ({}, {})", player_data.name, pos_data.x, pos_data.y);
-                }
+                let pos_data: PositionData = self.decode_data("position", &player_data.d, game_state)?;
+                game_state.update_player_position(&player_data.name, pos_data.x, pos_data.y);
+                debug!("更新玩家 {} 位置: ({}, {})", player_data.name, pos_data.x, pos_data.y);
             },
             "ability" => {
                 // 技能使用
-                if let Ok(ability_data) = serde_json::from_value::<AbilityData>(player_data.d.clone()) {
-                    game_state.update_player_ability(&player_data.name, &ability_data);
-                    debug!("玩家 {} 使用技能: {}", player_data.name, ability_data.ability_id);
-                }
+                let ability_data: AbilityData = self.decode_data("ability", &player_data.d, game_state)?;
+                game_state.update_player_ability(&player_data.name, &ability_data);
+                debug!("玩家 {} 使用技能: {}", player_data.name, ability_data.ability_id);
             },
             "health" => {
                 // 生命值更新
-                if let Ok(health_data) = serde_json::from_value::<HealthData>(player_data.d.clone()) {
-                    game_state.update_player_health(&player_data.name, health_data.current, health_data.max);
-                    debug!("更新玩家 {} 生命值: {}/{}", player_data.name, health_data.current, health_data.max);
-                }
+                let health_data: HealthData = self.decode_data("health", &player_data.d, game_state)?;
+                game_state.update_player_health(&player_data.name, health_data.current, health_data.max);
+                debug!("更新玩家 {} 生命值: {}/{}", player_data.name, health_data.current, health_data.max);
+            },
+            "mana" => {
+                // 魔力更新
+                let mana_data: ManaData = self.decode_data("mana", &player_data.d, game_state)?;
+                game_state.update_player_mana(&player_data.name, &mana_data);
+                debug!("更新玩家 {} 魔力: {}/{}", player_data.name, mana_data.current, mana_data.max);
+            },
+            "combat" => {
+                // 戰鬥日誌（name 為受到傷害的玩家）
+                let combat_data: CombatData = self.decode_data("combat", &player_data.d, game_state)?;
+                game_state.apply_combat_event(&player_data.name, &combat_data);
+                debug!("玩家 {} 受到 {} 的傷害: {}", player_data.name, combat_data.source, combat_data.amount);
+            },
+            "kill" => {
+                // 擊殺訊息（name 為被擊殺的玩家）
+                let kill_data: KillData = self.decode_data("kill", &player_data.d, game_state)?;
+                game_state.apply_kill_credit(&player_data.name, &kill_data);
+                debug!("玩家 {} 被 {} 擊殺", player_data.name, kill_data.killer);
+            },
+            "death" => {
+                // 陣亡與重生（a 為 death 或 respawn）
+                let death: DeathData = self.decode_data("death", &player_data.d, game_state)?;
+                game_state.apply_death_event(&player_data.name, &player_data.a, &death);
+            },
+            "gold" => {
+                // 金錢總額與變動原因
+                let gold_data: GoldData = self.decode_data("gold", &player_data.d, game_state)?;
+                game_state.apply_gold(&player_data.name, &gold_data);
+            },
+            "experience" => {
+                // 累積經驗與等級
+                let experience_data: ExperienceData = self.decode_data("experience", &player_data.d, game_state)?;
+                game_state.apply_experience(&player_data.name, &experience_data);
+            },
+            "status_effect" => {
+                // 增益與減益（a 為 apply 或 remove）
+                let effect: StatusEffectData = self.decode_data("status_effect", &player_data.d, game_state)?;
+                game_state.apply_status_effect(&player_data.name, &player_data.a, &effect);
             },
             "summon" => {
                 // 召喚物更新
-                if let Ok(summon_data) = serde_json::from_value::<SummonData>(player_data.d.clone()) {
-                    game_state.update_summon_state(&player_data.name, &summon_data);
-                    debug!("玩家 {} 召喚物更新: {}", player_data.name, summon_data.unit_type);
-                }
+                let summon_data: SummonData = self.decode_data("summon", &player_data.d, game_state)?;
+                game_state.update_summon_state(&player_data.name, &summon_data);
+                debug!("玩家 {} 召喚物更新: {}", player_data.name, summon_data.unit_type);
             },
             _ => {
                 debug!("未知的玩家數據類型: {}", player_data.t);
@@ -240,77 +534,139 @@ impl MqttHandler {
     }
     
     /// 處理畫面狀態回應訊息
-    async fn handle_screen_response_message(&self, topic: &str, payload: &str, game_state: &mut GameState) -> Result<()> {
+    async fn handle_screen_response_message(&self, topic: &str, payload: &[u8], game_state: &mut GameState) -> Result<()> {
         info!("🖥️ 收到畫面狀態回應 - 主題: {}", topic);
-        info!("📄 Screen response payload (前100字符): {}", &payload[..std::cmp::min(100, payload.len())]);
-        debug!("畫面狀態回應內容: {}", payload);
+        // 大型回應只在需要輸出日誌時才轉成文字
+        if log::log_enabled!(log::Level::Info) {
+            let text = self.format.describe(payload);
+            info!("📄 Screen response payload (前100字符): {}", text.chars().take(100).collect::<String>());
+            debug!("畫面狀態回應內容: {}", text);
+        }
         
-        match serde_json::from_str::<ScreenResponse>(payload) {
-            Ok(response) => {
-                info!("解析畫面狀態回應成功 - 範圍: {:?}", response.d.area);
-                
-                // 更新視口範圍
-                if let Some(area) = &response.d.area {
-                    game_state.viewport.center.x = (area.min_x + area.max_x) / 2.0;
-                    game_state.viewport.center.y = (area.min_y + area.max_y) / 2.0;
-                    game_state.viewport.width = area.max_x - area.min_x;
-                    game_state.viewport.height = area.max_y - area.min_y;
-                    debug!("更新視口中心: ({:.1}, {:.1}), 大小: {:.1}x{:.1}", 
-                           game_state.viewport.center.x, game_state.viewport.center.y,
-                           game_state.viewport.width, game_state.viewport.height);
-                }
-                
-                // 處理實體數據 - 將網路實體轉換為本地實體
-                if let Some(entities) = &response.d.entities {
-                    for net_entity in entities {
-                        // 將網路實體轉換為本地實體格式
-                        let entity = crate::game_state::Entity {
-                            id: net_entity.id,
-                            entity_type: match net_entity.entity_type.as_str() {
-                                "player" => crate::game_state::EntityType::Player("unknown".to_string()),
-                                "summon" => crate::game_state::EntityType::Summon(net_entity.entity_type.clone()),
-                                "projectile" => crate::game_state::EntityType::Projectile,
-                                _ => crate::game_state::EntityType::Effect,
-                            },
-                            position: vek::Vec2::new(net_entity.position.0, net_entity.position.1),
-                            health: net_entity.health.unwrap_or((100.0, 100.0)),
-                            owner: None,
-                        };
-                        game_state.entities.insert(entity.id, entity);
-                    }
-                    info!("更新 {} 個實體", entities.len());
-                }
-                
-                // 處理玩家數據
-                if let Some(players) = &response.d.players {
-                    for player in players {
-                        game_state.other_players.insert(player.name.clone(), player.clone());
-                    }
-                    info!("更新 {} 個玩家狀態", players.len());
-                }
-                
-                // 更新最後更新時間
-                game_state.last_update = SystemTime::now();
-                
-            },
+        let data = match self.format.decode::<serde_json::Value>(payload) {
+            Ok(data) => data,
             Err(e) => {
-                warn!("❌ 無法解析畫面狀態回應: {}", e);
-                info!("🔍 嘗試解析為原始 JSON...");
-                // 嘗試解析為簡單 JSON 對象
-                if let Ok(data) = serde_json::from_str::<serde_json::Value>(payload) {
-                    info!("✅ 收到原始畫面數據: {}", data);
-                } else {
-                    error!("❌ 完全無法解析 JSON 數據");
+                error!("❌ 完全無法解析畫面狀態回應: {}", e);
+                return Ok(());
+            }
+        };
+        // 能解碼但結構不符時多半是前後端協議不一致，而不是傳輸問題
+        let screen: ScreenData = self.decode_data("screen_response", &data["d"], game_state).inspect_err(|_| {
+            debug!("不符合結構的畫面數據: {}", data);
+        })?;
+        let response = ScreenResponse {
+            t: data["t"].as_str().unwrap_or("screen_response").to_string(),
+            d: screen,
+        };
+        info!("解析畫面狀態回應成功 - 範圍: {:?}", response.d.area);
+        
+        // 更新視口範圍
+        if let Some(area) = &response.d.area {
+            game_state.viewport.center.x = (area.min_x + area.max_x) / 2.0;
+            game_state.viewport.center.y = (area.min_y + area.max_y) / 2.0;
+            game_state.viewport.width = area.max_x - area.min_x;
+            game_state.viewport.height = area.max_y - area.min_y;
+            debug!("更新視口中心: ({:.1}, {:.1}), 大小: {:.1}x{:.1}", 
+                   game_state.viewport.center.x, game_state.viewport.center.y,
+                   game_state.viewport.width, game_state.viewport.height);
+        }
+        
+        if let Some(bounds) = &response.d.world_bounds {
+            game_state.world_bounds = Some(crate::game_state::WorldBounds {
+                min_x: bounds.min_x,
+                min_y: bounds.min_y,
+                max_x: bounds.max_x,
+                max_y: bounds.max_y,
+            });
+        }
+        
+        // 地形是靜態的，完整與增量畫面都直接累積
+        if let Some(terrain) = &response.d.terrain {
+            let cells: usize = terrain.iter().map(|data| game_state.terrain.insert(data)).sum();
+            debug!("更新 {} 筆地形（{} 格）", terrain.len(), cells);
+        }
+        
+        if response.t == "screen_delta" {
+            self.apply_screen_delta(&response.d, game_state);
+        } else {
+            // 帶序號的完整畫面是增量更新的新基準，取代目前的實體與玩家
+            if response.d.seq.is_some() {
+                game_state.entities.clear();
+                game_state.other_players.clear();
+            }
+            game_state.screen_seq = response.d.seq;
+            
+            // 處理實體數據 - 將網路實體轉換為本地實體
+            if let Some(entities) = &response.d.entities {
+                for net_entity in entities {
+                    game_state.entities.insert(net_entity.id, net_entity.to_entity());
+                }
+                info!("更新 {} 個實體", entities.len());
+            }
+            
+            // 處理玩家數據
+            if let Some(players) = &response.d.players {
+                for player in players {
+                    game_state.other_players.insert(player.name.clone(), player.clone());
                 }
+                info!("更新 {} 個玩家狀態", players.len());
             }
         }
         
+        // 記下畫面範圍內看過的區塊與塔，鏡頭移開後繼續顯示
+        let area = response.d.area.as_ref().map(|area| (Vec2::new(area.min_x, area.min_y), Vec2::new(area.max_x, area.max_y)));
+        game_state.world_map.observe(area, game_state.entities.values());
+        
+        // 更新最後更新時間
+        game_state.last_update = SystemTime::now();
+        
         Ok(())
     }
 
+    /// 套用增量畫面更新（新增/更新/移除實體與玩家），基準序號不符時放棄並要求下一次送完整畫面
+    fn apply_screen_delta(&self, delta: &ScreenData, game_state: &mut GameState) {
+        if delta.base_seq.is_none() || delta.base_seq != game_state.screen_seq {
+            warn!("增量畫面的基準 {:?} 與目前的畫面序號 {:?} 不符，下一次請求完整畫面", delta.base_seq, game_state.screen_seq);
+            game_state.screen_seq = None;
+            return;
+        }
+        
+        let added = delta.entities.as_deref().unwrap_or_default();
+        for net_entity in added {
+            game_state.entities.insert(net_entity.id, net_entity.to_entity());
+        }
+        let updated = delta.updated.as_deref().unwrap_or_default();
+        for update in updated {
+            let Some(entity) = game_state.entities.get_mut(&update.id) else {
+                debug!("增量更新的實體 {} 不存在", update.id);
+                continue;
+            };
+            if let Some((x, y)) = update.position {
+                entity.position = Vec2::new(x, y);
+            }
+            if let Some(health) = update.health {
+                entity.health = health;
+            }
+            if update.debug_label.is_some() {
+                entity.debug_label = update.debug_label.clone();
+            }
+        }
+        for id in &delta.removed {
+            game_state.entities.remove(id);
+        }
+        for player in delta.players.as_deref().unwrap_or_default() {
+            game_state.other_players.insert(player.name.clone(), player.clone());
+        }
+        for name in &delta.removed_players {
+            game_state.other_players.remove(name);
+        }
+        game_state.screen_seq = delta.seq;
+        debug!("套用增量畫面 #{:?}: 新增 {}、更新 {}、移除 {} 個實體", delta.seq, added.len(), updated.len(), delta.removed.len());
+    }
+
     /// 處理能力測試回應
-    async fn handle_ability_test_response(&self, payload: &str, _game_state: &mut GameState) -> Result<()> {
-        match serde_json::from_str::<TestResponse>(payload) {
+    async fn handle_ability_test_response(&self, payload: &[u8], _game_state: &mut GameState) -> Result<()> {
+        match self.format.decode::<TestResponse>(payload) {
             Ok(response) => {
                 info!("收到能力測試回應 - 命令: {}, 成功: {}", response.command, response.success);
                 if !response.success {
@@ -327,12 +683,28 @@ impl MqttHandler {
         Ok(())
     }
     
-    /// 獲取統計信息
-    pub fn get_stats(&self) -> (u64, u64, Option<SystemTime>) {
-        (self.messages_received, self.messages_processed, self.last_message_time)
+    /// 收發統計
+    pub fn stats(&self) -> &MqttStats {
+        &self.stats
     }
 }
 
+/// 小兵與防禦塔廣播數據
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UnitData {
+    pub id: u32,
+    pub position: (f32, f32),
+    /// (current, max)，未提供時沿用上次的值
+    #[serde(default)]
+    pub health: Option<(f32, f32)>,
+}
+
+/// 只帶 ID 的實體廣播（死亡、摧毀、命中等移除訊息）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct EntityRef {
+    id: u32,
+}
+
 /// 位置數據
 #[derive(Serialize, Deserialize, Clone, Debug)]
 struct PositionData {
@@ -357,6 +729,41 @@ struct HealthData {
     max: f32,
 }
 
+/// 魔力數據
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ManaData {
+    pub current: f32,
+    pub max: f32,
+    /// 每秒回復量（未提供時沿用目前的估計值）
+    #[serde(default)]
+    pub regen: Option<f32>,
+}
+
+/// 戰鬥日誌數據（單次傷害）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CombatData {
+    /// 傷害來源
+    pub source: String,
+    /// 造成傷害的技能
+    #[serde(default)]
+    pub ability: Option<String>,
+    /// 傷害量
+    pub amount: f32,
+    /// 此次傷害是否致命
+    #[serde(default)]
+    pub fatal: bool,
+}
+
+/// 擊殺數據
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct KillData {
+    /// 擊殺者
+    pub killer: String,
+    /// 助攻者
+    #[serde(default)]
+    pub assists: Vec<String>,
+}
+
 /// 召喚物數據
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct SummonData {
@@ -392,7 +799,25 @@ pub struct ScreenData {
     pub players: Option<Vec<PlayerState>>,
     pub projectiles: Option<Vec<ProjectileData>>,
     pub terrain: Option<Vec<TerrainData>>,
+    /// 地圖世界邊界（後端提供時覆蓋配置）
+    #[serde(default)]
+    pub world_bounds: Option<ScreenArea>,
     pub timestamp: u64,
+    /// 畫面序號（後端支援增量更新時提供）
+    #[serde(default)]
+    pub seq: Option<u64>,
+    /// 增量畫面的基準序號（screen_delta 時，entities 為新增的實體）
+    #[serde(default)]
+    pub base_seq: Option<u64>,
+    /// 增量畫面中位置或生命值改變的實體
+    #[serde(default)]
+    pub updated: Option<Vec<EntityUpdate>>,
+    /// 增量畫面中離開範圍或消失的實體 ID
+    #[serde(default)]
+    pub removed: Vec<u32>,
+    /// 增量畫面中離開範圍的玩家
+    #[serde(default)]
+    pub removed_players: Vec<String>,
 }
 
 /// 畫面範圍
@@ -412,6 +837,42 @@ pub struct NetworkEntity {
     pub position: (f32, f32),
     pub health: Option<(f32, f32)>,
     pub state: String,
+    /// 除錯標籤（例如 AI 狀態、路徑節點 ID），終端視圖開啟除錯標籤時顯示在實體旁
+    #[serde(default)]
+    pub debug_label: Option<String>,
+}
+
+impl NetworkEntity {
+    /// 轉換為本地實體格式
+    fn to_entity(&self) -> Entity {
+        Entity {
+            id: self.id,
+            entity_type: match self.entity_type.as_str() {
+                "player" => EntityType::Player("unknown".to_string()),
+                "summon" => EntityType::Summon(self.entity_type.clone()),
+                "projectile" => EntityType::Projectile,
+                "creep" => EntityType::Creep,
+                "tower" => EntityType::Tower,
+                _ => EntityType::Effect,
+            },
+            position: Vec2::new(self.position.0, self.position.1),
+            health: self.health.unwrap_or((100.0, 100.0)),
+            owner: None,
+            debug_label: self.debug_label.clone(),
+        }
+    }
+}
+
+/// 增量畫面中的實體變化（只帶改變的欄位）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EntityUpdate {
+    pub id: u32,
+    #[serde(default)]
+    pub position: Option<(f32, f32)>,
+    #[serde(default)]
+    pub health: Option<(f32, f32)>,
+    #[serde(default)]
+    pub debug_label: Option<String>,
 }
 
 /// 投射物數據
@@ -440,4 +901,374 @@ struct TestResponse {
     data: serde_json::Value,
     timestamp: u64,
     execution_time_ms: u64,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn message_pack_round_trips_player_data() {
+        let message = PlayerData {
+            name: "p1".to_string(),
+            t: "game_render".to_string(),
+            a: "update".to_string(),
+            d: serde_json::json!({ "entities": [{ "id": 7, "x": 1.5 }] }),
+        };
+        for format in [WireFormat::Json, WireFormat::MessagePack] {
+            let bytes = format.encode(&message).unwrap();
+            let decoded: PlayerData = format.decode(&bytes).unwrap();
+            assert_eq!(decoded.t, message.t);
+            assert_eq!(decoded.d, message.d);
+        }
+        assert!(WireFormat::Json.decode::<PlayerData>(&WireFormat::MessagePack.encode(&message).unwrap()).is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_mismatched_schema_and_outdated_backend() {
+        let backend = SharedBackendVersion::default();
+        let handler = MqttHandler::new().with_backend_version(backend.clone());
+        let mut errors = handler.errors().subscribe();
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+
+        // position 缺少 y，舊版會靜默忽略
+        let payload = br#"{"name":"p1","t":"position","a":"update","d":{"x":1.0}}"#;
+        let publish = Publish::new("td/p1/send", rumqttc::QoS::AtMostOnce, payload.to_vec());
+        let error = handler.route_message(&publish.topic, &publish.payload, &mut state).await.unwrap_err();
+        assert_eq!(error.downcast_ref::<SchemaMismatch>().unwrap().schema, "position");
+        handler.handle_message(&publish, &mut state).await.unwrap();
+        assert_eq!(state.schema_errors, 1);
+        assert_eq!(errors.try_recv().unwrap().kind, crate::protocol_errors::ProtocolErrorKind::Schema);
+
+        *backend.lock().unwrap() = Some(crate::version::BackendVersion {
+            version: "0.0.1".to_string(),
+            protocol: Some(MIN_PROTOCOL_VERSION - 1),
+            wire_formats: Vec::new(),
+        });
+        let valid = br#"{"name":"p1","t":"position","a":"update","d":{"x":1.0,"y":2.0}}"#;
+        assert!(handler.route_message("td/p1/send", valid, &mut state).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn tracks_mana_updates_cast_costs_and_regen() {
+        let handler = MqttHandler::new();
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        let payload = br#"{"name":"p1","t":"mana","a":"update","d":{"current":200.0,"max":400.0,"regen":10.0}}"#;
+        handler.route_message("td/p1/send", payload, &mut state).await.unwrap();
+        assert_eq!((state.local_player.mana, state.local_player.mana_regen), ((200.0, 400.0), 10.0));
+
+        state.apply_local_action("cast_ability", &serde_json::json!({ "ability_id": "sniper_mode" }));
+        assert_eq!(state.local_player.mana.0, 150.0, "施放扣除估計的魔力消耗");
+        state.update_cooldowns(2.0);
+        assert_eq!(state.local_player.mana.0, 170.0);
+        state.update_cooldowns(100.0);
+        assert_eq!(state.local_player.mana.0, 400.0, "回復不超過上限");
+
+        let other = br#"{"name":"p2","t":"mana","a":"update","d":{"current":1.0,"max":2.0}}"#;
+        handler.route_message("td/p2/send", other, &mut state).await.unwrap();
+        assert_eq!(state.local_player.mana, (400.0, 400.0));
+    }
+
+    #[tokio::test]
+    async fn tracks_creep_tower_and_projectile_broadcasts() {
+        let handler = MqttHandler::new();
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        let broadcasts: [&[u8]; 5] = [
+            br#"{"name":"server","t":"creep","a":"spawn","d":{"id":1,"position":[10.0,20.0],"health":[300.0,300.0]}}"#,
+            br#"{"name":"server","t":"creep","a":"move","d":{"id":1,"position":[15.0,20.0]}}"#,
+            br#"{"name":"server","t":"tower","a":"update","d":{"id":2,"position":[100.0,100.0],"health":[2000.0,2500.0]}}"#,
+            br#"{"name":"server","t":"projectile","a":"create","d":{"id":3,"projectile_type":"bullet","position":[0.0,0.0],"velocity":[5.0,0.0],"owner":"p2"}}"#,
+            br#"{"name":"server","t":"projectile","a":"hit","d":{"id":3}}"#,
+        ];
+        for payload in broadcasts {
+            handler.route_message("td/all/res", payload, &mut state).await.unwrap();
+        }
+
+        let creep = &state.entities[&1];
+        assert_eq!(creep.entity_type, EntityType::Creep);
+        assert_eq!(creep.position, Vec2::new(15.0, 20.0));
+        assert_eq!(creep.health, (300.0, 300.0));
+        assert_eq!(state.entities[&2].entity_type, EntityType::Tower);
+        assert!(!state.entities.contains_key(&3));
+        assert!(state.projectile_velocities.is_empty(), "命中後不再外推");
+
+        handler.route_message("td/all/res", br#"{"name":"server","t":"creep","a":"death","d":{"id":1}}"#, &mut state).await.unwrap();
+        assert!(!state.entities.contains_key(&1));
+    }
+
+    #[tokio::test]
+    async fn merges_screen_deltas_onto_sequenced_snapshot() {
+        let handler = MqttHandler::new();
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        state.entities.insert(99, NetworkEntity { id: 99, entity_type: "creep".to_string(), position: (0.0, 0.0), health: None, state: String::new(), debug_label: None }.to_entity());
+        let screen = |payload: &str| Publish::new("td/p1/screen_response", rumqttc::QoS::AtMostOnce, payload.as_bytes().to_vec());
+
+        // 帶序號的完整畫面取代舊的實體
+        let snapshot = r#"{"t":"screen_response","d":{"seq":1,"timestamp":0,"entities":[
+            {"id":1,"entity_type":"creep","position":[1.0,1.0],"health":[100.0,100.0],"state":"idle","debug_label":"patrol"},
+            {"id":2,"entity_type":"tower","position":[5.0,5.0],"health":[900.0,900.0],"state":"idle"}]}}"#;
+        handler.handle_message(&screen(snapshot), &mut state).await.unwrap();
+        assert_eq!(state.screen_seq, Some(1));
+        assert!(!state.entities.contains_key(&99));
+
+        let delta = r#"{"t":"screen_delta","d":{"seq":2,"base_seq":1,"timestamp":0,
+            "entities":[{"id":3,"entity_type":"projectile","position":[2.0,2.0],"health":null,"state":"flying"}],
+            "updated":[{"id":1,"position":[3.0,1.0],"debug_label":"chase p1"}],"removed":[2]}}"#;
+        handler.handle_message(&screen(delta), &mut state).await.unwrap();
+        assert_eq!(state.screen_seq, Some(2));
+        assert_eq!(state.entities[&1].position, Vec2::new(3.0, 1.0));
+        assert_eq!(state.entities[&1].health, (100.0, 100.0));
+        assert_eq!(state.entities[&1].debug_label.as_deref(), Some("chase p1"));
+        assert!(!state.entities.contains_key(&2));
+        assert_eq!(state.entities[&3].entity_type, EntityType::Projectile);
+
+        // 漏掉中間的增量時不套用，並要求重新同步
+        let gap = r#"{"t":"screen_delta","d":{"seq":5,"base_seq":4,"timestamp":0,"removed":[1]}}"#;
+        handler.handle_message(&screen(gap), &mut state).await.unwrap();
+        assert_eq!(state.screen_seq, None);
+        assert!(state.entities.contains_key(&1));
+    }
+
+    /// 協議型別的範例數據：每個型別提供可選欄位的各種組合，供來回序列化與其他測試使用
+    mod fixtures {
+        use super::super::*;
+
+        pub fn abilities() -> Vec<AbilityData> {
+            let targets = [(None, None), (Some((10.0, -4.5)), None), (None, Some(42)), (Some((0.0, 0.0)), Some(7))];
+            targets.into_iter().map(|(target_position, target_entity)| AbilityData {
+                ability_id: "sniper_mode".to_string(),
+                level: 2,
+                cooldown_remaining: 1.25,
+                target_position,
+                target_entity,
+            }).collect()
+        }
+
+        pub fn summons() -> Vec<SummonData> {
+            vec![SummonData { unit_type: "archer".to_string(), position: (3.0, 4.0), health: 150.0, state: "attacking".to_string() }]
+        }
+
+        pub fn player_states() -> Vec<PlayerState> {
+            vec![
+                PlayerState {
+                    name: "p2".to_string(),
+                    hero_type: "date_masamune".to_string(),
+                    position: (100.0, 200.0),
+                    health: (80.0, 120.0),
+                    abilities: Vec::new(),
+                    summons: Vec::new(),
+                },
+                PlayerState {
+                    name: "p3".to_string(),
+                    hero_type: "saika_magoichi".to_string(),
+                    position: (-5.5, 12.25),
+                    health: (0.0, 100.0),
+                    abilities: abilities(),
+                    summons: summons(),
+                },
+            ]
+        }
+
+        pub fn network_entities() -> Vec<NetworkEntity> {
+            vec![
+                NetworkEntity { id: 1, entity_type: "creep".to_string(), position: (1.0, 2.0), health: Some((300.0, 300.0)), state: "moving".to_string(), debug_label: Some("chase p1".to_string()) },
+                NetworkEntity { id: 2, entity_type: "projectile".to_string(), position: (5.0, 5.0), health: None, state: "flying".to_string(), debug_label: None },
+            ]
+        }
+
+        pub fn area() -> ScreenArea {
+            ScreenArea { min_x: -50.0, min_y: -25.0, max_x: 50.0, max_y: 25.0 }
+        }
+
+        pub fn projectiles() -> Vec<ProjectileData> {
+            vec![ProjectileData { id: 9, projectile_type: "bullet".to_string(), position: (0.5, 0.5), velocity: (12.0, -3.0), owner: "p1".to_string() }]
+        }
+
+        pub fn terrain() -> Vec<TerrainData> {
+            vec![TerrainData { position: (8.0, 8.0), terrain_type: "wall".to_string(), properties: serde_json::json!({ "blocking": true, "height": 2 }) }]
+        }
+
+        pub fn entity_updates() -> Vec<EntityUpdate> {
+            vec![
+                EntityUpdate { id: 1, position: Some((2.0, 2.0)), health: None, debug_label: Some("return".to_string()) },
+                EntityUpdate { id: 2, position: None, health: Some((50.0, 300.0)), debug_label: None },
+                EntityUpdate { id: 3, position: None, health: None, debug_label: None },
+            ]
+        }
+
+        /// 只有必填欄位的畫面與所有欄位都有值的完整畫面、增量畫面
+        pub fn screens() -> Vec<ScreenResponse> {
+            let minimal = ScreenData {
+                area: None,
+                entities: None,
+                players: None,
+                projectiles: None,
+                terrain: None,
+                world_bounds: None,
+                timestamp: 0,
+                seq: None,
+                base_seq: None,
+                updated: None,
+                removed: Vec::new(),
+                removed_players: Vec::new(),
+            };
+            let full = ScreenData {
+                area: Some(area()),
+                entities: Some(network_entities()),
+                players: Some(player_states()),
+                projectiles: Some(projectiles()),
+                terrain: Some(terrain()),
+                world_bounds: Some(ScreenArea { min_x: 0.0, min_y: 0.0, max_x: 4000.0, max_y: 4000.0 }),
+                timestamp: 1_700_000_000_123,
+                seq: Some(5),
+                ..minimal.clone()
+            };
+            let delta = ScreenData {
+                seq: Some(6),
+                base_seq: Some(5),
+                updated: Some(entity_updates()),
+                removed: vec![2],
+                removed_players: vec!["p2".to_string()],
+                ..minimal.clone()
+            };
+            vec![
+                ScreenResponse { t: "screen_response".to_string(), d: minimal },
+                ScreenResponse { t: "screen_response".to_string(), d: full },
+                ScreenResponse { t: "screen_delta".to_string(), d: delta },
+            ]
+        }
+
+        /// 包在 PlayerData 中的各類玩家與廣播訊息（主題內容）
+        pub fn player_messages() -> Vec<PlayerData> {
+            let message = |t: &str, a: &str, d: serde_json::Value| PlayerData { name: "p1".to_string(), t: t.to_string(), a: a.to_string(), d };
+            vec![
+                message("position", "update", serde_json::to_value(PositionData { x: 1.0, y: 2.0 }).unwrap()),
+                message("health", "update", serde_json::to_value(HealthData { current: 90.0, max: 100.0 }).unwrap()),
+                message("mana", "update", serde_json::to_value(ManaData { current: 120.0, max: 300.0, regen: Some(2.5) }).unwrap()),
+                message("ability", "cast", serde_json::to_value(&abilities()[3]).unwrap()),
+                message("summon", "update", serde_json::to_value(&summons()[0]).unwrap()),
+                message("combat", "damage", serde_json::json!({ "source": "p2", "amount": 35.0 })),
+                message("kill", "kill", serde_json::json!({ "killer": "p2" })),
+                message("death", "death", serde_json::to_value(DeathData { respawn_time: Some(12.0), position: None }).unwrap()),
+                message("death", "respawn", serde_json::json!({ "position": [400.0, 300.0] })),
+                message("gold", "update", serde_json::to_value(GoldData { gold: 640, reason: Some("creep".to_string()) }).unwrap()),
+                message("experience", "update", serde_json::to_value(ExperienceData { experience: 340, level: 3, next_level: Some(600) }).unwrap()),
+                message("status_effect", "apply", serde_json::json!({ "id": "haste", "stacks": 2, "duration": 5.0, "source": "p2" })),
+                message("creep", "spawn", serde_json::to_value(UnitData { id: 1, position: (1.0, 1.0), health: Some((300.0, 300.0)) }).unwrap()),
+                message("creep", "death", serde_json::to_value(EntityRef { id: 1 }).unwrap()),
+            ]
+        }
+    }
+
+    /// 以所有編碼格式來回序列化，確認內容不變
+    fn assert_round_trip<T: Serialize + DeserializeOwned>(value: &T) {
+        let expected = serde_json::to_value(value).unwrap();
+        for format in WireFormat::ALL {
+            let decoded: T = format.decode(&format.encode(value).unwrap()).unwrap();
+            assert_eq!(serde_json::to_value(&decoded).unwrap(), expected, "{} 來回序列化後內容改變", format.label());
+        }
+    }
+
+    #[test]
+    fn protocol_types_round_trip_in_every_wire_format() {
+        fixtures::abilities().iter().for_each(assert_round_trip);
+        fixtures::summons().iter().for_each(assert_round_trip);
+        fixtures::player_states().iter().for_each(assert_round_trip);
+        fixtures::network_entities().iter().for_each(assert_round_trip);
+        fixtures::projectiles().iter().for_each(assert_round_trip);
+        fixtures::terrain().iter().for_each(assert_round_trip);
+        fixtures::entity_updates().iter().for_each(assert_round_trip);
+        fixtures::screens().iter().for_each(assert_round_trip);
+        fixtures::player_messages().iter().for_each(assert_round_trip);
+        assert_round_trip(&fixtures::area());
+        for health in [None, Some((10.0, 20.0))] {
+            assert_round_trip(&UnitData { id: 4, position: (1.0, 2.0), health });
+        }
+        for (ability, fatal) in [(None, false), (Some("katana".to_string()), true)] {
+            assert_round_trip(&CombatData { source: "p2".to_string(), ability, amount: 12.5, fatal });
+        }
+        for assists in [Vec::new(), vec!["p3".to_string(), "p4".to_string()]] {
+            assert_round_trip(&KillData { killer: "p2".to_string(), assists });
+        }
+        assert_round_trip(&MqttMessage { topic: "td/all/res".to_string(), msg: "{}".to_string(), time: SystemTime::UNIX_EPOCH });
+        assert_round_trip(&TestResponse {
+            command: "cast".to_string(),
+            success: false,
+            data: serde_json::json!({ "error": "on cooldown" }),
+            timestamp: 1,
+            execution_time_ms: 3,
+        });
+    }
+
+    #[test]
+    fn protocol_field_names_are_locked() {
+        use crate::schema::fields_of;
+        assert_eq!(fields_of::<PlayerData>(), ["name", "t", "a", "d"]);
+        assert_eq!(fields_of::<AbilityData>(), ["ability_id", "level", "cooldown_remaining", "target_position", "target_entity"]);
+        assert_eq!(fields_of::<SummonData>(), ["unit_type", "position", "health", "state"]);
+        assert_eq!(fields_of::<PlayerState>(), ["name", "hero_type", "position", "health", "abilities", "summons"]);
+        assert_eq!(fields_of::<ManaData>(), ["current", "max", "regen"]);
+        assert_eq!(fields_of::<CombatData>(), ["source", "ability", "amount", "fatal"]);
+        assert_eq!(fields_of::<KillData>(), ["killer", "assists"]);
+        assert_eq!(fields_of::<UnitData>(), ["id", "position", "health"]);
+        assert_eq!(fields_of::<GoldData>(), ["gold", "reason"]);
+        assert_eq!(fields_of::<ExperienceData>(), ["experience", "level", "next_level"]);
+        assert_eq!(fields_of::<StatusEffectData>(), ["id", "stacks", "duration", "source", "debuff", "entity"]);
+        assert_eq!(fields_of::<DeathData>(), ["respawn_time", "position"]);
+        assert_eq!(fields_of::<ScreenResponse>(), ["t", "d"]);
+        assert_eq!(fields_of::<ScreenData>(), [
+            "area", "entities", "players", "projectiles", "terrain", "world_bounds",
+            "timestamp", "seq", "base_seq", "updated", "removed", "removed_players",
+        ]);
+        assert_eq!(fields_of::<ScreenArea>(), ["min_x", "min_y", "max_x", "max_y"]);
+        assert_eq!(fields_of::<NetworkEntity>(), ["id", "entity_type", "position", "health", "state", "debug_label"]);
+        assert_eq!(fields_of::<EntityUpdate>(), ["id", "position", "health", "debug_label"]);
+        assert_eq!(fields_of::<ProjectileData>(), ["id", "projectile_type", "position", "velocity", "owner"]);
+        assert_eq!(fields_of::<TerrainData>(), ["position", "terrain_type", "properties"]);
+    }
+
+    #[test]
+    fn sample_payloads_decode_with_optional_fields_omitted() {
+        let ability: AbilityData = serde_json::from_str(r#"{"ability_id":"a","level":1,"cooldown_remaining":0.0}"#).unwrap();
+        assert_eq!((ability.target_position, ability.target_entity), (None, None));
+
+        let combat: CombatData = serde_json::from_str(r#"{"source":"p2","amount":5.0}"#).unwrap();
+        assert_eq!((combat.ability, combat.fatal), (None, false));
+
+        let kill: KillData = serde_json::from_str(r#"{"killer":"p2"}"#).unwrap();
+        assert!(kill.assists.is_empty());
+
+        let unit: UnitData = serde_json::from_str(r#"{"id":3,"position":[1.0,2.0]}"#).unwrap();
+        assert_eq!(unit.health, None);
+
+        let update: EntityUpdate = serde_json::from_str(r#"{"id":3}"#).unwrap();
+        assert_eq!((update.position, update.health), (None, None));
+
+        // 畫面中只有 timestamp 是必填欄位
+        let screen: ScreenResponse = serde_json::from_str(r#"{"t":"screen_response","d":{"timestamp":7}}"#).unwrap();
+        let mut expected = fixtures::screens().swap_remove(0);
+        expected.d.timestamp = 7;
+        assert_eq!(serde_json::to_value(&screen).unwrap(), serde_json::to_value(&expected).unwrap());
+        assert!(serde_json::from_str::<ScreenResponse>(r#"{"t":"screen_response","d":{}}"#).is_err());
+
+        // 元組以陣列編碼
+        let entity = serde_json::to_value(&fixtures::network_entities()[1]).unwrap();
+        assert_eq!(entity, serde_json::json!({ "id": 2, "entity_type": "projectile", "position": [5.0, 5.0], "health": null, "state": "flying", "debug_label": null }));
+    }
+
+    #[tokio::test]
+    async fn fixture_messages_are_accepted_by_the_handler() {
+        let handler = MqttHandler::new();
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        for message in fixtures::player_messages() {
+            let payload = WireFormat::Json.encode(&message).unwrap();
+            handler.route_message("td/p1/send", &payload, &mut state).await.unwrap();
+        }
+        for screen in fixtures::screens() {
+            let payload = WireFormat::Json.encode(&screen).unwrap();
+            handler.route_message("td/p1/screen_response", &payload, &mut state).await.unwrap();
+        }
+        assert!(state.schema_report.schemas().values().all(|stats| stats.rejected == 0 && stats.extra.is_empty()));
+    }
+}
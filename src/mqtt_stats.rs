@@ -0,0 +1,232 @@
+/// MQTT 流量統計
+///
+/// 收發訊息數、位元組數與解析失敗數以共享的原子計數器記錄，事件循環中複製出的處理器與客戶端看到同一份統計；
+/// 另外依主題統計訊息數、位元組數、解析失敗數與最近幾秒的訊息速率，供 `stats` 命令與終端視圖的網路浮層使用；
+/// 最近收發的訊息內容保留在 TrafficHistory，供異常擷取寫出
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use bytes::Bytes;
+use colored::*;
+
+use crate::recording::{RecordDirection, TrafficHistory};
+
+/// 計算訊息速率的時間窗口
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+/// 單一主題的統計
+#[derive(Debug, Default)]
+struct TopicCounter {
+    messages: u64,
+    bytes: u64,
+    failures: u64,
+    /// 時間窗口內收到訊息的時間
+    recent: VecDeque<Instant>,
+}
+
+impl TopicCounter {
+    fn prune(&mut self, now: Instant) {
+        while self.recent.front().is_some_and(|time| now.duration_since(*time) > RATE_WINDOW) {
+            self.recent.pop_front();
+        }
+    }
+}
+
+/// 在事件循環與客戶端間共享的 MQTT 統計
+#[derive(Debug, Clone, Default)]
+pub struct MqttStats {
+    received: Arc<AtomicU64>,
+    processed: Arc<AtomicU64>,
+    parse_failures: Arc<AtomicU64>,
+    bytes_received: Arc<AtomicU64>,
+    messages_sent: Arc<AtomicU64>,
+    bytes_sent: Arc<AtomicU64>,
+    last_message: Arc<Mutex<Option<SystemTime>>>,
+    topics: Arc<Mutex<BTreeMap<String, TopicCounter>>>,
+    history: TrafficHistory,
+}
+
+/// 單一主題的統計快照
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopicStats {
+    pub topic: String,
+    pub messages: u64,
+    pub bytes: u64,
+    pub failures: u64,
+    /// 最近幾秒的平均訊息速率（則/秒）
+    pub per_sec: f32,
+}
+
+/// 統計快照
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MqttStatsSnapshot {
+    pub received: u64,
+    pub processed: u64,
+    pub parse_failures: u64,
+    pub bytes_received: u64,
+    pub messages_sent: u64,
+    pub bytes_sent: u64,
+    pub last_message: Option<SystemTime>,
+    /// 各主題的統計（速率高的在前）
+    pub topics: Vec<TopicStats>,
+}
+
+impl MqttStats {
+    /// 記錄收到的訊息
+    pub fn record_received(&self, topic: &str, payload: &Bytes) {
+        let bytes = payload.len();
+        self.history.record(RecordDirection::Incoming, topic, payload.clone());
+        self.received.fetch_add(1, Ordering::Relaxed);
+        self.bytes_received.fetch_add(bytes as u64, Ordering::Relaxed);
+        *self.last_message.lock().unwrap() = Some(SystemTime::now());
+        let now = Instant::now();
+        let mut topics = self.topics.lock().unwrap();
+        let counter = topics.entry(topic.to_string()).or_default();
+        counter.messages += 1;
+        counter.bytes += bytes as u64;
+        counter.prune(now);
+        counter.recent.push_back(now);
+    }
+
+    /// 記錄處理成功的訊息
+    pub fn record_processed(&self) {
+        self.processed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 記錄無法解析或處理失敗的訊息
+    pub fn record_failure(&self, topic: &str) {
+        self.parse_failures.fetch_add(1, Ordering::Relaxed);
+        self.topics.lock().unwrap().entry(topic.to_string()).or_default().failures += 1;
+    }
+
+    /// 記錄發佈的訊息
+    pub fn record_sent(&self, topic: &str, payload: &Bytes) {
+        self.history.record(RecordDirection::Outgoing, topic, payload.clone());
+        self.messages_sent.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(payload.len() as u64, Ordering::Relaxed);
+    }
+
+    /// 無法解析或處理失敗的訊息數
+    pub fn parse_failures(&self) -> u64 {
+        self.parse_failures.load(Ordering::Relaxed)
+    }
+
+    /// 最近收發的訊息
+    pub fn history(&self) -> &TrafficHistory {
+        &self.history
+    }
+
+    /// 目前的統計快照
+    pub fn snapshot(&self) -> MqttStatsSnapshot {
+        let now = Instant::now();
+        let mut topics: Vec<TopicStats> = self.topics.lock().unwrap().iter_mut()
+            .map(|(topic, counter)| {
+                counter.prune(now);
+                TopicStats {
+                    topic: topic.clone(),
+                    messages: counter.messages,
+                    bytes: counter.bytes,
+                    failures: counter.failures,
+                    per_sec: counter.recent.len() as f32 / RATE_WINDOW.as_secs_f32(),
+                }
+            })
+            .collect();
+        topics.sort_by(|a, b| b.per_sec.total_cmp(&a.per_sec).then(b.messages.cmp(&a.messages)));
+        MqttStatsSnapshot {
+            received: self.received.load(Ordering::Relaxed),
+            processed: self.processed.load(Ordering::Relaxed),
+            parse_failures: self.parse_failures.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            messages_sent: self.messages_sent.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            last_message: *self.last_message.lock().unwrap(),
+            topics,
+        }
+    }
+}
+
+/// 以 B/KB/MB 顯示位元組數
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1048575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1048576.0),
+    }
+}
+
+impl MqttStatsSnapshot {
+    /// 總覽（收發數量與位元組數）
+    fn summary(&self) -> String {
+        format!("收 {} 則 ({})  送 {} 則 ({})  解析失敗 {}",
+            self.received, format_bytes(self.bytes_received),
+            self.messages_sent, format_bytes(self.bytes_sent), self.parse_failures)
+    }
+
+    /// 網路浮層文字行（最多列出 max_topics 個主題）
+    pub fn overlay_lines(&self, max_topics: usize) -> Vec<String> {
+        let mut lines = vec![format!("MQTT {}", self.summary())];
+        if self.topics.is_empty() {
+            lines.push("尚未收到訊息".to_string());
+        }
+        for topic in self.topics.iter().take(max_topics) {
+            let failures = if topic.failures > 0 { format!("  失敗 {}", topic.failures) } else { String::new() };
+            lines.push(format!("{:>6.1}/s {:>9} {}{}", topic.per_sec, format_bytes(topic.bytes), topic.topic, failures));
+        }
+        lines
+    }
+
+    /// 顯示統計
+    pub fn print(&self) {
+        println!("\n{}", "MQTT 統計:".bright_cyan().bold());
+        println!("{}", "-".repeat(40).bright_black());
+        println!("  收到: {} 則（處理成功 {}，解析失敗 {}），{}",
+            self.received, self.processed.to_string().green(),
+            if self.parse_failures > 0 { self.parse_failures.to_string().red() } else { "0".normal() },
+            format_bytes(self.bytes_received));
+        println!("  發送: {} 則，{}", self.messages_sent, format_bytes(self.bytes_sent));
+        if let Some(last) = self.last_message.and_then(|time| time.elapsed().ok()) {
+            println!("  最後訊息: {:.1} 秒前", last.as_secs_f32());
+        }
+        if self.topics.is_empty() {
+            return;
+        }
+        println!("\n  {:>8} {:>8} {:>10} {:>6}  主題", "則/秒", "則數", "位元組", "失敗");
+        for topic in &self.topics {
+            let failures = if topic.failures > 0 { topic.failures.to_string().red() } else { "0".bright_black() };
+            println!("  {:>8.1} {:>8} {:>10} {:>6}  {}",
+                topic.per_sec, topic.messages, format_bytes(topic.bytes), failures, topic.topic.bright_white());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_counters_and_topics_are_ranked_by_rate() {
+        let stats = MqttStats::default();
+        let handler_copy = stats.clone();
+        for _ in 0..3 {
+            handler_copy.record_received("td/all/res", &Bytes::from(vec![b' '; 100]));
+            handler_copy.record_processed();
+        }
+        handler_copy.record_received("td/p1/send", &Bytes::from(vec![b' '; 2048]));
+        handler_copy.record_failure("td/p1/send");
+        stats.record_sent("td/p1/action", &Bytes::from(vec![b' '; 40]));
+
+        let snapshot = stats.snapshot();
+        assert_eq!((snapshot.received, snapshot.processed, snapshot.parse_failures), (4, 3, 1));
+        assert_eq!((snapshot.bytes_received, snapshot.messages_sent, snapshot.bytes_sent), (2348, 1, 40));
+        assert!(snapshot.last_message.is_some());
+        assert_eq!(snapshot.topics[0].topic, "td/all/res");
+        assert_eq!(snapshot.topics[0].per_sec, 3.0 / RATE_WINDOW.as_secs_f32());
+        assert_eq!(snapshot.topics[1].failures, 1);
+        assert_eq!(stats.history().recent().len(), 5);
+
+        let lines = snapshot.overlay_lines(1);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("2.3 KB"), "{}", lines[0]);
+    }
+}
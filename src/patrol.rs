@@ -0,0 +1,112 @@
+/// 巡邏路線
+///
+/// 互動模式的 `patrol x1,y1 x2,y2 ... [秒數]` 讓英雄沿著路徑點循環行走：以同步回來的位置判斷是否抵達目前的路徑點，
+/// 抵達後才送出前往下一個路徑點的移動命令，走完最後一個點回到第一個點。用來在持續移動下測試後端尋路與視窗更新；
+/// 送出移動後遲遲未抵達（命令遺失或被地形卡住）時會重送同一個目標
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, bail, Result};
+use vek::Vec2;
+
+/// 距離路徑點多近視為抵達（世界單位）
+const ARRIVAL_DISTANCE: f32 = 30.0;
+
+/// 送出移動後多久仍未抵達就重送
+const REISSUE_AFTER: Duration = Duration::from_secs(5);
+
+/// 循環的巡邏路線（座標與送出的移動命令相同座標系）
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatrolRoute {
+    waypoints: Vec<Vec2<f32>>,
+    /// 目前前往的路徑點
+    current: usize,
+    /// 最近一次送出移動的時間（尚未送出時為 None）
+    issued_at: Option<Instant>,
+    /// 走完的圈數
+    pub laps: u64,
+    /// 送出的移動命令數
+    pub moves: u64,
+}
+
+impl PatrolRoute {
+    /// 以至少兩個路徑點建立路線
+    pub fn new(waypoints: Vec<Vec2<f32>>) -> Result<Self> {
+        if waypoints.len() < 2 {
+            bail!("巡邏路線至少需要兩個路徑點");
+        }
+        Ok(Self { waypoints, current: 0, issued_at: None, laps: 0, moves: 0 })
+    }
+
+    /// 解析 `x,y` 形式的路徑點
+    pub fn parse(points: &[&str]) -> Result<Self> {
+        let waypoints = points.iter()
+            .map(|point| {
+                let (x, y) = point.split_once(',').ok_or_else(|| anyhow!("路徑點格式應為 x,y: {}", point))?;
+                Ok(Vec2::new(x.trim().parse()?, y.trim().parse()?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Self::new(waypoints)
+    }
+
+    /// 轉換所有路徑點的座標（例如本地座標轉為後端座標）
+    pub fn map(mut self, transform: impl Fn(Vec2<f32>) -> Vec2<f32>) -> Self {
+        self.waypoints.iter_mut().for_each(|point| *point = transform(*point));
+        self
+    }
+
+    pub fn waypoints(&self) -> &[Vec2<f32>] {
+        &self.waypoints
+    }
+
+    /// 目前前往的路徑點
+    pub fn target(&self) -> Vec2<f32> {
+        self.waypoints[self.current]
+    }
+
+    /// 依目前位置決定是否要送出移動命令，返回要前往的目標
+    pub fn advance(&mut self, position: Vec2<f32>, now: Instant) -> Option<Vec2<f32>> {
+        let Some(issued_at) = self.issued_at else {
+            return Some(self.issue(now));
+        };
+        if position.distance(self.target()) <= ARRIVAL_DISTANCE {
+            self.current = (self.current + 1) % self.waypoints.len();
+            if self.current == 0 {
+                self.laps += 1;
+            }
+            return Some(self.issue(now));
+        }
+        if now.duration_since(issued_at) >= REISSUE_AFTER {
+            log::debug!("巡邏目標 ({:.1}, {:.1}) 遲遲未抵達，重送移動", self.target().x, self.target().y);
+            return Some(self.issue(now));
+        }
+        None
+    }
+
+    fn issue(&mut self, now: Instant) -> Vec2<f32> {
+        self.issued_at = Some(now);
+        self.moves += 1;
+        self.target()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walks_the_loop_as_waypoints_are_reached() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut route = PatrolRoute::parse(&["100,100", "300, 100", "300,300"]).unwrap();
+
+        assert_eq!(route.advance(Vec2::new(0.0, 0.0), at(0)), Some(Vec2::new(100.0, 100.0)));
+        assert_eq!(route.advance(Vec2::new(50.0, 50.0), at(100)), None, "還沒抵達");
+        assert_eq!(route.advance(Vec2::new(90.0, 95.0), at(200)), Some(Vec2::new(300.0, 100.0)));
+        assert_eq!(route.advance(Vec2::new(200.0, 100.0), at(6000)), Some(Vec2::new(300.0, 100.0)), "逾時重送");
+        assert_eq!(route.advance(Vec2::new(300.0, 100.0), at(6100)), Some(Vec2::new(300.0, 300.0)));
+        assert_eq!(route.advance(Vec2::new(300.0, 300.0), at(6200)), Some(Vec2::new(100.0, 100.0)));
+        assert_eq!((route.laps, route.moves), (1, 5));
+
+        assert!(PatrolRoute::parse(&["100,100"]).is_err());
+        assert!(PatrolRoute::parse(&["100,100", "300"]).is_err());
+    }
+}
@@ -0,0 +1,116 @@
+/// MQTT 負載的編碼偵測
+///
+/// 收到的負載一律以原始位元組保存，不再轉成文字：先依內容判斷是 JSON、一般文字或二進位，
+/// 二進位負載依頻道交給 `[server] binary_decoders` 設定的解碼器（目前為 msgpack，之後的 protobuf 訊息也從這裡接入），
+/// 日誌中以十六進位預覽顯示，不會再因為 `from_utf8_lossy` 把二進位內容換成替代字元
+use std::collections::HashMap;
+use anyhow::Result;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::mqtt_handler::WireFormat;
+
+/// 日誌中預覽的位元組數
+const PREVIEW_BYTES: usize = 16;
+
+/// 依內容判斷的負載種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadKind {
+    /// 合法 UTF-8 且以 `{` 或 `[` 開頭
+    Json,
+    /// 其他合法 UTF-8
+    Text,
+    /// 非 UTF-8 的二進位內容
+    Binary,
+}
+
+impl PayloadKind {
+    /// 判斷負載種類
+    pub fn detect(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) if matches!(text.trim_start().chars().next(), Some('{' | '[')) => PayloadKind::Json,
+            Ok(_) => PayloadKind::Text,
+            Err(_) => PayloadKind::Binary,
+        }
+    }
+}
+
+/// 保留原始位元組的負載
+#[derive(Debug, Clone)]
+pub struct Payload {
+    bytes: Bytes,
+    kind: PayloadKind,
+}
+
+impl Payload {
+    pub fn new(bytes: Bytes) -> Self {
+        let kind = PayloadKind::detect(&bytes);
+        Self { bytes, kind }
+    }
+
+    pub fn kind(&self) -> PayloadKind {
+        self.kind
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// 二進位內容的日誌預覽，例如 `<5 位元組的二進位資料: 0a 1b 2c 3d 4e>`
+pub fn hex_preview(bytes: &[u8]) -> String {
+    let hex: Vec<String> = bytes.iter().take(PREVIEW_BYTES).map(|byte| format!("{:02x}", byte)).collect();
+    let more = if bytes.len() > PREVIEW_BYTES { " …" } else { "" };
+    format!("<{} 位元組的二進位資料: {}{}>", bytes.len(), hex.join(" "), more)
+}
+
+/// 二進位負載的解碼器
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BinaryCodec {
+    /// MessagePack（需啟用 msgpack 功能）
+    #[serde(rename = "msgpack")]
+    MessagePack,
+}
+
+impl BinaryCodec {
+    /// 解碼成與 JSON 訊息相同結構的值
+    pub fn decode(&self, bytes: &[u8]) -> Result<serde_json::Value> {
+        match self {
+            BinaryCodec::MessagePack => WireFormat::MessagePack.decode(bytes),
+        }
+    }
+}
+
+/// 各頻道的二進位解碼器（頻道為玩家主題的最後一段、廣播主題為 `broadcast`，其餘主題以完整主題比對）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BinaryDecoders(HashMap<String, BinaryCodec>);
+
+impl BinaryDecoders {
+    /// 頻道對應的解碼器
+    pub fn get(&self, channel: &str) -> Option<BinaryCodec> {
+        self.0.get(channel).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_binary_bytes_and_detects_the_encoding() {
+        assert_eq!(PayloadKind::detect(b" {\"t\": \"hero\"}"), PayloadKind::Json);
+        assert_eq!(PayloadKind::detect(b"[1, 2]"), PayloadKind::Json);
+        assert_eq!(PayloadKind::detect("離線".as_bytes()), PayloadKind::Text);
+
+        let binary = Payload::new(Bytes::from_static(&[0x81, 0xa1, b't', 0xff, 0x00]));
+        assert_eq!(binary.kind(), PayloadKind::Binary);
+        assert_eq!(binary.bytes(), &[0x81, 0xa1, b't', 0xff, 0x00]);
+        assert_eq!(hex_preview(binary.bytes()), "<5 位元組的二進位資料: 81 a1 74 ff 00>");
+        assert!(hex_preview(&[0u8; 20]).ends_with(" …>"));
+
+        let decoders: BinaryDecoders = toml::from_str("screen_response = \"msgpack\"").unwrap();
+        assert_eq!(decoders.get("screen_response"), Some(BinaryCodec::MessagePack));
+        assert_eq!(decoders.get("send"), None);
+    }
+}
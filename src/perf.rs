@@ -0,0 +1,146 @@
+/// 效能門檻
+///
+/// 執行演示序列時收集操作確認延遲（`[rpc] track_actions` 開啟時玩家操作帶上請求 ID，後端回應即為確認）、
+/// screen_response 的大小與每幀更新（tick）的耗時；演示序列的 `[performance]` 設定各項的上限，
+/// 超過時與失敗的步驟一樣讓演示不通過，效能退化也能在測試中被擋下
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use serde::Deserialize;
+
+use crate::demo::StepOutcome;
+
+/// 每種指標保留的樣本數
+const WINDOW: usize = 1024;
+
+/// 固定大小的樣本窗口
+#[derive(Debug, Clone, Default)]
+pub struct SampleWindow {
+    samples: VecDeque<f64>,
+}
+
+impl SampleWindow {
+    /// 加入樣本（超過窗口時丟棄最舊的）
+    pub fn push(&mut self, value: f64) {
+        if self.samples.len() == WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(value);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// 百分位數（最近秩法，percentile 介於 0 到 100；沒有樣本時為 None）
+    pub fn percentile(&self, percentile: f64) -> Option<f64> {
+        let mut sorted: Vec<f64> = self.samples.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+        sorted.get(rank.clamp(1, sorted.len().max(1)) - 1).copied()
+    }
+
+    /// 最大值
+    pub fn max(&self) -> Option<f64> {
+        self.samples.iter().copied().reduce(f64::max)
+    }
+}
+
+/// 收集到的效能指標
+#[derive(Debug, Clone, Default)]
+pub struct PerfMetrics {
+    /// 操作送出到後端確認的毫秒數
+    pub action_ack_ms: SampleWindow,
+    /// screen_response 負載大小（KB）
+    pub screen_response_kb: SampleWindow,
+    /// 每幀更新耗時（毫秒）
+    pub frame_time_ms: SampleWindow,
+}
+
+impl PerfMetrics {
+    pub fn record_action_ack(&mut self, latency: Duration) {
+        self.action_ack_ms.push(latency.as_secs_f64() * 1000.0);
+    }
+
+    pub fn record_screen_response(&mut self, bytes: usize) {
+        self.screen_response_kb.push(bytes as f64 / 1024.0);
+    }
+
+    pub fn record_frame(&mut self, elapsed: Duration) {
+        self.frame_time_ms.push(elapsed.as_secs_f64() * 1000.0);
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// 在事件循環、請求表與客戶端間共享的效能指標
+pub type SharedPerf = Arc<Mutex<PerfMetrics>>;
+
+/// 演示序列的效能門檻（未設置的項目不檢查）
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PerfGates {
+    /// 操作確認延遲的 p99 上限（毫秒）
+    #[serde(default)]
+    pub action_ack_p99_ms: Option<f64>,
+    /// screen_response 大小的上限（KB）
+    #[serde(default)]
+    pub screen_response_max_kb: Option<f64>,
+    /// 每幀更新耗時的 p99 上限（毫秒）
+    #[serde(default)]
+    pub frame_time_p99_ms: Option<f64>,
+}
+
+impl PerfGates {
+    /// 以收集到的指標檢查各門檻（沒有樣本的門檻視為未通過）
+    pub fn evaluate(&self, metrics: &PerfMetrics) -> Vec<StepOutcome> {
+        let gates = [
+            ("操作確認 p99", self.action_ack_p99_ms, &metrics.action_ack_ms, metrics.action_ack_ms.percentile(99.0), "ms"),
+            ("screen_response 最大", self.screen_response_max_kb, &metrics.screen_response_kb, metrics.screen_response_kb.max(), "KB"),
+            ("每幀耗時 p99", self.frame_time_p99_ms, &metrics.frame_time_ms, metrics.frame_time_ms.percentile(99.0), "ms"),
+        ];
+        gates.into_iter()
+            .filter_map(|(name, limit, window, value, unit)| {
+                let limit = limit?;
+                let name = format!("{} < {}{}", name, limit, unit);
+                Some(match value {
+                    Some(value) => StepOutcome {
+                        name,
+                        passed: value < limit,
+                        detail: format!("實際 {:.1}{}（{} 個樣本）", value, unit, window.len()),
+                    },
+                    None => StepOutcome { name, passed: false, detail: "沒有收集到樣本".to_string() },
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluates_gates_from_collected_samples() {
+        let mut metrics = PerfMetrics::default();
+        for ms in 1..=100 {
+            metrics.record_action_ack(Duration::from_millis(ms));
+            metrics.record_frame(Duration::from_millis(ms % 10));
+        }
+        metrics.record_screen_response(80 * 1024);
+        assert_eq!(metrics.action_ack_ms.percentile(99.0), Some(99.0));
+        assert_eq!(metrics.action_ack_ms.percentile(50.0), Some(50.0));
+
+        let gates: PerfGates = toml::from_str("action_ack_p99_ms = 120\nscreen_response_max_kb = 64").unwrap();
+        let outcomes = gates.evaluate(&metrics);
+        assert_eq!(outcomes.iter().map(|outcome| outcome.passed).collect::<Vec<_>>(), vec![true, false]);
+        assert!(outcomes[1].detail.starts_with("實際 80.0KB"));
+
+        metrics.clear();
+        let frame = PerfGates { frame_time_p99_ms: Some(16.0), ..PerfGates::default() }.evaluate(&metrics);
+        assert_eq!(frame[0].detail, "沒有收集到樣本");
+        assert!(!frame[0].passed);
+    }
+}
@@ -3,7 +3,8 @@
 /// 模擬真實玩家的遊戲操作行為
 use serde::{Deserialize, Serialize};
 use serde_json;
-use rand::{thread_rng, Rng};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use log::{info, debug};
 use anyhow::Result;
 use vek::Vec2;
@@ -15,7 +16,9 @@ pub struct PlayerSimulator {
     pub hero_type: String,
     pub current_position: Vec2<f32>,
     pub action_history: Vec<PlayerAction>,
-    pub auto_mode_enabled: bool,
+    /// 隨機操作的種子（以 --seed 指定時可重現同一串操作）
+    pub seed: u64,
+    rng: StdRng,
 }
 
 /// 玩家操作記錄
@@ -42,6 +45,9 @@ pub struct CastAbilityParams {
     pub target_position: Option<(f32, f32)>,
     pub target_entity: Option<u32>,
     pub level: Option<u8>,
+    /// 方向技能的單位方向向量
+    #[serde(default)]
+    pub direction: Option<(f32, f32)>,
 }
 
 /// 攻擊參數
@@ -52,19 +58,33 @@ pub struct AttackParams {
 }
 
 impl PlayerSimulator {
-    /// 創建新的玩家模擬器
+    /// 創建新的玩家模擬器（隨機種子）
     pub fn new(player_name: String, hero_type: String) -> Self {
         info!("創建玩家模擬器 - 玩家: {}, 英雄: {}", player_name, hero_type);
+        let seed = rand::random();
         
         Self {
             player_name,
             hero_type,
             current_position: Vec2::new(400.0, 300.0), // 預設起始位置
             action_history: Vec::new(),
-            auto_mode_enabled: false,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
         }
     }
     
+    /// 以指定種子重新開始隨機操作序列
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+    
+    /// 隨機操作使用的亂數產生器（擬人化的延遲與抖動也取自這裡，整串操作才能重現）
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+    
     /// 執行玩家操作
     pub async fn perform_action(&mut self, action: &str, params: serde_json::Value) -> Result<serde_json::Value> {
         debug!("執行玩家操作: {} - 參數: {}", action, params);
@@ -137,18 +157,30 @@ impl PlayerSimulator {
             return Err(anyhow::anyhow!("技能 {} 不屬於英雄 {}", cast_params.ability_id, self.hero_type));
         }
         
-        // 計算施法位置
-        let cast_position = cast_params.target_position
-            .unwrap_or((self.current_position.x, self.current_position.y));
+        // 依英雄目錄的施放距離檢查（0 為不限）
+        let range = crate::hero_catalog::registry().ability(&cast_params.ability_id).map_or(0.0, |ability| ability.range);
+        
+        // 計算施法位置（方向技能為沿方向到施放距離的終點）
+        let cast_position = match cast_params.direction {
+            Some((dx, dy)) => {
+                let end = self.current_position + Vec2::new(dx, dy) * range;
+                (end.x, end.y)
+            }
+            None => cast_params.target_position.unwrap_or((self.current_position.x, self.current_position.y)),
+        };
+        let distance = (Vec2::new(cast_position.0, cast_position.1) - self.current_position).magnitude();
+        let in_range = range <= 0.0 || distance <= range;
         
-        debug!("玩家 {} 施放技能: {} 在位置 ({:.1}, {:.1})", 
-               self.player_name, cast_params.ability_id, cast_position.0, cast_position.1);
+        debug!("玩家 {} 施放技能: {} 在位置 ({:.1}, {:.1}) - 距離: {:.1}, 範圍內: {}", 
+               self.player_name, cast_params.ability_id, cast_position.0, cast_position.1, distance, in_range);
         
         Ok(serde_json::json!({
             "ability_id": cast_params.ability_id,
             "level": cast_params.level.unwrap_or(1),
             "cast_position": cast_position,
             "target_entity": cast_params.target_entity,
+            "direction": cast_params.direction,
+            "in_range": in_range,
             "success": true
         }))
     }
@@ -195,90 +227,9 @@ impl PlayerSimulator {
         }))
     }
     
-    /// 生成隨機操作（自動遊戲模式）
-    pub fn generate_random_action(&self) -> Option<(String, serde_json::Value)> {
-        if !self.auto_mode_enabled {
-            return None;
-        }
-        
-        let mut rng = thread_rng();
-        let action_type = rng.gen_range(0..4);
-        
-        match action_type {
-            0 => {
-                // 隨機移動
-                let target_x = self.current_position.x + rng.gen_range(-100.0..100.0);
-                let target_y = self.current_position.y + rng.gen_range(-100.0..100.0);
-                
-                Some(("move".to_string(), serde_json::json!({
-                    "target_x": target_x.max(0.0).min(800.0),
-                    "target_y": target_y.max(0.0).min(600.0)
-                })))
-            },
-            1 => {
-                // 隨機施放技能
-                let abilities = self.get_hero_abilities();
-                if !abilities.is_empty() {
-                    let ability = &abilities[rng.gen_range(0..abilities.len())];
-                    
-                    Some(("cast_ability".to_string(), serde_json::json!({
-                        "ability_id": ability,
-                        "target_position": [
-                            self.current_position.x + rng.gen_range(-50.0..50.0),
-                            self.current_position.y + rng.gen_range(-50.0..50.0)
-                        ],
-                        "level": 1
-                    })))
-                } else {
-                    None
-                }
-            },
-            2 => {
-                // 隨機攻擊
-                let target_x = self.current_position.x + rng.gen_range(-80.0..80.0);
-                let target_y = self.current_position.y + rng.gen_range(-80.0..80.0);
-                
-                Some(("attack".to_string(), serde_json::json!({
-                    "target_position": [target_x, target_y],
-                    "attack_type": "basic"
-                })))
-            },
-            _ => {
-                // 等待（無操作）
-                None
-            }
-        }
-    }
-    
-    /// 驗證技能是否有效
+    /// 驗證技能是否屬於當前英雄
     fn is_ability_valid(&self, ability_id: &str) -> bool {
-        let hero_abilities = self.get_hero_abilities();
-        hero_abilities.contains(&ability_id.to_string())
-    }
-    
-    /// 獲取英雄技能列表
-    fn get_hero_abilities(&self) -> Vec<String> {
-        match self.hero_type.as_str() {
-            "saika_magoichi" => vec![
-                "sniper_mode".to_string(),
-                "saika_reinforcements".to_string(),
-                "rain_iron_cannon".to_string(),
-                "three_stage_technique".to_string(),
-            ],
-            "date_masamune" => vec![
-                "flame_blade".to_string(),
-                "fire_dash".to_string(),
-                "flame_assault".to_string(),
-                "matchlock_gun".to_string(),
-            ],
-            _ => vec![]
-        }
-    }
-    
-    /// 設置自動模式
-    pub fn set_auto_mode(&mut self, enabled: bool) {
-        self.auto_mode_enabled = enabled;
-        info!("玩家 {} 自動模式: {}", self.player_name, if enabled { "開啟" } else { "關閉" });
+        crate::hero_catalog::find_hero(&self.hero_type).is_some_and(|hero| hero.ability(ability_id).is_some())
     }
     
     /// 獲取操作歷史統計
@@ -292,52 +243,37 @@ impl PlayerSimulator {
         serde_json::json!({
             "total_actions": self.action_history.len(),
             "action_counts": stats,
-            "current_position": [self.current_position.x, self.current_position.y],
-            "auto_mode": self.auto_mode_enabled
+            "current_position": [self.current_position.x, self.current_position.y]
         })
     }
     
-    /// 執行預設操作序列（用於演示）
-    pub fn get_demo_sequence(&self) -> Vec<(String, serde_json::Value)> {
-        let abilities = self.get_hero_abilities();
-        let mut sequence = Vec::new();
-        
-        // 移動序列
-        sequence.push(("move".to_string(), serde_json::json!({
-            "target_x": 300.0,
-            "target_y": 200.0
-        })));
-        
-        // 施放第一個技能
-        if !abilities.is_empty() {
-            sequence.push(("cast_ability".to_string(), serde_json::json!({
-                "ability_id": abilities[0],
-                "target_position": [350.0, 250.0],
-                "level": 1
-            })));
-        }
-        
-        // 攻擊
-        sequence.push(("attack".to_string(), serde_json::json!({
-            "target_position": [400.0, 300.0],
-            "attack_type": "basic"
-        })));
-        
-        // 移動到新位置
-        sequence.push(("move".to_string(), serde_json::json!({
-            "target_x": 500.0,
-            "target_y": 400.0
-        })));
-        
-        // 如果有召喚技能，施放它
-        if abilities.contains(&"saika_reinforcements".to_string()) {
-            sequence.push(("cast_ability".to_string(), serde_json::json!({
-                "ability_id": "saika_reinforcements",
-                "target_position": [450.0, 350.0],
-                "level": 1
-            })));
-        }
-        
-        sequence
+    /// 匯出操作歷史與種子（自動遊戲出錯時以同一個種子重現）
+    pub fn export_history(&self) -> serde_json::Value {
+        serde_json::json!({
+            "seed": self.seed,
+            "player_name": self.player_name,
+            "hero_type": self.hero_type,
+            "actions": self.action_history,
+        })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_generates_same_actions() {
+        let tree = crate::behavior::BehaviorTree::default();
+        let state = crate::game_state::GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        let actions = |seed: u64| {
+            let mut simulator = PlayerSimulator::new("p1".to_string(), "saika_magoichi".to_string()).with_seed(seed);
+            (0..20).map(|_| tree.decide(&state, Vec2::zero(), simulator.rng())).collect::<Vec<_>>()
+        };
+        assert_eq!(actions(42), actions(42));
+        assert_ne!(actions(42), actions(43));
+
+        let simulator = PlayerSimulator::new("p1".to_string(), "saika_magoichi".to_string()).with_seed(7);
+        assert_eq!(simulator.export_history()["seed"], 7);
+    }
+}
@@ -0,0 +1,162 @@
+/// 本地移動預測
+///
+/// 未啟用時本地英雄只在伺服器狀態同步時跳到伺服器位置。設置 `[prediction] enabled = true` 後，
+/// 送出移動命令時記下目標，每幀沿著移動方向以 `move_speed` 推進預測位置；收到新的伺服器位置時以它為新的起點，
+/// 預測與伺服器的差距不直接跳過去，而是作為校正偏移在 `smoothing_ms` 內逐漸消化（差距超過 `snap_distance` 時直接對齊）。
+/// 斷線時的會話報告列出校正次數與平均、最大誤差，開關預測各跑一次即可比較兩者的畫面與誤差
+use std::time::Instant;
+use vek::Vec2;
+
+use crate::config::PredictionConfig;
+
+/// 校正誤差統計
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PredictionStats {
+    /// 收到的伺服器位置數
+    pub corrections: u64,
+    /// 差距過大直接對齊的次數
+    pub snaps: u64,
+    /// 預測與伺服器位置的誤差總和
+    pub total_error: f32,
+    pub max_error: f32,
+}
+
+impl PredictionStats {
+    pub fn average_error(&self) -> Option<f32> {
+        (self.corrections > 0).then(|| self.total_error / self.corrections as f32)
+    }
+}
+
+/// 本地英雄的移動預測
+#[derive(Debug, Clone)]
+pub struct MovementPredictor {
+    config: PredictionConfig,
+    /// 不含校正偏移的預測位置
+    base: Option<Vec2<f32>>,
+    /// 進行中的移動目標
+    target: Option<Vec2<f32>>,
+    /// 收到伺服器位置時的校正偏移，在 smoothing_ms 內線性消化（顯示位置 = base + 剩餘偏移）
+    correction: Vec2<f32>,
+    corrected_at: Option<Instant>,
+    /// 上次看到的伺服器位置
+    last_server: Option<Vec2<f32>>,
+    last_frame: Option<Instant>,
+    stats: PredictionStats,
+}
+
+impl MovementPredictor {
+    pub fn new(config: PredictionConfig) -> Self {
+        Self {
+            config,
+            base: None,
+            target: None,
+            correction: Vec2::zero(),
+            corrected_at: None,
+            last_server: None,
+            last_frame: None,
+            stats: PredictionStats::default(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// 記錄送出的移動命令
+    pub fn issue_move(&mut self, target: Vec2<f32>) {
+        self.target = Some(target);
+    }
+    /// 尚未消化的校正偏移
+    fn offset(&self, now: Instant) -> Vec2<f32> {
+        let Some(corrected_at) = self.corrected_at else {
+            return Vec2::zero();
+        };
+        let smoothing = self.config.smoothing_ms as f32 / 1000.0;
+        let age = now.duration_since(corrected_at).as_secs_f32();
+        if smoothing <= 0.0 || age >= smoothing {
+            return Vec2::zero();
+        }
+        self.correction * (1.0 - age / smoothing)
+    }
+
+    /// 以本幀同步到的伺服器位置更新預測，返回要顯示的位置
+    pub fn frame(&mut self, server: Vec2<f32>, now: Instant) -> Vec2<f32> {
+        let elapsed = self.last_frame.map_or(0.0, |last| now.duration_since(last).as_secs_f32());
+        self.last_frame = Some(now);
+
+        if self.last_server != Some(server) {
+            // 新的伺服器位置：以它為起點，差距轉為校正偏移
+            let shown = self.base.map_or(server, |base| base + self.offset(now));
+            let error = shown.distance(server);
+            if self.last_server.is_some() {
+                self.stats.corrections += 1;
+                self.stats.total_error += error;
+                self.stats.max_error = self.stats.max_error.max(error);
+            }
+            self.correction = if error > self.config.snap_distance {
+                if self.last_server.is_some() {
+                    self.stats.snaps += 1;
+                }
+                Vec2::zero()
+            } else {
+                shown - server
+            };
+            self.corrected_at = Some(now);
+            self.base = Some(server);
+            self.last_server = Some(server);
+        }
+
+        let mut base = self.base.unwrap_or(server);
+        if let Some(target) = self.target {
+            let step = self.config.move_speed * elapsed;
+            let remaining = target - base;
+            if remaining.magnitude() <= step {
+                base = target;
+                self.target = None;
+            } else {
+                base += remaining.normalized() * step;
+            }
+        }
+        self.base = Some(base);
+        base + self.offset(now)
+    }
+
+    pub fn stats(&self) -> &PredictionStats {
+        &self.stats
+    }
+
+    /// 清除預測（例如重新進入遊戲或回城傳送）
+    pub fn reset(&mut self) {
+        *self = Self::new(self.config.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn advances_along_the_move_and_smooths_corrections() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let config = PredictionConfig { enabled: true, move_speed: 100.0, smoothing_ms: 200, snap_distance: 50.0 };
+        let mut predictor = MovementPredictor::new(config);
+
+        assert_eq!(predictor.frame(Vec2::new(0.0, 0.0), at(0)), Vec2::new(0.0, 0.0));
+        predictor.issue_move(Vec2::new(100.0, 0.0));
+        assert_eq!(predictor.frame(Vec2::new(0.0, 0.0), at(500)), Vec2::new(50.0, 0.0), "伺服器位置未變時沿移動方向推進");
+
+        // 伺服器位置落後 10：顯示位置不跳動，校正偏移在 200ms 內消化
+        assert_eq!(predictor.frame(Vec2::new(40.0, 0.0), at(500)), Vec2::new(50.0, 0.0));
+        let halfway = predictor.frame(Vec2::new(40.0, 0.0), at(600));
+        assert!((halfway.x - 55.0).abs() < 0.01, "{:?}", halfway);
+        assert_eq!(predictor.frame(Vec2::new(40.0, 0.0), at(700)), Vec2::new(60.0, 0.0));
+        assert_eq!(predictor.frame(Vec2::new(40.0, 0.0), at(1500)), Vec2::new(100.0, 0.0), "抵達目標後停止");
+
+        assert_eq!(predictor.frame(Vec2::new(300.0, 0.0), at(1600)), Vec2::new(300.0, 0.0), "差距過大直接對齊");
+        let stats = predictor.stats();
+        assert_eq!((stats.corrections, stats.snaps, stats.max_error), (2, 1, 200.0));
+        assert_eq!(stats.average_error(), Some(105.0));
+    }
+}
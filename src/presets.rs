@@ -0,0 +1,121 @@
+/// 設定預設
+///
+/// 把英雄、出裝、按鍵配置、施法模式與鏡頭設定打包成具名預設（例如 `preset save aggressive-saika`），
+/// 以 TOML 檔案儲存，可在執行中切換，也可以直接複製給其他測試人員使用
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use anyhow::{Result, Context};
+
+use crate::config::{CameraConfig, InputConfig};
+
+/// 預設檔案
+pub const DEFAULT_PRESET_FILE: &str = "presets.toml";
+
+/// 單一預設
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    /// 英雄 ID
+    pub hero_type: String,
+    /// 購物行程購買的物品
+    #[serde(default)]
+    pub shop_items: Vec<String>,
+    /// 按鍵配置與施法模式
+    #[serde(default)]
+    pub input: InputConfig,
+    /// 鏡頭設定
+    #[serde(default)]
+    pub camera: CameraConfig,
+}
+
+/// 預設檔案內容
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PresetFile {
+    #[serde(default)]
+    presets: BTreeMap<String, Preset>,
+}
+
+/// 預設儲存
+#[derive(Debug, Clone)]
+pub struct PresetStore {
+    path: PathBuf,
+    presets: BTreeMap<String, Preset>,
+}
+
+impl PresetStore {
+    /// 從檔案載入預設（檔案不存在時為空）
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let presets = if path.exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("無法讀取預設檔案: {:?}", path))?;
+            toml::from_str::<PresetFile>(&content)
+                .with_context(|| format!("無法解析預設檔案: {:?}", path))?
+                .presets
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self { path, presets })
+    }
+
+    /// 創建空的預設儲存
+    pub fn empty(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), presets: BTreeMap::new() }
+    }
+
+    /// 所有預設（依名稱排序）
+    pub fn presets(&self) -> &BTreeMap<String, Preset> {
+        &self.presets
+    }
+
+    /// 依名稱查找預設
+    pub fn get(&self, name: &str) -> Option<&Preset> {
+        self.presets.get(name)
+    }
+
+    /// 保存預設（同名預設會被覆寫）並寫回檔案
+    pub fn save(&mut self, name: &str, preset: Preset) -> Result<()> {
+        self.presets.insert(name.to_string(), preset);
+        self.write()
+    }
+
+    /// 刪除預設並寫回檔案，返回是否存在
+    pub fn remove(&mut self, name: &str) -> Result<bool> {
+        let existed = self.presets.remove(name).is_some();
+        if existed {
+            self.write()?;
+        }
+        Ok(existed)
+    }
+
+    fn write(&self) -> Result<()> {
+        let file = PresetFile { presets: self.presets.clone() };
+        let content = toml::to_string_pretty(&file)?;
+        std::fs::write(&self.path, content)
+            .with_context(|| format!("無法寫入預設檔案: {:?}", self.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CastMode;
+
+    #[test]
+    fn preset_round_trips_through_toml() {
+        let mut input = InputConfig::default();
+        input.keymap.abilities = vec!['a', 's', 'd', 'f'];
+        input.cast_mode = CastMode::Quick;
+        let mut file = PresetFile::default();
+        file.presets.insert("aggressive-saika".to_string(), Preset {
+            hero_type: "saika_magoichi".to_string(),
+            shop_items: vec!["health_potion".to_string()],
+            input,
+            camera: CameraConfig::default(),
+        });
+
+        let content = toml::to_string_pretty(&file).unwrap();
+        let parsed: PresetFile = toml::from_str(&content).unwrap();
+        assert_eq!(parsed.presets, file.presets);
+    }
+}
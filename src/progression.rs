@@ -0,0 +1,127 @@
+/// 金錢、經驗與等級成長
+///
+/// 後端以 `td/{player_name}/send` 送出 `{"t": "gold", "d": {"gold": 1250, "reason": "creep"}}`（目前金錢總額與變動原因）
+/// 與 `{"t": "experience", "d": {"experience": 340, "level": 3, "next_level": 600}}`（累積經驗、等級與升到下一級所需的累積經驗）。
+/// 金錢總額的變化依原因累計為獲得或花費（第一則只作為基準），等級提升也一併記錄，
+/// 終端視圖底部的狀態列與互動模式的 `status` 顯示等級、經驗條與金錢
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+
+/// 未帶原因的金錢變動
+const UNKNOWN_REASON: &str = "other";
+
+/// 金錢訊息
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GoldData {
+    /// 目前金錢總額
+    pub gold: u32,
+    /// 變動原因（例如 creep、kill、passive、buy_item）
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// 經驗訊息
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ExperienceData {
+    /// 累積經驗
+    pub experience: u32,
+    pub level: u8,
+    /// 升到下一級所需的累積經驗（已滿級時不提供）
+    #[serde(default)]
+    pub next_level: Option<u32>,
+}
+
+/// 本地英雄的金錢收支與升級記錄
+#[derive(Debug, Clone, Default)]
+pub struct Progression {
+    /// 上一則金錢訊息的總額（尚未收到時為 None）
+    last_gold: Option<u32>,
+    /// 依原因累計的獲得金錢
+    pub earned: BTreeMap<String, u64>,
+    /// 依原因累計的花費金錢
+    pub spent: BTreeMap<String, u64>,
+    /// 每次升級的新等級與時間
+    pub level_ups: Vec<(u8, SystemTime)>,
+}
+
+impl Progression {
+    /// 記錄金錢總額，返回相對上一則的變動
+    pub fn record_gold(&mut self, gold: &GoldData) -> i64 {
+        let change = self.last_gold.map_or(0, |last| gold.gold as i64 - last as i64);
+        self.last_gold = Some(gold.gold);
+        let reason = gold.reason.clone().unwrap_or_else(|| UNKNOWN_REASON.to_string());
+        if change > 0 {
+            *self.earned.entry(reason).or_default() += change as u64;
+        } else if change < 0 {
+            *self.spent.entry(reason).or_default() += change.unsigned_abs();
+        }
+        change
+    }
+
+    /// 記錄升級
+    pub fn record_level(&mut self, previous: u8, level: u8, at: SystemTime) {
+        if level > previous {
+            self.level_ups.push((level, at));
+        }
+    }
+
+    pub fn total_earned(&self) -> u64 {
+        self.earned.values().sum()
+    }
+
+    pub fn total_spent(&self) -> u64 {
+        self.spent.values().sum()
+    }
+
+    /// 依原因列出收支（例如 `creep +120、buy_item -500`）
+    pub fn breakdown(&self) -> String {
+        let earned = self.earned.iter().map(|(reason, amount)| format!("{} +{}", reason, amount));
+        let spent = self.spent.iter().map(|(reason, amount)| format!("{} -{}", reason, amount));
+        earned.chain(spent).collect::<Vec<_>>().join("、")
+    }
+}
+
+/// 等級與經驗條（例如 `Lv 3 [██████░░░░] 340/600`，不知道下一級所需經驗時只顯示累積經驗）
+pub fn level_bar(level: u8, experience: u32, next_level: Option<u32>, width: usize) -> String {
+    match next_level.filter(|next| *next > 0) {
+        Some(next) => {
+            let filled = ((experience as f32 / next as f32).clamp(0.0, 1.0) * width as f32).round() as usize;
+            format!("Lv {} [{}{}] {}/{}", level, "█".repeat(filled), "░".repeat(width - filled), experience, next)
+        }
+        None => format!("Lv {} {} XP", level, experience),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gold(gold: u32, reason: Option<&str>) -> GoldData {
+        GoldData { gold, reason: reason.map(str::to_string) }
+    }
+
+    #[test]
+    fn tallies_gold_changes_by_reason_and_draws_the_level_bar() {
+        let mut progression = Progression::default();
+        assert_eq!(progression.record_gold(&gold(600, None)), 0, "第一則只作為基準");
+        assert_eq!(progression.record_gold(&gold(640, Some("creep"))), 40);
+        assert_eq!(progression.record_gold(&gold(940, Some("kill"))), 300);
+        assert_eq!(progression.record_gold(&gold(440, Some("buy_item"))), -500);
+        progression.record_gold(&gold(460, None));
+
+        assert_eq!((progression.total_earned(), progression.total_spent()), (360, 500));
+        assert_eq!(progression.breakdown(), "creep +40、kill +300、other +20、buy_item -500");
+
+        progression.record_level(1, 2, SystemTime::UNIX_EPOCH);
+        progression.record_level(2, 2, SystemTime::UNIX_EPOCH);
+        assert_eq!(progression.level_ups.len(), 1);
+
+        assert_eq!(level_bar(3, 300, Some(600), 10), "Lv 3 [█████░░░░░] 300/600");
+        assert_eq!(level_bar(3, 900, Some(600), 4), "Lv 3 [████] 900/600");
+        assert_eq!(level_bar(18, 9000, None, 10), "Lv 18 9000 XP");
+
+        let data: ExperienceData = serde_json::from_str(r#"{"experience": 340, "level": 3}"#).unwrap();
+        assert_eq!(data.next_level, None);
+    }
+}
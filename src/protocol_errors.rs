@@ -0,0 +1,157 @@
+/// 協議錯誤通道
+///
+/// MqttHandler 解碼或路由訊息失敗時，除了寫入日誌，也把錯誤發佈到 tokio broadcast 通道；
+/// 終端視圖訂閱通道，在畫面底部顯示累計的協議錯誤數與最近一次錯誤內容
+use std::time::SystemTime;
+use tokio::sync::broadcast::{self, error::TryRecvError};
+
+use crate::mqtt_handler::SchemaMismatch;
+
+/// 通道容量（訂閱端落後超過此數量時只計數不保留內容）
+const ERROR_BUS_CAPACITY: usize = 64;
+
+/// 協議錯誤類型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolErrorKind {
+    /// 訊息無法以目前的編碼格式解碼
+    Decode,
+    /// 訊息內容不符合預期結構
+    Schema,
+    /// 其他處理失敗（例如後端協議版本過舊）
+    Route,
+}
+
+impl ProtocolErrorKind {
+    /// 顯示用名稱
+    pub fn label(&self) -> &'static str {
+        match self {
+            ProtocolErrorKind::Decode => "解碼",
+            ProtocolErrorKind::Schema => "結構",
+            ProtocolErrorKind::Route => "處理",
+        }
+    }
+}
+
+/// 一次協議錯誤
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProtocolError {
+    pub kind: ProtocolErrorKind,
+    pub topic: String,
+    pub message: String,
+    pub time: SystemTime,
+}
+
+impl ProtocolError {
+    /// 依訊息處理錯誤的類型建立
+    pub fn from_error(topic: &str, error: &anyhow::Error) -> Self {
+        let kind = if error.downcast_ref::<SchemaMismatch>().is_some() {
+            ProtocolErrorKind::Schema
+        } else if is_decode_error(error) {
+            ProtocolErrorKind::Decode
+        } else {
+            ProtocolErrorKind::Route
+        };
+        Self { kind, topic: topic.to_string(), message: error.to_string(), time: SystemTime::now() }
+    }
+}
+
+/// 是否為訊息解碼失敗
+fn is_decode_error(error: &anyhow::Error) -> bool {
+    #[cfg(feature = "msgpack")]
+    if error.downcast_ref::<rmp_serde::decode::Error>().is_some() {
+        return true;
+    }
+    error.downcast_ref::<serde_json::Error>().is_some()
+}
+
+/// 協議錯誤的發佈端（可複製，所有複本共用同一個通道）
+#[derive(Debug, Clone)]
+pub struct ErrorBus {
+    sender: broadcast::Sender<ProtocolError>,
+}
+
+impl Default for ErrorBus {
+    fn default() -> Self {
+        Self { sender: broadcast::channel(ERROR_BUS_CAPACITY).0 }
+    }
+}
+
+impl ErrorBus {
+    /// 發佈錯誤（沒有訂閱者時直接丟棄）
+    pub fn publish(&self, error: ProtocolError) {
+        let _ = self.sender.send(error);
+    }
+
+    /// 訂閱之後發佈的錯誤
+    pub fn subscribe(&self) -> broadcast::Receiver<ProtocolError> {
+        self.sender.subscribe()
+    }
+}
+
+/// 訂閱端的錯誤統計
+#[derive(Debug)]
+pub struct ProtocolErrorFeed {
+    receiver: broadcast::Receiver<ProtocolError>,
+    count: u64,
+    last: Option<ProtocolError>,
+}
+
+impl ProtocolErrorFeed {
+    /// 從訂閱建立
+    pub fn new(receiver: broadcast::Receiver<ProtocolError>) -> Self {
+        Self { receiver, count: 0, last: None }
+    }
+
+    /// 取出通道中所有新的錯誤（落後而遺失的錯誤仍計入總數）
+    pub fn poll(&mut self) {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(error) => {
+                    self.count += 1;
+                    self.last = Some(error);
+                }
+                Err(TryRecvError::Lagged(skipped)) => self.count += skipped,
+                Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => break,
+            }
+        }
+    }
+
+    /// 累計的錯誤數
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// 狀態列文字（沒有錯誤時返回 None）
+    pub fn status_line(&self) -> Option<String> {
+        let last = self.last.as_ref()?;
+        Some(format!(" ⚠ 協議錯誤: {} | 最近（{}）{}: {} ", self.count, last.kind.label(), last.topic, last.message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn counts_errors_including_lagged_ones() {
+        let bus = ErrorBus::default();
+        let mut feed = ProtocolErrorFeed::new(bus.subscribe());
+        feed.poll();
+        assert_eq!(feed.status_line(), None);
+
+        let decode = anyhow::Error::from(serde_json::from_str::<u32>("{").unwrap_err());
+        bus.publish(ProtocolError::from_error("td/all/res", &decode));
+        bus.publish(ProtocolError::from_error("td/p1/send", &anyhow!("後端協議過舊")));
+        feed.poll();
+        assert_eq!(feed.count(), 2);
+        assert!(feed.status_line().unwrap().contains("協議錯誤: 2 | 最近（處理）td/p1/send: 後端協議過舊"));
+
+        for _ in 0..ERROR_BUS_CAPACITY + 6 {
+            bus.publish(ProtocolError::from_error("td/all/res", &decode));
+        }
+        feed.poll();
+        assert_eq!(feed.count, 2 + ERROR_BUS_CAPACITY as u64 + 6);
+        assert_eq!(feed.last.as_ref().unwrap().kind, ProtocolErrorKind::Decode);
+    }
+}
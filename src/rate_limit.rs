@@ -0,0 +1,168 @@
+/// 發佈限流
+///
+/// 依訊息類型限制每秒發佈次數，避免視野更新與畫面請求灌爆 broker。
+/// 超過頻率的訊息不會直接丟棄，而是只保留最新一則，間隔到了再送出；
+/// 與上一則已送出的內容相同時視為多餘，直接合併掉
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::RateLimitConfig;
+
+/// 一種訊息類型的限流狀態
+#[derive(Debug, Default)]
+struct Lane {
+    /// 最後一次發佈的時間
+    last_sent: Option<Instant>,
+    /// 最後一次發佈的內容
+    last_payload: Option<serde_json::Value>,
+    /// 等待間隔到期的最新內容
+    pending: Option<serde_json::Value>,
+}
+
+/// 限流統計
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimitStats {
+    /// 超過頻率而延後的訊息數
+    pub deferred: u64,
+    /// 被較新的訊息取代或與已送出內容相同而合併掉的訊息數
+    pub coalesced: u64,
+}
+
+/// 發佈限流器
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    /// 各訊息類型的最短發佈間隔（未設定的類型不限流）
+    intervals: BTreeMap<String, Duration>,
+    lanes: HashMap<String, Lane>,
+    stats: RateLimitStats,
+}
+
+/// 在客戶端與畫面請求循環間共享的限流器
+pub type SharedRateLimiter = Arc<Mutex<RateLimiter>>;
+
+impl RateLimiter {
+    /// 依配置創建
+    pub fn new(config: &RateLimitConfig) -> Self {
+        let intervals = config.per_second.iter()
+            .map(|(kind, rate)| (kind.clone(), Duration::from_secs_f32(1.0 / rate)))
+            .collect();
+        Self { intervals, ..Self::default() }
+    }
+
+    /// 該類型目前是否可以發佈（可以時記為已發佈；用於不需要合併內容的定時訊息）
+    pub fn admit(&mut self, kind: &str, now: Instant) -> bool {
+        let Some(interval) = self.intervals.get(kind).copied() else {
+            return true;
+        };
+        let lane = self.lanes.entry(kind.to_string()).or_default();
+        if lane.last_sent.is_some_and(|sent| now.duration_since(sent) < interval) {
+            self.stats.deferred += 1;
+            return false;
+        }
+        lane.last_sent = Some(now);
+        true
+    }
+
+    /// 提交一則訊息：返回現在應該發佈的內容，否則暫存為待發佈（取代較舊的待發佈內容）
+    pub fn submit(&mut self, kind: &str, payload: serde_json::Value, now: Instant) -> Option<serde_json::Value> {
+        let Some(interval) = self.intervals.get(kind).copied() else {
+            return Some(payload);
+        };
+        let lane = self.lanes.entry(kind.to_string()).or_default();
+        if lane.last_sent.is_none_or(|sent| now.duration_since(sent) >= interval) {
+            lane.last_sent = Some(now);
+            lane.last_payload = Some(payload.clone());
+            if lane.pending.take().is_some() {
+                self.stats.coalesced += 1;
+            }
+            return Some(payload);
+        }
+        if lane.pending.is_some() {
+            self.stats.coalesced += 1;
+        }
+        if lane.last_payload.as_ref() == Some(&payload) {
+            // 最新的內容已經送出過，等待中的舊內容也不必再送
+            lane.pending = None;
+            self.stats.coalesced += 1;
+        } else {
+            lane.pending = Some(payload);
+            self.stats.deferred += 1;
+        }
+        None
+    }
+
+    /// 取出間隔已到期的待發佈內容（並記為已發佈）
+    pub fn due(&mut self, now: Instant) -> Vec<(String, serde_json::Value)> {
+        let mut due = Vec::new();
+        for (kind, lane) in &mut self.lanes {
+            let interval = self.intervals.get(kind).copied().unwrap_or_default();
+            if lane.pending.is_none() || lane.last_sent.is_some_and(|sent| now.duration_since(sent) < interval) {
+                continue;
+            }
+            if let Some(payload) = lane.pending.take() {
+                lane.last_sent = Some(now);
+                lane.last_payload = Some(payload.clone());
+                due.push((kind.clone(), payload));
+            }
+        }
+        due
+    }
+
+    /// 忘記已發佈的內容（重新連線後相同內容也要重送）
+    pub fn forget_sent(&mut self) {
+        for lane in self.lanes.values_mut() {
+            lane.last_payload = None;
+        }
+    }
+
+    /// 限流統計
+    pub fn stats(&self) -> RateLimitStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(&RateLimitConfig { per_second: BTreeMap::from([("update_viewport".to_string(), 2.0)]) })
+    }
+
+    #[test]
+    fn coalesces_viewport_updates_within_the_interval() {
+        let mut limiter = limiter();
+        let start = Instant::now();
+        assert_eq!(limiter.submit("update_viewport", json!({ "x": 1 }), start), Some(json!({ "x": 1 })));
+        assert_eq!(limiter.submit("update_viewport", json!({ "x": 2 }), start), None);
+        assert_eq!(limiter.submit("update_viewport", json!({ "x": 3 }), start + Duration::from_millis(100)), None);
+        assert!(limiter.due(start + Duration::from_millis(400)).is_empty());
+
+        let later = start + Duration::from_millis(500);
+        assert_eq!(limiter.due(later), vec![("update_viewport".to_string(), json!({ "x": 3 }))]);
+        assert!(limiter.due(later + Duration::from_secs(1)).is_empty());
+        assert_eq!(limiter.stats(), RateLimitStats { deferred: 2, coalesced: 1 });
+
+        // 回到已送出的內容時不再重送
+        assert_eq!(limiter.submit("update_viewport", json!({ "x": 4 }), later), None);
+        assert_eq!(limiter.submit("update_viewport", json!({ "x": 3 }), later), None);
+        assert!(limiter.due(later + Duration::from_secs(1)).is_empty());
+
+        // 未設定的類型不限流
+        assert!(limiter.submit("move", json!({}), later).is_some());
+        assert!(limiter.admit("screen_request", later));
+        assert!(limiter.admit("screen_request", later));
+    }
+
+    #[test]
+    fn admits_periodic_messages_at_the_configured_rate() {
+        let mut limiter = RateLimiter::new(&RateLimitConfig::default());
+        let start = Instant::now();
+        assert!(limiter.admit("screen_request", start));
+        assert!(!limiter.admit("screen_request", start + Duration::from_millis(100)));
+        assert!(limiter.admit("screen_request", start + Duration::from_millis(500)));
+        assert_eq!(limiter.stats().deferred, 1);
+    }
+}
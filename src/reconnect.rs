@@ -0,0 +1,193 @@
+/// MQTT 自動重連
+///
+/// broker 重啟或網路中斷時，事件循環以指數退避重試連線，配置了備援 broker 時每次失敗改試下一個；
+/// 重新連上後重新訂閱所有主題、重送 enter_game 並依序補送斷線期間暫存的玩家操作，透過共享的連線狀態讓介面顯示「重連中」。
+/// 設置 `offline_queue.path` 時暫存的操作同步寫入 JSON Lines 保存檔（每行一筆錄製格式的記錄），
+/// 程式重啟後進入遊戲時先補送保存檔中尚未送出的操作
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use anyhow::{Context, Result};
+use log::warn;
+
+use crate::recording::{RecordDirection, RecordedMessage};
+
+/// 第一次重試前的等待時間
+const INITIAL_DELAY: Duration = Duration::from_millis(500);
+
+/// 重試間隔上限
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// 指數退避
+#[derive(Debug, Default)]
+pub struct Backoff {
+    attempt: u32,
+}
+
+impl Backoff {
+    /// 記錄一次失敗並返回下次重試前的等待時間
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = INITIAL_DELAY.saturating_mul(1 << self.attempt.min(16)).min(MAX_DELAY);
+        self.attempt += 1;
+        delay
+    }
+
+    /// 目前累計的失敗次數
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// 連線恢復後重置
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+/// 多個 broker 之間的輪替：連線失敗時改試下一個，試完最後一個後回到主要 broker
+#[derive(Debug, Clone)]
+pub struct BrokerRotation {
+    count: usize,
+    current: usize,
+}
+
+impl BrokerRotation {
+    /// 創建輪替（第 0 個為主要 broker）
+    pub fn new(count: usize) -> Self {
+        Self { count: count.max(1), current: 0 }
+    }
+
+    /// 目前使用的 broker
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// 改用下一個 broker 並返回其索引
+    pub fn advance(&mut self) -> usize {
+        self.current = (self.current + 1) % self.count;
+        self.current
+    }
+}
+
+/// 事件循環與客戶端共享的連線狀態
+#[derive(Debug, Default)]
+pub struct LinkState {
+    /// 重連嘗試次數（連線正常時為 None）
+    pub reconnecting: Option<u32>,
+    /// 目前連線（或正在嘗試）的 broker 索引，0 為主要 broker
+    pub broker: usize,
+    /// 已進入遊戲時送出的 enter_game（主題與內容），重連後重送
+    pub enter_game: Option<(String, Vec<u8>)>,
+    /// 斷線期間暫存的玩家操作（主題與內容），依序補送完畢前新的操作也排在後面
+    pub outbound: VecDeque<(String, Vec<u8>)>,
+    /// 暫存操作的保存檔（與 outbound 內容一致）
+    pub journal: Option<PathBuf>,
+}
+
+impl LinkState {
+    /// 暫存一則操作（有保存檔時附加到檔案）
+    pub fn enqueue(&mut self, topic: String, payload: Vec<u8>) {
+        if let Some(path) = &self.journal {
+            let result = std::fs::OpenOptions::new().create(true).append(true).open(path)
+                .and_then(|mut file| writeln!(file, "{}", journal_line(&topic, &payload)));
+            if let Err(e) = result {
+                warn!("無法寫入離線佇列保存檔 {:?}: {}", path, e);
+            }
+        }
+        self.outbound.push_back((topic, payload));
+    }
+
+    /// 最前面的操作已送出，移出佇列並更新保存檔（佇列清空時刪除檔案）
+    pub fn mark_sent(&mut self) {
+        self.outbound.pop_front();
+        let Some(path) = &self.journal else {
+            return;
+        };
+        let result = if self.outbound.is_empty() {
+            std::fs::remove_file(path).or_else(|e| if e.kind() == std::io::ErrorKind::NotFound { Ok(()) } else { Err(e) })
+        } else {
+            let lines: String = self.outbound.iter().map(|(topic, payload)| journal_line(topic, payload) + "\n").collect();
+            std::fs::write(path, lines)
+        };
+        if let Err(e) = result {
+            warn!("無法更新離線佇列保存檔 {:?}: {}", path, e);
+        }
+    }
+}
+
+fn journal_line(topic: &str, payload: &[u8]) -> String {
+    serde_json::to_string(&RecordedMessage::new(0, RecordDirection::Outgoing, topic, payload)).unwrap_or_default()
+}
+
+/// 讀取保存檔中尚未送出的操作（檔案不存在時為空，格式錯誤的行略過）
+pub fn load_journal(path: &Path) -> Result<VecDeque<(String, Vec<u8>)>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(VecDeque::new()),
+        Err(e) => return Err(e).with_context(|| format!("無法讀取離線佇列保存檔: {:?}", path)),
+    };
+    Ok(content.lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .filter_map(|(index, line)| match serde_json::from_str::<RecordedMessage>(line) {
+            Ok(message) => Some((message.topic.clone(), message.payload_bytes())),
+            Err(e) => {
+                warn!("離線佇列保存檔 {:?} 第 {} 筆格式錯誤: {}", path, index + 1, e);
+                None
+            }
+        })
+        .collect())
+}
+
+/// 在背景任務間共享的連線狀態
+pub type SharedLink = Arc<Mutex<LinkState>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_cap_and_resets() {
+        let mut backoff = Backoff::default();
+        let delays: Vec<Duration> = (0..8).map(|_| backoff.next_delay()).collect();
+        assert_eq!(delays[0], Duration::from_millis(500));
+        assert_eq!(delays[1], Duration::from_secs(1));
+        assert_eq!(delays[3], Duration::from_secs(4));
+        assert_eq!(delays[7], MAX_DELAY);
+        assert_eq!(backoff.attempt(), 8);
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), INITIAL_DELAY);
+    }
+
+    #[test]
+    fn journal_mirrors_the_queue_across_restarts() {
+        let path = std::env::temp_dir().join(format!("omobaf_offline_queue_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut link = LinkState { journal: Some(path.clone()), ..LinkState::default() };
+        link.enqueue("td/p1/action".to_string(), b"{\"t\":\"move\"}".to_vec());
+        link.enqueue("td/p1/action".to_string(), vec![0x81, 0xff]);
+        std::fs::write(&path, std::fs::read_to_string(&path).unwrap() + "not json\n").unwrap();
+
+        // 重啟後讀回尚未送出的操作（二進位負載原樣還原，格式錯誤的行略過）
+        let restored = load_journal(&path).unwrap();
+        assert_eq!(restored, link.outbound);
+
+        link.mark_sent();
+        assert_eq!(load_journal(&path).unwrap(), [("td/p1/action".to_string(), vec![0x81, 0xff])]);
+        link.mark_sent();
+        assert!(!path.exists(), "全部送出後刪除保存檔");
+        assert!(load_journal(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rotation_cycles_back_to_primary() {
+        let mut rotation = BrokerRotation::new(3);
+        assert_eq!(rotation.current(), 0);
+        assert_eq!([rotation.advance(), rotation.advance(), rotation.advance()], [1, 2, 0]);
+
+        let mut single = BrokerRotation::new(1);
+        assert_eq!(single.advance(), 0);
+    }
+}
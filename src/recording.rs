@@ -0,0 +1,399 @@
+/// MQTT 流量錄製格式
+///
+/// 每行一筆 JSON 記錄（JSON Lines），保存訊息方向、主題、負載與相對時間，
+/// 供回放與比較模式讀取；`--record <file>` 以 TrafficRecorder 把客戶端收發的每則訊息附加到錄製檔，
+/// 錄製中添加的書籤也以 `bookmark` 方向的記錄寫在同一條時間軸上，回放時列出並可直接跳轉。
+/// 錄製器與錄製檔的讀取屬於 `recording` 功能；記憶體中的訊息緩衝（異常擷取使用）一律建置
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "recording")]
+use std::fs::{File, OpenOptions};
+#[cfg(feature = "recording")]
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use anyhow::Result;
+#[cfg(feature = "recording")]
+use anyhow::Context;
+use bytes::Bytes;
+#[cfg(feature = "recording")]
+use log::warn;
+
+use crate::bookmarks::Bookmark;
+
+/// 書籤記錄使用的主題
+#[cfg(feature = "recording")]
+pub const BOOKMARK_TOPIC: &str = "omobaf/bookmark";
+
+/// 訊息方向
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordDirection {
+    /// 從 broker 收到的訊息
+    Incoming,
+    /// 發送到 broker 的訊息
+    Outgoing,
+    /// 錄製中添加的書籤（負載為書籤 JSON）
+    Bookmark,
+}
+
+/// 錄製的單筆訊息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedMessage {
+    /// 距離錄製開始的毫秒數
+    pub elapsed_ms: u64,
+    /// 記錄時間（Unix 毫秒）
+    pub timestamp_ms: u64,
+    /// 訊息方向
+    pub direction: RecordDirection,
+    /// MQTT 主題
+    pub topic: String,
+    /// 訊息負載（binary 為 true 時是十六進位編碼）
+    pub payload: String,
+    /// 負載不是 UTF-8 文字（例如 MessagePack）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub binary: bool,
+}
+
+impl RecordedMessage {
+    /// 建立一筆記錄（非 UTF-8 的負載以十六進位保存）
+    pub fn new(elapsed_ms: u64, direction: RecordDirection, topic: &str, payload: &[u8]) -> Self {
+        let (payload, binary) = match std::str::from_utf8(payload) {
+            Ok(text) => (text.to_string(), false),
+            Err(_) => (payload.iter().map(|byte| format!("{:02x}", byte)).collect(), true),
+        };
+        Self {
+            elapsed_ms,
+            timestamp_ms: unix_ms(SystemTime::now()),
+            direction,
+            topic: topic.to_string(),
+            payload,
+            binary,
+        }
+    }
+
+    /// 負載的原始位元組
+    pub fn payload_bytes(&self) -> Vec<u8> {
+        if !self.binary {
+            return self.payload.clone().into_bytes();
+        }
+        (0..self.payload.len() / 2)
+            .filter_map(|i| u8::from_str_radix(&self.payload[i * 2..i * 2 + 2], 16).ok())
+            .collect()
+    }
+}
+
+/// 錄製中的書籤
+#[cfg(feature = "recording")]
+#[derive(Debug, Clone)]
+pub struct RecordedBookmark {
+    /// 距離錄製開始的毫秒數（與訊息相同的時間軸）
+    pub elapsed_ms: u64,
+    pub bookmark: Bookmark,
+}
+
+/// 已載入的錄製檔
+#[cfg(feature = "recording")]
+#[derive(Debug, Clone)]
+pub struct Recording {
+    /// 錄製檔路徑（用於顯示）
+    pub name: String,
+    /// 依時間排序的訊息
+    pub messages: Vec<RecordedMessage>,
+}
+
+#[cfg(feature = "recording")]
+impl Recording {
+    /// 從 JSON Lines 檔案載入錄製
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("無法讀取錄製檔: {:?}", path))?;
+
+        let mut messages = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let message: RecordedMessage = serde_json::from_str(line)
+                .with_context(|| format!("錄製檔 {:?} 第 {} 行格式錯誤", path, i + 1))?;
+            messages.push(message);
+        }
+        messages.sort_by_key(|m| m.elapsed_ms);
+
+        Ok(Self {
+            name: path.display().to_string(),
+            messages,
+        })
+    }
+
+    /// 錄製總長度（毫秒）
+    pub fn duration_ms(&self) -> u64 {
+        self.messages.last().map(|m| m.elapsed_ms).unwrap_or(0)
+    }
+
+    /// 錄製中的書籤（依時間排序，格式錯誤的略過）
+    pub fn bookmarks(&self) -> Vec<RecordedBookmark> {
+        self.messages.iter()
+            .filter(|m| m.direction == RecordDirection::Bookmark)
+            .filter_map(|m| match serde_json::from_slice(&m.payload_bytes()) {
+                Ok(bookmark) => Some(RecordedBookmark { elapsed_ms: m.elapsed_ms, bookmark }),
+                Err(e) => {
+                    warn!("錄製中的書籤格式錯誤（{} ms）: {}", m.elapsed_ms, e);
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// 錄製播放游標
+///
+/// 依照播放時間逐步取出已到期的收到訊息
+#[cfg(feature = "recording")]
+#[derive(Debug, Clone)]
+pub struct RecordingCursor {
+    recording: Recording,
+    position: usize,
+}
+
+#[cfg(feature = "recording")]
+impl RecordingCursor {
+    /// 創建新的播放游標
+    pub fn new(recording: Recording) -> Self {
+        Self { recording, position: 0 }
+    }
+
+    /// 取出所有 elapsed_ms <= 指定時間的收到訊息
+    pub fn advance_to(&mut self, elapsed_ms: u64) -> Vec<RecordedMessage> {
+        let mut due = Vec::new();
+        while let Some(message) = self.recording.messages.get(self.position) {
+            if message.elapsed_ms > elapsed_ms {
+                break;
+            }
+            if message.direction == RecordDirection::Incoming {
+                due.push(message.clone());
+            }
+            self.position += 1;
+        }
+        due
+    }
+
+    /// 是否已播放完畢
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.recording.messages.len()
+    }
+
+    /// 底層錄製
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+}
+
+/// 錄製器內部狀態
+#[cfg(feature = "recording")]
+#[derive(Debug)]
+struct RecorderFile {
+    file: File,
+    /// elapsed_ms 的起點
+    start: Instant,
+    /// 附加到既有錄製檔時接續的毫秒數
+    offset_ms: u64,
+}
+
+/// MQTT 流量錄製器（可複製，事件循環與各發送任務共用同一個檔案）
+#[cfg(feature = "recording")]
+#[derive(Debug, Clone)]
+pub struct TrafficRecorder {
+    inner: Arc<Mutex<RecorderFile>>,
+}
+
+/// 建置時未啟用 recording 功能：錄製器無法開啟，也就不會有任何實例
+#[cfg(not(feature = "recording"))]
+#[derive(Debug, Clone)]
+pub enum TrafficRecorder {}
+
+#[cfg(not(feature = "recording"))]
+impl TrafficRecorder {
+    pub fn open(path: &Path) -> Result<Self> {
+        Err(anyhow::anyhow!("此版本建置時未啟用 recording 功能，無法錄製到 {:?}", path))
+    }
+
+    pub fn record(&self, _direction: RecordDirection, _topic: &str, _payload: &[u8]) {
+        match *self {}
+    }
+
+    pub fn bookmark(&self, _bookmark: &Bookmark) -> Result<()> {
+        match *self {}
+    }
+}
+
+#[cfg(feature = "recording")]
+impl TrafficRecorder {
+    /// 以附加模式開啟錄製檔；檔案已有紀錄時時間軸接在最後一筆之後
+    pub fn open(path: &Path) -> Result<Self> {
+        let now_ms = unix_ms(SystemTime::now());
+        let offset_ms = match Recording::load(path) {
+            Ok(existing) => existing.messages.iter().map(|m| m.elapsed_ms).max()
+                .zip(existing.messages.iter().map(|m| m.timestamp_ms).max())
+                .map(|(elapsed, timestamp)| elapsed + now_ms.saturating_sub(timestamp))
+                .unwrap_or(0),
+            Err(_) => 0,
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)
+            .with_context(|| format!("無法開啟錄製檔: {:?}", path))?;
+        Ok(Self {
+            inner: Arc::new(Mutex::new(RecorderFile { file, start: Instant::now(), offset_ms })),
+        })
+    }
+
+    /// 附加一筆訊息（每筆立即寫入，程式中斷也不會遺失已錄製的內容）
+    pub fn record(&self, direction: RecordDirection, topic: &str, payload: &[u8]) {
+        let mut inner = self.inner.lock().unwrap();
+        let elapsed_ms = inner.offset_ms + inner.start.elapsed().as_millis() as u64;
+        let message = RecordedMessage::new(elapsed_ms, direction, topic, payload);
+        let file = &mut inner.file;
+        let result = serde_json::to_writer(&mut *file, &message)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| Ok(file.write_all(b"\n")?));
+        if let Err(e) = result {
+            warn!("寫入錄製檔失敗: {}", e);
+        }
+    }
+
+    /// 以錄製的時間軸記錄書籤
+    pub fn bookmark(&self, bookmark: &Bookmark) -> Result<()> {
+        self.record(RecordDirection::Bookmark, BOOKMARK_TOPIC, &serde_json::to_vec(bookmark)?);
+        Ok(())
+    }
+}
+
+/// 最近一段時間的收發訊息（記憶體中的環形緩衝，異常擷取時寫出成錄製檔格式）
+#[derive(Debug, Clone)]
+pub struct TrafficHistory {
+    inner: Arc<Mutex<HistoryBuffer>>,
+}
+
+#[derive(Debug)]
+struct HistoryBuffer {
+    start: Instant,
+    window: Duration,
+    messages: VecDeque<HistoryEntry>,
+}
+
+/// 緩衝中的訊息（負載與 MQTT 封包共用同一塊記憶體，寫出時才轉成錄製格式）
+#[derive(Debug)]
+struct HistoryEntry {
+    elapsed_ms: u64,
+    timestamp_ms: u64,
+    direction: RecordDirection,
+    topic: String,
+    payload: Bytes,
+}
+
+impl Default for TrafficHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_WINDOW)
+    }
+}
+
+/// 預設保留的訊息時間長度
+pub const DEFAULT_HISTORY_WINDOW: Duration = Duration::from_secs(30);
+
+impl TrafficHistory {
+    /// 保留最近 `window` 內的訊息
+    pub fn new(window: Duration) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(HistoryBuffer { start: Instant::now(), window, messages: VecDeque::new() })),
+        }
+    }
+
+    /// 變更保留的時間長度
+    pub fn set_window(&self, window: Duration) {
+        self.inner.lock().unwrap().window = window;
+    }
+
+    /// 記錄一則訊息並丟棄超出時間窗口的舊訊息
+    pub fn record(&self, direction: RecordDirection, topic: &str, payload: Bytes) {
+        let mut inner = self.inner.lock().unwrap();
+        let elapsed_ms = inner.start.elapsed().as_millis() as u64;
+        inner.messages.push_back(HistoryEntry {
+            elapsed_ms,
+            timestamp_ms: unix_ms(SystemTime::now()),
+            direction,
+            topic: topic.to_string(),
+            payload,
+        });
+        let window_ms = inner.window.as_millis() as u64;
+        while inner.messages.front().is_some_and(|message| message.elapsed_ms + window_ms < elapsed_ms) {
+            inner.messages.pop_front();
+        }
+    }
+
+    /// 時間窗口內的訊息（依時間排序）
+    pub fn recent(&self) -> Vec<RecordedMessage> {
+        let inner = self.inner.lock().unwrap();
+        let now_ms = inner.start.elapsed().as_millis() as u64;
+        let window_ms = inner.window.as_millis() as u64;
+        inner.messages.iter()
+            .filter(|entry| entry.elapsed_ms + window_ms >= now_ms)
+            .map(|entry| RecordedMessage {
+                timestamp_ms: entry.timestamp_ms,
+                ..RecordedMessage::new(entry.elapsed_ms, entry.direction, &entry.topic, &entry.payload)
+            })
+            .collect()
+    }
+}
+
+fn unix_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+#[cfg(all(test, feature = "recording"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appends_messages_and_continues_timeline() {
+        let path = std::env::temp_dir().join(format!("omobaf_record_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let recorder = TrafficRecorder::open(&path).unwrap();
+        recorder.record(RecordDirection::Outgoing, "td/p1/action", br#"{"a":"move"}"#);
+        recorder.record(RecordDirection::Incoming, "td/all/res", &[0x82, 0xa1, 0xff]);
+        let reopened = TrafficRecorder::open(&path).unwrap();
+        reopened.record(RecordDirection::Incoming, "td/p1/pong", b"{}");
+
+        let recording = Recording::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(recording.messages.len(), 3);
+        assert_eq!(recording.messages[0].payload, r#"{"a":"move"}"#);
+        assert!(recording.messages[1].binary);
+        assert_eq!(recording.messages[1].payload_bytes(), vec![0x82, 0xa1, 0xff]);
+        assert_eq!(recording.messages[2].direction, RecordDirection::Incoming);
+        assert!(recording.messages[2].elapsed_ms >= recording.messages[1].elapsed_ms);
+    }
+
+    #[test]
+    fn bookmarks_share_the_recording_timeline() {
+        let path = std::env::temp_dir().join(format!("omobaf_record_bookmark_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let recorder = TrafficRecorder::open(&path).unwrap();
+        recorder.record(RecordDirection::Incoming, "td/all/res", b"{}");
+        let bookmark = Bookmark {
+            timestamp_ms: 0, session_secs: 1.5, player_name: "p1".to_string(), position: (10.0, 20.0), note: Some("不同步".to_string()),
+        };
+        recorder.bookmark(&bookmark).unwrap();
+        recorder.record(RecordDirection::Incoming, "td/p1/pong", b"{}");
+
+        let recording = Recording::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let bookmarks = recording.bookmarks();
+        assert_eq!(bookmarks.len(), 1);
+        assert_eq!(bookmarks[0].elapsed_ms, recording.messages[1].elapsed_ms);
+        assert_eq!(bookmarks[0].bookmark.note.as_deref(), Some("不同步"));
+        let mut cursor = RecordingCursor::new(recording);
+        assert_eq!(cursor.advance_to(u64::MAX).len(), 2, "書籤不會送入 MqttHandler");
+    }
+}
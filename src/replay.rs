@@ -0,0 +1,244 @@
+/// 錄製回放模式
+///
+/// `omobaf replay <file>` 依原始時間（或倍率）把錄製檔中收到的訊息（screen_response、廣播等）
+/// 送入 MqttHandler，並以終端視圖渲染，不需要連線到後端即可重現一場錄製；
+/// 錄製中的書籤列在左上角，`n`/`p` 跳到下一個或上一個書籤、數字鍵跳到對應編號（往回跳時從頭重新套用訊息）
+use std::path::Path;
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode};
+use crossterm::style::Color;
+use crossterm::terminal;
+use log::{info, warn};
+use rumqttc::{Publish, QoS};
+
+use crate::config::AppConfig;
+use crate::coords::CoordinateTransform;
+use crate::game_state::GameState;
+use crate::mqtt_handler::MqttHandler;
+use crate::recording::{RecordedBookmark, Recording, RecordingCursor};
+use crate::terminal_view::overlays::OverlayManager;
+use crate::terminal_view::{MapRenderer, RenderOverlay, ViewportManager};
+
+/// 回放會話
+pub struct Replay {
+    cursor: RecordingCursor,
+    state: GameState,
+    mqtt_handler: MqttHandler,
+    speed: f32,
+    paused: bool,
+    match_time_ms: f64,
+    /// 已送入 MqttHandler 的訊息數
+    applied: usize,
+    /// 處理失敗的訊息數
+    failed: usize,
+    /// 錄製中的書籤
+    bookmarks: Vec<RecordedBookmark>,
+}
+
+/// 往回跳書籤時保留的寬限（剛跳到的書籤不算「上一個」）
+const PREVIOUS_BOOKMARK_GRACE_MS: f64 = 1000.0;
+
+/// 目前時間之後（或之前）最近的書籤時間
+fn adjacent_bookmark(bookmarks: &[RecordedBookmark], now_ms: f64, forward: bool) -> Option<u64> {
+    if forward {
+        bookmarks.iter().map(|b| b.elapsed_ms).find(|&ms| ms as f64 > now_ms)
+    } else {
+        bookmarks.iter().rev().map(|b| b.elapsed_ms).find(|&ms| (ms as f64) < now_ms - PREVIOUS_BOOKMARK_GRACE_MS)
+    }
+}
+
+impl Replay {
+    /// 載入錄製
+    pub fn new(path: &Path, player_name: &str, hero_type: &str, speed: f32) -> Result<Self> {
+        let recording = Recording::load(path)?;
+        info!("載入回放錄製: {} ({} 筆，{:.1} 秒)",
+              recording.name, recording.messages.len(), recording.duration_ms() as f64 / 1000.0);
+        let app_config = AppConfig::load();
+
+        let bookmarks = recording.bookmarks();
+        Ok(Self {
+            cursor: RecordingCursor::new(recording),
+            state: GameState::new(player_name.to_string(), hero_type.to_string()),
+            mqtt_handler: MqttHandler::with_format(app_config.server.wire_format)
+                .with_topics(app_config.server.topics)
+                .with_binary_decoders(app_config.server.binary_decoders),
+            speed,
+            paused: false,
+            match_time_ms: 0.0,
+            applied: 0,
+            failed: 0,
+            bookmarks,
+        })
+    }
+
+    /// 運行回放視圖（按 q 或 Esc 退出）
+    pub async fn run(&mut self, show_vision: bool) -> Result<()> {
+        let renderer = MapRenderer::new();
+        let (term_width, term_height) = terminal::size()?;
+        let map_height = term_height.saturating_sub(3); // 留出日誌區域空間
+        let app_config = AppConfig::load();
+        let mut viewport = ViewportManager::new(app_config.camera.view_width, app_config.camera.view_height);
+        viewport.transform = CoordinateTransform::from_config(&app_config.coordinates);
+        let mut overlays = OverlayManager::new(&app_config.overlays);
+        if show_vision {
+            overlays.set("ranges", true);
+        }
+
+        renderer.init_terminal()?;
+        let result = self.run_loop(&renderer, &viewport, &mut overlays, term_width, map_height).await;
+        let _ = renderer.cleanup_terminal();
+        result?;
+
+        println!("回放結束：送入 {} 則訊息（{} 則處理失敗），回放到 {:.1} 秒",
+                 self.applied, self.failed, self.match_time_ms / 1000.0);
+        Ok(())
+    }
+
+    async fn run_loop(
+        &mut self,
+        renderer: &MapRenderer,
+        viewport: &ViewportManager,
+        overlays: &mut OverlayManager,
+        term_width: u16,
+        map_height: u16,
+    ) -> Result<()> {
+        let mut last_tick = Instant::now();
+
+        loop {
+            // 處理鍵盤輸入
+            while event::poll(Duration::from_millis(0))? {
+                if let Event::Key(key) = event::read()? {
+                    match key.code {
+                        KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                        KeyCode::Char(' ') => self.paused = !self.paused,
+                        KeyCode::Char('+') => self.speed = (self.speed * 2.0).min(16.0),
+                        KeyCode::Char('-') => self.speed = (self.speed / 2.0).max(0.125),
+                        KeyCode::Right => self.match_time_ms += 5000.0,
+                        KeyCode::Char(c @ ('n' | 'p')) => {
+                            if let Some(ms) = adjacent_bookmark(&self.bookmarks, self.match_time_ms, c == 'n') {
+                                self.seek(ms).await;
+                            }
+                        }
+                        KeyCode::Char(c @ '1'..='9') => {
+                            let index = c as usize - '1' as usize;
+                            if let Some(ms) = self.bookmarks.get(index).map(|b| b.elapsed_ms) {
+                                self.seek(ms).await;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            // 推進比賽時間
+            let dt = last_tick.elapsed().as_secs_f64() * 1000.0;
+            last_tick = Instant::now();
+            if !self.paused {
+                self.match_time_ms += dt * self.speed as f64;
+            }
+            self.apply_due_messages().await;
+
+            // 回放沒有鏡頭，畫面跟隨英雄
+            self.state.viewport.center = self.state.local_player.position;
+            let header = format!(
+                "⏵ 回放 t={:.1}/{:.1}s {:.2}x{} | 空白鍵暫停 +/- 速度 → 快轉 n/p 書籤 q 退出",
+                self.match_time_ms / 1000.0,
+                self.cursor.recording().duration_ms() as f64 / 1000.0,
+                self.speed,
+                if self.paused { " (暫停)" } else if self.cursor.is_finished() { " (回放結束)" } else { "" },
+            );
+            overlays.observe(&self.state);
+            let bookmarks = self.bookmark_lines();
+            let overlay = RenderOverlay {
+                header: Some((&header, Color::Cyan)),
+                stats: &bookmarks,
+                layers: Some(overlays),
+                ..RenderOverlay::default()
+            };
+            renderer.render(&self.state, viewport, term_width, map_height, &overlay)?;
+
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    /// 書籤列表（已經過的書籤以 ✓ 標記）
+    fn bookmark_lines(&self) -> Vec<String> {
+        if self.bookmarks.is_empty() {
+            return Vec::new();
+        }
+        let mut lines = vec![format!("🔖 書籤 ({})", self.bookmarks.len())];
+        lines.extend(self.bookmarks.iter().enumerate().map(|(i, recorded)| format!(
+            "{} {} {:>6.1}s {}",
+            if (recorded.elapsed_ms as f64) <= self.match_time_ms { '✓' } else { ' ' },
+            i + 1,
+            recorded.elapsed_ms as f64 / 1000.0,
+            recorded.bookmark.note.as_deref().unwrap_or(""),
+        )));
+        lines
+    }
+
+    /// 跳到指定的比賽時間（往回跳時重設狀態並從頭重新套用訊息）
+    async fn seek(&mut self, target_ms: u64) {
+        if (target_ms as f64) < self.match_time_ms {
+            let player = &self.state.local_player;
+            self.state = GameState::new(player.name.clone(), player.hero_type.clone());
+            self.cursor = RecordingCursor::new(self.cursor.recording().clone());
+            self.applied = 0;
+            self.failed = 0;
+        }
+        self.match_time_ms = target_ms as f64;
+        self.apply_due_messages().await;
+        info!("⏭ 回放跳到 {:.1} 秒", self.match_time_ms / 1000.0);
+    }
+
+    /// 把到期的收到訊息送入 MqttHandler 處理
+    async fn apply_due_messages(&mut self) {
+        for message in self.cursor.advance_to(self.match_time_ms as u64) {
+            let publish = Publish::new(message.topic.clone(), QoS::AtMostOnce, message.payload_bytes());
+            match self.mqtt_handler.handle_message(&publish, &mut self.state).await {
+                Ok(()) => self.applied += 1,
+                Err(e) => {
+                    self.failed += 1;
+                    warn!("回放訊息處理失敗 [{}]: {}", message.topic, e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bookmarks::Bookmark;
+    use crate::recording::{RecordDirection, TrafficRecorder};
+
+    #[tokio::test]
+    async fn jumps_between_recorded_bookmarks() {
+        let path = std::env::temp_dir().join(format!("omobaf_replay_bookmarks_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let recorder = TrafficRecorder::open(&path).unwrap();
+        let bookmark = |note: &str| Bookmark {
+            timestamp_ms: 0, session_secs: 0.0, player_name: "p1".to_string(), position: (0.0, 0.0), note: Some(note.to_string()),
+        };
+        recorder.record(RecordDirection::Incoming, "td/all/res", b"{}");
+        recorder.bookmark(&bookmark("first")).unwrap();
+        std::thread::sleep(Duration::from_millis(1100));
+        recorder.record(RecordDirection::Incoming, "td/all/res", b"{}");
+        recorder.bookmark(&bookmark("second")).unwrap();
+
+        let mut replay = Replay::new(&path, "p1", "saika_magoichi", 1.0).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(replay.bookmarks.len(), 2);
+        let second = adjacent_bookmark(&replay.bookmarks, replay.bookmarks[0].elapsed_ms as f64, true).unwrap();
+        assert_eq!(second, replay.bookmarks[1].elapsed_ms);
+        replay.seek(second).await;
+        assert_eq!(replay.applied + replay.failed, 2);
+        assert!(replay.bookmark_lines()[2].starts_with('✓'));
+
+        let first = adjacent_bookmark(&replay.bookmarks, replay.match_time_ms, false).unwrap();
+        replay.seek(first).await;
+        assert_eq!(replay.applied + replay.failed, 1, "往回跳時從頭重新套用");
+        assert!(replay.bookmark_lines()[2].starts_with(' '));
+    }
+}
@@ -0,0 +1,282 @@
+/// 內建的回放 broker
+///
+/// `omobaf serve <file>` 自己充當一個最小的 MQTT broker：接受連線、訂閱與心跳，轉送客戶端發佈的訊息，
+/// 並依錄製時間把錄製檔中收到的訊息發佈給訂閱者。多個 omobaf 實例連到這裡就會收到完全相同的流量，
+/// 不需要後端即可測試多個客戶端的渲染與狀態處理
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
+use bytes::{Bytes, BytesMut};
+use log::{debug, info, warn};
+use rumqttc::mqttbytes::v4::{self, Packet};
+use rumqttc::{ConnAck, ConnectReturnCode, PingResp, PubAck, PubComp, PubRec, Publish, QoS, SubAck, SubscribeReasonCode, UnsubAck};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::recording::{Recording, RecordingCursor};
+
+/// 單一封包的大小上限
+const MAX_PACKET_SIZE: usize = 10 * 1024 * 1024;
+/// 回放時鐘的推進間隔
+const PLAY_TICK: Duration = Duration::from_millis(10);
+
+/// 一個連上的客戶端
+#[derive(Debug)]
+struct Session {
+    client_id: String,
+    filters: Vec<String>,
+    outbox: mpsc::UnboundedSender<Publish>,
+}
+
+type Sessions = Arc<Mutex<HashMap<u64, Session>>>;
+
+/// 回放統計
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReplayStats {
+    /// 發佈的錄製訊息數
+    pub published: u64,
+    /// 送達訂閱者的次數（一則訊息送給多個客戶端時分別計算）
+    pub delivered: u64,
+    /// 播完的輪數
+    pub rounds: u64,
+}
+
+/// 執行中的回放 broker（drop 時停止接受連線）
+pub struct ReplayBroker {
+    sessions: Sessions,
+    local_addr: SocketAddr,
+    accept: JoinHandle<()>,
+}
+
+impl ReplayBroker {
+    /// 在指定位址開始接受 MQTT 連線
+    pub async fn start(listen: &str) -> Result<Self> {
+        let listener = TcpListener::bind(listen).await
+            .map_err(|e| anyhow!("無法監聽 {}: {}", listen, e))?;
+        let local_addr = listener.local_addr()?;
+        let sessions = Sessions::default();
+        let accept_sessions = sessions.clone();
+        let accept = tokio::spawn(async move {
+            let mut next_id = 0;
+            loop {
+                match listener.accept().await {
+                    Ok((stream, peer)) => {
+                        next_id += 1;
+                        let id = next_id;
+                        let sessions = accept_sessions.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = serve_client(stream, id, sessions.clone()).await {
+                                warn!("回放 broker 客戶端 {} 連線錯誤: {}", peer, e);
+                            }
+                            if let Some(session) = sessions.lock().unwrap().remove(&id) {
+                                info!("📴 客戶端 {} 已離線", session.client_id);
+                            }
+                        });
+                    }
+                    Err(e) => warn!("回放 broker 接受連線失敗: {}", e),
+                }
+            }
+        });
+        Ok(Self { sessions, local_addr, accept })
+    }
+
+    /// 實際監聽的位址
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// 已有訂閱的客戶端數
+    pub fn subscribed_clients(&self) -> usize {
+        self.sessions.lock().unwrap().values().filter(|session| !session.filters.is_empty()).count()
+    }
+
+    /// 等待指定數量的客戶端訂閱後，依錄製時間（乘上倍率）發佈錄製中收到的訊息；repeat 時播完從頭重播。
+    /// 空的錄製或倍率不是正數時不會前進，直接返回錯誤
+    pub async fn play(&self, recording: &Recording, speed: f32, wait_clients: usize, repeat: bool) -> Result<ReplayStats> {
+        if recording.messages.is_empty() {
+            return Err(anyhow!("錄製 {} 沒有任何訊息", recording.name));
+        }
+        if !(speed.is_finite() && speed > 0.0) {
+            return Err(anyhow!("回放倍率必須是正數: {}", speed));
+        }
+        if self.subscribed_clients() < wait_clients {
+            info!("⏳ 等待 {} 個客戶端連線並訂閱...", wait_clients);
+            while self.subscribed_clients() < wait_clients {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        }
+        let mut stats = ReplayStats::default();
+        loop {
+            info!("⏵ 開始第 {} 輪回放 ({} 筆，{:.1} 秒)", stats.rounds + 1,
+                  recording.messages.len(), recording.duration_ms() as f64 / 1000.0);
+            let mut cursor = RecordingCursor::new(recording.clone());
+            let start = Instant::now();
+            while !cursor.is_finished() {
+                tokio::time::sleep(PLAY_TICK).await;
+                let now_ms = (start.elapsed().as_secs_f64() * 1000.0 * speed as f64) as u64;
+                for message in cursor.advance_to(now_ms) {
+                    stats.published += 1;
+                    stats.delivered += route(&self.sessions, &message.topic, Bytes::from(message.payload_bytes())) as u64;
+                }
+            }
+            stats.rounds += 1;
+            if !repeat {
+                return Ok(stats);
+            }
+        }
+    }
+}
+
+impl Drop for ReplayBroker {
+    fn drop(&mut self) {
+        self.accept.abort();
+    }
+}
+
+/// 把訊息送給所有訂閱相符的客戶端（各客戶端共用同一份負載），返回送達的客戶端數
+fn route(sessions: &Sessions, topic: &str, payload: Bytes) -> usize {
+    let mut delivered = 0;
+    for session in sessions.lock().unwrap().values() {
+        if !session.filters.iter().any(|filter| rumqttc::matches(topic, filter)) {
+            continue;
+        }
+        if session.outbox.send(Publish::from_bytes(topic, QoS::AtMostOnce, payload.clone())).is_ok() {
+            delivered += 1;
+        }
+    }
+    delivered
+}
+
+/// 處理單一客戶端的封包，直到斷線
+async fn serve_client(mut stream: TcpStream, id: u64, sessions: Sessions) -> Result<()> {
+    let (outbox, mut inbox) = mpsc::unbounded_channel();
+    sessions.lock().unwrap().insert(id, Session { client_id: format!("#{}", id), filters: Vec::new(), outbox });
+    let mut incoming = BytesMut::with_capacity(4096);
+    let mut outgoing = BytesMut::new();
+
+    loop {
+        tokio::select! {
+            read = stream.read_buf(&mut incoming) => {
+                if read? == 0 {
+                    return Ok(());
+                }
+                loop {
+                    let packet = match v4::read(&mut incoming, MAX_PACKET_SIZE) {
+                        Ok(packet) => packet,
+                        Err(rumqttc::mqttbytes::Error::InsufficientBytes(_)) => break,
+                        Err(e) => return Err(anyhow!("封包格式錯誤: {}", e)),
+                    };
+                    if !handle_packet(packet, id, &sessions, &mut outgoing)? {
+                        stream.write_all(&outgoing).await?;
+                        return Ok(());
+                    }
+                }
+            }
+            Some(publish) = inbox.recv() => {
+                publish.write(&mut outgoing)?;
+            }
+        }
+        if !outgoing.is_empty() {
+            stream.write_all(&outgoing).await?;
+            outgoing.clear();
+        }
+    }
+}
+
+/// 處理一個封包並把回應寫入 outgoing，返回連線是否繼續
+fn handle_packet(packet: Packet, id: u64, sessions: &Sessions, outgoing: &mut BytesMut) -> Result<bool> {
+    let encode = |e: rumqttc::mqttbytes::Error| anyhow!("無法編碼封包: {}", e);
+    match packet {
+        Packet::Connect(connect) => {
+            info!("📶 客戶端 {} 已連線", connect.client_id);
+            if let Some(session) = sessions.lock().unwrap().get_mut(&id) {
+                session.client_id = connect.client_id;
+            }
+            ConnAck::new(ConnectReturnCode::Success, false).write(outgoing).map_err(encode)?;
+        }
+        Packet::Subscribe(subscribe) => {
+            let mut sessions = sessions.lock().unwrap();
+            let session = sessions.get_mut(&id).ok_or_else(|| anyhow!("會話不存在"))?;
+            let codes = subscribe.filters.iter()
+                .map(|filter| {
+                    if !rumqttc::valid_filter(&filter.path) {
+                        return SubscribeReasonCode::Failure;
+                    }
+                    debug!("客戶端 {} 訂閱 {}", session.client_id, filter.path);
+                    if !session.filters.contains(&filter.path) {
+                        session.filters.push(filter.path.clone());
+                    }
+                    SubscribeReasonCode::Success(QoS::AtMostOnce)
+                })
+                .collect();
+            SubAck::new(subscribe.pkid, codes).write(outgoing).map_err(encode)?;
+        }
+        Packet::Unsubscribe(unsubscribe) => {
+            if let Some(session) = sessions.lock().unwrap().get_mut(&id) {
+                session.filters.retain(|filter| !unsubscribe.topics.contains(filter));
+            }
+            UnsubAck::new(unsubscribe.pkid).write(outgoing).map_err(encode)?;
+        }
+        Packet::Publish(publish) => {
+            match publish.qos {
+                QoS::AtMostOnce => {}
+                QoS::AtLeastOnce => { PubAck::new(publish.pkid).write(outgoing).map_err(encode)?; }
+                QoS::ExactlyOnce => { PubRec::new(publish.pkid).write(outgoing).map_err(encode)?; }
+            }
+            route(sessions, &publish.topic, publish.payload);
+        }
+        Packet::PubRel(pubrel) => {
+            PubComp::new(pubrel.pkid).write(outgoing).map_err(encode)?;
+        }
+        Packet::PingReq => {
+            PingResp.write(outgoing).map_err(encode)?;
+        }
+        Packet::Disconnect => return Ok(false),
+        _ => {}
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::{RecordDirection, RecordedMessage};
+    use rumqttc::{AsyncClient, Event, MqttOptions};
+
+    #[tokio::test]
+    async fn serves_recorded_incoming_messages_to_subscribers() {
+        let broker = ReplayBroker::start("127.0.0.1:0").await.unwrap();
+        let addr = broker.local_addr();
+        let (client, mut eventloop) = AsyncClient::new(MqttOptions::new("viewer", addr.ip().to_string(), addr.port()), 10);
+        client.subscribe("td/+/res", QoS::AtMostOnce).await.unwrap();
+        let (received, mut topics) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok(event) = eventloop.poll().await {
+                if let Event::Incoming(Packet::Publish(publish)) = event {
+                    let _ = received.send(publish.topic);
+                }
+            }
+        });
+
+        let recording = Recording {
+            name: "test".to_string(),
+            messages: vec![
+                RecordedMessage::new(0, RecordDirection::Incoming, "td/all/res", b"{}"),
+                RecordedMessage::new(1, RecordDirection::Outgoing, "td/p1/action", b"{}"),
+                RecordedMessage::new(2, RecordDirection::Incoming, "td/p1/send", b"{}"),
+            ],
+        };
+        assert!(broker.play(&recording, 0.0, 1, true).await.is_err(), "倍率為 0 時不播放");
+        let empty = Recording { name: "empty".to_string(), messages: Vec::new() };
+        assert!(tokio::time::timeout(Duration::from_secs(1), broker.play(&empty, 1.0, 0, true)).await.unwrap().is_err());
+
+        let stats = tokio::time::timeout(Duration::from_secs(5), broker.play(&recording, 10.0, 1, false)).await.unwrap().unwrap();
+        assert_eq!(stats, ReplayStats { published: 2, delivered: 1, rounds: 1 });
+        let topic = tokio::time::timeout(Duration::from_secs(5), topics.recv()).await.unwrap();
+        assert_eq!(topic.as_deref(), Some("td/all/res"));
+    }
+}
@@ -0,0 +1,497 @@
+/// 回放比較模式
+///
+/// 將兩份錄製依比賽時間同步回放，左右並排渲染，並標示實體位置/生命值的分歧，
+/// 用於比較不同後端版本執行相同情境的結果。比賽時間取自畫面回應的伺服器時間戳：
+/// 以各錄製第一則帶時間戳的畫面回應換算錄製時間與比賽時間的偏移，從兩份都已開始的比賽時間起同步播放
+/// （任一份沒有時間戳時退回以錄製時間對齊）
+use std::path::Path;
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode};
+use crossterm::style::Color;
+use crossterm::terminal;
+use log::{info, warn};
+use rumqttc::{Publish, QoS};
+use vek::Vec2;
+
+use crate::game_state::GameState;
+use crate::mqtt_handler::{MqttHandler, WireFormat};
+use crate::recording::{RecordDirection, Recording, RecordingCursor};
+use crate::terminal_view::{MapDisplay, MapRenderer, ViewportManager};
+use crate::config::AppConfig;
+use crate::coords::CoordinateTransform;
+use crate::terminal_view::renderer::draw_text;
+
+/// 單一分歧
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    /// 分歧對象（實體 ID 或玩家名稱）
+    pub key: String,
+    /// 左側位置（不存在時為 None）
+    pub left_position: Option<Vec2<f32>>,
+    /// 右側位置（不存在時為 None）
+    pub right_position: Option<Vec2<f32>>,
+    /// 位置差距
+    pub position_delta: f32,
+    /// 生命值差距
+    pub health_delta: f32,
+}
+
+/// 比較統計
+#[derive(Debug, Default)]
+struct ComparisonSummary {
+    /// 出現分歧的幀數
+    divergent_frames: u64,
+    /// 總幀數
+    total_frames: u64,
+    /// 最大位置差距
+    max_position_delta: f32,
+    /// 第一次出現分歧的比賽時間（毫秒）
+    first_divergence_ms: Option<u64>,
+}
+
+/// 比較兩個遊戲狀態（本地英雄、實體與其他玩家），找出位置或生命值超過門檻的分歧
+pub fn compute_divergences(left: &GameState, right: &GameState, threshold: f32) -> Vec<Divergence> {
+    let mut divergences = Vec::new();
+
+    // 本地英雄兩側都存在
+    let (l, r) = (&left.local_player, &right.local_player);
+    let hero = Divergence {
+        key: format!("hero:{}", l.name),
+        left_position: Some(l.position),
+        right_position: Some(r.position),
+        position_delta: (l.position - r.position).magnitude(),
+        health_delta: (l.health.0 - r.health.0).abs(),
+    };
+    if hero.position_delta > threshold || hero.health_delta > 1.0 {
+        divergences.push(hero);
+    }
+
+    // 以實體 ID 對應
+    let mut entity_ids: Vec<u32> = left.entities.keys().chain(right.entities.keys()).copied().collect();
+    entity_ids.sort_unstable();
+    entity_ids.dedup();
+
+    for id in entity_ids {
+        let l = left.entities.get(&id);
+        let r = right.entities.get(&id);
+        let divergence = Divergence {
+            key: format!("entity#{}", id),
+            left_position: l.map(|e| e.position),
+            right_position: r.map(|e| e.position),
+            position_delta: match (l, r) {
+                (Some(l), Some(r)) => (l.position - r.position).magnitude(),
+                _ => f32::INFINITY,
+            },
+            health_delta: match (l, r) {
+                (Some(l), Some(r)) => (l.health.0 - r.health.0).abs(),
+                _ => 0.0,
+            },
+        };
+        if divergence.position_delta > threshold || divergence.health_delta > 1.0 {
+            divergences.push(divergence);
+        }
+    }
+
+    // 以玩家名稱對應
+    let mut names: Vec<&String> = left.other_players.keys().chain(right.other_players.keys()).collect();
+    names.sort();
+    names.dedup();
+
+    for name in names {
+        let l = left.other_players.get(name);
+        let r = right.other_players.get(name);
+        let to_vec = |p: (f32, f32)| Vec2::new(p.0, p.1);
+        let divergence = Divergence {
+            key: format!("player:{}", name),
+            left_position: l.map(|p| to_vec(p.position)),
+            right_position: r.map(|p| to_vec(p.position)),
+            position_delta: match (l, r) {
+                (Some(l), Some(r)) => (to_vec(l.position) - to_vec(r.position)).magnitude(),
+                _ => f32::INFINITY,
+            },
+            health_delta: match (l, r) {
+                (Some(l), Some(r)) => (l.health.0 - r.health.0).abs(),
+                _ => 0.0,
+            },
+        };
+        if divergence.position_delta > threshold || divergence.health_delta > 1.0 {
+            divergences.push(divergence);
+        }
+    }
+
+    divergences
+}
+
+/// 錄製的比賽時鐘：第一則帶時間戳的畫面回應的比賽時間，與比賽時間減去錄製時間的偏移
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct MatchClock {
+    first_ms: i64,
+    offset_ms: i64,
+}
+
+impl MatchClock {
+    /// 從錄製中第一則帶時間戳（非 0）的畫面回應推得
+    fn of(recording: &Recording) -> Option<Self> {
+        recording.messages.iter()
+            .filter(|message| message.direction == RecordDirection::Incoming)
+            .find_map(|message| {
+                let bytes = message.payload_bytes();
+                let value: serde_json::Value = serde_json::from_slice(&bytes)
+                    .or_else(|_| WireFormat::MessagePack.decode(&bytes)).ok()?;
+                if !matches!(value.get("t").and_then(|t| t.as_str()), Some("screen_response" | "screen_delta")) {
+                    return None;
+                }
+                let timestamp = value.get("d")?.get("timestamp")?.as_u64().filter(|timestamp| *timestamp > 0)? as i64;
+                Some(Self { first_ms: timestamp, offset_ms: timestamp - message.elapsed_ms as i64 })
+            })
+    }
+
+    /// 比賽時間對應的錄製時間
+    fn elapsed_at(&self, match_ms: i64) -> u64 {
+        (match_ms - self.offset_ms).max(0) as u64
+    }
+}
+
+/// 兩份錄製的比賽時鐘（任一份沒有時間戳時兩側皆以錄製時間為比賽時間）
+fn match_clocks(left: &Recording, right: &Recording) -> (MatchClock, MatchClock) {
+    match (MatchClock::of(left), MatchClock::of(right)) {
+        (Some(left), Some(right)) => (left, right),
+        _ => {
+            warn!("錄製中沒有帶時間戳的畫面回應，改以錄製時間對齊");
+            let elapsed = MatchClock { first_ms: 0, offset_ms: 0 };
+            (elapsed, elapsed)
+        }
+    }
+}
+
+/// 回放比較會話
+pub struct ReplayComparison {
+    left: RecordingCursor,
+    right: RecordingCursor,
+    /// 兩側的比賽時鐘
+    clocks: (MatchClock, MatchClock),
+    /// 同步播放起點的比賽時間（兩份錄製都已開始）
+    start_ms: i64,
+    left_state: GameState,
+    right_state: GameState,
+    mqtt_handler: MqttHandler,
+    transform: CoordinateTransform,
+    threshold: f32,
+    speed: f32,
+    paused: bool,
+    /// 自同步起點經過的比賽時間
+    match_time_ms: f64,
+    summary: ComparisonSummary,
+}
+
+impl ReplayComparison {
+    /// 載入兩份錄製（主題與座標轉換取自 `config`）
+    pub fn new(left_path: &Path, right_path: &Path, player_name: &str, hero_type: &str, threshold: f32, speed: f32, config: &AppConfig) -> Result<Self> {
+        let left = Recording::load(left_path)?;
+        let right = Recording::load(right_path)?;
+
+        info!("載入比較錄製: {} ({} 筆) vs {} ({} 筆)",
+              left.name, left.messages.len(), right.name, right.messages.len());
+        Ok(Self::from_recordings(left, right, player_name, hero_type, threshold, speed, config))
+    }
+
+    fn from_recordings(left: Recording, right: Recording, player_name: &str, hero_type: &str, threshold: f32, speed: f32, config: &AppConfig) -> Self {
+        let clocks = match_clocks(&left, &right);
+        Self {
+            start_ms: clocks.0.first_ms.max(clocks.1.first_ms),
+            clocks,
+            left: RecordingCursor::new(left),
+            right: RecordingCursor::new(right),
+            left_state: GameState::new(player_name.to_string(), hero_type.to_string()),
+            right_state: GameState::new(player_name.to_string(), hero_type.to_string()),
+            mqtt_handler: MqttHandler::new().with_topics(config.server.topics.clone()),
+            transform: CoordinateTransform::from_config(&config.coordinates),
+            threshold,
+            speed,
+            paused: false,
+            match_time_ms: 0.0,
+            summary: ComparisonSummary::default(),
+        }
+    }
+
+    /// 運行比較視圖（按 q 或 Esc 退出）
+    pub async fn run(&mut self) -> Result<()> {
+        let renderer = MapRenderer::new();
+        let (term_width, term_height) = terminal::size()?;
+        let map_height = term_height.saturating_sub(3); // 留出日誌區域空間
+        let half_width = term_width.saturating_sub(1) / 2;
+        let mut viewport = ViewportManager::new(half_width as f32, map_height as f32);
+        viewport.transform = self.transform;
+
+        renderer.init_terminal()?;
+        let result = self.run_loop(&renderer, &viewport, term_width, map_height, half_width).await;
+        let _ = renderer.cleanup_terminal();
+        result?;
+
+        self.print_summary();
+        Ok(())
+    }
+
+    async fn run_loop(
+        &mut self,
+        renderer: &MapRenderer,
+        viewport: &ViewportManager,
+        term_width: u16,
+        map_height: u16,
+        half_width: u16,
+    ) -> Result<()> {
+        let mut last_tick = Instant::now();
+
+        loop {
+            // 處理鍵盤輸入
+            while event::poll(Duration::from_millis(0))? {
+                if let Event::Key(key) = event::read()? {
+                    match key.code {
+                        KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                        KeyCode::Char(' ') => self.paused = !self.paused,
+                        KeyCode::Char('+') => self.speed = (self.speed * 2.0).min(16.0),
+                        KeyCode::Char('-') => self.speed = (self.speed / 2.0).max(0.125),
+                        KeyCode::Right => self.match_time_ms += 5000.0,
+                        _ => {}
+                    }
+                }
+            }
+
+            // 推進比賽時間
+            let dt = last_tick.elapsed().as_secs_f64() * 1000.0;
+            last_tick = Instant::now();
+            if !self.paused {
+                self.match_time_ms += dt * self.speed as f64;
+            }
+            self.apply_due_messages().await;
+
+            let divergences = compute_divergences(&self.left_state, &self.right_state, self.threshold);
+            self.update_summary(&divergences);
+
+            let grid = self.compose_frame(renderer, viewport, &divergences, term_width, map_height, half_width);
+            renderer.render_grid(&grid, term_width, map_height)?;
+
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    /// 把到期的訊息送入各自的 MqttHandler 處理（依比賽時間換算各自的錄製時間）
+    async fn apply_due_messages(&mut self) {
+        let now = self.start_ms + self.match_time_ms as i64;
+        for message in self.left.advance_to(self.clocks.0.elapsed_at(now)) {
+            let publish = Publish::new(message.topic.clone(), QoS::AtMostOnce, message.payload_bytes());
+            if let Err(e) = self.mqtt_handler.handle_message(&publish, &mut self.left_state).await {
+                warn!("左側回放訊息處理失敗: {}", e);
+            }
+        }
+        for message in self.right.advance_to(self.clocks.1.elapsed_at(now)) {
+            let publish = Publish::new(message.topic.clone(), QoS::AtMostOnce, message.payload_bytes());
+            if let Err(e) = self.mqtt_handler.handle_message(&publish, &mut self.right_state).await {
+                warn!("右側回放訊息處理失敗: {}", e);
+            }
+        }
+    }
+
+    fn update_summary(&mut self, divergences: &[Divergence]) {
+        self.summary.total_frames += 1;
+        if divergences.is_empty() {
+            return;
+        }
+        self.summary.divergent_frames += 1;
+        if self.summary.first_divergence_ms.is_none() {
+            self.summary.first_divergence_ms = Some(self.match_time_ms as u64);
+        }
+        for d in divergences.iter().filter(|d| d.position_delta.is_finite()) {
+            self.summary.max_position_delta = self.summary.max_position_delta.max(d.position_delta);
+        }
+    }
+
+    /// 組合左右兩側的地圖網格
+    fn compose_frame(
+        &self,
+        renderer: &MapRenderer,
+        viewport: &ViewportManager,
+        divergences: &[Divergence],
+        term_width: u16,
+        map_height: u16,
+        half_width: u16,
+    ) -> Vec<Vec<MapDisplay>> {
+        let mut left_grid = renderer.build_map_grid(&self.left_state, viewport, half_width, map_height);
+        let mut right_grid = renderer.build_map_grid(&self.right_state, viewport, half_width, map_height);
+
+        // 標示分歧位置
+        for d in divergences {
+            if let Some(pos) = d.left_position {
+                Self::highlight(&mut left_grid, viewport, pos, self.left_state.local_player.position);
+            }
+            if let Some(pos) = d.right_position {
+                Self::highlight(&mut right_grid, viewport, pos, self.right_state.local_player.position);
+            }
+        }
+
+        draw_text(&mut left_grid, 0, 0, &format!("A: {}", self.left.recording().name), Color::Cyan);
+        draw_text(&mut right_grid, 0, 0, &format!("B: {}", self.right.recording().name), Color::Cyan);
+
+        // 合併成完整網格，中間以分隔線隔開
+        let separator = MapDisplay { symbol: '│', color: Color::DarkGrey };
+        let mut grid: Vec<Vec<MapDisplay>> = left_grid.into_iter()
+            .zip(right_grid)
+            .map(|(mut row, right_row)| {
+                row.push(separator);
+                row.extend(right_row);
+                row
+            })
+            .collect();
+
+        // 底部狀態列
+        let finished = self.left.is_finished() && self.right.is_finished();
+        let status = format!(
+            "t={:.1}s {:.2}x{} | 分歧: {} | 空白鍵暫停 +/- 速度 → 快轉 q 退出",
+            self.match_time_ms / 1000.0,
+            self.speed,
+            if self.paused { " (暫停)" } else if finished { " (回放結束)" } else { "" },
+            divergences.len(),
+        );
+        let status_color = if divergences.is_empty() { Color::Green } else { Color::Red };
+        if let Some(last) = grid.len().checked_sub(1) {
+            for cell in grid[last].iter_mut().take(term_width as usize) {
+                *cell = MapDisplay { symbol: ' ', color: Color::White };
+            }
+            draw_text(&mut grid, 0, last, &status, status_color);
+        }
+
+        grid
+    }
+
+    fn highlight(grid: &mut [Vec<MapDisplay>], viewport: &ViewportManager, pos: Vec2<f32>, camera: Vec2<f32>) {
+        let height = grid.len();
+        let width = grid.first().map(|r| r.len()).unwrap_or(0);
+        if let Some((x, y)) = viewport.world_to_screen(pos, camera, width, height) {
+            grid[y][x].color = Color::Red;
+            if grid[y][x].symbol == MapDisplay::EMPTY.symbol {
+                grid[y][x].symbol = '!';
+            }
+        }
+    }
+
+    /// 輸出比較摘要
+    fn print_summary(&self) {
+        println!("=== 回放比較摘要 ===");
+        println!("A: {}", self.left.recording().name);
+        println!("B: {}", self.right.recording().name);
+        println!("比賽時間: {:.1}s / {:.1}s",
+                 self.match_time_ms / 1000.0,
+                 self.left.recording().duration_ms().max(self.right.recording().duration_ms()) as f64 / 1000.0);
+        println!("分歧幀數: {}/{}", self.summary.divergent_frames, self.summary.total_frames);
+        println!("最大位置差距: {:.2}", self.summary.max_position_delta);
+        match self.summary.first_divergence_ms {
+            Some(ms) => println!("首次分歧: {:.1}s", ms as f64 / 1000.0),
+            None => println!("未發現分歧"),
+        }
+
+        let final_divergences = compute_divergences(&self.left_state, &self.right_state, self.threshold);
+        if !final_divergences.is_empty() {
+            println!("最終分歧 ({}):", final_divergences.len());
+            for d in final_divergences.iter().take(20) {
+                if d.position_delta.is_infinite() {
+                    println!("  {} - 僅存在於一側", d.key);
+                } else {
+                    println!("  {} - 位置差距 {:.2}, 生命值差距 {:.1}", d.key, d.position_delta, d.health_delta);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::{Entity, EntityType};
+    use crate::mqtt_handler::PlayerState;
+    use crate::recording::RecordedMessage;
+
+    fn creep(id: u32, x: f32, health: f32) -> Entity {
+        Entity { id, entity_type: EntityType::Creep, position: Vec2::new(x, 0.0), health: (health, 300.0), owner: None, debug_label: None }
+    }
+
+    fn player(name: &str, x: f32) -> PlayerState {
+        PlayerState {
+            name: name.to_string(),
+            hero_type: "saika_magoichi".to_string(),
+            position: (x, 0.0),
+            health: (100.0, 100.0),
+            abilities: Vec::new(),
+            summons: Vec::new(),
+        }
+    }
+
+    fn recording(name: &str, frames: &[(u64, u64, f32)]) -> Recording {
+        let messages = frames.iter().map(|&(elapsed_ms, timestamp, x)| {
+            let payload = serde_json::json!({ "t": "screen_response", "d": {
+                "timestamp": timestamp,
+                "entities": [{ "id": 1, "entity_type": "creep", "position": [x, 0.0], "health": [300.0, 300.0], "state": "idle" }],
+            } });
+            RecordedMessage::new(elapsed_ms, RecordDirection::Incoming, "td/p1/screen_response", payload.to_string().as_bytes())
+        }).collect();
+        Recording { name: name.to_string(), messages }
+    }
+
+    #[tokio::test]
+    async fn aligns_recordings_on_match_time() {
+        // A 從比賽 10 秒開始錄，B 從 11 秒開始錄：兩者在相同比賽時間的畫面一致
+        let left = recording("a", &[(0, 10_000, 0.0), (1_000, 11_000, 10.0), (2_000, 12_000, 20.0)]);
+        let right = recording("b", &[(0, 11_000, 10.0), (1_000, 12_000, 20.0)]);
+        let mut comparison = ReplayComparison::from_recordings(left, right, "p1", "saika_magoichi", 1.0, 1.0, &AppConfig::default());
+        assert_eq!(comparison.start_ms, 11_000, "從兩份都已開始的比賽時間起播放");
+
+        comparison.apply_due_messages().await;
+        assert_eq!(comparison.left_state.entities[&1].position.x, 10.0);
+        assert!(compute_divergences(&comparison.left_state, &comparison.right_state, 1.0).is_empty());
+        comparison.match_time_ms = 1_000.0;
+        comparison.apply_due_messages().await;
+        assert_eq!(comparison.right_state.entities[&1].position.x, 20.0);
+        assert!(compute_divergences(&comparison.left_state, &comparison.right_state, 1.0).is_empty());
+
+        // 沒有時間戳時以錄製時間對齊
+        let left = recording("a", &[(0, 0, 0.0), (1_000, 0, 10.0)]);
+        let right = recording("b", &[(0, 11_000, 10.0)]);
+        let mut comparison = ReplayComparison::from_recordings(left, right, "p1", "saika_magoichi", 1.0, 1.0, &AppConfig::default());
+        comparison.apply_due_messages().await;
+        assert_eq!(comparison.left_state.entities[&1].position.x, 0.0);
+    }
+
+    #[test]
+    fn reports_position_health_and_one_sided_divergences() {
+        let mut left = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        let mut right = left.clone();
+        // 1: 門檻內；2: 位置超過門檻；3: 生命值相差超過 1；4: 只在左側
+        for (l, r) in [(creep(1, 10.0, 300.0), creep(1, 10.5, 300.0)), (creep(2, 0.0, 300.0), creep(2, 5.0, 300.0)), (creep(3, 0.0, 300.0), creep(3, 0.0, 250.0))] {
+            left.entities.insert(l.id, l);
+            right.entities.insert(r.id, r);
+        }
+        left.entities.insert(4, creep(4, 0.0, 300.0));
+        left.other_players.insert("p2".to_string(), player("p2", 0.0));
+        right.other_players.insert("p2".to_string(), player("p2", 3.0));
+        right.other_players.insert("p3".to_string(), player("p3", 0.0));
+
+        let divergences = compute_divergences(&left, &right, 1.0);
+        let keys: Vec<&str> = divergences.iter().map(|d| d.key.as_str()).collect();
+        assert_eq!(keys, ["entity#2", "entity#3", "entity#4", "player:p2", "player:p3"], "本地英雄一致時不列出");
+        assert_eq!(divergences[0].position_delta, 5.0);
+        assert_eq!(divergences[1].health_delta, 50.0);
+        assert!(divergences[2].position_delta.is_infinite());
+        assert_eq!((divergences[2].left_position, divergences[2].right_position), (Some(Vec2::new(0.0, 0.0)), None));
+        assert_eq!(divergences[3].position_delta, 3.0);
+        assert_eq!(divergences[4].left_position, None);
+
+        assert_eq!(compute_divergences(&left, &right, 10.0).len(), 3, "門檻放寬後只剩生命值與單側分歧");
+        assert!(compute_divergences(&left, &left, 0.0).is_empty());
+
+        right.local_player.position.x += 5.0;
+        right.local_player.health.0 -= 10.0;
+        let hero = &compute_divergences(&left, &right, 1.0)[0];
+        assert_eq!(hero.key, "hero:p1");
+        assert_eq!((hero.position_delta, hero.health_delta), (5.0, 10.0));
+    }
+}
@@ -0,0 +1,214 @@
+/// 後端請求與回應的關聯
+///
+/// 每個送往後端的請求帶上 UUID 格式的 `id`，後端在回應中原樣帶回（頂層 `id` 或 `d.request_id`），
+/// 事件循環依 ID 找到等待中的請求並喚醒呼叫端；超過時限仍未回應的請求會被移除並計入逾時，
+/// 回應遺失或對應不到請求時都能在統計中看出來，而不是像送出即忘的請求一樣無從得知；
+/// 玩家操作的回應另外記錄為操作確認延遲，供演示序列的效能門檻使用
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use rand::Rng;
+use serde::Deserialize;
+use tokio::sync::oneshot;
+
+use crate::mqtt_handler::WireFormat;
+use crate::perf::SharedPerf;
+
+/// 後端回應
+#[derive(Debug, Clone)]
+pub struct RpcResponse {
+    pub id: String,
+    pub topic: String,
+    /// 解碼後的完整訊息
+    pub message: serde_json::Value,
+    /// 從送出到收到回應的時間
+    pub latency: Duration,
+}
+
+/// 等待回應的請求
+#[derive(Debug)]
+struct PendingRequest {
+    action: String,
+    sent: Instant,
+    /// 等待回應的呼叫端（送出即忘的請求為 None，只追蹤回應與逾時）
+    waiter: Option<oneshot::Sender<RpcResponse>>,
+    /// 玩家操作（回應時記錄操作確認延遲）
+    is_action: bool,
+}
+
+/// 請求統計
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RpcStats {
+    pub sent: u64,
+    pub answered: u64,
+    pub timed_out: u64,
+    /// 帶有 ID 但對應不到請求的回應（多半是逾時後才到）
+    pub unmatched: u64,
+}
+
+#[derive(Debug, Default)]
+struct RequestTable {
+    pending: HashMap<String, PendingRequest>,
+    stats: RpcStats,
+}
+
+/// 在客戶端、畫面請求循環與事件循環間共享的請求表
+#[derive(Debug, Clone, Default)]
+pub struct RequestManager {
+    inner: Arc<Mutex<RequestTable>>,
+    /// 記錄操作確認延遲的效能指標
+    perf: SharedPerf,
+}
+
+impl RequestManager {
+    /// 登記送出的請求，返回請求 ID 與等待回應的接收端
+    pub fn register(&self, action: &str) -> (String, oneshot::Receiver<RpcResponse>) {
+        let (sender, receiver) = oneshot::channel();
+        (self.insert(action, Some(sender), false), receiver)
+    }
+
+    /// 登記送出即忘的請求（只追蹤回應與逾時），返回請求 ID
+    pub fn track(&self, action: &str) -> String {
+        self.insert(action, None, false)
+    }
+
+    /// 登記玩家操作（回應時記錄操作確認延遲），返回請求 ID
+    pub fn track_action(&self, action: &str) -> String {
+        self.insert(action, None, true)
+    }
+
+    /// 使用與客戶端共享的效能指標
+    pub fn with_perf(mut self, perf: SharedPerf) -> Self {
+        self.perf = perf;
+        self
+    }
+
+    fn insert(&self, action: &str, waiter: Option<oneshot::Sender<RpcResponse>>, is_action: bool) -> String {
+        let id = new_request_id();
+        let mut table = self.inner.lock().unwrap();
+        table.stats.sent += 1;
+        table.pending.insert(id.clone(), PendingRequest { action: action.to_string(), sent: Instant::now(), waiter, is_action });
+        id
+    }
+
+    /// 把回應交給對應的請求，返回是否有對應（只在有呼叫端等待時才以 message 產生回應內容）
+    pub fn resolve(&self, id: &str, topic: &str, message: impl FnOnce() -> serde_json::Value) -> bool {
+        let mut table = self.inner.lock().unwrap();
+        let Some(request) = table.pending.remove(id) else {
+            table.stats.unmatched += 1;
+            return false;
+        };
+        table.stats.answered += 1;
+        if request.is_action {
+            self.perf.lock().unwrap().record_action_ack(request.sent.elapsed());
+        }
+        if let Some(waiter) = request.waiter {
+            let _ = waiter.send(RpcResponse {
+                id: id.to_string(),
+                topic: topic.to_string(),
+                message: message(),
+                latency: request.sent.elapsed(),
+            });
+        }
+        true
+    }
+
+    /// 放棄請求（發佈失敗時），不計入逾時
+    pub fn cancel(&self, id: &str) {
+        let mut table = self.inner.lock().unwrap();
+        if table.pending.remove(id).is_some() {
+            table.stats.sent -= 1;
+        }
+    }
+
+    /// 呼叫端等待逾時：移除請求並計入逾時
+    pub fn time_out(&self, id: &str) {
+        let mut table = self.inner.lock().unwrap();
+        if table.pending.remove(id).is_some() {
+            table.stats.timed_out += 1;
+        }
+    }
+
+    /// 移除送出超過時限仍未回應的請求，返回逾時請求的操作名稱
+    pub fn expire(&self, timeout: Duration, now: Instant) -> Vec<String> {
+        let mut table = self.inner.lock().unwrap();
+        let expired: Vec<String> = table.pending.iter()
+            .filter(|(_, request)| now.duration_since(request.sent) >= timeout)
+            .map(|(id, _)| id.clone())
+            .collect();
+        table.stats.timed_out += expired.len() as u64;
+        expired.iter()
+            .filter_map(|id| table.pending.remove(id))
+            .map(|request| request.action)
+            .collect()
+    }
+
+    /// 等待回應中的請求數
+    pub fn pending(&self) -> usize {
+        self.inner.lock().unwrap().pending.len()
+    }
+
+    /// 請求統計
+    pub fn stats(&self) -> RpcStats {
+        self.inner.lock().unwrap().stats
+    }
+}
+
+/// 產生 UUID v4 格式的請求 ID
+pub fn new_request_id() -> String {
+    let mut bytes: [u8; 16] = rand::rng().random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// 只解碼關聯用欄位的回應（其餘欄位直接略過，不建立完整的 JSON 樹）
+#[derive(Debug, Deserialize)]
+struct Correlation {
+    id: Option<String>,
+    d: Option<CorrelationData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CorrelationData {
+    request_id: Option<String>,
+}
+
+/// 回應中帶回的請求 ID（頂層 `id` 或 `d.request_id`）
+pub fn response_id(format: WireFormat, payload: &[u8]) -> Option<String> {
+    let correlation = format.decode::<Correlation>(payload).ok()?;
+    correlation.id.or_else(|| correlation.d?.request_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn correlates_responses_and_expires_unanswered_requests() {
+        let requests = RequestManager::default();
+        let (id, reply) = requests.register("get_screen_area");
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[14..15], "4");
+        let tracked = requests.track("get_area");
+        let action = requests.track_action("move");
+        assert_eq!(requests.pending(), 3);
+
+        let response = json!({ "t": "screen_response", "d": { "request_id": id, "entities": [1, 2] } });
+        let payload = serde_json::to_vec(&response).unwrap();
+        assert_eq!(response_id(WireFormat::Json, &payload), Some(id.clone()));
+        assert!(requests.resolve(&id, "td/p1/screen_response", || response));
+        let response = reply.await.unwrap();
+        assert_eq!(response.topic, "td/p1/screen_response");
+
+        assert!(!requests.resolve(&id, "td/p1/screen_response", || json!({})), "同一個 ID 只對應一次");
+        assert!(requests.resolve(&action, "td/p1/response", || unreachable!("沒有呼叫端等待")));
+        assert_eq!(requests.perf.lock().unwrap().action_ack_ms.len(), 1);
+        assert!(requests.expire(Duration::from_secs(5), Instant::now()).is_empty());
+        assert_eq!(requests.expire(Duration::ZERO, Instant::now()), vec!["get_area".to_string()]);
+        assert!(!requests.resolve(&tracked, "td/p1/screen_response", || unreachable!("逾時的請求不解碼回應")));
+        assert_eq!(requests.stats(), RpcStats { sent: 3, answered: 2, timed_out: 1, unmatched: 2 });
+    }
+}
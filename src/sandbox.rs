@@ -0,0 +1,170 @@
+/// 練習沙盒
+///
+/// `sandbox on` 後，每次使用技能或道具就以作弊操作 reset_cooldowns 要求後端重置冷卻，英雄受傷或道具次數減少時
+/// 以 refill 補滿生命值與道具次數，並每隔固定時間重新生成同一組訓練假人，
+/// 讓設計者不必等冷卻或重開對局就能反覆測試同一組技能數值
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use colored::*;
+
+use crate::config::SandboxConfig;
+use crate::game_state::GameState;
+
+/// 沙盒要求後端執行的作弊操作
+#[derive(Debug, Clone, PartialEq)]
+pub enum SandboxAction {
+    /// 重置冷卻中的技能與道具
+    ResetCooldowns { abilities: Vec<String>, items: Vec<String> },
+    /// 補滿生命值與道具次數
+    Refill { health: bool, items: bool },
+    /// 移除並重新生成訓練假人
+    RespawnDummies { count: usize, hp: f32 },
+}
+
+/// 沙盒統計
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SandboxStats {
+    pub resets: u64,
+    pub refills: u64,
+    pub respawns: u64,
+}
+
+/// 練習沙盒狀態
+#[derive(Debug, Clone, Default)]
+pub struct Sandbox {
+    config: SandboxConfig,
+    active: bool,
+    /// 上次看到的道具次數（偵測道具使用）
+    charges: HashMap<String, u32>,
+    last_reset: Option<Instant>,
+    last_refill: Option<Instant>,
+    /// 循環生成的訓練假人（數量、生命值）與上次生成的時間
+    scenario: Option<(usize, f32, Instant)>,
+    stats: SandboxStats,
+}
+
+impl Sandbox {
+    /// 更新配置
+    pub fn set_config(&mut self, config: &SandboxConfig) {
+        self.config = config.clone();
+    }
+
+    /// 是否啟用
+    pub fn active(&self) -> bool {
+        self.active
+    }
+
+    /// 啟用或停用（重新啟用時立即重置一次）
+    pub fn set_active(&mut self, active: bool) {
+        self.active = active;
+        self.last_reset = None;
+        self.last_refill = None;
+        self.charges.clear();
+    }
+
+    /// 記住生成的訓練假人，之後依配置的間隔循環重新生成
+    pub fn remember_dummies(&mut self, count: usize, hp: f32, now: Instant) {
+        self.scenario = Some((count, hp, now));
+    }
+
+    /// 不再循環生成訓練假人
+    pub fn forget_dummies(&mut self) {
+        self.scenario = None;
+    }
+
+    /// 統計
+    pub fn stats(&self) -> SandboxStats {
+        self.stats
+    }
+
+    /// 依目前狀態決定要送出的作弊操作（未啟用時為空）
+    pub fn check(&mut self, state: &GameState, now: Instant) -> Vec<SandboxAction> {
+        let mut actions = Vec::new();
+        if !self.active {
+            return actions;
+        }
+        let player = &state.local_player;
+        let interval = Duration::from_millis(self.config.reset_interval_ms);
+        let due = |last: Option<Instant>| last.is_none_or(|last| now.duration_since(last) >= interval);
+
+        let abilities: Vec<String> = player.abilities.iter()
+            .filter(|ability| ability.cooldown_remaining > 0.0)
+            .map(|ability| ability.ability_id.clone())
+            .collect();
+        let items: Vec<String> = player.items.iter()
+            .filter(|item| item.cooldown_remaining > 0.0)
+            .map(|item| item.item_id.clone())
+            .collect();
+        if (!abilities.is_empty() || !items.is_empty()) && due(self.last_reset) {
+            self.last_reset = Some(now);
+            self.stats.resets += 1;
+            actions.push(SandboxAction::ResetCooldowns { abilities, items });
+        }
+
+        let spent = self.config.refill_items && player.items.iter()
+            .any(|item| self.charges.get(&item.item_id).is_some_and(|charges| item.charges < *charges));
+        let hurt = self.config.refill_health && player.health.0 < player.health.1;
+        if (spent || hurt) && due(self.last_refill) {
+            self.last_refill = Some(now);
+            self.stats.refills += 1;
+            actions.push(SandboxAction::Refill { health: self.config.refill_health, items: self.config.refill_items });
+        }
+        // 補滿前的次數不列為基準，補滿後回到原本的次數不算使用
+        for item in &player.items {
+            let charges = self.charges.entry(item.item_id.clone()).or_insert(item.charges);
+            *charges = (*charges).max(item.charges);
+        }
+
+        let loop_after = Duration::from_secs_f32(self.config.dummy_loop_secs.max(0.0));
+        if let Some((count, hp, spawned)) = &mut self.scenario {
+            if !loop_after.is_zero() && now.duration_since(*spawned) >= loop_after {
+                *spawned = now;
+                self.stats.respawns += 1;
+                actions.push(SandboxAction::RespawnDummies { count: *count, hp: *hp });
+            }
+        }
+        actions
+    }
+
+    /// 顯示狀態
+    pub fn print(&self) {
+        let state = if self.active { "啟用".green() } else { "停用".bright_black() };
+        println!("\n{} {}", "練習沙盒:".bright_cyan().bold(), state);
+        println!("  重置冷卻 {} 次，補滿 {} 次，重新生成假人 {} 次", self.stats.resets, self.stats.refills, self.stats.respawns);
+        match self.scenario {
+            Some((count, hp, _)) if self.config.dummy_loop_secs > 0.0 => println!("  訓練假人: {} 個（生命值 {:.0}），每 {:.0} 秒重新生成",
+                count, hp, self.config.dummy_loop_secs),
+            _ => println!("  {}", "沒有循環的訓練假人（以 dummy spawn 生成）".bright_black()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resets_after_use_and_loops_the_dummy_scenario() {
+        let mut sandbox = Sandbox::default();
+        sandbox.set_config(&SandboxConfig { reset_interval_ms: 200, dummy_loop_secs: 10.0, ..SandboxConfig::default() });
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        let start = Instant::now();
+        state.local_player.abilities[0].cooldown_remaining = 5.0;
+        assert!(sandbox.check(&state, start).is_empty(), "未啟用時不動作");
+
+        sandbox.set_active(true);
+        sandbox.remember_dummies(2, 500.0, start);
+        let ability = state.local_player.abilities[0].ability_id.clone();
+        assert_eq!(sandbox.check(&state, start), vec![SandboxAction::ResetCooldowns { abilities: vec![ability], items: vec![] }]);
+        assert!(sandbox.check(&state, start + Duration::from_millis(100)).is_empty(), "等待後端重置");
+
+        state.local_player.abilities[0].cooldown_remaining = 0.0;
+        state.local_player.health.0 = state.local_player.health.1 - 10.0;
+        let actions = sandbox.check(&state, start + Duration::from_secs(10));
+        assert_eq!(actions, vec![
+            SandboxAction::Refill { health: true, items: true },
+            SandboxAction::RespawnDummies { count: 2, hp: 500.0 },
+        ]);
+        assert_eq!(sandbox.stats(), SandboxStats { resets: 1, refills: 1, respawns: 1 });
+    }
+}
@@ -0,0 +1,344 @@
+/// 伺服器資料合理性監控
+///
+/// 類似反作弊的檢查：比較連續兩次同步的伺服器狀態，標記物理上不可能的更新
+/// （單位瞬移超過最大速度、負生命值、未施法但冷卻時間增加、超出地圖邊界），
+/// 以及與傷害貢獻不符的擊殺/助攻歸屬，作為協議異常記錄下來，以自動發現後端模擬的錯誤
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use log::warn;
+use vek::Vec2;
+
+use crate::config::SanityConfig;
+use crate::game_state::GameState;
+
+/// 保留的異常記錄上限
+const MAX_ANOMALIES: usize = 200;
+
+/// 施法後允許冷卻增加的時間窗口（秒）
+const CAST_GRACE_SECS: f32 = 2.0;
+
+/// 異常類型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnomalyKind {
+    /// 位移超過最大速度
+    Teleport,
+    /// 生命值為負
+    NegativeHealth,
+    /// 生命值超過上限
+    HealthOverMax,
+    /// 未施法但冷卻時間增加
+    CooldownIncreased,
+    /// 位置超出地圖邊界
+    OutOfBounds,
+    /// 擊殺/助攻歸屬與傷害貢獻不符
+    CreditMismatch,
+}
+
+impl AnomalyKind {
+    /// 顯示名稱
+    pub fn label(&self) -> &'static str {
+        match self {
+            AnomalyKind::Teleport => "瞬移",
+            AnomalyKind::NegativeHealth => "負生命值",
+            AnomalyKind::HealthOverMax => "生命值超過上限",
+            AnomalyKind::CooldownIncreased => "冷卻異常增加",
+            AnomalyKind::OutOfBounds => "超出地圖邊界",
+            AnomalyKind::CreditMismatch => "擊殺歸屬不符",
+        }
+    }
+}
+
+/// 協議異常
+#[derive(Debug, Clone)]
+pub struct Anomaly {
+    /// 異常類型
+    pub kind: AnomalyKind,
+    /// 發生異常的對象（例如 `entity#12`、`player:Alice`）
+    pub subject: String,
+    /// 詳細說明
+    pub details: String,
+    /// 發現時間（Unix 毫秒）
+    pub timestamp_ms: u64,
+}
+
+/// 單位的上一次觀測
+#[derive(Debug, Clone, Copy)]
+struct UnitSample {
+    position: Vec2<f32>,
+    /// 位置最後一次改變的時間（伺服器更新頻率低於每幀，速度以此計算）
+    changed_at: Instant,
+}
+
+/// 合理性監控器
+#[derive(Debug)]
+pub struct SanityMonitor {
+    config: SanityConfig,
+    /// 上一次觀測到的單位位置
+    units: HashMap<String, UnitSample>,
+    /// 上一次觀測到的技能冷卻
+    cooldowns: HashMap<String, f32>,
+    /// 最近本地施放的技能及施放時間
+    recent_casts: HashMap<String, Instant>,
+    /// 目前位於地圖邊界外的單位（只在離開邊界時標記一次）
+    out_of_bounds: HashSet<String>,
+    /// 已檢查過的最後一個擊殺驗證序號
+    last_credit_seq: u64,
+    /// 已發現的異常
+    anomalies: VecDeque<Anomaly>,
+    /// 各類型異常累計次數
+    counts: HashMap<AnomalyKind, u64>,
+}
+
+impl SanityMonitor {
+    /// 創建新的監控器
+    pub fn new(config: SanityConfig) -> Self {
+        Self {
+            config,
+            units: HashMap::new(),
+            cooldowns: HashMap::new(),
+            recent_casts: HashMap::new(),
+            out_of_bounds: HashSet::new(),
+            last_credit_seq: 0,
+            anomalies: VecDeque::new(),
+            counts: HashMap::new(),
+        }
+    }
+
+    /// 記錄本地施放的技能（施法後冷卻增加屬於正常行為）
+    pub fn note_cast(&mut self, ability_id: &str) {
+        self.recent_casts.insert(ability_id.to_string(), Instant::now());
+    }
+
+    /// 檢查最新同步的伺服器狀態，返回本次新發現的異常
+    pub fn check(&mut self, state: &GameState) -> Vec<Anomaly> {
+        if !self.config.enabled {
+            return Vec::new();
+        }
+
+        let now = Instant::now();
+        self.recent_casts.retain(|_, at| now.duration_since(*at).as_secs_f32() < CAST_GRACE_SECS);
+
+        let mut found = Vec::new();
+
+        // 收集所有單位的位置與生命值
+        let mut observed: Vec<(String, Vec2<f32>, (f32, f32))> = Vec::new();
+        let local = &state.local_player;
+        observed.push((format!("player:{}", local.name), local.position, local.health));
+        for (name, player) in &state.other_players {
+            observed.push((
+                format!("player:{}", name),
+                Vec2::new(player.position.0, player.position.1),
+                player.health,
+            ));
+        }
+        for (id, entity) in &state.entities {
+            observed.push((format!("entity#{}", id), entity.position, entity.health));
+        }
+
+        let mut units = HashMap::with_capacity(observed.len());
+        for (subject, position, health) in observed {
+            let mut sample = UnitSample { position, changed_at: now };
+            if let Some(previous) = self.units.get(&subject).copied() {
+                let distance = (position - previous.position).magnitude();
+                let dt = now.duration_since(previous.changed_at).as_secs_f32();
+                let allowed = self.config.max_speed * dt + self.config.teleport_grace;
+                if distance <= f32::EPSILON {
+                    sample.changed_at = previous.changed_at;
+                } else if distance > allowed {
+                    found.push(self.anomaly(
+                        AnomalyKind::Teleport,
+                        &subject,
+                        format!("({:.1}, {:.1}) -> ({:.1}, {:.1}) 位移 {:.1}，{:.2}s 內最多 {:.1}",
+                            previous.position.x, previous.position.y, position.x, position.y,
+                            distance, dt, allowed),
+                    ));
+                }
+            }
+
+            if health.0 < 0.0 {
+                found.push(self.anomaly(
+                    AnomalyKind::NegativeHealth,
+                    &subject,
+                    format!("生命值 {:.1}/{:.1}", health.0, health.1),
+                ));
+            } else if health.1 > 0.0 && health.0 > health.1 + 0.01 {
+                found.push(self.anomaly(
+                    AnomalyKind::HealthOverMax,
+                    &subject,
+                    format!("生命值 {:.1} 超過上限 {:.1}", health.0, health.1),
+                ));
+            }
+
+            if let Some(bounds) = state.world_bounds {
+                if bounds.contains(position) {
+                    self.out_of_bounds.remove(&subject);
+                } else if self.out_of_bounds.insert(subject.clone()) {
+                    found.push(self.anomaly(
+                        AnomalyKind::OutOfBounds,
+                        &subject,
+                        format!("({:.1}, {:.1}) 不在邊界 ({:.1}, {:.1})-({:.1}, {:.1}) 內",
+                            position.x, position.y, bounds.min_x, bounds.min_y, bounds.max_x, bounds.max_y),
+                    ));
+                }
+            }
+
+            units.insert(subject, sample);
+        }
+        self.units = units;
+
+        // 冷卻時間只應隨時間遞減，除非剛施放過技能
+        let mut cooldowns = HashMap::with_capacity(local.abilities.len());
+        for ability in &local.abilities {
+            if let Some(previous) = self.cooldowns.get(&ability.ability_id) {
+                let increase = ability.cooldown_remaining - previous;
+                if increase > self.config.cooldown_tolerance && !self.recent_casts.contains_key(&ability.ability_id) {
+                    found.push(self.anomaly(
+                        AnomalyKind::CooldownIncreased,
+                        &format!("ability:{}", ability.ability_id),
+                        format!("冷卻 {:.2}s -> {:.2}s，期間未施放", previous, ability.cooldown_remaining),
+                    ));
+                }
+            }
+            cooldowns.insert(ability.ability_id.clone(), ability.cooldown_remaining);
+        }
+        self.cooldowns = cooldowns;
+
+        // 上次檢查後新增的擊殺歸屬驗證結果
+        for check in state.credits.checks().iter().filter(|check| check.seq > self.last_credit_seq) {
+            if !check.passed() {
+                found.push(self.anomaly(
+                    AnomalyKind::CreditMismatch,
+                    &format!("kill:{}", check.credit.victim),
+                    format!("{} 擊殺，助攻 [{}]: {}",
+                        check.credit.killer, check.credit.assists.join(", "), check.failures.join("; ")),
+                ));
+            }
+        }
+        if let Some(last) = state.credits.checks().back() {
+            self.last_credit_seq = last.seq;
+        }
+
+        for anomaly in &found {
+            warn!("🚨 協議異常 [{}] {}: {}", anomaly.kind.label(), anomaly.subject, anomaly.details);
+            *self.counts.entry(anomaly.kind).or_insert(0) += 1;
+            if self.anomalies.len() >= MAX_ANOMALIES {
+                self.anomalies.pop_front();
+            }
+            self.anomalies.push_back(anomaly.clone());
+        }
+
+        found
+    }
+
+    fn anomaly(&self, kind: AnomalyKind, subject: &str, details: String) -> Anomaly {
+        Anomaly {
+            kind,
+            subject: subject.to_string(),
+            details,
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64,
+        }
+    }
+
+    /// 最近的異常記錄
+    pub fn anomalies(&self) -> &VecDeque<Anomaly> {
+        &self.anomalies
+    }
+
+    /// 各類型異常累計次數
+    pub fn counts(&self) -> &HashMap<AnomalyKind, u64> {
+        &self.counts
+    }
+
+    /// 清除異常記錄
+    pub fn clear(&mut self) {
+        self.anomalies.clear();
+        self.counts.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::{AbilityState, Entity, EntityType, WorldBounds};
+
+    fn state() -> GameState {
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        state.local_player.position = Vec2::new(100.0, 100.0);
+        state.local_player.health = (100.0, 100.0);
+        state.local_player.abilities = vec![AbilityState {
+            ability_id: "snipe".to_string(), level: 1, cooldown_remaining: 0.0, is_available: true, last_used: None,
+        }];
+        state.entities.insert(7, Entity {
+            id: 7, entity_type: EntityType::Creep, position: Vec2::new(200.0, 100.0), health: (300.0, 300.0), owner: None, debug_label: None,
+        });
+        state
+    }
+
+    fn kinds(found: &[Anomaly]) -> Vec<(AnomalyKind, &str)> {
+        found.iter().map(|anomaly| (anomaly.kind, anomaly.subject.as_str())).collect()
+    }
+
+    #[test]
+    fn flags_teleports_and_invalid_health() {
+        let mut monitor = SanityMonitor::new(SanityConfig::default());
+        let mut state = state();
+        assert!(monitor.check(&state).is_empty(), "第一次觀測只建立基準");
+
+        state.local_player.position = Vec2::new(130.0, 100.0);
+        state.entities.get_mut(&7).unwrap().position = Vec2::new(900.0, 100.0);
+        state.local_player.health = (-5.0, 100.0);
+        let found = monitor.check(&state);
+        assert_eq!(kinds(&found), [(AnomalyKind::NegativeHealth, "player:p1"), (AnomalyKind::Teleport, "entity#7")],
+            "容許距離內的位移不算瞬移");
+
+        state.local_player.health = (150.0, 100.0);
+        assert_eq!(kinds(&monitor.check(&state)), [(AnomalyKind::HealthOverMax, "player:p1")]);
+        assert_eq!(monitor.counts()[&AnomalyKind::Teleport], 1);
+        assert_eq!(monitor.anomalies().len(), 3);
+    }
+
+    #[test]
+    fn flags_cooldown_increases_without_a_cast() {
+        let mut monitor = SanityMonitor::new(SanityConfig::default());
+        let mut state = state();
+        monitor.check(&state);
+
+        state.local_player.abilities[0].cooldown_remaining = 8.0;
+        assert_eq!(kinds(&monitor.check(&state)), [(AnomalyKind::CooldownIncreased, "ability:snipe")]);
+        state.local_player.abilities[0].cooldown_remaining = 7.95;
+        assert!(monitor.check(&state).is_empty(), "冷卻遞減正常");
+
+        monitor.note_cast("snipe");
+        state.local_player.abilities[0].cooldown_remaining = 12.0;
+        assert!(monitor.check(&state).is_empty(), "剛施放過的技能冷卻增加屬於正常");
+    }
+
+    #[test]
+    fn flags_leaving_the_map_once_per_excursion() {
+        let mut monitor = SanityMonitor::new(SanityConfig::default());
+        let mut state = state();
+        state.world_bounds = Some(WorldBounds { min_x: 0.0, min_y: 0.0, max_x: 1000.0, max_y: 1000.0 });
+        state.entities.get_mut(&7).unwrap().position = Vec2::new(5.0, 100.0);
+        monitor.check(&state);
+
+        state.entities.get_mut(&7).unwrap().position = Vec2::new(-10.0, 100.0);
+        assert_eq!(kinds(&monitor.check(&state)), [(AnomalyKind::OutOfBounds, "entity#7")]);
+        assert!(monitor.check(&state).is_empty(), "仍在邊界外時不重複標記");
+
+        state.entities.get_mut(&7).unwrap().position = Vec2::new(10.0, 100.0);
+        assert!(monitor.check(&state).is_empty());
+        state.entities.get_mut(&7).unwrap().position = Vec2::new(-10.0, 100.0);
+        assert_eq!(kinds(&monitor.check(&state)), [(AnomalyKind::OutOfBounds, "entity#7")], "回到邊界內後再次離開會重新標記");
+    }
+
+    #[test]
+    fn disabled_monitor_reports_nothing() {
+        let mut monitor = SanityMonitor::new(SanityConfig { enabled: false, ..SanityConfig::default() });
+        let mut state = state();
+        state.local_player.health = (-1.0, 100.0);
+        assert!(monitor.check(&state).is_empty());
+    }
+}
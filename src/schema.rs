@@ -0,0 +1,188 @@
+/// 訊息結構驗證
+///
+/// 以 serde 衍生出的欄位清單比對收到的訊息數據，依訊息類型統計缺少與多出的欄位，
+/// 後端改名、刪除或新增欄位時能立即看出是哪個訊息的哪個欄位
+use std::collections::BTreeMap;
+use std::fmt;
+use colored::*;
+use serde::de::{self, DeserializeOwned, Deserializer, Visitor};
+
+/// 取得欄位清單時中止反序列化用的錯誤（攜帶結構的欄位名稱）
+#[derive(Debug)]
+struct FieldList(&'static [&'static str]);
+
+impl fmt::Display for FieldList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl std::error::Error for FieldList {}
+
+impl de::Error for FieldList {
+    fn custom<T: fmt::Display>(_msg: T) -> Self {
+        FieldList(&[])
+    }
+}
+
+/// 只接受 struct 的反序列化器：derive 產生的程式碼會把欄位名稱交給 deserialize_struct
+struct FieldProbe;
+
+impl<'de> Deserializer<'de> for FieldProbe {
+    type Error = FieldList;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, FieldList> {
+        Err(FieldList(&[]))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str], _visitor: V) -> Result<V::Value, FieldList> {
+        Err(FieldList(fields))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+    }
+}
+
+/// 結構 `T` 的欄位名稱（非 struct 時為空）
+pub fn fields_of<T: DeserializeOwned>() -> &'static [&'static str] {
+    match T::deserialize(FieldProbe) {
+        Err(FieldList(fields)) => fields,
+        Ok(_) => &[],
+    }
+}
+
+/// 訊息數據與預期結構的欄位差異
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldDiff {
+    /// 預期但數據中沒有的欄位
+    pub missing: Vec<String>,
+    /// 數據中有但結構沒有的欄位
+    pub extra: Vec<String>,
+}
+
+impl FieldDiff {
+    /// 比對數據與結構 `T` 的欄位（數據不是物件時沒有差異）
+    pub fn of<T: DeserializeOwned>(data: &serde_json::Value) -> Self {
+        let Some(object) = data.as_object() else {
+            return Self::default();
+        };
+        let fields = fields_of::<T>();
+        Self {
+            missing: fields.iter().filter(|field| !object.contains_key(**field)).map(|field| field.to_string()).collect(),
+            extra: object.keys().filter(|key| !fields.contains(&key.as_str())).cloned().collect(),
+        }
+    }
+}
+
+/// 單一訊息類型的驗證統計
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SchemaStats {
+    /// 檢查過的訊息數
+    pub checked: u64,
+    /// 無法解析而被拒絕的訊息數
+    pub rejected: u64,
+    /// 被拒絕的訊息中各欄位缺少的次數
+    pub missing: BTreeMap<String, u64>,
+    /// 各多出欄位出現的次數
+    pub extra: BTreeMap<String, u64>,
+}
+
+/// 依訊息類型彙整的結構驗證報告
+#[derive(Debug, Clone, Default)]
+pub struct SchemaReport {
+    schemas: BTreeMap<&'static str, SchemaStats>,
+}
+
+impl SchemaReport {
+    /// 記錄一則訊息的欄位差異，返回第一次出現的多出欄位
+    pub fn record(&mut self, schema: &'static str, diff: &FieldDiff, accepted: bool) -> Vec<String> {
+        let stats = self.schemas.entry(schema).or_default();
+        stats.checked += 1;
+        if !accepted {
+            stats.rejected += 1;
+            for field in &diff.missing {
+                *stats.missing.entry(field.clone()).or_default() += 1;
+            }
+        }
+        let mut new_fields = Vec::new();
+        for field in &diff.extra {
+            let count = stats.extra.entry(field.clone()).or_default();
+            if *count == 0 {
+                new_fields.push(field.clone());
+            }
+            *count += 1;
+        }
+        new_fields
+    }
+
+    /// 各訊息類型的統計
+    pub fn schemas(&self) -> &BTreeMap<&'static str, SchemaStats> {
+        &self.schemas
+    }
+
+    /// 清除統計
+    pub fn clear(&mut self) {
+        self.schemas.clear();
+    }
+
+    /// 顯示報告
+    pub fn print(&self) {
+        println!("\n{}", "訊息結構驗證:".bright_cyan().bold());
+        println!("{}", "-".repeat(40).bright_black());
+        if self.schemas.is_empty() {
+            println!("  {}", "尚未收到可驗證的訊息".bright_black());
+            return;
+        }
+        for (schema, stats) in &self.schemas {
+            let status = if stats.rejected > 0 {
+                format!("拒絕 {}", stats.rejected).red()
+            } else {
+                "全部通過".green()
+            };
+            println!("  {} - 檢查 {} 則，{}", schema.bright_white(), stats.checked, status);
+            for (field, count) in &stats.missing {
+                println!("    {} 缺少 {} ({} 次)", "✗".red(), field.bright_yellow(), count);
+            }
+            for (field, count) in &stats.extra {
+                println!("    {} 多出 {} ({} 次)", "+".yellow(), field.bright_yellow(), count);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Sample {
+        x: f32,
+        y: f32,
+        #[serde(default)]
+        label: Option<String>,
+    }
+
+    #[test]
+    fn reports_missing_and_extra_fields_per_schema() {
+        assert_eq!(fields_of::<Sample>(), &["x", "y", "label"]);
+
+        let diff = FieldDiff::of::<Sample>(&serde_json::json!({ "x": 1.0, "z": 2.0 }));
+        assert_eq!(diff.missing, vec!["y", "label"]);
+        assert_eq!(diff.extra, vec!["z"]);
+
+        let mut report = SchemaReport::default();
+        assert_eq!(report.record("sample", &diff, false), vec!["z"]);
+        assert!(report.record("sample", &diff, false).is_empty());
+        let ok = FieldDiff::of::<Sample>(&serde_json::json!({ "x": 1.0, "y": 2.0 }));
+        report.record("sample", &ok, true);
+
+        let stats = &report.schemas()["sample"];
+        assert_eq!((stats.checked, stats.rejected), (3, 2));
+        assert_eq!(stats.missing["y"], 2);
+        assert_eq!(stats.extra["z"], 2);
+    }
+}
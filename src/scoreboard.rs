@@ -0,0 +1,152 @@
+/// 死亡、重生與擊殺記分板
+///
+/// 後端以 `death` 訊息通知玩家陣亡或重生：`a` 為 `death` 或 `respawn`，`name` 為該玩家，
+/// `d` 為 `{"respawn_time": 12.0}`（陣亡時的重生秒數）或 `{"position": [400.0, 300.0]}`（重生位置）。
+/// 本地英雄生命歸零或受到致命傷害、以及 `kill` 訊息的被擊殺者同樣視為陣亡；每位玩家從存活變為陣亡時記一次死亡，
+/// `kill` 訊息另為擊殺者記擊殺、為助攻者記助攻。重生倒數在每幀的 `update_cooldowns` 中遞減，
+/// 本地英雄陣亡期間終端視圖以灰色顯示地圖並在中央顯示倒數
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+
+/// 死亡與重生訊息
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DeathData {
+    /// 距離重生的秒數（陣亡時提供）
+    #[serde(default)]
+    pub respawn_time: Option<f32>,
+    /// 重生位置（重生時提供）
+    #[serde(default)]
+    pub position: Option<(f32, f32)>,
+}
+
+/// 玩家的存活狀態
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LifeState {
+    pub is_alive: bool,
+    /// 距離重生的剩餘秒數（存活或後端未提供時為 None）
+    pub respawn_timer: Option<f32>,
+}
+
+impl Default for LifeState {
+    fn default() -> Self {
+        Self { is_alive: true, respawn_timer: None }
+    }
+}
+
+impl LifeState {
+    /// 標記陣亡，返回是否從存活變為陣亡（已陣亡時只更新重生倒數）
+    pub fn die(&mut self, respawn_time: Option<f32>) -> bool {
+        let was_alive = self.is_alive;
+        self.is_alive = false;
+        if respawn_time.is_some() {
+            self.respawn_timer = respawn_time;
+        }
+        was_alive
+    }
+
+    /// 標記重生，返回是否從陣亡變為存活
+    pub fn respawn(&mut self) -> bool {
+        let was_dead = !self.is_alive;
+        *self = Self::default();
+        was_dead
+    }
+
+    /// 遞減重生倒數（歸零後維持陣亡，等待後端的重生訊息）
+    pub fn tick(&mut self, delta_time: f32) {
+        if let Some(timer) = &mut self.respawn_timer {
+            *timer = (*timer - delta_time).max(0.0);
+        }
+    }
+
+    /// 陣亡提示（存活時為 None）
+    pub fn countdown(&self) -> Option<String> {
+        if self.is_alive {
+            return None;
+        }
+        Some(match self.respawn_timer {
+            Some(timer) if timer > 0.0 => format!("💀 已陣亡 - {:.0} 秒後重生", timer.ceil()),
+            Some(_) => "💀 已陣亡 - 即將重生".to_string(),
+            None => "💀 已陣亡 - 等待重生".to_string(),
+        })
+    }
+}
+
+/// 單一玩家的擊殺、死亡與助攻
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Kda {
+    pub kills: u32,
+    pub deaths: u32,
+    pub assists: u32,
+}
+
+impl Kda {
+    /// (擊殺 + 助攻) / 死亡（沒有死亡時以 1 計）
+    pub fn ratio(&self) -> f32 {
+        (self.kills + self.assists) as f32 / self.deaths.max(1) as f32
+    }
+}
+
+impl std::fmt::Display for Kda {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}/{}", self.kills, self.deaths, self.assists)
+    }
+}
+
+/// 各玩家的擊殺記分板
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scoreboard {
+    players: BTreeMap<String, Kda>,
+}
+
+impl Scoreboard {
+    /// 記錄一次擊殺與助攻（死亡由被擊殺者的存活狀態變化記錄）
+    pub fn record_kill(&mut self, killer: &str, assists: &[String]) {
+        self.players.entry(killer.to_string()).or_default().kills += 1;
+        for assist in assists.iter().filter(|assist| *assist != killer) {
+            self.players.entry(assist.clone()).or_default().assists += 1;
+        }
+    }
+
+    pub fn record_death(&mut self, player: &str) {
+        self.players.entry(player.to_string()).or_default().deaths += 1;
+    }
+
+    pub fn get(&self, player: &str) -> Kda {
+        self.players.get(player).copied().unwrap_or_default()
+    }
+
+    /// 依擊殺數（相同時死亡少者在前）排序的記分板
+    pub fn rows(&self) -> Vec<(&str, Kda)> {
+        let mut rows: Vec<_> = self.players.iter().map(|(name, kda)| (name.as_str(), *kda)).collect();
+        rows.sort_by(|a, b| b.1.kills.cmp(&a.1.kills).then(a.1.deaths.cmp(&b.1.deaths)));
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_deaths_once_and_ticks_respawn() {
+        let mut life = LifeState::default();
+        assert!(life.die(Some(2.0)));
+        assert!(!life.die(None), "已陣亡時不重複計算");
+        assert_eq!(life.respawn_timer, Some(2.0), "未提供重生時間時保留倒數");
+        life.tick(0.4);
+        assert_eq!(life.countdown().unwrap(), "💀 已陣亡 - 2 秒後重生");
+        life.tick(5.0);
+        assert_eq!(life.countdown().unwrap(), "💀 已陣亡 - 即將重生");
+        assert!(life.respawn() && life.countdown().is_none());
+
+        let mut board = Scoreboard::default();
+        board.record_kill("p2", &["p3".to_string(), "p2".to_string()]);
+        board.record_kill("p3", &[]);
+        board.record_kill("p3", &["p2".to_string()]);
+        board.record_death("p1");
+        assert_eq!(board.get("p2"), Kda { kills: 1, deaths: 0, assists: 1 });
+        assert_eq!(board.rows().iter().map(|(name, kda)| format!("{} {}", name, kda)).collect::<Vec<_>>(),
+            ["p3 2/0/1", "p2 1/0/1", "p1 0/1/0"]);
+        assert_eq!(board.get("p1").ratio(), 0.0);
+    }
+}
@@ -0,0 +1,287 @@
+/// 無介面的腳本執行
+///
+/// `omobaf run <script.yaml>` 不開啟終端視圖，依序執行腳本中宣告的步驟（connect、play、move、attack、cast、
+/// wait、assert_position、disconnect 與 testkit 的 expect_* 斷言），任一步驟失敗即停止並以非零狀態結束，
+/// 可以接進 CI 對 omobab 做端到端測試。座標與命令行的 move / cast 相同，使用本地座標（經 `[coordinates]` 轉換後送出）
+use std::path::Path;
+use std::time::Duration;
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use log::{info, warn};
+use serde::Deserialize;
+use tokio::time::{sleep, Instant};
+use vek::Vec2;
+
+use crate::demo::StepOutcome;
+use crate::game_client::GameClient;
+use crate::testkit::{self, CooldownExpect, EntityWithin, HealthBetween, MessageLog, MessageOnTopic};
+
+/// 等待與輪詢期間同步狀態的間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+fn default_level() -> u8 {
+    1
+}
+
+fn default_tolerance() -> f32 {
+    10.0
+}
+
+fn default_timeout_ms() -> u64 {
+    3000
+}
+
+/// 腳本中的一個步驟
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum ScriptStep {
+    /// 連線到 MQTT broker
+    Connect,
+    /// 進入遊戲（英雄為腳本的 `hero` 或配置中的英雄）
+    Play,
+    /// `move: [x, y]`
+    Move([f32; 2]),
+    /// `attack: [x, y]`
+    Attack([f32; 2]),
+    /// `cast: { ability, target: [x, y], level }`（未指定位置時由後端決定目標）
+    Cast {
+        ability: String,
+        #[serde(default)]
+        target: Option<[f32; 2]>,
+        #[serde(default = "default_level")]
+        level: u8,
+    },
+    /// `wait: <毫秒>`（期間持續同步狀態）
+    Wait(u64),
+    /// 在時限內英雄到達指定位置（容許誤差內）
+    AssertPosition {
+        x: f32,
+        y: f32,
+        #[serde(default = "default_tolerance")]
+        tolerance: f32,
+        #[serde(default = "default_timeout_ms")]
+        timeout_ms: u64,
+    },
+    /// 實體出現在位置附近
+    ExpectEntityWithin(EntityWithin),
+    /// 生命值介於範圍內
+    ExpectHealthBetween(HealthBetween),
+    /// 技能剩餘冷卻
+    ExpectCooldown(CooldownExpect),
+    /// 上一個操作之後收到符合主題的訊息
+    ExpectMessageOnTopic(MessageOnTopic),
+    Disconnect,
+}
+
+impl ScriptStep {
+    /// 報告中顯示的步驟名稱
+    pub fn label(&self) -> String {
+        match self {
+            ScriptStep::Connect => "connect".to_string(),
+            ScriptStep::Play => "play".to_string(),
+            ScriptStep::Move([x, y]) => format!("move ({}, {})", x, y),
+            ScriptStep::Attack([x, y]) => format!("attack ({}, {})", x, y),
+            ScriptStep::Cast { ability, target: Some([x, y]), .. } => format!("cast {} ({}, {})", ability, x, y),
+            ScriptStep::Cast { ability, target: None, .. } => format!("cast {}", ability),
+            ScriptStep::Wait(ms) => format!("wait {}ms", ms),
+            ScriptStep::AssertPosition { x, y, tolerance, .. } => format!("assert_position ({}, {}) ±{}", x, y, tolerance),
+            ScriptStep::ExpectEntityWithin(expect) => {
+                let target = expect.id.map(|id| format!("實體 {}", id)).or_else(|| expect.kind.clone()).unwrap_or_else(|| "實體".to_string());
+                format!("expect_entity_within {} ({}, {}) ±{}", target, expect.x, expect.y, expect.radius)
+            }
+            ScriptStep::ExpectHealthBetween(expect) => {
+                let target = expect.id.map_or_else(|| "英雄".to_string(), |id| format!("實體 {}", id));
+                format!("expect_health_between {} {}..{}", target, expect.min, expect.max)
+            }
+            ScriptStep::ExpectCooldown(expect) => format!("expect_cooldown {}", expect.ability),
+            ScriptStep::ExpectMessageOnTopic(expect) => format!("expect_message_on_topic {}", expect.topic),
+            ScriptStep::Disconnect => "disconnect".to_string(),
+        }
+    }
+
+    /// 是否為送出操作的步驟（訊息斷言只比對最後一個操作步驟開始後的訊息）
+    fn is_action(&self) -> bool {
+        matches!(self, ScriptStep::Connect | ScriptStep::Play | ScriptStep::Move(_) | ScriptStep::Attack(_) | ScriptStep::Cast { .. })
+    }
+}
+
+/// 動作腳本
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Script {
+    /// 腳本名稱（預設為檔名）
+    #[serde(default)]
+    pub name: Option<String>,
+    /// 使用的英雄（覆蓋配置）
+    #[serde(default)]
+    pub hero: Option<String>,
+    pub steps: Vec<ScriptStep>,
+}
+
+impl Script {
+    /// 載入腳本檔
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).with_context(|| format!("無法讀取腳本: {:?}", path))?;
+        let mut script = Self::parse(&content).with_context(|| format!("腳本有誤: {:?}", path))?;
+        if script.name.is_none() {
+            script.name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned());
+        }
+        Ok(script)
+    }
+
+    /// 解析 YAML 腳本（經由 JSON 值轉換，步驟可以寫成 `- move: [x, y]` 的單鍵映射）
+    pub fn parse(content: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_yaml::from_str(content)?;
+        let script: Script = serde_json::from_value(value)?;
+        if script.steps.is_empty() {
+            bail!("沒有任何步驟");
+        }
+        Ok(script)
+    }
+
+    /// 依序執行步驟，遇到失敗的步驟即停止
+    pub async fn run(&self, client: &mut GameClient) -> ScriptReport {
+        let mut outcomes = Vec::new();
+        let mut messages = MessageLog::new(client.subscribe_incoming());
+        for step in &self.steps {
+            info!("▶ {}", step.label());
+            if step.is_action() {
+                messages.mark();
+            }
+            let outcome = match run_step(client, step, &mut messages).await {
+                Ok(detail) => StepOutcome { name: step.label(), passed: true, detail },
+                Err(e) => StepOutcome { name: step.label(), passed: false, detail: format!("{:#}", e) },
+            };
+            let failed = !outcome.passed;
+            if failed {
+                warn!("腳本步驟失敗: {} {}", outcome.name, outcome.detail);
+            }
+            outcomes.push(outcome);
+            if failed {
+                break;
+            }
+        }
+        ScriptReport { name: self.name.clone().unwrap_or_default(), outcomes, total: self.steps.len() }
+    }
+}
+
+/// 執行單一步驟，返回報告中附帶的說明
+async fn run_step(client: &mut GameClient, step: &ScriptStep, messages: &mut MessageLog) -> Result<String> {
+    match step {
+        ScriptStep::Connect => client.connect().await.map(|_| String::new()),
+        ScriptStep::Play => client.enter_game().await.map(|_| String::new()),
+        ScriptStep::Move([x, y]) => {
+            let target = client.coordinates().to_backend(Vec2::new(*x, *y));
+            client.perform_action("move", serde_json::json!({ "target_x": target.x, "target_y": target.y })).await?;
+            Ok(String::new())
+        }
+        ScriptStep::Attack([x, y]) => {
+            let target = client.coordinates().to_backend(Vec2::new(*x, *y));
+            client.perform_action("attack", serde_json::json!({
+                "target_position": [target.x, target.y],
+                "attack_type": "basic",
+            })).await?;
+            Ok(String::new())
+        }
+        ScriptStep::Cast { ability, target, level } => {
+            let mut params = serde_json::json!({ "ability_id": ability, "level": level });
+            if let Some([x, y]) = target {
+                let target = client.coordinates().to_backend(Vec2::new(*x, *y));
+                params["target_position"] = serde_json::json!([target.x, target.y]);
+            }
+            client.perform_action("cast_ability", params).await?;
+            Ok(String::new())
+        }
+        ScriptStep::Wait(ms) => {
+            let deadline = Instant::now() + Duration::from_millis(*ms);
+            while Instant::now() < deadline {
+                client.tick().await?;
+                sleep(POLL_INTERVAL.min(deadline.saturating_duration_since(Instant::now()))).await;
+            }
+            Ok(String::new())
+        }
+        ScriptStep::AssertPosition { x, y, tolerance, timeout_ms } => {
+            let expected = client.coordinates().to_backend(Vec2::new(*x, *y));
+            let deadline = Instant::now() + Duration::from_millis(*timeout_ms);
+            loop {
+                client.tick().await?;
+                let position = client.get_game_state().local_player.position;
+                let distance = position.distance(expected);
+                if distance <= *tolerance {
+                    return Ok(format!("位置 ({:.1}, {:.1})", position.x, position.y));
+                }
+                if Instant::now() >= deadline {
+                    bail!("{} 毫秒內未到達：位置 ({:.1}, {:.1})，距離 {:.1}", timeout_ms, position.x, position.y, distance);
+                }
+                sleep(POLL_INTERVAL).await;
+            }
+        }
+        ScriptStep::ExpectEntityWithin(expect) => {
+            let center = client.coordinates().to_backend(Vec2::new(expect.x, expect.y));
+            let expect = EntityWithin { x: center.x, y: center.y, ..expect.clone() };
+            testkit::expect(client, &expect, messages).await
+        }
+        ScriptStep::ExpectHealthBetween(expect) => testkit::expect(client, expect, messages).await,
+        ScriptStep::ExpectCooldown(expect) => testkit::expect(client, expect, messages).await,
+        ScriptStep::ExpectMessageOnTopic(expect) => testkit::expect(client, expect, messages).await,
+        ScriptStep::Disconnect => client.disconnect().await.map(|_| String::new()),
+    }
+}
+
+/// 腳本執行報告
+#[derive(Debug, Clone)]
+pub struct ScriptReport {
+    pub name: String,
+    /// 已執行步驟的結果（失敗後的步驟不執行）
+    pub outcomes: Vec<StepOutcome>,
+    /// 腳本的步驟總數
+    pub total: usize,
+}
+
+impl ScriptReport {
+    /// 是否所有步驟都執行且通過
+    pub fn passed(&self) -> bool {
+        self.outcomes.len() == self.total && self.outcomes.iter().all(|outcome| outcome.passed)
+    }
+
+    /// 顯示報告
+    pub fn print(&self) {
+        println!("\n{} {}", "腳本結果:".bright_cyan().bold(), self.name);
+        for outcome in &self.outcomes {
+            let mark = if outcome.passed { "✓".green() } else { "✗".red() };
+            println!("  {} {} {}", mark, outcome.name, outcome.detail.bright_black());
+        }
+        let passed = self.outcomes.iter().filter(|outcome| outcome.passed).count();
+        println!("  通過 {}/{}", passed, self.total);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_bundled_smoke_script() {
+        let script = Script::parse(include_str!("../scripts/smoke.yaml")).unwrap();
+        assert_eq!(script.steps[0], ScriptStep::Connect);
+        assert!(script.steps.contains(&ScriptStep::Move([20.0, 10.0])));
+        assert!(script.steps.contains(&ScriptStep::AssertPosition { x: 20.0, y: 10.0, tolerance: 5.0, timeout_ms: 5000 }));
+        assert!(script.steps.contains(&ScriptStep::Cast { ability: "sniper_mode".to_string(), target: None, level: 1 }));
+        assert!(script.steps.contains(&ScriptStep::ExpectCooldown(CooldownExpect {
+            ability: "sniper_mode".to_string(), min: None, max: None, timeout_ms: 2000,
+        })));
+        let message = Script::parse("steps:\n  - expect_message_on_topic: { topic: \"td/+/res\", contains: hit }").unwrap();
+        assert_eq!(message.steps[0].label(), "expect_message_on_topic td/+/res");
+
+        assert!(Script::parse("steps: []").is_err());
+        assert!(Script::parse("steps:\n  - teleport: [1, 2]").is_err());
+        assert!(Script::parse("steps:\n  - cast: { ability: a, range: 3 }").is_err());
+
+        let report = ScriptReport {
+            name: "smoke".to_string(),
+            outcomes: vec![StepOutcome { name: "connect".to_string(), passed: true, detail: String::new() }],
+            total: 2,
+        };
+        assert!(!report.passed(), "未執行完所有步驟");
+    }
+}
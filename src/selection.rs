@@ -0,0 +1,128 @@
+/// 目標選擇
+///
+/// 終端視圖中點擊單位或按 Tab（Shift+Tab 反向）依距離輪流選擇視野內的單位，選擇的目標存在遊戲狀態中，
+/// 右側面板顯示其類型、生命與距離，並在地圖上以方括號標出。互動模式的 `attack`、`cast` 不帶座標時
+/// 以選擇的目標為目標（送出目標位置與 `target_entity`），終端視圖中指定單位的技能也直接施放在選擇的目標上
+use vek::Vec2;
+
+use crate::game_state::{Entity, EntityType, GameState};
+
+/// 游標多近的單位可被點選（世界單位，約 3 格）
+pub const PICK_RADIUS: f32 = 30.0;
+
+/// 可被選擇的單位（投射物與特效除外）
+fn selectable(entity: &Entity) -> bool {
+    !matches!(entity.entity_type, EntityType::Projectile | EntityType::Effect)
+}
+
+/// 游標附近最近的可選單位
+pub fn pick(state: &GameState, cursor: Vec2<f32>, radius: f32) -> Option<&Entity> {
+    state.entities.values()
+        .filter(|entity| selectable(entity))
+        .map(|entity| (entity, entity.position.distance(cursor)))
+        .filter(|(_, distance)| *distance <= radius)
+        .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.id.cmp(&b.0.id)))
+        .map(|(entity, _)| entity)
+}
+
+/// 選擇的目標（已離開時為 None）
+pub fn selected_entity(state: &GameState) -> Option<&Entity> {
+    state.entities.get(&state.selected_target?)
+}
+
+/// 視野內的可選單位 ID（依與英雄的距離排序）
+pub fn visible_targets(state: &GameState) -> Vec<u32> {
+    let hero = state.local_player.position;
+    let center = state.viewport.center;
+    let range = &state.viewport.display_range;
+    let mut targets: Vec<&Entity> = state.entities.values()
+        .filter(|entity| selectable(entity))
+        .filter(|entity| {
+            (entity.position.x - center.x).abs() <= range.width / 2.0
+                && (entity.position.y - center.y).abs() <= range.height / 2.0
+        })
+        .collect();
+    targets.sort_by(|a, b| a.position.distance(hero).total_cmp(&b.position.distance(hero)).then(a.id.cmp(&b.id)));
+    targets.into_iter().map(|entity| entity.id).collect()
+}
+
+/// 下一個目標（目前的目標不在視野內時從最近的開始）
+pub fn cycle(state: &GameState, reverse: bool) -> Option<u32> {
+    let targets = visible_targets(state);
+    if targets.is_empty() {
+        return None;
+    }
+    let next = match state.selected_target.and_then(|id| targets.iter().position(|target| *target == id)) {
+        Some(index) if reverse => (index + targets.len() - 1) % targets.len(),
+        Some(index) => (index + 1) % targets.len(),
+        None if reverse => targets.len() - 1,
+        None => 0,
+    };
+    Some(targets[next])
+}
+
+/// 單位類型名稱
+pub fn kind_label(entity_type: &EntityType) -> String {
+    match entity_type {
+        EntityType::Player(name) => format!("玩家 {}", name),
+        EntityType::Summon(kind) => format!("召喚物 {}", kind),
+        EntityType::Projectile => "投射物".to_string(),
+        EntityType::Effect => "特效".to_string(),
+        EntityType::Creep => "小兵".to_string(),
+        EntityType::Tower => "防禦塔".to_string(),
+    }
+}
+
+/// 目標面板文字行（沒有選擇目標時為空）
+pub fn panel_lines(state: &GameState) -> Vec<String> {
+    let Some(id) = state.selected_target else {
+        return Vec::new();
+    };
+    let Some(entity) = selected_entity(state) else {
+        return vec![format!("目標 #{}", id), "已不在視野內".to_string()];
+    };
+    let mut lines = vec![
+        format!("目標 #{} {}", id, kind_label(&entity.entity_type)),
+        format!("生命 {:.0}/{:.0}", entity.health.0, entity.health.1),
+        format!("距離 {:.0}", entity.position.distance(state.local_player.position)),
+    ];
+    if let Some(owner) = &entity.owner {
+        lines.push(format!("擁有者 {}", owner));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycles_visible_targets_by_distance() {
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        state.local_player.position = Vec2::new(0.0, 0.0);
+        state.viewport.center = Vec2::new(0.0, 0.0);
+        state.viewport.display_range.width = 1000.0;
+        state.viewport.display_range.height = 1000.0;
+        let entity = |id: u32, entity_type: EntityType, x: f32| Entity {
+            id, entity_type, position: Vec2::new(x, 0.0), health: (40.0, 100.0), owner: None, debug_label: None,
+        };
+        state.entities.insert(1, entity(1, EntityType::Creep, 300.0));
+        state.entities.insert(2, entity(2, EntityType::Tower, 100.0));
+        state.entities.insert(3, entity(3, EntityType::Projectile, 50.0));
+        state.entities.insert(4, entity(4, EntityType::Creep, 900.0));
+
+        assert_eq!(visible_targets(&state), [2, 1], "投射物與視野外的單位不可選");
+        assert_eq!(cycle(&state, false), Some(2));
+        state.selected_target = Some(2);
+        assert_eq!(cycle(&state, false), Some(1));
+        assert_eq!(cycle(&state, true), Some(1), "反向時繞回最後一個");
+        state.selected_target = Some(1);
+        assert_eq!(cycle(&state, false), Some(2));
+
+        assert_eq!(pick(&state, Vec2::new(110.0, 10.0), PICK_RADIUS).map(|entity| entity.id), Some(2));
+        assert!(pick(&state, Vec2::new(55.0, 0.0), PICK_RADIUS).is_none(), "投射物不可點選");
+        assert_eq!(panel_lines(&state), ["目標 #1 小兵", "生命 40/100", "距離 300"]);
+        state.entities.remove(&1);
+        assert_eq!(panel_lines(&state)[1], "已不在視野內");
+    }
+}
@@ -0,0 +1,164 @@
+/// 自我更新
+///
+/// `omobaf update` 從配置的發佈網址（GitHub releases API 的 latest 端點或相同格式的 JSON）取得最新版本，
+/// 版本較新時下載符合目前平台的執行檔與對應的 `.sha256` 校驗檔，校驗通過後才取代目前的執行檔；
+/// `--check-only` 只回報是否有新版本。測試機常拿舊的客戶端連新的後端，更新不必再手動複製檔案
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+
+use crate::config::UpdateConfig;
+use crate::version::CRATE_VERSION;
+
+/// 單一檔案的下載大小上限
+const MAX_DOWNLOAD_BYTES: u64 = 200 * 1024 * 1024;
+
+/// 發佈中的檔案
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+/// 一個發佈版本
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+impl Release {
+    /// 標籤中的版本（去掉開頭的 v）
+    pub fn version(&self) -> Result<semver::Version> {
+        let tag = self.tag_name.trim_start_matches('v');
+        semver::Version::parse(tag).with_context(|| format!("無法解析發佈版本: {}", self.tag_name))
+    }
+
+    /// 是否比目前的版本新
+    pub fn is_newer(&self) -> Result<bool> {
+        Ok(self.version()? > semver::Version::parse(CRATE_VERSION)?)
+    }
+
+    /// 依名稱找檔案
+    pub fn asset(&self, name: &str) -> Option<&ReleaseAsset> {
+        self.assets.iter().find(|asset| asset.name == name)
+    }
+}
+
+/// 目前平台的執行檔名稱，例如 `omobaf-linux-x86_64`、`omobaf-windows-x86_64.exe`
+pub fn platform_asset_name() -> String {
+    format!("omobaf-{}-{}{}", std::env::consts::OS, std::env::consts::ARCH, std::env::consts::EXE_SUFFIX)
+}
+
+/// 以 `.sha256` 校驗檔（`<十六進位雜湊>  <檔名>` 或只有雜湊）驗證下載的內容
+pub fn verify_checksum(content: &[u8], checksum_file: &str) -> Result<()> {
+    let expected = checksum_file.split_whitespace().next()
+        .ok_or_else(|| anyhow!("校驗檔是空的"))?
+        .to_ascii_lowercase();
+    let digest = ring::digest::digest(&ring::digest::SHA256, content);
+    let actual: String = digest.as_ref().iter().map(|byte| format!("{:02x}", byte)).collect();
+    if actual != expected {
+        return Err(anyhow!("SHA-256 不符：預期 {}，實際 {}", expected, actual));
+    }
+    Ok(())
+}
+
+/// 以新內容取代執行檔：先寫到旁邊的暫存檔，再把舊檔改名保留、把新檔改名到原位置（執行中的檔案在 Windows 上也能改名）
+pub fn replace_binary(target: &Path, content: &[u8]) -> Result<PathBuf> {
+    let staged = target.with_extension("new");
+    let backup = target.with_extension("old");
+    std::fs::write(&staged, content).with_context(|| format!("無法寫入 {}", staged.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))?;
+    }
+    let _ = std::fs::remove_file(&backup);
+    std::fs::rename(target, &backup).with_context(|| format!("無法移動目前的執行檔 {}", target.display()))?;
+    if let Err(e) = std::fs::rename(&staged, target) {
+        let _ = std::fs::rename(&backup, target);
+        return Err(anyhow!("無法放入新的執行檔: {}", e));
+    }
+    Ok(backup)
+}
+
+/// 取得最新的發佈資訊
+pub fn fetch_latest(config: &UpdateConfig) -> Result<Release> {
+    let response = ureq::get(&config.release_url)
+        .set("User-Agent", &format!("omobaf/{}", CRATE_VERSION))
+        .set("Accept", "application/vnd.github+json")
+        .call()
+        .with_context(|| format!("無法取得發佈資訊: {}", config.release_url))?;
+    response.into_json().context("發佈資訊格式錯誤")
+}
+
+/// 下載檔案內容
+fn download(asset: &ReleaseAsset) -> Result<Vec<u8>> {
+    use std::io::Read;
+    let response = ureq::get(&asset.browser_download_url)
+        .set("User-Agent", &format!("omobaf/{}", CRATE_VERSION))
+        .call()
+        .with_context(|| format!("無法下載 {}", asset.name))?;
+    let mut content = Vec::new();
+    response.into_reader().take(MAX_DOWNLOAD_BYTES).read_to_end(&mut content)?;
+    Ok(content)
+}
+
+/// 檢查並安裝更新（check_only 時只回報是否有新版本）
+pub fn run(config: &UpdateConfig, check_only: bool) -> Result<()> {
+    let release = fetch_latest(config)?;
+    let version = release.version()?;
+    if !release.is_newer()? {
+        println!("目前版本 {} 已是最新（最新發佈 {}）", CRATE_VERSION, version);
+        return Ok(());
+    }
+    let name = platform_asset_name();
+    let asset = release.asset(&name)
+        .ok_or_else(|| anyhow!("發佈 {} 中沒有此平台的執行檔 {}", release.tag_name, name))?;
+    println!("有新版本 {}（目前 {}）", version, CRATE_VERSION);
+    if check_only {
+        return Ok(());
+    }
+
+    let checksum = release.asset(&format!("{}.sha256", name))
+        .ok_or_else(|| anyhow!("發佈 {} 中沒有 {}.sha256，拒絕安裝未經校驗的執行檔", release.tag_name, name))?;
+    println!("下載 {} ...", asset.browser_download_url);
+    let content = download(asset)?;
+    let checksum = String::from_utf8(download(checksum)?).context("校驗檔不是文字")?;
+    verify_checksum(&content, &checksum)?;
+
+    let current = std::env::current_exe().context("無法取得目前執行檔的路徑")?;
+    let backup = replace_binary(&current, &content)?;
+    println!("已更新到 {}（舊版保留在 {}）", version, backup.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_and_replaces_the_binary_from_a_newer_release() {
+        let release: Release = serde_json::from_str(&format!(r#"{{
+            "tag_name": "v999.0.0",
+            "assets": [{{ "name": "{}", "browser_download_url": "https://example.com/omobaf" }}]
+        }}"#, platform_asset_name())).unwrap();
+        assert!(release.is_newer().unwrap());
+        assert!(release.asset(&platform_asset_name()).is_some());
+
+        // echo -n "abc" | sha256sum
+        let checksum = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  omobaf";
+        assert!(verify_checksum(b"abc", checksum).is_ok());
+        assert!(verify_checksum(b"abd", checksum).is_err());
+
+        let dir = std::env::temp_dir().join(format!("omobaf_update_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("omobaf");
+        std::fs::write(&target, b"old").unwrap();
+        let backup = replace_binary(&target, b"new").unwrap();
+        let (current, previous) = (std::fs::read(&target).unwrap(), std::fs::read(&backup).unwrap());
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!((current.as_slice(), previous.as_slice()), (&b"new"[..], &b"old"[..]));
+    }
+}
@@ -0,0 +1,199 @@
+/// 連線自我測試
+///
+/// 開始正式會話前確認 MQTT broker 可用：以獨立的客戶端訂閱唯一主題，
+/// 依序以三種 QoS 發佈隨機 nonce，確認訊息在時限內經 broker 回送，
+/// 並回報連線、訂閱與各 QoS 的往返延遲
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
+use colored::*;
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS};
+
+/// 單一 QoS 的往返結果
+#[derive(Debug, Clone)]
+pub struct QosCheck {
+    /// 發佈時使用的 QoS
+    pub qos: QoS,
+    /// 回送訊息的 QoS（逾時則為 None）
+    pub received_qos: Option<QoS>,
+    /// 往返延遲（逾時則為 None）
+    pub latency: Option<Duration>,
+}
+
+impl QosCheck {
+    /// 是否在時限內以相同 QoS 收到回送
+    pub fn passed(&self) -> bool {
+        self.received_qos == Some(self.qos)
+    }
+
+    /// 報告中的單行結果
+    pub fn summary(&self) -> String {
+        let label = format!("QoS {}", qos_level(self.qos));
+        match (self.latency, self.received_qos) {
+            (Some(latency), Some(_)) if self.passed() => {
+                format!("{} {} 往返 {}", "✓".green(), label, format_latency(latency))
+            }
+            (Some(latency), Some(received)) => {
+                format!("{} {} 往返 {}，但 broker 以 QoS {} 回送", "!".yellow(), label,
+                    format_latency(latency), qos_level(received))
+            }
+            _ => format!("{} {} 逾時未收到回送", "✗".red(), label),
+        }
+    }
+}
+
+/// MQTT 回送測試報告
+#[derive(Debug, Clone)]
+pub struct LoopbackReport {
+    /// broker 位址
+    pub broker: String,
+    /// 使用的測試主題
+    pub topic: String,
+    /// 建立連線（收到 CONNACK）所需時間
+    pub connect_latency: Duration,
+    /// 訂閱確認（收到 SUBACK）所需時間
+    pub subscribe_latency: Duration,
+    /// 各 QoS 的往返結果
+    pub checks: Vec<QosCheck>,
+}
+
+impl LoopbackReport {
+    /// 所有 QoS 是否皆通過
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(QosCheck::passed)
+    }
+
+    /// 打印報告
+    pub fn print(&self) {
+        println!("\n{}", "MQTT 回送自我測試:".bright_cyan().bold());
+        println!("{}", "-".repeat(40).bright_black());
+        println!("  Broker: {}", self.broker);
+        println!("  主題: {}", self.topic.bright_black());
+        println!("  連線: {}", format_latency(self.connect_latency));
+        println!("  訂閱: {}", format_latency(self.subscribe_latency));
+
+        for check in &self.checks {
+            println!("  {}", check.summary());
+        }
+
+        if self.passed() {
+            println!("{} broker 回送正常，可以開始會話", "✓".green());
+        } else {
+            println!("{} broker 回送異常，請檢查 broker 設定", "✗".red());
+        }
+    }
+}
+
+/// 執行 MQTT 回送測試（連線選項由 `GameClient::endpoint_options` 建立，沿用正式會話的認證與 TLS 設定）
+pub async fn mqtt_loopback(mut options: MqttOptions, timeout: Duration) -> Result<LoopbackReport> {
+    let nonce: u64 = rand::random();
+    let topic = format!("omobaf/selftest/{:016x}", nonce);
+    let (host, port) = options.broker_address();
+
+    options.set_keep_alive(Duration::from_secs(5));
+    let (client, mut eventloop) = AsyncClient::new(options, 10);
+
+    let started = Instant::now();
+    wait_for(&mut eventloop, timeout, "CONNACK", |packet| matches!(packet, Packet::ConnAck(_))).await?;
+    let connect_latency = started.elapsed();
+
+    // 以 QoS 2 訂閱，回送訊息的 QoS 應等於發佈時的 QoS
+    let started = Instant::now();
+    client.subscribe(&topic, QoS::ExactlyOnce).await?;
+    wait_for(&mut eventloop, timeout, "SUBACK", |packet| matches!(packet, Packet::SubAck(_))).await?;
+    let subscribe_latency = started.elapsed();
+
+    let mut checks = Vec::new();
+    for qos in [QoS::AtMostOnce, QoS::AtLeastOnce, QoS::ExactlyOnce] {
+        let payload = format!("{:016x}-{}", nonce, qos_level(qos));
+        let started = Instant::now();
+        client.publish(&topic, qos, false, payload.clone()).await?;
+
+        let received = wait_for(&mut eventloop, timeout, "PUBLISH", |packet| {
+            matches!(packet, Packet::Publish(publish) if publish.payload.as_ref() == payload.as_bytes())
+        }).await;
+
+        checks.push(match received {
+            Ok(Packet::Publish(publish)) => QosCheck {
+                qos,
+                received_qos: Some(publish.qos),
+                latency: Some(started.elapsed()),
+            },
+            _ => QosCheck { qos, received_qos: None, latency: None },
+        });
+    }
+
+    let _ = client.disconnect().await;
+    // 讓事件循環送出 DISCONNECT
+    let _ = tokio::time::timeout(Duration::from_millis(200), eventloop.poll()).await;
+
+    Ok(LoopbackReport {
+        broker: format!("{}:{}", host, port),
+        topic,
+        connect_latency,
+        subscribe_latency,
+        checks,
+    })
+}
+
+/// 驅動事件循環直到收到符合條件的封包或逾時
+async fn wait_for<F>(eventloop: &mut EventLoop, timeout: Duration, what: &str, mut matches: F) -> Result<Packet>
+where
+    F: FnMut(&Packet) -> bool,
+{
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match tokio::time::timeout(remaining, eventloop.poll()).await {
+            Ok(Ok(Event::Incoming(packet))) if matches(&packet) => return Ok(packet),
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => return Err(anyhow!("等待 {} 時連線錯誤: {}", what, e)),
+            Err(_) => return Err(anyhow!("等待 {} 逾時 ({}ms)", what, timeout.as_millis())),
+        }
+    }
+}
+
+fn qos_level(qos: QoS) -> u8 {
+    match qos {
+        QoS::AtMostOnce => 0,
+        QoS::AtLeastOnce => 1,
+        QoS::ExactlyOnce => 2,
+    }
+}
+
+fn format_latency(latency: Duration) -> String {
+    format!("{:.1}ms", latency.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reports_passing_and_failing_qos_checks() {
+        // broker 最多以 QoS 1 回送：QoS 0、1 通過，QoS 2 被降級
+        let addr = crate::testkit::stub_broker(QoS::AtLeastOnce).await.unwrap();
+        let options = MqttOptions::new("selftest", addr.ip().to_string(), addr.port());
+        let report = mqtt_loopback(options, Duration::from_secs(3)).await.unwrap();
+
+        assert_eq!(report.broker, addr.to_string());
+        let results: Vec<(QoS, Option<QoS>)> = report.checks.iter().map(|check| (check.qos, check.received_qos)).collect();
+        assert_eq!(results, [
+            (QoS::AtMostOnce, Some(QoS::AtMostOnce)),
+            (QoS::AtLeastOnce, Some(QoS::AtLeastOnce)),
+            (QoS::ExactlyOnce, Some(QoS::AtLeastOnce)),
+        ]);
+        assert!(report.checks[1].passed());
+        assert!(report.checks[1].summary().contains("QoS 1 往返"));
+        assert!(!report.checks[2].passed());
+        assert!(report.checks[2].summary().contains("但 broker 以 QoS 1 回送"));
+        assert!(!report.passed());
+
+        let timed_out = QosCheck { qos: QoS::AtMostOnce, received_qos: None, latency: None };
+        assert!(!timed_out.passed());
+        assert!(timed_out.summary().contains("QoS 0 逾時未收到回送"));
+
+        let addr = crate::testkit::stub_broker(QoS::ExactlyOnce).await.unwrap();
+        let options = MqttOptions::new("selftest", addr.ip().to_string(), addr.port());
+        assert!(mqtt_loopback(options, Duration::from_secs(3)).await.unwrap().passed());
+    }
+}
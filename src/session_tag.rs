@@ -0,0 +1,99 @@
+/// 會話標籤
+///
+/// 多個會話（bot1、bot2、後端管理）同時輸出日誌時，以 tokio task-local 記錄
+/// 目前執行中的會話名稱，日誌格式化時加上帶固定顏色的 `[名稱]` 前綴，
+/// 讓交錯的輸出仍然容易分辨
+use std::future::Future;
+use std::io::Write;
+use colored::{Color, Colorize};
+
+tokio::task_local! {
+    static SESSION_TAG: String;
+}
+
+/// 會話標籤可用的顏色（避開日誌級別使用的紅、黃）
+const PALETTE: &[Color] = &[
+    Color::Cyan,
+    Color::Magenta,
+    Color::Green,
+    Color::Blue,
+    Color::BrightCyan,
+    Color::BrightMagenta,
+    Color::BrightGreen,
+    Color::BrightBlue,
+];
+
+/// 在指定會話標籤下執行 future（包括其中產生的日誌）
+pub async fn scope<F: Future>(tag: impl Into<String>, future: F) -> F::Output {
+    SESSION_TAG.scope(tag.into(), future).await
+}
+
+/// 產生新任務並沿用指定的會話標籤（無標籤時等同 `tokio::spawn`）
+pub fn spawn<F>(tag: Option<String>, future: F) -> tokio::task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match tag {
+        Some(tag) => tokio::spawn(SESSION_TAG.scope(tag, future)),
+        None => tokio::spawn(future),
+    }
+}
+
+/// 目前任務的會話標籤
+pub fn current() -> Option<String> {
+    SESSION_TAG.try_with(|tag| tag.clone()).ok()
+}
+
+/// 會話名稱對應的固定顏色（以 FNV-1a 雜湊選色，不同次執行結果一致）
+pub fn color_for(tag: &str) -> Color {
+    let hash = tag.bytes().fold(0x811c9dc5u32, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    });
+    PALETTE[hash as usize % PALETTE.len()]
+}
+
+/// 帶顏色的 `[名稱]` 標籤
+pub fn colored_label(tag: &str) -> String {
+    format!("[{}]", tag).color(color_for(tag)).bold().to_string()
+}
+
+/// 加上會話標籤前綴的 env_logger 格式
+pub fn format_record(buf: &mut env_logger::fmt::Formatter, record: &log::Record) -> std::io::Result<()> {
+    let level = match record.level() {
+        log::Level::Error => "ERROR".red(),
+        log::Level::Warn => "WARN".yellow(),
+        log::Level::Info => "INFO".green(),
+        log::Level::Debug => "DEBUG".blue(),
+        log::Level::Trace => "TRACE".bright_black(),
+    };
+    let timestamp = buf.timestamp();
+    match current() {
+        Some(tag) => writeln!(buf, "[{} {} {}] {} {}", timestamp, level, record.target(), colored_label(&tag), record.args()),
+        None => writeln!(buf, "[{} {} {}] {}", timestamp, level, record.target(), record.args()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn scopes_tag_to_the_session_task() {
+        assert_eq!(current(), None);
+        let tag = scope("bot1", async {
+            let inner = spawn(current(), async { current() }).await.unwrap();
+            (current(), inner)
+        }).await;
+        assert_eq!(tag, (Some("bot1".to_string()), Some("bot1".to_string())));
+        assert_eq!(spawn(None, async { current() }).await.unwrap(), None);
+        assert_eq!(current(), None);
+    }
+
+    #[test]
+    fn colors_are_stable_per_name() {
+        assert_eq!(color_for("bot1"), color_for("bot1"));
+        assert!(PALETTE.contains(&color_for("main")));
+        assert!(colored_label("bot1").contains("[bot1]"));
+    }
+}
@@ -0,0 +1,196 @@
+/// 商店目錄與道具欄
+///
+/// 商店目錄寫在 `[shop] catalog` 指定的 TOML 檔中（未設定時使用內建的 `data/shop.toml`），列出每種道具的價格、
+/// 賣出價、每次購買的使用次數、每格可疊加的份數與冷卻時間，以及進入遊戲時道具欄中的道具。
+/// 互動模式的 `buy` / `sell` 以 `interact` 操作（`buy_item` / `sell_item`）送往後端，同時在本地扣除或退還金錢、
+/// 放入或移出道具欄：同種道具先疊加到未滿的格子，滿了才佔用新的格子。後端下一則 `gold` 訊息以後端為準
+use std::collections::HashSet;
+use std::path::Path;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+
+use crate::game_state::ItemState;
+
+/// 內建的商店目錄
+const DEFAULT_CATALOG: &str = include_str!("../data/shop.toml");
+
+/// 道具欄格數（1 號位起）
+pub const INVENTORY_SLOTS: u8 = 6;
+
+/// 商店中的一種道具
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShopItem {
+    pub id: String,
+    pub name: String,
+    /// 購買價格
+    pub cost: u32,
+    /// 賣出時每份退還的金錢（未設置時為價格的一半）
+    #[serde(default)]
+    pub sell_value: Option<u32>,
+    /// 每次購買得到的使用次數
+    #[serde(default = "default_charges")]
+    pub charges: u32,
+    /// 每格最多疊加的份數
+    #[serde(default = "default_max_stack")]
+    pub max_stack: u32,
+    /// 使用後的冷卻秒數
+    #[serde(default = "default_cooldown")]
+    pub cooldown: f32,
+}
+
+fn default_charges() -> u32 {
+    1
+}
+
+fn default_max_stack() -> u32 {
+    1
+}
+
+fn default_cooldown() -> f32 {
+    5.0
+}
+
+impl ShopItem {
+    /// 每份的賣出價
+    pub fn sell_value(&self) -> u32 {
+        self.sell_value.unwrap_or(self.cost / 2)
+    }
+
+    /// 一格最多的使用次數
+    fn slot_capacity(&self) -> u32 {
+        self.charges * self.max_stack
+    }
+}
+
+/// 商店目錄
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShopCatalog {
+    /// 進入遊戲時道具欄中的道具
+    #[serde(default)]
+    pub starting_items: Vec<String>,
+    #[serde(rename = "item", default)]
+    pub items: Vec<ShopItem>,
+}
+
+impl Default for ShopCatalog {
+    fn default() -> Self {
+        Self::parse(DEFAULT_CATALOG).expect("內建的商店目錄有誤")
+    }
+}
+
+impl ShopCatalog {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("無法讀取商店目錄: {:?}", path))?;
+        Self::parse(&content).with_context(|| format!("商店目錄有誤: {:?}", path))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let catalog: Self = toml::from_str(content)?;
+        let mut ids = HashSet::new();
+        for item in &catalog.items {
+            if !ids.insert(item.id.as_str()) {
+                bail!("道具 {} 重複定義", item.id);
+            }
+            if item.charges == 0 || item.max_stack == 0 {
+                bail!("道具 {} 的 charges 與 max_stack 必須大於 0", item.id);
+            }
+            if item.cooldown < 0.0 {
+                bail!("道具 {} 的 cooldown 不能為負", item.id);
+            }
+        }
+        for id in &catalog.starting_items {
+            catalog.find(id).ok_or_else(|| anyhow!("初始道具 {} 不在目錄中", id))?;
+        }
+        if catalog.starting_items.len() > INVENTORY_SLOTS as usize {
+            bail!("初始道具超過 {} 格", INVENTORY_SLOTS);
+        }
+        Ok(catalog)
+    }
+
+    pub fn find(&self, id: &str) -> Option<&ShopItem> {
+        self.items.iter().find(|item| item.id == id)
+    }
+
+    /// 進入遊戲時的道具欄（每格放滿）
+    pub fn starting_inventory(&self) -> Vec<ItemState> {
+        self.starting_items.iter()
+            .filter_map(|id| self.find(id))
+            .zip(1..)
+            .map(|(item, slot)| new_slot(item, slot, item.slot_capacity()))
+            .collect()
+    }
+}
+
+fn new_slot(item: &ShopItem, slot: u8, charges: u32) -> ItemState {
+    ItemState {
+        item_id: item.id.clone(),
+        name: item.name.clone(),
+        slot,
+        charges,
+        cooldown: item.cooldown,
+        cooldown_remaining: 0.0,
+        is_available: true,
+        last_used: None,
+    }
+}
+
+/// 把一份道具放入道具欄，返回放入的格子（先疊加到未滿的同種道具）
+pub fn stock(items: &mut Vec<ItemState>, item: &ShopItem) -> Result<u8> {
+    if let Some(existing) = items.iter_mut()
+        .find(|existing| existing.item_id == item.id && existing.charges + item.charges <= item.slot_capacity())
+    {
+        existing.charges += item.charges;
+        return Ok(existing.slot);
+    }
+    let slot = (1..=INVENTORY_SLOTS)
+        .find(|slot| items.iter().all(|existing| existing.slot != *slot))
+        .ok_or_else(|| anyhow!("道具欄已滿"))?;
+    items.push(new_slot(item, slot, item.charges));
+    items.sort_by_key(|existing| existing.slot);
+    Ok(slot)
+}
+
+/// 移出整格道具，返回移出的道具
+pub fn unstock(items: &mut Vec<ItemState>, slot: u8) -> Result<ItemState> {
+    let index = items.iter().position(|existing| existing.slot == slot)
+        .ok_or_else(|| anyhow!("{} 號位沒有道具", slot))?;
+    Ok(items.remove(index))
+}
+
+/// 賣出一格道具退還的金錢（依剩餘使用次數折算份數，不足一份也算一份）
+pub fn refund(item: &ShopItem, charges: u32) -> u32 {
+    item.sell_value() * charges.div_ceil(item.charges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stacks_purchases_and_refunds_sales() {
+        let catalog = ShopCatalog::default();
+        let mut items = catalog.starting_inventory();
+        assert_eq!(items.iter().map(|item| (item.slot, item.charges)).collect::<Vec<_>>(), [(1, 5), (2, 3), (3, 2), (4, 4)]);
+
+        let potion = catalog.find("health_potion").unwrap();
+        let boots = catalog.find("swift_boots").unwrap();
+        items[0].charges = 3;
+        assert_eq!(stock(&mut items, potion).unwrap(), 1, "疊加到未滿的格子");
+        assert_eq!(stock(&mut items, potion).unwrap(), 1);
+        assert_eq!(stock(&mut items, potion).unwrap(), 5, "滿了才佔用新的格子");
+        assert_eq!(stock(&mut items, boots).unwrap(), 6);
+        assert!(stock(&mut items, boots).is_err(), "道具欄已滿");
+
+        let sold = unstock(&mut items, 1).unwrap();
+        assert_eq!((sold.item_id.as_str(), refund(potion, sold.charges)), ("health_potion", 125));
+        assert_eq!(refund(boots, 1), 350);
+        assert_eq!(stock(&mut items, boots).unwrap(), 1, "賣出後空出的格子可再使用");
+        assert!(unstock(&mut items, 1).is_ok() && unstock(&mut items, 1).is_err());
+
+        assert!(ShopCatalog::parse("starting_items = [\"missing\"]").is_err());
+        assert!(ShopCatalog::parse("[[item]]\nid = \"a\"\nname = \"A\"\ncost = 1\n[[item]]\nid = \"a\"\nname = \"A\"\ncost = 1").is_err());
+    }
+}
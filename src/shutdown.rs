@@ -0,0 +1,174 @@
+/// 關閉協調
+///
+/// 背景任務（MQTT 事件循環、心跳、畫面請求循環）與執行緒（終端視圖的輸入執行緒）都持有 ShutdownToken，
+/// 斷線或離開時由 Shutdown 發出停止訊號並等待它們在目前這一輪結束後自行退出；
+/// 超過寬限時間仍未結束的任務才強制中止並記錄在日誌中，不再直接 abort 或留下沒人停止的執行緒
+use std::future::Future;
+use std::time::Duration;
+use log::{debug, warn};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// 停止訊號送出後等待任務自行結束的預設時間
+pub const SHUTDOWN_GRACE: Duration = Duration::from_millis(500);
+
+/// 任務端持有的停止訊號
+#[derive(Debug, Clone)]
+pub struct ShutdownToken {
+    receiver: watch::Receiver<bool>,
+}
+
+impl ShutdownToken {
+    /// 是否已要求停止（執行緒中以輪詢檢查）
+    pub fn is_cancelled(&self) -> bool {
+        *self.receiver.borrow()
+    }
+
+    /// 等待停止訊號（發出訊號的一方被丟棄時也視為停止）
+    pub async fn cancelled(&self) {
+        let mut receiver = self.receiver.clone();
+        let _ = receiver.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+/// 發出停止訊號的一方
+#[derive(Debug)]
+pub struct ShutdownSignal {
+    sender: watch::Sender<bool>,
+}
+
+impl ShutdownSignal {
+    /// 要求持有對應 token 的任務停止
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+}
+
+/// 建立一組停止訊號與 token
+pub fn signal() -> (ShutdownSignal, ShutdownToken) {
+    let (sender, receiver) = watch::channel(false);
+    (ShutdownSignal { sender }, ShutdownToken { receiver })
+}
+
+/// 被追蹤的背景任務
+#[derive(Debug)]
+struct TrackedTask {
+    name: &'static str,
+    signal: ShutdownSignal,
+    handle: JoinHandle<()>,
+}
+
+/// 關閉結果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// 收到訊號後自行結束的任務數
+    pub stopped: usize,
+    /// 超過寬限時間而被強制中止的任務
+    pub aborted: Vec<&'static str>,
+}
+
+/// 追蹤背景任務並依序關閉
+#[derive(Debug, Default)]
+pub struct Shutdown {
+    tasks: Vec<TrackedTask>,
+}
+
+impl Shutdown {
+    /// 以指定的會話標籤產生任務並追蹤（同名的任務應先以 stop 停止）
+    pub fn spawn<F, Fut>(&mut self, name: &'static str, session_tag: Option<String>, task: F)
+    where
+        F: FnOnce(ShutdownToken) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.tasks.retain(|tracked| !tracked.handle.is_finished());
+        let (signal, token) = signal();
+        let handle = crate::session_tag::spawn(session_tag, task(token));
+        self.tasks.push(TrackedTask { name, signal, handle });
+    }
+
+    /// 等待任務自行結束（不送出停止訊號），逾時返回 false 且任務繼續追蹤
+    pub async fn wait(&mut self, name: &str, timeout: Duration) -> bool {
+        let Some(tracked) = self.tasks.iter_mut().find(|tracked| tracked.name == name) else {
+            return true;
+        };
+        tokio::time::timeout(timeout, &mut tracked.handle).await.is_ok()
+    }
+
+    /// 停止單一任務，返回是否在寬限時間內自行結束
+    pub async fn stop(&mut self, name: &str, grace: Duration) -> bool {
+        let Some(index) = self.tasks.iter().position(|tracked| tracked.name == name) else {
+            return true;
+        };
+        let tracked = self.tasks.remove(index);
+        tracked.signal.trigger();
+        finish(tracked, grace).await
+    }
+
+    /// 對所有任務送出停止訊號，並依產生的相反順序等待它們結束
+    pub async fn shutdown(&mut self, grace: Duration) -> ShutdownReport {
+        for tracked in &self.tasks {
+            tracked.signal.trigger();
+        }
+        let mut report = ShutdownReport::default();
+        while let Some(tracked) = self.tasks.pop() {
+            let name = tracked.name;
+            if finish(tracked, grace).await {
+                report.stopped += 1;
+            } else {
+                report.aborted.push(name);
+            }
+        }
+        report
+    }
+
+    /// 對所有任務送出停止訊號但不等待（不能 await 的地方使用，任務在目前這一輪結束後退出）
+    pub fn cancel_all(&mut self) {
+        for tracked in self.tasks.drain(..) {
+            tracked.signal.trigger();
+        }
+    }
+}
+
+/// 等待任務結束，逾時則強制中止
+async fn finish(mut tracked: TrackedTask, grace: Duration) -> bool {
+    if tokio::time::timeout(grace, &mut tracked.handle).await.is_ok() {
+        debug!("任務 {} 已停止", tracked.name);
+        return true;
+    }
+    warn!("任務 {} 在 {} 毫秒內沒有結束，強制中止", tracked.name, grace.as_millis());
+    tracked.handle.abort();
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stops_cooperative_tasks_and_aborts_stuck_ones() {
+        let mut shutdown = Shutdown::default();
+        shutdown.spawn("loop", None, |token| async move {
+            let mut ticker = tokio::time::interval(Duration::from_millis(5));
+            loop {
+                tokio::select! {
+                    _ = token.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+            }
+        });
+        shutdown.spawn("stuck", None, |_token| async move {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        });
+
+        let report = shutdown.shutdown(Duration::from_millis(100)).await;
+        assert_eq!(report, ShutdownReport { stopped: 1, aborted: vec!["stuck"] });
+        assert!(shutdown.stop("loop", Duration::ZERO).await, "已停止的任務不再追蹤");
+
+        let (signal, token) = signal();
+        let thread = std::thread::spawn(move || while !token.is_cancelled() {
+            std::thread::sleep(Duration::from_millis(1));
+        });
+        signal.trigger();
+        thread.join().unwrap();
+    }
+}
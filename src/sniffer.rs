@@ -0,0 +1,233 @@
+/// 主題監聽
+///
+/// 互動模式的 `sniff [pattern]` 以另一條 MQTT 連線訂閱任意主題過濾器（支援 `+` 與 `#` 萬用字元），
+/// 把收到的訊息解碼後串流到可捲動的窗格，不必再另外執行監聽程式
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use crossterm::event::{self, Event as TermEvent, KeyCode};
+use crossterm::style::Color;
+use crossterm::terminal;
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use tokio::sync::mpsc;
+
+use crate::mqtt_handler::WireFormat;
+use crate::shutdown::{Shutdown, SHUTDOWN_GRACE};
+use crate::terminal_view::renderer::draw_text;
+use crate::terminal_view::text_width::str_width;
+use crate::terminal_view::{MapDisplay, MapRenderer};
+
+/// 未指定時監聽的主題過濾器
+pub const DEFAULT_PATTERN: &str = "#";
+/// 窗格最多保留的訊息數（超過時丟棄最舊的）
+const MAX_ENTRIES: usize = 2000;
+
+/// 收到的一則訊息
+#[derive(Debug, Clone)]
+pub struct SniffEntry {
+    /// 自開始監聽經過的時間
+    pub elapsed: Duration,
+    pub topic: String,
+    /// 解碼後的內容
+    pub text: String,
+}
+
+/// 可捲動的訊息窗格
+#[derive(Debug, Default)]
+pub struct SniffPane {
+    entries: VecDeque<SniffEntry>,
+    /// 自底部往上捲動的則數（0 表示跟隨最新訊息）
+    scroll: usize,
+    /// 超過上限而丟棄的訊息數
+    dropped: u64,
+}
+
+impl SniffPane {
+    /// 加入訊息（往上捲動時畫面停在原本的位置）
+    pub fn push(&mut self, entry: SniffEntry) {
+        self.entries.push_back(entry);
+        if self.scroll > 0 {
+            self.scroll += 1;
+        }
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.pop_front();
+            self.dropped += 1;
+            self.scroll = self.scroll.min(self.entries.len() - 1);
+        }
+    }
+
+    /// 往較舊的訊息捲動
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_add(lines).min(self.entries.len().saturating_sub(1));
+    }
+
+    /// 往較新的訊息捲動
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    /// 回到最新訊息並持續跟隨
+    pub fn follow(&mut self) {
+        self.scroll = 0;
+    }
+
+    /// 是否跟隨最新訊息
+    pub fn following(&self) -> bool {
+        self.scroll == 0
+    }
+
+    /// 收到的訊息總數
+    pub fn total(&self) -> u64 {
+        self.entries.len() as u64 + self.dropped
+    }
+
+    /// 目前捲動位置下可見的訊息（最多 height 則，舊的在前）
+    pub fn visible(&self, height: usize) -> impl Iterator<Item = &SniffEntry> {
+        let end = self.entries.len() - self.scroll;
+        self.entries.range(end.saturating_sub(height)..end)
+    }
+}
+
+/// 訂閱主題過濾器並顯示監聽窗格（按 q 或 Esc 結束），返回收到的訊息數
+pub async fn run(options: MqttOptions, pattern: &str, format: WireFormat) -> Result<u64> {
+    if !rumqttc::valid_filter(pattern) {
+        return Err(anyhow!("無效的主題過濾器: {}", pattern));
+    }
+    let (client, mut eventloop) = AsyncClient::new(options, 100);
+    client.subscribe(pattern, QoS::AtMostOnce).await?;
+
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let mut tasks = Shutdown::default();
+    tasks.spawn("sniff_poller", None, |shutdown| async move {
+        loop {
+            let event = tokio::select! {
+                _ = shutdown.cancelled() => break,
+                event = eventloop.poll() => event,
+            };
+            let message = match event {
+                Ok(Event::Incoming(Packet::Publish(publish))) => Ok((publish.topic, publish.payload)),
+                Ok(_) => continue,
+                Err(e) => Err(e.to_string()),
+            };
+            let failed = message.is_err();
+            if sender.send(message).is_err() {
+                break;
+            }
+            if failed {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+                }
+            }
+        }
+    });
+
+    let renderer = MapRenderer::new();
+    renderer.init_terminal()?;
+    let result = run_pane(&renderer, &mut receiver, pattern, format).await;
+    let _ = renderer.cleanup_terminal();
+    let _ = client.disconnect().await;
+    tasks.shutdown(SHUTDOWN_GRACE).await;
+    result
+}
+
+async fn run_pane(
+    renderer: &MapRenderer,
+    receiver: &mut mpsc::UnboundedReceiver<Result<(String, Bytes), String>>,
+    pattern: &str,
+    format: WireFormat,
+) -> Result<u64> {
+    let started = Instant::now();
+    let mut pane = SniffPane::default();
+    let mut status = String::new();
+
+    loop {
+        let (width, height) = terminal::size()?;
+        let pane_height = height.saturating_sub(3); // 留出日誌區域空間
+        let page = pane_height.saturating_sub(1).max(1) as usize;
+
+        while event::poll(Duration::from_millis(0))? {
+            if let TermEvent::Key(key) = event::read()? {
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(pane.total()),
+                    KeyCode::Up | KeyCode::Char('k') => pane.scroll_up(1),
+                    KeyCode::Down | KeyCode::Char('j') => pane.scroll_down(1),
+                    KeyCode::PageUp => pane.scroll_up(page),
+                    KeyCode::PageDown => pane.scroll_down(page),
+                    KeyCode::Home => pane.scroll_up(usize::MAX),
+                    KeyCode::End => pane.follow(),
+                    _ => {}
+                }
+            }
+        }
+
+        while let Ok(message) = receiver.try_recv() {
+            match message {
+                Ok((topic, payload)) => {
+                    status.clear();
+                    pane.push(SniffEntry { elapsed: started.elapsed(), topic, text: format.describe(&payload).into_owned() });
+                }
+                Err(e) => status = format!("連線錯誤: {}", e),
+            }
+        }
+
+        let grid = compose(&pane, pattern, &status, width, pane_height);
+        renderer.render_grid(&grid, width, pane_height)?;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+/// 組合窗格畫面：第一行為標題與操作說明，其下為訊息
+fn compose(pane: &SniffPane, pattern: &str, status: &str, width: u16, height: u16) -> Vec<Vec<MapDisplay>> {
+    let mut grid = vec![vec![MapDisplay::EMPTY; width as usize]; height as usize];
+    let position = if pane.following() { "跟隨最新" } else { "已捲動，End 回到最新" };
+    let header = format!("🎧 sniff {}  共 {} 則  {}  ↑↓/PgUp/PgDn 捲動  q 結束", pattern, pane.total(), position);
+    draw_text(&mut grid, 0, 0, &header, Color::Cyan);
+    if !status.is_empty() {
+        draw_text(&mut grid, str_width(&header) + 2, 0, status, Color::Red);
+    }
+    for (row, entry) in pane.visible(height.saturating_sub(1) as usize).enumerate() {
+        let prefix = format!("{:>8.3}s {} ", entry.elapsed.as_secs_f32(), entry.topic);
+        draw_text(&mut grid, 0, row + 1, &prefix, Color::Green);
+        draw_text(&mut grid, str_width(&prefix), row + 1, &entry.text, Color::White);
+    }
+    grid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(index: usize) -> SniffEntry {
+        SniffEntry { elapsed: Duration::from_millis(index as u64), topic: "td/all/res".to_string(), text: index.to_string() }
+    }
+
+    fn texts(pane: &SniffPane, height: usize) -> Vec<String> {
+        pane.visible(height).map(|entry| entry.text.clone()).collect()
+    }
+
+    #[test]
+    fn scrolled_pane_stays_put_while_messages_arrive() {
+        let mut pane = SniffPane::default();
+        for index in 0..5 {
+            pane.push(entry(index));
+        }
+        assert_eq!(texts(&pane, 2), ["3", "4"]);
+
+        pane.scroll_up(2);
+        pane.push(entry(5));
+        assert_eq!(texts(&pane, 2), ["1", "2"], "往上捲動時不跟隨新訊息");
+        pane.scroll_up(usize::MAX);
+        assert_eq!(texts(&pane, 2), ["0"]);
+
+        pane.follow();
+        assert_eq!(texts(&pane, 2), ["4", "5"]);
+
+        for index in 6..MAX_ENTRIES + 10 {
+            pane.push(entry(index));
+        }
+        assert_eq!(pane.total(), (MAX_ENTRIES + 10) as u64);
+        assert_eq!(pane.visible(usize::MAX).count(), MAX_ENTRIES);
+    }
+}
@@ -0,0 +1,281 @@
+/// 跨客戶端的狀態雜湊
+///
+/// `[state_hash]` 開啟後，客戶端在每個伺服器時間的幀邊界（以時間同步估計的伺服器時間除以間隔）計算一次
+/// 遊戲狀態的穩定雜湊：只取觀察區域內的實體與玩家，位置與生命值依 `precision` 量化後依 ID / 名稱排序再雜湊，
+/// 與本地英雄自己的技能、道具等無關。`publish = true` 時發佈到 `td/{player_name}/state_hash`；
+/// `omobaf hash-watch` 訂閱所有玩家的雜湊，依幀比對，某個客戶端連續多幀與多數不同時標示為分歧，
+/// 自動找出後端廣播不一致
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use anyhow::Result;
+use log::{info, warn};
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use serde::{Deserialize, Serialize};
+use vek::Vec2;
+
+use crate::config::{StateHashArea, StateHashConfig};
+use crate::game_state::GameState;
+use crate::mqtt_handler::WireFormat;
+
+/// FNV-1a 64 位元（跨版本與平台穩定，不使用 std 的隨機化雜湊）
+#[derive(Debug, Clone, Copy)]
+struct Fnv(u64);
+
+impl Fnv {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    fn new() -> Self {
+        Fnv(Self::OFFSET)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn write_i64(&mut self, value: i64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_str(&mut self, value: &str) {
+        self.write_i64(value.len() as i64);
+        self.write(value.as_bytes());
+    }
+}
+
+/// 一幀的雜湊
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHash {
+    /// 幀編號（伺服器時間 / 間隔）
+    pub frame: u64,
+    pub hash: u64,
+    /// 納入雜湊的實體與玩家數
+    pub objects: usize,
+}
+
+/// 依配置計算狀態雜湊
+pub fn compute(state: &GameState, config: &StateHashConfig, frame: u64) -> FrameHash {
+    let quantize = |value: f32| (value / config.precision).round() as i64;
+    let in_area = |position: Vec2<f32>| match &config.area {
+        Some(StateHashArea { x, y, radius }) => position.distance(Vec2::new(*x, *y)) <= *radius,
+        None => true,
+    };
+
+    let mut entities: Vec<_> = state.entities.values().filter(|entity| in_area(entity.position)).collect();
+    entities.sort_by_key(|entity| entity.id);
+    let local = &state.local_player;
+    let mut players: BTreeMap<&str, (Vec2<f32>, f32)> = state.other_players.values()
+        .map(|player| (player.name.as_str(), (Vec2::new(player.position.0, player.position.1), player.health.0)))
+        .collect();
+    players.insert(local.name.as_str(), (local.position, local.health.0));
+    players.retain(|_, (position, _)| in_area(*position));
+
+    let mut hasher = Fnv::new();
+    for entity in &entities {
+        hasher.write_i64(entity.id as i64);
+        hasher.write_str(crate::testkit::entity_kind(&entity.entity_type));
+        hasher.write_i64(quantize(entity.position.x));
+        hasher.write_i64(quantize(entity.position.y));
+        hasher.write_i64(quantize(entity.health.0));
+    }
+    for (name, (position, health)) in &players {
+        hasher.write_str(name);
+        hasher.write_i64(quantize(position.x));
+        hasher.write_i64(quantize(position.y));
+        hasher.write_i64(quantize(*health));
+    }
+    FrameHash { frame, hash: hasher.0, objects: entities.len() + players.len() }
+}
+
+/// 發佈的雜湊訊息內容
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HashReport {
+    pub player: String,
+    pub frame: u64,
+    /// 十六進位字串（避免 JSON 數字在其他語言中失去精度）
+    pub hash: String,
+    pub objects: usize,
+}
+
+/// 發佈的訊息
+pub fn message(player: &str, frame_hash: &FrameHash) -> serde_json::Value {
+    let report = HashReport {
+        player: player.to_string(),
+        frame: frame_hash.frame,
+        hash: format!("{:016x}", frame_hash.hash),
+        objects: frame_hash.objects,
+    };
+    serde_json::json!({ "t": "state_hash", "a": "update", "d": report })
+}
+
+/// 解析收到的雜湊訊息
+pub fn parse(format: WireFormat, payload: &[u8]) -> Option<HashReport> {
+    let value: serde_json::Value = format.decode(payload).ok()?;
+    serde_json::from_value(value.get("d")?.clone()).ok()
+}
+
+/// 某個客戶端與多數客戶端的雜湊持續不同
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub player: String,
+    /// 最近一次不同的幀
+    pub frame: u64,
+    /// 連續不同的幀數
+    pub frames: u32,
+    pub hash: String,
+    /// 多數客戶端的雜湊
+    pub majority: String,
+}
+
+/// 比對多個客戶端的雜湊
+#[derive(Debug, Default)]
+pub struct HashComparator {
+    /// 各幀各玩家的雜湊
+    frames: BTreeMap<u64, BTreeMap<String, String>>,
+    /// 各玩家連續與多數不同的幀數
+    streaks: HashMap<String, u32>,
+    /// 連續幾幀不同才標示為分歧（單幀不同通常只是取樣時間差）
+    tolerance: u32,
+}
+
+impl HashComparator {
+    pub fn new(tolerance: u32) -> Self {
+        Self { tolerance: tolerance.max(1), ..Self::default() }
+    }
+
+    pub fn observe(&mut self, report: HashReport) {
+        self.frames.entry(report.frame).or_default().insert(report.player, report.hash);
+    }
+
+    /// 比對早於 before 的幀（之後的幀可能還有雜湊在路上），返回這次達到門檻的分歧
+    pub fn settle(&mut self, before: u64) -> Vec<Divergence> {
+        let pending = self.frames.split_off(&before);
+        let settled = std::mem::replace(&mut self.frames, pending);
+        let mut divergences = Vec::new();
+        for (frame, hashes) in settled {
+            // 只有一個客戶端回報的幀無從比對
+            if hashes.len() < 2 {
+                continue;
+            }
+            let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+            for hash in hashes.values() {
+                *counts.entry(hash.as_str()).or_default() += 1;
+            }
+            let (majority, votes) = counts.iter().max_by_key(|(_, count)| **count).map(|(hash, count)| (*hash, *count)).unwrap_or_default();
+            // 沒有多數（例如兩個客戶端互不相同）時無法判斷是誰分歧
+            let contested = counts.values().filter(|count| **count == votes).count() > 1;
+            for (player, hash) in &hashes {
+                let streak = self.streaks.entry(player.clone()).or_default();
+                if contested || hash == majority {
+                    *streak = 0;
+                    continue;
+                }
+                *streak += 1;
+                if *streak == self.tolerance {
+                    divergences.push(Divergence {
+                        player: player.clone(),
+                        frame,
+                        frames: *streak,
+                        hash: hash.clone(),
+                        majority: majority.to_string(),
+                    });
+                }
+            }
+        }
+        divergences
+    }
+}
+
+/// 多久比對一次已收齊的幀
+const SETTLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 比對結果的統計
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WatchSummary {
+    /// 收到的雜湊數
+    pub reports: u64,
+    /// 標示的分歧數
+    pub divergences: u64,
+}
+
+/// 訂閱所有客戶端的雜湊並持續比對，直到按下 Ctrl-C
+pub async fn watch(options: MqttOptions, pattern: &str, format: WireFormat, tolerance: u32) -> Result<WatchSummary> {
+    let (client, mut eventloop) = AsyncClient::new(options, 100);
+    client.subscribe(pattern, QoS::AtMostOnce).await?;
+    info!("🧮 監看 {} 的狀態雜湊（連續 {} 幀與多數不同時標示分歧，按 Ctrl-C 結束）", pattern, tolerance);
+
+    let mut comparator = HashComparator::new(tolerance);
+    let mut summary = WatchSummary::default();
+    let mut latest_frame = 0;
+    let mut settle = tokio::time::interval(SETTLE_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            _ = settle.tick() => {
+                // 最新一幀的雜湊可能還有客戶端未送達，只比對更早的幀
+                for divergence in comparator.settle(latest_frame) {
+                    summary.divergences += 1;
+                    println!("⚠️ 幀 {}: {} 已連續 {} 幀與多數不同（{} ≠ {}）",
+                        divergence.frame, divergence.player, divergence.frames, divergence.hash, divergence.majority);
+                }
+            }
+            event = eventloop.poll() => match event {
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    if let Some(report) = parse(format, &publish.payload) {
+                        summary.reports += 1;
+                        latest_frame = latest_frame.max(report.frame);
+                        comparator.observe(report);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("監看連線錯誤: {}", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            },
+        }
+    }
+    let _ = client.disconnect().await;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::{Entity, EntityType};
+
+    fn report(player: &str, frame: u64, hash: &str) -> HashReport {
+        HashReport { player: player.to_string(), frame, hash: hash.to_string(), objects: 1 }
+    }
+
+    #[test]
+    fn hashes_quantized_area_and_flags_persistent_divergence() {
+        let config = StateHashConfig { precision: 1.0, area: Some(StateHashArea { x: 0.0, y: 0.0, radius: 50.0 }), ..StateHashConfig::default() };
+        let mut a = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        let creep = Entity { id: 3, entity_type: EntityType::Creep, position: Vec2::new(10.2, 5.0), health: (80.0, 100.0), owner: None, debug_label: None };
+        a.entities.insert(3, creep.clone());
+        let mut b = a.clone();
+        b.entities.get_mut(&3).unwrap().position.x = 9.9;
+        b.entities.insert(9, Entity { id: 9, position: Vec2::new(500.0, 500.0), ..creep });
+        assert_eq!(compute(&a, &config, 1), compute(&b, &config, 1), "量化後相同、區域外的實體不計入");
+        b.entities.get_mut(&3).unwrap().health.0 = 60.0;
+        assert_ne!(compute(&a, &config, 1).hash, compute(&b, &config, 1).hash);
+
+        let hashed = compute(&a, &config, 7);
+        let payload = serde_json::to_vec(&message("p1", &hashed)).unwrap();
+        assert_eq!(parse(WireFormat::Json, &payload).unwrap().hash, format!("{:016x}", hashed.hash));
+
+        let mut comparator = HashComparator::new(2);
+        for frame in 1..=3 {
+            comparator.observe(report("p1", frame, "aa"));
+            comparator.observe(report("p2", frame, "aa"));
+            comparator.observe(report("p3", frame, if frame == 1 { "aa" } else { "bb" }));
+        }
+        comparator.observe(report("p1", 4, "aa"));
+        let divergences = comparator.settle(4);
+        assert_eq!(divergences, vec![Divergence { player: "p3".to_string(), frame: 3, frames: 2, hash: "bb".to_string(), majority: "aa".to_string() }]);
+        assert!(comparator.settle(5).is_empty(), "只有一個客戶端回報的幀不比對");
+    }
+}
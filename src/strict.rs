@@ -0,0 +1,85 @@
+/// 嚴格模式
+///
+/// `--strict` 在命令結束時檢查這次執行期間的協議錯誤、訊息中未定義的欄位、位置同步差異與未被伺服器確認的操作，
+/// 任何一項超過門檻就讓命令失敗並以非零結束碼退出，供後端合併前的驗證流程使用
+use std::collections::BTreeMap;
+use colored::*;
+
+use crate::config::StrictConfig;
+use crate::expectations::ActionTally;
+use crate::game_state::GameState;
+
+/// 嚴格模式檢查結果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StrictReport {
+    /// 未通過的檢查說明
+    pub violations: Vec<String>,
+}
+
+impl StrictReport {
+    /// 依遊戲狀態、協議錯誤數與操作驗證統計檢查
+    pub fn evaluate(state: &GameState, protocol_errors: u64, tallies: &BTreeMap<String, ActionTally>, config: &StrictConfig) -> Self {
+        let mut violations = Vec::new();
+        if protocol_errors > 0 {
+            violations.push(format!("{} 則訊息解碼或處理失敗（其中 {} 則結構不符）", protocol_errors, state.schema_errors));
+        }
+        for (schema, stats) in state.schema_report.schemas() {
+            if !stats.extra.is_empty() {
+                let fields: Vec<&str> = stats.extra.keys().map(String::as_str).collect();
+                violations.push(format!("{} 訊息出現前端未定義的欄位: {}", schema, fields.join(", ")));
+            }
+        }
+        if state.sync_errors > config.max_sync_errors {
+            violations.push(format!("位置同步差異 {} 次，超過門檻 {} 次", state.sync_errors, config.max_sync_errors));
+        }
+        for (action, tally) in tallies {
+            if tally.mismatched > 0 {
+                violations.push(format!("{} 有 {} 次操作未被伺服器確認或結果不符", action, tally.mismatched));
+            }
+        }
+        Self { violations }
+    }
+
+    /// 是否全部通過
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// 顯示結果
+    pub fn print(&self) {
+        if self.passed() {
+            println!("{} 嚴格模式檢查通過", "✅".green());
+            return;
+        }
+        println!("\n{}", "嚴格模式檢查未通過:".red().bold());
+        for violation in &self.violations {
+            println!("  {} {}", "✗".red(), violation);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fails_on_protocol_errors_desync_and_unconfirmed_actions() {
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        let mut tallies = BTreeMap::new();
+        tallies.insert("move".to_string(), ActionTally { matched: 3, mismatched: 0 });
+        let config = StrictConfig { max_sync_errors: 2 };
+        state.sync_errors = 2;
+        assert!(StrictReport::evaluate(&state, 0, &tallies, &config).passed());
+
+        state.sync_errors = 3;
+        tallies.insert("cast".to_string(), ActionTally { matched: 0, mismatched: 1 });
+        let report = StrictReport::evaluate(&state, 1, &tallies, &config);
+        assert_eq!(report.violations.len(), 3);
+        assert!(report.violations[2].starts_with("cast"));
+
+        let diff = crate::schema::FieldDiff { missing: Vec::new(), extra: vec!["shield".to_string()] };
+        state.schema_report.record("health", &diff, true);
+        let report = StrictReport::evaluate(&state, 1, &tallies, &config);
+        assert_eq!(report.violations[1], "health 訊息出現前端未定義的欄位: shield");
+    }
+}
@@ -0,0 +1,255 @@
+/// 多客戶端壓力測試
+///
+/// `omobaf swarm <n>` 在同一個行程中建立 N 個 GameClient（各自的 client ID 與玩家名稱），依間隔逐一連線、
+/// 進入遊戲後以 PlayerSimulator 自動遊戲；每秒輸出一行各連線狀態的人數、總操作數與錯誤數，
+/// 結束時列出每個客戶端的結果，供後端負載測試使用
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use log::{info, warn};
+use tokio::time::{interval, sleep, Instant, MissedTickBehavior};
+
+use crate::game_client::{ClientState, GameClient};
+use crate::shutdown::{Shutdown, ShutdownToken};
+
+/// 儀表板輸出間隔
+const DASHBOARD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 結束時等待各客戶端斷線的寬限時間
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// 壓力測試參數
+#[derive(Debug, Clone)]
+pub struct SwarmOptions {
+    /// 總時長（從第一個客戶端開始連線起算）
+    pub duration: Duration,
+    /// 相鄰兩個客戶端開始連線的間隔
+    pub ramp: Duration,
+    /// 每個客戶端兩次自動操作的間隔
+    pub action_interval: Duration,
+}
+
+/// 單一客戶端所處的階段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MemberPhase {
+    /// 等待輪到自己連線
+    Waiting,
+    Connecting,
+    InGame,
+    Reconnecting,
+    /// 遊戲中但伺服器資料停滯或失去伺服器
+    Stale,
+    Disconnected,
+    /// 連線或進入遊戲失敗
+    Failed,
+    /// 已結束並斷線
+    Finished,
+}
+
+impl MemberPhase {
+    /// 客戶端狀態對應的階段
+    pub fn of(state: &ClientState) -> Self {
+        match state {
+            ClientState::Connecting | ClientState::Connected => MemberPhase::Connecting,
+            ClientState::InGame => MemberPhase::InGame,
+            ClientState::Reconnecting(_) => MemberPhase::Reconnecting,
+            ClientState::Stale(_) | ClientState::ServerLost(_) => MemberPhase::Stale,
+            ClientState::Disconnected => MemberPhase::Disconnected,
+            ClientState::Error(_) => MemberPhase::Failed,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MemberPhase::Waiting => "等待",
+            MemberPhase::Connecting => "連線中",
+            MemberPhase::InGame => "遊戲中",
+            MemberPhase::Reconnecting => "重連中",
+            MemberPhase::Stale => "停滯",
+            MemberPhase::Disconnected => "已斷線",
+            MemberPhase::Failed => "失敗",
+            MemberPhase::Finished => "已結束",
+        }
+    }
+}
+
+/// 單一客戶端的統計
+#[derive(Debug, Clone)]
+pub struct MemberStatus {
+    pub name: String,
+    /// 英雄、陣營與 client ID
+    pub identity: String,
+    pub phase: MemberPhase,
+    /// 成功送出的自動操作數
+    pub actions: u64,
+    pub errors: u64,
+    pub last_error: Option<String>,
+}
+
+impl MemberStatus {
+    fn new(name: String, identity: String) -> Self {
+        Self { name, identity, phase: MemberPhase::Waiting, actions: 0, errors: 0, last_error: None }
+    }
+
+    fn record_error(&mut self, error: &anyhow::Error) {
+        self.errors += 1;
+        self.last_error = Some(error.to_string());
+    }
+}
+
+/// 各客戶端任務與儀表板共享的統計
+type SharedMembers = Arc<Mutex<Vec<MemberStatus>>>;
+
+/// 全體客戶端的彙總
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SwarmSummary {
+    /// 各階段的客戶端數
+    pub phases: BTreeMap<MemberPhase, usize>,
+    pub actions: u64,
+    pub errors: u64,
+}
+
+impl SwarmSummary {
+    pub fn of(members: &[MemberStatus]) -> Self {
+        let mut summary = Self::default();
+        for member in members {
+            *summary.phases.entry(member.phase).or_default() += 1;
+            summary.actions += member.actions;
+            summary.errors += member.errors;
+        }
+        summary
+    }
+
+    /// 各階段人數與總數，例如 `遊戲中 8 / 重連中 2 | 操作 120 | 錯誤 3`
+    pub fn counts(&self) -> String {
+        let phases: Vec<String> = self.phases.iter()
+            .map(|(phase, count)| format!("{} {}", phase.label(), count))
+            .collect();
+        format!("{} | 操作 {} | 錯誤 {}", phases.join(" / "), self.actions, self.errors)
+    }
+}
+
+/// 執行壓力測試直到時間結束或按下 Ctrl-C，返回每個客戶端的最終統計
+pub async fn run(clients: Vec<GameClient>, options: SwarmOptions) -> Vec<MemberStatus> {
+    let members: SharedMembers = Arc::new(Mutex::new(
+        clients.iter().map(|client| MemberStatus::new(client.player_name().to_string(), client.identity())).collect(),
+    ));
+    let mut tasks = Shutdown::default();
+    for (index, client) in clients.into_iter().enumerate() {
+        let tag = client.player_name().to_string();
+        let members = members.clone();
+        let options = options.clone();
+        let delay = options.ramp * index as u32;
+        tasks.spawn("swarm_member", Some(tag), move |token| run_member(index, client, members, delay, options, token));
+    }
+
+    let start = Instant::now();
+    let deadline = start + options.duration;
+    let mut dashboard = interval(DASHBOARD_INTERVAL);
+    dashboard.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep_until(deadline) => break,
+            _ = tokio::signal::ctrl_c() => {
+                info!("收到 Ctrl-C，停止壓力測試");
+                break;
+            }
+            _ = dashboard.tick() => {
+                let summary = SwarmSummary::of(&members.lock().unwrap());
+                println!("[{:>4}s] {}", start.elapsed().as_secs(), summary.counts());
+            }
+        }
+    }
+
+    let report = tasks.shutdown(SHUTDOWN_GRACE).await;
+    if !report.aborted.is_empty() {
+        warn!("{} 個客戶端沒有在時限內斷線，已強制中止", report.aborted.len());
+    }
+    let statuses = members.lock().unwrap().clone();
+    statuses
+}
+
+/// 單一客戶端：等待輪到自己後連線、進入遊戲，持續自動遊戲直到收到停止訊號
+async fn run_member(index: usize, mut client: GameClient, members: SharedMembers, delay: Duration, options: SwarmOptions, token: ShutdownToken) {
+    let update = |change: &dyn Fn(&mut MemberStatus)| change(&mut members.lock().unwrap()[index]);
+    tokio::select! {
+        _ = token.cancelled() => return,
+        _ = sleep(delay) => {}
+    }
+
+    update(&|member| member.phase = MemberPhase::Connecting);
+    let joined = match client.connect().await {
+        Ok(()) => client.enter_game().await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = joined {
+        warn!("{} ({}) 無法進入遊戲: {}", client.player_name(), client.identity(), e);
+        update(&|member| {
+            member.record_error(&e);
+            member.phase = MemberPhase::Failed;
+        });
+        let _ = client.disconnect().await;
+        return;
+    }
+
+    let mut ticker = interval(options.action_interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = token.cancelled() => break,
+            _ = ticker.tick() => {}
+        }
+        let synced = client.tick().await;
+        let phase = MemberPhase::of(client.get_state());
+        let acted = if client.get_state().in_game() { Some(client.auto_play_step().await) } else { None };
+        update(&|member| {
+            member.phase = phase;
+            if let Err(e) = &synced {
+                member.record_error(e);
+            }
+            match &acted {
+                Some(Ok(())) => member.actions += 1,
+                Some(Err(e)) => member.record_error(e),
+                None => {}
+            }
+        });
+    }
+
+    if let Err(e) = client.disconnect().await {
+        warn!("{} 斷線失敗: {}", client.player_name(), e);
+    }
+    update(&|member| member.phase = MemberPhase::Finished);
+}
+
+/// 輸出每個客戶端的最終統計
+pub fn print_report(members: &[MemberStatus]) {
+    println!("壓力測試結束: {}", SwarmSummary::of(members).counts());
+    for member in members {
+        let error = member.last_error.as_deref().map(|e| format!("（最後錯誤: {}）", e)).unwrap_or_default();
+        println!("  {:<16} {:<6} 操作 {:>5}  錯誤 {:>3}  {}{}", member.name, member.phase.label(), member.actions, member.errors, member.identity, error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregates_member_phases_and_counters() {
+        let mut members: Vec<MemberStatus> = (1..=4).map(|i| MemberStatus::new(format!("swarm_{:02}", i), "saika_magoichi [omobaf_swarm]".to_string())).collect();
+        members[0].phase = MemberPhase::of(&ClientState::InGame);
+        members[0].actions = 12;
+        members[1].phase = MemberPhase::of(&ClientState::ServerLost(30));
+        members[1].actions = 3;
+        members[1].record_error(&anyhow::anyhow!("操作逾時"));
+        members[2].phase = MemberPhase::of(&ClientState::Reconnecting(2));
+
+        let summary = SwarmSummary::of(&members);
+        assert_eq!(summary.phases.get(&MemberPhase::InGame), Some(&1));
+        assert_eq!(summary.phases.get(&MemberPhase::Stale), Some(&1));
+        assert_eq!(summary.phases.get(&MemberPhase::Waiting), Some(&1));
+        assert_eq!((summary.actions, summary.errors), (15, 1));
+        assert_eq!(members[1].last_error.as_deref(), Some("操作逾時"));
+        assert_eq!(summary.counts(), "等待 1 / 遊戲中 1 / 重連中 1 / 停滯 1 | 操作 15 | 錯誤 1");
+    }
+}
@@ -0,0 +1,121 @@
+/// 預判瞄準
+///
+/// 追蹤其他單位的移動速度，攻擊移動中的單位時把送出的目標位置
+/// 往前推「速度 × 往返延遲」，用來評估後端如何處理帶預判的輸入
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use vek::Vec2;
+
+use crate::game_state::GameState;
+
+/// 位置超過此時間沒有變化時視為靜止（畫面狀態每 3 秒請求一次）
+const STALE_AFTER: Duration = Duration::from_secs(5);
+
+/// 單一單位的移動軌跡
+#[derive(Debug, Clone, Copy)]
+struct Track {
+    position: Vec2<f32>,
+    /// 位置最後一次改變的時間
+    changed_at: Instant,
+    velocity: Vec2<f32>,
+}
+
+/// 預判後的目標
+#[derive(Debug, Clone)]
+pub struct LeadTarget {
+    /// 被預判的單位（例如 `entity#12`、`player:Alice`）
+    pub subject: String,
+    /// 單位目前位置
+    pub position: Vec2<f32>,
+    /// 估計速度（世界單位/秒）
+    pub velocity: Vec2<f32>,
+    /// 預判後送出的位置
+    pub led_position: Vec2<f32>,
+}
+
+/// 單位速度追蹤器
+#[derive(Debug, Default)]
+pub struct VelocityTracker {
+    tracks: HashMap<String, Track>,
+}
+
+impl VelocityTracker {
+    /// 以最新同步的狀態更新速度估計（不含本地玩家）
+    pub fn update(&mut self, state: &GameState, now: Instant) {
+        let mut observed: Vec<(String, Vec2<f32>)> = state.other_players.iter()
+            .map(|(name, player)| (format!("player:{}", name), Vec2::new(player.position.0, player.position.1)))
+            .collect();
+        observed.extend(state.entities.iter().map(|(id, entity)| (format!("entity#{}", id), entity.position)));
+
+        let mut tracks = HashMap::with_capacity(observed.len());
+        for (subject, position) in observed {
+            let track = match self.tracks.get(&subject) {
+                Some(previous) if (position - previous.position).magnitude() > f32::EPSILON => {
+                    let dt = now.duration_since(previous.changed_at).as_secs_f32().max(0.001);
+                    Track { position, changed_at: now, velocity: (position - previous.position) / dt }
+                }
+                Some(previous) => *previous,
+                None => Track { position, changed_at: now, velocity: Vec2::zero() },
+            };
+            tracks.insert(subject, track);
+        }
+        self.tracks = tracks;
+    }
+
+    /// 找出目標點附近最近的移動中單位，返回預判 `lead` 時間後的位置
+    pub fn lead(&self, target: Vec2<f32>, pick_radius: f32, lead: Duration, now: Instant) -> Option<LeadTarget> {
+        let (subject, track) = self.tracks.iter()
+            .filter(|(_, track)| now.duration_since(track.changed_at) < STALE_AFTER)
+            .filter(|(_, track)| track.velocity.magnitude() > f32::EPSILON)
+            .map(|(subject, track)| (subject, track, (track.position - target).magnitude()))
+            .filter(|(_, _, distance)| *distance <= pick_radius)
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(subject, track, _)| (subject, track))?;
+
+        Some(LeadTarget {
+            subject: subject.clone(),
+            position: track.position,
+            velocity: track.velocity,
+            led_position: track.position + track.velocity * lead.as_secs_f32(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::{Entity, EntityType};
+
+    fn state_with(entities: &[(u32, Vec2<f32>)]) -> GameState {
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        for &(id, position) in entities {
+            state.entities.insert(id, Entity { id, entity_type: EntityType::Creep, position, health: (300.0, 300.0), owner: None, debug_label: None });
+        }
+        state
+    }
+
+    #[test]
+    fn leads_the_nearest_moving_unit_by_velocity_times_rtt() {
+        let start = Instant::now();
+        let mut tracker = VelocityTracker::default();
+        tracker.update(&state_with(&[(1, Vec2::new(0.0, 0.0)), (2, Vec2::new(50.0, 0.0)), (3, Vec2::new(5.0, 0.0))]), start);
+        assert!(tracker.lead(Vec2::zero(), 100.0, Duration::from_millis(200), start).is_none(), "第一次看見時沒有速度");
+
+        // 1 與 2 在 0.5 秒內移動，3 靜止
+        let later = start + Duration::from_millis(500);
+        tracker.update(&state_with(&[(1, Vec2::new(10.0, 0.0)), (2, Vec2::new(50.0, 20.0)), (3, Vec2::new(5.0, 0.0))]), later);
+        let lead = tracker.lead(Vec2::new(4.0, 0.0), 100.0, Duration::from_millis(200), later).unwrap();
+        assert_eq!(lead.subject, "entity#1", "略過靜止的 3，選最近的移動中單位");
+        assert_eq!(lead.velocity, Vec2::new(20.0, 0.0));
+        assert_eq!(lead.led_position, Vec2::new(14.0, 0.0));
+
+        let lead = tracker.lead(Vec2::new(50.0, 30.0), 15.0, Duration::from_secs(1), later).unwrap();
+        assert_eq!((lead.subject.as_str(), lead.led_position), ("entity#2", Vec2::new(50.0, 60.0)));
+        assert!(tracker.lead(Vec2::new(200.0, 0.0), 15.0, Duration::from_secs(1), later).is_none(), "超出挑選半徑");
+
+        // 位置停止變化超過 STALE_AFTER 後視為靜止
+        let stale = later + STALE_AFTER;
+        tracker.update(&state_with(&[(1, Vec2::new(10.0, 0.0)), (2, Vec2::new(50.0, 20.0))]), stale);
+        assert!(tracker.lead(Vec2::new(10.0, 0.0), 100.0, Duration::from_secs(1), stale).is_none());
+    }
+}
@@ -1,8 +1,10 @@
 /// 終端視圖專用日誌系統
 /// 
-/// 在視圖模式下收集日誌並顯示在底部區域
+/// 在視圖模式下收集日誌並顯示在底部區域。日誌的標籤、顏色與訊息在寫入時就整理好，
+/// 渲染時只在日誌內容或終端大小改變後才重繪日誌區域，慢速終端不必每幀重寫相同的內容
 use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
 use crossterm::{
     cursor,
     queue,
@@ -10,19 +12,63 @@ use crossterm::{
     terminal::{Clear, ClearType},
 };
 use std::io::{self, Write};
+use crate::terminal_view::text_width::{str_width, truncate_with_ellipsis};
 
 /// 日誌條目
 #[derive(Debug, Clone)]
 pub struct LogEntry {
-    pub level: String,
-    pub message: String,
     pub timestamp: std::time::Instant,
+    /// 顯示用的內容（寫入時整理好）
+    pub line: LogLine,
+}
+
+/// 整理好的日誌行
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub color: Color,
+    /// 級別標籤，如 `[INFO]`
+    pub label: String,
+    /// 產生此日誌的會話標籤與顏色
+    pub tag: Option<(String, Color)>,
+    /// 標籤加會話標籤的顯示寬度
+    pub prefix_width: usize,
+    /// 換行已替換為空白的訊息
+    pub text: String,
+}
+
+impl LogLine {
+    fn new(level: &str, message: &str, session: Option<&str>) -> Self {
+        let color = match level {
+            "ERROR" => Color::Red,
+            "WARN" => Color::Yellow,
+            "INFO" => Color::Green,
+            "DEBUG" => Color::Blue,
+            _ => Color::White,
+        };
+        let label = format!("[{}]", level);
+        let tag = session.map(|session| (format!(" [{}]", session), session_color(session)));
+        let prefix_width = str_width(&label) + tag.as_ref().map_or(0, |(tag, _)| str_width(tag));
+        Self { color, label, tag, prefix_width, text: message.replace('\n', " ") }
+    }
+}
+
+/// 上次渲染日誌區域時的日誌版本與終端大小
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RenderedLogs {
+    revision: u64,
+    terminal_width: u16,
+    terminal_height: u16,
+    log_lines: usize,
 }
 
 /// 終端日誌收集器
 pub struct TerminalLogger {
     entries: Arc<Mutex<VecDeque<LogEntry>>>,
     max_entries: usize,
+    /// 每次新增或清空日誌時遞增
+    revision: AtomicU64,
+    /// 上次渲染的日誌區域（None 表示需要重繪）
+    rendered: Mutex<Option<RenderedLogs>>,
 }
 
 impl TerminalLogger {
@@ -31,6 +77,8 @@ impl TerminalLogger {
         Self {
             entries: Arc::new(Mutex::new(VecDeque::new())),
             max_entries,
+            revision: AtomicU64::new(0),
+            rendered: Mutex::new(None),
         }
     }
     
@@ -50,17 +98,18 @@ impl TerminalLogger {
     
     /// 添加日誌條目
     pub fn log(&self, level: &str, message: String) {
+        let line = LogLine::new(level, &message, crate::session_tag::current().as_deref());
         let mut entries = self.entries.lock().unwrap();
         entries.push_back(LogEntry {
-            level: level.to_string(),
-            message,
             timestamp: std::time::Instant::now(),
+            line,
         });
         
         // 限制最大條目數
         while entries.len() > self.max_entries {
             entries.pop_front();
         }
+        self.revision.fetch_add(1, Ordering::Relaxed);
     }
     
     /// 獲取最近的日誌條目
@@ -78,10 +127,35 @@ impl TerminalLogger {
     pub fn clear(&self) {
         let mut entries = self.entries.lock().unwrap();
         entries.clear();
+        self.revision.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// 終端畫面被清除後要求下次渲染時重繪日誌區域
+    pub fn invalidate(&self) {
+        *self.rendered.lock().unwrap() = None;
+    }
+    
+    /// 日誌內容或終端大小自上次渲染後是否改變（改變時記下這次的狀態）
+    fn needs_redraw(&self, terminal_width: u16, terminal_height: u16, log_lines: usize) -> bool {
+        let current = RenderedLogs {
+            revision: self.revision.load(Ordering::Relaxed),
+            terminal_width,
+            terminal_height,
+            log_lines,
+        };
+        let mut rendered = self.rendered.lock().unwrap();
+        if *rendered == Some(current) {
+            return false;
+        }
+        *rendered = Some(current);
+        true
     }
     
-    /// 在終端底部渲染日誌
+    /// 在終端底部渲染日誌（內容與終端大小都沒變時不輸出任何東西）
     pub fn render_logs(&self, stdout: &mut io::Stdout, terminal_width: u16, terminal_height: u16, log_lines: usize) -> io::Result<()> {
+        if !self.needs_redraw(terminal_width, terminal_height, log_lines) {
+            return Ok(());
+        }
         let logs = self.get_recent_logs(log_lines);
         let log_start_y = terminal_height.saturating_sub(log_lines as u16);
         
@@ -96,29 +170,22 @@ impl TerminalLogger {
             if i >= log_lines {
                 break;
             }
+            let line = &entry.line;
             
             queue!(stdout, cursor::MoveTo(0, log_start_y + i as u16))?;
+            queue!(stdout, SetForegroundColor(line.color))?;
+            queue!(stdout, Print(&line.label))?;
             
-            // 設置顏色
-            let color = match entry.level.as_str() {
-                "ERROR" => Color::Red,
-                "WARN" => Color::Yellow,
-                "INFO" => Color::Green,
-                "DEBUG" => Color::Blue,
-                _ => Color::White,
-            };
-            
-            queue!(stdout, SetForegroundColor(color))?;
-            queue!(stdout, Print(format!("[{}]", entry.level)))?;
+            // 會話標籤
+            if let Some((tag, color)) = &line.tag {
+                queue!(stdout, SetForegroundColor(*color))?;
+                queue!(stdout, Print(tag))?;
+            }
             queue!(stdout, SetForegroundColor(Color::White))?;
             
-            // 截斷過長的訊息
-            let max_msg_len = terminal_width as usize - 8; // 留出級別標籤空間
-            let message = if entry.message.len() > max_msg_len {
-                format!("{}...", &entry.message[..max_msg_len - 3])
-            } else {
-                entry.message.clone()
-            };
+            // 依顯示寬度截斷過長的訊息（中文與 emoji 佔兩格）
+            let max_msg_width = (terminal_width as usize).saturating_sub(line.prefix_width + 1);
+            let message = truncate_with_ellipsis(&line.text, max_msg_width);
             
             queue!(stdout, Print(format!(" {}", message)))?;
         }
@@ -128,6 +195,22 @@ impl TerminalLogger {
     }
 }
 
+/// 會話標籤在終端視圖中的顏色
+fn session_color(session: &str) -> Color {
+    use colored::Color as Ansi;
+    match crate::session_tag::color_for(session) {
+        Ansi::Cyan => Color::DarkCyan,
+        Ansi::Magenta => Color::DarkMagenta,
+        Ansi::Green => Color::DarkGreen,
+        Ansi::Blue => Color::DarkBlue,
+        Ansi::BrightCyan => Color::Cyan,
+        Ansi::BrightMagenta => Color::Magenta,
+        Ansi::BrightGreen => Color::Green,
+        Ansi::BrightBlue => Color::Blue,
+        _ => Color::White,
+    }
+}
+
 /// 自定義日誌寫入器，將日誌重定向到終端日誌收集器
 pub struct TerminalLogWriter;
 
@@ -166,4 +249,26 @@ impl Write for TerminalLogWriter {
     fn flush(&mut self) -> io::Result<()> {
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redraws_only_after_new_logs_or_resize() {
+        let logger = TerminalLogger::new(10);
+        assert!(logger.needs_redraw(80, 24, 3));
+        assert!(!logger.needs_redraw(80, 24, 3), "內容未變時不重繪");
+
+        logger.log("WARN", "第一行\n第二行".to_string());
+        assert!(logger.needs_redraw(80, 24, 3));
+        assert!(!logger.needs_redraw(80, 24, 3));
+        assert!(logger.needs_redraw(100, 24, 3), "終端大小改變時重繪");
+
+        logger.invalidate();
+        assert!(logger.needs_redraw(100, 24, 3), "畫面被清除後重繪");
+        let line = &logger.get_recent_logs(1)[0].line;
+        assert_eq!((line.label.as_str(), line.text.as_str(), line.color), ("[WARN]", "第一行 第二行", Color::Yellow));
+    }
+}
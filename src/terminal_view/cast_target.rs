@@ -0,0 +1,148 @@
+/// 技能目標與瞄準預覽
+///
+/// 技能依英雄目錄的 `cast_type` 決定目標：`point` 施放在游標位置、`unit` 施放在游標下的單位（附近沒有單位時不施放）、
+/// `self` 施放在英雄自己身上、`direction` 朝英雄到游標的方向發射。選擇技能後到點擊之前，終端視圖依游標位置
+/// 畫出預覽：地點與單位目標標出落點，方向技能畫出從英雄出發、長度為施放距離的彈道，並在左下角顯示目前的目標
+use vek::Vec2;
+
+use crate::game_state::GameState;
+use crate::hero_catalog::{AbilityDefinition, CastType};
+use crate::selection;
+
+/// 未設定施放距離的方向技能預覽長度
+const DEFAULT_DIRECTION_LENGTH: f32 = 300.0;
+
+/// 方向預覽的點間距（世界單位，一格）
+const PREVIEW_STEP: f32 = 10.0;
+
+/// 技能的施放目標（後端座標）
+#[derive(Debug, Clone, PartialEq)]
+pub enum CastTarget {
+    Point(Vec2<f32>),
+    Unit { id: u32, position: Vec2<f32> },
+    /// 英雄自己（英雄位置）
+    SelfCast(Vec2<f32>),
+    /// 單位方向向量
+    Direction { origin: Vec2<f32>, direction: Vec2<f32> },
+}
+
+impl CastTarget {
+    /// 依施放方式與游標位置決定目標（單位目標附近沒有單位、方向目標游標與英雄重疊時為 None）
+    pub fn resolve(cast_type: CastType, game_state: &GameState, cursor: Vec2<f32>) -> Option<Self> {
+        let hero = game_state.local_player.position;
+        match cast_type {
+            CastType::Point => Some(CastTarget::Point(cursor)),
+            CastType::Unit => selection::pick(game_state, cursor, selection::PICK_RADIUS)
+                .map(|entity| CastTarget::Unit { id: entity.id, position: entity.position }),
+            CastType::SelfCast => Some(CastTarget::SelfCast(hero)),
+            CastType::Direction => {
+                let offset = cursor - hero;
+                (offset.magnitude() > f32::EPSILON).then(|| CastTarget::Direction { origin: hero, direction: offset.normalized() })
+            }
+        }
+    }
+
+    /// 目標位置（方向目標為英雄位置）
+    pub fn position(&self) -> Vec2<f32> {
+        match self {
+            CastTarget::Point(position) | CastTarget::SelfCast(position) => *position,
+            CastTarget::Unit { position, .. } => *position,
+            CastTarget::Direction { origin, .. } => *origin,
+        }
+    }
+
+    /// `cast_ability` 操作參數
+    pub fn params(&self, ability_id: &str) -> serde_json::Value {
+        let position = self.position();
+        let mut params = serde_json::json!({
+            "ability_id": ability_id,
+            "target_position": [position.x, position.y],
+            "level": 1
+        });
+        match self {
+            CastTarget::Unit { id, .. } => params["target_entity"] = serde_json::json!(id),
+            CastTarget::Direction { direction, .. } => params["direction"] = serde_json::json!([direction.x, direction.y]),
+            CastTarget::Point(_) | CastTarget::SelfCast(_) => {}
+        }
+        params
+    }
+
+    /// 日誌用的描述
+    pub fn describe(&self) -> String {
+        match self {
+            CastTarget::Point(position) => format!("位置 ({:.1}, {:.1})", position.x, position.y),
+            CastTarget::Unit { id, .. } => format!("單位 #{}", id),
+            CastTarget::SelfCast(_) => "自身".to_string(),
+            CastTarget::Direction { direction, .. } => format!("方向 ({:.2}, {:.2})", direction.x, direction.y),
+        }
+    }
+}
+
+/// 選擇技能後的瞄準預覽
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetingPreview {
+    pub ability: AbilityDefinition,
+    /// 依游標位置決定的目標（沒有有效目標時為 None）
+    pub target: Option<CastTarget>,
+}
+
+impl TargetingPreview {
+    pub fn new(ability: &AbilityDefinition, game_state: &GameState, cursor: Vec2<f32>) -> Self {
+        Self { ability: ability.clone(), target: CastTarget::resolve(ability.cast_type, game_state, cursor) }
+    }
+
+    /// 要標記的世界座標：方向技能為彈道上的點（最後一點為終點），其他為落點
+    pub fn markers(&self) -> Vec<Vec2<f32>> {
+        match &self.target {
+            Some(CastTarget::Direction { origin, direction }) => {
+                let length = if self.ability.range > 0.0 { self.ability.range } else { DEFAULT_DIRECTION_LENGTH };
+                let steps = (length / PREVIEW_STEP).ceil() as usize;
+                (1..=steps).map(|step| *origin + *direction * (step as f32 * PREVIEW_STEP).min(length)).collect()
+            }
+            Some(target) => vec![target.position()],
+            None => Vec::new(),
+        }
+    }
+
+    /// 狀態列文字
+    pub fn status(&self) -> String {
+        let target = match &self.target {
+            Some(target) => target.describe(),
+            None if self.ability.cast_type == CastType::Unit => "游標附近沒有單位".to_string(),
+            None => "移動游標以指定方向".to_string(),
+        };
+        format!("✦ {}（{}）→ {}", self.ability.name, self.ability.cast_type.label(), target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::{Entity, EntityType};
+
+    #[test]
+    fn resolves_targets_by_cast_type() {
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        state.local_player.position = Vec2::new(100.0, 100.0);
+        state.entities.insert(7, Entity {
+            id: 7, entity_type: EntityType::Creep, position: Vec2::new(200.0, 100.0), health: (100.0, 100.0), owner: None, debug_label: None,
+        });
+        let cursor = Vec2::new(190.0, 110.0);
+
+        assert_eq!(CastTarget::resolve(CastType::Point, &state, cursor), Some(CastTarget::Point(cursor)));
+        let unit = CastTarget::resolve(CastType::Unit, &state, cursor).unwrap();
+        assert_eq!(unit.params("three_stage_technique")["target_entity"], 7);
+        assert_eq!(CastTarget::resolve(CastType::Unit, &state, Vec2::new(100.0, 300.0)), None, "游標附近沒有單位");
+        assert_eq!(CastTarget::resolve(CastType::SelfCast, &state, cursor).unwrap().position(), Vec2::new(100.0, 100.0));
+
+        let direction = CastTarget::resolve(CastType::Direction, &state, Vec2::new(100.0, 50.0)).unwrap();
+        assert_eq!(direction.params("fire_dash")["direction"], serde_json::json!([0.0, -1.0]));
+        assert_eq!(CastTarget::resolve(CastType::Direction, &state, Vec2::new(100.0, 100.0)), None);
+
+        let mut ability = crate::hero_catalog::registry().ability("fire_dash").unwrap().clone();
+        ability.cast_type = CastType::Direction;
+        ability.range = 25.0;
+        let preview = TargetingPreview::new(&ability, &state, Vec2::new(200.0, 100.0));
+        assert_eq!(preview.markers(), [Vec2::new(110.0, 100.0), Vec2::new(120.0, 100.0), Vec2::new(125.0, 100.0)]);
+    }
+}
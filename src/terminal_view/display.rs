@@ -1,6 +1,10 @@
 /// 地圖顯示符號和顏色定義
 use crossterm::style::Color;
 
+use crate::game_state::{Entity, EntityType};
+use crate::terrain::TerrainKind;
+use crate::world_map::Fade;
+
 /// 地圖符號和顏色定義
 #[derive(Clone, Copy)]
 pub struct MapDisplay {
@@ -9,6 +13,38 @@ pub struct MapDisplay {
 }
 
 impl MapDisplay {
+    /// 實體的地圖符號（召喚物依擁有者區分敵我）
+    pub fn for_entity(entity: &Entity, local_player: &str) -> MapDisplay {
+        match entity.entity_type {
+            EntityType::Player(_) => MapDisplay::PLAYER_ENEMY,
+            EntityType::Summon(_) if entity.owner.as_deref() == Some(local_player) => MapDisplay::SUMMON_ALLY,
+            EntityType::Summon(_) => MapDisplay::SUMMON_ENEMY,
+            EntityType::Projectile => MapDisplay::PROJECTILE,
+            EntityType::Effect => MapDisplay::EFFECT,
+            EntityType::Creep => MapDisplay::CREEP,
+            EntityType::Tower => MapDisplay::TOWER,
+        }
+    }
+    
+    /// 地形的地圖符號
+    pub fn for_terrain(kind: TerrainKind) -> MapDisplay {
+        match kind {
+            TerrainKind::Wall => MapDisplay::WALL,
+            TerrainKind::Tree => MapDisplay::TREE,
+            TerrainKind::Water => MapDisplay::WATER,
+            TerrainKind::Mountain => MapDisplay::MOUNTAIN,
+        }
+    }
+    
+    /// 依記憶的新舊程度淡化顏色
+    pub fn faded(self, fade: Fade) -> MapDisplay {
+        match fade {
+            Fade::Fresh => self,
+            Fade::Stale => MapDisplay { color: Color::Grey, ..self },
+            Fade::Old => MapDisplay { color: Color::DarkGrey, ..self },
+        }
+    }
+    
     // 玩家相關符號
     pub const PLAYER_SELF: MapDisplay = MapDisplay { symbol: '@', color: Color::Yellow };
     pub const PLAYER_ALLY: MapDisplay = MapDisplay { symbol: 'A', color: Color::Green };
@@ -18,6 +54,8 @@ impl MapDisplay {
     pub const SUMMON_ALLY: MapDisplay = MapDisplay { symbol: 's', color: Color::Cyan };
     pub const SUMMON_ENEMY: MapDisplay = MapDisplay { symbol: 'S', color: Color::Magenta };
     pub const PROJECTILE: MapDisplay = MapDisplay { symbol: '*', color: Color::White };
+    pub const CREEP: MapDisplay = MapDisplay { symbol: 'c', color: Color::DarkYellow };
+    pub const TOWER: MapDisplay = MapDisplay { symbol: 'H', color: Color::DarkMagenta };
     
     // 地形符號
     pub const EMPTY: MapDisplay = MapDisplay { symbol: '.', color: Color::DarkGrey };
@@ -26,6 +64,10 @@ impl MapDisplay {
     pub const WATER: MapDisplay = MapDisplay { symbol: '~', color: Color::Blue };
     pub const MOUNTAIN: MapDisplay = MapDisplay { symbol: '^', color: Color::DarkGrey };
     
+    // 地圖邊界
+    pub const BOUNDARY: MapDisplay = MapDisplay { symbol: '#', color: Color::DarkRed };
+    pub const OUT_OF_BOUNDS: MapDisplay = MapDisplay { symbol: ' ', color: Color::Black };
+    
     // 視野相關
     pub const VISION_EDGE: MapDisplay = MapDisplay { symbol: '○', color: Color::Yellow };
     pub const FOG_OF_WAR: MapDisplay = MapDisplay { symbol: '?', color: Color::DarkGrey };
@@ -33,4 +75,15 @@ impl MapDisplay {
     // 特效符號
     pub const EFFECT: MapDisplay = MapDisplay { symbol: '!', color: Color::Red };
     pub const EXPLOSION: MapDisplay = MapDisplay { symbol: '%', color: Color::Red };
+    
+    // 鍵盤目標模式的準星
+    pub const TARGET_CURSOR: MapDisplay = MapDisplay { symbol: '+', color: Color::White };
+    
+    // 技能瞄準預覽（方向技能的彈道與落點）
+    pub const TARGET_PATH: MapDisplay = MapDisplay { symbol: '·', color: Color::Magenta };
+    pub const TARGET_MARK: MapDisplay = MapDisplay { symbol: '◎', color: Color::Magenta };
+    
+    // 選擇的目標兩側的方括號
+    pub const SELECT_LEFT: MapDisplay = MapDisplay { symbol: '[', color: Color::White };
+    pub const SELECT_RIGHT: MapDisplay = MapDisplay { symbol: ']', color: Color::White };
 }
\ No newline at end of file
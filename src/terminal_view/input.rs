@@ -7,13 +7,40 @@ use std::thread;
 use std::time::Duration;
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 use log::debug;
+use crate::shutdown::ShutdownSignal;
+#[cfg(not(windows))]
+use crate::shutdown::ShutdownToken;
 use crate::terminal_logger::TerminalLogger;
 
 #[cfg(windows)]
 use winapi::um::winuser::{GetAsyncKeyState, VK_ESCAPE};
 use vek::Vec2;
+use crate::config::{CastMode, InputConfig, Keymap};
 use crate::game_state::GameState;
+use crate::hero_catalog::{AbilityDefinition, CastType};
+use crate::selection;
+use super::cast_target::{CastTarget, TargetingPreview};
+use crate::key_macros::{KeyMacros, MacroCommand};
+use super::overlays::OverlayManager;
 use super::viewport::ViewportManager;
+use super::text_input::{TextInput, TextInputEvent};
+
+/// 鍵盤目標模式中方向鍵每次移動準星的距離（世界單位，一個字元）
+const CURSOR_STEP: f32 = 10.0;
+
+/// 按住 Shift 時準星移動的倍數
+const CURSOR_FAST_MULTIPLIER: f32 = 5.0;
+
+/// 終端是否可能支援滑鼠回報（TERM 未設置、dumb 或 Linux 主控台時視為不支援）
+pub fn terminal_supports_mouse() -> bool {
+    if cfg!(windows) {
+        return true;
+    }
+    match std::env::var("TERM") {
+        Ok(term) => !term.is_empty() && term != "dumb" && term != "linux",
+        Err(_) => false,
+    }
+}
 
 /// 用戶輸入事件
 #[derive(Debug, Clone)]
@@ -28,20 +55,90 @@ pub enum UserInput {
     MoveAttack(Vec2<f32>),
     /// Ctrl+左鍵點擊強制攻擊 (世界座標，包括友軍)
     ForceAttack(Vec2<f32>),
-    /// 施放技能 (技能ID, 世界座標)
-    CastAbility(String, Vec2<f32>),
+    /// 施放技能 (技能ID, 目標)
+    CastAbility(String, CastTarget),
+    /// 選擇目標 (實體ID，None 為取消選擇)
+    Select(Option<u32>),
     /// 使用道具 (道具ID, 世界座標)
     UseItem(String, Option<Vec2<f32>>),
+    /// 在當前時刻添加書籤
+    Bookmark,
+    /// 送出聊天訊息
+    Chat(String),
+    /// 執行功能鍵綁定的巨集 (按鍵名稱, 命令)
+    Macro(String, Vec<MacroCommand>),
     /// 取消當前操作
     Cancel,
     /// 繼續循環
     Continue,
 }
 
+/// 可重新配置的按鍵動作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyAction {
+    /// 技能欄第 N 個技能
+    Ability(usize),
+    Bookmark,
+    ToggleStats,
+    ToggleDeathRecap,
+    ToggleNetwork,
+    ToggleDebugLabels,
+    ToggleFog,
+}
+
+impl Keymap {
+    /// 查找字元對應的動作（不分大小寫）
+    fn action(&self, c: char) -> Option<KeyAction> {
+        let c = c.to_ascii_lowercase();
+        let matches = |key: char| key.to_ascii_lowercase() == c;
+        if let Some(index) = self.abilities.iter().position(|key| matches(*key)) {
+            Some(KeyAction::Ability(index))
+        } else if matches(self.bookmark) {
+            Some(KeyAction::Bookmark)
+        } else if matches(self.stats) {
+            Some(KeyAction::ToggleStats)
+        } else if matches(self.death_recap) {
+            Some(KeyAction::ToggleDeathRecap)
+        } else if matches(self.network) {
+            Some(KeyAction::ToggleNetwork)
+        } else if matches(self.debug_labels) {
+            Some(KeyAction::ToggleDebugLabels)
+        } else if matches(self.fog) {
+            Some(KeyAction::ToggleFog)
+        } else {
+            None
+        }
+    }
+}
+
 /// 輸入處理器
 pub struct InputHandler {
     /// 當前選擇的技能（技能模式）
     pub selected_ability: Option<String>,
+    /// 是否顯示統計浮層
+    pub show_stats: bool,
+    /// 是否顯示死亡回顧浮層
+    pub show_death_recap: bool,
+    /// 是否顯示網路統計浮層
+    pub show_network: bool,
+    /// 地圖疊加層（Alt+數字鍵依編號切換）
+    pub overlays: OverlayManager,
+    /// 聊天輸入框（開啟時按鍵都送入輸入框）
+    pub text_input: Option<TextInput>,
+    /// 按鍵配置
+    pub keymap: Keymap,
+    /// 功能鍵綁定的巨集
+    pub macros: KeyMacros,
+    /// 施法模式
+    pub cast_mode: CastMode,
+    /// 是否啟用滑鼠（false 時直接使用鍵盤目標模式）
+    pub mouse: bool,
+    /// 鍵盤目標模式：方向鍵移動準星，Enter 移動、A 攻擊
+    keyboard_targeting: bool,
+    /// 鍵盤目標模式的準星世界座標（None 時位於英雄位置）
+    target_cursor: Option<Vec2<f32>>,
+    /// 滑鼠最後所在的世界座標（快速施法的目標）
+    hover: Option<Vec2<f32>>,
     /// 退出標誌
     exit_requested: Arc<AtomicBool>,
     /// 輸入線程句柄
@@ -49,9 +146,8 @@ pub struct InputHandler {
     /// Linux: 從背景執行緒接收事件的通道（非阻塞讀取）
     #[cfg(not(windows))]
     event_rx: Option<Receiver<Event>>,
-    /// Linux: 停止背景執行緒的旗標
-    #[cfg(not(windows))]
-    stop_flag: Option<Arc<AtomicBool>>,
+    /// 停止輸入執行緒的訊號（drop 時也會送出並等待執行緒結束）
+    stop_signal: Option<ShutdownSignal>,
 }
 
 impl InputHandler {
@@ -62,10 +158,11 @@ impl InputHandler {
         // 僅在 Windows 上啟動後台鍵盤檢測線程（使用 WinAPI），
         // 以避免在 Linux 上和主循環同時讀取 crossterm 事件造成事件被搶讀。
         #[cfg(windows)]
-        let input_thread = {
+        let (stop_signal, input_thread) = {
             let exit_flag_clone = exit_flag.clone();
-            Some(thread::spawn(move || {
-                loop {
+            let (stop_signal, stop) = crate::shutdown::signal();
+            (stop_signal, Some(thread::spawn(move || {
+                while !stop.is_cancelled() {
                     thread::sleep(Duration::from_millis(50));
                     unsafe {
                         // 檢測 ESC 鍵
@@ -87,49 +184,42 @@ impl InputHandler {
                         }
                     }
                 }
-            }))
+            })))
         };
 
         #[cfg(not(windows))]
         {
             // Linux: 在 new() 就建立事件通道與背景執行緒
             let (tx, rx) = mpsc::channel::<Event>();
-            let stop_flag = Arc::new(AtomicBool::new(false));
-            let stop_flag_clone = stop_flag.clone();
-
-            let handle = thread::spawn(move || {
-                loop {
-                    // 每 50ms 檢查是否有事件，並允許響應停止旗標
-                    if stop_flag_clone.load(Ordering::Relaxed) {
-                        break;
-                    }
-                    match event::poll(Duration::from_millis(50)) {
-                        Ok(true) => {
-                            match event::read() {
-                                Ok(ev) => { let _ = tx.send(ev); }
-                                Err(_) => thread::sleep(Duration::from_millis(5)),
-                            }
-                        }
-                        Ok(false) => { /* no event; loop to check stop flag */ }
-                        Err(_) => thread::sleep(Duration::from_millis(5)),
-                    }
-                }
-            });
+            let (stop_signal, stop) = crate::shutdown::signal();
+            let handle = spawn_event_reader(tx, stop);
 
             return Self {
                 selected_ability: None,
+                show_stats: false,
+                show_death_recap: false,
+                show_network: false,
+                overlays: OverlayManager::default(),
+                text_input: None,
+                keymap: Keymap::default(),
+                macros: KeyMacros::default(),
+                cast_mode: CastMode::default(),
+                mouse: true,
+                keyboard_targeting: false,
+                target_cursor: None,
+                hover: None,
                 exit_requested: exit_flag,
                 input_thread: Some(handle),
                 event_rx: Some(rx),
-                stop_flag: Some(stop_flag),
+                stop_signal: Some(stop_signal),
             };
         }
 
         #[cfg(windows)]
-        return Self { selected_ability: None, exit_requested: exit_flag, input_thread };
+        return Self { selected_ability: None, show_stats: false, show_death_recap: false, show_network: false, overlays: OverlayManager::default(), text_input: None, keymap: Keymap::default(), macros: KeyMacros::default(), cast_mode: CastMode::default(), mouse: true, keyboard_targeting: false, target_cursor: None, hover: None, exit_requested: exit_flag, input_thread, stop_signal: Some(stop_signal) };
     }
 
-    /// 在 Linux 上啟動背景事件讀取執行緒（阻塞 read，主循環非阻塞 try_recv）
+    /// 在 Linux 上啟動背景事件讀取執行緒（主循環非阻塞 try_recv）
     #[cfg(not(windows))]
     pub fn start_event_thread(&mut self) {
         // 已啟動則略過
@@ -138,31 +228,10 @@ impl InputHandler {
         }
 
         let (tx, rx) = mpsc::channel::<Event>();
-        let stop_flag = Arc::new(AtomicBool::new(false));
-        let stop_flag_clone = stop_flag.clone();
-
-        // 背景執行緒：阻塞讀取事件並送入通道
-        let handle = thread::spawn(move || {
-            loop {
-                // 若要求停止，嘗試優雅退出（需要有事件或下一輪檢查）
-                if stop_flag_clone.load(Ordering::Relaxed) {
-                    break;
-                }
-                match event::read() {
-                    Ok(ev) => {
-                        let _ = tx.send(ev);
-                    }
-                    Err(_) => {
-                        // 避免忙迴圈
-                        thread::sleep(Duration::from_millis(5));
-                    }
-                }
-            }
-        });
-
+        let (stop_signal, stop) = crate::shutdown::signal();
         self.event_rx = Some(rx);
-        self.stop_flag = Some(stop_flag);
-        self.input_thread = Some(handle);
+        self.stop_signal = Some(stop_signal);
+        self.input_thread = Some(spawn_event_reader(tx, stop));
     }
 
     /// 嘗試非阻塞取得一個事件
@@ -179,18 +248,18 @@ impl InputHandler {
         }
     }
 
-    /// 嘗試停止事件讀取執行緒（注意：若 read 阻塞，可能延後生效）
-    #[cfg(not(windows))]
+    /// 停止輸入執行緒並等待它結束（執行緒每 50ms 檢查一次停止訊號）
     pub fn stop_event_thread(&mut self) {
-        if let Some(flag) = &self.stop_flag {
-            flag.store(true, Ordering::Relaxed);
+        if let Some(signal) = self.stop_signal.take() {
+            signal.trigger();
         }
         if let Some(handle) = self.input_thread.take() {
-            // 嘗試加入，避免長時間阻塞
             let _ = handle.join();
         }
-        self.event_rx = None;
-        self.stop_flag = None;
+        #[cfg(not(windows))]
+        {
+            self.event_rx = None;
+        }
     }
     
     /// 等待用戶按鍵
@@ -265,7 +334,53 @@ impl InputHandler {
     
     /// 處理鍵盤事件
     pub fn handle_key_event(&mut self, key_event: KeyEvent, game_state: &GameState) -> io::Result<UserInput> {
+        // 輸入框開啟時，所有按鍵都交給輸入框處理
+        if let Some(input) = self.text_input.as_mut() {
+            return Ok(match input.handle_key(key_event) {
+                TextInputEvent::Edited => UserInput::Continue,
+                TextInputEvent::Cancel => {
+                    self.text_input = None;
+                    UserInput::Cancel
+                }
+                TextInputEvent::Submit(text) => {
+                    self.text_input = None;
+                    if text.trim().is_empty() {
+                        UserInput::Cancel
+                    } else {
+                        UserInput::Chat(text)
+                    }
+                }
+            });
+        }
+        
+        // 功能鍵綁定的巨集
+        if let KeyCode::F(key) = key_event.code {
+            if let Some(commands) = self.macros.get(key) {
+                return Ok(UserInput::Macro(format!("F{}", key), commands.to_vec()));
+            }
+        }
+        
+        // Alt+數字鍵切換對應編號的疊加層
+        if key_event.modifiers.contains(KeyModifiers::ALT) {
+            if let KeyCode::Char(c @ '1'..='9') = key_event.code {
+                self.toggle_overlay(&c.to_string());
+                return Ok(UserInput::Continue);
+            }
+        }
+        
+        // 可重新配置的按鍵優先
+        if let KeyCode::Char(c) = key_event.code {
+            if let Some(action) = self.keymap.action(c) {
+                return Ok(self.handle_key_action(action, game_state));
+            }
+        }
+        
         match key_event.code {
+            // 開啟聊天輸入框
+            KeyCode::Enter => {
+                self.text_input = Some(TextInput::new("聊天: "));
+                Ok(UserInput::Continue)
+            },
             KeyCode::Esc => {
                 self.handle_esc_key()
             },
@@ -280,30 +395,10 @@ impl InputHandler {
                     Ok(UserInput::Quit)
                 }
             },
-            // 技能快捷鍵 - W/E/R/T 對應當前英雄的技能
-            KeyCode::Char('w') | KeyCode::Char('W') => {
-                if let Some(ability) = self.get_hero_ability(game_state, 0) {
-                    self.selected_ability = Some(ability);
-                }
-                Ok(UserInput::Continue)
-            },
-            KeyCode::Char('e') | KeyCode::Char('E') => {
-                if let Some(ability) = self.get_hero_ability(game_state, 1) {
-                    self.selected_ability = Some(ability);
-                }
-                Ok(UserInput::Continue)
-            },
-            KeyCode::Char('r') | KeyCode::Char('R') => {
-                if let Some(ability) = self.get_hero_ability(game_state, 2) {
-                    self.selected_ability = Some(ability);
-                }
-                Ok(UserInput::Continue)
-            },
-            KeyCode::Char('t') | KeyCode::Char('T') => {
-                if let Some(ability) = self.get_hero_ability(game_state, 3) {
-                    self.selected_ability = Some(ability);
-                }
-                Ok(UserInput::Continue)
+            // Tab / Shift+Tab 依距離輪流選擇視野內的單位
+            KeyCode::Tab | KeyCode::BackTab => {
+                let reverse = key_event.code == KeyCode::BackTab || key_event.modifiers.contains(KeyModifiers::SHIFT);
+                Ok(selection::cycle(game_state, reverse).map_or(UserInput::Continue, |id| UserInput::Select(Some(id))))
             },
             // 道具快捷鍵 - 數字鍵 1-9
             KeyCode::Char(c) if c.is_ascii_digit() && c != '0' => {
@@ -318,6 +413,145 @@ impl InputHandler {
         }
     }
     
+    /// 套用按鍵配置與施法模式
+    pub fn configure(&mut self, config: &InputConfig) {
+        self.keymap = config.keymap.clone();
+        // 配置驗證已列出格式錯誤，這裡只略過無法解析的巨集
+        self.macros = KeyMacros::parse(&config.macros).unwrap_or_else(|e| {
+            TerminalLogger::global().log("WARN", format!("巨集按鍵配置有誤，已停用: {:#}", e));
+            KeyMacros::default()
+        });
+        self.cast_mode = config.cast_mode;
+        self.mouse = config.mouse;
+    }
+    
+    /// 切換為鍵盤目標模式（終端不支援滑鼠或指定 --no-mouse 時）
+    pub fn enable_keyboard_targeting(&mut self) {
+        self.keyboard_targeting = true;
+    }
+    
+    /// 鍵盤目標模式的準星位置（未啟用時為 None）
+    pub fn target_cursor(&self, game_state: &GameState) -> Option<Vec2<f32>> {
+        self.keyboard_targeting
+            .then(|| self.target_cursor.unwrap_or(game_state.local_player.position))
+    }
+    
+    /// 處理鍵盤目標模式的按鍵（方向鍵移動準星、Enter 移動或施放、A 攻擊、/ 聊天、空白鍵準星回到英雄），
+    /// 不是目標模式按鍵時返回 None
+    pub fn handle_target_key(
+        &mut self,
+        key_event: KeyEvent,
+        game_state: &GameState,
+        viewport: &ViewportManager,
+    ) -> Option<UserInput> {
+        if !self.keyboard_targeting || self.text_input.is_some() {
+            return None;
+        }
+        let cursor = self.target_cursor.unwrap_or(game_state.local_player.position);
+        let direction = match key_event.code {
+            KeyCode::Up => Some(Vec2::new(0.0, -1.0)),
+            KeyCode::Down => Some(Vec2::new(0.0, 1.0)),
+            KeyCode::Left => Some(Vec2::new(-1.0, 0.0)),
+            KeyCode::Right => Some(Vec2::new(1.0, 0.0)),
+            _ => None,
+        };
+        if let Some(direction) = direction {
+            // 方向以螢幕為準，再轉回後端座標
+            let step = if key_event.modifiers.contains(KeyModifiers::SHIFT) {
+                CURSOR_STEP * CURSOR_FAST_MULTIPLIER
+            } else {
+                CURSOR_STEP
+            };
+            let local = viewport.transform.to_local(cursor) + direction * step;
+            let mut target = viewport.transform.to_backend(local);
+            if let Some(bounds) = game_state.world_bounds {
+                target = bounds.clamp(target);
+            }
+            self.target_cursor = Some(target);
+            self.hover = Some(target);
+            return Some(UserInput::Continue);
+        }
+        match key_event.code {
+            KeyCode::Enter => Some(match self.selected_ability {
+                Some(_) => self.cast_selected(game_state, cursor),
+                None => reject_blocked_move(game_state, cursor, UserInput::Move(cursor)),
+            }),
+            KeyCode::Char('a' | 'A') => Some(UserInput::Attack(cursor)),
+            KeyCode::Char('/') => {
+                self.text_input = Some(TextInput::new("聊天: "));
+                Some(UserInput::Continue)
+            }
+            KeyCode::Char(' ') => {
+                self.target_cursor = None;
+                self.hover = None;
+                Some(UserInput::Continue)
+            }
+            _ => None,
+        }
+    }
+    
+    /// 執行按鍵配置中的動作
+    fn handle_key_action(&mut self, action: KeyAction, game_state: &GameState) -> UserInput {
+        match action {
+            // 技能快捷鍵 - 對應當前英雄的技能欄
+            KeyAction::Ability(index) => {
+                let Some(ability) = self.get_hero_ability(game_state, index) else {
+                    return UserInput::Continue;
+                };
+                // 指定單位的技能在已選擇目標時直接施放在目標上
+                if ability.cast_type == CastType::Unit {
+                    if let Some(entity) = selection::selected_entity(game_state) {
+                        self.selected_ability = None;
+                        return UserInput::CastAbility(ability.id.clone(), CastTarget::Unit { id: entity.id, position: entity.position });
+                    }
+                }
+                self.selected_ability = Some(ability.id.clone());
+                // 自身技能不需要瞄準，快速施法則直接以游標位置施放
+                if ability.cast_type == CastType::SelfCast || self.cast_mode == CastMode::Quick {
+                    let cursor = self.hover.unwrap_or(game_state.local_player.position);
+                    return self.cast_selected(game_state, cursor);
+                }
+                UserInput::Continue
+            },
+            KeyAction::Bookmark => UserInput::Bookmark,
+            KeyAction::ToggleStats => {
+                self.show_stats = !self.show_stats;
+                UserInput::Continue
+            },
+            KeyAction::ToggleDeathRecap => {
+                self.show_death_recap = !self.show_death_recap;
+                UserInput::Continue
+            },
+            KeyAction::ToggleNetwork => {
+                self.show_network = !self.show_network;
+                UserInput::Continue
+            },
+            KeyAction::ToggleDebugLabels => {
+                self.toggle_overlay("labels");
+                UserInput::Continue
+            },
+            KeyAction::ToggleFog => {
+                self.toggle_overlay("fog");
+                UserInput::Continue
+            },
+        }
+    }
+    
+    /// 切換疊加層並在日誌顯示切換後的狀態
+    fn toggle_overlay(&mut self, key: &str) {
+        if let Some((name, enabled)) = self.overlays.toggle(key) {
+            TerminalLogger::global().log("INFO", format!("疊加層 {}: {}", name, if enabled { "開啟" } else { "關閉" }));
+        }
+    }
+    
+    /// 處理貼上事件（輸入法或剪貼簿一次送出的文字）
+    pub fn handle_paste(&mut self, text: &str) -> UserInput {
+        if let Some(input) = self.text_input.as_mut() {
+            input.insert_str(text);
+        }
+        UserInput::Continue
+    }
+    
     /// 處理滑鼠事件
     pub fn handle_mouse_event(
         &mut self,
@@ -327,34 +561,50 @@ impl InputHandler {
         terminal_width: u16,
         terminal_height: u16,
     ) -> io::Result<UserInput> {
-        // 計算世界座標
-        let world_pos = viewport.screen_to_world(
+        // 計算世界座標（畫面以鏡頭視野中心為中心）
+        let mut world_pos = viewport.screen_to_world(
             mouse_event.column,
             mouse_event.row,
-            game_state.local_player.position,
+            game_state.viewport.center,
             terminal_width as usize,
             terminal_height as usize,
         );
         
+        // 點擊地圖邊界外時限制在邊界內
+        if let (MouseEventKind::Down(_), Some(bounds)) = (mouse_event.kind, game_state.world_bounds) {
+            if !bounds.contains(world_pos) {
+                let clamped = bounds.clamp(world_pos);
+                TerminalLogger::global().log("WARN", format!(
+                    "⚠️ 目標 ({:.1}, {:.1}) 超出地圖邊界，已限制為 ({:.1}, {:.1})",
+                    world_pos.x, world_pos.y, clamped.x, clamped.y));
+                world_pos = clamped;
+            }
+        }
+        
         match mouse_event.kind {
+            MouseEventKind::Moved | MouseEventKind::Drag(_) => {
+                self.hover = Some(world_pos);
+                Ok(UserInput::Continue)
+            },
             MouseEventKind::Down(MouseButton::Left) => {
-                // 如果有選擇的技能，施放技能
-                if let Some(ability_id) = &self.selected_ability {
-                    let result = UserInput::CastAbility(ability_id.clone(), world_pos);
-                    self.selected_ability = None; // 清除技能選擇
-                    return Ok(result);
+                // 如果有選擇的技能，依施放方式施放（沒有有效目標時保留選擇）
+                if self.selected_ability.is_some() {
+                    return Ok(self.cast_selected(game_state, world_pos));
                 }
                 
                 // 檢查修飾鍵
                 if mouse_event.modifiers.contains(KeyModifiers::SHIFT) {
                     // Shift+左鍵 = 移動攻擊
-                    Ok(UserInput::MoveAttack(world_pos))
+                    Ok(reject_blocked_move(game_state, world_pos, UserInput::MoveAttack(world_pos)))
                 } else if mouse_event.modifiers.contains(KeyModifiers::CONTROL) {
                     // Ctrl+左鍵 = 強制攻擊
                     Ok(UserInput::ForceAttack(world_pos))
+                } else if let Some(entity) = selection::pick(game_state, world_pos, selection::PICK_RADIUS) {
+                    // 點擊單位 = 選擇目標
+                    Ok(UserInput::Select(Some(entity.id)))
                 } else {
                     // 普通左鍵 = 移動
-                    Ok(UserInput::Move(world_pos))
+                    Ok(reject_blocked_move(game_state, world_pos, UserInput::Move(world_pos)))
                 }
             },
             MouseEventKind::Down(MouseButton::Right) => {
@@ -407,28 +657,101 @@ impl InputHandler {
         self.exit_requested.load(Ordering::Relaxed)
     }
     
-    /// 根據英雄類型和索引獲取技能ID
-    fn get_hero_ability(&self, game_state: &GameState, index: usize) -> Option<String> {
-        let abilities = match game_state.local_player.hero_type.as_str() {
-            "saika_magoichi" => vec![
-                "sniper_mode",
-                "saika_reinforcements", 
-                "rain_iron_cannon",
-                "three_stage_technique"
-            ],
-            "date_masamune" => vec![
-                "flame_blade",
-                "fire_dash",
-                "flame_assault", 
-                "matchlock_gun"
-            ],
-            _ => return None,
+    /// 以游標位置施放選擇的技能：依施放方式決定目標，成功時清除選擇；單位目標附近沒有單位時保留選擇並提示
+    fn cast_selected(&mut self, game_state: &GameState, cursor: Vec2<f32>) -> UserInput {
+        let Some(ability) = self.selected_ability.as_deref().and_then(|id| crate::hero_catalog::registry().ability(id)) else {
+            self.selected_ability = None;
+            return UserInput::Continue;
         };
-        
-        if index < abilities.len() {
-            Some(abilities[index].to_string())
-        } else {
-            None
+        match CastTarget::resolve(ability.cast_type, game_state, cursor) {
+            Some(target) => {
+                self.selected_ability = None;
+                UserInput::CastAbility(ability.id.clone(), target)
+            }
+            None => {
+                TerminalLogger::global().log("WARN", format!("⚠️ {}：{}", ability.name, TargetingPreview::new(ability, game_state, cursor).status()));
+                UserInput::Continue
+            }
         }
     }
-}
\ No newline at end of file
+    
+    /// 選擇技能後依游標位置（鍵盤準星優先）的瞄準預覽
+    pub fn targeting_preview(&self, game_state: &GameState) -> Option<TargetingPreview> {
+        let ability = crate::hero_catalog::registry().ability(self.selected_ability.as_deref()?)?;
+        let cursor = self.target_cursor.or(self.hover).unwrap_or(game_state.local_player.position);
+        Some(TargetingPreview::new(ability, game_state, cursor))
+    }
+    
+    /// 技能欄第 `index` 個按鍵對應的技能（英雄目錄中的快捷鍵優先，否則依技能順序）
+    fn get_hero_ability(&self, game_state: &GameState, index: usize) -> Option<&'static AbilityDefinition> {
+        let key = *self.keymap.abilities.get(index)?;
+        crate::hero_catalog::find_hero(&game_state.local_player.hero_type)?.ability_for_key(key, index)
+    }
+}
+
+impl Drop for InputHandler {
+    fn drop(&mut self) {
+        self.stop_event_thread();
+    }
+}
+
+/// 移動目標是阻擋移動的地形時不送出，在日誌中說明原因
+fn reject_blocked_move(game_state: &GameState, target: Vec2<f32>, input: UserInput) -> UserInput {
+    match game_state.terrain.blocking_at(target) {
+        Some(cell) => {
+            TerminalLogger::global().log("WARN", format!(
+                "⚠️ 目標 ({:.1}, {:.1}) 是{}，無法移動", target.x, target.y, cell.kind.label()));
+            UserInput::Continue
+        }
+        None => input,
+    }
+}
+
+/// Linux: 背景事件讀取執行緒（每 50ms 檢查一次停止訊號，不會阻塞在 read 上而無法結束）
+#[cfg(not(windows))]
+fn spawn_event_reader(tx: mpsc::Sender<Event>, stop: ShutdownToken) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        while !stop.is_cancelled() {
+            match event::poll(Duration::from_millis(50)) {
+                Ok(true) => match event::read() {
+                    Ok(ev) => {
+                        if tx.send(ev).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => thread::sleep(Duration::from_millis(5)),
+                },
+                Ok(false) => {}
+                Err(_) => thread::sleep(Duration::from_millis(5)),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::WorldBounds;
+
+    #[test]
+    fn clicks_outside_the_map_are_clamped_to_the_boundary() {
+        let mut handler = InputHandler::new();
+        // 10x5 格、每格 10 單位，鏡頭在 (20, 25)：左邊三欄在 x < 0
+        let viewport = ViewportManager::new(100.0, 50.0);
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        state.viewport.center = Vec2::new(20.0, 25.0);
+        state.world_bounds = Some(WorldBounds { min_x: 0.0, min_y: 0.0, max_x: 1000.0, max_y: 1000.0 });
+        let click = |column: u16, kind: MouseEventKind| MouseEvent { kind, column, row: 2, modifiers: KeyModifiers::NONE };
+
+        let input = handler.handle_mouse_event(click(0, MouseEventKind::Down(MouseButton::Left)), &state, &viewport, 10, 5).unwrap();
+        assert!(matches!(input, UserInput::Move(target) if target.x == 0.0), "{:?}", input);
+        let input = handler.handle_mouse_event(click(1, MouseEventKind::Down(MouseButton::Right)), &state, &viewport, 10, 5).unwrap();
+        assert!(matches!(input, UserInput::Attack(target) if target.x == 0.0), "{:?}", input);
+        let input = handler.handle_mouse_event(click(6, MouseEventKind::Down(MouseButton::Left)), &state, &viewport, 10, 5).unwrap();
+        assert!(matches!(input, UserInput::Move(target) if target.x > 0.0), "邊界內不受影響: {:?}", input);
+
+        // 游標移動只更新懸停位置，不限制
+        handler.handle_mouse_event(click(0, MouseEventKind::Moved), &state, &viewport, 10, 5).unwrap();
+        assert!(handler.hover.is_some_and(|hover| hover.x < 0.0));
+    }
+}
@@ -0,0 +1,183 @@
+/// 實體位置內插與外推
+///
+/// 畫面狀態約每 3 秒才更新一次，視圖卻以 60fps 渲染，直接畫同步到的位置時單位會一格一格地跳。
+/// 這裡為每個實體與其他玩家保留最近兩次快照：收到新位置時，從畫面上目前的位置開始，
+/// 在兩次快照的間隔內線性移動到新位置；帶有速度的投射物則以最新位置加上「速度 × 經過時間」外推。
+/// 只影響畫面：平滑後的位置以覆寫表交給渲染器，不複製遊戲狀態，送出的操作與各種統計仍使用同步到的實際位置
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use vek::Vec2;
+
+use crate::game_state::{Entity, EntityType, GameState};
+use crate::mqtt_handler::PlayerState;
+
+/// 還不知道快照間隔時的預設內插時間（畫面狀態每 3 秒請求一次）
+const DEFAULT_SPAN: Duration = Duration::from_secs(3);
+
+/// 內插時間的下限與上限（單位久未移動後再移動時不拖太久）
+const MIN_SPAN: Duration = Duration::from_millis(50);
+const MAX_SPAN: Duration = Duration::from_secs(4);
+
+/// 投射物外推的時間上限
+const MAX_EXTRAPOLATION: Duration = Duration::from_secs(3);
+
+/// 追蹤的單位
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Subject {
+    Entity(u32),
+    Player(String),
+}
+
+/// 單一單位最近兩次快照
+#[derive(Debug, Clone, Copy)]
+struct Track {
+    /// 最新快照的位置與收到時間
+    latest: Vec2<f32>,
+    latest_at: Instant,
+    /// 收到最新快照時畫面上的位置（內插起點）
+    from: Vec2<f32>,
+    /// 前一次到最新一次快照的間隔
+    span: Duration,
+}
+
+impl Track {
+    fn new(position: Vec2<f32>, now: Instant) -> Self {
+        Self { latest: position, latest_at: now, from: position, span: DEFAULT_SPAN }
+    }
+
+    /// 畫面上的位置：有速度時外推，否則在快照間內插
+    fn shown(&self, velocity: Option<Vec2<f32>>, now: Instant) -> Vec2<f32> {
+        let elapsed = now.saturating_duration_since(self.latest_at);
+        match velocity {
+            Some(velocity) => self.latest + velocity * elapsed.min(MAX_EXTRAPOLATION).as_secs_f32(),
+            None => Vec2::lerp(self.from, self.latest, elapsed.as_secs_f32() / self.span.as_secs_f32()),
+        }
+    }
+
+    /// 收到新的快照位置
+    fn observe(&mut self, position: Vec2<f32>, velocity: Option<Vec2<f32>>, now: Instant) {
+        if position == self.latest {
+            return;
+        }
+        self.from = self.shown(velocity, now);
+        self.span = now.saturating_duration_since(self.latest_at).clamp(MIN_SPAN, MAX_SPAN);
+        self.latest = position;
+        self.latest_at = now;
+    }
+}
+
+/// 渲染時取代同步位置的畫面位置（沒有覆寫的單位畫在同步到的位置）
+#[derive(Debug, Clone, Default)]
+pub struct PositionOverrides(HashMap<Subject, Vec2<f32>>);
+
+impl PositionOverrides {
+    /// 實體在畫面上的位置
+    pub fn entity(&self, entity: &Entity) -> Vec2<f32> {
+        self.0.get(&Subject::Entity(entity.id)).copied().unwrap_or(entity.position)
+    }
+
+    /// 其他玩家在畫面上的位置
+    pub fn player(&self, player: &PlayerState) -> Vec2<f32> {
+        self.0.get(&Subject::Player(player.name.clone())).copied()
+            .unwrap_or(Vec2::new(player.position.0, player.position.1))
+    }
+}
+
+/// 實體位置平滑器
+#[derive(Debug, Default)]
+pub struct Interpolator {
+    tracks: HashMap<Subject, Track>,
+}
+
+impl Interpolator {
+    /// 以同步到的狀態更新快照，返回實體與其他玩家平滑後的畫面位置（本地英雄不在其中）
+    pub fn smooth(&mut self, state: &GameState, now: Instant) -> PositionOverrides {
+        let mut tracks = HashMap::with_capacity(state.entities.len() + state.other_players.len());
+        let mut shown = HashMap::with_capacity(tracks.capacity());
+        for (id, entity) in &state.entities {
+            let velocity = match entity.entity_type {
+                EntityType::Projectile => state.projectile_velocities.get(id).copied(),
+                _ => None,
+            };
+            let subject = Subject::Entity(*id);
+            shown.insert(subject.clone(), self.track(&mut tracks, subject, entity.position, velocity, now));
+        }
+        for player in state.other_players.values() {
+            let subject = Subject::Player(player.name.clone());
+            let position = Vec2::new(player.position.0, player.position.1);
+            shown.insert(subject.clone(), self.track(&mut tracks, subject, position, None, now));
+        }
+        // 消失的單位不再追蹤
+        self.tracks = tracks;
+        PositionOverrides(shown)
+    }
+
+    fn track(&self, tracks: &mut HashMap<Subject, Track>, subject: Subject, position: Vec2<f32>, velocity: Option<Vec2<f32>>, now: Instant) -> Vec2<f32> {
+        let mut track = self.tracks.get(&subject).copied().unwrap_or_else(|| Track::new(position, now));
+        track.observe(position, velocity, now);
+        tracks.insert(subject, track);
+        track.shown(velocity, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolates_between_snapshots_and_extrapolates_projectiles() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        let entity = |id: u32, entity_type: EntityType, x: f32| Entity {
+            id, entity_type, position: Vec2::new(x, 0.0), health: (100.0, 100.0), owner: None, debug_label: None,
+        };
+        state.entities.insert(1, entity(1, EntityType::Creep, 0.0));
+        state.entities.insert(2, entity(2, EntityType::Projectile, 0.0));
+        state.projectile_velocities.insert(2, Vec2::new(10.0, 0.0));
+        let mut interpolator = Interpolator::default();
+        let x = |smoothed: &PositionOverrides, id: u32| smoothed.0[&Subject::Entity(id)].x;
+
+        let smoothed = interpolator.smooth(&state, at(0));
+        assert_eq!((x(&smoothed, 1), x(&smoothed, 2)), (0.0, 0.0));
+        let smoothed = interpolator.smooth(&state, at(1000));
+        assert_eq!((x(&smoothed, 1), x(&smoothed, 2)), (0.0, 10.0), "投射物以速度外推");
+
+        // 小兵 2 秒後出現在 100：在 2 秒內從 0 移過去
+        state.entities.insert(1, entity(1, EntityType::Creep, 100.0));
+        assert_eq!(x(&interpolator.smooth(&state, at(2000)), 1), 0.0);
+        assert_eq!(x(&interpolator.smooth(&state, at(3000)), 1), 50.0);
+        assert_eq!(x(&interpolator.smooth(&state, at(5000)), 1), 100.0);
+        assert_eq!(x(&interpolator.smooth(&state, at(6000)), 1), 100.0, "到達後停在最新位置");
+
+        // 內插途中再收到新位置時從畫面上的位置接續，不會跳動
+        state.entities.insert(1, entity(1, EntityType::Creep, 0.0));
+        interpolator.smooth(&state, at(7000));
+        state.entities.insert(1, entity(1, EntityType::Creep, 200.0));
+        assert_eq!(x(&interpolator.smooth(&state, at(7500)), 1), 100.0 - 100.0 * 0.5 / 4.0);
+
+        let projectile = state.entities.remove(&2).unwrap();
+        let smoothed = interpolator.smooth(&state, at(8000));
+        assert!(!interpolator.tracks.contains_key(&Subject::Entity(2)));
+        assert_eq!(smoothed.entity(&state.entities[&1]).x, 200.0);
+        assert_eq!(smoothed.entity(&projectile), projectile.position, "沒有覆寫的單位畫在同步到的位置");
+    }
+
+    #[test]
+    fn overrides_other_player_positions() {
+        let start = Instant::now();
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        let player = PlayerState {
+            name: "p2".to_string(), hero_type: "saika_magoichi".to_string(), position: (0.0, 0.0), health: (100.0, 100.0), abilities: Vec::new(), summons: Vec::new(),
+        };
+        state.other_players.insert("p2".to_string(), player.clone());
+        let mut interpolator = Interpolator::default();
+        interpolator.smooth(&state, start);
+
+        state.other_players.get_mut("p2").unwrap().position = (0.0, 80.0);
+        let smoothed = interpolator.smooth(&state, start + Duration::from_secs(1));
+        assert_eq!(smoothed.player(&player), Vec2::new(0.0, 0.0), "收到新位置的瞬間仍畫在原處");
+        let smoothed = interpolator.smooth(&state, start + Duration::from_secs(2));
+        assert_eq!(smoothed.player(&state.other_players["p2"]), Vec2::new(0.0, 80.0));
+    }
+}
@@ -2,23 +2,42 @@
 /// 
 /// 使用 crossterm 提供跨平台終端控制和豐富的視覺效果
 
+pub mod cast_target;
 pub mod display;
 pub mod input;
+pub mod interpolation;
+pub mod overlays;
 pub mod renderer;
+pub mod structures;
+pub mod text_input;
+pub mod text_width;
 pub mod viewport;
 
 use std::io::{self, Write};
-use crossterm::terminal;
+use std::time::SystemTime;
+use crossterm::{style::Color, terminal};
+use tokio::sync::broadcast;
 use vek::Vec2;
+use crate::anomaly_capture::AnomalyEvidence;
+use crate::client_handle::ClientSnapshot;
+use crate::game_client::{ClientState, GameClient};
+use crate::heartbeat::HeartbeatStats;
 use crate::game_state::GameState;
+use crate::mqtt_stats::MqttStats;
+use crate::protocol_errors::{ProtocolError, ProtocolErrorFeed};
 use log::debug;
 use crate::terminal_logger::TerminalLogger;
+use overlays::OverlayManager;
+use interpolation::{Interpolator, PositionOverrides};
 
 pub use display::MapDisplay;
 pub use input::{UserInput, InputHandler};
-pub use renderer::MapRenderer;
+pub use renderer::{MapRenderer, RenderOverlay};
 pub use viewport::ViewportManager;
 
+/// 網路統計浮層最多列出的主題數
+const NETWORK_OVERLAY_TOPICS: usize = 8;
+
 /// 終端視圖主控制器
 pub struct TerminalView {
     /// 視口管理器
@@ -27,12 +46,30 @@ pub struct TerminalView {
     pub renderer: MapRenderer,
     /// 輸入處理器
     pub input_handler: InputHandler,
-    /// 是否顯示視野範圍
-    pub show_vision: bool,
     /// 終端寬度（字符數）
     pub terminal_width: u16,
     /// 終端高度（字符數）
     pub terminal_height: u16,
+    /// 統計浮層內容（按 M 切換顯示）
+    pub stats_overlay: Vec<String>,
+    /// 死亡回顧浮層內容（陣亡時自動顯示，按 D 切換）
+    pub death_recap_overlay: Vec<String>,
+    /// 網路統計浮層內容（按 N 切換顯示）
+    pub network_overlay: Vec<String>,
+    /// 連線狀態提示與顏色（重連中或伺服器資料停滯時顯示）
+    pub link_banner: Option<(String, Color)>,
+    /// 前後端協議版本不符的警告（沒有連線提示時顯示）
+    pub version_banner: Option<String>,
+    /// 頂部延遲資訊（依 p95 延遲著色）
+    pub latency_header: Option<(String, Color)>,
+    /// 訂閱中的協議錯誤統計
+    protocol_errors: Option<ProtocolErrorFeed>,
+    /// 已顯示過的最近一次死亡時間
+    seen_death: Option<SystemTime>,
+    /// 快照間的實體位置平滑
+    interpolation: Interpolator,
+    /// 實時模式最近一幀平滑後的畫面位置
+    positions: PositionOverrides,
 }
 
 impl TerminalView {
@@ -41,58 +78,214 @@ impl TerminalView {
         let view_size = radius * 2.0;
         let (width, height) = terminal::size()?;
         
-        Ok(Self {
+        Ok(Self::with_vision(Self {
             viewport: ViewportManager::new(view_size, view_size),
             renderer: MapRenderer::new(),
             input_handler: InputHandler::new(),
-            show_vision,
             terminal_width: width,
             terminal_height: height.saturating_sub(3), // 留出日誌區域空間
-        })
+            stats_overlay: Vec::new(),
+            death_recap_overlay: Vec::new(),
+            network_overlay: Vec::new(),
+            link_banner: None,
+            version_banner: None,
+            latency_header: None,
+            protocol_errors: None,
+            seen_death: None,
+            interpolation: Interpolator::default(),
+            positions: PositionOverrides::default(),
+        }, show_vision))
     }
     
     /// 創建指定寬高的終端視圖
     pub fn new_rect(width: f32, height: f32, show_vision: bool) -> io::Result<Self> {
         let (term_width, term_height) = terminal::size()?;
         
-        Ok(Self {
+        Ok(Self::with_vision(Self {
             viewport: ViewportManager::new(width, height),
             renderer: MapRenderer::new(),
             input_handler: InputHandler::new(),
-            show_vision,
             terminal_width: term_width,
             terminal_height: term_height.saturating_sub(3),
-        })
+            stats_overlay: Vec::new(),
+            death_recap_overlay: Vec::new(),
+            network_overlay: Vec::new(),
+            link_banner: None,
+            version_banner: None,
+            latency_header: None,
+            protocol_errors: None,
+            seen_death: None,
+            interpolation: Interpolator::default(),
+            positions: PositionOverrides::default(),
+        }, show_vision))
+    }
+    
+    /// 依 --vision 開啟視野範圍疊加層
+    fn with_vision(mut view: Self, show_vision: bool) -> Self {
+        view.input_handler.overlays.set("ranges", show_vision);
+        view
     }
     
     /// 初始化終端
     pub fn init_terminal(&mut self) -> io::Result<()> {
         self.renderer.init_terminal()?;
         // Linux: 事件執行緒已在 InputHandler::new() 啟動，這裡不需重啟
+        let mouse = self.input_handler.mouse
+            && input::terminal_supports_mouse()
+            && self.renderer.enable_mouse_capture();
+        if !mouse {
+            self.input_handler.enable_keyboard_targeting();
+            TerminalLogger::global().log("INFO", "⌨️ 滑鼠不可用，使用鍵盤目標模式：方向鍵移動準星（Shift 加速）、Enter 移動/施放、A 攻擊、/ 聊天、空白鍵準星回到英雄".to_string());
+        }
         Ok(())
     }
     
     /// 清理終端
     pub fn cleanup_terminal(&mut self) -> io::Result<()> {
-        // Linux: 停止背景事件讀取執行緒
-        #[cfg(not(windows))]
-        {
-            self.input_handler.stop_event_thread();
-        }
+        // 停止背景事件讀取執行緒
+        self.input_handler.stop_event_thread();
         self.renderer.cleanup_terminal()
     }
     
     /// 渲染終端視圖
     pub fn render(&self, game_state: &GameState) -> io::Result<()> {
-        self.renderer.render(
+        let frame = self.compose_frame(game_state);
+        self.renderer.render_grid(&frame, self.terminal_width, self.terminal_height)
+    }
+    
+    /// 組合目前這一幀的畫面（地圖加上浮層、提示與輸入框）
+    pub fn compose_frame(&self, game_state: &GameState) -> Vec<Vec<MapDisplay>> {
+        let protocol_errors = self.protocol_errors.as_ref().and_then(ProtocolErrorFeed::status_line);
+        let targeting = self.input_handler.targeting_preview(game_state);
+        let target_panel = crate::selection::panel_lines(game_state);
+        let overlay = RenderOverlay {
+            stats: if self.input_handler.show_stats { &self.stats_overlay } else { &[] },
+            death_recap: if self.input_handler.show_death_recap { &self.death_recap_overlay } else { &[] },
+            target_panel: &target_panel,
+            network: if self.input_handler.show_network { &self.network_overlay } else { &[] },
+            banner: self.link_banner.as_ref().map(|(text, color)| (text.as_str(), *color))
+                .or(self.version_banner.as_deref().map(|text| (text, Color::Yellow))),
+            header: self.latency_header.as_ref().map(|(text, color)| (text.as_str(), *color)),
+            input_line: self.input_handler.text_input.as_ref()
+                .map(|input| input.visible(self.terminal_width as usize)),
+            target_cursor: self.input_handler.target_cursor(game_state),
+            targeting: targeting.as_ref(),
+            protocol_errors: protocol_errors.as_deref(),
+            layers: Some(&self.input_handler.overlays),
+            positions: Some(&self.positions),
+        };
+        self.renderer.compose_frame(
             game_state,
             &self.viewport,
-            self.show_vision,
             self.terminal_width,
-            self.terminal_height
+            self.terminal_height,
+            &overlay,
         )
     }
     
+    /// 更新統計浮層內容（浮層未顯示時不做任何事）
+    pub fn update_stats_overlay(&mut self, client: &GameClient) {
+        if self.input_handler.show_stats {
+            self.set_stats_overlay(client.metrics_overlay(20));
+        }
+    }
+    
+    /// 更新網路統計浮層內容（浮層未顯示時不做任何事）
+    pub fn update_network_overlay(&mut self, stats: &MqttStats) {
+        if self.input_handler.show_network {
+            self.network_overlay = stats.snapshot().overlay_lines(NETWORK_OVERLAY_TOPICS);
+        }
+    }
+    
+    /// 把異常證據連同目前畫面寫入異常資料夾
+    pub fn capture_anomaly(&self, evidence: &AnomalyEvidence, game_state: &GameState) {
+        let frame = renderer::grid_text(&self.compose_frame(game_state));
+        let logger = TerminalLogger::global();
+        match evidence.save(&frame) {
+            Ok(folder) => logger.log("WARN", format!("📸 {}，已擷取到 {}", evidence.reasons.join("、"), folder.display())),
+            Err(e) => logger.log("ERROR", format!("擷取異常失敗: {}", e)),
+        }
+    }
+    
+    /// 以客戶端快照更新統計浮層、連線提示與頂部資訊
+    pub fn update_from_snapshot(&mut self, snapshot: &ClientSnapshot) {
+        if self.input_handler.show_stats {
+            self.set_stats_overlay(snapshot.metrics_overlay.clone());
+        }
+        if self.input_handler.show_network {
+            self.network_overlay = snapshot.mqtt_stats.overlay_lines(NETWORK_OVERLAY_TOPICS);
+        }
+        self.update_link_status(&snapshot.state);
+        self.update_latency_header(snapshot.heartbeat);
+        self.update_version_warning(snapshot.protocol_mismatch.clone());
+    }
+    
+    fn set_stats_overlay(&mut self, lines: Vec<String>) {
+        self.stats_overlay = lines;
+        if self.stats_overlay.is_empty() {
+            self.stats_overlay.push("尚未定義指標 (metrics add <名稱> <運算式>)".to_string());
+        }
+    }
+    
+    /// 依客戶端狀態更新連線提示
+    pub fn update_link_status(&mut self, state: &ClientState) {
+        self.link_banner = match state {
+            ClientState::Reconnecting(attempt) => Some((format!(" ⚠ 連線中斷，重連中（第 {} 次） ", attempt), Color::Yellow)),
+            ClientState::Stale(age) | ClientState::ServerLost(age) => Some((crate::liveness::banner(*age), Color::Red)),
+            _ => None,
+        };
+    }
+    
+    /// 更新協議版本不符的警告
+    pub fn update_version_warning(&mut self, warning: Option<String>) {
+        self.version_banner = warning;
+    }
+    
+    /// 依心跳統計更新頂部延遲資訊
+    pub fn update_latency_header(&mut self, stats: Option<HeartbeatStats>) {
+        self.latency_header = stats.map(|stats| {
+            let color = match stats.p95.as_millis() {
+                0..=99 => Color::Green,
+                100..=249 => Color::Yellow,
+                _ => Color::Red,
+            };
+            (format!("💓 {}", stats.summary()), color)
+        });
+    }
+    
+    /// 訂閱客戶端的協議錯誤，之後在畫面底部顯示錯誤數與最近一次錯誤
+    pub fn watch_protocol_errors(&mut self, receiver: broadcast::Receiver<ProtocolError>) {
+        self.protocol_errors = Some(ProtocolErrorFeed::new(receiver));
+    }
+    
+    /// 更新死亡回顧浮層，英雄剛陣亡時自動顯示
+    pub fn update_death_recap(&mut self, game_state: &GameState) {
+        let Some(recap) = game_state.combat_log.last_recap() else {
+            return;
+        };
+        if self.seen_death == Some(recap.died_at) {
+            return;
+        }
+        self.seen_death = Some(recap.died_at);
+        self.death_recap_overlay = recap.lines();
+        if game_state.local_player.health.0 <= 0.0 {
+            self.input_handler.show_death_recap = true;
+        }
+    }
+    
+    /// 換上配置或互動模式的疊加層（建立視圖時以 --vision 開啟的視野範圍保持開啟）
+    pub fn install_overlays(&mut self, mut overlays: OverlayManager) {
+        if self.input_handler.overlays.is_enabled("ranges") {
+            overlays.set("ranges", true);
+        }
+        self.input_handler.overlays = overlays;
+    }
+    
+    /// 取回疊加層（視圖結束後交還給互動模式）
+    pub fn take_overlays(&mut self) -> OverlayManager {
+        std::mem::take(&mut self.input_handler.overlays)
+    }
+    
     /// 等待用戶按鍵
     pub fn wait_for_key(&self) -> io::Result<crossterm::event::KeyEvent> {
         self.input_handler.wait_for_key()
@@ -100,7 +293,14 @@ impl TerminalView {
     
     /// 實時模式循環
     pub fn render_live(&mut self, game_state: &GameState) -> io::Result<UserInput> {
-        // 渲染當前狀態
+        self.update_death_recap(game_state);
+        self.input_handler.overlays.observe(game_state);
+        if let Some(feed) = &mut self.protocol_errors {
+            feed.poll();
+        }
+        
+        // 渲染當前狀態（實體位置在快照間平滑移動）
+        self.positions = self.interpolation.smooth(game_state, std::time::Instant::now());
         self.render(game_state)?;
         
         // 在 view 模式下使用特殊的輸入處理
@@ -121,9 +321,15 @@ impl TerminalView {
             if let Some(ev) = self.input_handler.try_recv_event() {
                 match ev {
                     crossterm::event::Event::Key(key_event) => {
+                        if let Some(result) = self.input_handler.handle_target_key(key_event, game_state, &self.viewport) {
+                            return Ok(result);
+                        }
                         let result = self.input_handler.handle_key_event(key_event, game_state);
                         return result;
                     }
+                    crossterm::event::Event::Paste(text) => {
+                        return Ok(self.input_handler.handle_paste(&text));
+                    }
                     crossterm::event::Event::Mouse(mouse_event) => {
                         return self.input_handler.handle_mouse_event(
                             mouse_event,
@@ -0,0 +1,598 @@
+/// 可個別開關的地圖疊加層
+///
+/// 每種輔助顯示都是註冊在 OverlayManager 中的一層：迷霧（fog）、座標網格（grid）、熱圖（heatmap）、
+/// 移動軌跡（trails）、視野範圍（ranges）與除錯標籤（labels）。互動模式以 `overlays` 命令、終端視圖中以
+/// Alt+數字鍵（依註冊順序編號）個別開關，不再由單一的 show_vision 決定全部。
+/// 熱圖與軌跡只在開啟時累積，關閉時清除
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use crossterm::style::Color;
+use vek::Vec2;
+
+use super::interpolation::PositionOverrides;
+use super::renderer::draw_text;
+use super::text_width::truncate_to_width;
+use super::{MapDisplay, ViewportManager};
+use crate::config::OverlaysConfig;
+use crate::game_state::{EntityType, GameState};
+
+/// 內建疊加層的名稱（依註冊順序，即 Alt+數字鍵的編號）
+pub const LAYER_NAMES: [&str; 6] = ["fog", "grid", "heatmap", "trails", "ranges", "labels"];
+
+/// 除錯標籤的最大顯示寬度（超過時截斷，避免長標籤蓋住整列）
+const DEBUG_LABEL_MAX_WIDTH: usize = 16;
+
+/// 疊加層繪製時的畫面
+pub struct LayerFrame<'a> {
+    pub grid: &'a mut [Vec<MapDisplay>],
+    pub game_state: &'a GameState,
+    /// 實體與其他玩家在畫面上的位置
+    pub positions: &'a PositionOverrides,
+    pub viewport: &'a ViewportManager,
+    pub camera: Vec2<f32>,
+}
+
+impl LayerFrame<'_> {
+    fn size(&self) -> (usize, usize) {
+        (self.grid.first().map_or(0, Vec::len), self.grid.len())
+    }
+
+    fn to_screen(&self, position: Vec2<f32>) -> Option<(usize, usize)> {
+        let (width, height) = self.size();
+        self.viewport.world_to_screen(position, self.camera, width, height)
+    }
+
+    fn to_world(&self, x: usize, y: usize) -> Vec2<f32> {
+        let (width, height) = self.size();
+        self.viewport.screen_to_world(x as u16, y as u16, self.camera, width, height)
+    }
+
+    /// 只在空地上繪製（不蓋住邊界與其他疊加層）
+    fn paint_empty(&mut self, x: usize, y: usize, display: MapDisplay) {
+        if let Some(cell) = self.grid.get_mut(y).and_then(|row| row.get_mut(x)) {
+            if cell.symbol == MapDisplay::EMPTY.symbol {
+                *cell = display;
+            }
+        }
+    }
+
+    /// 所有空地格子
+    fn empty_cells(&self) -> Vec<(usize, usize)> {
+        let (width, height) = self.size();
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.grid[y][x].symbol == MapDisplay::EMPTY.symbol)
+            .collect()
+    }
+}
+
+/// 一個疊加層
+pub trait OverlayLayer: Send {
+    fn name(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+
+    /// 畫在實體之上（預設畫在實體之下，不蓋住單位）
+    fn above_entities(&self) -> bool {
+        false
+    }
+
+    /// 以每幀的狀態累積資料（只在開啟時呼叫）
+    fn observe(&mut self, _game_state: &GameState) {}
+
+    /// 關閉時清除累積的資料
+    fn reset(&mut self) {}
+
+    fn draw(&self, frame: &mut LayerFrame);
+}
+
+/// 戰爭迷霧：英雄與己方召喚物的視野半徑外顯示迷霧符號、隱藏其中的單位，
+/// 曾在視野內看過、現在離開視野的單位以暗灰色顯示在最後看到的位置，直到該位置重新進入視野
+struct FogLayer {
+    radius: f32,
+    /// 離開視野前最後看到的位置與符號
+    remembered: HashMap<TrailKey, (Vec2<f32>, MapDisplay)>,
+}
+
+impl FogLayer {
+    /// 視野來源：本地英雄與己方召喚物
+    fn vision_sources(game_state: &GameState, positions: &PositionOverrides) -> Vec<Vec2<f32>> {
+        let name = &game_state.local_player.name;
+        std::iter::once(game_state.local_player.position)
+            .chain(game_state.local_player.summons.iter().map(|summon| summon.position))
+            .chain(game_state.entities.values()
+                .filter(|entity| matches!(entity.entity_type, EntityType::Summon(_)) && entity.owner.as_ref() == Some(name))
+                .map(|entity| positions.entity(entity)))
+            .collect()
+    }
+
+    fn visible(&self, sources: &[Vec2<f32>], position: Vec2<f32>) -> bool {
+        sources.iter().any(|source| source.distance(position) <= self.radius)
+    }
+
+    /// 地圖上的其他單位（不含本地英雄）、符號與是否記住（投射物不記住）
+    fn units(game_state: &GameState, positions: &PositionOverrides) -> Vec<(TrailKey, Vec2<f32>, MapDisplay, bool)> {
+        game_state.entities.values()
+            .map(|entity| (
+                TrailKey::Entity(entity.id),
+                positions.entity(entity),
+                MapDisplay::for_entity(entity, &game_state.local_player.name),
+                entity.entity_type != EntityType::Projectile,
+            ))
+            .chain(game_state.other_players.values()
+                .map(|player| (TrailKey::Player(player.name.clone()), positions.player(player), MapDisplay::PLAYER_ENEMY, true)))
+            .collect()
+    }
+}
+
+impl OverlayLayer for FogLayer {
+    fn name(&self) -> &'static str {
+        "fog"
+    }
+
+    fn description(&self) -> &'static str {
+        "戰爭迷霧：視野外顯示迷霧並隱藏單位，記住離開視野的單位"
+    }
+
+    fn above_entities(&self) -> bool {
+        true
+    }
+
+    fn observe(&mut self, game_state: &GameState) {
+        let synced = PositionOverrides::default();
+        let sources = Self::vision_sources(game_state, &synced);
+        let mut seen = std::collections::HashSet::new();
+        for (key, position, display, remember) in Self::units(game_state, &synced) {
+            if remember && self.visible(&sources, position) {
+                self.remembered.insert(key.clone(), (position, display));
+                seen.insert(key);
+            }
+        }
+        // 記憶中的位置重新進入視野時，以目前看到的為準
+        self.remembered.retain(|key, (position, _)| seen.contains(key) || !sources.iter().any(|source| source.distance(*position) <= self.radius));
+    }
+
+    fn reset(&mut self) {
+        self.remembered.clear();
+    }
+
+    fn draw(&self, frame: &mut LayerFrame) {
+        let sources = Self::vision_sources(frame.game_state, frame.positions);
+        let (width, height) = frame.size();
+        // 逐格計算可見性：空地畫成迷霧，其他內容（地形、建築）轉暗
+        for y in 0..height {
+            for x in 0..width {
+                if self.visible(&sources, frame.to_world(x, y)) {
+                    continue;
+                }
+                let cell = &mut frame.grid[y][x];
+                if cell.symbol == MapDisplay::EMPTY.symbol {
+                    *cell = MapDisplay::FOG_OF_WAR;
+                } else {
+                    cell.color = Color::DarkGrey;
+                }
+            }
+        }
+        // 隱藏視野外單位目前的位置，改在最後看到的位置以暗灰色顯示
+        for (_, position, _, _) in Self::units(frame.game_state, frame.positions) {
+            if !self.visible(&sources, position) {
+                if let Some((x, y)) = frame.to_screen(position) {
+                    frame.grid[y][x] = MapDisplay::FOG_OF_WAR;
+                }
+            }
+        }
+        for (position, display) in self.remembered.values() {
+            if !self.visible(&sources, *position) {
+                if let Some((x, y)) = frame.to_screen(*position) {
+                    frame.grid[y][x] = MapDisplay { symbol: display.symbol, color: Color::DarkGrey };
+                }
+            }
+        }
+    }
+}
+
+/// 每隔固定世界單位的座標網格線
+struct GridLayer {
+    spacing: f32,
+}
+
+impl GridLayer {
+    fn line_index(&self, value: f32) -> i64 {
+        (value / self.spacing).floor() as i64
+    }
+}
+
+impl OverlayLayer for GridLayer {
+    fn name(&self) -> &'static str {
+        "grid"
+    }
+
+    fn description(&self) -> &'static str {
+        "世界座標網格線"
+    }
+
+    fn draw(&self, frame: &mut LayerFrame) {
+        let (width, height) = frame.size();
+        // 與左側（上方）格子落在不同網格區間的欄（列）畫成網格線
+        let columns: Vec<bool> = (0..width)
+            .map(|x| x > 0 && self.line_index(frame.to_world(x, 0).x) != self.line_index(frame.to_world(x - 1, 0).x))
+            .collect();
+        let rows: Vec<bool> = (0..height)
+            .map(|y| y > 0 && self.line_index(frame.to_world(0, y).y) != self.line_index(frame.to_world(0, y - 1).y))
+            .collect();
+        for (x, y) in frame.empty_cells() {
+            let symbol = match (columns[x], rows[y]) {
+                (true, true) => '┼',
+                (true, false) => '┊',
+                (false, true) => '┈',
+                (false, false) => continue,
+            };
+            frame.paint_empty(x, y, MapDisplay { symbol, color: Color::DarkGrey });
+        }
+    }
+}
+
+/// 單位出現頻率的熱圖
+struct HeatmapLayer {
+    cell: f32,
+    counts: HashMap<(i64, i64), u32>,
+}
+
+impl HeatmapLayer {
+    fn bucket(&self, position: Vec2<f32>) -> (i64, i64) {
+        ((position.x / self.cell).floor() as i64, (position.y / self.cell).floor() as i64)
+    }
+}
+
+impl OverlayLayer for HeatmapLayer {
+    fn name(&self) -> &'static str {
+        "heatmap"
+    }
+
+    fn description(&self) -> &'static str {
+        "單位出現頻率的熱圖（開啟後開始累積）"
+    }
+
+    fn observe(&mut self, game_state: &GameState) {
+        let positions = game_state.entities.values().map(|entity| entity.position)
+            .chain(game_state.other_players.values().map(|player| Vec2::new(player.position.0, player.position.1)))
+            .collect::<Vec<_>>();
+        for position in positions {
+            *self.counts.entry(self.bucket(position)).or_default() += 1;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.counts.clear();
+    }
+
+    fn draw(&self, frame: &mut LayerFrame) {
+        let Some(max) = self.counts.values().copied().max() else {
+            return;
+        };
+        for (x, y) in frame.empty_cells() {
+            let Some(count) = self.counts.get(&self.bucket(frame.to_world(x, y))) else {
+                continue;
+            };
+            let ratio = *count as f32 / max as f32;
+            let display = match ratio {
+                r if r > 2.0 / 3.0 => MapDisplay { symbol: '▓', color: Color::Red },
+                r if r > 1.0 / 3.0 => MapDisplay { symbol: '▒', color: Color::DarkYellow },
+                _ => MapDisplay { symbol: '░', color: Color::DarkBlue },
+            };
+            frame.paint_empty(x, y, display);
+        }
+    }
+}
+
+/// 軌跡與迷霧記憶追蹤的對象
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum TrailKey {
+    Entity(u32),
+    Player(String),
+}
+
+/// 單位最近走過的位置
+struct TrailsLayer {
+    length: usize,
+    trails: HashMap<TrailKey, VecDeque<Vec2<f32>>>,
+}
+
+impl OverlayLayer for TrailsLayer {
+    fn name(&self) -> &'static str {
+        "trails"
+    }
+
+    fn description(&self) -> &'static str {
+        "單位最近的移動軌跡"
+    }
+
+    fn observe(&mut self, game_state: &GameState) {
+        let current: HashMap<TrailKey, Vec2<f32>> = game_state.entities.values()
+            .map(|entity| (TrailKey::Entity(entity.id), entity.position))
+            .chain(game_state.other_players.values()
+                .map(|player| (TrailKey::Player(player.name.clone()), Vec2::new(player.position.0, player.position.1))))
+            .chain(std::iter::once((TrailKey::Player(game_state.local_player.name.clone()), game_state.local_player.position)))
+            .collect();
+        // 消失的單位不再保留軌跡
+        self.trails.retain(|key, _| current.contains_key(key));
+        for (key, position) in current {
+            let trail = self.trails.entry(key).or_default();
+            if trail.back() != Some(&position) {
+                trail.push_back(position);
+            }
+            while trail.len() > self.length + 1 {
+                trail.pop_front();
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.trails.clear();
+    }
+
+    fn draw(&self, frame: &mut LayerFrame) {
+        for trail in self.trails.values() {
+            // 最後一個點是目前位置，由實體本身顯示
+            for position in trail.iter().take(trail.len().saturating_sub(1)) {
+                if let Some((x, y)) = frame.to_screen(*position) {
+                    frame.paint_empty(x, y, MapDisplay { symbol: '∙', color: Color::DarkCyan });
+                }
+            }
+        }
+    }
+}
+
+/// 視野邊界框與距離標記
+struct RangesLayer;
+
+impl OverlayLayer for RangesLayer {
+    fn name(&self) -> &'static str {
+        "ranges"
+    }
+
+    fn description(&self) -> &'static str {
+        "視野邊界框與 1/4、3/4 距離標記"
+    }
+
+    fn above_entities(&self) -> bool {
+        true
+    }
+
+    fn draw(&self, frame: &mut LayerFrame) {
+        let (width, height) = frame.size();
+        if width < 2 || height < 2 {
+            return;
+        }
+        let (last_x, last_y) = (width - 1, height - 1);
+        let border = |symbol| MapDisplay { symbol, color: Color::Yellow };
+        for x in 0..width {
+            let (top, bottom) = match x {
+                0 => ('┌', '└'),
+                x if x == last_x => ('┐', '┘'),
+                _ => ('─', '─'),
+            };
+            frame.paint_empty(x, 0, border(top));
+            frame.paint_empty(x, last_y, border(bottom));
+        }
+        for y in 1..last_y {
+            frame.paint_empty(0, y, border('│'));
+            frame.paint_empty(last_x, y, border('│'));
+        }
+
+        // 有空間時在 1/4 與 3/4 位置加上距離標記
+        if width > 20 && height > 10 {
+            let marker = MapDisplay { symbol: '+', color: Color::DarkYellow };
+            let (center_x, center_y) = (width / 2, height / 2);
+            for (x, y) in [(center_x, height / 4), (center_x, height * 3 / 4), (width / 4, center_y), (width * 3 / 4, center_y)] {
+                frame.paint_empty(x, y, marker);
+            }
+        }
+    }
+}
+
+/// 實體右側的後端除錯標籤
+struct LabelsLayer;
+
+impl OverlayLayer for LabelsLayer {
+    fn name(&self) -> &'static str {
+        "labels"
+    }
+
+    fn description(&self) -> &'static str {
+        "實體右側的後端除錯標籤"
+    }
+
+    fn draw(&self, frame: &mut LayerFrame) {
+        for entity in frame.game_state.entities.values() {
+            let Some(label) = entity.debug_label.as_deref().filter(|label| !label.is_empty()) else {
+                continue;
+            };
+            if let Some((x, y)) = frame.to_screen(frame.positions.entity(entity)) {
+                draw_text(frame.grid, x + 1, y, truncate_to_width(label, DEBUG_LABEL_MAX_WIDTH), Color::Magenta);
+            }
+        }
+    }
+}
+
+/// 已註冊的疊加層
+struct Registered {
+    layer: Box<dyn OverlayLayer>,
+    enabled: bool,
+}
+
+/// 疊加層管理
+pub struct OverlayManager {
+    layers: Vec<Registered>,
+}
+
+impl OverlayManager {
+    /// 註冊內建疊加層，依配置開啟
+    pub fn new(config: &OverlaysConfig) -> Self {
+        let mut manager = Self { layers: Vec::new() };
+        let layers: [Box<dyn OverlayLayer>; 6] = [
+            Box::new(FogLayer { radius: config.fog_radius, remembered: HashMap::new() }),
+            Box::new(GridLayer { spacing: config.grid_spacing }),
+            Box::new(HeatmapLayer { cell: config.heatmap_cell, counts: HashMap::new() }),
+            Box::new(TrailsLayer { length: config.trail_length, trails: HashMap::new() }),
+            Box::new(RangesLayer),
+            Box::new(LabelsLayer),
+        ];
+        for layer in layers {
+            let enabled = config.enabled.iter().any(|name| name == layer.name());
+            manager.register(layer, enabled);
+        }
+        manager
+    }
+
+    /// 註冊疊加層（編號接在既有的之後）
+    pub fn register(&mut self, layer: Box<dyn OverlayLayer>, enabled: bool) {
+        self.layers.push(Registered { layer, enabled });
+    }
+
+    /// 以名稱或編號（從 1 起算）查找
+    fn find(&self, key: &str) -> Option<usize> {
+        match key.parse::<usize>() {
+            Ok(number) => (1..=self.layers.len()).contains(&number).then(|| number - 1),
+            Err(_) => self.layers.iter().position(|registered| registered.layer.name() == key),
+        }
+    }
+
+    /// 開啟或關閉，返回疊加層名稱（找不到時為 None）
+    pub fn set(&mut self, key: &str, enabled: bool) -> Option<&'static str> {
+        let index = self.find(key)?;
+        let registered = &mut self.layers[index];
+        if registered.enabled && !enabled {
+            registered.layer.reset();
+        }
+        registered.enabled = enabled;
+        Some(registered.layer.name())
+    }
+
+    /// 切換開關，返回疊加層名稱與切換後的狀態
+    pub fn toggle(&mut self, key: &str) -> Option<(&'static str, bool)> {
+        let enabled = !self.layers[self.find(key)?].enabled;
+        self.set(key, enabled).map(|name| (name, enabled))
+    }
+
+    pub fn is_enabled(&self, key: &str) -> bool {
+        self.find(key).is_some_and(|index| self.layers[index].enabled)
+    }
+
+    /// 所有疊加層：(編號, 名稱, 說明, 是否開啟)
+    pub fn entries(&self) -> Vec<(usize, &'static str, &'static str, bool)> {
+        self.layers.iter().enumerate()
+            .map(|(index, registered)| (index + 1, registered.layer.name(), registered.layer.description(), registered.enabled))
+            .collect()
+    }
+
+    /// 開啟中的疊加層以本幀狀態累積資料
+    pub fn observe(&mut self, game_state: &GameState) {
+        for registered in self.layers.iter_mut().filter(|registered| registered.enabled) {
+            registered.layer.observe(game_state);
+        }
+    }
+
+    /// 依註冊順序繪製開啟中、位於實體之下（或之上）的疊加層
+    pub fn draw(&self, frame: &mut LayerFrame, above_entities: bool) {
+        for registered in &self.layers {
+            if registered.enabled && registered.layer.above_entities() == above_entities {
+                registered.layer.draw(frame);
+            }
+        }
+    }
+}
+
+impl Default for OverlayManager {
+    fn default() -> Self {
+        Self::new(&OverlaysConfig::default())
+    }
+}
+
+impl fmt::Debug for OverlayManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let enabled: Vec<&str> = self.layers.iter()
+            .filter(|registered| registered.enabled)
+            .map(|registered| registered.layer.name())
+            .collect();
+        f.debug_struct("OverlayManager").field("enabled", &enabled).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::Entity;
+    use crate::terminal_view::renderer::grid_text;
+
+    #[test]
+    fn toggles_layers_and_draws_trails_under_entities() {
+        let mut overlays = OverlayManager::default();
+        assert_eq!(overlays.entries().iter().map(|entry| entry.1).collect::<Vec<_>>(), LAYER_NAMES);
+        assert_eq!(overlays.toggle("4"), Some(("trails", true)));
+        assert!(overlays.is_enabled("trails"));
+        assert_eq!(overlays.set("ranges", true), Some("ranges"));
+        assert_eq!(overlays.toggle("7"), None);
+        assert_eq!(overlays.set("smoke", true), None);
+
+        // 10x5 格、每格 10 單位，鏡頭在 (50, 25)
+        let viewport = ViewportManager::new(100.0, 50.0);
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        state.local_player.position = Vec2::new(-500.0, -500.0);
+        let creep = Entity {
+            id: 1, entity_type: EntityType::Creep, position: Vec2::new(25.0, 25.0),
+            health: (100.0, 100.0), owner: None, debug_label: None,
+        };
+        for x in [25.0, 35.0, 45.0] {
+            state.entities.insert(1, Entity { position: Vec2::new(x, 25.0), ..creep.clone() });
+            overlays.observe(&state);
+        }
+        let mut grid = vec![vec![MapDisplay::EMPTY; 10]; 5];
+        overlays.draw(&mut LayerFrame { grid: &mut grid, game_state: &state, positions: &PositionOverrides::default(), viewport: &viewport, camera: Vec2::new(50.0, 25.0) }, false);
+        assert_eq!(grid_text(&grid)[2], "..∙∙......", "軌跡畫在走過的空地上");
+        overlays.draw(&mut LayerFrame { grid: &mut grid, game_state: &state, positions: &PositionOverrides::default(), viewport: &viewport, camera: Vec2::new(50.0, 25.0) }, true);
+        assert_eq!(grid_text(&grid)[0], "┌────────┐");
+
+        overlays.toggle("trails");
+        overlays.set("trails", true);
+        let mut grid = vec![vec![MapDisplay::EMPTY; 10]; 5];
+        overlays.draw(&mut LayerFrame { grid: &mut grid, game_state: &state, positions: &PositionOverrides::default(), viewport: &viewport, camera: Vec2::new(50.0, 25.0) }, false);
+        assert!(!grid_text(&grid).concat().contains('∙'), "關閉時清除累積的軌跡");
+    }
+
+    #[test]
+    fn fog_hides_unseen_units_and_remembers_last_seen_position() {
+        let mut overlays = OverlayManager::new(&OverlaysConfig { enabled: vec!["fog".to_string()], fog_radius: 30.0, ..OverlaysConfig::default() });
+        let viewport = ViewportManager::new(100.0, 50.0);
+        let camera = Vec2::new(50.0, 25.0);
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        state.local_player.position = Vec2::new(35.0, 25.0);
+        let creep = |id: u32, x: f32| Entity {
+            id, entity_type: EntityType::Creep, position: Vec2::new(x, 25.0), health: (100.0, 100.0), owner: None, debug_label: None,
+        };
+        state.entities.insert(1, creep(1, 55.0));
+        state.entities.insert(2, creep(2, 95.0));
+        overlays.observe(&state);
+        // 英雄離開後小兵 1 在視野外移動，小兵 2 一直在視野外
+        state.local_player.position = Vec2::new(15.0, 25.0);
+        state.entities.insert(1, creep(1, 75.0));
+        overlays.observe(&state);
+
+        let render = |overlays: &OverlayManager, state: &GameState| {
+            let mut grid = vec![vec![MapDisplay::EMPTY; 10]; 5];
+            for entity in state.entities.values() {
+                let (x, y) = viewport.world_to_screen(entity.position, camera, 10, 5).unwrap();
+                grid[y][x] = MapDisplay::CREEP;
+            }
+            overlays.draw(&mut LayerFrame { grid: &mut grid, game_state: state, positions: &PositionOverrides::default(), viewport: &viewport, camera }, true);
+            grid
+        };
+        let grid = render(&overlays, &state);
+        assert_eq!(grid_text(&grid)[2], ".....c????", "視野外單位隱藏，離開視野的單位留在最後看到的位置");
+        assert!(matches!(grid[2][5].color, Color::DarkGrey));
+
+        // 最後看到的位置重新進入視野時以目前看到的為準
+        state.local_player.position = Vec2::new(65.0, 25.0);
+        overlays.observe(&state);
+        assert_eq!(grid_text(&render(&overlays, &state))[2], "????...c.c");
+    }
+}
@@ -1,5 +1,10 @@
+use super::cast_target::TargetingPreview;
+use super::interpolation::PositionOverrides;
+use super::overlays::{LayerFrame, OverlayManager};
+use super::text_width::{char_width, pad_to_width, str_width};
 use super::{MapDisplay, ViewportManager};
-use crate::game_state::{EntityType, GameState};
+use crate::game_state::GameState;
+use crate::progression::level_bar;
 use crossterm::{
     cursor, event, execute, queue,
     style::{Color, Print, ResetColor, SetForegroundColor},
@@ -9,6 +14,54 @@ use crossterm::{
 use std::io::{self, Write};
 use vek::Vec2;
 
+/// 網格中寬字元右半部的佔位（輸出時略過）
+pub const WIDE_CONTINUATION: char = '\0';
+
+/// 疊加在地圖上的介面元素
+#[derive(Debug, Default)]
+pub struct RenderOverlay<'a> {
+    /// 統計浮層文字行
+    pub stats: &'a [String],
+    /// 死亡回顧文字行（顯示在右上角）
+    pub death_recap: &'a [String],
+    /// 選擇目標的資訊面板（顯示在右側、死亡回顧下方）
+    pub target_panel: &'a [String],
+    /// 網路統計文字行（顯示在左下角）
+    pub network: &'a [String],
+    /// 連線狀態提示與顏色（顯示在頂部中央）
+    pub banner: Option<(&'a str, Color)>,
+    /// 頂部左側的延遲資訊與顏色
+    pub header: Option<(&'a str, Color)>,
+    /// 輸入框內容與游標欄位
+    pub input_line: Option<(String, usize)>,
+    /// 鍵盤目標模式的準星（世界座標）
+    pub target_cursor: Option<Vec2<f32>>,
+    /// 選擇技能後的瞄準預覽
+    pub targeting: Option<&'a TargetingPreview>,
+    /// 協議錯誤狀態列（顯示在底部，輸入框開啟時被覆蓋）
+    pub protocol_errors: Option<&'a str>,
+    /// 開啟中的疊加層（迷霧、網格、熱圖、軌跡、視野範圍、除錯標籤）
+    pub layers: Option<&'a OverlayManager>,
+    /// 快照間平滑後的實體與其他玩家位置（未設置時畫在同步到的位置）
+    pub positions: Option<&'a PositionOverrides>,
+}
+
+/// 終端上的矩形區域（字元座標）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    /// 創建區域
+    pub fn new(x: u16, y: u16, width: u16, height: u16) -> Self {
+        Self { x, y, width, height }
+    }
+}
+
 /// 地圖渲染器
 pub struct MapRenderer;
 
@@ -26,8 +79,9 @@ impl MapRenderer {
             terminal::EnterAlternateScreen,
             Clear(ClearType::All),
             cursor::Hide,
-            event::EnableMouseCapture
+            event::EnableBracketedPaste
         )?;
+        crate::terminal_logger::TerminalLogger::global().invalidate();
         // 確保輸入緩衝區被清空
         while event::poll(std::time::Duration::from_millis(0))? {
             let _ = event::read()?;
@@ -35,10 +89,16 @@ impl MapRenderer {
         Ok(())
     }
 
+    /// 啟用滑鼠回報，返回是否成功（失敗時改用鍵盤目標模式）
+    pub fn enable_mouse_capture(&self) -> bool {
+        execute!(io::stdout(), event::EnableMouseCapture).is_ok()
+    }
+
     /// 清理終端
     pub fn cleanup_terminal(&self) -> io::Result<()> {
         execute!(
             io::stdout(),
+            event::DisableBracketedPaste,
             event::DisableMouseCapture,
             cursor::Show,
             terminal::LeaveAlternateScreen
@@ -47,91 +107,216 @@ impl MapRenderer {
         Ok(())
     }
 
-    /// 渲染終端視圖
+    /// 渲染終端視圖（地圖佔滿日誌區以上的整個終端）
+    #[cfg(feature = "replay")]
     pub fn render(
         &self,
         game_state: &GameState,
         viewport: &ViewportManager,
-        show_vision: bool,
         terminal_width: u16,
         terminal_height: u16,
+        overlay: &RenderOverlay,
     ) -> io::Result<()> {
         let mut stdout = io::stdout();
+        let area = Rect::new(0, 0, terminal_width, terminal_height);
+        self.render_region(&mut stdout, game_state, viewport, area, overlay)?;
+
+        // 顯示底部日誌
+        self.print_logs(&mut stdout, terminal_width, terminal_height)?;
 
-        // 只在初次渲染時清除螢幕，之後使用 cursor 移動
-        queue!(stdout, cursor::MoveTo(0, 0))?;
+        stdout.flush()?;
+        Ok(())
+    }
 
+    /// 把地圖與疊加元素繪製到指定區域（只寫入區域內的格子，不輸出日誌也不 flush，
+    /// 分割畫面、小地圖等版面可以在同一幀組合多個區域）
+    #[cfg(feature = "replay")]
+    pub fn render_region(
+        &self,
+        out: &mut impl Write,
+        game_state: &GameState,
+        viewport: &ViewportManager,
+        area: Rect,
+        overlay: &RenderOverlay,
+    ) -> io::Result<()> {
+        let map_grid = self.compose_frame(game_state, viewport, area.width, area.height, overlay);
+        self.draw_grid(out, &map_grid, area)
+    }
+
+    /// 組合一幀畫面的網格（地圖、實體與疊加元素），不輸出到終端
+    pub fn compose_frame(
+        &self,
+        game_state: &GameState,
+        viewport: &ViewportManager,
+        width: u16,
+        height: u16,
+        overlay: &RenderOverlay,
+    ) -> Vec<Vec<MapDisplay>> {
+        let area = Rect::new(0, 0, width, height);
         // 檢查是否有有效的遊戲資料
         if !game_state.has_valid_data() {
             // 沒有資料時顯示等待畫面
-            self.render_waiting_screen(
-                &mut stdout,
-                game_state,
-                viewport,
-                show_vision,
-                terminal_width,
-                terminal_height,
-            )?;
-        } else {
-            // 創建地圖網格
-            let mut map_grid =
-                self.create_map_grid(game_state, viewport, terminal_width, terminal_height);
-
-            // 渲染玩家和實體
-            self.render_entities(
-                game_state,
-                &mut map_grid,
-                viewport,
-                terminal_width,
-                terminal_height,
-            );
-
-            // 渲染視野範圍（如果啟用）
-            if show_vision {
-                self.render_vision_range(
-                    &mut map_grid,
-                    game_state,
-                    terminal_width,
-                    terminal_height,
-                );
+            return self.waiting_grid(area.width, area.height);
+        }
+
+        // 以鏡頭視野中心為畫面中心（客戶端每個 tick 依鏡頭模式更新）
+        let camera = game_state.viewport.center;
+        let width = area.width as usize;
+        let synced = PositionOverrides::default();
+        let positions = overlay.positions.unwrap_or(&synced);
+
+        // 創建地圖網格
+        let mut map_grid = self.create_map_grid(game_state, viewport, camera, area.width, area.height);
+
+        // 先畫位於實體之下的疊加層（除錯標籤、軌跡等），實體符號畫在其上，不會被蓋住
+        if let Some(layers) = overlay.layers {
+            layers.draw(&mut LayerFrame { grid: &mut map_grid, game_state, positions, viewport, camera }, false);
+        }
+
+        // 渲染玩家和實體
+        self.render_entities(game_state, positions, &mut map_grid, viewport, camera);
+
+        // 位於實體之上的疊加層（視野範圍）
+        if let Some(layers) = overlay.layers {
+            layers.draw(&mut LayerFrame { grid: &mut map_grid, game_state, positions, viewport, camera }, true);
+        }
+
+        // 以方括號標出選擇的目標
+        if let Some(entity) = crate::selection::selected_entity(game_state) {
+            if let Some((x, y)) = viewport.world_to_screen(positions.entity(entity), camera, width, area.height as usize) {
+                if x > 0 && x + 1 < width {
+                    map_grid[y][x - 1] = MapDisplay::SELECT_LEFT;
+                    map_grid[y][x + 1] = MapDisplay::SELECT_RIGHT;
+                }
             }
+        }
 
-            // 輸出地圖到終端
-            self.print_map(&mut stdout, &map_grid)?;
+        // 繪製技能瞄準預覽：彈道上的點與終點或落點
+        if let Some(preview) = overlay.targeting {
+            let markers = preview.markers();
+            for (index, marker) in markers.iter().enumerate() {
+                if let Some((x, y)) = viewport.world_to_screen(*marker, camera, width, area.height as usize) {
+                    map_grid[y][x] = if index + 1 == markers.len() { MapDisplay::TARGET_MARK } else { MapDisplay::TARGET_PATH };
+                }
+            }
+        }
+        
+        // 繪製鍵盤目標模式的準星
+        if let Some(target) = overlay.target_cursor {
+            if let Some((x, y)) = viewport.world_to_screen(target, camera, width, area.height as usize) {
+                map_grid[y][x] = MapDisplay::TARGET_CURSOR;
+            }
+        }
 
-            // 顯示底部日誌
-            self.print_logs(&mut stdout, terminal_width, terminal_height)?;
+        // 本地英雄陣亡時地圖轉為灰色，中央顯示重生倒數
+        if let Some(countdown) = game_state.local_player.life.countdown() {
+            for cell in map_grid.iter_mut().flatten() {
+                cell.color = Color::DarkGrey;
+            }
+            let countdown_x = width.saturating_sub(str_width(&countdown)) / 2;
+            draw_text(&mut map_grid, countdown_x, area.height as usize / 2, &countdown, Color::Red);
         }
 
-        stdout.flush()?;
-        Ok(())
+        // 繪製統計浮層、死亡回顧與輸入框
+        let panel_width = overlay.stats.iter().map(|line| str_width(line)).max().unwrap_or(0)
+            .min(width.saturating_sub(2));
+        for (row, line) in overlay.stats.iter().enumerate() {
+            draw_text(&mut map_grid, 1, row + 1, &pad_to_width(line, panel_width), Color::Cyan);
+        }
+        let recap_width = overlay.death_recap.iter().map(|line| str_width(line)).max().unwrap_or(0)
+            .min(width.saturating_sub(2));
+        let recap_x = width.saturating_sub(recap_width + 1);
+        for (row, line) in overlay.death_recap.iter().enumerate() {
+            draw_text(&mut map_grid, recap_x, row + 1, &pad_to_width(line, recap_width), Color::Red);
+        }
+        let target_width = overlay.target_panel.iter().map(|line| str_width(line)).max().unwrap_or(0)
+            .min(width.saturating_sub(2));
+        let target_x = width.saturating_sub(target_width + 1);
+        let target_y = if overlay.death_recap.is_empty() { 1 } else { overlay.death_recap.len() + 2 };
+        for (row, line) in overlay.target_panel.iter().enumerate() {
+            draw_text(&mut map_grid, target_x, target_y + row, &pad_to_width(line, target_width), Color::White);
+        }
+        let network_width = overlay.network.iter().map(|line| str_width(line)).max().unwrap_or(0)
+            .min(width.saturating_sub(2));
+        let network_y = (area.height as usize).saturating_sub(overlay.network.len() + 2);
+        for (row, line) in overlay.network.iter().enumerate() {
+            draw_text(&mut map_grid, 1, network_y + row, &pad_to_width(line, network_width), Color::Green);
+        }
+        if let Some((header, color)) = overlay.header {
+            draw_text(&mut map_grid, 1, 0, header, color);
+        }
+        if let Some((banner, color)) = overlay.banner {
+            let banner_x = width.saturating_sub(str_width(banner)) / 2;
+            draw_text(&mut map_grid, banner_x, 0, banner, color);
+        }
+        // 底部右側的英雄狀態列：生命條、魔力條、等級、經驗條、金錢與 K/D/A
+        let hero = &game_state.local_player;
+        let status_line = format!("HP {}  MP {}  {}  金錢 {}  K/D/A {}",
+            meter(hero.health, 8), meter(hero.mana, 8),
+            level_bar(hero.level, hero.experience, hero.next_level_experience, 10), hero.gold,
+            game_state.scoreboard.get(&hero.name));
+        let status_x = width.saturating_sub(str_width(&status_line) + 1);
+        draw_text(&mut map_grid, status_x, (area.height as usize).saturating_sub(1), &status_line, Color::Yellow);
+        // 狀態列上方的增益（綠）與減益（紅）圖示列
+        let icons: Vec<_> = hero.buffs.iter().map(|buff| (buff.icon(), if buff.debuff { Color::Red } else { Color::Green })).collect();
+        let strip_width = icons.iter().map(|(icon, _)| str_width(icon) + 1).sum::<usize>();
+        let mut icon_x = width.saturating_sub(strip_width);
+        for (icon, color) in &icons {
+            draw_text(&mut map_grid, icon_x, (area.height as usize).saturating_sub(2), icon, *color);
+            icon_x += str_width(icon) + 1;
+        }
+        if let Some(preview) = overlay.targeting {
+            draw_text(&mut map_grid, 1, (area.height as usize).saturating_sub(2), &preview.status(), Color::Magenta);
+        }
+        if let Some(status) = overlay.protocol_errors {
+            draw_text(&mut map_grid, 1, (area.height as usize).saturating_sub(1), status, Color::Red);
+        }
+        if let Some((line, cursor_col)) = &overlay.input_line {
+            draw_input_line(&mut map_grid, line, *cursor_col);
+        }
+
+        map_grid
     }
 
-    /// 渲染等待畫面
-    fn render_waiting_screen(
+    /// 建立以英雄為中心、包含實體的地圖網格（不輸出到終端）
+    #[cfg(feature = "replay")]
+    pub fn build_map_grid(
         &self,
-        stdout: &mut io::Stdout,
         game_state: &GameState,
         viewport: &ViewportManager,
-        show_vision: bool,
+        width: u16,
+        height: u16,
+    ) -> Vec<Vec<MapDisplay>> {
+        let camera = game_state.local_player.position;
+        let mut map_grid = self.create_map_grid(game_state, viewport, camera, width, height);
+        self.render_entities(game_state, &PositionOverrides::default(), &mut map_grid, viewport, camera);
+        map_grid
+    }
+
+    /// 輸出任意組合好的網格，並顯示底部日誌
+    pub fn render_grid(
+        &self,
+        grid: &[Vec<MapDisplay>],
         terminal_width: u16,
         terminal_height: u16,
     ) -> io::Result<()> {
-        let width = terminal_width as usize;
-        let height = terminal_height as usize;
+        let mut stdout = io::stdout();
+        self.draw_grid(&mut stdout, grid, Rect::new(0, 0, terminal_width, terminal_height))?;
+        self.print_logs(&mut stdout, terminal_width, terminal_height)?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// 等待畫面的網格
+    fn waiting_grid(&self, width: u16, height: u16) -> Vec<Vec<MapDisplay>> {
+        let width = width as usize;
+        let height = height as usize;
 
         // 創建空白地圖網格
         let mut map_grid = vec![vec![MapDisplay::EMPTY; width]; height];
         // 在地圖中心顯示等待訊息
         self.render_waiting_message(&mut map_grid, width, height);
-
-        // 輸出地圖到終端
-        self.print_map(stdout, &map_grid)?;
-
-        // 顯示底部日誌
-        self.print_logs(stdout, terminal_width, terminal_height)?;
-
-        Ok(())
+        map_grid
     }
 
     /// 在地圖中心渲染等待訊息
@@ -148,9 +333,9 @@ impl MapRenderer {
         for (i, message) in messages.iter().enumerate() {
             let y = start_y + i;
             if y < height {
-                let msg_len = message.len();
+                let msg_width = str_width(message);
                 let center_x = width / 2;
-                let start_x = center_x.saturating_sub(msg_len / 2);
+                let start_x = center_x.saturating_sub(msg_width / 2);
 
                 // 清除該行的背景
                 for x in 0..width {
@@ -163,15 +348,8 @@ impl MapRenderer {
                 }
 
                 // 渲染訊息文字
-                for (j, ch) in message.chars().enumerate() {
-                    let x = start_x + j;
-                    if x < width {
-                        grid[y][x] = MapDisplay {
-                            symbol: ch,
-                            color: if i == 0 { Color::Yellow } else { Color::White },
-                        };
-                    }
-                }
+                let color = if i == 0 { Color::Yellow } else { Color::White };
+                draw_text(grid, start_x, y, message, color);
             }
         }
 
@@ -191,41 +369,82 @@ impl MapRenderer {
         &self,
         game_state: &GameState,
         viewport: &ViewportManager,
+        camera: Vec2<f32>,
         terminal_width: u16,
         terminal_height: u16,
     ) -> Vec<Vec<MapDisplay>> {
         let width = terminal_width as usize;
         let height = terminal_height as usize;
 
-        // 初始化為空地
+        // 初始化為空地，已知的地形畫上對應符號（依多久沒看見淡化）
         let mut grid = vec![vec![MapDisplay::EMPTY; width]; height];
+        if !game_state.terrain.is_empty() {
+            for (y, row) in grid.iter_mut().enumerate() {
+                for (x, cell) in row.iter_mut().enumerate() {
+                    let world = viewport.screen_to_world(x as u16, y as u16, camera, width, height);
+                    if let Some(terrain) = game_state.terrain.get(world) {
+                        *cell = MapDisplay::for_terrain(terrain.kind).faded(game_state.world_map.fade(world));
+                    }
+                }
+            }
+        }
+
+        // 標記地圖邊界外的格子，緊鄰邊界內側的外部格子畫成邊界牆
+        if let Some(bounds) = game_state.world_bounds {
+            let inside: Vec<Vec<bool>> = (0..height)
+                .map(|y| (0..width)
+                    .map(|x| bounds.contains(viewport.screen_to_world(x as u16, y as u16, camera, width, height)))
+                    .collect())
+                .collect();
+            for y in 0..height {
+                for x in 0..width {
+                    if inside[y][x] {
+                        continue;
+                    }
+                    let touches_inside = (y > 0 && inside[y - 1][x])
+                        || (y + 1 < height && inside[y + 1][x])
+                        || (x > 0 && inside[y][x - 1])
+                        || (x + 1 < width && inside[y][x + 1]);
+                    grid[y][x] = if touches_inside { MapDisplay::BOUNDARY } else { MapDisplay::OUT_OF_BOUNDS };
+                }
+            }
+        }
 
         grid
     }
-    /// 渲染實體
+    /// 渲染實體（實體與其他玩家畫在 `positions` 中的畫面位置）
     fn render_entities(
         &self,
         game_state: &GameState,
-        grid: &mut Vec<Vec<MapDisplay>>,
+        positions: &PositionOverrides,
+        grid: &mut [Vec<MapDisplay>],
         viewport: &ViewportManager,
-        terminal_width: u16,
-        terminal_height: u16,
+        camera: Vec2<f32>,
     ) {
-        let term_width = terminal_width as usize;
-        let term_height = terminal_height as usize;
+        let term_width = grid.first().map_or(0, Vec::len);
+        let term_height = grid.len();
         let player_pos = game_state.local_player.position;
 
+        // 先畫已離開畫面、記憶中的塔，再畫地圖目錄中的塔與主堡，單位畫在建築之上
+        for remembered in game_state.world_map.structures().filter(|remembered| !game_state.entities.contains_key(&remembered.id)) {
+            if let Some((x, y)) =
+                viewport.world_to_screen(remembered.position, camera, term_width, term_height)
+            {
+                grid[y][x] = MapDisplay::TOWER.faded(game_state.world_map.structure_fade(remembered));
+            }
+        }
+        let structures = super::structures::render_structures(game_state, grid, viewport, camera);
+
         // 渲染自己的玩家
         if let Some((x, y)) =
-            viewport.world_to_screen(player_pos, player_pos, term_width, term_height)
+            viewport.world_to_screen(player_pos, camera, term_width, term_height)
         {
             grid[y][x] = MapDisplay::PLAYER_SELF;
         }
 
         // 渲染其他玩家
-        for (_name, player_state) in &game_state.other_players {
-            let pos = Vec2::new(player_state.position.0, player_state.position.1);
-            if let Some((x, y)) = viewport.world_to_screen(pos, player_pos, term_width, term_height)
+        for player_state in game_state.other_players.values() {
+            if let Some((x, y)) = viewport.world_to_screen(positions.player(player_state), camera, term_width, term_height)
             {
                 grid[y][x] = MapDisplay::PLAYER_ENEMY;
             }
@@ -234,156 +453,22 @@ impl MapRenderer {
         // 渲染己方召喚物
         for summon in &game_state.local_player.summons {
             if let Some((x, y)) =
-                viewport.world_to_screen(summon.position, player_pos, term_width, term_height)
+                viewport.world_to_screen(summon.position, camera, term_width, term_height)
             {
                 grid[y][x] = MapDisplay::SUMMON_ALLY;
             }
         }
 
         // 渲染其他實體
-        for entity in game_state.entities.values() {
+        for entity in game_state.entities.values().filter(|entity| !structures.contains(&entity.id)) {
             if let Some((x, y)) =
-                viewport.world_to_screen(entity.position, player_pos, term_width, term_height)
+                viewport.world_to_screen(positions.entity(entity), camera, term_width, term_height)
             {
-                let display = match entity.entity_type {
-                    EntityType::Player(_) => MapDisplay::PLAYER_ENEMY,
-                    EntityType::Summon(_) => {
-                        if entity.owner.as_ref() == Some(&game_state.local_player.name) {
-                            MapDisplay::SUMMON_ALLY
-                        } else {
-                            MapDisplay::SUMMON_ENEMY
-                        }
-                    }
-                    EntityType::Projectile => MapDisplay::PROJECTILE,
-                    EntityType::Effect => MapDisplay::EFFECT,
-                };
-                grid[y][x] = display;
+                grid[y][x] = MapDisplay::for_entity(entity, &game_state.local_player.name);
             }
         }
     }
 
-    /// 渲染視野範圍和額外信息
-    fn render_vision_range(
-        &self,
-        grid: &mut Vec<Vec<MapDisplay>>,
-        _game_state: &GameState,
-        terminal_width: u16,
-        terminal_height: u16,
-    ) {
-        let term_width = terminal_width as usize;
-        let term_height = terminal_height as usize;
-
-        // 繪製視圖邊界框
-        self.draw_vision_border(grid, term_width, term_height);
-
-        // 顯示距離信息（如果有空間）
-        if term_width > 20 && term_height > 10 {
-            self.add_distance_markers(grid, term_width, term_height);
-        }
-    }
-
-    /// 繪製視野邊界框
-    fn draw_vision_border(
-        &self,
-        grid: &mut Vec<Vec<MapDisplay>>,
-        term_width: usize,
-        term_height: usize,
-    ) {
-        for x in 0..term_width {
-            // 上邊界
-            if grid[0][x].symbol == MapDisplay::EMPTY.symbol {
-                grid[0][x] = if x == 0 {
-                    MapDisplay {
-                        symbol: '┌',
-                        color: Color::Yellow,
-                    }
-                } else if x == term_width - 1 {
-                    MapDisplay {
-                        symbol: '┐',
-                        color: Color::Yellow,
-                    }
-                } else {
-                    MapDisplay {
-                        symbol: '─',
-                        color: Color::Yellow,
-                    }
-                };
-            }
-            // 下邊界
-            if grid[term_height - 1][x].symbol == MapDisplay::EMPTY.symbol {
-                grid[term_height - 1][x] = if x == 0 {
-                    MapDisplay {
-                        symbol: '└',
-                        color: Color::Yellow,
-                    }
-                } else if x == term_width - 1 {
-                    MapDisplay {
-                        symbol: '┘',
-                        color: Color::Yellow,
-                    }
-                } else {
-                    MapDisplay {
-                        symbol: '─',
-                        color: Color::Yellow,
-                    }
-                };
-            }
-        }
-
-        for y in 1..term_height - 1 {
-            // 左邊界
-            if grid[y][0].symbol == MapDisplay::EMPTY.symbol {
-                grid[y][0] = MapDisplay {
-                    symbol: '│',
-                    color: Color::Yellow,
-                };
-            }
-            // 右邊界
-            if grid[y][term_width - 1].symbol == MapDisplay::EMPTY.symbol {
-                grid[y][term_width - 1] = MapDisplay {
-                    symbol: '│',
-                    color: Color::Yellow,
-                };
-            }
-        }
-    }
-
-    /// 添加距離標記
-    fn add_distance_markers(
-        &self,
-        grid: &mut Vec<Vec<MapDisplay>>,
-        term_width: usize,
-        term_height: usize,
-    ) {
-        let center_x = term_width / 2;
-        let center_y = term_height / 2;
-
-        // 在 1/4 和 3/4 位置添加距離環
-        let quarter_x = term_width / 4;
-        let three_quarter_x = term_width * 3 / 4;
-        let quarter_y = term_height / 4;
-        let three_quarter_y = term_height * 3 / 4;
-
-        let distance_marker = MapDisplay {
-            symbol: '+',
-            color: Color::DarkYellow,
-        };
-
-        // 在適當的位置添加距離標記
-        if grid[quarter_y][center_x].symbol == MapDisplay::EMPTY.symbol {
-            grid[quarter_y][center_x] = distance_marker;
-        }
-        if grid[three_quarter_y][center_x].symbol == MapDisplay::EMPTY.symbol {
-            grid[three_quarter_y][center_x] = distance_marker;
-        }
-        if grid[center_y][quarter_x].symbol == MapDisplay::EMPTY.symbol {
-            grid[center_y][quarter_x] = distance_marker;
-        }
-        if grid[center_y][three_quarter_x].symbol == MapDisplay::EMPTY.symbol {
-            grid[center_y][three_quarter_x] = distance_marker;
-        }
-    }
-
     /// 標記視野中心
     fn mark_vision_center(
         &self,
@@ -406,21 +491,29 @@ impl MapRenderer {
         }
     }
 
-    /// 打印地圖到終端
-    fn print_map(&self, stdout: &mut io::Stdout, grid: &Vec<Vec<MapDisplay>>) -> io::Result<()> {
-        for (row_idx, row) in grid.iter().enumerate() {
-            queue!(stdout, cursor::MoveTo(0, row_idx as u16))?;
-            for display in row {
-                queue!(
-                    stdout,
-                    SetForegroundColor(display.color),
-                    Print(display.symbol)
-                )?;
+    /// 把網格輸出到指定區域，超出區域的行列會被截斷，不足的部分以空白補滿
+    pub fn draw_grid(&self, out: &mut impl Write, grid: &[Vec<MapDisplay>], area: Rect) -> io::Result<()> {
+        let width = area.width as usize;
+        for row_idx in 0..area.height {
+            queue!(out, cursor::MoveTo(area.x, area.y + row_idx))?;
+            let row = grid.get(row_idx as usize).map(Vec::as_slice).unwrap_or_default();
+            let mut col = 0;
+            for display in row.iter().take(width) {
+                col += 1;
+                // 寬字元右半部已由左半部輸出
+                if display.symbol == WIDE_CONTINUATION {
+                    continue;
+                }
+                // 最後一欄放不下寬字元時以空白代替，避免寫到區域外
+                let symbol = if col == width && char_width(display.symbol) == 2 { ' ' } else { display.symbol };
+                queue!(out, SetForegroundColor(display.color), Print(symbol))?;
+            }
+            // 補滿區域寬度，避免殘留上一幀或相鄰區域以外的字元
+            if col < width {
+                queue!(out, Print(" ".repeat(width - col)))?;
             }
-            // 清除到行尾，避免殘留字符
-            queue!(stdout, Clear(ClearType::UntilNewLine))?;
         }
-        queue!(stdout, ResetColor)?;
+        queue!(out, ResetColor)?;
         Ok(())
     }
 
@@ -441,3 +534,127 @@ impl MapRenderer {
         Ok(())
     }
 }
+
+/// 數值條（例如 `[█████░░░] 120/200`）
+fn meter((current, max): (f32, f32), width: usize) -> String {
+    let ratio = if max > 0.0 { (current / max).clamp(0.0, 1.0) } else { 0.0 };
+    let filled = (ratio * width as f32).round() as usize;
+    format!("[{}{}] {:.0}/{:.0}", "█".repeat(filled), "░".repeat(width - filled), current, max)
+}
+
+/// 在網格指定位置寫入一行文字（超出寬度的部分會被截斷）
+///
+/// 寬字元佔兩格，右半格以 WIDE_CONTINUATION 佔位，放不下的寬字元會被略過
+/// 網格的純文字內容（每列一行，去除顏色）
+pub fn grid_text(grid: &[Vec<MapDisplay>]) -> Vec<String> {
+    grid.iter()
+        .map(|row| row.iter().map(|display| display.symbol).filter(|symbol| *symbol != WIDE_CONTINUATION).collect::<String>().trim_end().to_string())
+        .collect()
+}
+
+pub fn draw_text(grid: &mut [Vec<MapDisplay>], x: usize, y: usize, text: &str, color: Color) {
+    if let Some(row) = grid.get_mut(y) {
+        let mut col = x;
+        for ch in text.chars() {
+            let width = char_width(ch);
+            if width == 0 {
+                continue;
+            }
+            if col + width > row.len() {
+                break;
+            }
+            // 覆寫到既有寬字元的一半時，把另一半清成空白，避免欄位錯位
+            if row[col].symbol == WIDE_CONTINUATION && col > 0 {
+                row[col - 1] = MapDisplay { symbol: ' ', color };
+            }
+            row[col] = MapDisplay { symbol: ch, color };
+            if width == 2 {
+                row[col + 1] = MapDisplay { symbol: WIDE_CONTINUATION, color };
+            }
+            col += width;
+        }
+        if let Some(next) = row.get_mut(col) {
+            if next.symbol == WIDE_CONTINUATION {
+                *next = MapDisplay { symbol: ' ', color };
+            }
+        }
+    }
+}
+
+/// 在地圖最底行繪製輸入框，並標示游標位置
+fn draw_input_line(grid: &mut [Vec<MapDisplay>], line: &str, cursor_col: usize) {
+    let Some(last) = grid.len().checked_sub(1) else {
+        return;
+    };
+    for cell in grid[last].iter_mut() {
+        *cell = MapDisplay { symbol: ' ', color: Color::White };
+    }
+    draw_text(grid, 0, last, line, Color::White);
+    if let Some(cell) = grid[last].get_mut(cursor_col) {
+        if cell.symbol == ' ' {
+            cell.symbol = '_';
+        }
+        cell.color = Color::Yellow;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::{Entity, EntityType, WorldBounds};
+    use crate::terminal_view::interpolation::Interpolator;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn draws_a_wall_along_the_map_boundary() {
+        // 10x5 格、每格 10 單位，英雄（鏡頭）在 (20, 15)：左邊三欄與最上一列在邊界外
+        let viewport = ViewportManager::new(100.0, 50.0);
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        state.local_player.position = Vec2::new(20.0, 15.0);
+        state.world_bounds = Some(WorldBounds { min_x: 0.0, min_y: 0.0, max_x: 1000.0, max_y: 1000.0 });
+        let renderer = MapRenderer::new();
+        let camera = state.local_player.position;
+        let mut grid = renderer.create_map_grid(&state, &viewport, camera, 10, 5);
+        renderer.render_entities(&state, &PositionOverrides::default(), &mut grid, &viewport, camera);
+        assert_eq!(grid_text(&grid), ["   #######", "  #.......", "  #..@....", "  #.......", "  #......."],
+            "緊鄰邊界的外部格子畫成牆，更外面的留白");
+        assert!(matches!(grid[1][2].color, Color::DarkRed));
+    }
+
+    #[test]
+    fn draws_units_at_their_smoothed_positions() {
+        // 10x5 格、每格 10 單位，鏡頭在 (50, 25)，英雄在畫面外
+        let viewport = ViewportManager::new(100.0, 50.0);
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        state.local_player.position = Vec2::new(-500.0, -500.0);
+        state.viewport.center = Vec2::new(50.0, 25.0);
+        let creep = |x: f32| Entity {
+            id: 1, entity_type: EntityType::Creep, position: Vec2::new(x, 25.0), health: (100.0, 100.0), owner: None, debug_label: None,
+        };
+        state.entities.insert(1, creep(25.0));
+        let mut interpolator = Interpolator::default();
+        let start = Instant::now();
+        interpolator.smooth(&state, start);
+        state.entities.insert(1, creep(75.0));
+        let positions = interpolator.smooth(&state, start + Duration::from_secs(1));
+
+        let renderer = MapRenderer::new();
+        let row = |overlay: &RenderOverlay| grid_text(&renderer.compose_frame(&state, &viewport, 10, 5, overlay))[2].clone();
+        assert_eq!(row(&RenderOverlay { positions: Some(&positions), ..RenderOverlay::default() }), "..c.......", "剛收到新位置時仍畫在原處");
+        assert_eq!(row(&RenderOverlay::default()), ".......c..", "沒有平滑位置時畫在同步到的位置");
+    }
+
+    #[test]
+    fn draws_grid_only_inside_region() {
+        let grid = vec![vec![MapDisplay::PLAYER_SELF; 2]; 3];
+        let mut out = Vec::new();
+        MapRenderer::new().draw_grid(&mut out, &grid, Rect::new(5, 3, 3, 2)).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        // 每行從區域左緣開始，只輸出區域高度的行數，不清除到行尾
+        assert!(out.contains("\x1b[4;6H") && out.contains("\x1b[5;6H"));
+        assert!(!out.contains("\x1b[6;6H") && !out.contains("\x1b[K"));
+        assert_eq!(out.matches('@').count(), 4);
+        assert!(out.contains("@ \x1b[5;6H"));
+    }
+}
@@ -0,0 +1,140 @@
+/// 建築的多格顯示
+///
+/// 地圖目錄（`[[map.structures]]`）中的塔與主堡以 2x2 或 3x3 的圖樣顯示，和單格的單位明顯區分：
+/// 外圈是生命環，依生命比例從左上角順時針點亮，點亮的格子使用陣營顏色；3x3 的中央標示種類（塔 T、主堡 B）。
+/// 位置附近有後端的塔實體時以實體的生命值為準，沒有對應實體時（尚未收到或主堡不在廣播中）整圈以暗灰色顯示
+use std::collections::HashSet;
+use crossterm::style::Color;
+use vek::Vec2;
+
+use super::{MapDisplay, ViewportManager};
+use crate::game_state::{Entity, EntityType, GameState, MapStructure, StructureKind, Team};
+
+/// 塔實體與目錄位置的最大距離（世界單位）
+const MATCH_RADIUS: f32 = 5.0;
+
+/// 陣營顏色
+pub fn team_color(team: Team) -> Color {
+    match team {
+        Team::Blue => Color::Blue,
+        Team::Red => Color::Red,
+        Team::Neutral => Color::White,
+    }
+}
+
+/// 生命環上的格子（列、欄），從左上角順時針
+fn ring(size: usize) -> Vec<(usize, usize)> {
+    let last = size - 1;
+    let top = (0..last).map(|col| (0, col));
+    let right = (0..last).map(|row| (row, last));
+    let bottom = (1..=last).rev().map(|col| (last, col));
+    let left = (1..=last).rev().map(|row| (row, 0));
+    top.chain(right).chain(bottom).chain(left).collect()
+}
+
+/// 外框符號
+fn frame_symbol(row: usize, col: usize, size: usize) -> char {
+    let last = size - 1;
+    match (row == 0, row == last, col == 0, col == last) {
+        (true, _, true, _) => '┌',
+        (true, _, _, true) => '┐',
+        (_, true, true, _) => '└',
+        (_, true, _, true) => '┘',
+        (true, ..) | (_, true, ..) => '─',
+        _ => '│',
+    }
+}
+
+/// 建築圖樣（size × size），health 為生命比例（None 表示未知）
+pub fn pattern(kind: StructureKind, size: usize, team: Team, health: Option<f32>) -> Vec<Vec<MapDisplay>> {
+    let color = team_color(team);
+    let mut cells = vec![vec![MapDisplay { symbol: ' ', color }; size]; size];
+    let ring = ring(size);
+    let lit = match health {
+        Some(ratio) if ratio > 0.0 => ((ratio.min(1.0) * ring.len() as f32).ceil() as usize).max(1),
+        _ => 0,
+    };
+    for (index, &(row, col)) in ring.iter().enumerate() {
+        cells[row][col] = MapDisplay {
+            symbol: frame_symbol(row, col, size),
+            color: if index < lit { color } else { Color::DarkGrey },
+        };
+    }
+    if size >= 3 {
+        let symbol = match kind {
+            StructureKind::Tower => 'T',
+            StructureKind::Base => 'B',
+        };
+        cells[size / 2][size / 2] = MapDisplay { symbol, color };
+    }
+    cells
+}
+
+/// 位置附近的塔實體
+fn matching_entity<'a>(structure: &MapStructure, game_state: &'a GameState) -> Option<&'a Entity> {
+    let position = Vec2::new(structure.position.0, structure.position.1);
+    game_state.entities.values()
+        .filter(|entity| entity.entity_type == EntityType::Tower)
+        .map(|entity| (entity, entity.position.distance(position)))
+        .filter(|(_, distance)| *distance <= MATCH_RADIUS)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(entity, _)| entity)
+}
+
+/// 在網格上畫出目錄中的建築，返回已畫成建築的塔實體 ID（這些實體不再以單格顯示）
+pub fn render_structures(
+    game_state: &GameState,
+    grid: &mut [Vec<MapDisplay>],
+    viewport: &ViewportManager,
+    camera: Vec2<f32>,
+) -> HashSet<u32> {
+    let height = grid.len();
+    let width = grid.first().map_or(0, Vec::len);
+    let mut drawn = HashSet::new();
+    for structure in &game_state.structures {
+        let position = Vec2::new(structure.position.0, structure.position.1);
+        let Some((x, y)) = viewport.world_to_screen(position, camera, width, height) else {
+            continue;
+        };
+        let entity = matching_entity(structure, game_state);
+        let health = entity.map(|entity| if entity.health.1 > 0.0 { entity.health.0 / entity.health.1 } else { 0.0 });
+        let size = structure.footprint();
+        let cells = pattern(structure.kind, size, structure.team, health);
+        let (left, top) = (x as isize - (size / 2) as isize, y as isize - (size / 2) as isize);
+        for (row, line) in cells.into_iter().enumerate() {
+            for (col, cell) in line.into_iter().enumerate() {
+                let (gx, gy) = (left + col as isize, top + row as isize);
+                if gx >= 0 && gy >= 0 && (gx as usize) < width && (gy as usize) < height {
+                    grid[gy as usize][gx as usize] = cell;
+                }
+            }
+        }
+        if let Some(entity) = entity {
+            drawn.insert(entity.id);
+        }
+    }
+    drawn
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal_view::renderer::grid_text;
+
+    #[test]
+    fn draws_team_colored_health_ring() {
+        let base = pattern(StructureKind::Base, 3, Team::Blue, Some(0.5));
+        assert_eq!(grid_text(&base), vec!["┌─┐", "│B│", "└─┘"]);
+        // 8 格的生命環點亮一半：上排三格與右側中間一格
+        let lit: Vec<(usize, usize)> = ring(3).into_iter().filter(|&(row, col)| base[row][col].color == Color::Blue).collect();
+        assert_eq!(lit, vec![(0, 0), (0, 1), (0, 2), (1, 2)]);
+        assert_eq!(base[2][0].color, Color::DarkGrey);
+
+        let tower = pattern(StructureKind::Tower, 2, Team::Red, None);
+        assert_eq!(grid_text(&tower), vec!["┌┐", "└┘"]);
+        assert!(tower.iter().flatten().all(|cell| cell.color == Color::DarkGrey), "生命未知時整圈暗灰");
+        let dying = pattern(StructureKind::Tower, 2, Team::Red, Some(0.01));
+        assert_eq!(dying[0][0].color, Color::Red);
+        assert_eq!(dying[0][1].color, Color::DarkGrey);
+    }
+}
@@ -0,0 +1,168 @@
+/// 文字輸入元件
+///
+/// 在 raw mode 下安全地輸入多位元組文字（中文玩家名稱、聊天訊息），
+/// 以字元為單位移動游標與刪除，並依顯示寬度捲動與繪製
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::text_width::{char_width, str_width};
+
+/// 輸入元件處理按鍵後的結果
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextInputEvent {
+    /// 內容或游標已更新
+    Edited,
+    /// 按下 Enter 送出內容
+    Submit(String),
+    /// 按下 Esc 取消輸入
+    Cancel,
+}
+
+/// 單行文字輸入
+#[derive(Debug, Clone, Default)]
+pub struct TextInput {
+    /// 提示文字（例如「聊天: 」）
+    pub prompt: String,
+    /// 以字元儲存內容，避免在多位元組字元中間切割
+    chars: Vec<char>,
+    /// 游標位置（字元索引）
+    cursor: usize,
+}
+
+impl TextInput {
+    /// 創建新的輸入元件
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            chars: Vec::new(),
+            cursor: 0,
+        }
+    }
+
+    /// 目前內容
+    pub fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    /// 在游標處插入字元
+    pub fn insert_char(&mut self, ch: char) {
+        if ch.is_control() {
+            return;
+        }
+        self.chars.insert(self.cursor, ch);
+        self.cursor += 1;
+    }
+
+    /// 在游標處插入字串（例如輸入法一次送出的整段文字或貼上）
+    pub fn insert_str(&mut self, text: &str) {
+        for ch in text.chars() {
+            self.insert_char(ch);
+        }
+    }
+
+    /// 處理按鍵
+    pub fn handle_key(&mut self, key: KeyEvent) -> TextInputEvent {
+        match key.code {
+            KeyCode::Enter => {
+                let text = self.text();
+                self.chars.clear();
+                self.cursor = 0;
+                return TextInputEvent::Submit(text);
+            }
+            KeyCode::Esc => return TextInputEvent::Cancel,
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.chars.remove(self.cursor);
+            }
+            KeyCode::Delete if self.cursor < self.chars.len() => {
+                self.chars.remove(self.cursor);
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.chars.len()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.chars.len(),
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.chars.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => self.insert_char(ch),
+            _ => {}
+        }
+        TextInputEvent::Edited
+    }
+
+    /// 依可用寬度取得可見內容與游標所在欄位
+    ///
+    /// 內容超出寬度時會水平捲動，確保游標始終可見
+    pub fn visible(&self, width: usize) -> (String, usize) {
+        let prompt_width = str_width(&self.prompt);
+        let available = width.saturating_sub(prompt_width + 1).max(1);
+
+        // 從游標往前找出能放入可用寬度的起點
+        let mut start = self.cursor;
+        let mut used = 0;
+        while start > 0 {
+            let w = char_width(self.chars[start - 1]);
+            if used + w > available {
+                break;
+            }
+            used += w;
+            start -= 1;
+        }
+
+        let mut text = self.prompt.clone();
+        let mut cursor_col = prompt_width;
+        let mut shown = 0;
+        for (i, &ch) in self.chars.iter().enumerate().skip(start) {
+            let w = char_width(ch);
+            if shown + w > available {
+                break;
+            }
+            if i < self.cursor {
+                cursor_col += w;
+            }
+            text.push(ch);
+            shown += w;
+        }
+
+        (text, cursor_col)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn edits_multibyte_text_by_character() {
+        let mut input = TextInput::new("");
+        input.insert_str("你好世界");
+        input.handle_key(key(KeyCode::Left));
+        input.handle_key(key(KeyCode::Backspace));
+        assert_eq!(input.text(), "你好界");
+        input.handle_key(key(KeyCode::Home));
+        input.handle_key(key(KeyCode::Char('A')));
+        assert_eq!(input.text(), "A你好界");
+    }
+
+    #[test]
+    fn visible_scrolls_by_display_width() {
+        let mut input = TextInput::new("> ");
+        input.insert_str("中文名稱");
+        // 提示 2 格 + 預留 1 格，剩 5 格只能放兩個寬字元
+        let (text, cursor_col) = input.visible(8);
+        assert_eq!(text, "> 名稱");
+        assert_eq!(cursor_col, 6);
+    }
+
+    #[test]
+    fn submit_returns_text_and_clears() {
+        let mut input = TextInput::new("");
+        input.insert_str("gg");
+        assert_eq!(input.handle_key(key(KeyCode::Enter)), TextInputEvent::Submit("gg".to_string()));
+        assert_eq!(input.text(), "");
+    }
+}
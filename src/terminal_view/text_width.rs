@@ -0,0 +1,72 @@
+/// 顯示寬度工具
+///
+/// 終端中 CJK 字元與 emoji 佔兩格，這裡提供以顯示寬度（而非字元數或位元組數）
+/// 計算、截斷與補齊字串的函數
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// 字串的顯示寬度
+pub fn str_width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+/// 字元的顯示寬度（控制字元視為 0）
+pub fn char_width(ch: char) -> usize {
+    UnicodeWidthChar::width(ch).unwrap_or(0)
+}
+
+/// 截斷字串使其顯示寬度不超過 max_width
+pub fn truncate_to_width(text: &str, max_width: usize) -> &str {
+    let mut width = 0;
+    for (index, ch) in text.char_indices() {
+        let w = char_width(ch);
+        if width + w > max_width {
+            return &text[..index];
+        }
+        width += w;
+    }
+    text
+}
+
+/// 超出寬度時截斷並加上 "..."
+pub fn truncate_with_ellipsis(text: &str, max_width: usize) -> String {
+    if str_width(text) <= max_width {
+        return text.to_string();
+    }
+    if max_width < 3 {
+        return truncate_to_width(text, max_width).to_string();
+    }
+    format!("{}...", truncate_to_width(text, max_width - 3))
+}
+
+/// 截斷並以空白補齊到剛好 width 的顯示寬度
+pub fn pad_to_width(text: &str, width: usize) -> String {
+    let truncated = truncate_to_width(text, width);
+    let mut result = String::with_capacity(truncated.len() + width);
+    result.push_str(truncated);
+    result.extend(std::iter::repeat_n(' ', width - str_width(truncated)));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wide_characters_count_as_two_columns() {
+        assert_eq!(str_width("abc"), 3);
+        assert_eq!(str_width("雜賀孫市"), 8);
+    }
+
+    #[test]
+    fn truncation_never_splits_wide_characters() {
+        assert_eq!(truncate_to_width("伊達政宗", 5), "伊達");
+        assert_eq!(truncate_with_ellipsis("伊達政宗 date", 9), "伊達政...");
+        assert_eq!(truncate_with_ellipsis("short", 9), "short");
+    }
+
+    #[test]
+    fn padding_fills_to_exact_width() {
+        assert_eq!(pad_to_width("名稱", 6), "名稱  ");
+        assert_eq!(pad_to_width("中文名稱", 5), "中文 ");
+    }
+}
@@ -1,12 +1,16 @@
 /// 視口管理模塊
 use vek::Vec2;
 
+use crate::coords::CoordinateTransform;
+
 /// 視口管理器
 pub struct ViewportManager {
     /// 視圖寬度（世界單位）
     pub view_width: f32,
     /// 視圖高度（世界單位）
     pub view_height: f32,
+    /// 後端座標與螢幕方向之間的轉換
+    pub transform: CoordinateTransform,
 }
 
 impl ViewportManager {
@@ -15,6 +19,7 @@ impl ViewportManager {
         Self {
             view_width: width,
             view_height: height,
+            transform: CoordinateTransform::default(),
         }
     }
     
@@ -33,9 +38,10 @@ impl ViewportManager {
         let screen_center_x = screen_width as f32 / 2.0;
         let screen_center_y = screen_height as f32 / 2.0;
         
-        // 計算物體相對於相機中心的偏移
-        let offset_x = world_pos.x - camera_center.x;
-        let offset_y = world_pos.y - camera_center.y;
+        // 計算物體相對於相機中心的偏移（轉為 Y 軸朝下的本地座標）
+        let offset = self.transform.to_local(world_pos) - self.transform.to_local(camera_center);
+        let offset_x = offset.x;
+        let offset_y = offset.y;
         
         // 將偏移轉換為螢幕座標
         let screen_x = screen_center_x + (offset_x / WORLD_UNITS_PER_CHAR);
@@ -75,9 +81,7 @@ impl ViewportManager {
         let offset_y = (screen_y as f32 - screen_center_y) * WORLD_UNITS_PER_CHAR;
         
         // 轉換為世界座標
-        let world_x = camera_center.x + offset_x;
-        let world_y = camera_center.y + offset_y;
-        
-        Vec2::new(world_x, world_y)
+        let local = self.transform.to_local(camera_center) + Vec2::new(offset_x, offset_y);
+        self.transform.to_backend(local)
     }
 }
\ No newline at end of file
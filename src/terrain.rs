@@ -0,0 +1,168 @@
+/// 後端地形
+///
+/// screen_response（與增量畫面）的 `terrain` 每筆為 `{"position", "terrain_type", "properties"}`：
+/// `terrain_type` 為 `wall`、`tree`、`water` 或 `mountain`，`properties.size` 為地形邊長（未提供時為一格），
+/// `properties.size` 超過 64 格時以 64 格計（避免異常的尺寸讓客戶端逐格填入而卡住），
+/// `properties.blocking` 決定是否阻擋移動（未提供時皆阻擋，與地形編輯器匯出的格式相同）。
+/// 地形是靜態的，收到後以 16x16 格為一區塊累積在遊戲狀態中，不隨畫面快照清除；
+/// 終端視圖以對應的地形符號繪製，點擊阻擋的地形時不送出移動
+use std::collections::HashMap;
+use log::debug;
+use vek::Vec2;
+
+use crate::map_editor::DEFAULT_CELL_SIZE;
+use crate::mqtt_handler::TerrainData;
+
+/// 每個區塊的邊長（格）
+const CHUNK_SIZE: i32 = 16;
+
+/// 單筆地形邊長的上限（格）
+const MAX_SIZE_CELLS: f32 = 64.0;
+
+/// 地形種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerrainKind {
+    Wall,
+    Tree,
+    Water,
+    Mountain,
+}
+
+impl TerrainKind {
+    /// 依後端的地形類型名稱解析
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "wall" => Some(TerrainKind::Wall),
+            "tree" => Some(TerrainKind::Tree),
+            "water" => Some(TerrainKind::Water),
+            "mountain" => Some(TerrainKind::Mountain),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TerrainKind::Wall => "牆壁",
+            TerrainKind::Tree => "樹木",
+            TerrainKind::Water => "水域",
+            TerrainKind::Mountain => "山地",
+        }
+    }
+}
+
+/// 一格地形
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerrainCell {
+    pub kind: TerrainKind,
+    /// 是否阻擋移動
+    pub blocking: bool,
+}
+
+type Chunk = Box<[Option<TerrainCell>]>;
+
+/// 分區塊保存的地形格子（格子 (0, 0) 為世界原點所在的格子）
+#[derive(Debug, Clone)]
+pub struct TerrainChunks {
+    cell_size: f32,
+    chunks: HashMap<(i32, i32), Chunk>,
+}
+
+impl Default for TerrainChunks {
+    fn default() -> Self {
+        Self { cell_size: DEFAULT_CELL_SIZE, chunks: HashMap::new() }
+    }
+}
+
+impl TerrainChunks {
+    fn cell_index(&self, position: Vec2<f32>) -> (i32, i32) {
+        ((position.x / self.cell_size).floor() as i32, (position.y / self.cell_size).floor() as i32)
+    }
+
+    fn locate((x, y): (i32, i32)) -> ((i32, i32), usize) {
+        let chunk = (x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE));
+        let offset = (y.rem_euclid(CHUNK_SIZE) * CHUNK_SIZE + x.rem_euclid(CHUNK_SIZE)) as usize;
+        (chunk, offset)
+    }
+
+    fn set(&mut self, cell: (i32, i32), value: TerrainCell) {
+        let (chunk, offset) = Self::locate(cell);
+        self.chunks.entry(chunk)
+            .or_insert_with(|| vec![None; (CHUNK_SIZE * CHUNK_SIZE) as usize].into_boxed_slice())[offset] = Some(value);
+    }
+
+    /// 加入後端送來的地形（覆蓋相同格子，未知的地形類型略過），返回加入的格數
+    pub fn insert(&mut self, data: &TerrainData) -> usize {
+        let Some(kind) = TerrainKind::parse(&data.terrain_type) else {
+            debug!("未知的地形類型: {}", data.terrain_type);
+            return 0;
+        };
+        let blocking = data.properties.get("blocking").and_then(|value| value.as_bool()).unwrap_or(true);
+        let mut size = data.properties.get("size").and_then(|value| value.as_f64()).map_or(self.cell_size, |size| size as f32);
+        let max_size = MAX_SIZE_CELLS * self.cell_size;
+        if size > max_size {
+            debug!("地形邊長 {} 超過上限，以 {} 計", size, max_size);
+            size = max_size;
+        }
+        // 覆蓋中心落在地形方塊內的所有格子（至少一格）
+        let half = size.max(self.cell_size) / 2.0 - self.cell_size / 2.0;
+        let center = Vec2::new(data.position.0, data.position.1);
+        let (min_x, min_y) = self.cell_index(center - half);
+        let (max_x, max_y) = self.cell_index(center + half);
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                self.set((x, y), TerrainCell { kind, blocking });
+            }
+        }
+        ((max_x - min_x + 1) * (max_y - min_y + 1)) as usize
+    }
+
+    /// 世界座標上的地形
+    pub fn get(&self, position: Vec2<f32>) -> Option<TerrainCell> {
+        let (chunk, offset) = Self::locate(self.cell_index(position));
+        self.chunks.get(&chunk).and_then(|cells| cells[offset])
+    }
+
+    /// 該位置阻擋移動的地形（沒有或不阻擋時為 None）
+    pub fn blocking_at(&self, position: Vec2<f32>) -> Option<TerrainCell> {
+        self.get(position).filter(|cell| cell.blocking)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stores_terrain_in_chunks_and_reports_blocking_cells() {
+        let mut terrain = TerrainChunks::default();
+        let data = |x: f32, y: f32, terrain_type: &str, properties: serde_json::Value| TerrainData {
+            position: (x, y), terrain_type: terrain_type.to_string(), properties,
+        };
+        assert_eq!(terrain.insert(&data(5.0, 5.0, "wall", serde_json::json!({}))), 1);
+        assert_eq!(terrain.insert(&data(-15.0, 165.0, "water", serde_json::json!({ "blocking": false }))), 1);
+        assert_eq!(terrain.insert(&data(200.0, 200.0, "mountain", serde_json::json!({ "size": 30.0 }))), 9);
+        assert_eq!(terrain.insert(&data(0.0, 0.0, "lava", serde_json::json!({}))), 0, "未知的地形略過");
+
+        assert!(terrain.blocking_at(Vec2::new(9.0, 1.0)).is_some());
+        assert!(terrain.get(Vec2::new(11.0, 1.0)).is_none());
+        assert_eq!(terrain.get(Vec2::new(-12.0, 161.0)).map(|cell| (cell.kind, cell.blocking)), Some((TerrainKind::Water, false)));
+        assert!(terrain.blocking_at(Vec2::new(-12.0, 161.0)).is_none());
+        assert_eq!(terrain.blocking_at(Vec2::new(191.0, 219.0)).map(|cell| cell.kind), Some(TerrainKind::Mountain), "較大的地形覆蓋多格");
+        assert!(terrain.get(Vec2::new(189.0, 200.0)).is_none());
+        assert_eq!(terrain.chunks.len(), 3);
+    }
+
+    #[test]
+    fn clamps_oversized_terrain() {
+        let mut terrain = TerrainChunks::default();
+        let data = TerrainData { position: (0.0, 0.0), terrain_type: "wall".to_string(), properties: serde_json::json!({ "size": 1e9 }) };
+        let cells = MAX_SIZE_CELLS as usize;
+        assert_eq!(terrain.insert(&data), cells * cells, "超過上限的邊長以上限計");
+        assert!(terrain.blocking_at(Vec2::new(0.0, 0.0)).is_some());
+        assert!(terrain.get(Vec2::new(MAX_SIZE_CELLS * DEFAULT_CELL_SIZE, 0.0)).is_none());
+    }
+}
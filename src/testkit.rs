@@ -0,0 +1,316 @@
+/// 後端回應的斷言
+///
+/// 腳本（`omobaf run`）除了送出操作，也要能驗證後端的遊戲邏輯。這裡的斷言在時限內持續同步狀態並重新檢查：
+/// 條件成立即通過，逾時則以最後一次看到的實際值失敗。訊息斷言只比對上一個操作步驟開始後收到的訊息，
+/// 避免前面步驟留下的訊息讓斷言誤判通過
+use std::time::{Duration, Instant};
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use tokio::sync::broadcast::{self, error::TryRecvError};
+use tokio::time::sleep;
+use vek::Vec2;
+
+use crate::game_client::{GameClient, IncomingMessage};
+use crate::game_state::{EntityType, GameState};
+
+/// 重新檢查的間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+fn default_timeout_ms() -> u64 {
+    3000
+}
+
+/// 實體種類名稱（與查詢運算式的 entities[].type 相同）
+pub fn entity_kind(entity_type: &EntityType) -> &'static str {
+    match entity_type {
+        EntityType::Player(_) => "player",
+        EntityType::Summon(_) => "summon",
+        EntityType::Projectile => "projectile",
+        EntityType::Effect => "effect",
+        EntityType::Creep => "creep",
+        EntityType::Tower => "tower",
+    }
+}
+
+/// 可在時限內重複檢查的斷言
+pub trait Assertion: Sync {
+    fn timeout(&self) -> Duration;
+
+    /// 條件成立時返回說明，不成立時以 Err 返回目前的實際情況
+    fn check(&self, state: &GameState, messages: &MessageLog) -> std::result::Result<String, String>;
+}
+
+/// `expect_entity_within`：指定種類或 ID 的實體出現在位置附近（後端座標，腳本中寫本地座標、執行前轉換）
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EntityWithin {
+    /// 實體種類（creep、tower、projectile、summon…）
+    #[serde(default)]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub id: Option<u32>,
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Assertion for EntityWithin {
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+
+    fn check(&self, state: &GameState, _messages: &MessageLog) -> std::result::Result<String, String> {
+        let center = Vec2::new(self.x, self.y);
+        let nearest = state.entities.values()
+            .filter(|entity| self.id.is_none_or(|id| entity.id == id))
+            .filter(|entity| self.kind.as_deref().is_none_or(|kind| entity_kind(&entity.entity_type) == kind))
+            .map(|entity| (entity, entity.position.distance(center)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+        match nearest {
+            Some((entity, distance)) if distance <= self.radius => Ok(format!("實體 {} 距離 {:.1}", entity.id, distance)),
+            Some((entity, distance)) => Err(format!("最近的實體 {} 距離 {:.1}", entity.id, distance)),
+            None => Err("沒有符合的實體".to_string()),
+        }
+    }
+}
+
+/// `expect_health_between`：本地英雄（或指定 ID 的實體）的生命值介於 min 與 max 之間
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HealthBetween {
+    /// 實體 ID（未設置時為本地英雄）
+    #[serde(default)]
+    pub id: Option<u32>,
+    pub min: f32,
+    pub max: f32,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Assertion for HealthBetween {
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+
+    fn check(&self, state: &GameState, _messages: &MessageLog) -> std::result::Result<String, String> {
+        let health = match self.id {
+            None => state.local_player.health.0,
+            Some(id) => state.entities.get(&id).ok_or_else(|| format!("沒有實體 {}", id))?.health.0,
+        };
+        let detail = format!("生命值 {:.1}", health);
+        if (self.min..=self.max).contains(&health) { Ok(detail) } else { Err(detail) }
+    }
+}
+
+/// `expect_cooldown`：技能的剩餘冷卻介於 min 與 max 秒之間（兩者都未設置時為「正在冷卻」）
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CooldownExpect {
+    pub ability: String,
+    #[serde(default)]
+    pub min: Option<f32>,
+    #[serde(default)]
+    pub max: Option<f32>,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Assertion for CooldownExpect {
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+
+    fn check(&self, state: &GameState, _messages: &MessageLog) -> std::result::Result<String, String> {
+        let ability = state.local_player.abilities.iter()
+            .find(|ability| ability.ability_id == self.ability)
+            .ok_or_else(|| format!("沒有技能 {}", self.ability))?;
+        let remaining = ability.cooldown_remaining;
+        let passed = match (self.min, self.max) {
+            (None, None) => remaining > 0.0,
+            (min, max) => min.is_none_or(|min| remaining >= min) && max.is_none_or(|max| remaining <= max),
+        };
+        let detail = format!("剩餘冷卻 {:.2}s", remaining);
+        if passed { Ok(detail) } else { Err(detail) }
+    }
+}
+
+/// `expect_message_on_topic`：符合主題過濾器（支援 `+`、`#`）且內容包含指定文字的訊息
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MessageOnTopic {
+    pub topic: String,
+    #[serde(default)]
+    pub contains: Option<String>,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Assertion for MessageOnTopic {
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+
+    fn check(&self, _state: &GameState, messages: &MessageLog) -> std::result::Result<String, String> {
+        let on_topic: Vec<&IncomingMessage> = messages.entries.iter()
+            .filter(|message| rumqttc::matches(&message.topic, &self.topic))
+            .collect();
+        let found = on_topic.iter()
+            .find(|message| self.contains.as_deref().is_none_or(|text| message.text.contains(text)));
+        match found {
+            Some(message) => Ok(format!("{}: {}", message.topic, message.text)),
+            None => Err(format!("{} 則訊息符合主題但內容不符", on_topic.len())),
+        }
+    }
+}
+
+/// 腳本執行期間收到的訊息
+#[derive(Debug)]
+pub struct MessageLog {
+    receiver: broadcast::Receiver<IncomingMessage>,
+    /// 上一個操作步驟開始後收到的訊息
+    entries: Vec<IncomingMessage>,
+}
+
+impl MessageLog {
+    pub fn new(receiver: broadcast::Receiver<IncomingMessage>) -> Self {
+        Self { receiver, entries: Vec::new() }
+    }
+
+    /// 讀出通道中的新訊息
+    pub fn poll(&mut self) {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(message) => self.entries.push(message),
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+    }
+
+    /// 開始新的操作步驟：捨棄之前收到的訊息
+    pub fn mark(&mut self) {
+        self.poll();
+        self.entries.clear();
+    }
+}
+
+/// 在時限內持續同步狀態並檢查斷言
+pub async fn expect(client: &mut GameClient, assertion: &dyn Assertion, messages: &mut MessageLog) -> Result<String> {
+    let deadline = Instant::now() + assertion.timeout();
+    loop {
+        client.tick().await?;
+        messages.poll();
+        match assertion.check(client.get_game_state(), messages) {
+            Ok(detail) => return Ok(detail),
+            Err(actual) if Instant::now() >= deadline => {
+                bail!("{} 毫秒內未成立：{}", assertion.timeout().as_millis(), actual)
+            }
+            Err(_) => sleep(POLL_INTERVAL).await,
+        }
+    }
+}
+
+/// 測試用的最小 MQTT broker：接受連線與訂閱，把客戶端發佈的訊息回送給同一個客戶端，
+/// 回送的 QoS 不超過 `max_qos`（用來模擬會降級 QoS 的 broker）
+#[cfg(test)]
+pub async fn stub_broker(max_qos: rumqttc::QoS) -> Result<std::net::SocketAddr> {
+    use bytes::BytesMut;
+    use rumqttc::mqttbytes::v4::{self, Packet};
+    use rumqttc::{ConnAck, ConnectReturnCode, PingResp, PubAck, PubComp, PubRec, PubRel, QoS, SubAck, SubscribeReasonCode};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut incoming = BytesMut::new();
+                let mut outgoing = BytesMut::new();
+                let mut next_pkid = 0u16;
+                while matches!(stream.read_buf(&mut incoming).await, Ok(read) if read > 0) {
+                    while let Ok(packet) = v4::read(&mut incoming, 1024 * 1024) {
+                        let written = match packet {
+                            Packet::Connect(_) => ConnAck::new(ConnectReturnCode::Success, false).write(&mut outgoing),
+                            Packet::Subscribe(subscribe) => {
+                                let codes = subscribe.filters.iter().map(|filter| SubscribeReasonCode::Success(filter.qos)).collect();
+                                SubAck::new(subscribe.pkid, codes).write(&mut outgoing)
+                            }
+                            Packet::Publish(mut publish) => {
+                                let _ = match publish.qos {
+                                    QoS::AtMostOnce => Ok(0),
+                                    QoS::AtLeastOnce => PubAck::new(publish.pkid).write(&mut outgoing),
+                                    QoS::ExactlyOnce => PubRec::new(publish.pkid).write(&mut outgoing),
+                                };
+                                if publish.qos as u8 > max_qos as u8 {
+                                    publish.qos = max_qos;
+                                }
+                                next_pkid = next_pkid % u16::MAX + 1;
+                                publish.pkid = if publish.qos == QoS::AtMostOnce { 0 } else { next_pkid };
+                                publish.write(&mut outgoing)
+                            }
+                            Packet::PubRec(pubrec) => PubRel::new(pubrec.pkid).write(&mut outgoing),
+                            Packet::PubRel(pubrel) => PubComp::new(pubrel.pkid).write(&mut outgoing),
+                            Packet::PingReq => PingResp.write(&mut outgoing),
+                            Packet::Disconnect => return,
+                            _ => Ok(0),
+                        };
+                        if written.is_err() {
+                            return;
+                        }
+                    }
+                    if stream.write_all(&outgoing).await.is_err() {
+                        return;
+                    }
+                    outgoing.clear();
+                }
+            });
+        }
+    });
+    Ok(addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::{AbilityState, Entity};
+
+    #[test]
+    fn checks_entities_health_cooldowns_and_messages() {
+        let mut state = GameState::new("p1".to_string(), "saika_magoichi".to_string());
+        state.local_player.health = (420.0, 500.0);
+        state.entities.insert(7, Entity {
+            id: 7, entity_type: EntityType::Creep, position: Vec2::new(103.0, 104.0),
+            health: (80.0, 100.0), owner: None, debug_label: None,
+        });
+        state.local_player.abilities = vec![AbilityState {
+            ability_id: "sniper_mode".to_string(), level: 1, cooldown_remaining: 7.5, is_available: false, last_used: None,
+        }];
+        let (sender, receiver) = broadcast::channel(8);
+        let mut messages = MessageLog::new(receiver);
+
+        let near: EntityWithin = serde_json::from_value(serde_json::json!({ "kind": "creep", "x": 100, "y": 100, "radius": 6 })).unwrap();
+        assert_eq!(near.check(&state, &messages), Ok("實體 7 距離 5.0".to_string()));
+        let tower = EntityWithin { kind: Some("tower".to_string()), ..near };
+        assert_eq!(tower.check(&state, &messages), Err("沒有符合的實體".to_string()));
+
+        let hero = HealthBetween { id: None, min: 400.0, max: 500.0, timeout_ms: 0 };
+        assert!(hero.check(&state, &messages).is_ok());
+        assert_eq!(HealthBetween { id: Some(7), ..hero }.check(&state, &messages), Err("生命值 80.0".to_string()));
+
+        let cooling = CooldownExpect { ability: "sniper_mode".to_string(), min: None, max: None, timeout_ms: 0 };
+        assert!(cooling.check(&state, &messages).is_ok());
+        assert!(CooldownExpect { max: Some(5.0), ..cooling }.check(&state, &messages).is_err());
+
+        let hit = MessageOnTopic { topic: "td/+/res".to_string(), contains: Some("\"hit\"".to_string()), timeout_ms: 0 };
+        sender.send(IncomingMessage { topic: "td/p1/res".to_string(), text: r#"{"a":"miss"}"#.to_string() }).unwrap();
+        messages.poll();
+        assert_eq!(hit.check(&state, &messages), Err("1 則訊息符合主題但內容不符".to_string()));
+        sender.send(IncomingMessage { topic: "td/p1/res".to_string(), text: r#"{"a":"hit"}"#.to_string() }).unwrap();
+        messages.poll();
+        assert!(hit.check(&state, &messages).is_ok());
+        messages.mark();
+        assert!(hit.check(&state, &messages).is_err(), "新的操作步驟不再比對之前的訊息");
+    }
+}
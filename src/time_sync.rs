@@ -0,0 +1,137 @@
+/// 伺服器時間同步
+///
+/// 定期發佈帶本地時間的 time_sync 到 `td/{player_name}/time_sync`，後端在 `td/{player_name}/time_sync_ack`
+/// 原樣帶回 `client_time` 並附上 `server_time`。以往返時間的一半估計單次的時鐘偏移，取窗口內往返最短的樣本作為偏移，
+/// 並以窗口內偏移隨時間的斜率估計漂移；玩家操作以估計的伺服器時間標記，冷卻時間對不上時可以先排除時鐘的因素
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::Deserialize;
+
+use crate::mqtt_handler::WireFormat;
+
+/// 估計偏移與漂移用的樣本數
+const WINDOW: usize = 16;
+
+/// 估計漂移需要的最短樣本時間跨度（毫秒）
+const MIN_DRIFT_SPAN_MS: i64 = 10_000;
+
+/// 時間的 Unix 毫秒
+pub fn unix_ms(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as i64)
+}
+
+/// 一次時間同步的結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSample {
+    /// 收到回應的本地時間（Unix 毫秒）
+    pub received_ms: i64,
+    /// 伺服器時鐘減本地時鐘（毫秒）
+    pub offset_ms: i64,
+    pub rtt_ms: i64,
+}
+
+/// 估計的伺服器時鐘
+#[derive(Debug, Clone, Default)]
+pub struct ServerClock {
+    samples: VecDeque<ClockSample>,
+}
+
+impl ServerClock {
+    /// 記錄一次回應（client_time 為送出時的本地時間），返回這次的樣本；回應的時間早於送出時間時忽略
+    pub fn observe(&mut self, client_time: i64, server_time: i64, now: i64) -> Option<ClockSample> {
+        let rtt_ms = now.checked_sub(client_time).filter(|rtt| *rtt >= 0)?;
+        let sample = ClockSample { received_ms: now, offset_ms: server_time - (client_time + rtt_ms / 2), rtt_ms };
+        if self.samples.len() == WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        Some(sample)
+    }
+
+    /// 往返最短的樣本（網路延遲不對稱的影響最小，相同時取最新的）
+    pub fn best(&self) -> Option<ClockSample> {
+        self.samples.iter().rev().min_by_key(|sample| sample.rtt_ms).copied()
+    }
+
+    /// 估計的時鐘偏移（尚未同步時為 None）
+    pub fn offset_ms(&self) -> Option<i64> {
+        self.best().map(|sample| sample.offset_ms)
+    }
+
+    /// 以估計的偏移換算出的伺服器時間（尚未同步時使用本地時間）
+    pub fn server_time(&self, now: i64) -> i64 {
+        now + self.offset_ms().unwrap_or(0)
+    }
+
+    /// 偏移每分鐘的變化（最小平方法斜率，樣本跨度不足時為 None）
+    pub fn drift_ms_per_min(&self) -> Option<f64> {
+        let first = self.samples.front()?;
+        let last = self.samples.back()?;
+        if last.received_ms - first.received_ms < MIN_DRIFT_SPAN_MS {
+            return None;
+        }
+        let n = self.samples.len() as f64;
+        let points = || self.samples.iter().map(|sample| ((sample.received_ms - first.received_ms) as f64, sample.offset_ms as f64));
+        let (mean_t, mean_offset) = points().fold((0.0, 0.0), |(t, o), (x, y)| (t + x / n, o + y / n));
+        let (covariance, variance) = points().fold((0.0, 0.0), |(c, v), (x, y)| {
+            (c + (x - mean_t) * (y - mean_offset), v + (x - mean_t) * (x - mean_t))
+        });
+        Some(covariance / variance * 60_000.0)
+    }
+
+    /// 單行摘要
+    pub fn summary(&self) -> Option<String> {
+        let best = self.best()?;
+        let drift = match self.drift_ms_per_min() {
+            Some(drift) => format!("，漂移 {:+.1}ms/分", drift),
+            None => String::new(),
+        };
+        Some(format!("偏移 {:+}ms (往返 {}ms，{} 個樣本){}", best.offset_ms, best.rtt_ms, self.samples.len(), drift))
+    }
+}
+
+/// time_sync_ack 的內容
+#[derive(Debug, Deserialize)]
+struct TimeSyncAck {
+    client_time: i64,
+    server_time: i64,
+}
+
+/// 解析 time_sync_ack（`{"d": {...}}` 或直接帶欄位），返回 (client_time, server_time)
+pub fn parse_ack(format: WireFormat, payload: &[u8]) -> Option<(i64, i64)> {
+    let value: serde_json::Value = format.decode(payload).ok()?;
+    let ack: TimeSyncAck = serde_json::from_value(value.get("d").cloned().unwrap_or(value)).ok()?;
+    Some((ack.client_time, ack.server_time))
+}
+
+/// 送出的 time_sync 訊息
+pub fn request(now: i64) -> serde_json::Value {
+    serde_json::json!({ "t": "time_sync", "a": "time_sync", "d": { "client_time": now } })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_offset_from_the_fastest_round_trip_and_drift() {
+        let mut clock = ServerClock::default();
+        assert_eq!(clock.server_time(1_000), 1_000);
+        // 伺服器快 500ms，每 10 秒多快 1ms；第二個樣本的往返較長且不對稱
+        clock.observe(0, 520, 40).unwrap();
+        clock.observe(10_000, 10_700, 10_300).unwrap();
+        clock.observe(20_000, 20_522, 20_040).unwrap();
+        assert_eq!(clock.offset_ms(), Some(502));
+        assert_eq!(clock.server_time(30_000), 30_502);
+        assert!(clock.observe(5_000, 0, 4_000).is_none(), "回應早於送出");
+
+        let mut steady = ServerClock::default();
+        for minute in 0..3 {
+            let sent = minute * 60_000;
+            steady.observe(sent, sent + 500 + minute * 3 + 10, sent + 20);
+        }
+        assert!((steady.drift_ms_per_min().unwrap() - 3.0).abs() < 1e-9);
+        let ack = serde_json::to_vec(&serde_json::json!({ "t": "time_sync_ack", "d": { "client_time": 1, "server_time": 2 } })).unwrap();
+        assert_eq!(parse_ack(WireFormat::Json, &ack), Some((1, 2)));
+    }
+}
@@ -0,0 +1,158 @@
+/// MQTT 的 TLS 傳輸層
+///
+/// rumqttc 以連線的 broker 位址作為 SNI 並驗證憑證。broker 以 IP 或內部位址連線、憑證卻簽給其他名稱時，
+/// 設置 `server.tls.server_name` 後連線位址不變，改以該名稱驗證伺服器憑證（自訂 rustls 驗證器）
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use rumqttc::tokio_rustls::rustls::{
+    self,
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    client::WebPkiServerVerifier,
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+use rumqttc::{TlsConfiguration, Transport};
+
+use crate::config::TlsConfig;
+
+/// 依 TLS 配置建立 MQTT 傳輸層（`verify_name` 為驗證憑證用的伺服器名稱，未設置時驗證連線的 broker 位址）
+pub fn transport(tls: &TlsConfig, verify_name: Option<&str>) -> Result<Transport> {
+    let read_pem = |path: &str| std::fs::read(path)
+        .with_context(|| format!("無法讀取 TLS 憑證檔案: {}", path));
+
+    let client_auth = match (&tls.client_cert, &tls.client_key) {
+        (Some(cert), Some(key)) => Some((read_pem(cert)?, read_pem(key)?)),
+        (None, None) => None,
+        _ => return Err(anyhow::anyhow!("TLS 用戶端憑證與私鑰需同時設置")),
+    };
+    let ca = tls.ca_cert.as_deref().map(read_pem).transpose()?;
+    if ca.is_none() && client_auth.is_some() {
+        return Err(anyhow::anyhow!("使用用戶端憑證時需設置 ca_cert"));
+    }
+
+    match (verify_name, ca) {
+        (Some(name), ca) => Ok(Transport::tls_with_config(TlsConfiguration::Rustls(Arc::new(client_config(ca, client_auth, name)?)))),
+        (None, Some(ca)) => Ok(Transport::tls(ca, client_auth, None)),
+        (None, None) => Ok(Transport::tls_with_default_config()),
+    }
+}
+
+/// 以指定名稱驗證伺服器憑證的 rustls 設定（未提供 CA 時使用系統根憑證）
+fn client_config(ca: Option<Vec<u8>>, client_auth: Option<(Vec<u8>, Vec<u8>)>, verify_name: &str) -> Result<ClientConfig> {
+    let mut roots = RootCertStore::empty();
+    match ca {
+        Some(pem) => {
+            for cert in rustls_pemfile::certs(&mut pem.as_slice()) {
+                roots.add(cert.context("無法解析 CA 憑證")?).context("CA 憑證無效")?;
+            }
+        }
+        None => {
+            roots.add_parsable_certificates(rustls_native_certs::load_native_certs().context("無法載入系統根憑證")?);
+        }
+    }
+    let name = ServerName::try_from(verify_name.to_string())
+        .map_err(|_| anyhow::anyhow!("TLS 伺服器名稱無效: {}", verify_name))?;
+    let inner = WebPkiServerVerifier::builder(Arc::new(roots)).build().context("沒有可用的 CA 憑證")?;
+    let builder = ClientConfig::builder()
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(NamedServerVerifier { name, inner }));
+
+    match client_auth {
+        Some((cert, key)) => {
+            let certs = rustls_pemfile::certs(&mut cert.as_slice()).collect::<Result<Vec<_>, _>>()
+                .context("無法解析 TLS 用戶端憑證")?;
+            let key = rustls_pemfile::private_key(&mut key.as_slice()).context("無法解析 TLS 用戶端私鑰")?
+                .context("TLS 用戶端私鑰檔案中沒有私鑰")?;
+            builder.with_client_auth_cert(certs, key).context("TLS 用戶端憑證無效")
+        }
+        None => Ok(builder.with_no_client_auth()),
+    }
+}
+
+/// 以配置的伺服器名稱取代連線位址驗證憑證，簽章驗證沿用 webpki
+#[derive(Debug)]
+struct NamedServerVerifier {
+    name: ServerName<'static>,
+    inner: Arc<WebPkiServerVerifier>,
+}
+
+impl ServerCertVerifier for NamedServerVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.inner.verify_server_cert(end_entity, intermediates, &self.name, ocsp_response, now)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CA: &str = include_str!("../data/tls/test_ca.pem");
+    const BROKER_CERT: &str = include_str!("../data/tls/test_broker.pem");
+    const BROKER_KEY: &str = include_str!("../data/tls/test_broker.key");
+
+    #[test]
+    fn verifies_the_configured_name_instead_of_the_dialed_address() {
+        let roots = {
+            let mut roots = RootCertStore::empty();
+            roots.add(rustls_pemfile::certs(&mut CA.as_bytes()).next().unwrap().unwrap()).unwrap();
+            Arc::new(roots)
+        };
+        let leaf = rustls_pemfile::certs(&mut BROKER_CERT.as_bytes()).next().unwrap().unwrap();
+        let dialed = ServerName::try_from("127.0.0.1").unwrap();
+        let now = UnixTime::since_unix_epoch(std::time::Duration::from_secs(1_900_000_000));
+
+        let webpki = WebPkiServerVerifier::builder(roots.clone()).build().unwrap();
+        assert!(webpki.verify_server_cert(&leaf, &[], &dialed, &[], now).is_err(), "憑證簽給 broker.local，不是連線的 IP");
+        let named = |name: &str| NamedServerVerifier { name: ServerName::try_from(name.to_string()).unwrap(), inner: webpki.clone() };
+        assert!(named("broker.local").verify_server_cert(&leaf, &[], &dialed, &[], now).is_ok());
+        assert!(named("other.local").verify_server_cert(&leaf, &[], &dialed, &[], now).is_err());
+    }
+
+    #[test]
+    fn builds_client_configs_and_rejects_bad_inputs() {
+        let ca = Some(CA.as_bytes().to_vec());
+        let client_auth = Some((BROKER_CERT.as_bytes().to_vec(), BROKER_KEY.as_bytes().to_vec()));
+        assert!(client_config(ca.clone(), None, "broker.local").is_ok());
+        assert!(client_config(ca.clone(), client_auth, "broker.local").is_ok());
+
+        let error = client_config(ca.clone(), None, "not a host name!").unwrap_err();
+        assert!(error.to_string().contains("TLS 伺服器名稱無效"), "{:#}", error);
+        let error = client_config(Some(Vec::new()), None, "broker.local").unwrap_err();
+        assert!(error.to_string().contains("沒有可用的 CA 憑證"), "{:#}", error);
+        let error = client_config(ca, Some((BROKER_CERT.as_bytes().to_vec(), Vec::new())), "broker.local").unwrap_err();
+        assert!(error.to_string().contains("沒有私鑰"), "{:#}", error);
+
+        let half = TlsConfig { enabled: true, client_cert: Some("client.pem".to_string()), ..TlsConfig::default() };
+        assert!(transport(&half, None).err().unwrap().to_string().contains("需同時設置"));
+    }
+}
@@ -0,0 +1,99 @@
+/// MQTT 主題命名空間
+///
+/// 後端預設以 `td/...` 作為遊戲主題，部署在其他命名空間（例如 `game2/td/...`）或多租戶 broker 上時，
+/// 以 `[server.topics] prefix` 設定前綴；訂閱、發佈與訊息路由都透過 TopicScheme 組出主題
+use serde::{Deserialize, Serialize};
+
+/// 廣播主題在前綴之後的路徑
+const BROADCAST_PATH: &str = "all/res";
+
+/// 遊戲主題的命名方式
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TopicScheme {
+    /// 所有遊戲主題的前綴（不含結尾的 `/`）
+    #[serde(default = "default_topic_prefix")]
+    pub prefix: String,
+}
+
+fn default_topic_prefix() -> String {
+    "td".to_string()
+}
+
+impl Default for TopicScheme {
+    fn default() -> Self {
+        Self { prefix: default_topic_prefix() }
+    }
+}
+
+/// 前綴之下的遊戲主題
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameTopic<'a> {
+    /// 遊戲狀態廣播（`{prefix}/all/res`）
+    Broadcast,
+    /// 玩家主題（`{prefix}/{player}/{channel}`）
+    Player { player: &'a str, channel: &'a str },
+}
+
+impl TopicScheme {
+    /// 遊戲狀態廣播主題
+    pub fn broadcast(&self) -> String {
+        format!("{}/{}", self.prefix, BROADCAST_PATH)
+    }
+
+    /// 玩家主題，例如 `player("p1", "send")` 為 `td/p1/send`
+    pub fn player(&self, player_name: &str, channel: &str) -> String {
+        format!("{}/{}/{}", self.prefix, player_name, channel)
+    }
+
+    /// 所有玩家的同一類主題（訂閱用，例如 `td/+/send`）
+    pub fn any_player(&self, channel: &str) -> String {
+        self.player("+", channel)
+    }
+
+    /// 解析收到的主題（不在前綴之下時返回 None）
+    pub fn parse<'a>(&self, topic: &'a str) -> Option<GameTopic<'a>> {
+        let path = topic.strip_prefix(self.prefix.as_str())?.strip_prefix('/')?;
+        if path == BROADCAST_PATH {
+            return Some(GameTopic::Broadcast);
+        }
+        let (player, channel) = path.split_once('/')?;
+        Some(GameTopic::Player { player, channel })
+    }
+
+    /// 前綴的格式問題（空白、包含萬用字元或以 `/` 開頭結尾）
+    pub fn prefix_issue(&self) -> Option<String> {
+        let prefix = &self.prefix;
+        if prefix.is_empty() {
+            Some("不可為空".to_string())
+        } else if prefix.contains(['+', '#']) {
+            Some(format!("不可包含萬用字元 + 或 #: {}", prefix))
+        } else if prefix.starts_with('/') || prefix.ends_with('/') || prefix.contains("//") {
+            Some(format!("不可以 / 開頭、結尾或包含空的層級: {}", prefix))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_and_parses_topics_under_prefix() {
+        let scheme = TopicScheme { prefix: "game2/td".to_string() };
+        assert_eq!(scheme.broadcast(), "game2/td/all/res");
+        assert_eq!(scheme.player("p1", "send"), "game2/td/p1/send");
+        assert_eq!(scheme.any_player("send"), "game2/td/+/send");
+
+        assert_eq!(scheme.parse("game2/td/all/res"), Some(GameTopic::Broadcast));
+        assert_eq!(scheme.parse("game2/td/p1/screen_response"), Some(GameTopic::Player { player: "p1", channel: "screen_response" }));
+        assert_eq!(scheme.parse("td/p1/send"), None);
+        assert_eq!(scheme.parse("game2/tdx/p1/send"), None);
+
+        assert!(scheme.prefix_issue().is_none());
+        assert!(TopicScheme { prefix: "td/".to_string() }.prefix_issue().is_some());
+        assert!(TopicScheme { prefix: "tenant/+".to_string() }.prefix_issue().is_some());
+    }
+}
@@ -0,0 +1,110 @@
+/// 版本與能力握手
+///
+/// 連線後發佈 `td/{player_name}/hello` 告知前端版本、協議版本與支援的編碼格式，enter_game 也帶上協議版本，
+/// 後端回覆到 `td/{player_name}/hello_ack`，前後端協議版本不同時在視圖中顯示警告
+use std::sync::{Arc, Mutex};
+use colored::*;
+use serde::Deserialize;
+use crate::mqtt_handler::WireFormat;
+
+/// 前端實作的訊息協議版本（訊息結構有不相容變更時遞增）
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// 前端仍能解析的最舊協議版本（更舊的後端訊息一律拒絕處理）
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// crate 版本
+pub const CRATE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// 編譯時的 git 提交雜湊
+pub const GIT_HASH: &str = env!("OMOBAF_GIT_HASH");
+
+/// 支援的壓縮方式（目前不壓縮）
+pub const SUPPORTED_COMPRESSIONS: &[&str] = &["none"];
+
+/// 後端在 hello_ack 中回報的版本資訊
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BackendVersion {
+    pub version: String,
+    /// 後端的協議版本（舊版後端可能未提供）
+    #[serde(default)]
+    pub protocol: Option<u32>,
+    #[serde(default)]
+    pub wire_formats: Vec<String>,
+}
+
+impl BackendVersion {
+    /// 從 hello_ack 訊息解析（`{"d": {...}}` 或直接是版本物件）
+    pub fn parse(format: WireFormat, payload: &[u8]) -> Option<Self> {
+        let value: serde_json::Value = format.decode(payload).ok()?;
+        let data = value.get("d").cloned().unwrap_or(value);
+        serde_json::from_value(data).ok()
+    }
+
+    /// 協議版本與前端不同時的警告文字
+    pub fn protocol_mismatch(&self) -> Option<String> {
+        match self.protocol {
+            Some(protocol) if protocol != PROTOCOL_VERSION => Some(format!(
+                " ⚠ 協議版本不符：前端 v{} / 後端 v{}（{}） ", PROTOCOL_VERSION, protocol, self.version)),
+            _ => None,
+        }
+    }
+}
+
+/// 在背景任務間共享的後端版本
+pub type SharedBackendVersion = Arc<Mutex<Option<BackendVersion>>>;
+
+/// 連線後發送的 hello 訊息
+pub fn hello_message(player_name: &str) -> serde_json::Value {
+    serde_json::json!({
+        "name": player_name,
+        "t": "hello",
+        "a": "hello",
+        "d": {
+            "client_version": CRATE_VERSION,
+            "git_hash": GIT_HASH,
+            "protocol": PROTOCOL_VERSION,
+            "wire_formats": WireFormat::ALL.iter().map(WireFormat::label).collect::<Vec<_>>(),
+            "compressions": SUPPORTED_COMPRESSIONS,
+        }
+    })
+}
+
+/// 顯示版本與能力（已連線時附上後端版本）
+pub fn print_version(backend: Option<&BackendVersion>) {
+    println!("\n{}", "版本資訊:".bright_cyan().bold());
+    println!("  omobaf: {} ({})", CRATE_VERSION, GIT_HASH);
+    println!("  協議版本: v{}", PROTOCOL_VERSION);
+    println!("  編碼格式: {}", WireFormat::ALL.iter().map(WireFormat::label).collect::<Vec<_>>().join(", "));
+    println!("  壓縮方式: {}", SUPPORTED_COMPRESSIONS.join(", "));
+    match backend {
+        Some(backend) => {
+            let protocol = backend.protocol.map(|protocol| format!("v{}", protocol)).unwrap_or_else(|| "未知".to_string());
+            println!("  後端: {} (協議 {})", backend.version, protocol);
+            if !backend.wire_formats.is_empty() {
+                println!("  後端編碼格式: {}", backend.wire_formats.join(", "));
+            }
+            if let Some(warning) = backend.protocol_mismatch() {
+                println!("{}", warning.trim().yellow());
+            }
+        }
+        None => println!("  後端: {}", "未連接或尚未回報".bright_black()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hello_ack_and_detects_mismatch() {
+        let payload = br#"{"t":"hello_ack","d":{"version":"0.3.1","protocol":1}}"#;
+        let backend = BackendVersion::parse(WireFormat::Json, payload).unwrap();
+        assert_eq!(backend.version, "0.3.1");
+        assert_eq!(backend.protocol_mismatch(), None);
+
+        let newer = BackendVersion { protocol: Some(PROTOCOL_VERSION + 1), ..backend.clone() };
+        assert!(newer.protocol_mismatch().unwrap().contains("後端 v2"));
+        assert_eq!(BackendVersion { protocol: None, ..backend }.protocol_mismatch(), None);
+    }
+}
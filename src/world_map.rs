@@ -0,0 +1,165 @@
+/// 世界地圖記憶
+///
+/// 畫面回應只包含目前範圍內的實體，帶序號的完整畫面會清除範圍外的實體，移動鏡頭後看過的塔也跟著消失。
+/// 世界地圖記憶以格子為鍵保存看過的建築（後端的塔實體），並記錄每個區塊（16x16 格）最後出現在畫面範圍內的時間；
+/// 塔在畫面範圍內但已不在實體中時視為被摧毀而遺忘。終端視圖在塔離開畫面後以記憶的位置繼續顯示，
+/// 地形與記憶的建築依區塊的年齡淡化（稍久為灰色、很久為暗灰色），時鐘在每幀的 `update_cooldowns` 中推進
+use std::collections::HashMap;
+use vek::Vec2;
+
+use crate::game_state::{Entity, EntityType};
+use crate::map_editor::DEFAULT_CELL_SIZE;
+
+/// 每個區塊的邊長（格）
+const CHUNK_SIZE: i32 = 16;
+
+/// 多久沒看見開始淡化（秒）
+pub const STALE_AFTER: f32 = 10.0;
+
+/// 多久沒看見視為久遠（秒）
+pub const OLD_AFTER: f32 = 60.0;
+
+/// 記憶的新舊程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fade {
+    /// 目前或剛剛看見
+    Fresh,
+    Stale,
+    Old,
+}
+
+impl Fade {
+    pub fn from_age(age: f32) -> Self {
+        if age >= OLD_AFTER {
+            Fade::Old
+        } else if age >= STALE_AFTER {
+            Fade::Stale
+        } else {
+            Fade::Fresh
+        }
+    }
+}
+
+/// 記憶中的建築
+#[derive(Debug, Clone, PartialEq)]
+pub struct RememberedStructure {
+    pub id: u32,
+    pub position: Vec2<f32>,
+    /// 最後看見的時間（記憶時鐘的秒數）
+    pub last_seen: f32,
+}
+
+/// 看過的世界：建築與各區塊最後看見的時間
+#[derive(Debug, Clone)]
+pub struct WorldMemory {
+    cell_size: f32,
+    clock: f32,
+    seen: HashMap<(i32, i32), f32>,
+    structures: HashMap<(i32, i32), RememberedStructure>,
+}
+
+impl Default for WorldMemory {
+    fn default() -> Self {
+        Self { cell_size: DEFAULT_CELL_SIZE, clock: 0.0, seen: HashMap::new(), structures: HashMap::new() }
+    }
+}
+
+impl WorldMemory {
+    fn cell_index(&self, position: Vec2<f32>) -> (i32, i32) {
+        ((position.x / self.cell_size).floor() as i32, (position.y / self.cell_size).floor() as i32)
+    }
+
+    fn chunk_of(&self, position: Vec2<f32>) -> (i32, i32) {
+        let (x, y) = self.cell_index(position);
+        (x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE))
+    }
+
+    /// 推進記憶時鐘
+    pub fn tick(&mut self, delta_time: f32) {
+        self.clock += delta_time;
+    }
+
+    /// 記錄一次畫面：範圍內（`area` 為最小與最大角）的區塊標記為剛看見，範圍內消失的建築遺忘，目前的塔記下位置
+    pub fn observe<'a>(&mut self, area: Option<(Vec2<f32>, Vec2<f32>)>, entities: impl IntoIterator<Item = &'a Entity>) {
+        let towers: Vec<&Entity> = entities.into_iter().filter(|entity| entity.entity_type == EntityType::Tower).collect();
+        if let Some((min, max)) = area {
+            let chunk_length = self.cell_size * CHUNK_SIZE as f32;
+            let (min_chunk, max_chunk) = (self.chunk_of(min), self.chunk_of(max));
+            for y in min_chunk.1..=max_chunk.1 {
+                for x in min_chunk.0..=max_chunk.0 {
+                    // 區塊中心在範圍內才算看見
+                    let center = Vec2::new(x as f32 + 0.5, y as f32 + 0.5) * chunk_length;
+                    if center.x >= min.x && center.x <= max.x && center.y >= min.y && center.y <= max.y {
+                        self.seen.insert((x, y), self.clock);
+                    }
+                }
+            }
+            self.structures.retain(|_, structure| {
+                let inside = structure.position.x >= min.x && structure.position.x <= max.x
+                    && structure.position.y >= min.y && structure.position.y <= max.y;
+                !inside || towers.iter().any(|tower| tower.id == structure.id)
+            });
+        }
+        for tower in towers {
+            self.structures.retain(|_, structure| structure.id != tower.id);
+            let cell = self.cell_index(tower.position);
+            self.structures.insert(cell, RememberedStructure {
+                id: tower.id,
+                position: tower.position,
+                last_seen: self.clock,
+            });
+        }
+    }
+
+    /// 該位置所在區塊多久沒看見（從未看見時為 None）
+    pub fn age(&self, position: Vec2<f32>) -> Option<f32> {
+        self.seen.get(&self.chunk_of(position)).map(|last_seen| self.clock - last_seen)
+    }
+
+    /// 該位置的淡化程度（從未看見時視為剛看見，不淡化）
+    pub fn fade(&self, position: Vec2<f32>) -> Fade {
+        self.age(position).map_or(Fade::Fresh, Fade::from_age)
+    }
+
+    /// 記憶中的建築
+    pub fn structures(&self) -> impl Iterator<Item = &RememberedStructure> {
+        self.structures.values()
+    }
+
+    /// 建築的淡化程度
+    pub fn structure_fade(&self, structure: &RememberedStructure) -> Fade {
+        Fade::from_age(self.clock - structure.last_seen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remembers_towers_out_of_view_and_fades_by_age() {
+        let tower = |id: u32, x: f32| Entity {
+            id, entity_type: EntityType::Tower, position: Vec2::new(x, 50.0), health: (800.0, 1000.0), owner: None, debug_label: None,
+        };
+        let creep = Entity { entity_type: EntityType::Creep, ..tower(9, 60.0) };
+        let mut memory = WorldMemory::default();
+        let left = Some((Vec2::new(0.0, 0.0), Vec2::new(320.0, 320.0)));
+        let right = Some((Vec2::new(320.0, 0.0), Vec2::new(640.0, 320.0)));
+
+        memory.observe(left, [&tower(1, 50.0), &tower(2, 250.0), &creep]);
+        assert_eq!(memory.structures().count(), 2, "只記憶塔");
+        memory.tick(15.0);
+        memory.observe(right, []);
+        assert_eq!(memory.structures().count(), 2, "範圍外的塔保留");
+        assert_eq!(memory.fade(Vec2::new(50.0, 50.0)), Fade::Stale);
+        assert_eq!(memory.fade(Vec2::new(500.0, 50.0)), Fade::Fresh);
+        assert_eq!(memory.fade(Vec2::new(5000.0, 50.0)), Fade::Fresh, "從未看見的區塊不淡化");
+
+        memory.tick(60.0);
+        let remembered = memory.structures().find(|structure| structure.id == 1).unwrap();
+        assert_eq!(memory.structure_fade(remembered), Fade::Old);
+        memory.observe(left, [&tower(1, 50.0)]);
+        assert_eq!(memory.structures().map(|structure| structure.id).collect::<Vec<_>>(), [1], "範圍內消失的塔遺忘");
+        assert_eq!(memory.age(Vec2::new(50.0, 50.0)), Some(0.0));
+    }
+}